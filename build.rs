@@ -15,7 +15,7 @@ fn get_shader_format<'a>(path: &'a Path) -> Option<ShaderFormat<'a>> {
     let name = path.file_prefix()?.to_str()?;
 
     match ext {
-        "frag" | "vert" => Some(ShaderFormat::Glsl { name, ext }),
+        "frag" | "vert" | "comp" => Some(ShaderFormat::Glsl { name, ext }),
         "hlsl" => {
             let profile = if stem.ends_with("_vs") {
                 "vs_6_6"
@@ -1,7 +1,7 @@
 use std::path::Path;
 
 use aluminium::types::{PbrVertex, Vertex};
-use aluminium::{Mesh, MeshDesc, Res, VulkanResult, WorldRenderer};
+use aluminium::{Geometry, Mesh, MeshDesc, Res, Submesh, VulkanResult, WorldRenderer};
 use bytemuck::{Pod, Zeroable};
 
 #[derive(Clone)]
@@ -15,48 +15,71 @@ fn load_gltf_node(world: &WorldRenderer, model: &mut GltfModel, node: gltf::Node
     }
 
     if let Some(mesh) = node.mesh() {
+        // Every primitive is a submesh of one shared mesh
+        let mut vertices = vec![];
+        let mut indices: Vec<u32> = vec![];
+        let mut submeshes = vec![];
+
         let primitives = mesh.primitives();
         for i in primitives {
             let reader = i.reader(|i| Some(&buffers[i.index()]));
 
-            let indices: Vec<_> = reader.read_indices().unwrap().into_u32().collect();
-            let positions: Vec<_> = reader.read_positions().unwrap().map(|x| [x[0], x[1], x[2], 0.0]).collect();
-            
-            let tex_coords = if let Some(tex_coords) = reader.read_tex_coords(0) {
-                tex_coords.into_f32().map(|x| x).collect()
+            let first_index = indices.len() as u32;
+            let base_vertex = vertices.len() as i32;
+            indices.extend(reader.read_indices().unwrap().into_u32());
+            submeshes.push(
+                Submesh::new(first_index..indices.len() as u32)
+                    .base_vertex(base_vertex)
+                    .material(i.material().index().unwrap_or(0) as u32),
+            );
+
+            let positions: Vec<_> = reader.read_positions().unwrap().collect();
+            let local_indices = indices[first_index as usize..].to_vec();
+            let mut geometry = Geometry::new(positions, local_indices);
+
+            geometry.uvs = if let Some(tex_coords) = reader.read_tex_coords(0) {
+                tex_coords.into_f32().collect()
             } else {
-                vec![[0.0, 0.0]; positions.len()]
+                vec![[0.0, 0.0]; geometry.vertex_count()]
             };
 
-            let tangents = if let Some(tangents) = reader.read_tangents() {
-                tangents.map(|x| x).collect()
-            } else {
-                vec![[0.0, 0.0, 0.0, 0.0]; positions.len()]
-            };
-
-            let normals: Vec<_> = reader.read_normals().unwrap().map(|x|  [x[0], x[1], x[2], 0.0]).collect();
+            // Fill attributes the file leaves out
+            match reader.read_normals() {
+                Some(normals) => geometry.normals = normals.collect(),
+                None => geometry.compute_smooth_normals(),
+            }
+            match reader.read_tangents() {
+                Some(tangents) => geometry.tangents = tangents.collect(),
+                None => geometry.compute_tangents(),
+            }
 
             let colors: Vec<_> = if let Some(colors) = reader.read_colors(0) {
                 colors.into_rgba_f32().map(|x| x).collect()
             } else {
-                vec![[0.9, 0.6, 0.4, 1.0]; positions.len()]
+                vec![[0.9, 0.6, 0.4, 1.0]; geometry.vertex_count()]
             };
 
-            let mut vertices = vec![];
-
-            for (index, pos) in positions.into_iter().enumerate() {
+            for (index, [x, y, z]) in geometry.positions.iter().copied().enumerate() {
+                let [nx, ny, nz] = geometry.normals[index];
                 vertices.push(PbrVertex {
-                    pos,
-                    normal: normals[index],
-                    uv: tex_coords[index],
+                    pos: [x, y, z, 0.0],
+                    normal: [nx, ny, nz, 0.0],
+                    uv: geometry.uvs[index],
                     color: colors[index],
-                    tangent: tangents[index]
+                    tangent: geometry.tangents[index]
                 });
             }
-
-            let mesh = world.create::<Mesh>(MeshDesc::new(&vertices).with_indices(&indices))?;
-            model.meshes.push(mesh);
         }
+
+        // Indices are relative to the base vertex of their primitive
+        let mesh = match indices.iter().all(|&index| u16::try_from(index).is_ok()) {
+            true => {
+                let indices: Vec<u16> = indices.iter().map(|&index| index as u16).collect();
+                world.create::<Mesh>(MeshDesc::new(&vertices).with_indices(&indices).with_submeshes(&submeshes))?
+            },
+            false => world.create::<Mesh>(MeshDesc::new(&vertices).with_indices(&indices).with_submeshes(&submeshes))?,
+        };
+        model.meshes.push(mesh);
     }
 
     Ok(())
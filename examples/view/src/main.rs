@@ -4,18 +4,19 @@ use std::error::Error;
 use std::time::Instant;
 
 use aluminium::{
-    BackBuffer, FrameGraphTexture, Handle, PresentPass, RasterPass, RasterPipeline, RasterPipelineDesc, RenderTargetsDesc, Res, Scissor, ShaderType, VertexInput, Viewport, WorldRenderer
+    BackBuffer, EguiPass, FrameGraphTexture, Handle, PresentPass, RasterPass, RasterPipeline, RasterPipelineDesc, RenderTargetsDesc, Res, Scissor, Viewport, WorldRenderer
 };
 
 use tracing_subscriber::filter::LevelFilter;
 use winit::application::ApplicationHandler;
 use winit::event::WindowEvent;
 use winit::event_loop::{ActiveEventLoop, EventLoop};
+use winit::keyboard::{KeyCode, PhysicalKey};
 use winit::window::{Window, WindowId};
 use winit::*;
 
 mod ui;
-pub use ui::UiRenderer;
+pub use ui::Inspector;
 
 mod gltf_loader;
 pub use gltf_loader::{GltfModel, load_gltf};
@@ -63,6 +64,7 @@ pub use gltf_loader::{GltfModel, load_gltf};
 #[derive(Default)]
 struct App {
     global_time: Option<std::time::Instant>,
+    inspector: Option<Inspector>,
     model: Option<GltfModel>,
     pipeline: Option<Res<RasterPipeline>>,
     world: Option<WorldRenderer>,
@@ -71,10 +73,18 @@ struct App {
 
 impl ApplicationHandler for App {
     fn window_event(&mut self, event_loop: &ActiveEventLoop, _: WindowId, event: WindowEvent) {
+        if let (Some(inspector), Some(window)) = (self.inspector.as_mut(), self.window.as_ref()) {
+            inspector.on_event(&event, window.scale_factor() as f32);
+        }
+
         match event {
             WindowEvent::CloseRequested => {
                 event_loop.exit();
             },
+            WindowEvent::KeyboardInput { event, .. } if event.state.is_pressed() && event.physical_key == PhysicalKey::Code(KeyCode::F12) => {
+                let world = self.world.as_mut().unwrap();
+                world.capture_next_frame("screenshot.ppm").expect("Error capture frame");
+            },
             WindowEvent::Resized(size) => {
                 let (width, height) = (size.width, size.height);
                 let world = self.world.as_mut().unwrap();
@@ -89,6 +99,13 @@ impl ApplicationHandler for App {
                 let model = self.model.as_ref().unwrap();
                 let time_sec = self.global_time.as_ref().unwrap().elapsed().as_secs_f32();
 
+                let size = window.inner_size();
+                let inspector = self.inspector.as_mut().unwrap();
+                let ui = inspector.run([size.width, size.height], window.scale_factor() as f32, model.meshes.len());
+                let lod_threshold = inspector.lod_threshold;
+                world.update_egui_textures(&ui.textures_delta).expect("Error update egui textures");
+                let (primitives, pixels_per_point) = (&ui.primitives, ui.pixels_per_point);
+
                 let _ = world.draw_frame(move |graph| {
 
                     #[derive(Clone, Copy, Default)]
@@ -107,12 +124,15 @@ impl ApplicationHandler for App {
                                 ctx.push_constants([time_sec, 2.0]);
                                 ctx.set_viewport(Viewport::FullRes);
                                 ctx.set_scissor(Scissor::FullRes);
+                                ctx.set_lod_threshold(lod_threshold);
                                 for mesh in &model.meshes {
                                     ctx.draw_mesh(mesh);
                                 }
                             }
                         )
                     );
+
+                    graph.add_pass(EguiPass::new("Inspector", primitives, pixels_per_point));
                 });
             },
             _ => (),
@@ -141,11 +161,6 @@ impl ApplicationHandler for App {
             RasterPipelineDesc::new()
                     .vertex_shader("./shaders/spv/raster_vs.spv")
                     .fragment_shader("./shaders/spv/raster_ps.spv")
-                    .vertex_input(
-                        VertexInput::new()
-                            .with(ShaderType::Float3)
-                            .with(ShaderType::Float3),
-                    )
                     .dynamic_scissors(true)
                     .dynamic_viewport(true),
             )
@@ -158,6 +173,7 @@ impl ApplicationHandler for App {
         .expect("Error load gltf model");
 
         self.global_time = Some(Instant::now());
+        self.inspector = Some(Inspector::default());
         self.model = Some(model);
         self.pipeline = Some(pipeline);
         self.world = Some(world);
@@ -0,0 +1,35 @@
+#version 450
+
+// Paint and antialiased coverage of the triangles canvas.vert places
+
+layout(location = 0) in vec2 oLocal;
+layout(location = 1) in vec2 oAa;
+layout(location = 2) flat in vec4 oClip;
+layout(location = 3) flat in vec4 oColor0;
+layout(location = 4) flat in vec4 oColor1;
+layout(location = 5) flat in vec4 oGradient;
+layout(location = 6) flat in uint oKind;
+
+layout(location = 0) out vec4 finalColor;
+
+const uint PAINT_LINEAR = 1u;
+const uint PAINT_RADIAL = 2u;
+
+void main() {
+    vec2 p = gl_FragCoord.xy;
+    if (p.x < oClip.x || p.y < oClip.y || p.x > oClip.z || p.y > oClip.w) {
+        discard;
+    }
+
+    float t = 0.0;
+    if (oKind == PAINT_LINEAR) {
+        vec2 axis = oGradient.zw - oGradient.xy;
+        t = clamp(dot(oLocal - oGradient.xy, axis) / max(dot(axis, axis), 1e-12), 0.0, 1.0);
+    } else if (oKind == PAINT_RADIAL) {
+        t = clamp(length(oLocal - oGradient.xy) / max(oGradient.z, 1e-6), 0.0, 1.0);
+    }
+
+    vec4 color = mix(oColor0, oColor1, t);
+    float coverage = clamp(oAa.y + 0.5 - abs(oAa.x), 0.0, 1.0);
+    finalColor = vec4(color.rgb, color.a * coverage);
+}
@@ -0,0 +1,50 @@
+#version 450
+
+// Triangles of Canvas, see CanvasPass
+//
+// Positions are canvas pixels with y down, gradients are evaluated at the
+// position before the transform of the canvas
+
+layout(location = 0) in vec2 vPosition;
+layout(location = 1) in vec2 vLocal;
+// Distance to the centerline and half width in pixels
+layout(location = 2) in vec2 vAa;
+// Min and max corners in pixels
+layout(location = 3) in vec4 vClip;
+layout(location = 4) in vec4 vColor0;
+layout(location = 5) in vec4 vColor1;
+layout(location = 6) in vec4 vGradient;
+layout(location = 7) in uint vKind;
+
+struct FrameData {
+    uvec2 resolution;
+    uint  frame_idx;
+    float delta_time_sec;
+    float time_sec;
+    float pad;
+};
+
+layout(set = 1, binding = 1) uniform FrameBuffer {
+    FrameData frame_data;
+};
+
+layout(location = 0) out vec2 oLocal;
+layout(location = 1) out vec2 oAa;
+layout(location = 2) flat out vec4 oClip;
+layout(location = 3) flat out vec4 oColor0;
+layout(location = 4) flat out vec4 oColor1;
+layout(location = 5) flat out vec4 oGradient;
+layout(location = 6) flat out uint oKind;
+
+void main() {
+    oLocal = vLocal;
+    oAa = vAa;
+    oClip = vClip;
+    oColor0 = vColor0;
+    oColor1 = vColor1;
+    oGradient = vGradient;
+    oKind = vKind;
+
+    vec2 resolution = vec2(frame_data.resolution);
+    gl_Position = vec4(vPosition / resolution * 2.0 - 1.0, 0.0, 1.0);
+}
@@ -0,0 +1,28 @@
+#version 450
+
+// Lines accumulated by DebugDraw, see DebugPass
+//
+// Positions are in world space, colors are linear with straight alpha
+
+layout(location = 0) in vec3 vPosition;
+layout(location = 1) in vec4 vColor;
+
+struct Camera {
+    mat4 view;
+    mat4 proj;
+    mat4 view_proj;
+    mat4 inv_view;
+    mat4 inv_proj;
+    mat4 inv_view_proj;
+};
+
+layout(set = 1, binding = 0) uniform CameraBuffer {
+    Camera camera;
+};
+
+layout(location = 0) out vec4 oColor;
+
+void main() {
+    oColor = vColor;
+    gl_Position = camera.view_proj * vec4(vPosition, 1.0);
+}
@@ -1,28 +1,25 @@
 #version 450
-#extension GL_ARB_separate_shader_objects : enable
+
+// Color attachments are sRGB, the output stays linear and blending uses
+// premultiplied alpha
 
 layout(location = 0) in vec4 oColor;
 layout(location = 1) in vec2 oUV;
 
-layout(binding = 0, set = 0) uniform sampler2D fontsSampler;
-
-layout(location = 0) out vec4 finalColor;
+layout(set = 0, binding = 0) uniform texture2D textures[16384];
+layout(set = 0, binding = 2) uniform sampler samplers[5];
 
-layout(constant_id = 0) const bool SRGB_FRAMEBUFFER = false;
+layout(push_constant) uniform Push {
+    uint transform_idx;
+    // Texture and sampler of the draw
+    uint tex_idx[8];
+    // Screen size in points
+    float user_data[23];
+} push;
 
-const float GAMMA = 2.2;
-const float INV_GAMMA = 1.0 / GAMMA;
+layout(location = 0) out vec4 finalColor;
 
-vec3 LINEARtoSRGB(vec3 color) {
-    return pow(color, vec3(INV_GAMMA));
-}
-vec4 LINEARtoSRGB(vec4 color) {
-    return vec4(LINEARtoSRGB(color.rgb), color.a);
-}
 void main() {
-    if (SRGB_FRAMEBUFFER) {
-        finalColor = oColor * texture(fontsSampler, oUV);
-    } else {
-        finalColor = LINEARtoSRGB(oColor * texture(fontsSampler, oUV));
-    }
+    vec4 texel = texture(sampler2D(textures[push.tex_idx[0]], samplers[push.tex_idx[1]]), oUV);
+    finalColor = oColor * texel;
 }
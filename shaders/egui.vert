@@ -1,29 +1,35 @@
 #version 450
-#extension GL_ARB_separate_shader_objects : enable
+
+// Triangles tessellated by egui, see EguiPass
+//
+// Positions are in points with (0, 0) at the top left corner, colors are
+// sRGB with premultiplied alpha
 
 layout(location = 0) in vec2 vPosition;
 layout(location = 1) in vec2 vUV;
 layout(location = 2) in vec4 vColor;
 
-layout(push_constant) uniform Matrices {
-    mat4 ortho;
-} matrices;
+layout(push_constant) uniform Push {
+    uint transform_idx;
+    // Texture and sampler of the draw
+    uint tex_idx[8];
+    // Screen size in points
+    float user_data[23];
+} push;
 
 layout(location = 0) out vec4 oColor;
 layout(location = 1) out vec2 oUV;
 
-const float GAMMA = 2.2;
-
-vec3 SRGBtoLINEAR(vec3 color) {
-    return pow(color, vec3(GAMMA));
-}
-vec4 SRGBtoLINEAR(vec4 color) {
-    return vec4(SRGBtoLINEAR(color.rgb), color.a);
+vec3 srgb_to_linear(vec3 color) {
+    vec3 low = color / 12.92;
+    vec3 high = pow((color + 0.055) / 1.055, vec3(2.4));
+    return mix(high, low, lessThanEqual(color, vec3(0.04045)));
 }
 
 void main() {
-    oColor = SRGBtoLINEAR(vColor);
+    oColor = vec4(srgb_to_linear(vColor.rgb), vColor.a);
     oUV = vUV;
 
-    gl_Position = matrices.ortho*vec4(vPosition.x, vPosition.y, 0.0, 1.0);
+    // Vulkan clip space has y pointing down like egui
+    gl_Position = vec4(vPosition / vec2(push.user_data[0], push.user_data[1]) * 2.0 - 1.0, 0.0, 1.0);
 }
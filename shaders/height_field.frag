@@ -0,0 +1,78 @@
+#version 450
+
+// Surface of a HeightField colored by height through its colormap, with
+// contour lines and a wireframe about one pixel wide
+
+layout(location = 0) in float oHeight;
+layout(location = 1) in vec3 oNormal;
+layout(location = 2) in vec2 oGrid;
+layout(location = 3) in vec3 oWorld;
+
+struct Camera {
+    mat4 view;
+    mat4 proj;
+    mat4 view_proj;
+    mat4 inv_view;
+    mat4 inv_proj;
+    mat4 inv_view_proj;
+};
+
+layout(set = 0, binding = 0) uniform texture2D textures[16384];
+layout(set = 0, binding = 2) uniform sampler samplers[5];
+
+layout(set = 1, binding = 0) uniform CameraBuffer {
+    Camera camera;
+};
+
+layout(push_constant) uniform Push {
+    uint transform_idx;
+    uint tex_idx[8];
+    // Layout of shaders/height_field.vert
+    float user_data[23];
+} push;
+
+layout(location = 0) out vec4 finalColor;
+
+// SAMPLER_CLAMP of shaders/inc/bindless.glsl
+const uint SAMPLER_CLAMP = 1;
+const float COLORMAP_SIZE = 256.0;
+
+// Coverage of lines at whole numbers of value
+float lines(float value) {
+    float distance = abs(fract(value - 0.5) - 0.5) / max(fwidth(value), 1e-6);
+    return clamp(1.0 - distance, 0.0, 1.0);
+}
+
+void main() {
+    // colormap() of shaders/inc/colormap.glsl
+    float lo = push.user_data[0];
+    float hi = push.user_data[1];
+
+    float t = (oHeight - lo) / (hi - lo);
+    if (push.user_data[2] != 0.0) {
+        t = oHeight > 0.0 ? (log(oHeight) - log(lo)) / (log(hi) - log(lo)) : -1.0;
+    }
+    if (isnan(t) || (push.user_data[3] == 0.0 && (t < 0.0 || t > 1.0))) {
+        discard;
+    }
+
+    float u = (clamp(t, 0.0, 1.0) * (COLORMAP_SIZE - 1.0) + 0.5) / COLORMAP_SIZE;
+    vec3 color = textureLod(sampler2D(textures[push.tex_idx[0]], samplers[SAMPLER_CLAMP]), vec2(u, 0.5), 0.0).rgb;
+
+    // Head light, both sides of the surface are lit
+    vec3 view = normalize(camera.inv_view[3].xyz - oWorld);
+    color *= 0.3 + 0.7 * abs(dot(normalize(oNormal), view));
+
+    float interval = push.user_data[7];
+    if (interval > 0.0) {
+        vec4 contour = vec4(push.user_data[8], push.user_data[9], push.user_data[10], push.user_data[11]);
+        color = mix(color, contour.rgb, contour.a * lines(oHeight / interval));
+    }
+
+    vec4 wireframe = vec4(push.user_data[12], push.user_data[13], push.user_data[14], push.user_data[15]);
+    if (wireframe.a > 0.0) {
+        color = mix(color, wireframe.rgb, wireframe.a * max(lines(oGrid.x), lines(oGrid.y)));
+    }
+
+    finalColor = vec4(color, 1.0);
+}
@@ -0,0 +1,71 @@
+#version 450
+
+// Samples of a HeightField, see PassContext::draw_height_field
+//
+// Vertex i is column i % columns and row i / columns of the grid, the
+// first sample is at the origin
+
+layout(location = 0) in float vHeight;
+layout(location = 1) in vec3 vNormal;
+
+struct Camera {
+    mat4 view;
+    mat4 proj;
+    mat4 view_proj;
+    mat4 inv_view;
+    mat4 inv_proj;
+    mat4 inv_view_proj;
+};
+
+struct Transform {
+    mat4 model;
+    mat4 normal;
+};
+
+layout(set = 1, binding = 0) uniform CameraBuffer {
+    Camera camera;
+};
+
+layout(set = 1, binding = 2) readonly buffer TransformBuffer {
+    Transform transforms[];
+};
+
+layout(push_constant) uniform Push {
+    uint transform_idx;
+    uint tex_idx[8];
+    // 0-3 ScalarRange of the colormap in tex_idx[0], 4-5 cell size,
+    // 6 columns, 7 contour interval, 8-11 contour color, 12-15 wireframe
+    // color
+    float user_data[23];
+} push;
+
+// transform_idx of draws placed by no transform, NO_TRANSFORM of
+// src/frame_graph/pass_context.rs. transforms[] may be empty then
+const uint NO_TRANSFORM = 0xFFFFFFFFu;
+
+layout(location = 0) out float oHeight;
+layout(location = 1) out vec3 oNormal;
+// Column and row, lines of the wireframe are at whole numbers
+layout(location = 2) out vec2 oGrid;
+layout(location = 3) out vec3 oWorld;
+
+void main() {
+    uint columns = uint(push.user_data[6]);
+    uint index = uint(gl_VertexIndex);
+    vec2 grid = vec2(float(index % columns), float(index / columns));
+
+    mat4 model = mat4(1.0);
+    mat4 normal = mat4(1.0);
+    if (push.transform_idx != NO_TRANSFORM) {
+        model = transforms[push.transform_idx].model;
+        normal = transforms[push.transform_idx].normal;
+    }
+
+    vec4 world = model * vec4(grid * vec2(push.user_data[4], push.user_data[5]), vHeight, 1.0);
+    gl_Position = camera.view_proj * world;
+
+    oHeight = vHeight;
+    oNormal = (normal * vec4(vNormal, 0.0)).xyz;
+    oGrid = grid;
+    oWorld = world.xyz;
+}
@@ -0,0 +1,100 @@
+// Resources bound by aluminium for every pipeline, GLSL version of
+// bindless.hlsl
+//
+// Set 0 (bindless, updated at any time)
+//   binding 0  texture2D textures[], texture3D textures3d[] for 3D textures
+//   binding 1  image2D rw_textures[]
+//   binding 2  sampler samplers[5]
+//
+// Set 1 (per frame, rewritten once per frame before passes run)
+//   binding 0  CameraBuffer camera
+//   binding 1  FrameBuffer frame_data
+//   binding 2  TransformBuffer transforms[]
+//   binding 3  ObjectBuffer objects[], GPU scene objects
+//   binding 4  draw commands written by the cull pass
+//   binding 5  draw count written by the cull pass
+//
+// Matrices are column-major, depth is reversed-Z (near = 1, far = 0)
+//
+// #extension GL_GOOGLE_include_directive : require
+// #include "inc/bindless.glsl"
+
+#extension GL_EXT_nonuniform_qualifier : require
+
+struct Camera {
+    mat4 view;
+    mat4 proj;
+    mat4 view_proj;
+    mat4 inv_view;
+    mat4 inv_proj;
+    mat4 inv_view_proj;
+};
+
+struct FrameData {
+    // Render target size in pixels
+    uvec2 resolution;
+    // Number of frames drawn before this one
+    uint  frame_idx;
+    // Seconds since the previous frame
+    float delta_time_sec;
+    // Seconds since the renderer was created
+    float time_sec;
+    float pad;
+};
+
+// World matrices baked from the transform hierarchy, see
+// WorldRenderer::transform_index and PassContext::bind_transform
+struct Transform {
+    // Local to world
+    mat4 model;
+    // Inverse transpose of model, for normals
+    mat4 normal;
+};
+
+// Object of the GPU scene, draws of PassContext::draw_indirect_count read
+// objects[gl_InstanceIndex]
+struct Object {
+    // Local bounding sphere, xyz center and w radius
+    vec4 sphere;
+    uint first_index;
+    uint index_count;
+    int vertex_offset;
+    uint transform_idx;
+};
+
+layout(set = 0, binding = 0) uniform texture2D textures[];
+// The same descriptors, 3D textures are read through this view of binding 0
+layout(set = 0, binding = 0) uniform texture3D textures3d[];
+layout(set = 0, binding = 1, rgba8) uniform image2D rw_textures[];
+layout(set = 0, binding = 2) uniform sampler samplers[5];
+
+layout(set = 1, binding = 0) uniform CameraBuffer {
+    Camera camera;
+};
+
+layout(set = 1, binding = 1) uniform FrameBuffer {
+    FrameData frame_data;
+};
+
+layout(set = 1, binding = 2) readonly buffer TransformBuffer {
+    Transform transforms[];
+};
+
+layout(set = 1, binding = 3) readonly buffer ObjectBuffer {
+    Object objects[];
+};
+
+const uint SAMPLER_REPEAT = 0;
+const uint SAMPLER_CLAMP = 1;
+const uint SAMPLER_BORDER = 2;
+const uint SAMPLER_MIP_LINEAR = 3;
+const uint SAMPLER_MIP_POINT = 4;
+
+layout(push_constant) uniform Push {
+    // 4 bytes
+    uint transform_idx;
+    // 4 * 8 = 32 bytes
+    uint tex_idx[8];
+    // 4 * 23 = 92 bytes, written by PassContext::push_constants
+    float user_data[23];
+} push;
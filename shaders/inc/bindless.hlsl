@@ -1,49 +1,83 @@
+// Resources bound by aluminium for every pipeline
+//
+// Set 0 (bindless, updated at any time)
+//   binding 0  Texture2D textures[]
+//   binding 1  RWTexture2D rw_textures[]
+//   binding 2  SamplerState samplers[5]
+//
+// Set 1 (per frame, rewritten once per frame before passes run)
+//   binding 0  ConstantBuffer<Camera> camera
+//   binding 1  ConstantBuffer<FrameData> frame_data
+//   binding 2  StructuredBuffer<Transform> transforms
+//   binding 3  StructuredBuffer<Object> objects, GPU scene objects
+//   binding 4  draw commands written by the cull pass
+//   binding 5  draw count written by the cull pass
+//
+// Matrices are column-major, depth is reversed-Z (near = 1, far = 0)
+//
+// #include "inc/bindless.hlsl"
 
 struct Camera {
-    float4x4 view;           
-    float4x4 proj;           
-    float4x4 view_proj;      
-    float4x4 inv_view;       
-    float4x4 inv_proj;       
+    float4x4 view;
+    float4x4 proj;
+    float4x4 view_proj;
+    float4x4 inv_view;
+    float4x4 inv_proj;
     float4x4 inv_view_proj;
 };
 
 struct FrameData {
+    // Render target size in pixels
     uint2   resolution;
+    // Number of frames drawn before this one
     uint    frame_idx;
+    // Seconds since the previous frame
     float   delta_time_sec;
+    // Seconds since the renderer was created
     float   time_sec;
     float   pad;
 };
 
+// World matrices baked from the transform hierarchy, see
+// WorldRenderer::transform_index and PassContext::bind_transform
 struct Transform {
-    float4   rot;
-    float4   scale;
-    float4   pos;
-    float4   pad;
+    // Local to world
+    float4x4 model;
+    // Inverse transpose of model, for normals
+    float4x4 normal;
 };
 
-[[vk::binding(0,  0)]] ConstantBuffer<Camera> camera;
-[[vk::binding(1,  0)]] ConstantBuffer<FrameData> frame_data;
-[[vk::binding(2,  0)]] StructuredBuffer<Transform> transforms;
+// Object of the GPU scene, draws of PassContext::draw_indirect_count read
+// objects[SV_InstanceID + SV_StartInstanceLocation]
+struct Object {
+    // Local bounding sphere, xyz center and w radius
+    float4 sphere;
+    uint   first_index;
+    uint   index_count;
+    int    vertex_offset;
+    uint   transform_idx;
+};
+
+[[vk::binding(0, 0)]] Texture2D     textures[];
+[[vk::binding(1, 0)]] RWTexture2D<float4> rw_textures[];
+[[vk::binding(2, 0)]] SamplerState  samplers[5];
 
-[[vk::binding(0, 1)]] Texture2D     textures[];
-[[vk::binding(1, 1)]] RWTexture2D   rw_textures[];
-[[vk::binding(2, 1)]] SamplerState  samplers[5];
+[[vk::binding(0, 1)]] ConstantBuffer<Camera> camera;
+[[vk::binding(1, 1)]] ConstantBuffer<FrameData> frame_data;
+[[vk::binding(2, 1)]] StructuredBuffer<Transform> transforms;
+[[vk::binding(3, 1)]] StructuredBuffer<Object> objects;
 
-const uint SAMPLER_REPEAT = 0;
-const uint SAMPLER_CLAMP = 1;
-const uint SAMPLER_BORDER = 2;
-const uint SAMPLER_MIP_LINEAR = 3;
-const uint SAMPLER_MIP_POINT = 4;
+static const uint SAMPLER_REPEAT = 0;
+static const uint SAMPLER_CLAMP = 1;
+static const uint SAMPLER_BORDER = 2;
+static const uint SAMPLER_MIP_LINEAR = 3;
+static const uint SAMPLER_MIP_POINT = 4;
 
 [[vk::push_constant]] struct Push {
     // 4 bytes
-    uint transform_idx;  
+    uint transform_idx;
     // 4 * 8 = 32 bytes
-    uint tex_idx[8];    
-    // 4 * 7 = 28 bytes
-    uint rw_tex_idx[7];  
-    // 4 * 16 = 64 bytes
-    uint user_data[16];       
+    uint tex_idx[8];
+    // 4 * 23 = 92 bytes, written by PassContext::push_constants
+    float user_data[23];
 } push;
@@ -0,0 +1,43 @@
+// Scalars mapped to colors through the lookup tables of Colormap, see
+// PassContext::bind_colormap
+//
+// The ScalarRange pushed with PassContext::push_constants is read from
+// push.user_data[0..4]: min, max, 1 for a logarithmic scale, 1 when values
+// outside the range take the colors of its ends
+//
+// #include "inc/bindless.glsl"
+// #include "inc/colormap.glsl"
+
+// Texels of every lookup table, LUT_SIZE of src/resources/colormap
+const float COLORMAP_SIZE = 256.0;
+
+// Position of value in the range from 0 to 1, below 0 or above 1 outside of
+// it and NaN for NaN
+float colormap_coord(float value) {
+    float lo = push.user_data[0];
+    float hi = push.user_data[1];
+
+    if (push.user_data[2] != 0.0) {
+        // Values at or below 0 are below the range
+        return value > 0.0 ? (log(value) - log(lo)) / (log(hi) - log(lo)) : -1.0;
+    }
+
+    return (value - lo) / (hi - lo);
+}
+
+// Linear color of value in the table of push.tex_idx[slot], transparent for
+// NaN and for values outside an unclamped range
+vec4 colormap(uint slot, float value) {
+    float t = colormap_coord(value);
+
+    if (isnan(t)) {
+        return vec4(0.0);
+    }
+    if (push.user_data[3] == 0.0 && (t < 0.0 || t > 1.0)) {
+        return vec4(0.0);
+    }
+
+    // Centers of the first and last texels hold 0 and 1
+    float u = (clamp(t, 0.0, 1.0) * (COLORMAP_SIZE - 1.0) + 0.5) / COLORMAP_SIZE;
+    return textureLod(sampler2D(textures[nonuniformEXT(push.tex_idx[slot])], samplers[SAMPLER_CLAMP]), vec2(u, 0.5), 0.0);
+}
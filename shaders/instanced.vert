@@ -0,0 +1,19 @@
+#version 450
+
+// Meshes moved and colored per instance, drawn with
+// PassContext::draw_mesh_instanced and a pipeline with
+// instance_input(VertexInput::new().with(ShaderType::Float2).with(ShaderType::Float4))
+//
+// Positions are in clip space
+
+layout(location = 0) in vec4 vPosition;
+// Locations 1-4 are the other attributes of the mesh
+layout(location = 5) in vec2 iOffset;
+layout(location = 6) in vec4 iColor;
+
+layout(location = 0) out vec4 oColor;
+
+void main() {
+    oColor = iColor;
+    gl_Position = vec4(vPosition.xy + iOffset, vPosition.z, 1.0);
+}
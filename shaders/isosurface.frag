@@ -0,0 +1,41 @@
+#version 450
+
+// Isosurface in one color lit by a head light
+
+layout(location = 0) in vec3 oNormal;
+layout(location = 1) in vec3 oWorld;
+
+struct Camera {
+    mat4 view;
+    mat4 proj;
+    mat4 view_proj;
+    mat4 inv_view;
+    mat4 inv_proj;
+    mat4 inv_view_proj;
+};
+
+layout(set = 1, binding = 0) uniform CameraBuffer {
+    Camera camera;
+};
+
+layout(push_constant) uniform Push {
+    uint transform_idx;
+    uint tex_idx[8];
+    // Layout of shaders/isosurface.vert
+    float user_data[23];
+} push;
+
+layout(location = 0) out vec4 finalColor;
+
+void main() {
+    vec4 color = vec4(push.user_data[0], push.user_data[1], push.user_data[2], push.user_data[3]);
+
+    // Both sides are lit, surfaces open at the border of the box show
+    // their inside
+    vec3 view = normalize(camera.inv_view[3].xyz - oWorld);
+    // Flat scalars have no normal
+    float facing = length(oNormal) > 0.0 ? abs(dot(normalize(oNormal), view)) : 1.0;
+    float light = 0.3 + 0.7 * facing;
+
+    finalColor = vec4(color.rgb * light, color.a);
+}
@@ -0,0 +1,58 @@
+#version 450
+
+// Triangles written by shaders/marching_cubes.comp, see
+// PassContext::draw_isosurface
+
+layout(location = 0) in vec3 vPosition;
+layout(location = 1) in vec3 vNormal;
+
+struct Camera {
+    mat4 view;
+    mat4 proj;
+    mat4 view_proj;
+    mat4 inv_view;
+    mat4 inv_proj;
+    mat4 inv_view_proj;
+};
+
+struct Transform {
+    mat4 model;
+    mat4 normal;
+};
+
+layout(set = 1, binding = 0) uniform CameraBuffer {
+    Camera camera;
+};
+
+layout(set = 1, binding = 2) readonly buffer TransformBuffer {
+    Transform transforms[];
+};
+
+layout(push_constant) uniform Push {
+    uint transform_idx;
+    uint tex_idx[8];
+    // 0-3 linear color
+    float user_data[23];
+} push;
+
+// transform_idx of draws placed by no transform, NO_TRANSFORM of
+// src/frame_graph/pass_context.rs. transforms[] may be empty then
+const uint NO_TRANSFORM = 0xFFFFFFFFu;
+
+layout(location = 0) out vec3 oNormal;
+layout(location = 1) out vec3 oWorld;
+
+void main() {
+    mat4 model = mat4(1.0);
+    mat4 normal = mat4(1.0);
+    if (push.transform_idx != NO_TRANSFORM) {
+        model = transforms[push.transform_idx].model;
+        normal = transforms[push.transform_idx].normal;
+    }
+
+    vec4 world = model * vec4(vPosition, 1.0);
+    gl_Position = camera.view_proj * world;
+
+    oNormal = (normal * vec4(vNormal, 0.0)).xyz;
+    oWorld = world.xyz;
+}
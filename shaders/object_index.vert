@@ -0,0 +1,19 @@
+#version 450
+
+// Scene objects placed and colored by their index, drawn with
+// PassContext::draw_indirect_count
+//
+// Transforms are ignored, so only the cull pass decides which objects show
+// up: object 0 is moved to the left half of the target in red, every other
+// object to the right half in green. Positions are in clip space
+
+layout(location = 0) in vec4 vPosition;
+
+layout(location = 0) out vec4 oColor;
+
+void main() {
+    // The first instance of a scene draw is the object index
+    bool first = gl_InstanceIndex == 0;
+    oColor = first ? vec4(1.0, 0.0, 0.0, 1.0) : vec4(0.0, 1.0, 0.0, 1.0);
+    gl_Position = vec4(vPosition.x + (first ? -0.5 : 0.5), vPosition.yz, 1.0);
+}
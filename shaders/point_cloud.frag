@@ -0,0 +1,51 @@
+#version 450
+
+layout(location = 0) in vec2 oCorner;
+layout(location = 1) in vec4 oColor;
+layout(location = 2) flat in float oValue;
+
+layout(set = 0, binding = 0) uniform texture2D textures[16384];
+layout(set = 0, binding = 2) uniform sampler samplers[5];
+
+layout(push_constant) uniform Push {
+    uint transform_idx;
+    uint tex_idx[8];
+    // Layout of shaders/point_cloud.vert
+    float user_data[23];
+} push;
+
+layout(location = 0) out vec4 finalColor;
+
+// SAMPLER_CLAMP of shaders/inc/bindless.glsl
+const uint SAMPLER_CLAMP = 1;
+const float COLORMAP_SIZE = 256.0;
+const float SHAPE_CIRCLE = 1.0;
+const float COLOR_SCALAR = 1.0;
+
+void main() {
+    // Points write depth, circles are cut out instead of blended
+    if (push.user_data[5] == SHAPE_CIRCLE && dot(oCorner, oCorner) > 1.0) {
+        discard;
+    }
+
+    if (push.user_data[6] != COLOR_SCALAR) {
+        finalColor = oColor;
+        return;
+    }
+
+    // colormap() of shaders/inc/colormap.glsl, transparent points are cut
+    // out like the corners of circles
+    float lo = push.user_data[0];
+    float hi = push.user_data[1];
+
+    float t = (oValue - lo) / (hi - lo);
+    if (push.user_data[2] != 0.0) {
+        t = oValue > 0.0 ? (log(oValue) - log(lo)) / (log(hi) - log(lo)) : -1.0;
+    }
+    if (isnan(t) || (push.user_data[3] == 0.0 && (t < 0.0 || t > 1.0))) {
+        discard;
+    }
+
+    float u = (clamp(t, 0.0, 1.0) * (COLORMAP_SIZE - 1.0) + 0.5) / COLORMAP_SIZE;
+    finalColor = textureLod(sampler2D(textures[push.tex_idx[0]], samplers[SAMPLER_CLAMP]), vec2(u, 0.5), 0.0);
+}
@@ -0,0 +1,96 @@
+#version 450
+
+// Points of a PointCloud chunk expanded into screen-aligned quads, see
+// PassContext::draw_point_cloud
+//
+// Every instance is one point, vertices 0-5 are the two triangles of its
+// sprite
+
+layout(location = 0) in vec3 vPosition;
+// sRGBA8 color or f32 scalar bits
+layout(location = 1) in uint vValue;
+
+struct Camera {
+    mat4 view;
+    mat4 proj;
+    mat4 view_proj;
+    mat4 inv_view;
+    mat4 inv_proj;
+    mat4 inv_view_proj;
+};
+
+struct FrameData {
+    uvec2 resolution;
+    uint  frame_idx;
+    float delta_time_sec;
+    float time_sec;
+    float pad;
+};
+
+struct Transform {
+    mat4 model;
+    mat4 normal;
+};
+
+layout(set = 1, binding = 0) uniform CameraBuffer {
+    Camera camera;
+};
+
+layout(set = 1, binding = 1) uniform FrameBuffer {
+    FrameData frame_data;
+};
+
+layout(set = 1, binding = 2) readonly buffer TransformBuffer {
+    Transform transforms[];
+};
+
+layout(push_constant) uniform Push {
+    uint transform_idx;
+    uint tex_idx[8];
+    // 0-3 scalar range of shaders/inc/colormap.glsl, 4 size in pixels,
+    // 5 shape, 6 color mode
+    float user_data[23];
+} push;
+
+// transform_idx of draws placed by no transform, NO_TRANSFORM of
+// src/frame_graph/pass_context.rs. transforms[] may be empty then
+const uint NO_TRANSFORM = 0xFFFFFFFFu;
+
+// Corner of the sprite from -1 to 1
+layout(location = 0) out vec2 oCorner;
+layout(location = 1) out vec4 oColor;
+// Scalar of points colored by the colormap of push.tex_idx[0]
+layout(location = 2) flat out float oValue;
+
+const float COLOR_SCALAR = 1.0;
+
+vec3 srgb_to_linear(vec3 color) {
+    vec3 low = color / 12.92;
+    vec3 high = pow((color + 0.055) / 1.055, vec3(2.4));
+    return mix(high, low, lessThanEqual(color, vec3(0.04045)));
+}
+
+void main() {
+    mat4 mvp = camera.view_proj;
+    if (push.transform_idx != NO_TRANSFORM) {
+        mvp = mvp * transforms[push.transform_idx].model;
+    }
+
+    oValue = uintBitsToFloat(vValue);
+    if (push.user_data[6] == COLOR_SCALAR) {
+        oColor = vec4(0.0);
+    } else {
+        vec4 color = unpackUnorm4x8(vValue);
+        oColor = vec4(srgb_to_linear(color.rgb), color.a);
+    }
+
+    int index = int(gl_VertexIndex);
+    int corner = index == 0 || index == 3 ? 0 : index == 1 ? 1 : index == 2 || index == 4 ? 2 : 3;
+    oCorner = vec2(corner == 1 || corner == 2 ? 1.0 : -1.0, corner < 2 ? -1.0 : 1.0);
+
+    // Sizes stay in pixels at any distance
+    vec4 clip = mvp * vec4(vPosition, 1.0);
+    vec2 resolution = vec2(frame_data.resolution);
+    clip.xy += oCorner * push.user_data[4] / resolution * clip.w;
+    gl_Position = clip;
+}
@@ -0,0 +1,76 @@
+#version 450
+
+// Analytic coverage of the shapes polyline.vert expands
+
+layout(location = 0) in vec2 oLocal;
+layout(location = 1) in vec2 oJoin;
+layout(location = 2) in float oDistance;
+layout(location = 3) flat in vec4 oEnds;
+layout(location = 4) flat in vec4 oNormals;
+
+layout(push_constant) uniform Push {
+    uint transform_idx;
+    uint tex_idx[8];
+    float user_data[23];
+} push;
+
+layout(location = 0) out vec4 finalColor;
+
+const float END_BUTT = 1.0;
+const float END_SQUARE = 2.0;
+const float END_ROUND = 3.0;
+
+const float PIECE_BODY = 0.0;
+const float PIECE_MITER = 1.0;
+
+// Distance to the centerline past the end of a segment, the edge is at
+// half the width
+float end_distance(float beyond, float across, float mode, float half_width) {
+    if (mode == END_BUTT) {
+        return max(across, half_width + beyond);
+    }
+    if (mode == END_SQUARE) {
+        return max(across, beyond);
+    }
+    if (mode == END_ROUND) {
+        return length(vec2(beyond, across));
+    }
+    return across;
+}
+
+void main() {
+    float half_width = push.user_data[0] * 0.5;
+    vec2 o1 = oNormals.xy;
+    vec2 o2 = oNormals.zw;
+
+    float d;
+    if (oEnds.w == PIECE_BODY) {
+        float across = abs(oLocal.y);
+        d = across;
+        if (oLocal.x < 0.0) {
+            d = end_distance(-oLocal.x, across, oEnds.y, half_width);
+        } else if (oLocal.x > oEnds.x) {
+            d = end_distance(oLocal.x - oEnds.x, across, oEnds.z, half_width);
+        }
+    } else if (oEnds.w == PIECE_MITER) {
+        d = max(dot(oJoin, o1), dot(oJoin, o2));
+    } else {
+        vec2 m = normalize(o1 + o2);
+        d = dot(oJoin, m) / dot(o1, m);
+    }
+
+    float alpha = clamp(half_width - d + 0.5, 0.0, 1.0);
+
+    float dash = push.user_data[5];
+    float gap = push.user_data[6];
+    if (dash > 0.0 && gap > 0.0 && mod(oDistance, dash + gap) > dash) {
+        discard;
+    }
+
+    if (alpha <= 0.0) {
+        discard;
+    }
+
+    vec4 color = vec4(push.user_data[1], push.user_data[2], push.user_data[3], push.user_data[4]);
+    finalColor = vec4(color.rgb, color.a * alpha);
+}
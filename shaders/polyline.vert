@@ -0,0 +1,192 @@
+#version 450
+
+// Polylines expanded into screen-space quads, see PassContext::draw_polyline
+//
+// Every instance draws the segment a -> b with prev and next around it. The
+// first and last points are stored twice, so prev == a at the start and
+// next == b at the end. Vertices 0-5 are the body of the segment, 6-11 the
+// miter or bevel join at b
+
+// xyz position, w distance along the line in world units
+layout(location = 0) in vec4 vPrev;
+layout(location = 1) in vec4 vA;
+layout(location = 2) in vec4 vB;
+layout(location = 3) in vec4 vNext;
+
+struct Camera {
+    mat4 view;
+    mat4 proj;
+    mat4 view_proj;
+    mat4 inv_view;
+    mat4 inv_proj;
+    mat4 inv_view_proj;
+};
+
+struct FrameData {
+    uvec2 resolution;
+    uint  frame_idx;
+    float delta_time_sec;
+    float time_sec;
+    float pad;
+};
+
+struct Transform {
+    mat4 model;
+    mat4 normal;
+};
+
+layout(set = 1, binding = 0) uniform CameraBuffer {
+    Camera camera;
+};
+
+layout(set = 1, binding = 1) uniform FrameBuffer {
+    FrameData frame_data;
+};
+
+layout(set = 1, binding = 2) readonly buffer TransformBuffer {
+    Transform transforms[];
+};
+
+layout(push_constant) uniform Push {
+    uint transform_idx;
+    uint tex_idx[8];
+    // 0 width in pixels, 1-4 color, 5-6 dash and gap, 7 join, 8 cap,
+    // 9 miter limit
+    float user_data[23];
+} push;
+
+// transform_idx of draws placed by no transform, NO_TRANSFORM of
+// src/frame_graph/pass_context.rs. transforms[] may be empty then
+const uint NO_TRANSFORM = 0xFFFFFFFFu;
+
+// Along and across the segment in pixels
+layout(location = 0) out vec2 oLocal;
+// Offset from b in pixels
+layout(location = 1) out vec2 oJoin;
+// Distance along the line in world units
+layout(location = 2) out float oDistance;
+// Length in pixels, end modes at a and b, piece
+layout(location = 3) flat out vec4 oEnds;
+// Outer normals of this and the next segment
+layout(location = 4) flat out vec4 oNormals;
+
+// Width of the anti-aliased edge in pixels
+const float AA = 1.0;
+
+const float JOIN_MITER = 0.0;
+const float JOIN_ROUND = 1.0;
+
+// End of a segment without its own edge, a miter or bevel join follows
+const float END_OPEN = 0.0;
+const float END_SQUARE = 2.0;
+
+const float PIECE_BODY = 0.0;
+const float PIECE_MITER = 1.0;
+const float PIECE_BEVEL = 2.0;
+
+vec2 to_screen(vec4 clip, vec2 resolution) {
+    return (clip.xy / clip.w * 0.5 + 0.5) * resolution;
+}
+
+vec4 to_clip(vec2 screen, vec4 clip, vec2 resolution) {
+    return vec4(screen / resolution * 2.0 - 1.0, clip.z / clip.w, 1.0);
+}
+
+// Caps map to 1 butt, 2 square and 3 round, joins to open or round
+float end_mode(bool is_end) {
+    if (is_end) {
+        return push.user_data[8] + 1.0;
+    }
+    return push.user_data[7] == JOIN_ROUND ? 3.0 : END_OPEN;
+}
+
+float extension(float mode, float half_width) {
+    if (mode == END_OPEN) {
+        return 0.0;
+    }
+    return mode >= END_SQUARE ? half_width + AA : AA;
+}
+
+void main() {
+    vec2 resolution = vec2(frame_data.resolution);
+    mat4 mvp = camera.view_proj;
+    if (push.transform_idx != NO_TRANSFORM) {
+        mvp = mvp * transforms[push.transform_idx].model;
+    }
+    float half_width = push.user_data[0] * 0.5;
+
+    vec4 clip_a = mvp * vec4(vA.xyz, 1.0);
+    vec4 clip_b = mvp * vec4(vB.xyz, 1.0);
+    vec2 a = to_screen(clip_a, resolution);
+    vec2 b = to_screen(clip_b, resolution);
+
+    vec2 dir = b - a;
+    float len = length(dir);
+    vec2 t = len > 1e-6 ? dir / len : vec2(1.0, 0.0);
+    vec2 n = vec2(-t.y, t.x);
+
+    bool is_start = vPrev.xyz == vA.xyz;
+    bool is_end = vNext.xyz == vB.xyz;
+    float mode_a = end_mode(is_start);
+    float mode_b = end_mode(is_end);
+
+    oEnds = vec4(len, mode_a, mode_b, PIECE_BODY);
+    oNormals = vec4(n, n);
+    oJoin = vec2(0.0);
+
+    int index = int(gl_VertexIndex);
+    if (index < 6) {
+        // Two triangles of the body
+        int corner = index == 0 || index == 3 ? 0 : index == 1 ? 1 : index == 2 || index == 4 ? 2 : 3;
+        float along = corner == 0 || corner == 3 ? -extension(mode_a, half_width) : len + extension(mode_b, half_width);
+        float across = (corner < 2 ? -1.0 : 1.0) * (half_width + AA);
+
+        oLocal = vec2(along, across);
+        oDistance = mix(vA.w, vB.w, len > 1e-6 ? along / len : 0.0);
+        gl_Position = to_clip(a + t * along + n * across, corner == 0 || corner == 3 ? clip_a : clip_b, resolution);
+        return;
+    }
+
+    // Join at b, collapsed when b has its own edge or the line goes straight
+    oLocal = vec2(len, 0.0);
+    oDistance = vB.w;
+    gl_Position = to_clip(b, clip_b, resolution);
+
+    vec2 next = to_screen(mvp * vec4(vNext.xyz, 1.0), resolution);
+    vec2 t2 = next - b;
+    if (mode_b != END_OPEN || length(t2) <= 1e-6) {
+        return;
+    }
+    t2 = normalize(t2);
+
+    float turn = t.x * t2.y - t.y * t2.x;
+    if (abs(turn) <= 1e-4) {
+        return;
+    }
+
+    // The outer side is away from the turn
+    float side = turn > 0.0 ? -1.0 : 1.0;
+    vec2 o1 = n * side;
+    vec2 o2 = vec2(-t2.y, t2.x) * side;
+    vec2 m = normalize(o1 + o2);
+    float miter = 1.0 / dot(m, o1);
+
+    bool is_miter = push.user_data[7] == JOIN_MITER && miter <= push.user_data[9];
+    float tip = is_miter ? (half_width + AA) * miter : half_width / miter + AA;
+
+    // Fan b, o1 corner, tip, o2 corner
+    int fan = index - 6;
+    vec2 offset = vec2(0.0);
+    if (fan == 1) {
+        offset = o1 * (half_width + AA);
+    } else if (fan == 2 || fan == 4) {
+        offset = m * tip;
+    } else if (fan == 5) {
+        offset = o2 * (half_width + AA);
+    }
+
+    oEnds.w = is_miter ? PIECE_MITER : PIECE_BEVEL;
+    oNormals = vec4(o1, o2);
+    oJoin = offset;
+    gl_Position = to_clip(b + offset, clip_b, resolution);
+}
@@ -0,0 +1,39 @@
+#version 450
+
+// Scalar of scalar.vert through the colormap of slot 0, the body of
+// colormap() in shaders/inc/colormap.glsl
+
+layout(location = 0) in float oValue;
+
+layout(set = 0, binding = 0) uniform texture2D textures[16384];
+layout(set = 0, binding = 2) uniform sampler samplers[5];
+
+layout(push_constant) uniform Push {
+    uint transform_idx;
+    uint tex_idx[8];
+    // ScalarRange: min, max, log, clamp
+    float user_data[23];
+} push;
+
+layout(location = 0) out vec4 finalColor;
+
+// SAMPLER_CLAMP of shaders/inc/bindless.glsl
+const uint SAMPLER_CLAMP = 1;
+const float COLORMAP_SIZE = 256.0;
+
+void main() {
+    float lo = push.user_data[0];
+    float hi = push.user_data[1];
+
+    float t = (oValue - lo) / (hi - lo);
+    if (push.user_data[2] != 0.0) {
+        // Values at or below 0 are below the range
+        t = oValue > 0.0 ? (log(oValue) - log(lo)) / (log(hi) - log(lo)) : -1.0;
+    }
+    if (isnan(t) || (push.user_data[3] == 0.0 && (t < 0.0 || t > 1.0))) {
+        discard;
+    }
+
+    float u = (clamp(t, 0.0, 1.0) * (COLORMAP_SIZE - 1.0) + 0.5) / COLORMAP_SIZE;
+    finalColor = textureLod(sampler2D(textures[push.tex_idx[0]], samplers[SAMPLER_CLAMP]), vec2(u, 0.5), 0.0);
+}
@@ -0,0 +1,46 @@
+#version 450
+
+// Meshes colored by one scalar per vertex, drawn with
+// PassContext::draw_mesh_with_attributes and a pipeline with
+// vertex_input(VertexInput::new().with(ShaderType::Float))
+
+layout(location = 0) in vec4 vPosition;
+// Locations 1-4 are the other attributes of the mesh
+layout(location = 5) in float vValue;
+
+struct Camera {
+    mat4 view;
+    mat4 proj;
+    mat4 view_proj;
+    mat4 inv_view;
+    mat4 inv_proj;
+    mat4 inv_view_proj;
+};
+
+struct Transform {
+    mat4 model;
+    mat4 normal;
+};
+
+layout(set = 1, binding = 0) uniform CameraBuffer {
+    Camera camera;
+};
+
+layout(set = 1, binding = 2) readonly buffer TransformBuffer {
+    Transform transforms[];
+};
+
+layout(push_constant) uniform Push {
+    uint transform_idx;
+    uint tex_idx[8];
+    float user_data[23];
+} push;
+
+// Interpolated before the lookup, so colors follow the colormap across
+// triangles
+layout(location = 0) out float oValue;
+
+void main() {
+    gl_Position = camera.view_proj * transforms[push.transform_idx].model * vec4(vPosition.xyz, 1.0);
+    oValue = vValue;
+}
@@ -0,0 +1,33 @@
+#version 450
+
+// Coverage from the distance field, 0.5 on the outline
+
+layout(location = 0) in vec2 oUV;
+layout(location = 1) in vec4 oColor;
+
+layout(set = 0, binding = 0) uniform texture2D textures[16384];
+layout(set = 0, binding = 2) uniform sampler samplers[5];
+
+layout(push_constant) uniform Push {
+    uint transform_idx;
+    uint tex_idx[8];
+    float user_data[23];
+} push;
+
+layout(location = 0) out vec4 finalColor;
+
+// SAMPLER_CLAMP of shaders/inc/bindless.glsl
+const uint SAMPLER_CLAMP = 1;
+
+void main() {
+    float distance = texture(sampler2D(textures[push.tex_idx[0]], samplers[SAMPLER_CLAMP]), oUV).r;
+    // One pixel wide edge at any size
+    float width = max(fwidth(distance), 1e-4);
+    float alpha = clamp((distance - 0.5) / width + 0.5, 0.0, 1.0);
+
+    if (alpha <= 0.0) {
+        discard;
+    }
+
+    finalColor = vec4(oColor.rgb, oColor.a * alpha);
+}
@@ -0,0 +1,89 @@
+#version 450
+
+// Glyph quads of a Text, see PassContext::draw_text
+//
+// Every instance is one glyph, vertices 0-5 are the two triangles of its
+// quad. Quads are in ems from the anchor with y down
+
+layout(location = 0) in vec4 vRect;
+layout(location = 1) in vec4 vUV;
+
+struct Camera {
+    mat4 view;
+    mat4 proj;
+    mat4 view_proj;
+    mat4 inv_view;
+    mat4 inv_proj;
+    mat4 inv_view_proj;
+};
+
+struct FrameData {
+    uvec2 resolution;
+    uint  frame_idx;
+    float delta_time_sec;
+    float time_sec;
+    float pad;
+};
+
+struct Transform {
+    mat4 model;
+    mat4 normal;
+};
+
+layout(set = 1, binding = 0) uniform CameraBuffer {
+    Camera camera;
+};
+
+layout(set = 1, binding = 1) uniform FrameBuffer {
+    FrameData frame_data;
+};
+
+layout(set = 1, binding = 2) readonly buffer TransformBuffer {
+    Transform transforms[];
+};
+
+layout(push_constant) uniform Push {
+    uint transform_idx;
+    // Atlas texture
+    uint tex_idx[8];
+    // 0-2 anchor, pixels on screen or a world position, 3 is 1 for world
+    // anchors, 4 pixels per em, 5-8 color
+    float user_data[23];
+} push;
+
+// transform_idx of draws placed by no transform, NO_TRANSFORM of
+// src/frame_graph/pass_context.rs. transforms[] may be empty then
+const uint NO_TRANSFORM = 0xFFFFFFFFu;
+
+layout(location = 0) out vec2 oUV;
+layout(location = 1) out vec4 oColor;
+
+const float ANCHOR_WORLD = 1.0;
+
+void main() {
+    int index = int(gl_VertexIndex);
+    int corner = index == 0 || index == 3 ? 0 : index == 1 ? 1 : index == 2 || index == 4 ? 2 : 3;
+    vec2 t = vec2(corner == 1 || corner == 2 ? 1.0 : 0.0, corner < 2 ? 0.0 : 1.0);
+
+    vec2 offset = mix(vRect.xy, vRect.zw, t) * push.user_data[4];
+    vec2 resolution = vec2(frame_data.resolution);
+    vec3 anchor = vec3(push.user_data[0], push.user_data[1], push.user_data[2]);
+
+    if (push.user_data[3] == ANCHOR_WORLD) {
+        mat4 mvp = camera.view_proj;
+        if (push.transform_idx != NO_TRANSFORM) {
+            mvp = mvp * transforms[push.transform_idx].model;
+        }
+
+        // Billboards keep their size in pixels at any distance
+        vec4 clip = mvp * vec4(anchor, 1.0);
+        clip.xy += offset / resolution * 2.0 * clip.w;
+        gl_Position = clip;
+    } else {
+        // Reversed-Z near plane, screen text is never hidden
+        gl_Position = vec4((anchor.xy + offset) / resolution * 2.0 - 1.0, 1.0, 1.0);
+    }
+
+    oUV = mix(vUV.xy, vUV.zw, t);
+    oColor = vec4(push.user_data[5], push.user_data[6], push.user_data[7], push.user_data[8]);
+}
@@ -0,0 +1,183 @@
+#version 450
+#extension GL_EXT_nonuniform_qualifier : require
+
+// Ray march of a Volume inside its proxy box, see PassContext::draw_volume
+//
+// Rays start at the near plane or where they enter the box and end where
+// they leave it or reach the depth buffer. Samples are colored by the
+// colormap, weighted by the opacity curve and composited front to back
+// until the ray is nearly opaque. The output is premultiplied by alpha
+
+layout(location = 0) in vec3 oWorld;
+
+struct Camera {
+    mat4 view;
+    mat4 proj;
+    mat4 view_proj;
+    mat4 inv_view;
+    mat4 inv_proj;
+    mat4 inv_view_proj;
+};
+
+struct FrameData {
+    uvec2 resolution;
+    uint frame_idx;
+    float delta_time_sec;
+    float time_sec;
+    float pad;
+};
+
+struct Transform {
+    mat4 model;
+    mat4 normal;
+};
+
+layout(set = 0, binding = 0) uniform texture2D textures[16384];
+// Binding 0 again, 3D textures are read through this view of it
+layout(set = 0, binding = 0) uniform texture3D textures3d[16384];
+layout(set = 0, binding = 2) uniform sampler samplers[5];
+
+layout(set = 1, binding = 0) uniform CameraBuffer {
+    Camera camera;
+};
+
+layout(set = 1, binding = 1) uniform FrameBuffer {
+    FrameData frame_data;
+};
+
+layout(set = 1, binding = 2) readonly buffer TransformBuffer {
+    Transform transforms[];
+};
+
+layout(push_constant) uniform Push {
+    uint transform_idx;
+    uint tex_idx[8];
+    // Layout of shaders/volume.vert
+    float user_data[23];
+} push;
+
+// transform_idx of draws placed by no transform, NO_TRANSFORM of
+// src/frame_graph/pass_context.rs. transforms[] may be empty then
+const uint NO_TRANSFORM = 0xFFFFFFFFu;
+
+layout(location = 0) out vec4 finalColor;
+
+// SAMPLER_CLAMP and SAMPLER_MIP_POINT of shaders/inc/bindless.glsl
+const uint SAMPLER_CLAMP = 1;
+const uint SAMPLER_MIP_POINT = 4;
+const float COLORMAP_SIZE = 256.0;
+// Samples behind a ray this opaque are hidden
+const float OPAQUE = 0.99;
+
+vec3 unproject(vec2 ndc, float depth) {
+    vec4 point = camera.inv_view_proj * vec4(ndc, depth, 1.0);
+    return point.xyz / point.w;
+}
+
+// colormap_coord() of shaders/inc/colormap.glsl
+float colormap_coord(float value) {
+    float lo = push.user_data[0];
+    float hi = push.user_data[1];
+
+    if (push.user_data[2] != 0.0) {
+        return value > 0.0 ? (log(value) - log(lo)) / (log(hi) - log(lo)) : -1.0;
+    }
+
+    return (value - lo) / (hi - lo);
+}
+
+// Linear between the points of the OpacityCurve, the ends hold past them
+float opacity(float t) {
+    uint count = uint(push.user_data[10]);
+    vec2 previous = vec2(push.user_data[11], push.user_data[12]);
+    if (t <= previous.x) {
+        return previous.y;
+    }
+
+    for (uint i = 1; i < count; i++) {
+        vec2 next = vec2(push.user_data[11 + 2 * i], push.user_data[12 + 2 * i]);
+        if (t <= next.x) {
+            return mix(previous.y, next.y, (t - previous.x) / max(next.x - previous.x, 1e-6));
+        }
+        previous = next;
+    }
+
+    return previous.y;
+}
+
+void main() {
+    // The fragment is at t = 1 of the ray from the near plane
+    vec2 ndc = gl_FragCoord.xy / vec2(frame_data.resolution) * 2.0 - 1.0;
+    vec3 origin = unproject(ndc, 1.0);
+    vec3 dir = oWorld - origin;
+
+    // The normal matrix is the inverse transpose of the model matrix
+    mat4 inv_model = mat4(1.0);
+    if (push.transform_idx != NO_TRANSFORM) {
+        inv_model = transpose(transforms[push.transform_idx].normal);
+    }
+    vec3 local_origin = (inv_model * vec4(origin, 1.0)).xyz;
+    vec3 local_dir = (inv_model * vec4(dir, 0.0)).xyz;
+
+    vec3 extent = vec3(push.user_data[6], push.user_data[7], push.user_data[8]);
+    vec3 t0 = (-0.5 * extent - local_origin) / local_dir;
+    vec3 t1 = (0.5 * extent - local_origin) / local_dir;
+    vec3 t_min = min(t0, t1);
+    vec3 t_max = max(t0, t1);
+    float t_near = max(max(t_min.x, t_min.y), max(t_min.z, 0.0));
+    float t_far = min(min(t_max.x, t_max.y), t_max.z);
+
+    // Both sides of the box are drawn whatever their winding, only rays
+    // leaving through this fragment march
+    if (t_far > 1.0 + 1e-4) {
+        discard;
+    }
+
+    // Reversed-Z, cleared to 0 where nothing was drawn
+    float depth = texelFetch(sampler2D(textures[nonuniformEXT(push.tex_idx[2])], samplers[SAMPLER_MIP_POINT]), ivec2(gl_FragCoord.xy), 0).r;
+    if (depth > 0.0) {
+        vec3 hit = unproject(ndc, depth);
+        t_far = min(t_far, dot(hit - origin, dir) / dot(dir, dir));
+    }
+    if (t_far <= t_near) {
+        discard;
+    }
+
+    // Steps of equal length in the box, opacities are given per texel
+    vec3 size = vec3(textureSize(sampler3D(textures3d[nonuniformEXT(push.tex_idx[1])], samplers[SAMPLER_CLAMP]), 0));
+    float samples = max(push.user_data[9], 1.0);
+    float dt = length(extent) / samples / length(local_dir);
+    float texels = length(local_dir * dt / extent * size);
+
+    // Offset per pixel against banding
+    float jitter = fract(52.9829189 * fract(dot(gl_FragCoord.xy, vec2(0.06711056, 0.00583715))));
+
+    vec4 color = vec4(0.0);
+    float t = t_near + dt * jitter;
+    for (uint i = 0; i <= uint(samples) && t < t_far; i++, t += dt) {
+        vec3 uvw = (local_origin + local_dir * t) / extent + 0.5;
+        float texel = textureLod(sampler3D(textures3d[nonuniformEXT(push.tex_idx[1])], samplers[SAMPLER_CLAMP]), uvw, 0.0).r;
+        float coord = colormap_coord(texel * push.user_data[4] + push.user_data[5]);
+
+        // NaN and values outside an unclamped range are transparent
+        if (isnan(coord) || (push.user_data[3] == 0.0 && (coord < 0.0 || coord > 1.0))) {
+            continue;
+        }
+        coord = clamp(coord, 0.0, 1.0);
+
+        float alpha = 1.0 - pow(1.0 - clamp(opacity(coord), 0.0, 1.0), texels);
+        float u = (coord * (COLORMAP_SIZE - 1.0) + 0.5) / COLORMAP_SIZE;
+        vec3 sample_color = textureLod(sampler2D(textures[nonuniformEXT(push.tex_idx[0])], samplers[SAMPLER_CLAMP]), vec2(u, 0.5), 0.0).rgb;
+
+        color.rgb += (1.0 - color.a) * alpha * sample_color;
+        color.a += (1.0 - color.a) * alpha;
+        if (color.a >= OPAQUE) {
+            break;
+        }
+    }
+
+    if (color.a <= 0.0) {
+        discard;
+    }
+    finalColor = color;
+}
@@ -0,0 +1,70 @@
+#version 450
+
+// Proxy box of a Volume, see PassContext::draw_volume
+//
+// The 36 vertices of the box come from gl_VertexIndex, it spans the extent
+// of the volume around the local origin
+
+struct Camera {
+    mat4 view;
+    mat4 proj;
+    mat4 view_proj;
+    mat4 inv_view;
+    mat4 inv_proj;
+    mat4 inv_view_proj;
+};
+
+struct Transform {
+    mat4 model;
+    mat4 normal;
+};
+
+layout(set = 1, binding = 0) uniform CameraBuffer {
+    Camera camera;
+};
+
+layout(set = 1, binding = 2) readonly buffer TransformBuffer {
+    Transform transforms[];
+};
+
+layout(push_constant) uniform Push {
+    uint transform_idx;
+    // 0 colormap, 1 scalar texture, 2 depth buffer
+    uint tex_idx[8];
+    // 0-3 ScalarRange of the colormap, 4-5 scale and offset of texels, 6-8
+    // box extent, 9 samples along its diagonal, 10 number of opacity
+    // points, 11-20 position and opacity of each point
+    float user_data[23];
+} push;
+
+// transform_idx of draws placed by no transform, NO_TRANSFORM of
+// src/frame_graph/pass_context.rs. transforms[] may be empty then
+const uint NO_TRANSFORM = 0xFFFFFFFFu;
+
+layout(location = 0) out vec3 oWorld;
+
+// Corners of the 12 triangles, bits 0, 1 and 2 of a corner select its x, y
+// and z side
+const uint CORNERS[36] = uint[36](
+    0, 2, 6, 0, 6, 4,
+    1, 5, 7, 1, 7, 3,
+    0, 4, 5, 0, 5, 1,
+    2, 3, 7, 2, 7, 6,
+    0, 1, 3, 0, 3, 2,
+    4, 6, 7, 4, 7, 5
+);
+
+void main() {
+    uint corner = CORNERS[gl_VertexIndex];
+    vec3 side = vec3(float(corner & 1u), float((corner >> 1) & 1u), float((corner >> 2) & 1u)) - 0.5;
+    vec3 extent = vec3(push.user_data[6], push.user_data[7], push.user_data[8]);
+
+    mat4 model = mat4(1.0);
+    if (push.transform_idx != NO_TRANSFORM) {
+        model = transforms[push.transform_idx].model;
+    }
+
+    vec4 world = model * vec4(side * extent, 1.0);
+    gl_Position = camera.view_proj * world;
+    oWorld = world.xyz;
+}
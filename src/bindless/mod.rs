@@ -2,19 +2,34 @@ use std::sync::atomic::{AtomicU32, Ordering};
 use std::sync::Arc;
 
 use ash::vk;
+use parking_lot::Mutex;
 
-use crate::core::{DescriptorPool, DescriptorPoolBuilder, DescriptorSetLayout, DescriptorSetLayoutBuilder, Device, VulkanResult};
+use crate::core::{
+    DescriptorPool, DescriptorPoolBuilder, DescriptorSetLayout, DescriptorSetLayoutBuilder, Device, Sampler, SamplerBuilder, VulkanResult,
+};
 use crate::render_context::RenderContext;
 
 const MAX_SAMPLED_IMAGE: u32 = 16_384;
 const MAX_STORAGE_IMAGES: u32 = 1_024;
 const MAX_SAMPLER: u32 = 5;
 
+/// Linear filtering clamped to the edge, `SAMPLER_CLAMP` of
+/// `shaders/inc/bindless.glsl`
+#[allow(dead_code)]
+pub(crate) const SAMPLER_CLAMP: u32 = 1;
+
+/// Nearest filtering, `SAMPLER_MIP_POINT` of `shaders/inc/bindless.glsl`
+#[allow(dead_code)]
+pub(crate) const SAMPLER_MIP_POINT: u32 = 4;
+
 pub(crate) struct Bindless {
     pub(crate) set_layout: DescriptorSetLayout,
     pub(crate) set: vk::DescriptorSet,
     pub(crate) pool: DescriptorPool,
     pub(crate) next_texture: AtomicU32,
+    /// Released texture indices, reused before new ones
+    free_textures: Mutex<Vec<u32>>,
+    samplers: Vec<Sampler>,
 }
 
 impl Bindless {
@@ -75,8 +90,37 @@ impl Bindless {
         let layouts = [set_layout.raw];
         let set = pool.create_descriptor_set(&ctx.device, &layouts)?[0];
 
+        // In the order of the SAMPLER_* constants of the shaders
+        let samplers = vec![
+            SamplerBuilder::repeat(&ctx.device).build()?,
+            SamplerBuilder::clamp(&ctx.device).build()?,
+            SamplerBuilder::border(&ctx.device).build()?,
+            SamplerBuilder::repeat(&ctx.device)
+                .mipmap_mode(vk::SamplerMipmapMode::LINEAR)
+                .build()?,
+            SamplerBuilder::repeat(&ctx.device)
+                .filter(vk::Filter::NEAREST)
+                .build()?,
+        ];
+
+        let sampler_infos: Vec<vk::DescriptorImageInfo> = samplers
+            .iter()
+            .map(|sampler| vk::DescriptorImageInfo::default().sampler(sampler.raw))
+            .collect();
+
+        let write = vk::WriteDescriptorSet::default()
+            .dst_set(set)
+            .dst_binding(2)
+            .dst_array_element(0)
+            .descriptor_type(vk::DescriptorType::SAMPLER)
+            .image_info(&sampler_infos);
+
+        unsafe { ctx.device.update_descriptor_sets(&[write], &[]) };
+
         Ok(Self {
             next_texture: AtomicU32::new(0),
+            free_textures: Mutex::new(vec![]),
+            samplers,
             set_layout,
             set,
             pool,
@@ -84,15 +128,47 @@ impl Bindless {
     }
 
     pub fn alloc_texture(&self, device: &Device, image_view: vk::ImageView) -> u32 {
-        let index = self.next_texture.fetch_add(1, Ordering::Relaxed);
+        let index = self.next_index();
         self.update_texture(device, index, image_view);
         index
     }
 
+    /// Index of the depth buffer view `image_view`, see
+    /// [`Bindless::update_depth_texture`]
+    pub fn alloc_depth_texture(&self, device: &Device, image_view: vk::ImageView) -> u32 {
+        let index = self.next_index();
+        self.update_depth_texture(device, index, image_view);
+        index
+    }
+
+    fn next_index(&self) -> u32 {
+        self.free_textures
+            .lock()
+            .pop()
+            .unwrap_or_else(|| self.next_texture.fetch_add(1, Ordering::Relaxed))
+    }
+
+    /// Make `index` available to [`Bindless::alloc_texture`], no frame in
+    /// flight may read it anymore
+    #[allow(dead_code)]
+    pub fn release_texture(&self, index: u32) {
+        self.free_textures.lock().push(index);
+    }
+
     pub fn update_texture(&self, device: &Device, index: u32, image_view: vk::ImageView) {
+        self.write_texture(device, index, image_view, vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL);
+    }
+
+    /// Show the depth buffer view `image_view` as `textures[index]`, it is
+    /// sampled in `DEPTH_STENCIL_READ_ONLY_OPTIMAL` while still attached
+    pub fn update_depth_texture(&self, device: &Device, index: u32, image_view: vk::ImageView) {
+        self.write_texture(device, index, image_view, vk::ImageLayout::DEPTH_STENCIL_READ_ONLY_OPTIMAL);
+    }
+
+    fn write_texture(&self, device: &Device, index: u32, image_view: vk::ImageView, layout: vk::ImageLayout) {
         let image_info = vk::DescriptorImageInfo::default()
             .image_view(image_view)
-            .image_layout(vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL);
+            .image_layout(layout);
 
         let write = vk::WriteDescriptorSet::default()
             .dst_set(self.set)
@@ -105,6 +181,9 @@ impl Bindless {
     }
 
     pub fn destroy(&self, device: &Device) {
+        for sampler in &self.samplers {
+            sampler.destroy(device);
+        }
         unsafe {
             device.destroy_descriptor_pool(self.pool.raw, None);
             device.destroy_descriptor_set_layout(self.set_layout.raw, None);
@@ -0,0 +1,188 @@
+use winit::event::{DeviceEvent, ElementState, WindowEvent};
+use winit::keyboard::{KeyCode, PhysicalKey};
+
+use super::{clamp3, fov_y, forward, lerp, lerp3, scroll_lines, smoothing_factor, CameraController, Pointer};
+use crate::camera::Camera;
+use crate::math::{self, Vec3};
+
+const UP: Vec3 = [0.0, 1.0, 0.0];
+
+/// First-person flight
+///
+/// - Right drag looks around
+/// - `W` `A` `S` `D` move, `E`/`Space` up, `Q`/`Ctrl` down, `Shift` boosts
+/// - Wheel changes speed
+///
+/// Raw [`DeviceEvent::MouseMotion`] is used for looking once it arrives,
+/// cursor movement otherwise
+pub struct FlyController {
+    position: Vec3,
+    yaw: f32,
+    pitch: f32,
+    goal_position: Vec3,
+    goal_yaw: f32,
+    goal_pitch: f32,
+    speed: f32,
+    boost: f32,
+    sensitivity: f32,
+    smoothing: f32,
+    bounds: Option<(Vec3, Vec3)>,
+    pointer: Pointer,
+    raw_motion: bool,
+    // forward, back, left, right, up, down
+    keys: [bool; 6],
+}
+
+impl FlyController {
+    /// Start at `position` looking down `-Z`
+    pub fn new(position: Vec3) -> Self {
+        Self {
+            position,
+            yaw: 0.0,
+            pitch: 0.0,
+            goal_position: position,
+            goal_yaw: 0.0,
+            goal_pitch: 0.0,
+            speed: 5.0,
+            boost: 4.0,
+            sensitivity: 0.003,
+            smoothing: 0.0,
+            bounds: None,
+            pointer: Pointer::default(),
+            raw_motion: false,
+            keys: [false; 6],
+        }
+    }
+
+    /// World units per second
+    pub fn speed(mut self, value: f32) -> Self {
+        self.speed = value;
+        self
+    }
+
+    /// Speed multiplier while `Shift` is held
+    pub fn boost(mut self, value: f32) -> Self {
+        self.boost = value;
+        self
+    }
+
+    /// Radians per mouse count
+    pub fn sensitivity(mut self, value: f32) -> Self {
+        self.sensitivity = value;
+        self
+    }
+
+    /// Time constant in seconds, zero moves immediately
+    pub fn smoothing(mut self, seconds: f32) -> Self {
+        self.smoothing = seconds;
+        self
+    }
+
+    /// Box the camera cannot leave
+    pub fn bounds(mut self, min: Vec3, max: Vec3) -> Self {
+        self.bounds = Some((min, max));
+        self.goal_position = clamp3(self.goal_position, self.bounds);
+        self
+    }
+
+    /// Set yaw around Y and pitch above the horizon in radians
+    pub fn set_angles(&mut self, yaw: f32, pitch: f32) {
+        let limit = 89f32.to_radians();
+        self.goal_yaw = yaw;
+        self.goal_pitch = pitch.clamp(-limit, limit);
+    }
+
+    /// Current position
+    pub fn position(&self) -> Vec3 {
+        self.position
+    }
+
+    fn look(&mut self, dx: f32, dy: f32) {
+        self.set_angles(self.goal_yaw - dx * self.sensitivity, self.goal_pitch - dy * self.sensitivity);
+    }
+}
+
+impl CameraController for FlyController {
+    fn window_event(&mut self, event: &WindowEvent) -> bool {
+        if let Some([dx, dy]) = self.pointer.window_event(event) {
+            if self.pointer.right && !self.raw_motion {
+                self.look(dx as f32, dy as f32);
+                return true;
+            }
+            return false;
+        }
+
+        match event {
+            WindowEvent::KeyboardInput { event, .. } => {
+                let pressed = event.state == ElementState::Pressed;
+                let index = match event.physical_key {
+                    PhysicalKey::Code(KeyCode::KeyW) => 0,
+                    PhysicalKey::Code(KeyCode::KeyS) => 1,
+                    PhysicalKey::Code(KeyCode::KeyA) => 2,
+                    PhysicalKey::Code(KeyCode::KeyD) => 3,
+                    PhysicalKey::Code(KeyCode::KeyE | KeyCode::Space) => 4,
+                    PhysicalKey::Code(KeyCode::KeyQ | KeyCode::ControlLeft) => 5,
+                    _ => return false,
+                };
+                self.keys[index] = pressed;
+                true
+            },
+            WindowEvent::MouseWheel { delta, .. } => {
+                self.speed *= 1.2f32.powf(scroll_lines(delta));
+                true
+            },
+            WindowEvent::Focused(false) => {
+                self.keys = [false; 6];
+                false
+            },
+            WindowEvent::MouseInput { .. } => self.pointer.right,
+            _ => false,
+        }
+    }
+
+    fn device_event(&mut self, event: &DeviceEvent) -> bool {
+        if let DeviceEvent::MouseMotion { delta } = event {
+            self.raw_motion = true;
+            if self.pointer.right {
+                self.look(delta.0 as f32, delta.1 as f32);
+                return true;
+            }
+        }
+        false
+    }
+
+    fn update(&mut self, camera: &mut Camera, dt: f32) {
+        let forward_dir = forward(self.goal_yaw, self.goal_pitch);
+        let right = math::normalize(math::cross(forward_dir, UP));
+
+        let axis = |positive: bool, negative: bool| (positive as i32 - negative as i32) as f32;
+        let [f, b, l, r, u, d] = self.keys;
+
+        let direction = math::add(
+            math::add(math::scale(forward_dir, axis(f, b)), math::scale(right, axis(r, l))),
+            math::scale(UP, axis(u, d)),
+        );
+
+        let speed = if self.pointer.shift { self.speed * self.boost } else { self.speed };
+        let step = math::scale(math::normalize(direction), speed * dt);
+        self.goal_position = clamp3(math::add(self.goal_position, step), self.bounds);
+
+        let t = smoothing_factor(self.smoothing, dt);
+        self.position = lerp3(self.position, self.goal_position, t);
+        self.yaw = lerp(self.yaw, self.goal_yaw, t);
+        self.pitch = lerp(self.pitch, self.goal_pitch, t);
+
+        let target = math::add(self.position, forward(self.yaw, self.pitch));
+        camera.look_at(self.position, target, UP);
+    }
+
+    /// Back off along the current direction until the box is visible
+    fn fit(&mut self, camera: &Camera, min: Vec3, max: Vec3) {
+        let center = math::scale(math::add(min, max), 0.5);
+        let radius = (math::length(math::sub(max, min)) * 0.5).max(1e-3);
+        let distance = radius / (fov_y(camera) * 0.5).sin();
+
+        let position = math::sub(center, math::scale(forward(self.goal_yaw, self.goal_pitch), distance));
+        self.goal_position = clamp3(position, self.bounds);
+    }
+}
@@ -0,0 +1,147 @@
+use winit::event::{DeviceEvent, ElementState, MouseButton, MouseScrollDelta, WindowEvent};
+
+use super::{Camera, Projection};
+use crate::math::Vec3;
+
+mod orbit;
+pub use orbit::OrbitController;
+
+mod fly;
+pub use fly::FlyController;
+
+mod pan_zoom;
+pub use pan_zoom::PanZoomController;
+
+/// Interactive camera driven by winit events
+///
+/// Events are fed as they arrive, [`CameraController::update`] is called once
+/// per frame and writes the smoothed state into [`Camera`]
+///
+/// # Example
+/// ```ignore
+/// let mut orbit = OrbitController::new([0.0; 3], 5.0);
+///
+/// // in ApplicationHandler::window_event
+/// orbit.window_event(&event);
+///
+/// // before WorldRenderer::draw_frame
+/// orbit.update(&mut world.camera_mut(), dt);
+/// ```
+pub trait CameraController {
+    /// Consume a window event, returns `true` if it was used
+    fn window_event(&mut self, event: &WindowEvent) -> bool;
+
+    /// Consume a raw device event, returns `true` if it was used
+    fn device_event(&mut self, _event: &DeviceEvent) -> bool {
+        false
+    }
+
+    /// Advance smoothing by `dt` seconds and write the view into `camera`
+    fn update(&mut self, camera: &mut Camera, dt: f32);
+
+    /// Frame the box `min`..`max`, the camera moves there on next updates
+    fn fit(&mut self, camera: &Camera, min: Vec3, max: Vec3);
+}
+
+/// Mouse state shared by controllers
+#[derive(Default)]
+pub(crate) struct Pointer {
+    pub position: Option<[f64; 2]>,
+    pub left: bool,
+    pub right: bool,
+    pub middle: bool,
+    pub shift: bool,
+}
+
+impl Pointer {
+    /// Track buttons, modifiers and cursor, returns cursor movement
+    pub fn window_event(&mut self, event: &WindowEvent) -> Option<[f64; 2]> {
+        match event {
+            WindowEvent::CursorMoved { position, .. } => {
+                let new = [position.x, position.y];
+                let delta = self.position.map(|old| [new[0] - old[0], new[1] - old[1]]);
+                self.position = Some(new);
+                return delta;
+            },
+            WindowEvent::CursorLeft { .. } => {
+                self.position = None;
+            },
+            WindowEvent::MouseInput { state, button, .. } => {
+                let pressed = *state == ElementState::Pressed;
+                match button {
+                    MouseButton::Left => self.left = pressed,
+                    MouseButton::Right => self.right = pressed,
+                    MouseButton::Middle => self.middle = pressed,
+                    _ => {},
+                }
+            },
+            WindowEvent::ModifiersChanged(modifiers) => {
+                self.shift = modifiers.state().shift_key();
+            },
+            WindowEvent::Focused(false) => {
+                *self = Self {
+                    position: self.position,
+                    ..Self::default()
+                };
+            },
+            _ => {},
+        }
+        None
+    }
+
+    pub fn any_button(&self) -> bool {
+        self.left || self.right || self.middle
+    }
+}
+
+/// Wheel movement in lines, pixel deltas are converted with a typical line
+/// height
+pub(crate) fn scroll_lines(delta: &MouseScrollDelta) -> f32 {
+    match delta {
+        MouseScrollDelta::LineDelta(_, y) => *y,
+        MouseScrollDelta::PixelDelta(position) => position.y as f32 / 40.0,
+    }
+}
+
+/// Blend factor of exponential smoothing, `time` is the time constant in
+/// seconds and zero disables smoothing
+pub(crate) fn smoothing_factor(time: f32, dt: f32) -> f32 {
+    if time <= 0.0 {
+        1.0
+    } else {
+        1.0 - (-dt / time).exp()
+    }
+}
+
+pub(crate) fn lerp(a: f32, b: f32, t: f32) -> f32 {
+    a + (b - a) * t
+}
+
+pub(crate) fn lerp3(a: Vec3, b: Vec3, t: f32) -> Vec3 {
+    [lerp(a[0], b[0], t), lerp(a[1], b[1], t), lerp(a[2], b[2], t)]
+}
+
+pub(crate) fn clamp3(value: Vec3, bounds: Option<(Vec3, Vec3)>) -> Vec3 {
+    match bounds {
+        Some((min, max)) => [
+            value[0].clamp(min[0], max[0]),
+            value[1].clamp(min[1], max[1]),
+            value[2].clamp(min[2], max[2]),
+        ],
+        None => value,
+    }
+}
+
+/// Vertical field of view of a perspective camera, 45 degrees otherwise
+pub(crate) fn fov_y(camera: &Camera) -> f32 {
+    match camera.projection() {
+        Projection::Perspective { fov_y, .. } => fov_y,
+        _ => std::f32::consts::FRAC_PI_4,
+    }
+}
+
+/// Direction of a camera looking along `yaw` around Y and `pitch` above the
+/// horizon, yaw 0 looks down `-Z`
+pub(crate) fn forward(yaw: f32, pitch: f32) -> Vec3 {
+    [-yaw.sin() * pitch.cos(), pitch.sin(), -yaw.cos() * pitch.cos()]
+}
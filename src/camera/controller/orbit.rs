@@ -0,0 +1,250 @@
+use winit::event::WindowEvent;
+
+use super::{clamp3, fov_y, forward, lerp, lerp3, scroll_lines, smoothing_factor, CameraController, Pointer};
+use crate::camera::{Camera, Projection};
+use crate::math::{self, Vec3};
+
+const UP: Vec3 = [0.0, 1.0, 0.0];
+
+/// Orbit around a target point
+///
+/// - Left drag rotates
+/// - Right or middle drag, or Shift + left drag pans the target
+/// - Wheel zooms
+pub struct OrbitController {
+    target: Vec3,
+    distance: f32,
+    yaw: f32,
+    pitch: f32,
+    goal_target: Vec3,
+    goal_distance: f32,
+    goal_yaw: f32,
+    goal_pitch: f32,
+    rotate_speed: f32,
+    zoom_speed: f32,
+    smoothing: f32,
+    distance_limits: (f32, f32),
+    pitch_limits: (f32, f32),
+    bounds: Option<(Vec3, Vec3)>,
+    pointer: Pointer,
+    viewport_height: f32,
+    fov_y: f32,
+}
+
+impl OrbitController {
+    /// Look at `target` from `distance` along `-Z`
+    pub fn new(target: Vec3, distance: f32) -> Self {
+        let limit = 89f32.to_radians();
+        Self {
+            target,
+            distance,
+            yaw: 0.0,
+            pitch: 0.0,
+            goal_target: target,
+            goal_distance: distance,
+            goal_yaw: 0.0,
+            goal_pitch: 0.0,
+            rotate_speed: 0.005,
+            zoom_speed: 0.1,
+            smoothing: 0.0,
+            distance_limits: (1e-3, f32::INFINITY),
+            pitch_limits: (-limit, limit),
+            bounds: None,
+            pointer: Pointer::default(),
+            viewport_height: 720.0,
+            fov_y: std::f32::consts::FRAC_PI_4,
+        }
+    }
+
+    /// Radians per dragged pixel
+    pub fn rotate_speed(mut self, value: f32) -> Self {
+        self.rotate_speed = value;
+        self
+    }
+
+    /// Fraction of the distance per wheel line
+    pub fn zoom_speed(mut self, value: f32) -> Self {
+        self.zoom_speed = value;
+        self
+    }
+
+    /// Time constant in seconds, zero moves immediately
+    pub fn smoothing(mut self, seconds: f32) -> Self {
+        self.smoothing = seconds;
+        self
+    }
+
+    /// Closest and farthest distance to the target
+    pub fn distance_limits(mut self, min: f32, max: f32) -> Self {
+        self.distance_limits = (min, max);
+        self.goal_distance = self.goal_distance.clamp(min, max);
+        self
+    }
+
+    /// Lowest and highest elevation in radians, within ±90 degrees
+    pub fn pitch_limits(mut self, min: f32, max: f32) -> Self {
+        self.pitch_limits = (min, max);
+        self.goal_pitch = self.goal_pitch.clamp(min, max);
+        self
+    }
+
+    /// Box the target can be panned within
+    pub fn bounds(mut self, min: Vec3, max: Vec3) -> Self {
+        self.bounds = Some((min, max));
+        self.goal_target = clamp3(self.goal_target, self.bounds);
+        self
+    }
+
+    /// Set yaw around Y and pitch above the horizon in radians
+    pub fn set_angles(&mut self, yaw: f32, pitch: f32) {
+        self.goal_yaw = yaw;
+        self.goal_pitch = pitch.clamp(self.pitch_limits.0, self.pitch_limits.1);
+    }
+
+    /// Point the camera orbits around
+    pub fn target(&self) -> Vec3 {
+        self.target
+    }
+
+    /// Current distance to the target
+    pub fn distance(&self) -> f32 {
+        self.distance
+    }
+
+    fn rotate(&mut self, dx: f32, dy: f32) {
+        self.goal_yaw -= dx * self.rotate_speed;
+        self.goal_pitch = (self.goal_pitch - dy * self.rotate_speed).clamp(self.pitch_limits.0, self.pitch_limits.1);
+    }
+
+    fn pan(&mut self, dx: f32, dy: f32) {
+        let forward = forward(self.goal_yaw, self.goal_pitch);
+        let right = math::normalize(math::cross(forward, UP));
+        let up = math::cross(right, forward);
+
+        // One pixel covers this much world space at the target
+        let scale = 2.0 * self.goal_distance * (self.fov_y * 0.5).tan() / self.viewport_height;
+
+        let offset = math::sub(math::scale(up, dy * scale), math::scale(right, dx * scale));
+        self.goal_target = clamp3(math::add(self.goal_target, offset), self.bounds);
+    }
+
+    fn zoom(&mut self, lines: f32) {
+        let (min, max) = self.distance_limits;
+        self.goal_distance = (self.goal_distance * (1.0 - self.zoom_speed).powf(lines)).clamp(min, max);
+    }
+}
+
+impl CameraController for OrbitController {
+    fn window_event(&mut self, event: &WindowEvent) -> bool {
+        if let Some([dx, dy]) = self.pointer.window_event(event) {
+            let (dx, dy) = (dx as f32, dy as f32);
+            if self.pointer.right || self.pointer.middle || (self.pointer.left && self.pointer.shift) {
+                self.pan(dx, dy);
+                return true;
+            }
+            if self.pointer.left {
+                self.rotate(dx, dy);
+                return true;
+            }
+            return false;
+        }
+
+        match event {
+            WindowEvent::MouseWheel { delta, .. } => {
+                self.zoom(scroll_lines(delta));
+                true
+            },
+            WindowEvent::Resized(size) => {
+                self.viewport_height = size.height.max(1) as f32;
+                false
+            },
+            WindowEvent::MouseInput { .. } => self.pointer.any_button(),
+            _ => false,
+        }
+    }
+
+    fn update(&mut self, camera: &mut Camera, dt: f32) {
+        let t = smoothing_factor(self.smoothing, dt);
+
+        self.target = lerp3(self.target, self.goal_target, t);
+        self.distance = lerp(self.distance, self.goal_distance, t);
+        self.yaw = lerp(self.yaw, self.goal_yaw, t);
+        self.pitch = lerp(self.pitch, self.goal_pitch, t);
+        self.fov_y = fov_y(camera);
+
+        let eye = math::sub(self.target, math::scale(forward(self.yaw, self.pitch), self.distance));
+        camera.look_at(eye, self.target, UP);
+    }
+
+    /// The whole box stays visible from the current direction, the camera
+    /// should use a perspective projection
+    fn fit(&mut self, camera: &Camera, min: Vec3, max: Vec3) {
+        let center = math::scale(math::add(min, max), 0.5);
+        let radius = (math::length(math::sub(max, min)) * 0.5).max(1e-3);
+
+        let fov_y = fov_y(camera);
+        let aspect = match camera.projection() {
+            Projection::Perspective { aspect, .. } => aspect,
+            _ => 1.0,
+        };
+        let fov_x = 2.0 * ((fov_y * 0.5).tan() * aspect).atan();
+        let fov = fov_y.min(fov_x);
+
+        let (min_distance, max_distance) = self.distance_limits;
+        self.goal_target = clamp3(center, self.bounds);
+        self.goal_distance = (radius / (fov * 0.5).sin()).clamp(min_distance, max_distance);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn close(a: Vec3, b: Vec3) -> bool {
+        (0..3).all(|i| (a[i] - b[i]).abs() < 1e-5)
+    }
+
+    #[test]
+    fn rotation_is_clamped_in_pitch() {
+        let mut orbit = OrbitController::new([0.0; 3], 5.0).rotate_speed(0.01);
+
+        orbit.rotate(10.0, -20.0);
+        assert!((orbit.goal_yaw + 0.1).abs() < 1e-6);
+        assert!((orbit.goal_pitch - 0.2).abs() < 1e-6);
+
+        orbit.rotate(0.0, -1e4);
+        assert_eq!(orbit.goal_pitch, 89f32.to_radians());
+        assert!(close(forward(0.0, 0.0), [0.0, 0.0, -1.0]));
+        assert!(close(forward(std::f32::consts::FRAC_PI_2, 0.0), [-1.0, 0.0, 0.0]));
+    }
+
+    #[test]
+    fn pan_follows_the_cursor_at_the_target() {
+        let mut orbit = OrbitController::new([0.0; 3], 5.0);
+        // Dragging over the viewport height moves the visible height
+        let visible = 2.0 * 5.0 * (std::f32::consts::FRAC_PI_8).tan();
+
+        orbit.pan(0.0, 720.0);
+        assert!(close(orbit.goal_target, [0.0, visible, 0.0]));
+
+        orbit.pan(360.0, 0.0);
+        assert!(close(orbit.goal_target, [-visible / 2.0, visible, 0.0]));
+
+        let mut bounded = OrbitController::new([0.0; 3], 5.0).bounds([-1.0; 3], [1.0; 3]);
+        bounded.pan(-1e4, 1e4);
+        assert!(close(bounded.goal_target, [1.0, 1.0, 0.0]));
+    }
+
+    #[test]
+    fn zoom_scales_distance_within_limits() {
+        let mut orbit = OrbitController::new([0.0; 3], 5.0).distance_limits(1.0, 6.0);
+
+        orbit.zoom(2.0);
+        assert!((orbit.goal_distance - 5.0 * 0.81).abs() < 1e-5);
+
+        orbit.zoom(-10.0);
+        assert_eq!(orbit.goal_distance, 6.0);
+        orbit.zoom(100.0);
+        assert_eq!(orbit.goal_distance, 1.0);
+    }
+}
@@ -0,0 +1,224 @@
+use winit::event::WindowEvent;
+
+use super::{lerp, scroll_lines, smoothing_factor, CameraController, Pointer};
+use crate::camera::Camera;
+use crate::math::{self, Vec3};
+
+/// 2D view of the XY plane with an orthographic projection
+///
+/// - Left, right or middle drag pans
+/// - Wheel zooms around the cursor
+pub struct PanZoomController {
+    center: [f32; 2],
+    half_height: f32,
+    goal_center: [f32; 2],
+    goal_half_height: f32,
+    zoom_speed: f32,
+    smoothing: f32,
+    zoom_limits: (f32, f32),
+    bounds: Option<([f32; 2], [f32; 2])>,
+    margin: f32,
+    pointer: Pointer,
+    viewport: [f32; 2],
+}
+
+impl PanZoomController {
+    /// Show `center` with `half_height` world units above and below it
+    pub fn new(center: [f32; 2], half_height: f32) -> Self {
+        Self {
+            center,
+            half_height,
+            goal_center: center,
+            goal_half_height: half_height,
+            zoom_speed: 0.1,
+            smoothing: 0.0,
+            zoom_limits: (1e-4, f32::INFINITY),
+            bounds: None,
+            margin: 0.05,
+            pointer: Pointer::default(),
+            viewport: [1280.0, 720.0],
+        }
+    }
+
+    /// Fraction of the visible height per wheel line
+    pub fn zoom_speed(mut self, value: f32) -> Self {
+        self.zoom_speed = value;
+        self
+    }
+
+    /// Time constant in seconds, zero moves immediately
+    pub fn smoothing(mut self, seconds: f32) -> Self {
+        self.smoothing = seconds;
+        self
+    }
+
+    /// Smallest and largest visible half height
+    pub fn zoom_limits(mut self, min: f32, max: f32) -> Self {
+        self.zoom_limits = (min, max);
+        self.goal_half_height = self.goal_half_height.clamp(min, max);
+        self
+    }
+
+    /// Rectangle the view center cannot leave
+    pub fn bounds(mut self, min: [f32; 2], max: [f32; 2]) -> Self {
+        self.bounds = Some((min, max));
+        self.goal_center = self.clamp(self.goal_center);
+        self
+    }
+
+    /// Free space around boxes framed by [`CameraController::fit`], as a
+    /// fraction of their size
+    pub fn margin(mut self, value: f32) -> Self {
+        self.margin = value;
+        self
+    }
+
+    /// Current view center
+    pub fn center(&self) -> [f32; 2] {
+        self.center
+    }
+
+    /// Current visible half height
+    pub fn half_height(&self) -> f32 {
+        self.half_height
+    }
+
+    fn aspect(&self) -> f32 {
+        self.viewport[0] / self.viewport[1].max(1.0)
+    }
+
+    fn clamp(&self, center: [f32; 2]) -> [f32; 2] {
+        match self.bounds {
+            Some((min, max)) => [center[0].clamp(min[0], max[0]), center[1].clamp(min[1], max[1])],
+            None => center,
+        }
+    }
+
+    fn pan(&mut self, dx: f32, dy: f32) {
+        let units_per_pixel = 2.0 * self.goal_half_height / self.viewport[1].max(1.0);
+        self.goal_center = self.clamp([
+            self.goal_center[0] - dx * units_per_pixel,
+            self.goal_center[1] + dy * units_per_pixel,
+        ]);
+    }
+
+    /// Keep the point under the cursor in place
+    fn zoom(&mut self, lines: f32) {
+        let (min, max) = self.zoom_limits;
+        let half_height = (self.goal_half_height * (1.0 - self.zoom_speed).powf(lines)).clamp(min, max);
+
+        let [x, y] = match self.pointer.position {
+            Some([px, py]) => [
+                px as f32 / self.viewport[0].max(1.0) * 2.0 - 1.0,
+                1.0 - py as f32 / self.viewport[1].max(1.0) * 2.0,
+            ],
+            None => [0.0, 0.0],
+        };
+
+        let aspect = self.aspect();
+        let shrink = self.goal_half_height - half_height;
+        self.goal_center = self.clamp([
+            self.goal_center[0] + x * shrink * aspect,
+            self.goal_center[1] + y * shrink,
+        ]);
+        self.goal_half_height = half_height;
+    }
+}
+
+impl CameraController for PanZoomController {
+    fn window_event(&mut self, event: &WindowEvent) -> bool {
+        if let Some([dx, dy]) = self.pointer.window_event(event) {
+            if self.pointer.any_button() {
+                self.pan(dx as f32, dy as f32);
+                return true;
+            }
+            return false;
+        }
+
+        match event {
+            WindowEvent::MouseWheel { delta, .. } => {
+                self.zoom(scroll_lines(delta));
+                true
+            },
+            WindowEvent::Resized(size) => {
+                self.viewport = [size.width as f32, size.height as f32];
+                false
+            },
+            _ => false,
+        }
+    }
+
+    fn update(&mut self, camera: &mut Camera, dt: f32) {
+        let t = smoothing_factor(self.smoothing, dt);
+        self.center = [
+            lerp(self.center[0], self.goal_center[0], t),
+            lerp(self.center[1], self.goal_center[1], t),
+        ];
+        self.half_height = lerp(self.half_height, self.goal_half_height, t);
+
+        let half_width = self.half_height * self.aspect();
+        let [x, y] = self.center;
+
+        camera.set_view(math::IDENTITY).orthographic(
+            x - half_width,
+            x + half_width,
+            y - self.half_height,
+            y + self.half_height,
+            -1.0,
+            1.0,
+        );
+    }
+
+    /// Frame the XY extent of the box, Z is ignored
+    fn fit(&mut self, _camera: &Camera, min: Vec3, max: Vec3) {
+        let half_width = (max[0] - min[0]) * 0.5;
+        let half_height = (max[1] - min[1]) * 0.5;
+        let (low, high) = self.zoom_limits;
+
+        self.goal_center = self.clamp([(min[0] + max[0]) * 0.5, (min[1] + max[1]) * 0.5]);
+        self.goal_half_height = (half_height.max(half_width / self.aspect()) * (1.0 + self.margin)).clamp(low, high);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn pan_moves_by_dragged_pixels() {
+        // 720 pixels show 2 units
+        let mut view = PanZoomController::new([0.0, 0.0], 1.0);
+
+        view.pan(360.0, -720.0);
+        assert_eq!(view.goal_center, [-1.0, -2.0]);
+
+        let mut bounded = PanZoomController::new([0.0, 0.0], 1.0).bounds([-0.5, -0.5], [0.5, 0.5]);
+        bounded.pan(1e4, 0.0);
+        assert_eq!(bounded.goal_center, [-0.5, 0.0]);
+    }
+
+    #[test]
+    fn zoom_keeps_the_point_under_the_cursor() {
+        let mut view = PanZoomController::new([1.0, 2.0], 1.0);
+        let aspect = view.aspect();
+        let right_edge = |view: &PanZoomController| view.goal_center[0] + view.goal_half_height * aspect;
+
+        view.pointer.position = Some([1280.0, 360.0]);
+        let before = right_edge(&view);
+        view.zoom(3.0);
+
+        assert!((view.goal_half_height - 0.729).abs() < 1e-5);
+        assert!((right_edge(&view) - before).abs() < 1e-5);
+        assert!((view.goal_center[1] - 2.0).abs() < 1e-6);
+    }
+
+    #[test]
+    fn zoom_without_cursor_keeps_the_center() {
+        let mut view = PanZoomController::new([1.0, 2.0], 1.0).zoom_limits(0.5, 2.0);
+
+        view.zoom(-20.0);
+        assert_eq!((view.goal_center, view.goal_half_height), ([1.0, 2.0], 2.0));
+        view.zoom(20.0);
+        assert_eq!(view.goal_half_height, 0.5);
+    }
+}
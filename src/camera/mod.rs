@@ -2,58 +2,257 @@ use ash::vk;
 use bytemuck::{Pod, Zeroable};
 
 use crate::core::{Device, VulkanResult};
+use crate::math::{self, Frustum, Mat4, Vec3};
 use crate::per_frame::{PerFrameBuffer, PerFrameBufferBuilder};
 
+mod controller;
+pub use controller::{CameraController, FlyController, OrbitController, PanZoomController};
+
+/// Camera matrices as seen by shaders at set 1, binding 0
 #[repr(C)]
 #[derive(Clone, Copy, Pod, Zeroable)]
 pub struct CameraData {
-    view: [[f32; 4]; 4],
-    proj: [[f32; 4]; 4],
-    view_proj: [[f32; 4]; 4],
-    inv_view: [[f32; 4]; 4],
-    inv_proj: [[f32; 4]; 4],
-    inv_view_proj: [[f32; 4]; 4],
+    view: Mat4,
+    proj: Mat4,
+    view_proj: Mat4,
+    inv_view: Mat4,
+    inv_proj: Mat4,
+    inv_view_proj: Mat4,
 }
 
 impl CameraData {
     pub fn identity() -> Self {
         Self {
-            view: [[1.0, 0.0, 0.0, 0.0], [0.0, 1.0, 0.0, 0.0], [0.0, 0.0, 1.0, 0.0], [0.0, 0.0, 0.0, 1.0]],
-            proj: [[1.0, 0.0, 0.0, 0.0], [0.0, 1.0, 0.0, 0.0], [0.0, 0.0, 1.0, 0.0], [0.0, 0.0, 0.0, 1.0]],
-            view_proj: [[1.0, 0.0, 0.0, 0.0], [0.0, 1.0, 0.0, 0.0], [0.0, 0.0, 1.0, 0.0], [0.0, 0.0, 0.0, 1.0]],
-            inv_view: [[1.0, 0.0, 0.0, 0.0], [0.0, 1.0, 0.0, 0.0], [0.0, 0.0, 1.0, 0.0], [0.0, 0.0, 0.0, 1.0]],
-            inv_proj: [[1.0, 0.0, 0.0, 0.0], [0.0, 1.0, 0.0, 0.0], [0.0, 0.0, 1.0, 0.0], [0.0, 0.0, 0.0, 1.0]],
-            inv_view_proj: [[1.0, 0.0, 0.0, 0.0], [0.0, 1.0, 0.0, 0.0], [0.0, 0.0, 1.0, 0.0], [0.0, 0.0, 0.0, 1.0]],
+            view: math::IDENTITY,
+            proj: math::IDENTITY,
+            view_proj: math::IDENTITY,
+            inv_view: math::IDENTITY,
+            inv_proj: math::IDENTITY,
+            inv_view_proj: math::IDENTITY,
         }
     }
 }
 
+/// How the projection matrix was built, kept to follow window resizes
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Projection {
+    /// Set directly with [`Camera::proj_mut`], never changed on resize
+    Custom,
+    /// See [`Camera::perspective`]
+    Perspective {
+        /// Vertical field of view in radians
+        fov_y: f32,
+        /// Width / height
+        aspect: f32,
+        /// Distance to the near plane
+        near: f32,
+        /// Distance to the far plane, may be infinite
+        far: f32,
+    },
+    /// See [`Camera::orthographic`]
+    Orthographic {
+        /// Left plane
+        left: f32,
+        /// Right plane
+        right: f32,
+        /// Bottom plane
+        bottom: f32,
+        /// Top plane
+        top: f32,
+        /// Distance to the near plane
+        near: f32,
+        /// Distance to the far plane
+        far: f32,
+    },
+}
+
+/// Camera of the rendered world
+///
+/// Projections use reversed-Z (near plane at depth 1, far at depth 0) and
+/// Vulkan clip space. Derived matrices are recomputed on change, raw changes
+/// through [`Camera::view_mut`] and [`Camera::proj_mut`] are picked up at the
+/// next frame. Data is uploaded every frame into the per-frame uniform buffer
+/// bound at set 1, binding 0
 pub struct Camera {
     is_dirty: bool,
     buffer: PerFrameBuffer,
     data: CameraData,
+    projection: Projection,
 }
 
 impl Camera {
-    pub fn proj(&mut self) -> &[[f32; 4]; 4] {
+    /// Projection matrix
+    pub fn proj(&self) -> &Mat4 {
         &self.data.proj
     }
 
-    pub fn view(&mut self) -> &[[f32; 4]; 4] {
+    /// World to view matrix
+    pub fn view(&self) -> &Mat4 {
         &self.data.view
     }
 
-    pub fn view_mut(&mut self) -> &mut [[f32; 4]; 4] {
+    /// `proj * view`
+    pub fn view_proj(&self) -> &Mat4 {
+        &self.data.view_proj
+    }
+
+    /// World space planes of the visible volume, includes raw changes not
+    /// uploaded yet
+    pub fn frustum(&self) -> Frustum {
+        Frustum::from_view_proj(&math::mul(&self.data.proj, &self.data.view))
+    }
+
+    /// View to world matrix
+    pub fn inv_view(&self) -> &Mat4 {
+        &self.data.inv_view
+    }
+
+    /// Clip to view matrix
+    pub fn inv_proj(&self) -> &Mat4 {
+        &self.data.inv_proj
+    }
+
+    /// Clip to world matrix
+    pub fn inv_view_proj(&self) -> &Mat4 {
+        &self.data.inv_view_proj
+    }
+
+    /// Parameters of the current projection
+    pub fn projection(&self) -> Projection {
+        self.projection
+    }
+
+    /// Position of the camera in world space
+    pub fn position(&self) -> Vec3 {
+        let m = &self.data.inv_view;
+        [m[3][0], m[3][1], m[3][2]]
+    }
+
+    /// Raw view matrix
+    pub fn view_mut(&mut self) -> &mut Mat4 {
         self.is_dirty = true;
         &mut self.data.view
     }
 
-    pub fn proj_mut(&mut self) -> &mut [[f32; 4]; 4] {
+    /// Raw projection, it is no longer updated on resize
+    pub fn proj_mut(&mut self) -> &mut Mat4 {
         self.is_dirty = true;
+        self.projection = Projection::Custom;
         &mut self.data.proj
     }
 
-    pub fn new(device: &Device, frame_count: usize) -> VulkanResult<Self> {
+    /// Reversed-Z perspective projection, `far` may be [`f32::INFINITY`]
+    ///
+    /// # Example
+    /// ```ignore
+    /// world
+    ///     .camera_mut()
+    ///     .perspective(60f32.to_radians(), 16.0 / 9.0, 0.1, f32::INFINITY)
+    ///     .look_at([0.0, 2.0, 5.0], [0.0, 0.0, 0.0], [0.0, 1.0, 0.0]);
+    /// ```
+    pub fn perspective(&mut self, fov_y: f32, aspect: f32, near: f32, far: f32) -> &mut Self {
+        self.set_projection(Projection::Perspective { fov_y, aspect, near, far })
+    }
+
+    /// Reversed-Z orthographic projection
+    pub fn orthographic(&mut self, left: f32, right: f32, bottom: f32, top: f32, near: f32, far: f32) -> &mut Self {
+        self.set_projection(Projection::Orthographic {
+            left,
+            right,
+            bottom,
+            top,
+            near,
+            far,
+        })
+    }
+
+    /// Place the camera at `eye` looking at `target`
+    pub fn look_at(&mut self, eye: Vec3, target: Vec3, up: Vec3) -> &mut Self {
+        self.set_view(math::look_at(eye, target, up))
+    }
+
+    /// Replace the view matrix
+    pub fn set_view(&mut self, view: Mat4) -> &mut Self {
+        self.data.view = view;
+        self.recompute();
+        self
+    }
+
+    /// Replace the projection, see [`Camera::perspective`] and
+    /// [`Camera::orthographic`]
+    pub fn set_projection(&mut self, projection: Projection) -> &mut Self {
+        self.projection = projection;
+        self.data.proj = match projection {
+            Projection::Custom => self.data.proj,
+            Projection::Perspective { fov_y, aspect, near, far } => math::perspective(fov_y, aspect, near, far),
+            Projection::Orthographic {
+                left,
+                right,
+                bottom,
+                top,
+                near,
+                far,
+            } => math::orthographic(left, right, bottom, top, near, far),
+        };
+        self.recompute();
+        self
+    }
+
+    /// Follow a new width / height ratio
+    ///
+    /// Orthographic projections keep their height and center
+    pub fn set_aspect(&mut self, aspect: f32) {
+        let projection = match self.projection {
+            Projection::Custom => return,
+            Projection::Perspective { fov_y, near, far, .. } => Projection::Perspective { fov_y, aspect, near, far },
+            Projection::Orthographic {
+                left,
+                right,
+                bottom,
+                top,
+                near,
+                far,
+            } => {
+                let center = (left + right) * 0.5;
+                let half_width = (top - bottom) * 0.5 * aspect;
+                Projection::Orthographic {
+                    left: center - half_width,
+                    right: center + half_width,
+                    bottom,
+                    top,
+                    near,
+                    far,
+                }
+            },
+        };
+        self.set_projection(projection);
+    }
+
+    /// Recompute derived matrices if needed and upload them for `frame`
+    pub(crate) fn update(&mut self, frame: usize) -> VulkanResult<()> {
+        profiling::scope!("Camera::update");
+
+        // Raw matrices may have been changed through `view_mut`/`proj_mut`
+        if self.is_dirty {
+            self.recompute();
+        }
+
+        self.buffer.get_mut(frame as u32).upload_data(&[self.data])
+    }
+
+    fn recompute(&mut self) {
+        self.data.view_proj = math::mul(&self.data.proj, &self.data.view);
+        self.data.inv_view = math::inverse(&self.data.view);
+        self.data.inv_proj = math::inverse(&self.data.proj);
+        self.data.inv_view_proj = math::inverse(&self.data.view_proj);
+        self.is_dirty = false;
+    }
+
+    pub(crate) fn buffer(&self) -> &PerFrameBuffer {
+        &self.buffer
+    }
+
+    pub(crate) fn new(device: &Device, frame_count: usize) -> VulkanResult<Self> {
         let size = size_of::<CameraData>() as u64;
 
         let mut buffer = PerFrameBufferBuilder::new(device)
@@ -73,10 +272,11 @@ impl Camera {
             is_dirty: false,
             buffer,
             data,
+            projection: Projection::Custom,
         })
     }
 
-    pub fn destroy(&mut self, device: &Device) {
+    pub(crate) fn destroy(&mut self, device: &Device) {
         self.buffer.destroy(device);
     }
 }
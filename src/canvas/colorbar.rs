@@ -0,0 +1,263 @@
+//! Legend of a colormap drawn on the canvas, with ticks at round values
+
+use super::{Canvas, Paint, Path, Stroke};
+use crate::capture::srgb_to_linear;
+use crate::resources::{Colormap, ScalarRange};
+
+/// Gradients the bar is split into, each one blends two samples of the
+/// colormap
+const SEGMENTS: usize = 32;
+
+/// Length of tick marks in pixels, labels start at their end
+const TICK_LENGTH: f32 = 4.0;
+
+/// Bar showing the colors of a [`Colormap`] over a [`ScalarRange`], drawn
+/// by [`Canvas::colorbar`]
+///
+/// The canvas has no text, labels of [`Colorbar::ticks`] are drawn by the
+/// caller at the end of each tick mark
+///
+/// # Example
+/// ```ignore
+/// let range = ScalarRange::new(0.1, 1000.0).log(true);
+/// let colorbar = Colorbar::new(Colormap::Magma, range).rect([20.0, 20.0], [16.0, 240.0]).vertical(true);
+///
+/// world.canvas().colorbar(&colorbar);
+/// let labels = colorbar
+///     .ticks()
+///     .iter()
+///     .map(|tick| world.create::<Text>(TextDesc::new(&font, &tick.label).anchor(TextAnchor::Screen(tick.pos))))
+///     .collect::<Result<Vec<_>, _>>()?;
+/// ```
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Colorbar {
+    colormap: Colormap,
+    range: ScalarRange,
+    pos: [f32; 2],
+    size: [f32; 2],
+    vertical: bool,
+    tick_count: usize,
+    color: [f32; 4],
+}
+
+/// Round value marked along a [`Colorbar`]
+#[derive(Clone, Debug, PartialEq)]
+pub struct ColorbarTick {
+    /// Scalar at the tick
+    pub value: f32,
+    /// End of the tick mark outside the bar, in the coordinates of the bar
+    pub pos: [f32; 2],
+    /// `value` with as many decimals as the tick spacing needs
+    pub label: String,
+}
+
+impl Colorbar {
+    /// Horizontal bar of 200x16 pixels at the origin with about 5 white
+    /// ticks
+    pub fn new(colormap: Colormap, range: ScalarRange) -> Self {
+        Self {
+            colormap,
+            range,
+            pos: [0.0; 2],
+            size: [200.0, 16.0],
+            vertical: false,
+            tick_count: 5,
+            color: [1.0; 4],
+        }
+    }
+
+    /// Place the bar from its top left corner, before the transform of the
+    /// canvas
+    pub fn rect(mut self, pos: [f32; 2], size: [f32; 2]) -> Self {
+        self.pos = pos;
+        self.size = size;
+        self
+    }
+
+    /// Put the start of the colormap at the bottom instead of the left
+    pub fn vertical(mut self, vertical: bool) -> Self {
+        self.vertical = vertical;
+        self
+    }
+
+    /// Roughly how many ticks to mark, 0 for none
+    pub fn tick_count(mut self, count: usize) -> Self {
+        self.tick_count = count;
+        self
+    }
+
+    /// Linear color of the outline and tick marks
+    pub fn color(mut self, color: [f32; 4]) -> Self {
+        self.color = color;
+        self
+    }
+
+    /// Ticks at round values inside the range, multiples of 1, 2 or 5 times
+    /// a power of ten, or powers of ten on a logarithmic scale
+    pub fn ticks(&self) -> Vec<ColorbarTick> {
+        let (lo, hi) = (self.range.min().min(self.range.max()), self.range.min().max(self.range.max()));
+        if self.tick_count == 0 || !lo.is_finite() || !hi.is_finite() || hi <= lo {
+            return vec![];
+        }
+
+        let ticks: Vec<(f32, String)> = match self.range.is_log() {
+            true if lo > 0.0 => {
+                let (first, last) = (lo.log10().ceil() as i32, hi.log10().floor() as i32);
+                let decades = (last - first + 1).max(0) as usize;
+                let stride = decades.div_ceil(self.tick_count).max(1);
+
+                (first..=last)
+                    .step_by(stride)
+                    .map(|exponent| {
+                        let label = match exponent {
+                            -3..=3 => format!("{:.*}", (-exponent).max(0) as usize, 10f32.powi(exponent)),
+                            _ => format!("1e{exponent}"),
+                        };
+                        (10f32.powi(exponent), label)
+                    })
+                    .collect()
+            },
+            true => vec![],
+            false => {
+                let step = nice_step((hi - lo) / self.tick_count.saturating_sub(1).max(1) as f32);
+                let decimals = (-step.log10().floor()).max(0.0) as usize;
+                let (first, last) = ((lo / step - 1e-4).ceil() as i64, (hi / step + 1e-4).floor() as i64);
+
+                (first..=last)
+                    .map(|k| {
+                        let value = k as f32 * step;
+                        // No "-0.0" for the tick at zero
+                        let value = if k == 0 { 0.0 } else { value };
+                        (value, format!("{value:.decimals$}"))
+                    })
+                    .collect()
+            },
+        };
+
+        let range = self.range.clamp(true);
+        let [x, y] = self.pos;
+        let [w, h] = self.size;
+
+        ticks
+            .into_iter()
+            .map(|(value, label)| {
+                let t = range.coord(value).unwrap_or(0.0);
+                let pos = match self.vertical {
+                    true => [x + w + TICK_LENGTH, y + (1.0 - t) * h],
+                    false => [x + t * w, y + h + TICK_LENGTH],
+                };
+                ColorbarTick { value, pos, label }
+            })
+            .collect()
+    }
+
+    /// Add the bar, its outline and tick marks to `canvas`
+    pub(super) fn draw(&self, canvas: &mut Canvas) {
+        let [x, y] = self.pos;
+        let [w, h] = self.size;
+        let length = if self.vertical { h } else { w };
+
+        let color = |t: f32| {
+            let [r, g, b, _] = self.colormap.sample(t).map(|c| srgb_to_linear(f32::from(c) / 255.0));
+            [r, g, b, 1.0]
+        };
+
+        for i in 0..SEGMENTS {
+            let (t0, t1) = (i as f32 / SEGMENTS as f32, (i + 1) as f32 / SEGMENTS as f32);
+            let (start, end) = (t0 * length, t1 * length);
+            // Reaching a pixel into the next segment hides the seam of the
+            // antialiased edges, the next one is drawn over it
+            let overlap = if i + 1 < SEGMENTS { 1.0 } else { 0.0 };
+
+            let (path, from, to) = match self.vertical {
+                true => (
+                    Path::rect([x, y + h - end - overlap], [w, end - start + overlap]),
+                    [x, y + h - start],
+                    [x, y + h - end],
+                ),
+                false => (Path::rect([x + start, y], [end - start + overlap, h]), [x + start, y], [x + end, y]),
+            };
+
+            canvas.fill(&path, Paint::LinearGradient {
+                start: from,
+                end: to,
+                start_color: color(t0),
+                end_color: color(t1),
+            });
+        }
+
+        let stroke = Stroke::new(1.0);
+        canvas.stroke(&Path::rect(self.pos, self.size), &stroke, self.color);
+
+        for tick in self.ticks() {
+            let [tx, ty] = tick.pos;
+            let base = match self.vertical {
+                true => [tx - TICK_LENGTH, ty],
+                false => [tx, ty - TICK_LENGTH],
+            };
+            canvas.stroke(&Path::new().move_to(base).line_to(tick.pos), &stroke, self.color);
+        }
+    }
+}
+
+/// 1, 2 or 5 times a power of ten closest to `raw`
+fn nice_step(raw: f32) -> f32 {
+    let magnitude = 10f32.powf(raw.log10().floor());
+
+    [1.0, 2.0, 5.0, 10.0]
+        .map(|m| m * magnitude)
+        .into_iter()
+        .min_by(|a, b| (a / raw).ln().abs().total_cmp(&(b / raw).ln().abs()))
+        .unwrap()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn linear_ticks_are_round() {
+        let colorbar = Colorbar::new(Colormap::Viridis, ScalarRange::new(-0.3, 1.0))
+            .rect([10.0, 0.0], [130.0, 10.0])
+            .tick_count(7);
+        let ticks = colorbar.ticks();
+        let labels: Vec<_> = ticks.iter().map(|tick| tick.label.as_str()).collect();
+
+        assert_eq!(labels, ["-0.2", "0.0", "0.2", "0.4", "0.6", "0.8", "1.0"]);
+        // 0 is 0.3 of 1.3 along 130 pixels
+        assert!((ticks[1].pos[0] - 40.0).abs() < 1e-3);
+        assert_eq!(ticks[1].pos[1], 10.0 + TICK_LENGTH);
+
+        assert!(colorbar.tick_count(0).ticks().is_empty());
+        assert!(Colorbar::new(Colormap::Viridis, ScalarRange::new(1.0, 1.0)).ticks().is_empty());
+    }
+
+    #[test]
+    fn log_ticks_are_decades() {
+        let range = ScalarRange::new(0.05, 2000.0).log(true);
+        let colorbar = Colorbar::new(Colormap::Magma, range).rect([0.0, 0.0], [10.0, 100.0]).vertical(true);
+        let ticks = colorbar.ticks();
+        let labels: Vec<_> = ticks.iter().map(|tick| tick.label.as_str()).collect();
+
+        assert_eq!(labels, ["0.1", "1", "10", "100", "1000"]);
+        // Values grow upwards
+        assert!(ticks.windows(2).all(|pair| pair[1].pos[1] < pair[0].pos[1]));
+        assert_eq!(ticks[0].pos[0], 10.0 + TICK_LENGTH);
+
+        let wide = Colorbar::new(Colormap::Magma, ScalarRange::new(1e-6, 1e6).log(true)).tick_count(4);
+        let labels: Vec<_> = wide.ticks().into_iter().map(|tick| tick.label).collect();
+        assert_eq!(labels, ["1e-6", "0.01", "100", "1e6"]);
+    }
+
+    #[test]
+    fn bars_cover_their_rect() {
+        let mut canvas = Canvas::default();
+        canvas.colorbar(&Colorbar::new(Colormap::Turbo, ScalarRange::new(0.0, 1.0)).tick_count(0));
+
+        // Only antialiased edges and the outline reach past 200x16
+        assert!(canvas.vertex_count() > 0);
+        for vertex in &canvas.vertices {
+            assert!((-1.5..=201.5).contains(&vertex.pos[0]) && (-1.5..=17.5).contains(&vertex.pos[1]));
+        }
+    }
+}
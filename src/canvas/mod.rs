@@ -0,0 +1,571 @@
+//! 2D shapes in pixels accumulated during a frame and drawn by
+//! [`CanvasPass`](crate::CanvasPass)
+
+use ash::vk;
+use bytemuck::{Pod, Zeroable};
+
+use crate::core::{
+    read_shader_from_bytes, Device, GraphicsPipeline, GraphicsPipelineBuilder, PipelineLayout, PipelineLayoutBuilder, ShaderBuilder, VulkanResult,
+};
+use crate::resources::{LineCap, LineJoin, MeshBuffer};
+
+mod colorbar;
+mod path;
+mod tessellate;
+
+pub use colorbar::{Colorbar, ColorbarTick};
+pub use path::Path;
+use tessellate::{StrokeStyle, TessVertex};
+
+const VERTEX_SHADER: &[u8] = include_bytes!(concat!(env!("CARGO_MANIFEST_DIR"), "/shaders/spv/canvas_vert.spv"));
+const FRAGMENT_SHADER: &[u8] = include_bytes!(concat!(env!("CARGO_MANIFEST_DIR"), "/shaders/spv/canvas_frag.spv"));
+
+/// 2D affine transform `[a, b, c, d, e, f]` mapping `[x, y]` to
+/// `[a x + c y + e, b x + d y + f]`
+pub(crate) type Affine = [f32; 6];
+
+pub(crate) const IDENTITY: Affine = [1.0, 0.0, 0.0, 1.0, 0.0, 0.0];
+
+pub(crate) fn apply(m: &Affine, p: [f32; 2]) -> [f32; 2] {
+    [m[0] * p[0] + m[2] * p[1] + m[4], m[1] * p[0] + m[3] * p[1] + m[5]]
+}
+
+/// `n` applied first, then `m`
+fn multiply(m: &Affine, n: &Affine) -> Affine {
+    [
+        m[0] * n[0] + m[2] * n[1],
+        m[1] * n[0] + m[3] * n[1],
+        m[0] * n[2] + m[2] * n[3],
+        m[1] * n[2] + m[3] * n[3],
+        m[0] * n[4] + m[2] * n[5] + m[4],
+        m[1] * n[4] + m[3] * n[5] + m[5],
+    ]
+}
+
+/// `None` when the transform flattens everything to a line or a point
+fn invert(m: &Affine) -> Option<Affine> {
+    let det = m[0] * m[3] - m[1] * m[2];
+    if det.abs() <= f32::EPSILON * f32::EPSILON {
+        return None;
+    }
+
+    Some([
+        m[3] / det,
+        -m[1] / det,
+        -m[2] / det,
+        m[0] / det,
+        (m[2] * m[5] - m[3] * m[4]) / det,
+        (m[1] * m[4] - m[0] * m[5]) / det,
+    ])
+}
+
+/// Everything is inside
+const NO_CLIP: [f32; 4] = [f32::MIN, f32::MIN, f32::MAX, f32::MAX];
+
+/// Color of filled and stroked shapes, linear RGBA with straight alpha
+///
+/// Gradient points are in the coordinates of the path, before the
+/// transform of the canvas
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Paint {
+    /// One color everywhere
+    Solid([f32; 4]),
+    /// Colors blended along the line from `start` to `end`, constant past
+    /// both ends
+    LinearGradient {
+        /// Point colored `start_color`
+        start: [f32; 2],
+        /// Point colored `end_color`
+        end: [f32; 2],
+        /// Color at and before `start`
+        start_color: [f32; 4],
+        /// Color at and past `end`
+        end_color: [f32; 4],
+    },
+    /// Colors blended from `center` out to `radius`, constant past it
+    RadialGradient {
+        /// Point colored `inner_color`
+        center: [f32; 2],
+        /// Distance from `center` colored `outer_color`
+        radius: f32,
+        /// Color at `center`
+        inner_color: [f32; 4],
+        /// Color at and past `radius`
+        outer_color: [f32; 4],
+    },
+}
+
+impl From<[f32; 4]> for Paint {
+    fn from(color: [f32; 4]) -> Self {
+        Self::Solid(color)
+    }
+}
+
+impl Paint {
+    /// Both colors, the gradient and its kind as `shaders/canvas.frag`
+    /// reads them
+    fn params(&self) -> ([f32; 4], [f32; 4], [f32; 4], u32) {
+        match *self {
+            Self::Solid(color) => (color, color, [0.0; 4], 0),
+            Self::LinearGradient { start, end, start_color, end_color } => (start_color, end_color, [start[0], start[1], end[0], end[1]], 1),
+            Self::RadialGradient { center, radius, inner_color, outer_color } => (inner_color, outer_color, [center[0], center[1], radius, 0.0], 2),
+        }
+    }
+}
+
+/// Outline drawn along a [`Path`] by [`Canvas::stroke`]
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Stroke {
+    width: f32,
+    join: LineJoin,
+    cap: LineCap,
+    miter_limit: f32,
+}
+
+impl Default for Stroke {
+    fn default() -> Self {
+        Self::new(1.0)
+    }
+}
+
+impl Stroke {
+    /// Miter joins and butt caps of `width` pixels, scaled by the transform
+    /// of the canvas
+    pub fn new(width: f32) -> Self {
+        Self {
+            width,
+            join: LineJoin::Miter,
+            cap: LineCap::Butt,
+            miter_limit: 4.0,
+        }
+    }
+
+    /// Corners between segments
+    pub fn join(mut self, join: LineJoin) -> Self {
+        self.join = join;
+        self
+    }
+
+    /// Ends of open contours
+    pub fn cap(mut self, cap: LineCap) -> Self {
+        self.cap = cap;
+        self
+    }
+
+    /// Longest miter as a multiple of the width, longer ones are beveled
+    pub fn miter_limit(mut self, limit: f32) -> Self {
+        self.miter_limit = limit;
+        self
+    }
+}
+
+/// Vertex in the layout of `shaders/canvas.vert`
+#[repr(C)]
+#[derive(Clone, Copy, Debug, PartialEq, Pod, Zeroable)]
+struct CanvasVertex {
+    pos: [f32; 2],
+    /// Position before the transform, where gradients are evaluated
+    local: [f32; 2],
+    aa: [f32; 2],
+    clip: [f32; 4],
+    color0: [f32; 4],
+    color1: [f32; 4],
+    gradient: [f32; 4],
+    kind: u32,
+}
+
+/// Transform and clip restored by [`Canvas::restore`]
+#[derive(Clone, Copy, Debug)]
+struct State {
+    transform: Affine,
+    clip: [f32; 4],
+}
+
+impl Default for State {
+    fn default() -> Self {
+        Self {
+            transform: IDENTITY,
+            clip: NO_CLIP,
+        }
+    }
+}
+
+/// Immediate-mode 2D drawing for chart axes, ticks, legends and shapes
+///
+/// Coordinates are pixels of the frame, x right and y down from the top
+/// left corner, independent of the [`Camera`](crate::Camera). Shapes are
+/// drawn in call order over everything drawn before
+/// [`CanvasPass`](crate::CanvasPass) and last one frame, together with the
+/// transform and clip. Every call returns `self` to chain shapes
+///
+/// Fills cover every contour of a path on its own, holes are not cut out
+/// of them. Edges are antialiased
+///
+/// # Example
+/// ```ignore
+/// let plot = Path::rounded_rect([40.0, 20.0], [400.0, 300.0], 6.0);
+///
+/// world
+///     .canvas()
+///     .fill(&plot, [0.1, 0.1, 0.1, 0.8])
+///     .stroke(&plot, &Stroke::new(2.0), [1.0; 4])
+///     .save()
+///     .clip_rect([40.0, 20.0], [400.0, 300.0])
+///     .translate([40.0, 320.0])
+///     .stroke(&curve, &Stroke::new(1.5).join(LineJoin::Round), [0.2, 0.6, 1.0, 1.0])
+///     .restore();
+///
+/// world.draw_frame(|graph| {
+///     graph.add_pass(scene_pass);
+///     graph.add_pass(CanvasPass::new("Canvas"));
+/// })?;
+/// ```
+#[derive(Default)]
+pub struct Canvas {
+    vertices: Vec<CanvasVertex>,
+    state: State,
+    saved: Vec<State>,
+    /// Triangles of the shape being added
+    scratch: Vec<TessVertex>,
+}
+
+impl Canvas {
+    /// Remember the transform and clip until the matching
+    /// [`Canvas::restore`]
+    pub fn save(&mut self) -> &mut Self {
+        self.saved.push(self.state);
+        self
+    }
+
+    /// Go back to the transform and clip of the last [`Canvas::save`],
+    /// does nothing without one
+    pub fn restore(&mut self) -> &mut Self {
+        if let Some(state) = self.saved.pop() {
+            self.state = state;
+        }
+        self
+    }
+
+    /// Move the following shapes by `offset`
+    pub fn translate(&mut self, offset: [f32; 2]) -> &mut Self {
+        self.transform(&[1.0, 0.0, 0.0, 1.0, offset[0], offset[1]])
+    }
+
+    /// Scale the following shapes around the origin
+    pub fn scale(&mut self, factor: [f32; 2]) -> &mut Self {
+        self.transform(&[factor[0], 0.0, 0.0, factor[1], 0.0, 0.0])
+    }
+
+    /// Rotate the following shapes around the origin by `angle` radians,
+    /// clockwise on screen
+    pub fn rotate(&mut self, angle: f32) -> &mut Self {
+        let (sin, cos) = angle.sin_cos();
+        self.transform(&[cos, sin, -sin, cos, 0.0, 0.0])
+    }
+
+    /// Back to pixels of the frame, the clip stays
+    pub fn reset_transform(&mut self) -> &mut Self {
+        self.state.transform = IDENTITY;
+        self
+    }
+
+    /// Hide the following shapes outside the rectangle, within the current
+    /// clip
+    ///
+    /// The rectangle is transformed, a rotated one clips to its bounds
+    pub fn clip_rect(&mut self, pos: [f32; 2], size: [f32; 2]) -> &mut Self {
+        let [x, y] = pos;
+        let [w, h] = size;
+        let corners = [[x, y], [x + w, y], [x, y + h], [x + w, y + h]].map(|p| apply(&self.state.transform, p));
+
+        let clip = &mut self.state.clip;
+        clip[0] = clip[0].max(corners.iter().map(|p| p[0]).fold(f32::MAX, f32::min));
+        clip[1] = clip[1].max(corners.iter().map(|p| p[1]).fold(f32::MAX, f32::min));
+        clip[2] = clip[2].min(corners.iter().map(|p| p[0]).fold(f32::MIN, f32::max));
+        clip[3] = clip[3].min(corners.iter().map(|p| p[1]).fold(f32::MIN, f32::max));
+        self
+    }
+
+    /// Show the following shapes everywhere
+    pub fn reset_clip(&mut self) -> &mut Self {
+        self.state.clip = NO_CLIP;
+        self
+    }
+
+    /// Cover the inside of every closed or open contour of `path`
+    pub fn fill(&mut self, path: &Path, paint: impl Into<Paint>) -> &mut Self {
+        let contours = path.flatten(&self.state.transform);
+        let mut scratch = std::mem::take(&mut self.scratch);
+
+        tessellate::fill(&contours, &mut scratch);
+        self.push(&mut scratch, &paint.into());
+
+        self.scratch = scratch;
+        self
+    }
+
+    /// Draw the outline of `path`
+    pub fn stroke(&mut self, path: &Path, stroke: &Stroke, paint: impl Into<Paint>) -> &mut Self {
+        let contours = path.flatten(&self.state.transform);
+        let mut scratch = std::mem::take(&mut self.scratch);
+
+        // Widths scale with the area of the transform
+        let [a, b, c, d, ..] = self.state.transform;
+        let style = StrokeStyle {
+            width: stroke.width * (a * d - b * c).abs().sqrt(),
+            join: stroke.join,
+            cap: stroke.cap,
+            miter_limit: stroke.miter_limit,
+        };
+        tessellate::stroke(&contours, &style, &mut scratch);
+        self.push(&mut scratch, &paint.into());
+
+        self.scratch = scratch;
+        self
+    }
+
+    /// Draw the bar of a colormap legend with its outline and tick marks
+    pub fn colorbar(&mut self, colorbar: &Colorbar) -> &mut Self {
+        colorbar.draw(self);
+        self
+    }
+
+    /// Number of vertices waiting for the next frame
+    pub fn vertex_count(&self) -> usize {
+        self.vertices.len()
+    }
+
+    /// Move the triangles of `scratch` to the vertices with the paint and
+    /// clip attached
+    fn push(&mut self, scratch: &mut Vec<TessVertex>, paint: &Paint) {
+        let State { transform, clip } = self.state;
+        let empty = clip[0] >= clip[2] || clip[1] >= clip[3];
+
+        if let (Some(inverse), false) = (invert(&transform), empty) {
+            let (color0, color1, gradient, kind) = paint.params();
+            self.vertices.extend(scratch.iter().map(|vertex| CanvasVertex {
+                pos: vertex.pos,
+                local: apply(&inverse, vertex.pos),
+                aa: vertex.aa,
+                clip,
+                color0,
+                color1,
+                gradient,
+                kind,
+            }));
+        }
+
+        scratch.clear();
+    }
+
+    fn transform(&mut self, m: &Affine) -> &mut Self {
+        self.state.transform = multiply(&self.state.transform, m);
+        self
+    }
+
+    /// Start the next frame empty with the default transform and clip
+    fn clear(&mut self) {
+        self.vertices.clear();
+        self.state = State::default();
+        self.saved.clear();
+    }
+}
+
+/// Buffers and pipeline drawing [`Canvas`]
+pub(crate) struct CanvasRenderer {
+    pub shapes: Canvas,
+    vertices: MeshBuffer,
+    /// Vertices in the buffer of every frame
+    counts: Vec<u32>,
+    layout: PipelineLayout,
+    pipeline: GraphicsPipeline,
+}
+
+impl CanvasRenderer {
+    pub fn new(
+        device: &Device,
+        frame_count: usize,
+        render_pass: vk::RenderPass,
+        set_layouts: Vec<vk::DescriptorSetLayout>,
+    ) -> VulkanResult<Self> {
+        // The layout of every raster pipeline, set 1 holds the resolution
+        let layout = PipelineLayoutBuilder::new(device)
+            .set_layouts(set_layouts)
+            .push_constant(vec![vk::PushConstantRange::default()
+                .offset(0)
+                .size(128)
+                .stage_flags(vk::ShaderStageFlags::VERTEX | vk::ShaderStageFlags::FRAGMENT)])
+            .build()?;
+
+        let spv = read_shader_from_bytes(VERTEX_SHADER).expect("Invalid canvas vertex shader");
+        let vertex = ShaderBuilder::new(device).bytecode(&spv).build()?;
+        let spv = read_shader_from_bytes(FRAGMENT_SHADER).expect("Invalid canvas fragment shader");
+        let fragment = ShaderBuilder::new(device).bytecode(&spv).build()?;
+
+        let bindings = [vk::VertexInputBindingDescription {
+            binding: 0,
+            stride: size_of::<CanvasVertex>() as u32,
+            input_rate: vk::VertexInputRate::VERTEX,
+        }];
+        let attribute = |location: u32, format: vk::Format, offset: usize| vk::VertexInputAttributeDescription {
+            location,
+            binding: 0,
+            format,
+            offset: offset as u32,
+        };
+        let attributes = [
+            attribute(0, vk::Format::R32G32_SFLOAT, std::mem::offset_of!(CanvasVertex, pos)),
+            attribute(1, vk::Format::R32G32_SFLOAT, std::mem::offset_of!(CanvasVertex, local)),
+            attribute(2, vk::Format::R32G32_SFLOAT, std::mem::offset_of!(CanvasVertex, aa)),
+            attribute(3, vk::Format::R32G32B32A32_SFLOAT, std::mem::offset_of!(CanvasVertex, clip)),
+            attribute(4, vk::Format::R32G32B32A32_SFLOAT, std::mem::offset_of!(CanvasVertex, color0)),
+            attribute(5, vk::Format::R32G32B32A32_SFLOAT, std::mem::offset_of!(CanvasVertex, color1)),
+            attribute(6, vk::Format::R32G32B32A32_SFLOAT, std::mem::offset_of!(CanvasVertex, gradient)),
+            attribute(7, vk::Format::R32_UINT, std::mem::offset_of!(CanvasVertex, kind)),
+        ];
+
+        let vertex_input_info = vk::PipelineVertexInputStateCreateInfo::default()
+            .vertex_binding_descriptions(&bindings)
+            .vertex_attribute_descriptions(&attributes);
+
+        let color_blend = vk::PipelineColorBlendAttachmentState::default()
+            .color_write_mask(vk::ColorComponentFlags::RGBA)
+            .blend_enable(true)
+            .src_color_blend_factor(vk::BlendFactor::SRC_ALPHA)
+            .dst_color_blend_factor(vk::BlendFactor::ONE_MINUS_SRC_ALPHA)
+            .color_blend_op(vk::BlendOp::ADD)
+            .src_alpha_blend_factor(vk::BlendFactor::ONE)
+            .dst_alpha_blend_factor(vk::BlendFactor::ONE_MINUS_SRC_ALPHA)
+            .alpha_blend_op(vk::BlendOp::ADD);
+
+        // Shapes are drawn over everything in call order
+        let depth_stencil = vk::PipelineDepthStencilStateCreateInfo::default()
+            .depth_test_enable(false)
+            .depth_write_enable(false)
+            .max_depth_bounds(1.0);
+
+        // Viewport and scissors are dynamic, set for every draw
+        let pipeline = GraphicsPipelineBuilder::new(device)
+            .vertex_shader(vertex.raw)
+            .fragment_shader(fragment.raw)
+            .render_pass(render_pass)
+            .pipeline_layout(layout.raw)
+            .viewport(vec![vk::Viewport::default().width(1.0).height(1.0).max_depth(1.0)])
+            .scissors(vec![vk::Rect2D::default()])
+            .input_assembly(vk::PipelineInputAssemblyStateCreateInfo::default().topology(vk::PrimitiveTopology::TRIANGLE_LIST))
+            .rasterization(
+                vk::PipelineRasterizationStateCreateInfo::default()
+                    .polygon_mode(vk::PolygonMode::FILL)
+                    .line_width(1.0)
+                    .cull_mode(vk::CullModeFlags::NONE),
+            )
+            .depth_stencil(depth_stencil)
+            .multisampling(vk::PipelineMultisampleStateCreateInfo::default().rasterization_samples(vk::SampleCountFlags::TYPE_1))
+            .color_blending(vk::PipelineColorBlendStateCreateInfo::default().attachments(&[color_blend]))
+            .dynamic_state(vec![vk::DynamicState::VIEWPORT, vk::DynamicState::SCISSOR])
+            .vertex_input_info(vertex_input_info)
+            .build()?;
+
+        Ok(Self {
+            shapes: Canvas::default(),
+            vertices: MeshBuffer::new_dynamic(vk::BufferUsageFlags::VERTEX_BUFFER, size_of::<CanvasVertex>(), frame_count),
+            counts: vec![0; frame_count],
+            layout,
+            pipeline,
+        })
+    }
+
+    /// Move the shapes of this frame into the buffer of `frame` and start
+    /// an empty [`Canvas`], the frame must not be in flight
+    pub fn update(&mut self, device: &Device, frame: usize) -> VulkanResult<()> {
+        profiling::scope!("CanvasRenderer::update");
+
+        self.counts[frame] = self.shapes.vertices.len() as u32;
+        self.vertices.replace(bytemuck::cast_slice(&self.shapes.vertices));
+        self.shapes.clear();
+
+        self.vertices.prepare(device, frame)
+    }
+
+    /// Draw the shapes of `frame`, must be inside the render pass
+    ///
+    /// `sets` are the bindless and per-frame sets of the frame
+    pub(crate) unsafe fn draw(&self, device: &ash::Device, cbuf: vk::CommandBuffer, frame: usize, resolution: vk::Extent2D, sets: &[vk::DescriptorSet]) {
+        profiling::scope!("CanvasRenderer::draw");
+
+        let Some(buffer) = self.vertices.raw(frame).filter(|_| self.counts[frame] > 0) else {
+            return;
+        };
+
+        let viewport = vk::Viewport::default()
+            .width(resolution.width as f32)
+            .height(resolution.height as f32)
+            .max_depth(1.0);
+        let scissor = vk::Rect2D::default().extent(resolution);
+
+        device.cmd_bind_pipeline(cbuf, vk::PipelineBindPoint::GRAPHICS, self.pipeline.raw);
+        device.cmd_bind_descriptor_sets(cbuf, vk::PipelineBindPoint::GRAPHICS, self.layout.raw, 0, sets, &[]);
+        device.cmd_bind_vertex_buffers(cbuf, 0, &[buffer], &[0]);
+        device.cmd_set_viewport(cbuf, 0, &[viewport]);
+        device.cmd_set_scissor(cbuf, 0, &[scissor]);
+        device.cmd_draw(cbuf, self.counts[frame], 1, 0, 0);
+    }
+
+    pub fn destroy(&mut self, device: &Device) {
+        let vertices = std::mem::replace(&mut self.vertices, MeshBuffer::new_dynamic(vk::BufferUsageFlags::VERTEX_BUFFER, size_of::<CanvasVertex>(), 0));
+        for mut buffer in vertices.into_buffers() {
+            buffer.destroy(device);
+        }
+        self.pipeline.destroy(device);
+        unsafe {
+            device.destroy_pipeline_layout(self.layout.raw, None);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn transforms_compose_and_invert() {
+        let mut canvas = Canvas::default();
+        canvas.translate([10.0, 20.0]).scale([2.0, 2.0]).rotate(std::f32::consts::FRAC_PI_2);
+
+        let m = canvas.state.transform;
+        let p = apply(&m, [1.0, 0.0]);
+        assert!((p[0] - 10.0).abs() < 1e-5 && (p[1] - 22.0).abs() < 1e-5);
+
+        let back = apply(&invert(&m).unwrap(), p);
+        assert!((back[0] - 1.0).abs() < 1e-5 && back[1].abs() < 1e-5);
+        assert!(invert(&[1.0, 2.0, 2.0, 4.0, 0.0, 0.0]).is_none());
+    }
+
+    #[test]
+    fn save_restores_transform_and_clip() {
+        let mut canvas = Canvas::default();
+        canvas
+            .translate([5.0, 5.0])
+            .save()
+            .clip_rect([0.0, 0.0], [10.0, 10.0])
+            .clip_rect([5.0, -5.0], [10.0, 10.0]);
+        assert_eq!(canvas.state.clip, [10.0, 5.0, 15.0, 10.0]);
+
+        canvas.restore().fill(&Path::rect([0.0, 0.0], [4.0, 4.0]), [1.0; 4]);
+        assert_eq!(canvas.state.clip, NO_CLIP);
+        // Gradients see the position before the transform
+        let vertex = canvas.vertices[0];
+        assert_eq!(vertex.local, [vertex.pos[0] - 5.0, vertex.pos[1] - 5.0]);
+    }
+
+    #[test]
+    fn empty_clips_drop_shapes() {
+        let mut canvas = Canvas::default();
+        canvas
+            .clip_rect([0.0, 0.0], [10.0, 10.0])
+            .clip_rect([20.0, 20.0], [10.0, 10.0])
+            .stroke(&Path::circle([5.0, 5.0], 4.0), &Stroke::default(), [1.0; 4]);
+
+        assert_eq!(canvas.vertex_count(), 0);
+    }
+}
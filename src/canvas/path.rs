@@ -0,0 +1,277 @@
+//! Outlines of lines, arcs and bezier curves flattened to polygons
+
+use std::f32::consts::{FRAC_PI_2, PI, TAU};
+
+use super::{apply, Affine};
+
+/// Largest distance in pixels between a curve and its flattened segments
+const TOLERANCE: f32 = 0.25;
+
+/// Most segments one curve is split into
+const MAX_SEGMENTS: usize = 256;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Verb {
+    MoveTo([f32; 2]),
+    LineTo([f32; 2]),
+    QuadTo([f32; 2], [f32; 2]),
+    CubicTo([f32; 2], [f32; 2], [f32; 2]),
+    Arc { center: [f32; 2], radius: f32, start: f32, end: f32 },
+    Close,
+}
+
+/// Outline filled or stroked by a [`Canvas`](crate::Canvas)
+///
+/// Coordinates are canvas pixels, x right and y down, before the transform
+/// of the canvas. A path holds any number of contours, each started by
+/// [`Path::move_to`]
+///
+/// # Example
+/// ```ignore
+/// let arrow = Path::new()
+///     .move_to([0.0, 0.0])
+///     .line_to([20.0, 10.0])
+///     .line_to([0.0, 20.0])
+///     .quad_to([5.0, 10.0], [0.0, 0.0])
+///     .close();
+/// ```
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Path {
+    verbs: Vec<Verb>,
+}
+
+impl Path {
+    /// Path without contours
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Axis-aligned rectangle from its top left corner
+    pub fn rect(pos: [f32; 2], size: [f32; 2]) -> Self {
+        let [x, y] = pos;
+        let [w, h] = size;
+        Self::polygon(&[[x, y], [x + w, y], [x + w, y + h], [x, y + h]])
+    }
+
+    /// Rectangle with corners rounded by `radius`, at most half the shorter
+    /// side
+    pub fn rounded_rect(pos: [f32; 2], size: [f32; 2], radius: f32) -> Self {
+        let [x, y] = pos;
+        let [w, h] = size;
+        let r = radius.clamp(0.0, w.abs().min(h.abs()) * 0.5);
+
+        if r == 0.0 {
+            return Self::rect(pos, size);
+        }
+
+        Self::new()
+            .arc([x + w - r, y + r], r, -FRAC_PI_2, 0.0)
+            .arc([x + w - r, y + h - r], r, 0.0, FRAC_PI_2)
+            .arc([x + r, y + h - r], r, FRAC_PI_2, PI)
+            .arc([x + r, y + r], r, PI, PI + FRAC_PI_2)
+            .close()
+    }
+
+    /// Closed circle of `radius` around `center`
+    pub fn circle(center: [f32; 2], radius: f32) -> Self {
+        Self::new().arc(center, radius, 0.0, TAU).close()
+    }
+
+    /// Closed contour through `points`
+    pub fn polygon(points: &[[f32; 2]]) -> Self {
+        let Some((&first, rest)) = points.split_first() else {
+            return Self::new();
+        };
+
+        rest.iter().fold(Self::new().move_to(first), |path, &p| path.line_to(p)).close()
+    }
+
+    /// Start a new contour at `p`
+    pub fn move_to(mut self, p: [f32; 2]) -> Self {
+        self.verbs.push(Verb::MoveTo(p));
+        self
+    }
+
+    /// Straight segment to `p`
+    pub fn line_to(mut self, p: [f32; 2]) -> Self {
+        self.verbs.push(Verb::LineTo(p));
+        self
+    }
+
+    /// Quadratic bezier curve to `p` pulled towards `control`
+    pub fn quad_to(mut self, control: [f32; 2], p: [f32; 2]) -> Self {
+        self.verbs.push(Verb::QuadTo(control, p));
+        self
+    }
+
+    /// Cubic bezier curve to `p` pulled towards `control1`, then `control2`
+    pub fn cubic_to(mut self, control1: [f32; 2], control2: [f32; 2], p: [f32; 2]) -> Self {
+        self.verbs.push(Verb::CubicTo(control1, control2, p));
+        self
+    }
+
+    /// Arc of the circle around `center` from angle `start` to `end` in
+    /// radians, a straight segment leads to its first point
+    ///
+    /// Angles grow from +x towards +y, clockwise on screen
+    pub fn arc(mut self, center: [f32; 2], radius: f32, start: f32, end: f32) -> Self {
+        self.verbs.push(Verb::Arc { center, radius, start, end });
+        self
+    }
+
+    /// Join the end of the contour to its first point
+    pub fn close(mut self) -> Self {
+        self.verbs.push(Verb::Close);
+        self
+    }
+
+    /// Nothing was added
+    pub fn is_empty(&self) -> bool {
+        self.verbs.is_empty()
+    }
+
+    /// Contours in pixels after `transform`, curves split finely enough to
+    /// stay within [`TOLERANCE`]
+    pub(super) fn flatten(&self, transform: &Affine) -> Vec<Contour> {
+        let mut contours = vec![];
+        let mut contour = Contour::default();
+        // Pixels per path unit along the longer axis of the transform
+        let scale = (transform[0].hypot(transform[1])).max(transform[2].hypot(transform[3]));
+
+        for verb in &self.verbs {
+            match *verb {
+                Verb::MoveTo(p) => {
+                    contours.push(std::mem::take(&mut contour));
+                    contour.push(apply(transform, p));
+                }
+                Verb::LineTo(p) => contour.push(apply(transform, p)),
+                Verb::QuadTo(c, p) => {
+                    let p0 = contour.last();
+                    let (c, p) = (apply(transform, c), apply(transform, p));
+                    // Wang's formula
+                    let d = [p0[0] - 2.0 * c[0] + p[0], p0[1] - 2.0 * c[1] + p[1]];
+                    let n = segments((0.25 * d[0].hypot(d[1]) / TOLERANCE).sqrt());
+                    for step in 1..=n {
+                        let t = step as f32 / n as f32;
+                        let s = 1.0 - t;
+                        contour.push([0, 1].map(|i| s * s * p0[i] + 2.0 * s * t * c[i] + t * t * p[i]));
+                    }
+                }
+                Verb::CubicTo(c1, c2, p) => {
+                    let p0 = contour.last();
+                    let (c1, c2, p) = (apply(transform, c1), apply(transform, c2), apply(transform, p));
+                    let d1 = [p0[0] - 2.0 * c1[0] + c2[0], p0[1] - 2.0 * c1[1] + c2[1]];
+                    let d2 = [c1[0] - 2.0 * c2[0] + p[0], c1[1] - 2.0 * c2[1] + p[1]];
+                    let n = segments((0.75 * d1[0].hypot(d1[1]).max(d2[0].hypot(d2[1])) / TOLERANCE).sqrt());
+                    for step in 1..=n {
+                        let t = step as f32 / n as f32;
+                        let s = 1.0 - t;
+                        contour.push([0, 1].map(|i| s * s * s * p0[i] + 3.0 * s * s * t * c1[i] + 3.0 * s * t * t * c2[i] + t * t * t * p[i]));
+                    }
+                }
+                Verb::Arc { center, radius, start, end } => {
+                    // Chords of this angle stay within the tolerance
+                    let r = radius.abs() * scale;
+                    let step = if r > TOLERANCE { 2.0 * (1.0 - TOLERANCE / r).acos() } else { PI };
+                    let n = segments((end - start).abs() / step);
+                    for i in 0..=n {
+                        let angle = start + (end - start) * i as f32 / n as f32;
+                        contour.push(apply(transform, [center[0] + radius * angle.cos(), center[1] + radius * angle.sin()]));
+                    }
+                }
+                Verb::Close => {
+                    contour.closed = true;
+                    if let [first, .., last] = contour.points[..] {
+                        // Full circles end within rounding of their start
+                        if (first[0] - last[0]).hypot(first[1] - last[1]) < 1e-3 {
+                            contour.points.pop();
+                        }
+                    }
+                    // Drawing on after a close starts at the same point
+                    let start = contour.points.first().copied();
+                    contours.push(std::mem::take(&mut contour));
+                    contour.points.extend(start);
+                }
+            }
+        }
+
+        contours.push(contour);
+        contours.retain(|contour| contour.points.len() > 1);
+        contours
+    }
+}
+
+fn segments(n: f32) -> usize {
+    (n.ceil() as usize).clamp(1, MAX_SEGMENTS)
+}
+
+/// Flattened contour in pixels without repeated points
+#[derive(Debug, Default, PartialEq)]
+pub(super) struct Contour {
+    pub points: Vec<[f32; 2]>,
+    /// The last point connects back to the first
+    pub closed: bool,
+}
+
+impl Contour {
+    fn push(&mut self, p: [f32; 2]) {
+        if self.points.last() != Some(&p) {
+            self.points.push(p);
+        }
+    }
+
+    /// Current point, the origin for a curve starting a path
+    fn last(&self) -> [f32; 2] {
+        self.points.last().copied().unwrap_or_default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::canvas::IDENTITY;
+
+    #[test]
+    fn arcs_stay_within_the_tolerance() {
+        let contours = Path::circle([10.0, 20.0], 50.0).flatten(&IDENTITY);
+        assert_eq!(contours.len(), 1);
+
+        let points = &contours[0].points;
+        assert!(contours[0].closed);
+        assert_ne!(points.first(), points.last());
+
+        for (i, a) in points.iter().enumerate() {
+            let b = points[(i + 1) % points.len()];
+            let mid = [(a[0] + b[0]) * 0.5 - 10.0, (a[1] + b[1]) * 0.5 - 20.0];
+            assert!(50.0 - mid[0].hypot(mid[1]) <= TOLERANCE + 1e-3);
+        }
+    }
+
+    #[test]
+    fn contours_split_at_move_to() {
+        let path = Path::rect([0.0, 0.0], [4.0, 4.0])
+            .move_to([10.0, 0.0])
+            .line_to([10.0, 10.0])
+            .move_to([20.0, 0.0]);
+        let contours = path.flatten(&IDENTITY);
+
+        // A lone point is dropped
+        assert_eq!(contours.len(), 2);
+        assert_eq!(contours[0].points, vec![[0.0, 0.0], [4.0, 0.0], [4.0, 4.0], [0.0, 4.0]]);
+        assert_eq!(contours[1], Contour { points: vec![[10.0, 0.0], [10.0, 10.0]], closed: false });
+    }
+
+    #[test]
+    fn curves_end_at_their_last_point() {
+        let scaled = [2.0, 0.0, 0.0, 2.0, 1.0, 0.0];
+        let path = Path::new()
+            .move_to([0.0, 0.0])
+            .quad_to([50.0, 100.0], [100.0, 0.0])
+            .cubic_to([100.0, 50.0], [0.0, 50.0], [0.0, 0.0]);
+        let points = &path.flatten(&scaled)[0].points;
+
+        assert!(points.len() > 20);
+        assert!(points.contains(&[201.0, 0.0]));
+        assert_eq!(points.last(), Some(&[1.0, 0.0]));
+    }
+}
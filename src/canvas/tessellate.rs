@@ -0,0 +1,353 @@
+//! Triangles of filled and stroked contours with antialiased edges
+//!
+//! Every vertex carries `aa = [distance, half width]` in pixels, the
+//! fragment shader covers `half width + 0.5 - |distance|` of the pixel.
+//! Fills fade over one pixel centered on the outline
+
+use std::f32::consts::PI;
+
+use super::path::Contour;
+use crate::resources::{LineCap, LineJoin};
+
+/// Corners flatter than this cosine need no join
+const STRAIGHT: f32 = 0.9999;
+
+/// Vertex of a triangle list before paint and clip are attached
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub(super) struct TessVertex {
+    pub pos: [f32; 2],
+    pub aa: [f32; 2],
+}
+
+/// Coverage 1 inside the fill, 0 on the outer edge of the fringe
+const FILL_INSIDE: [f32; 2] = [0.0, 0.5];
+const FILL_OUTSIDE: [f32; 2] = [1.0, 0.5];
+
+fn sub(a: [f32; 2], b: [f32; 2]) -> [f32; 2] {
+    [a[0] - b[0], a[1] - b[1]]
+}
+
+fn cross(a: [f32; 2], b: [f32; 2]) -> f32 {
+    a[0] * b[1] - a[1] * b[0]
+}
+
+fn dot(a: [f32; 2], b: [f32; 2]) -> f32 {
+    a[0] * b[0] + a[1] * b[1]
+}
+
+/// Moved from `p` along `dir` by `distance`
+fn offset(p: [f32; 2], dir: [f32; 2], distance: f32) -> [f32; 2] {
+    [p[0] + dir[0] * distance, p[1] + dir[1] * distance]
+}
+
+fn normalize(v: [f32; 2]) -> [f32; 2] {
+    let len = v[0].hypot(v[1]);
+    if len > 0.0 {
+        [v[0] / len, v[1] / len]
+    } else {
+        [0.0, 0.0]
+    }
+}
+
+/// Unit normal of the direction from `a` to `b`, a quarter turn
+/// clockwise on screen
+fn normal(a: [f32; 2], b: [f32; 2]) -> [f32; 2] {
+    let [x, y] = normalize(sub(b, a));
+    [-y, x]
+}
+
+/// Twice the signed area, positive when clockwise on screen
+fn area(points: &[[f32; 2]]) -> f32 {
+    (0..points.len())
+        .map(|i| cross(points[i], points[(i + 1) % points.len()]))
+        .sum()
+}
+
+/// Triangles of a simple polygon as indices into `points`, clockwise on
+/// screen
+///
+/// Convex polygons become a fan, others are ear clipped. Self intersecting
+/// polygons still terminate but may cover the wrong area
+pub(super) fn triangulate(points: &[[f32; 2]]) -> Vec<[usize; 3]> {
+    let mut indices = (0..points.len()).collect::<Vec<_>>();
+    if area(points) < 0.0 {
+        indices.reverse();
+    }
+
+    let turn = |i: &[usize], a: usize| {
+        let [p, q, r] = [i[(a + i.len() - 1) % i.len()], i[a], i[(a + 1) % i.len()]].map(|j| points[j]);
+        cross(sub(q, p), sub(r, q))
+    };
+
+    let mut triangles = Vec::with_capacity(points.len().saturating_sub(2));
+    if (0..indices.len()).all(|a| turn(&indices, a) >= 0.0) {
+        for a in 1..indices.len().saturating_sub(1) {
+            triangles.push([indices[0], indices[a], indices[a + 1]]);
+        }
+        return triangles;
+    }
+
+    let mut a = 0;
+    let mut misses = 0;
+    while indices.len() > 3 {
+        let len = indices.len();
+        let [prev, ear, next] = [(a + len - 1) % len, a, (a + 1) % len].map(|j| indices[j]);
+        let triangle = [points[prev], points[ear], points[next]];
+
+        // A convex corner with no other corner inside its triangle, or any
+        // corner once none is left
+        let is_ear = turn(&indices, a) > 0.0
+            && !indices
+                .iter()
+                .filter(|&&j| j != prev && j != ear && j != next)
+                .any(|&j| contains(&triangle, points[j]));
+
+        if is_ear || misses >= len {
+            triangles.push([prev, ear, next]);
+            indices.remove(a);
+            a %= indices.len();
+            misses = 0;
+        } else {
+            a = (a + 1) % len;
+            misses += 1;
+        }
+    }
+
+    if let [a, b, c] = indices[..] {
+        triangles.push([a, b, c]);
+    }
+    triangles
+}
+
+/// Inside or on the edge of a clockwise triangle
+fn contains(triangle: &[[f32; 2]; 3], p: [f32; 2]) -> bool {
+    (0..3).all(|i| cross(sub(triangle[(i + 1) % 3], triangle[i]), sub(p, triangle[i])) >= 0.0)
+}
+
+/// Fill of closed contours, each one on its own
+pub(super) fn fill(contours: &[Contour], out: &mut Vec<TessVertex>) {
+    for contour in contours {
+        let points = &contour.points;
+        if points.len() < 3 || area(points) == 0.0 {
+            continue;
+        }
+
+        // Outward normals of the corners, longer at sharp corners so the
+        // fringe keeps its width
+        let outward = if area(points) > 0.0 { -1.0 } else { 1.0 };
+        let len = points.len();
+        let miters = (0..len)
+            .map(|i| {
+                let (prev, p, next) = (points[(i + len - 1) % len], points[i], points[(i + 1) % len]);
+                let (n0, n1) = (normal(prev, p), normal(p, next));
+                let m = [n0[0] + n1[0], n0[1] + n1[1]];
+                let scale = outward / (dot(m, m) * 0.5).max(0.05);
+                [m[0] * scale, m[1] * scale]
+            })
+            .collect::<Vec<_>>();
+
+        let inner = |i: usize| TessVertex { pos: offset(points[i], miters[i], -0.5), aa: FILL_INSIDE };
+        let outer = |i: usize| TessVertex { pos: offset(points[i], miters[i], 0.5), aa: FILL_OUTSIDE };
+
+        for triangle in triangulate(points) {
+            out.extend(triangle.map(inner));
+        }
+        for i in 0..len {
+            let j = (i + 1) % len;
+            out.extend([inner(i), outer(i), outer(j), inner(i), outer(j), inner(j)]);
+        }
+    }
+}
+
+/// Shape of a stroke in pixels
+#[derive(Clone, Copy, Debug)]
+pub(super) struct StrokeStyle {
+    pub width: f32,
+    pub join: LineJoin,
+    pub cap: LineCap,
+    pub miter_limit: f32,
+}
+
+/// Outline of `width` centered on contours
+pub(super) fn stroke(contours: &[Contour], style: &StrokeStyle, out: &mut Vec<TessVertex>) {
+    let half = style.width * 0.5;
+    // The fringe fades over the last pixel
+    let extent = half + 0.5;
+    let vertex = |pos: [f32; 2], distance: f32| TessVertex { pos, aa: [distance, half] };
+
+    for contour in contours {
+        let mut points = contour.points.clone();
+        let closed = contour.closed && points.len() > 2;
+        if closed {
+            points.push(points[0]);
+        }
+
+        let segments = points.len() - 1;
+        let normals = (0..segments).map(|s| normal(points[s], points[s + 1])).collect::<Vec<_>>();
+        // Offset directions at both ends of every segment, scaled past one
+        // at miter joins
+        let mut ends = normals.iter().map(|&n| [n, n]).collect::<Vec<_>>();
+
+        let joins = if closed { 0..segments } else { 1..segments };
+        for b in joins {
+            let a = (b + segments - 1) % segments;
+            let (n0, n1) = (normals[a], normals[b]);
+            if dot(n0, n1) > STRAIGHT {
+                continue;
+            }
+
+            let m = [n0[0] + n1[0], n0[1] + n1[1]];
+            let len = m[0].hypot(m[1]);
+            // Ratio of the miter length to the width
+            let miter = if len > 0.0 { 2.0 / len } else { f32::INFINITY };
+
+            if style.join == LineJoin::Miter && miter <= style.miter_limit {
+                let m = [m[0] / len * miter, m[1] / len * miter];
+                ends[a][1] = m;
+                ends[b][0] = m;
+                continue;
+            }
+
+            // Fill the gap on the outer side of the corner, the inner sides
+            // of the segments overlap
+            let p = points[b];
+            let side = if cross(n0, n1) > 0.0 { -1.0 } else { 1.0 };
+            let (from, to) = ([n0[0] * side, n0[1] * side], [n1[0] * side, n1[1] * side]);
+            match style.join {
+                LineJoin::Round => fan(p, from, to, extent, &vertex, out),
+                _ => out.extend([vertex(p, 0.0), vertex(offset(p, from, extent), extent), vertex(offset(p, to, extent), extent)]),
+            }
+        }
+
+        if !closed {
+            // Away from the line at both ends
+            let start_dir = normalize(sub(points[0], points[1]));
+            let end_dir = normalize(sub(points[segments], points[segments - 1]));
+
+            match style.cap {
+                LineCap::Butt => {}
+                LineCap::Square => {
+                    points[0] = offset(points[0], start_dir, half);
+                    points[segments] = offset(points[segments], end_dir, half);
+                }
+                LineCap::Round => {
+                    let (n0, n1) = (normals[0], normals[segments - 1]);
+                    fan(points[0], n0, start_dir, extent, &vertex, out);
+                    fan(points[0], start_dir, [-n0[0], -n0[1]], extent, &vertex, out);
+                    fan(points[segments], [-n1[0], -n1[1]], end_dir, extent, &vertex, out);
+                    fan(points[segments], end_dir, n1, extent, &vertex, out);
+                }
+            }
+        }
+
+        for s in 0..segments {
+            let (a, b) = (points[s], points[s + 1]);
+            let [na, nb] = ends[s];
+            let left = [vertex(offset(a, na, extent), extent), vertex(offset(b, nb, extent), extent)];
+            let right = [vertex(offset(a, na, -extent), -extent), vertex(offset(b, nb, -extent), -extent)];
+            out.extend([left[0], right[0], right[1], left[0], right[1], left[1]]);
+        }
+    }
+}
+
+/// Triangles around `center` between the unit directions `from` and `to`,
+/// less than half a turn apart
+fn fan(center: [f32; 2], from: [f32; 2], to: [f32; 2], radius: f32, vertex: &impl Fn([f32; 2], f32) -> TessVertex, out: &mut Vec<TessVertex>) {
+    let angle = cross(from, to).atan2(dot(from, to));
+    // Chords within a quarter pixel of the circle
+    let step = if radius > 0.25 { 2.0 * (1.0 - 0.25 / radius).acos() } else { PI };
+    let n = ((angle.abs() / step).ceil() as usize).max(1);
+
+    let start = from[1].atan2(from[0]);
+    let point = |i: usize| {
+        let a = start + angle * i as f32 / n as f32;
+        vertex(offset(center, [a.cos(), a.sin()], radius), radius)
+    };
+    for i in 0..n {
+        out.extend([vertex(center, 0.0), point(i), point(i + 1)]);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn triangle_area(points: &[[f32; 2]], [a, b, c]: [usize; 3]) -> f32 {
+        cross(sub(points[b], points[a]), sub(points[c], points[a])) * 0.5
+    }
+
+    #[test]
+    fn concave_polygons_are_covered() {
+        // An L with its notch at the top right, counter-clockwise on screen
+        let l = [[0.0, 0.0], [0.0, 4.0], [4.0, 4.0], [4.0, 2.0], [2.0, 2.0], [2.0, 0.0]];
+        let triangles = triangulate(&l);
+
+        assert_eq!(triangles.len(), 4);
+        let total = triangles.iter().map(|&t| triangle_area(&l, t)).sum::<f32>();
+        assert_eq!(total, 12.0);
+        // Every triangle is clockwise with a positive area
+        assert!(triangles.iter().all(|&t| triangle_area(&l, t) > 0.0));
+    }
+
+    #[test]
+    fn convex_polygons_are_fans() {
+        let square = [[0.0, 0.0], [1.0, 0.0], [1.0, 1.0], [0.0, 1.0]];
+        assert_eq!(triangulate(&square), vec![[0, 1, 2], [0, 2, 3]]);
+    }
+
+    #[test]
+    fn fill_fringe_is_a_pixel_wide() {
+        let contour = Contour { points: vec![[0.0, 0.0], [10.0, 0.0], [10.0, 10.0], [0.0, 10.0]], closed: true };
+        let mut out = vec![];
+        fill(&[contour], &mut out);
+
+        // Two triangles inside and a quad per edge
+        assert_eq!(out.len(), 6 + 4 * 6);
+        assert_eq!(out[0], TessVertex { pos: [0.5, 0.5], aa: FILL_INSIDE });
+        assert!(out.contains(&TessVertex { pos: [-0.5, -0.5], aa: FILL_OUTSIDE }));
+    }
+
+    #[test]
+    fn miter_joins_share_corners() {
+        let contour = Contour { points: vec![[0.0, 0.0], [10.0, 0.0], [10.0, 10.0]], closed: false };
+        let style = StrokeStyle {
+            width: 2.0,
+            join: LineJoin::Miter,
+            cap: LineCap::Butt,
+            miter_limit: 4.0,
+        };
+        let mut out = vec![];
+        stroke(&[contour], &style, &mut out);
+
+        assert_eq!(out.len(), 12);
+        // Both segments meet at the outer and inner miter points
+        for corner in [[8.5, 1.5], [11.5, -1.5]] {
+            let count = out.iter().filter(|v| (v.pos[0] - corner[0]).abs() < 1e-5 && (v.pos[1] - corner[1]).abs() < 1e-5).count();
+            assert_eq!(count, 3, "{corner:?}");
+        }
+
+        // Past the limit the corner is beveled by one more triangle
+        let bevel = StrokeStyle { miter_limit: 1.0, ..style };
+        let contour = Contour { points: vec![[0.0, 0.0], [10.0, 0.0], [10.0, 10.0]], closed: false };
+        let mut out = vec![];
+        stroke(&[contour], &bevel, &mut out);
+        assert_eq!(out.len(), 15);
+    }
+
+    #[test]
+    fn round_caps_reach_past_the_ends() {
+        let contour = Contour { points: vec![[0.0, 0.0], [10.0, 0.0]], closed: false };
+        let style = StrokeStyle {
+            width: 4.0,
+            join: LineJoin::Round,
+            cap: LineCap::Round,
+            miter_limit: 4.0,
+        };
+        let mut out = vec![];
+        stroke(&[contour], &style, &mut out);
+
+        let min_x = out.iter().map(|v| v.pos[0]).fold(f32::MAX, f32::min);
+        let max_x = out.iter().map(|v| v.pos[0]).fold(f32::MIN, f32::max);
+        assert!((min_x + 2.5).abs() < 1e-5 && (max_x - 12.5).abs() < 1e-5);
+    }
+}
@@ -0,0 +1,210 @@
+use std::path::PathBuf;
+
+use ash::vk;
+use tracing::{info, warn};
+
+mod screenshot;
+pub use screenshot::Screenshot;
+pub(crate) use screenshot::srgb_to_linear;
+
+use crate::core::{Device, GpuBuffer, GpuBufferBuilder, VulkanResult};
+
+/// Where the captured frame goes
+pub(crate) enum CaptureTarget {
+    File(PathBuf),
+    Memory,
+}
+
+/// Copies the final image of a frame into host memory
+///
+/// A capture is requested before a frame, recorded right after the last pass
+/// and read back once the frame fence is signaled
+pub(crate) struct FrameCapture {
+    /// Requested, but not recorded yet
+    pending: Option<CaptureTarget>,
+    /// Recorded into the current frame
+    in_flight: Option<CaptureTarget>,
+    /// Host-visible buffer the image is copied into
+    readback: Option<GpuBuffer>,
+    readback_size: u64,
+    /// Last in-memory capture or the error of the last capture
+    result: Option<VulkanResult<Screenshot>>,
+}
+
+impl FrameCapture {
+    pub fn new() -> Self {
+        Self {
+            pending: None,
+            in_flight: None,
+            readback: None,
+            readback_size: 0,
+            result: None,
+        }
+    }
+
+    pub fn request(&mut self, target: CaptureTarget) {
+        self.pending = Some(target);
+    }
+
+    pub fn is_in_flight(&self) -> bool {
+        self.in_flight.is_some()
+    }
+
+    pub fn take(&mut self) -> VulkanResult<Option<Screenshot>> {
+        self.result.take().transpose()
+    }
+
+    /// Record copy of `image` into the readback buffer
+    ///
+    /// `image` must be in `layout` and is returned to it afterwards
+    pub fn record(
+        &mut self,
+        device: &Device,
+        cbuf: vk::CommandBuffer,
+        image: vk::Image,
+        layout: vk::ImageLayout,
+        extent: vk::Extent2D,
+        usage: vk::ImageUsageFlags,
+    ) -> VulkanResult<()> {
+        let Some(target) = self.pending.take() else {
+            return Ok(());
+        };
+
+        debug_assert!(
+            usage.contains(vk::ImageUsageFlags::TRANSFER_SRC),
+            "Capture must be checked for support before request"
+        );
+
+        let size = extent.width as u64 * extent.height as u64 * 4;
+
+        if self.readback_size != size {
+            if let Some(mut buffer) = self.readback.take() {
+                buffer.destroy(device);
+            }
+            self.readback = Some(
+                GpuBufferBuilder::gpu_to_cpu(device)
+                    .size(size)
+                    .usage(vk::BufferUsageFlags::TRANSFER_DST)
+                    .build()?,
+            );
+            self.readback_size = size;
+        }
+
+        let buffer = self.readback.as_ref().unwrap().raw;
+
+        let range = vk::ImageSubresourceRange {
+            aspect_mask: vk::ImageAspectFlags::COLOR,
+            base_mip_level: 0,
+            level_count: 1,
+            base_array_layer: 0,
+            layer_count: 1,
+        };
+
+        let to_transfer = vk::ImageMemoryBarrier::default()
+            .image(image)
+            .subresource_range(range)
+            .old_layout(layout)
+            .new_layout(vk::ImageLayout::TRANSFER_SRC_OPTIMAL)
+            .src_access_mask(vk::AccessFlags::COLOR_ATTACHMENT_WRITE)
+            .dst_access_mask(vk::AccessFlags::TRANSFER_READ)
+            .src_queue_family_index(vk::QUEUE_FAMILY_IGNORED)
+            .dst_queue_family_index(vk::QUEUE_FAMILY_IGNORED);
+
+        let to_final = vk::ImageMemoryBarrier::default()
+            .image(image)
+            .subresource_range(range)
+            .old_layout(vk::ImageLayout::TRANSFER_SRC_OPTIMAL)
+            .new_layout(layout)
+            .src_access_mask(vk::AccessFlags::TRANSFER_READ)
+            .dst_access_mask(vk::AccessFlags::empty())
+            .src_queue_family_index(vk::QUEUE_FAMILY_IGNORED)
+            .dst_queue_family_index(vk::QUEUE_FAMILY_IGNORED);
+
+        let to_host = vk::BufferMemoryBarrier::default()
+            .buffer(buffer)
+            .offset(0)
+            .size(vk::WHOLE_SIZE)
+            .src_access_mask(vk::AccessFlags::TRANSFER_WRITE)
+            .dst_access_mask(vk::AccessFlags::HOST_READ)
+            .src_queue_family_index(vk::QUEUE_FAMILY_IGNORED)
+            .dst_queue_family_index(vk::QUEUE_FAMILY_IGNORED);
+
+        let region = vk::BufferImageCopy::default()
+            .buffer_offset(0)
+            .buffer_row_length(0)
+            .buffer_image_height(0)
+            .image_subresource(vk::ImageSubresourceLayers {
+                aspect_mask: vk::ImageAspectFlags::COLOR,
+                mip_level: 0,
+                base_array_layer: 0,
+                layer_count: 1,
+            })
+            .image_extent(extent.into());
+
+        unsafe {
+            profiling::scope!("FrameCapture::record");
+            device.cmd_pipeline_barrier(
+                cbuf,
+                vk::PipelineStageFlags::COLOR_ATTACHMENT_OUTPUT,
+                vk::PipelineStageFlags::TRANSFER,
+                vk::DependencyFlags::empty(),
+                &[],
+                &[],
+                &[to_transfer],
+            );
+            device.cmd_copy_image_to_buffer(cbuf, image, vk::ImageLayout::TRANSFER_SRC_OPTIMAL, buffer, &[region]);
+            device.cmd_pipeline_barrier(
+                cbuf,
+                vk::PipelineStageFlags::TRANSFER,
+                vk::PipelineStageFlags::BOTTOM_OF_PIPE | vk::PipelineStageFlags::HOST,
+                vk::DependencyFlags::empty(),
+                &[],
+                &[to_host],
+                &[to_final],
+            );
+        }
+
+        self.in_flight = Some(target);
+
+        Ok(())
+    }
+
+    /// Read back the recorded copy, the frame must be finished on the GPU
+    ///
+    /// Failures are kept for [`FrameCapture::take`], the frame itself is fine
+    pub fn finish(&mut self, extent: vk::Extent2D, format: vk::Format) {
+        let Some(target) = self.in_flight.take() else {
+            return;
+        };
+
+        profiling::scope!("FrameCapture::finish");
+
+        let raw = self
+            .readback
+            .as_ref()
+            .expect("Readback buffer must exist for a recorded capture")
+            .download_data(self.readback_size as usize);
+
+        let screenshot = Screenshot::from_raw(extent.width, extent.height, format, raw);
+
+        self.result = match (target, screenshot) {
+            (CaptureTarget::File(path), Ok(screenshot)) => match screenshot.save(&path) {
+                Ok(()) => {
+                    info!("Frame captured to {:?}", path);
+                    None
+                },
+                Err(err) => {
+                    warn!("Failed to save frame to {:?}: {}", path, err);
+                    Some(Err(err))
+                },
+            },
+            (_, screenshot) => Some(screenshot),
+        };
+    }
+
+    pub fn destroy(&mut self, device: &Device) {
+        if let Some(mut buffer) = self.readback.take() {
+            buffer.destroy(device);
+        }
+    }
+}
@@ -0,0 +1,229 @@
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+
+use ash::vk;
+
+use crate::core::{CaptureError, VulkanError, VulkanResult};
+
+/// Pixels of a captured frame
+///
+/// Pixels are tightly packed RGBA8 in sRGB encoding, top row first. Alpha is
+/// always opaque, the swapchain is presented with opaque composite alpha so
+/// this is what ends up on the screen
+#[derive(Clone)]
+pub struct Screenshot {
+    /// Width in pixels
+    pub width: u32,
+    /// Height in pixels
+    pub height: u32,
+    /// RGBA8 pixels
+    pub pixels: Vec<u8>,
+}
+
+impl Screenshot {
+    /// Convert raw texels copied from an image of `format` into RGBA8
+    pub(crate) fn from_raw(width: u32, height: u32, format: vk::Format, mut raw: Vec<u8>) -> VulkanResult<Self> {
+        // UNORM swapchains are still presented as sRGB, shaders are
+        // expected to write already encoded values there
+        let swizzle = match format {
+            vk::Format::R8G8B8A8_SRGB | vk::Format::R8G8B8A8_UNORM => false,
+            vk::Format::B8G8R8A8_SRGB | vk::Format::B8G8R8A8_UNORM => true,
+            format => return Err(VulkanError::Capture(CaptureError::UnsupportedFormat(format))),
+        };
+
+        for texel in raw.chunks_exact_mut(4) {
+            if swizzle {
+                texel.swap(0, 2);
+            }
+            texel[3] = u8::MAX;
+        }
+
+        Ok(Self { width, height, pixels: raw })
+    }
+
+    /// Decode pixels into linear RGBA32F
+    pub fn to_linear_rgba32f(&self) -> Vec<f32> {
+        let mut table = [0.0f32; 256];
+        for (value, linear) in table.iter_mut().enumerate() {
+            *linear = srgb_to_linear(value as f32 / 255.0);
+        }
+
+        self.pixels
+            .chunks_exact(4)
+            .flat_map(|texel| [table[texel[0] as usize], table[texel[1] as usize], table[texel[2] as usize], texel[3] as f32 / 255.0])
+            .collect()
+    }
+
+    /// Save pixels into a file, the format is selected by extension
+    ///
+    /// - `ppm` is always available
+    /// - `png` requires the `png` feature
+    /// - `exr` requires the `exr` feature, pixels are stored as linear floats
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> VulkanResult<()> {
+        profiling::scope!("Screenshot::save");
+
+        let path = path.as_ref();
+        let extension = path
+            .extension()
+            .and_then(|ext| ext.to_str())
+            .map(|ext| ext.to_ascii_lowercase())
+            .unwrap_or_default();
+
+        match extension.as_str() {
+            "ppm" => self.save_ppm(path),
+            #[cfg(feature = "png")]
+            "png" => self.save_png(path),
+            #[cfg(feature = "exr")]
+            "exr" => self.save_exr(path),
+            #[cfg(not(feature = "png"))]
+            "png" => Err(VulkanError::Capture(CaptureError::MissingFeature("png"))),
+            #[cfg(not(feature = "exr"))]
+            "exr" => Err(VulkanError::Capture(CaptureError::MissingFeature("exr"))),
+            _ => Err(VulkanError::Capture(CaptureError::UnknownExtension(extension))),
+        }
+    }
+
+    /// Load pixels from a file, the format is selected by extension
+    ///
+    /// - `ppm` (binary, 8-bit) is always available
+    /// - `png` requires the `png` feature
+    pub fn open<P: AsRef<Path>>(path: P) -> VulkanResult<Self> {
+        profiling::scope!("Screenshot::open");
+
+        let path = path.as_ref();
+        let extension = path
+            .extension()
+            .and_then(|ext| ext.to_str())
+            .map(|ext| ext.to_ascii_lowercase())
+            .unwrap_or_default();
+
+        match extension.as_str() {
+            "ppm" => Self::open_ppm(path),
+            "png" => Self::open_png(path),
+            _ => Err(VulkanError::Capture(CaptureError::UnknownExtension(extension))),
+        }
+    }
+
+    fn open_ppm(path: &Path) -> VulkanResult<Self> {
+        let data = std::fs::read(path).map_err(|e| VulkanError::Capture(CaptureError::Io(e)))?;
+
+        Self::from_ppm(&data).map_err(|e| VulkanError::Capture(CaptureError::Decode(e.to_string())))
+    }
+
+    /// Parse binary PPM, the header is `P6 <width> <height> 255` with
+    /// optional `#` comments
+    fn from_ppm(data: &[u8]) -> Result<Self, &'static str> {
+        let mut cursor = 0;
+        let mut fields = [0u32; 3];
+
+        if !data.starts_with(b"P6") {
+            return Err("Not a binary PPM file");
+        }
+        cursor += 2;
+
+        for field in &mut fields {
+            loop {
+                match data.get(cursor) {
+                    Some(b'#') => {
+                        while data.get(cursor).is_some_and(|c| *c != b'\n') {
+                            cursor += 1;
+                        }
+                    },
+                    Some(c) if c.is_ascii_whitespace() => cursor += 1,
+                    _ => break,
+                }
+            }
+
+            let start = cursor;
+            while data.get(cursor).is_some_and(u8::is_ascii_digit) {
+                cursor += 1;
+            }
+
+            *field = std::str::from_utf8(&data[start..cursor])
+                .ok()
+                .and_then(|value| value.parse().ok())
+                .ok_or("Malformed PPM header")?;
+        }
+
+        let [width, height, max] = fields;
+
+        if max != 255 {
+            return Err("Only 8-bit PPM files are supported");
+        }
+
+        // Single whitespace separates the header from pixels
+        let rgb = data.get(cursor + 1..).ok_or("Missing PPM pixels")?;
+        let size = width as usize * height as usize * 3;
+
+        if rgb.len() < size {
+            return Err("Missing PPM pixels");
+        }
+
+        let pixels = rgb[..size]
+            .chunks_exact(3)
+            .flat_map(|texel| [texel[0], texel[1], texel[2], u8::MAX])
+            .collect();
+
+        Ok(Self { width, height, pixels })
+    }
+
+    #[cfg(feature = "png")]
+    fn open_png(path: &Path) -> VulkanResult<Self> {
+        let image = image::open(path)
+            .map_err(|e| VulkanError::Capture(CaptureError::Decode(e.to_string())))?
+            .to_rgba8();
+
+        Ok(Self {
+            width: image.width(),
+            height: image.height(),
+            pixels: image.into_raw(),
+        })
+    }
+
+    #[cfg(not(feature = "png"))]
+    fn open_png(_path: &Path) -> VulkanResult<Self> {
+        Err(VulkanError::Capture(CaptureError::MissingFeature("png")))
+    }
+
+    fn save_ppm(&self, path: &Path) -> VulkanResult<()> {
+        let file = File::create(path).map_err(|e| VulkanError::Capture(CaptureError::Io(e)))?;
+        let mut writer = BufWriter::new(file);
+
+        let rgb = self
+            .pixels
+            .chunks_exact(4)
+            .flat_map(|texel| [texel[0], texel[1], texel[2]])
+            .collect::<Vec<_>>();
+
+        write!(writer, "P6\n{} {}\n255\n", self.width, self.height)
+            .and_then(|_| writer.write_all(&rgb))
+            .and_then(|_| writer.flush())
+            .map_err(|e| VulkanError::Capture(CaptureError::Io(e)))
+    }
+
+    #[cfg(feature = "png")]
+    fn save_png(&self, path: &Path) -> VulkanResult<()> {
+        image::save_buffer_with_format(path, &self.pixels, self.width, self.height, image::ColorType::Rgba8, image::ImageFormat::Png)
+            .map_err(|e| VulkanError::Capture(CaptureError::Encode(e.to_string())))
+    }
+
+    #[cfg(feature = "exr")]
+    fn save_exr(&self, path: &Path) -> VulkanResult<()> {
+        let image = image::Rgba32FImage::from_raw(self.width, self.height, self.to_linear_rgba32f())
+            .expect("Pixel count must match resolution");
+
+        image
+            .save_with_format(path, image::ImageFormat::OpenExr)
+            .map_err(|e| VulkanError::Capture(CaptureError::Encode(e.to_string())))
+    }
+}
+
+/// sRGB electro-optical transfer function
+pub(crate) fn srgb_to_linear(value: f32) -> f32 {
+    if value <= 0.04045 {
+        value / 12.92
+    } else {
+        ((value + 0.055) / 1.055).powf(2.4)
+    }
+}
@@ -37,6 +37,50 @@ impl GpuBuffer {
         Ok(())
     }
 
+    /// Copy slice as raw bytes into [`vk::Buffer`] starting at `offset` bytes,
+    /// the rest of the buffer is kept
+    pub fn upload_data_at<T: Pod + Zeroable>(&mut self, offset: usize, data: &[T]) -> VulkanResult<()> {
+        let size = std::mem::size_of_val(data);
+        let allocation = self.allocation.as_ref().expect("Buffer alredy free");
+
+        assert!((offset + size) as u64 <= allocation.size(), "Write out of buffer bounds");
+
+        let dst = allocation
+            .mapped_ptr()
+            .expect("Buffer is not host-visible or not mapped")
+            .cast::<u8>()
+            .as_ptr();
+
+        unsafe {
+            profiling::scope!("Upload bytes");
+            std::ptr::copy_nonoverlapping(data.as_ptr().cast::<u8>(), dst.add(offset), size);
+        }
+
+        Ok(())
+    }
+
+    /// Copy the first `size` bytes of a host-visible [`vk::Buffer`]
+    pub fn download_data(&self, size: usize) -> Vec<u8> {
+        let allocation = self.allocation.as_ref().expect("Buffer alredy free");
+
+        assert!(size as u64 <= allocation.size(), "Read out of buffer bounds");
+
+        let src = allocation
+            .mapped_ptr()
+            .expect("Buffer is not host-visible or not mapped")
+            .cast::<u8>()
+            .as_ptr();
+
+        let mut data = vec![0u8; size];
+
+        unsafe {
+            profiling::scope!("Download bytes");
+            std::ptr::copy_nonoverlapping(src, data.as_mut_ptr(), size);
+        }
+
+        data
+    }
+
     pub fn destroy(&mut self, device: &Device) {
         if let Some(allocation) = self.allocation.take() {
             let size = allocation.size();
@@ -0,0 +1,84 @@
+use ash::vk;
+use tracing::debug;
+
+use super::device::Device;
+use super::{VulkanError, VulkanResult};
+
+pub struct ComputePipeline {
+    pub raw: vk::Pipeline,
+}
+
+impl ComputePipeline {
+    pub fn destroy(&self, device: &Device) {
+        unsafe {
+            device.destroy_pipeline(self.raw, None);
+        }
+        debug!(
+            handle = ?self.raw,
+            "Compute Pipeline destroyed"
+        );
+    }
+}
+
+pub struct ComputePipelineBuilder<'n> {
+    device: &'n Device,
+    pipeline_layout: Option<vk::PipelineLayout>,
+    cache: Option<vk::PipelineCache>,
+    shader: Option<vk::ShaderModule>,
+}
+
+impl<'n> ComputePipelineBuilder<'n> {
+    pub fn new(device: &'n Device) -> Self {
+        Self {
+            device,
+            pipeline_layout: None,
+            cache: None,
+            shader: None,
+        }
+    }
+
+    #[allow(dead_code)]
+    pub fn cache(mut self, cache: vk::PipelineCache) -> Self {
+        self.cache = Some(cache);
+        self
+    }
+
+    pub fn pipeline_layout(mut self, layout: vk::PipelineLayout) -> Self {
+        self.pipeline_layout = Some(layout);
+        self
+    }
+
+    /// Compute shader with a `main` entry point, destroyed once the pipeline
+    /// is built
+    pub fn shader(mut self, module: vk::ShaderModule) -> Self {
+        self.shader = Some(module);
+        self
+    }
+
+    pub fn build(self) -> VulkanResult<ComputePipeline> {
+        let shader = self.shader.expect("Missing compute shader");
+        let layout = self.pipeline_layout.expect("Missing pipeline layout");
+
+        let stage = vk::PipelineShaderStageCreateInfo::default()
+            .module(shader)
+            .name(c"main")
+            .stage(vk::ShaderStageFlags::COMPUTE);
+
+        let create_info = vk::ComputePipelineCreateInfo::default()
+            .stage(stage)
+            .layout(layout);
+
+        let pipeline = unsafe {
+            profiling::scope!("vkCreateComputePipelines");
+            self.device
+                .create_compute_pipelines(self.cache.unwrap_or(vk::PipelineCache::null()), &[create_info], None)
+                .map_err(|(_, err)| VulkanError::Unknown(err))?[0]
+        };
+
+        debug!("Create Compute Pipeline: {:#?}", create_info);
+
+        unsafe { self.device.destroy_shader_module(shader, None) };
+
+        Ok(ComputePipeline { raw: pipeline })
+    }
+}
@@ -19,6 +19,10 @@ pub struct Device {
     pub(crate) props2: vk::PhysicalDeviceProperties2<'static>,
     pub(crate) driver_props: vk::PhysicalDeviceDriverProperties<'static>,
     pub(crate) queue_family_props: Vec<vk::QueueFamilyProperties>,
+    /// Loaded when `VK_KHR_draw_indirect_count` is supported
+    pub(crate) draw_indirect_count: Option<ash::khr::draw_indirect_count::Device>,
+    /// `VK_INDEX_TYPE_UINT8_KHR` is enabled
+    pub(crate) index_type_uint8: bool,
     pub(crate) raw: ash::Device,
 }
 
@@ -36,6 +40,21 @@ impl Device {
         true
     }
 
+    /// 8-bit index buffers, meshes widen `u8` indices to `u16` without it
+    pub fn index_type_uint8(&self) -> bool {
+        self.index_type_uint8
+    }
+
+    /// More than one draw per indirect call, enabled when supported
+    pub fn multi_draw_indirect(&self) -> bool {
+        self.features2.features.multi_draw_indirect == vk::TRUE
+    }
+
+    /// Non-zero `first_instance` in indirect draws, enabled when supported
+    pub fn draw_indirect_first_instance(&self) -> bool {
+        self.features2.features.draw_indirect_first_instance == vk::TRUE
+    }
+
     pub fn destroy(&mut self) {
         unsafe {
             ManuallyDrop::drop(&mut self.allocator);
@@ -53,7 +72,7 @@ impl std::ops::Deref for Device {
 }
 
 impl Device {
-    pub fn get_device_extensions(instance: &Instance, phys_dev: &PhysicalDevice) -> VulkanResult<HashSet<&'static CStr>> {
+    pub fn get_device_extensions(instance: &Instance, phys_dev: &PhysicalDevice, present: bool) -> VulkanResult<HashSet<&'static CStr>> {
         let mut extensions = HashSet::new();
 
         let available_extensions = unsafe {
@@ -74,13 +93,16 @@ impl Device {
 
         debug!("Available device extension: {:#?}", available_extension_names);
 
-        let required_extensions = [
-            c"VK_KHR_swapchain",
+        let mut required_extensions = vec![
             c"VK_EXT_descriptor_indexing",
             c"VK_KHR_driver_properties",
             c"VK_KHR_synchronization2",
         ];
 
+        if present {
+            required_extensions.push(c"VK_KHR_swapchain");
+        }
+
         for i in required_extensions {
             if !available_extension_names.contains(&i) {
                 return Err(VulkanError::LogicalDevice(crate::core::LogicalDeviceError::MissingRequiredExtension(
@@ -94,6 +116,11 @@ impl Device {
         let optional_extensions: Vec<Vec<&'static CStr>> = vec![
             // Buffer Device Address
             vec![c"VK_KHR_buffer_device_address", c"VK_KHR_device_group"],
+            // GPU-driven draws with a count written by shaders
+            vec![c"VK_KHR_draw_indirect_count"],
+            // 8-bit mesh indices
+            vec![c"VK_KHR_index_type_uint8"],
+            vec![c"VK_EXT_index_type_uint8"],
         ];
 
         for i in &optional_extensions {
@@ -149,8 +176,9 @@ impl Device {
         props2
     }
 
-    pub fn new(instance: &Instance, phys_dev: &PhysicalDevice) -> VulkanResult<Self> {
-        let extensions = Self::get_device_extensions(instance, phys_dev)?;
+    /// Create logical device, `present` requires swapchain support
+    pub fn new(instance: &Instance, phys_dev: &PhysicalDevice, present: bool) -> VulkanResult<Self> {
+        let extensions = Self::get_device_extensions(instance, phys_dev, present)?;
         let p_extensions = extensions
             .iter()
             .map(|p| p.as_ptr().cast::<i8>())
@@ -192,11 +220,37 @@ impl Device {
             .descriptor_binding_uniform_buffer_update_after_bind(true)
             .runtime_descriptor_array(true);
 
-        let create_info = vk::DeviceCreateInfo::default()
+        // Indirect draws of the GPU scene, only enabled when supported
+        let supported = unsafe { instance.raw.get_physical_device_features(phys_dev.raw) };
+        let features = vk::PhysicalDeviceFeatures::default()
+            .multi_draw_indirect(supported.multi_draw_indirect == vk::TRUE)
+            .draw_indirect_first_instance(supported.draw_indirect_first_instance == vk::TRUE);
+
+        // KHR and EXT share the feature struct
+        let uint8_extension = extensions.contains(c"VK_KHR_index_type_uint8") || extensions.contains(c"VK_EXT_index_type_uint8");
+        let mut uint8_supported = vk::PhysicalDeviceIndexTypeUint8FeaturesKHR::default();
+        if uint8_extension {
+            let mut features2 = vk::PhysicalDeviceFeatures2::default().push_next(&mut uint8_supported);
+            unsafe {
+                profiling::scope!("vkGetPhysicalDeviceFeatures2");
+                instance
+                    .raw
+                    .get_physical_device_features2(phys_dev.raw, &mut features2);
+            }
+        }
+        let index_type_uint8 = uint8_supported.index_type_uint8 == vk::TRUE;
+        let mut uint8_features = vk::PhysicalDeviceIndexTypeUint8FeaturesKHR::default().index_type_uint8(index_type_uint8);
+
+        let mut create_info = vk::DeviceCreateInfo::default()
             .queue_create_infos(&queue_infos)
             .enabled_extension_names(&p_extensions)
+            .enabled_features(&features)
             .push_next(&mut descriptor_indexing);
 
+        if index_type_uint8 {
+            create_info = create_info.push_next(&mut uint8_features);
+        }
+
         let device = unsafe {
             profiling::scope!("vkCreateDevice");
             instance
@@ -218,7 +272,12 @@ impl Device {
             Allocator::new(&create_info).unwrap()
         };
 
-        let features2 = Self::get_features2(instance, phys_dev);
+        let mut features2 = Self::get_features2(instance, phys_dev);
+        features2.features = features;
+
+        let draw_indirect_count = extensions
+            .contains(c"VK_KHR_draw_indirect_count")
+            .then(|| ash::khr::draw_indirect_count::Device::new(&instance.raw, &device));
 
         let driver_props = Self::get_driver_props(instance, phys_dev);
         let props2 = Self::get_props2(instance, phys_dev);
@@ -241,6 +300,8 @@ impl Device {
             features2,
             allocator: ManuallyDrop::new(Mutex::new(allocator)),
             queue_family_props,
+            draw_indirect_count,
+            index_type_uint8,
         })
     }
 }
@@ -0,0 +1,31 @@
+use ash::vk;
+use thiserror::Error;
+
+/// Errors of frame capture
+#[derive(Debug, Error)]
+pub enum CaptureError {
+    /// Frame finished without the capture it was asked for
+    #[error("Frame finished without a capture")]
+    NoCapture,
+    /// Surface does not allow copying from swapchain images
+    #[error("Swapchain images cannot be used as a transfer source on this surface")]
+    TransferNotSupported,
+    /// Captured image has a format without conversion to RGBA8
+    #[error("Capture of format {0:?} is not supported")]
+    UnsupportedFormat(vk::Format),
+    /// File extension does not match any known image format
+    #[error("Unknown image file extension: {0:?}")]
+    UnknownExtension(String),
+    /// Image format is known, but its encoder is disabled
+    #[error("Image file extension {0:?} requires the `{0}` feature")]
+    MissingFeature(&'static str),
+    /// Error while reading or writing the file
+    #[error("Error access image file: {0}")]
+    Io(std::io::Error),
+    /// Error from the image encoder
+    #[error("Error encode image: {0}")]
+    Encode(String),
+    /// Image file is malformed or uses an unsupported layout
+    #[error("Error decode image: {0}")]
+    Decode(String),
+}
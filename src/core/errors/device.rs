@@ -7,4 +7,6 @@ pub enum LogicalDeviceError {
     CreateDevice(vk::Result),
     #[error("Required Vulkan extension not available: {0}")]
     MissingRequiredExtension(String),
+    #[error("Required Vulkan feature not available: {0}")]
+    MissingFeature(&'static str),
 }
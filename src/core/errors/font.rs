@@ -0,0 +1,12 @@
+use thiserror::Error;
+
+/// Errors of font loading
+#[derive(Debug, Error)]
+pub enum FontError {
+    /// Font bytes are not a TrueType or OpenType font
+    #[error("Error parse font: {0}")]
+    Parse(String),
+    /// Font has no outline for any requested character
+    #[error("Font has no glyphs for the requested characters")]
+    NoGlyphs,
+}
@@ -31,6 +31,12 @@ pub use command_pool::CommandPoolError;
 
 pub mod surface;
 pub use surface::SurfaceError;
+
+pub mod capture;
+pub use capture::CaptureError;
+
+pub mod font;
+pub use font::FontError;
 use thiserror::Error;
 
 #[derive(Debug, Error)]
@@ -57,6 +63,12 @@ pub enum VulkanError {
     Surface(SurfaceError),
     #[error("RenderPass error: {0}")]
     RenderPass(RenderPassError),
+    /// Frame capture is unsupported or its image could not be saved
+    #[error("Capture error: {0}")]
+    Capture(CaptureError),
+    /// Font could not be parsed or has none of the requested glyphs
+    #[error("Font error: {0}")]
+    Font(FontError),
     #[error("Unknown error")]
     Unknown(vk::Result),
 }
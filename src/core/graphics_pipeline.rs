@@ -8,6 +8,18 @@ pub struct GraphicsPipeline {
     pub raw: vk::Pipeline,
 }
 
+impl GraphicsPipeline {
+    pub fn destroy(&self, device: &Device) {
+        unsafe {
+            device.destroy_pipeline(self.raw, None);
+        }
+        debug!(
+            handle = ?self.raw,
+            "Graphics Pipeline destroyed"
+        );
+    }
+}
+
 pub struct GraphicsPipelineBuilder<'n> {
     device: &'n Device,
     pipeline_layout: Option<vk::PipelineLayout>,
@@ -19,6 +31,7 @@ pub struct GraphicsPipelineBuilder<'n> {
     input_assembly_info: Option<vk::PipelineInputAssemblyStateCreateInfo<'n>>,
     multisampling_info: Option<vk::PipelineMultisampleStateCreateInfo<'n>>,
     rasterization: Option<vk::PipelineRasterizationStateCreateInfo<'n>>,
+    depth_stencil: Option<vk::PipelineDepthStencilStateCreateInfo<'n>>,
     viewport: Option<Vec<vk::Viewport>>,
     scissors: Option<Vec<vk::Rect2D>>,
     dynamic_state: Option<Vec<vk::DynamicState>>,
@@ -39,6 +52,7 @@ impl<'n> GraphicsPipelineBuilder<'n> {
             input_assembly_info: None,
             multisampling_info: None,
             rasterization: None,
+            depth_stencil: None,
             fragment_shader: None,
             vertex_shader: None,
             scissors: None,
@@ -58,6 +72,15 @@ impl<'n> GraphicsPipelineBuilder<'n> {
         self
     }
 
+    /// Depth test without writes against reversed-Z depth by default
+    ///
+    /// Reversed-Z compares with `GREATER_OR_EQUAL` against depth cleared to
+    /// 0, near fragments have depth close to 1
+    pub fn depth_stencil(mut self, depth_stencil: vk::PipelineDepthStencilStateCreateInfo<'static>) -> Self {
+        self.depth_stencil = Some(depth_stencil);
+        self
+    }
+
     pub fn multisampling(mut self, multisampling: vk::PipelineMultisampleStateCreateInfo<'static>) -> Self {
         self.multisampling_info = Some(multisampling);
         self
@@ -179,14 +202,16 @@ impl<'n> GraphicsPipelineBuilder<'n> {
         let layout = self.pipeline_layout.unwrap();
         let render_pass = self.render_pass.unwrap();
 
-        let depth_stencil_state = vk::PipelineDepthStencilStateCreateInfo::default()
-            .depth_test_enable(true)
-            .depth_write_enable(false)
-            .depth_compare_op(vk::CompareOp::LESS)
-            .depth_bounds_test_enable(false)
-            .min_depth_bounds(0.0)
-            .max_depth_bounds(1.0)
-            .stencil_test_enable(false);
+        let depth_stencil_state = self.depth_stencil.unwrap_or(
+            vk::PipelineDepthStencilStateCreateInfo::default()
+                .depth_test_enable(true)
+                .depth_write_enable(false)
+                .depth_compare_op(vk::CompareOp::GREATER_OR_EQUAL)
+                .depth_bounds_test_enable(false)
+                .min_depth_bounds(0.0)
+                .max_depth_bounds(1.0)
+                .stencil_test_enable(false),
+        );
 
         create_info = create_info
             .vertex_input_state(&vertex_input_info)
@@ -48,7 +48,7 @@ impl Instance {
         true
     }
 
-    fn get_instance_extensions(window: &winit::window::Window, app: &App) -> VulkanResult<HashSet<&'static CStr>> {
+    fn get_instance_extensions(window: Option<&winit::window::Window>, app: &App) -> VulkanResult<HashSet<&'static CStr>> {
         let mut extensions = HashSet::new();
 
         let available_extensions = unsafe {
@@ -87,6 +87,11 @@ impl Instance {
             }
         }
 
+        // Headless rendering does not need any surface extensions
+        let Some(window) = window else {
+            return Ok(extensions);
+        };
+
         let window_extensions = ash_window::enumerate_required_extensions(
             window
                 .display_handle()
@@ -145,7 +150,7 @@ impl Instance {
         Ok(layers)
     }
 
-    pub fn new(window: &winit::window::Window, app: &App) -> VulkanResult<Instance> {
+    pub fn new(window: Option<&winit::window::Window>, app: &App) -> VulkanResult<Instance> {
         let layers = Self::get_instance_layers(app)?;
         let p_layers = layers
             .iter()
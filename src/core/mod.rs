@@ -54,6 +54,9 @@ pub use errors::*;
 mod graphics_pipeline;
 pub use graphics_pipeline::*;
 
+mod compute_pipeline;
+pub use compute_pipeline::*;
+
 mod semaphore;
 pub use semaphore::*;
 
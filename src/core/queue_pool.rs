@@ -18,20 +18,20 @@ pub struct Queue {
 }
 
 impl QueuePool {
-    pub fn new(device: &ash::Device, phys_dev: &vk::PhysicalDevice, surface: &Surface, props: &[vk::QueueFamilyProperties]) -> Self {
+    pub fn new(device: &ash::Device, phys_dev: &vk::PhysicalDevice, surface: Option<&Surface>, props: &[vk::QueueFamilyProperties]) -> Self {
         let mut queues = vec![];
 
         for (family_index, prop) in props.iter().enumerate() {
             let mut queue_family = vec![];
             for queue_index in 0..prop.queue_count {
                 let queue = unsafe { device.get_device_queue(family_index as u32, queue_index) };
-                let is_present = unsafe {
+                let is_present = surface.is_some_and(|surface| unsafe {
                     surface
                         .loader
                         .get_physical_device_surface_support(*phys_dev, family_index as u32, surface.raw)
                         .map_err(VulkanError::Unknown)
                         .unwrap()
-                };
+                });
                 queue_family.push(Queue {
                     raw: queue,
                     flags: prop.queue_flags,
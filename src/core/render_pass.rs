@@ -58,7 +58,8 @@ impl<'a> RenderPassBuilder<'a> {
                     .format(depth)
                     .samples(vk::SampleCountFlags::TYPE_1)
                     .load_op(vk::AttachmentLoadOp::CLEAR)
-                    .store_op(vk::AttachmentStoreOp::DONT_CARE)
+                    // Kept for the render passes of RenderPassBuilder::resume
+                    .store_op(vk::AttachmentStoreOp::STORE)
                     .stencil_load_op(vk::AttachmentLoadOp::DONT_CARE)
                     .stencil_store_op(vk::AttachmentStoreOp::DONT_CARE)
                     .initial_layout(vk::ImageLayout::UNDEFINED)
@@ -77,6 +78,88 @@ impl<'a> RenderPassBuilder<'a> {
         }
     }
 
+    /// Render pass compatible with [`RenderPassBuilder::default`] which
+    /// keeps the content of both attachments, to continue drawing after the
+    /// previous render pass ended
+    ///
+    /// With `read_only_depth` the depth buffer can also be sampled by
+    /// shaders, but not written
+    pub fn resume(device: &'a Device, color: vk::Format, depth: vk::Format, read_only_depth: bool) -> Self {
+        let depth_layout = match read_only_depth {
+            true => vk::ImageLayout::DEPTH_STENCIL_READ_ONLY_OPTIMAL,
+            false => vk::ImageLayout::DEPTH_STENCIL_ATTACHMENT_OPTIMAL,
+        };
+
+        let subpass = Subpass::new(
+            SubpassDesc::empty()
+                .add_color_attachment_ref(
+                    vk::AttachmentReference::default()
+                        .attachment(0)
+                        .layout(vk::ImageLayout::COLOR_ATTACHMENT_OPTIMAL),
+                )
+                .add_depth_attachment_ref(vk::AttachmentReference::default().attachment(1).layout(depth_layout))
+                .with_bind_point(vk::PipelineBindPoint::GRAPHICS),
+        );
+
+        RenderPassBuilder {
+            device,
+            attachments: Some(vec![
+                vk::AttachmentDescription::default()
+                    .format(color)
+                    .samples(vk::SampleCountFlags::TYPE_1)
+                    .load_op(vk::AttachmentLoadOp::LOAD)
+                    .store_op(vk::AttachmentStoreOp::STORE)
+                    .stencil_load_op(vk::AttachmentLoadOp::DONT_CARE)
+                    .stencil_store_op(vk::AttachmentStoreOp::DONT_CARE)
+                    .initial_layout(vk::ImageLayout::PRESENT_SRC_KHR)
+                    .final_layout(vk::ImageLayout::PRESENT_SRC_KHR),
+                vk::AttachmentDescription::default()
+                    .format(depth)
+                    .samples(vk::SampleCountFlags::TYPE_1)
+                    .load_op(vk::AttachmentLoadOp::LOAD)
+                    .store_op(vk::AttachmentStoreOp::STORE)
+                    .stencil_load_op(vk::AttachmentLoadOp::DONT_CARE)
+                    .stencil_store_op(vk::AttachmentStoreOp::DONT_CARE)
+                    .initial_layout(vk::ImageLayout::DEPTH_STENCIL_ATTACHMENT_OPTIMAL)
+                    .final_layout(vk::ImageLayout::DEPTH_STENCIL_ATTACHMENT_OPTIMAL),
+            ]),
+            // Draws and depth reads of the previous render pass finish
+            // before this one reads or overwrites the attachments
+            dependencies: Some(vec![vk::SubpassDependency {
+                src_subpass: vk::SUBPASS_EXTERNAL,
+                dst_subpass: 0,
+                src_stage_mask: vk::PipelineStageFlags::COLOR_ATTACHMENT_OUTPUT
+                    | vk::PipelineStageFlags::LATE_FRAGMENT_TESTS
+                    | vk::PipelineStageFlags::FRAGMENT_SHADER,
+                dst_stage_mask: vk::PipelineStageFlags::COLOR_ATTACHMENT_OUTPUT
+                    | vk::PipelineStageFlags::EARLY_FRAGMENT_TESTS
+                    | vk::PipelineStageFlags::FRAGMENT_SHADER,
+                src_access_mask: vk::AccessFlags::COLOR_ATTACHMENT_WRITE | vk::AccessFlags::DEPTH_STENCIL_ATTACHMENT_WRITE,
+                dst_access_mask: vk::AccessFlags::COLOR_ATTACHMENT_READ
+                    | vk::AccessFlags::COLOR_ATTACHMENT_WRITE
+                    | vk::AccessFlags::DEPTH_STENCIL_ATTACHMENT_READ
+                    | vk::AccessFlags::DEPTH_STENCIL_ATTACHMENT_WRITE
+                    | vk::AccessFlags::SHADER_READ,
+                // Shaders may sample the depth of other pixels
+                dependency_flags: vk::DependencyFlags::empty(),
+            }]),
+            subpasses: Some(vec![subpass]),
+        }
+    }
+
+    /// Layout of the color attachment after the render pass,
+    /// [`vk::ImageLayout::PRESENT_SRC_KHR`] by default. Resumed render passes
+    /// also expect it before they start
+    pub fn color_final_layout(mut self, layout: vk::ImageLayout) -> Self {
+        if let Some(color) = self.attachments.as_mut().and_then(|a| a.first_mut()) {
+            color.final_layout = layout;
+            if color.load_op == vk::AttachmentLoadOp::LOAD {
+                color.initial_layout = layout;
+            }
+        }
+        self
+    }
+
     pub fn build(self) -> VulkanResult<RenderPass> {
         let device = self.device;
         let raw_subpasses = self
@@ -4,18 +4,15 @@ use tracing::debug;
 use super::{Device, VulkanError, VulkanResult};
 
 pub struct Sampler {
-    #[allow(dead_code)]
     pub raw: vk::Sampler,
 }
 
 impl Sampler {
-    #[allow(dead_code)]
     pub fn destroy(&self, device: &Device) {
         unsafe { device.destroy_sampler(self.raw, None) };
     }
 }
 
-#[allow(dead_code)]
 pub struct SamplerBuilder<'a> {
     device: &'a Device,
     anisotropy_enable: bool,
@@ -23,27 +20,63 @@ pub struct SamplerBuilder<'a> {
     address_mode_v: vk::SamplerAddressMode,
     address_mode_w: vk::SamplerAddressMode,
     border_color: vk::BorderColor,
+    filter: vk::Filter,
+    mipmap_mode: vk::SamplerMipmapMode,
 }
 
 impl<'a> SamplerBuilder<'a> {
-    pub fn repeat(device: &'a Device) -> Self {
+    /// Linear filtering of the same address mode on every axis
+    fn address_mode(device: &'a Device, address_mode: vk::SamplerAddressMode) -> Self {
         Self {
             device,
             anisotropy_enable: false,
-            address_mode_u: vk::SamplerAddressMode::REPEAT,
-            address_mode_v: vk::SamplerAddressMode::REPEAT,
-            address_mode_w: vk::SamplerAddressMode::REPEAT,
+            address_mode_u: address_mode,
+            address_mode_v: address_mode,
+            address_mode_w: address_mode,
             border_color: vk::BorderColor::FLOAT_OPAQUE_WHITE,
+            filter: vk::Filter::LINEAR,
+            mipmap_mode: vk::SamplerMipmapMode::NEAREST,
+        }
+    }
+
+    pub fn repeat(device: &'a Device) -> Self {
+        Self::address_mode(device, vk::SamplerAddressMode::REPEAT)
+    }
+
+    pub fn clamp(device: &'a Device) -> Self {
+        Self::address_mode(device, vk::SamplerAddressMode::CLAMP_TO_EDGE)
+    }
+
+    /// Reads outside the image return transparent black
+    pub fn border(device: &'a Device) -> Self {
+        Self {
+            border_color: vk::BorderColor::FLOAT_TRANSPARENT_BLACK,
+            ..Self::address_mode(device, vk::SamplerAddressMode::CLAMP_TO_BORDER)
         }
     }
 
+    /// Filter of magnification and minification
+    pub fn filter(mut self, filter: vk::Filter) -> Self {
+        self.filter = filter;
+        self
+    }
+
+    pub fn mipmap_mode(mut self, mipmap_mode: vk::SamplerMipmapMode) -> Self {
+        self.mipmap_mode = mipmap_mode;
+        self
+    }
+
     pub fn build(self) -> VulkanResult<Sampler> {
         let create_info = vk::SamplerCreateInfo::default()
             .address_mode_u(self.address_mode_u)
             .address_mode_v(self.address_mode_v)
             .address_mode_w(self.address_mode_w)
             .anisotropy_enable(self.anisotropy_enable)
-            .border_color(self.border_color);
+            .border_color(self.border_color)
+            .mag_filter(self.filter)
+            .min_filter(self.filter)
+            .mipmap_mode(self.mipmap_mode)
+            .max_lod(vk::LOD_CLAMP_NONE);
 
         let sampler = unsafe {
             profiling::scope!("vkCreateSampler");
@@ -59,6 +92,8 @@ impl<'a> SamplerBuilder<'a> {
             address_mode_w = ?self.address_mode_w,
             anisotropy_enable = self.anisotropy_enable,
             border_color = ?self.border_color,
+            filter = ?self.filter,
+            mipmap_mode = ?self.mipmap_mode,
             "Sampler created"
         );
 
@@ -16,6 +16,7 @@ pub struct SwapchainBuilder<'a> {
     present_mode: Option<vk::PresentModeKHR>,
     color_space: Option<vk::ColorSpaceKHR>,
     min_image_count: Option<u32>,
+    image_usage: Option<vk::ImageUsageFlags>,
     surface: Option<&'a Surface>,
     instance: Option<&'a Instance>,
     device: &'a Device,
@@ -31,6 +32,7 @@ impl<'a> SwapchainBuilder<'a> {
             format: None,
             present_mode: None,
             min_image_count: None,
+            image_usage: None,
             surface: None,
             instance: None,
         }
@@ -76,6 +78,11 @@ impl<'a> SwapchainBuilder<'a> {
         self
     }
 
+    pub fn image_usage(mut self, usage: vk::ImageUsageFlags) -> Self {
+        self.image_usage = Some(usage);
+        self
+    }
+
     pub fn build(self) -> VulkanResult<Swapchain> {
         let instance = self.instance.expect("Missing Instance");
         let surface = self.surface.expect("Missing Surface");
@@ -85,6 +92,7 @@ impl<'a> SwapchainBuilder<'a> {
         let format = self.format.expect("Missing Format");
         let color_space = self.color_space.expect("Missing Color space");
         let old_swapchain = self.old_swapchain.unwrap_or(vk::SwapchainKHR::null());
+        let image_usage = self.image_usage.unwrap_or(vk::ImageUsageFlags::COLOR_ATTACHMENT);
 
         let swapchain_loader = ash::khr::swapchain::Device::new(&instance.raw, &self.device.raw);
 
@@ -93,7 +101,7 @@ impl<'a> SwapchainBuilder<'a> {
             .composite_alpha(vk::CompositeAlphaFlagsKHR::OPAQUE)
             .pre_transform(vk::SurfaceTransformFlagsKHR::IDENTITY)
             .image_array_layers(1)
-            .image_usage(vk::ImageUsageFlags::COLOR_ATTACHMENT)
+            .image_usage(image_usage)
             .image_sharing_mode(vk::SharingMode::EXCLUSIVE)
             .image_format(format)
             .surface(surface.raw)
@@ -115,6 +123,7 @@ impl<'a> SwapchainBuilder<'a> {
             clipped = true,
             format = ?format,
             min_image_count = ?min_image_count,
+            image_usage = ?image_usage,
             present_mode = ?present_mode,
             color_space = ?color_space,
             old_swapchain = ?old_swapchain,
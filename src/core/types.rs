@@ -3,6 +3,8 @@ use std::mem::offset_of;
 use ash::vk;
 use bytemuck::{Pod, Zeroable};
 
+use crate::Geometry;
+
 pub trait AttributeDescriptions {
     fn attr_desc() -> Vec<vk::VertexInputAttributeDescription>;
 }
@@ -23,16 +25,24 @@ pub struct Vertex {
 }
 
 impl Vertex {
-    /// Create cube mesh
-    #[allow(dead_code)]
-    pub fn cube(_x: f32, _y: f32, _z: f32) -> Vec<Vertex> {
-        let vertices = vec![];
+    /// White box of size `x`, `y`, `z` as a triangle list without indices,
+    /// see [`Geometry::cube`] for an indexed one
+    pub fn cube(x: f32, y: f32, z: f32) -> Vec<Vertex> {
+        let cube = Geometry::cube([x, y, z]);
+        let vertices = cube.to_vertices([1.0; 3]);
 
-        vertices
+        cube.indices.iter().map(|&index| vertices[index as usize]).collect()
     }
 
-    pub fn triangle(_x: f32, _y: f32, _z: f32) -> Vec<Vertex> {
-        vec![]
+    /// White triangle of width `x` and height `y` facing `+Z` at depth `z`
+    pub fn triangle(x: f32, y: f32, z: f32) -> Vec<Vertex> {
+        [[-0.5 * x, -0.5 * y], [0.5 * x, -0.5 * y], [0.0, 0.5 * y]]
+            .map(|[px, py]| Vertex {
+                pos: [px, py, z],
+                color: [1.0; 3],
+                normal: [0.0, 0.0, 1.0],
+            })
+            .to_vec()
     }
 }
 
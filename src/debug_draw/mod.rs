@@ -0,0 +1,419 @@
+//! Lines accumulated during a frame and drawn by [`DebugPass`](crate::DebugPass)
+
+use std::f32::consts::TAU;
+
+use ash::vk;
+use bytemuck::{Pod, Zeroable};
+
+use crate::camera::Camera;
+use crate::core::{
+    read_shader_from_bytes, Device, GraphicsPipeline, GraphicsPipelineBuilder, PipelineLayout, PipelineLayoutBuilder, ShaderBuilder, VulkanResult,
+};
+use crate::math::{add, inverse, mul, scale, transform_point, Aabb, Mat4, Sphere, Vec3};
+use crate::resources::MeshBuffer;
+
+const VERTEX_SHADER: &[u8] = include_bytes!(concat!(env!("CARGO_MANIFEST_DIR"), "/shaders/spv/debug_vert.spv"));
+const FRAGMENT_SHADER: &[u8] = include_bytes!(concat!(env!("CARGO_MANIFEST_DIR"), "/shaders/spv/debug_frag.spv"));
+
+/// Segments of every circle of [`DebugDraw::sphere`]
+const CIRCLE_SEGMENTS: usize = 32;
+
+/// Depth of the far corners of [`DebugDraw::frustum`] for infinite
+/// projections, a thousand near distances away with reversed-Z
+const INFINITE_FAR_DEPTH: f32 = 1e-3;
+
+/// Vertex in the layout of `shaders/debug.vert`
+#[repr(C)]
+#[derive(Clone, Copy, Debug, PartialEq, Pod, Zeroable)]
+struct DebugVertex {
+    pos: Vec3,
+    color: [f32; 4],
+}
+
+/// Immediate-mode lines for visualizing bounds, normals, rays and frames
+///
+/// Shapes are world space with linear RGBA colors and last one frame, they
+/// are drawn where [`DebugPass`](crate::DebugPass) is added to the graph and
+/// dropped if it is not. Every call returns `self` to chain shapes
+///
+/// # Example
+/// ```ignore
+/// world
+///     .debug()
+///     .aabb(&bounds, [0.0, 1.0, 0.0, 1.0])
+///     .depth_test(false)
+///     .axes(&world.world_matrix(&transform), 0.5);
+///
+/// world.draw_frame(|graph| {
+///     graph.add_pass(scene_pass);
+///     graph.add_pass(DebugPass::new("Debug"));
+/// })?;
+/// ```
+#[derive(Default)]
+pub struct DebugDraw {
+    /// Lines hidden by geometry in front of them
+    tested: Vec<DebugVertex>,
+    /// Lines drawn over everything
+    overlay: Vec<DebugVertex>,
+    /// Shapes added from now on are drawn over everything
+    no_depth_test: bool,
+}
+
+impl DebugDraw {
+    /// Remove every shape and test depth again, keeps the allocations
+    fn clear(&mut self) {
+        self.tested.clear();
+        self.overlay.clear();
+        self.no_depth_test = false;
+    }
+
+    /// Hide the following shapes behind geometry drawn before
+    /// [`DebugPass`](crate::DebugPass), on by default
+    pub fn depth_test(&mut self, enabled: bool) -> &mut Self {
+        self.no_depth_test = !enabled;
+        self
+    }
+
+    /// Segment from `a` to `b`
+    pub fn line(&mut self, a: Vec3, b: Vec3, color: [f32; 4]) -> &mut Self {
+        let lines = match self.no_depth_test {
+            true => &mut self.overlay,
+            false => &mut self.tested,
+        };
+        lines.push(DebugVertex { pos: a, color });
+        lines.push(DebugVertex { pos: b, color });
+        self
+    }
+
+    /// Segment from `origin` along `direction`, its length is the length of
+    /// `direction`
+    pub fn ray(&mut self, origin: Vec3, direction: Vec3, color: [f32; 4]) -> &mut Self {
+        self.line(origin, add(origin, direction), color)
+    }
+
+    /// Twelve edges of the box
+    pub fn aabb(&mut self, aabb: &Aabb, color: [f32; 4]) -> &mut Self {
+        let corner = |i: usize| {
+            [
+                if i & 1 == 0 { aabb.min[0] } else { aabb.max[0] },
+                if i & 2 == 0 { aabb.min[1] } else { aabb.max[1] },
+                if i & 4 == 0 { aabb.min[2] } else { aabb.max[2] },
+            ]
+        };
+        self.box_edges(&std::array::from_fn(corner), color)
+    }
+
+    /// Circles around the sphere in the XY, YZ and XZ planes
+    pub fn sphere(&mut self, sphere: &Sphere, color: [f32; 4]) -> &mut Self {
+        let point = |axes: (usize, usize), segment: usize| {
+            let angle = segment as f32 / CIRCLE_SEGMENTS as f32 * TAU;
+            let mut offset = [0.0; 3];
+            offset[axes.0] = angle.cos() * sphere.radius;
+            offset[axes.1] = angle.sin() * sphere.radius;
+            add(sphere.center, offset)
+        };
+
+        for axes in [(0, 1), (1, 2), (0, 2)] {
+            for segment in 0..CIRCLE_SEGMENTS {
+                self.line(point(axes, segment), point(axes, segment + 1), color);
+            }
+        }
+        self
+    }
+
+    /// Local X, Y and Z axes of `transform` in red, green and blue, `size`
+    /// long before scaling
+    pub fn axes(&mut self, transform: &Mat4, size: f32) -> &mut Self {
+        let origin = transform_point(transform, [0.0; 3]);
+        let colors = [[1.0, 0.0, 0.0, 1.0], [0.0, 1.0, 0.0, 1.0], [0.0, 0.0, 1.0, 1.0]];
+
+        for (axis, color) in colors.into_iter().enumerate() {
+            let mut end = [0.0; 3];
+            end[axis] = size;
+            self.line(origin, transform_point(transform, end), color);
+        }
+        self
+    }
+
+    /// Edges of the volume `camera` sees
+    ///
+    /// Infinite perspective projections end a thousand near distances away
+    pub fn frustum(&mut self, camera: &Camera, color: [f32; 4]) -> &mut Self {
+        // Includes raw changes of the camera not uploaded yet
+        let inv_view_proj = &inverse(&mul(camera.proj(), camera.view()));
+
+        let corner = |i: usize| {
+            let x = if i & 1 == 0 { -1.0 } else { 1.0 };
+            let y = if i & 2 == 0 { -1.0 } else { 1.0 };
+            // Reversed-Z, the near plane is at depth 1
+            let depth = if i & 4 == 0 { 1.0 } else { 0.0 };
+            unproject(inv_view_proj, [x, y, depth]).unwrap_or_else(|| {
+                unproject(inv_view_proj, [x, y, INFINITE_FAR_DEPTH]).unwrap_or_default()
+            })
+        };
+        self.box_edges(&std::array::from_fn(corner), color)
+    }
+
+    /// Number of vertices waiting for the next frame
+    pub fn vertex_count(&self) -> usize {
+        self.tested.len() + self.overlay.len()
+    }
+
+    /// Edges between corners indexed by their `x | y << 1 | z << 2` bits
+    fn box_edges(&mut self, corners: &[Vec3; 8], color: [f32; 4]) -> &mut Self {
+        for i in 0..8 {
+            for bit in [1, 2, 4] {
+                if i & bit == 0 {
+                    self.line(corners[i], corners[i | bit], color);
+                }
+            }
+        }
+        self
+    }
+}
+
+/// Clip space point back to world space, `None` at infinity
+fn unproject(inv_view_proj: &Mat4, p: Vec3) -> Option<Vec3> {
+    let m = inv_view_proj;
+    let w = m[0][3] * p[0] + m[1][3] * p[1] + m[2][3] * p[2] + m[3][3];
+
+    if w.abs() <= f32::EPSILON {
+        return None;
+    }
+
+    Some(scale(transform_point(m, p), 1.0 / w))
+}
+
+/// Buffers and pipelines drawing [`DebugDraw`]
+pub(crate) struct DebugRenderer {
+    pub shapes: DebugDraw,
+    /// Tested lines followed by overlay lines
+    vertices: MeshBuffer,
+    /// Tested and overlay vertices in the buffer of every frame
+    counts: Vec<[u32; 2]>,
+    layout: PipelineLayout,
+    /// With and without depth test
+    pipelines: [GraphicsPipeline; 2],
+}
+
+impl DebugRenderer {
+    pub fn new(
+        device: &Device,
+        frame_count: usize,
+        render_pass: vk::RenderPass,
+        set_layouts: Vec<vk::DescriptorSetLayout>,
+    ) -> VulkanResult<Self> {
+        // The layout of every raster pipeline, set 1 holds the camera
+        let layout = PipelineLayoutBuilder::new(device)
+            .set_layouts(set_layouts)
+            .push_constant(vec![vk::PushConstantRange::default()
+                .offset(0)
+                .size(128)
+                .stage_flags(vk::ShaderStageFlags::VERTEX | vk::ShaderStageFlags::FRAGMENT)])
+            .build()?;
+
+        let pipelines = [
+            Self::pipeline(device, render_pass, &layout, true)?,
+            Self::pipeline(device, render_pass, &layout, false)?,
+        ];
+
+        Ok(Self {
+            shapes: DebugDraw::default(),
+            vertices: MeshBuffer::new_dynamic(vk::BufferUsageFlags::VERTEX_BUFFER, size_of::<DebugVertex>(), frame_count),
+            counts: vec![[0; 2]; frame_count],
+            layout,
+            pipelines,
+        })
+    }
+
+    fn pipeline(device: &Device, render_pass: vk::RenderPass, layout: &PipelineLayout, depth_test: bool) -> VulkanResult<GraphicsPipeline> {
+        let spv = read_shader_from_bytes(VERTEX_SHADER).expect("Invalid debug vertex shader");
+        let vertex = ShaderBuilder::new(device).bytecode(&spv).build()?;
+        let spv = read_shader_from_bytes(FRAGMENT_SHADER).expect("Invalid debug fragment shader");
+        let fragment = ShaderBuilder::new(device).bytecode(&spv).build()?;
+
+        let bindings = [vk::VertexInputBindingDescription {
+            binding: 0,
+            stride: size_of::<DebugVertex>() as u32,
+            input_rate: vk::VertexInputRate::VERTEX,
+        }];
+        let attributes = [
+            vk::VertexInputAttributeDescription {
+                location: 0,
+                binding: 0,
+                format: vk::Format::R32G32B32_SFLOAT,
+                offset: std::mem::offset_of!(DebugVertex, pos) as u32,
+            },
+            vk::VertexInputAttributeDescription {
+                location: 1,
+                binding: 0,
+                format: vk::Format::R32G32B32A32_SFLOAT,
+                offset: std::mem::offset_of!(DebugVertex, color) as u32,
+            },
+        ];
+
+        let vertex_input_info = vk::PipelineVertexInputStateCreateInfo::default()
+            .vertex_binding_descriptions(&bindings)
+            .vertex_attribute_descriptions(&attributes);
+
+        let color_blend = vk::PipelineColorBlendAttachmentState::default()
+            .color_write_mask(vk::ColorComponentFlags::RGBA)
+            .blend_enable(true)
+            .src_color_blend_factor(vk::BlendFactor::SRC_ALPHA)
+            .dst_color_blend_factor(vk::BlendFactor::ONE_MINUS_SRC_ALPHA)
+            .color_blend_op(vk::BlendOp::ADD)
+            .src_alpha_blend_factor(vk::BlendFactor::ONE)
+            .dst_alpha_blend_factor(vk::BlendFactor::ONE_MINUS_SRC_ALPHA)
+            .alpha_blend_op(vk::BlendOp::ADD);
+
+        // Lines never write depth, tested lines keep the reversed-Z compare
+        let depth_stencil = vk::PipelineDepthStencilStateCreateInfo::default()
+            .depth_test_enable(depth_test)
+            .depth_write_enable(false)
+            .depth_compare_op(vk::CompareOp::GREATER_OR_EQUAL)
+            .max_depth_bounds(1.0);
+
+        // Viewport and scissors are dynamic, set for every draw
+        GraphicsPipelineBuilder::new(device)
+            .vertex_shader(vertex.raw)
+            .fragment_shader(fragment.raw)
+            .render_pass(render_pass)
+            .pipeline_layout(layout.raw)
+            .viewport(vec![vk::Viewport::default().width(1.0).height(1.0).max_depth(1.0)])
+            .scissors(vec![vk::Rect2D::default()])
+            .input_assembly(vk::PipelineInputAssemblyStateCreateInfo::default().topology(vk::PrimitiveTopology::LINE_LIST))
+            .rasterization(
+                vk::PipelineRasterizationStateCreateInfo::default()
+                    .polygon_mode(vk::PolygonMode::FILL)
+                    .line_width(1.0)
+                    .cull_mode(vk::CullModeFlags::NONE),
+            )
+            .depth_stencil(depth_stencil)
+            .multisampling(vk::PipelineMultisampleStateCreateInfo::default().rasterization_samples(vk::SampleCountFlags::TYPE_1))
+            .color_blending(vk::PipelineColorBlendStateCreateInfo::default().attachments(&[color_blend]))
+            .dynamic_state(vec![vk::DynamicState::VIEWPORT, vk::DynamicState::SCISSOR])
+            .vertex_input_info(vertex_input_info)
+            .build()
+    }
+
+    /// Move the shapes of this frame into the buffer of `frame` and start
+    /// an empty [`DebugDraw`], the frame must not be in flight
+    pub fn update(&mut self, device: &Device, frame: usize) -> VulkanResult<()> {
+        profiling::scope!("DebugRenderer::update");
+
+        let DebugDraw { tested, overlay, .. } = &mut self.shapes;
+        self.counts[frame] = [tested.len() as u32, overlay.len() as u32];
+
+        tested.append(overlay);
+        self.vertices.replace(bytemuck::cast_slice(tested));
+        self.shapes.clear();
+
+        self.vertices.prepare(device, frame)
+    }
+
+    /// Draw the lines of `frame`, must be inside the render pass
+    ///
+    /// `sets` are the bindless and per-frame sets of the frame
+    pub(crate) unsafe fn draw(&self, device: &ash::Device, cbuf: vk::CommandBuffer, frame: usize, resolution: vk::Extent2D, sets: &[vk::DescriptorSet]) {
+        profiling::scope!("DebugRenderer::draw");
+
+        let Some(buffer) = self.vertices.raw(frame) else {
+            return;
+        };
+
+        let viewport = vk::Viewport::default()
+            .width(resolution.width as f32)
+            .height(resolution.height as f32)
+            .max_depth(1.0);
+        let scissor = vk::Rect2D::default().extent(resolution);
+
+        device.cmd_bind_descriptor_sets(cbuf, vk::PipelineBindPoint::GRAPHICS, self.layout.raw, 0, sets, &[]);
+        device.cmd_bind_vertex_buffers(cbuf, 0, &[buffer], &[0]);
+        device.cmd_set_viewport(cbuf, 0, &[viewport]);
+        device.cmd_set_scissor(cbuf, 0, &[scissor]);
+
+        let mut first = 0;
+        for (pipeline, count) in self.pipelines.iter().zip(self.counts[frame]) {
+            if count > 0 {
+                device.cmd_bind_pipeline(cbuf, vk::PipelineBindPoint::GRAPHICS, pipeline.raw);
+                device.cmd_draw(cbuf, count, 1, first, 0);
+            }
+            first += count;
+        }
+    }
+
+    pub fn destroy(&mut self, device: &Device) {
+        let vertices = std::mem::replace(&mut self.vertices, MeshBuffer::new_dynamic(vk::BufferUsageFlags::VERTEX_BUFFER, size_of::<DebugVertex>(), 0));
+        for mut buffer in vertices.into_buffers() {
+            buffer.destroy(device);
+        }
+        for pipeline in &self.pipelines {
+            pipeline.destroy(device);
+        }
+        unsafe {
+            device.destroy_pipeline_layout(self.layout.raw, None);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::math::{length, orthographic, perspective, sub};
+
+    #[test]
+    fn aabb_has_twelve_edges() {
+        let mut debug = DebugDraw::default();
+        debug.aabb(&Aabb::new([-1.0; 3], [1.0; 3]), [1.0; 4]);
+
+        assert_eq!(debug.tested.len(), 24);
+        for pair in debug.tested.chunks(2) {
+            assert_eq!(length(sub(pair[0].pos, pair[1].pos)), 2.0);
+        }
+    }
+
+    #[test]
+    fn depth_test_selects_the_list() {
+        let mut debug = DebugDraw::default();
+        debug
+            .line([0.0; 3], [1.0; 3], [1.0; 4])
+            .depth_test(false)
+            .ray([0.0; 3], [0.0, 2.0, 0.0], [1.0; 4]);
+
+        assert_eq!(debug.tested.len(), 2);
+        assert_eq!(debug.overlay[1].pos, [0.0, 2.0, 0.0]);
+        assert_eq!(debug.vertex_count(), 4);
+
+        // The next frame starts with depth testing
+        debug.clear();
+        debug.line([0.0; 3], [1.0; 3], [1.0; 4]);
+        assert_eq!((debug.tested.len(), debug.overlay.len()), (2, 0));
+    }
+
+    #[test]
+    fn sphere_circles_lie_on_the_surface() {
+        let mut debug = DebugDraw::default();
+        debug.sphere(&Sphere::new([1.0, 2.0, 3.0], 2.0), [1.0; 4]);
+
+        assert_eq!(debug.tested.len(), 3 * CIRCLE_SEGMENTS * 2);
+        for vertex in &debug.tested {
+            let distance = length(sub(vertex.pos, [1.0, 2.0, 3.0]));
+            assert!((distance - 2.0).abs() < 1e-5);
+        }
+    }
+
+    #[test]
+    fn frustum_corners_unproject() {
+        let inv = inverse(&orthographic(-2.0, 2.0, -1.0, 1.0, 0.5, 10.0));
+
+        // Reversed-Z looking down -Z, clip space Y points down
+        let near = unproject(&inv, [1.0, 1.0, 1.0]).unwrap();
+        let far = unproject(&inv, [-1.0, -1.0, 0.0]).unwrap();
+        assert!(length(sub(near, [2.0, -1.0, -0.5])) < 1e-4);
+        assert!(length(sub(far, [-2.0, 1.0, -10.0])) < 1e-4);
+
+        let infinite = inverse(&perspective(1.0, 1.0, 0.1, f32::INFINITY));
+        assert!(unproject(&infinite, [0.0, 0.0, 0.0]).is_none());
+        assert!(unproject(&infinite, [0.0, 0.0, INFINITE_FAR_DEPTH]).is_some());
+    }
+}
@@ -20,7 +20,9 @@ mod resources;
 pub use resources::*;
 
 use crate::TemporalFrameGraph;
+use crate::capture::FrameCapture;
 use crate::core::{CommandPool, CommandPoolBuilder, Device, SwapchainError, VulkanError, VulkanResult};
+use crate::math;
 use crate::render_context::RenderContext;
 use crate::resources::{Destroy, Res, Resources};
 
@@ -28,6 +30,10 @@ use crate::resources::{Destroy, Res, Resources};
 pub struct FrameGraph {
     cmd_pool: CommandPool,
     cmd_buffers: Vec<vk::CommandBuffer>,
+    pub(crate) capture: FrameCapture,
+    /// Bindless index of the depth buffer, allocated by the first pass
+    /// reading it
+    depth_texture: Option<u32>,
 }
 
 impl FrameGraph {
@@ -39,9 +45,21 @@ impl FrameGraph {
         Ok(FrameGraph {
             cmd_pool,
             cmd_buffers,
+            capture: FrameCapture::new(),
+            depth_texture: None,
         })
     }
 
+    /// Show the new depth buffer to passes reading depth, called after the
+    /// render targets were recreated and no frame is in flight
+    pub(crate) fn targets_resized(&self, ctx: &Arc<RenderContext>, resources: &Arc<Resources>) {
+        if let Some(index) = self.depth_texture {
+            resources
+                .bindless
+                .update_depth_texture(&ctx.device, index, ctx.window.read().depth_view.raw);
+        }
+    }
+
     pub(crate) fn compile(&mut self, temp: &mut TemporalFrameGraph<'_>, _ctx: &Arc<RenderContext>, _resources: &Arc<Resources>) -> VulkanResult<()> {
     profiling::scope!("FrameGraph::compile");
 
@@ -59,13 +77,18 @@ impl FrameGraph {
 
     pub(crate) fn execute(&mut self, temp: &mut TemporalFrameGraph<'_>, ctx: &Arc<RenderContext>, resources: &Arc<Resources>) -> VulkanResult<()> {
         profiling::scope!("FrameGraph::execute");
-        let queue = ctx.device.queue_pool.get_present().unwrap();
+        let queue = match ctx.is_headless() {
+            true => ctx.device.queue_pool.graphics(),
+            false => ctx.device.queue_pool.get_present(),
+        }
+        .unwrap();
         let device = &ctx.device;
 
         // ------------------------Acquire Next Image-----------------------------
-        let image_index = {
+        let (image_index, frame, frame_index, resolution) = {
             let window = &ctx.window.try_read().expect("Error borrowed Window for read");
-            let sync = &window.frame_sync[window.current_frame % window.frame_sync.len()];
+            let frame = window.current_frame % window.frame_sync.len();
+            let sync = &window.frame_sync[frame];
 
             // Wait fence for next frame or skip frame
             unsafe {
@@ -80,104 +103,221 @@ impl FrameGraph {
             }
 
             // Get image index or skip a frame
-            unsafe {
-                match window
-                    .swapchain
-                    .loader
-                    .acquire_next_image(window.swapchain.raw, u64::MAX, sync.image_available.raw, vk::Fence::null())
-                {
-                    Ok((index, _)) => index,
-                    Err(vk::Result::ERROR_OUT_OF_DATE_KHR) => {
-                        return Err(VulkanError::Swapchain(SwapchainError::SwapchainOutOfDateKhr));
-                    },
-                    Err(e) => {
-                        return Err(VulkanError::Unknown(e));
-                    },
-                }
-            }
+            let image_index = match &window.swapchain {
+                Some(swapchain) => unsafe {
+                    match swapchain
+                        .loader
+                        .acquire_next_image(swapchain.raw, u64::MAX, sync.image_available.raw, vk::Fence::null())
+                    {
+                        Ok((index, _)) => index,
+                        Err(vk::Result::ERROR_OUT_OF_DATE_KHR) => {
+                            return Err(VulkanError::Swapchain(SwapchainError::SwapchainOutOfDateKhr));
+                        },
+                        Err(e) => {
+                            return Err(VulkanError::Unknown(e));
+                        },
+                    }
+                },
+                // Headless images are used in turn, the fence guards them
+                None => (window.current_frame % window.images.len()) as u32,
+            };
+
+            (image_index, frame, window.current_frame as u32, window.resolution)
         };
 
+        // ------------------------Update Per-Frame Data-----------------------------
+        // Buffers of this frame are no longer read by the GPU after the fence
+        resources.camera.write().update(frame)?;
+        resources.frame_values.write().update(frame, frame_index, resolution)?;
+        {
+            let mut transforms = resources.transforms.write();
+            transforms.remove_released(std::mem::take(&mut *resources.released_transforms.lock()));
+            transforms.update(device, &resources.per_frame, frame)?;
+        }
+        resources.instances.write().update(device, frame)?;
+        resources.meshes.write().update(device, frame)?;
+        resources.polylines.write().update(device, frame)?;
+        resources.height_fields.write().update(device, frame)?;
+        resources.point_clouds.write().update(device);
+        resources.textures.write().update(device, &resources.bindless);
+        resources.isosurfaces.write().update(device);
+        resources.uploads.write().update(device);
+        #[cfg(feature = "text")]
+        resources.text.write().update(device, &resources.bindless, frame)?;
+        resources.debug.write().update(device, frame)?;
+        resources.canvas.write().update(device, frame)?;
+        resources
+            .scene
+            .write()
+            .update(device, &resources.per_frame, &resources.transforms.read(), frame)?;
+        #[cfg(feature = "egui")]
+        resources.egui.write().update(device, &resources.bindless);
+
         let cmd_buffer = self.cmd_buffers[image_index as usize];
 
         // ------------------------Record Command Buffers-----------------------------
         {
             let window = ctx.window.read();
             let resolution = window.resolution;
+            let frame_buffer = &window.frame_buffers[image_index as usize];
+
+            let clear_values = vec![
+                ClearValue {
+                    color: vk::ClearColorValue {
+                        float32: [0.2, 0.2, 0.2, 1.0],
+                    },
+                },
+                // Reversed-Z, the far plane is at depth 0
+                ClearValue {
+                    depth_stencil: vk::ClearDepthStencilValue { depth: 0.0, stencil: 0 },
+                },
+            ];
+
+            unsafe {
+                device
+                    .reset_command_buffer(cmd_buffer, vk::CommandBufferResetFlags::empty())
+                    .map_err(VulkanError::Unknown)?;
+
+                let begin_info = vk::CommandBufferBeginInfo::default().flags(vk::CommandBufferUsageFlags::ONE_TIME_SUBMIT);
+
+                device
+                    .begin_command_buffer(cmd_buffer, &begin_info)
+                    .map_err(VulkanError::Unknown)?;
+            }
+
+            // Indirect draws of the GPU scene are written before any pass
+            unsafe {
+                let sets = [resources.bindless_set(), resources.per_frame_set(frame)];
+                resources.scene.read().record_cull(device, cmd_buffer, frame, &sets);
+            }
+
+            // Pixels of textures created since the last frame
+            unsafe {
+                resources.uploads.write().record(device, cmd_buffer, ctx.frame_count());
+            }
+
+            // Isosurfaces read the textures uploaded above
+            unsafe {
+                let sets = [resources.bindless_set(), resources.per_frame_set(frame)];
+                resources.isosurfaces.write().record_extract(device, cmd_buffer, &sets);
+            }
+
+            // Textures egui changed since the last frame
+            #[cfg(feature = "egui")]
+            unsafe {
+                resources.egui.write().record_uploads(device, cmd_buffer);
+            }
+
+            let render_area = vk::Rect2D {
+                offset: vk::Offset2D { x: 0, y: 0 },
+                extent: resolution,
+            };
+
+            let render_pass_begin_info = vk::RenderPassBeginInfo::default()
+                .render_pass(window.render_pass.raw)
+                .framebuffer(frame_buffer.raw)
+                .render_area(render_area)
+                .clear_values(&clear_values);
+
+            let reads_depth = temp
+                .passes
+                .iter()
+                .any(|pass| matches!(pass, Pass::Present(pass) if pass.reads_depth));
+            let depth_texture = match reads_depth {
+                true => Some(
+                    *self
+                        .depth_texture
+                        .get_or_insert_with(|| resources.bindless.alloc_depth_texture(device, window.depth_view.raw)),
+                ),
+                false => None,
+            };
+
+            // The back buffer is always cleared and transitioned for present,
+            // even if the graph has no passes
+            unsafe {
+                device.cmd_begin_render_pass(cmd_buffer, &render_pass_begin_info, vk::SubpassContents::INLINE);
+            }
+
+            let mut depth_read_only = false;
+
             for pass in temp.passes.drain(..) {
-                
                 match pass {
                     Pass::Present(pass) => {
-                        let frame_buffer = &window.frame_buffers[image_index as usize];
-
-                        let clear_values = vec![
-                            ClearValue {
-                                color: vk::ClearColorValue {
-                                    float32: [0.2, 0.2, 0.2, 1.0],
-                                },
-                            },
-                            ClearValue {
-                                depth_stencil: vk::ClearDepthStencilValue { depth: 1.0, stencil: 0 },
-                            },
-                        ];
-
-                        unsafe {
-                            device
-                                .reset_command_buffer(cmd_buffer, vk::CommandBufferResetFlags::empty())
-                                .map_err(VulkanError::Unknown)?;
-
-                            let begin_info = vk::CommandBufferBeginInfo::default().flags(vk::CommandBufferUsageFlags::ONE_TIME_SUBMIT);
-
-                            device
-                                .begin_command_buffer(cmd_buffer, &begin_info)
-                                .map_err(VulkanError::Unknown)?;
-                        }
-
-                        let render_pass_begin_info = vk::RenderPassBeginInfo::default()
-                            .render_pass(window.render_pass.raw)
-                            .framebuffer(frame_buffer.raw)
-                            .render_area(vk::Rect2D {
-                                offset: vk::Offset2D { x: 0, y: 0 },
-                                extent: resolution,
-                            })
-                            .clear_values(&clear_values);
-
-                        unsafe {
-                            device.cmd_begin_render_pass(cmd_buffer, &render_pass_begin_info, vk::SubpassContents::INLINE);
+                        // Depth is only sampled while read-only, the render
+                        // pass is continued with the layout the pass needs
+                        if pass.reads_depth != depth_read_only {
+                            let render_pass = match pass.reads_depth {
+                                true => &window.depth_read_pass,
+                                false => &window.resume_pass,
+                            };
+                            let begin_info = vk::RenderPassBeginInfo::default()
+                                .render_pass(render_pass.raw)
+                                .framebuffer(frame_buffer.raw)
+                                .render_area(render_area);
+
+                            unsafe {
+                                device.cmd_end_render_pass(cmd_buffer);
+                                device.cmd_begin_render_pass(cmd_buffer, &begin_info, vk::SubpassContents::INLINE);
+                            }
+                            depth_read_only = pass.reads_depth;
                         }
 
                         let mut pass_ctx = PassContext {
                             layout: None,
                             external_resources: resources.clone(),
                             resolution,
+                            frame,
+                            transform_idx: 0,
+                            tex_idx: [0; 8],
+                            depth_texture: depth_texture.filter(|_| pass.reads_depth),
+                            world: math::IDENTITY,
+                            bound_transform: None,
+                            lod_threshold: 1.0,
                             device: ctx.device.raw.clone(),
                             cbuf: cmd_buffer,
+                            render_context: ctx.clone(),
                         };
 
                         (pass.execute)(&mut pass_ctx);
-
-                        unsafe {
-                            device.cmd_end_render_pass(cmd_buffer);
-                        }
-
-                        unsafe {
-                            device
-                                .end_command_buffer(cmd_buffer)
-                                .map_err(VulkanError::Unknown)?;
-                        }
                     },
                     Pass::Raster(_pass) => {},
                     Pass::Compute(_pass) => {}
                 }
             }
+
+            unsafe {
+                device.cmd_end_render_pass(cmd_buffer);
+            }
+
+            self.capture.record(
+                device,
+                cmd_buffer,
+                window.images[image_index as usize],
+                window.final_layout,
+                resolution,
+                window.image_usage,
+            )?;
+
+            unsafe {
+                device
+                    .end_command_buffer(cmd_buffer)
+                    .map_err(VulkanError::Unknown)?;
+            }
         }
 
         let mut window = ctx.window.try_write().expect("Window already borrowed mutably");
         let sync = &window.frame_sync[window.current_frame % window.frame_sync.len()];
 
         // -----------------------Submit-----------------------------
-        let wait_semaphores = [sync.image_available.raw];
-        let wait_stages = [vk::PipelineStageFlags::COLOR_ATTACHMENT_OUTPUT];
-        let signal_semaphores = [sync.render_finished.raw];
+        // Nothing is acquired or presented without a swapchain
+        let (wait_semaphores, wait_stages, signal_semaphores) = match window.swapchain {
+            Some(_) => (
+                vec![sync.image_available.raw],
+                vec![vk::PipelineStageFlags::COLOR_ATTACHMENT_OUTPUT],
+                vec![sync.render_finished.raw],
+            ),
+            None => (vec![], vec![], vec![]),
+        };
 
         let binding = [cmd_buffer];
 
@@ -195,21 +335,31 @@ impl FrameGraph {
         }
 
         // -----------------------Present-----------------------------
-        let swapchain = [window.swapchain.raw];
-        let image_indices = [image_index];
+        if let Some(swapchain) = &window.swapchain {
+            let swapchains = [swapchain.raw];
+            let image_indices = [image_index];
 
-        let present_info = vk::PresentInfoKHR::default()
-            .wait_semaphores(&signal_semaphores)
-            .swapchains(&swapchain)
-            .image_indices(&image_indices);
+            let present_info = vk::PresentInfoKHR::default()
+                .wait_semaphores(&signal_semaphores)
+                .swapchains(&swapchains)
+                .image_indices(&image_indices);
 
-        unsafe {
-            profiling::scope!("vkQueuePresent");
-            window
-                .swapchain
-                .loader
-                .queue_present(queue.raw, &present_info)
-                .map_err(VulkanError::Unknown)?;
+            unsafe {
+                profiling::scope!("vkQueuePresent");
+                swapchain
+                    .loader
+                    .queue_present(queue.raw, &present_info)
+                    .map_err(VulkanError::Unknown)?;
+            }
+        }
+
+        if self.capture.is_in_flight() {
+            unsafe {
+                device
+                    .wait_for_fences(&[sync.in_flight_fence.raw], true, u64::MAX)
+                    .map_err(VulkanError::Unknown)?;
+            }
+            self.capture.finish(window.resolution, window.format);
         }
 
         trace!(
@@ -222,6 +372,7 @@ impl FrameGraph {
     }
 
     pub(crate) fn destroy(&mut self, device: &Device) {
+        self.capture.destroy(device);
         self.cmd_pool.destroy(device);
     }
 }
@@ -0,0 +1,48 @@
+use super::{Pass, PassContext, PresentPass};
+
+/// Draws the shapes of [`WorldRenderer::canvas`] over everything drawn
+/// before it
+///
+/// # Example
+/// ```ignore
+/// world.canvas().stroke(&Path::rect([10.0, 10.0], [200.0, 100.0]), &Stroke::new(1.0), [1.0; 4]);
+///
+/// world.draw_frame(|graph| {
+///     graph.add_pass(scene_pass);
+///     graph.add_pass(CanvasPass::new("Canvas"));
+/// })?;
+/// ```
+///
+/// [`WorldRenderer::canvas`]: crate::WorldRenderer::canvas
+pub struct CanvasPass {
+    name: String,
+}
+
+impl CanvasPass {
+    /// Pass drawing the shapes of this frame
+    pub fn new<S: Into<String>>(name: S) -> Self {
+        Self { name: name.into() }
+    }
+}
+
+impl<'frame> From<CanvasPass> for Pass<'frame> {
+    fn from(pass: CanvasPass) -> Self {
+        Pass::Present(PresentPass {
+            name: pass.name,
+            reads: vec![],
+            reads_depth: false,
+            execute: Box::new(move |ctx: &mut PassContext| {
+                profiling::scope!("CanvasPass");
+
+                let resources = &ctx.external_resources;
+                let sets = [resources.bindless_set(), resources.per_frame_set(ctx.frame)];
+                unsafe {
+                    resources
+                        .canvas
+                        .read()
+                        .draw(&ctx.device, ctx.cbuf, ctx.frame, ctx.resolution, &sets);
+                }
+            }),
+        })
+    }
+}
@@ -0,0 +1,50 @@
+use super::{Pass, PassContext, PresentPass};
+
+/// Draws the shapes of [`WorldRenderer::debug`] over everything drawn before
+/// it
+///
+/// Shapes with depth test are hidden by geometry drawn by earlier passes
+///
+/// # Example
+/// ```ignore
+/// world.debug().sphere(&bounds, [1.0, 1.0, 0.0, 1.0]);
+///
+/// world.draw_frame(|graph| {
+///     graph.add_pass(scene_pass);
+///     graph.add_pass(DebugPass::new("Debug"));
+/// })?;
+/// ```
+///
+/// [`WorldRenderer::debug`]: crate::WorldRenderer::debug
+pub struct DebugPass {
+    name: String,
+}
+
+impl DebugPass {
+    /// Pass drawing the shapes of this frame
+    pub fn new<S: Into<String>>(name: S) -> Self {
+        Self { name: name.into() }
+    }
+}
+
+impl<'frame> From<DebugPass> for Pass<'frame> {
+    fn from(pass: DebugPass) -> Self {
+        Pass::Present(PresentPass {
+            name: pass.name,
+            reads: vec![],
+            reads_depth: false,
+            execute: Box::new(move |ctx: &mut PassContext| {
+                profiling::scope!("DebugPass");
+
+                let resources = &ctx.external_resources;
+                let sets = [resources.bindless_set(), resources.per_frame_set(ctx.frame)];
+                unsafe {
+                    resources
+                        .debug
+                        .read()
+                        .draw(&ctx.device, ctx.cbuf, ctx.frame, ctx.resolution, &sets);
+                }
+            }),
+        })
+    }
+}
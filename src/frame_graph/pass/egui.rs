@@ -0,0 +1,82 @@
+use egui::ClippedPrimitive;
+use tracing::error;
+
+use super::{Pass, PassContext, PresentPass};
+
+/// Draws the output of egui over everything drawn before it
+///
+/// Textures of the output are uploaded with
+/// [`WorldRenderer::update_egui_textures`] before the frame is drawn.
+/// `TextureId::User(index)` shows `textures[index]` of the bindless set, so
+/// images rendered by aluminium can be placed in egui panels. A frame holds
+/// at most one `EguiPass`, the whole egui output is drawn by it
+///
+/// # Example
+/// ```ignore
+/// let output = egui_ctx.run_ui(raw_input, |ui| inspector.ui(ui));
+/// let primitives = egui_ctx.tessellate(output.shapes, output.pixels_per_point);
+///
+/// world.update_egui_textures(&output.textures_delta)?;
+/// world.draw_frame(|graph| {
+///     graph.add_pass(scene_pass);
+///     graph.add_pass(EguiPass::new("Ui", &primitives, output.pixels_per_point));
+/// })?;
+/// ```
+///
+/// # Panics
+/// - if a second `EguiPass` is recorded in the same frame
+///
+/// [`WorldRenderer::update_egui_textures`]: crate::WorldRenderer::update_egui_textures
+pub struct EguiPass<'frame> {
+    name: String,
+    primitives: &'frame [ClippedPrimitive],
+    pixels_per_point: f32,
+}
+
+impl<'frame> EguiPass<'frame> {
+    /// Pass drawing `primitives` tessellated with `pixels_per_point`
+    pub fn new<S: Into<String>>(name: S, primitives: &'frame [ClippedPrimitive], pixels_per_point: f32) -> Self {
+        Self {
+            name: name.into(),
+            primitives,
+            pixels_per_point,
+        }
+    }
+}
+
+impl<'frame> From<EguiPass<'frame>> for Pass<'frame> {
+    fn from(pass: EguiPass<'frame>) -> Self {
+        let EguiPass {
+            name,
+            primitives,
+            pixels_per_point,
+        } = pass;
+
+        Pass::Present(PresentPass {
+            name,
+            reads: vec![],
+            reads_depth: false,
+            execute: Box::new(move |ctx: &mut PassContext| {
+                profiling::scope!("EguiPass");
+
+                let resources = &ctx.external_resources;
+                let sets = [resources.bindless_set(), resources.per_frame_set(ctx.frame)];
+                let result = unsafe {
+                    resources.egui.write().draw(
+                        &ctx.render_context.device,
+                        ctx.cbuf,
+                        ctx.frame,
+                        ctx.resolution,
+                        &sets,
+                        primitives,
+                        pixels_per_point,
+                    )
+                };
+
+                if let Err(err) = result {
+                    error!("Error draw egui: {:?}", err);
+                }
+            }),
+        })
+    }
+}
@@ -2,12 +2,23 @@ mod present;
 
 pub use present::*;
 
+mod canvas;
+pub use canvas::CanvasPass;
+
+mod debug;
+pub use debug::DebugPass;
+
 mod compute;
 pub use compute::ComputePass;
 
 mod raster;
 pub use raster::*;
 
+#[cfg(feature = "egui")]
+mod egui;
+#[cfg(feature = "egui")]
+pub use egui::EguiPass;
+
 use crate::{Handle, frame_graph::{RenderTarget, RenderTargetsDesc}};
 
 use super::PassContext;
@@ -24,7 +35,8 @@ pub enum Pass<'frame> {
 pub struct PassBuilder<'a> {
     pub(crate) reads: Vec<bool>,
     pub(crate) writes: Vec<bool>,
-    pub(crate) render_target_desc: Option<RenderTargetsDesc<'a>>
+    pub(crate) render_target_desc: Option<RenderTargetsDesc<'a>>,
+    pub(crate) reads_depth: bool,
 }
 
 impl<'a> PassBuilder<'a> {
@@ -38,6 +50,14 @@ impl<'a> PassBuilder<'a> {
         todo!()
     }
 
+    /// Sample the depth buffer drawn by earlier passes, its bindless index is
+    /// [`PassContext::depth_texture`]
+    ///
+    /// Depth is read-only during the pass, draws may still test against it
+    pub fn read_depth(&mut self) {
+        self.reads_depth = true;
+    }
+
     pub fn render_targets(&mut self, desc: RenderTargetsDesc<'a>) {
         
     }
@@ -9,6 +9,8 @@ use std::any::{TypeId};
 pub struct PresentPass<'frame> {
     pub(crate) name: String,
     pub(crate) reads: Vec<bool>,
+    /// Declared with [`PassBuilder::read_depth`]
+    pub(crate) reads_depth: bool,
     pub(crate) execute: Box<dyn FnOnce(&mut PassContext) + Send + 'frame>,
 }
 
@@ -25,6 +27,7 @@ impl<'frame> PresentPass<'frame> {
             reads: vec![],
             writes: vec![],
             render_target_desc: None,
+            reads_depth: false,
         };
         
         let data = setup(&mut builder);
@@ -32,6 +35,7 @@ impl<'frame> PresentPass<'frame> {
         Self {
             name: name.into(),
             reads: builder.reads,
+            reads_depth: builder.reads_depth,
             execute: Box::new(move |ctx| {
                 execute(ctx, &data);
             }),
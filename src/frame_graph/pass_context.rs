@@ -1,19 +1,62 @@
+use std::ops::{Range, RangeBounds};
 use std::sync::Arc;
 
 use ash::vk::{self};
 use bytemuck::{Pod, Zeroable};
+use tracing::error;
 
 use crate::frame_graph::{Scissor, Viewport};
-use crate::resources::{Res, Resources, Texture, TextureView};
-use crate::{Mesh, RasterPipeline};
+use crate::math::{self, Mat4};
+use crate::render_context::RenderContext;
+use crate::resources::{Colormap, HeightField, InstanceBuffer, Isosurface, PointCloud, PointColor, Polyline, Res, Resources, Texture, Transform, Volume};
+use crate::{Mesh, RasterPipeline, Submesh};
+
+/// `transform_idx` of draws placed by no transform, shaders skip
+/// `transforms[]` for it, which may be empty
+pub(crate) const NO_TRANSFORM: u32 = u32::MAX;
+
+/// Indices at the start of the push constants of every pipeline, the
+/// `transform_idx` and `tex_idx` of `shaders/inc/bindless.glsl`
+#[repr(C)]
+#[derive(Clone, Copy, Debug, PartialEq, Pod, Zeroable)]
+pub(crate) struct PushHeader {
+    pub transform_idx: u32,
+    pub tex_idx: [u32; 8],
+}
+
+impl PushHeader {
+    /// Header of a built-in draw, [`NO_TRANSFORM`] without a `transform`
+    pub fn new(transform: Option<u32>, tex_idx: [u32; 8]) -> Self {
+        Self {
+            transform_idx: transform.unwrap_or(NO_TRANSFORM),
+            tex_idx,
+        }
+    }
+}
 
 /// The context of the currently running pass
 pub struct PassContext {
     pub(crate) external_resources: Arc<Resources>,
     pub(crate) layout: Option<vk::PipelineLayout>,
     pub(crate) resolution: vk::Extent2D,
+    /// Index of the frame in flight, selects per-frame descriptor set
+    pub(crate) frame: usize,
+    /// `transform_idx` of the next push constants
+    pub(crate) transform_idx: u32,
+    /// `tex_idx` of the next push constants
+    pub(crate) tex_idx: [u32; 8],
+    /// Bindless index of the depth buffer in passes reading it
+    pub(crate) depth_texture: Option<u32>,
+    /// World matrix of the bound transform, places meshes for LOD selection
+    pub(crate) world: Mat4,
+    /// Transform bound since the last built-in draw, taken by the next one
+    pub(crate) bound_transform: Option<u32>,
+    /// Pixels of error allowed when choosing a mesh LOD
+    pub(crate) lod_threshold: f32,
     pub(crate) device: ash::Device,
     pub(crate) cbuf: vk::CommandBuffer,
+    /// Creates buffers of passes that grow while recording
+    pub(crate) render_context: Arc<RenderContext>,
 }
 
 impl PassContext {
@@ -80,9 +123,71 @@ impl PassContext {
         let pipeline = cache.raster_pipelines.get(handle);
         let layout = cache.pipeline_layout.get(&pipeline.layout);
         self.device.cmd_bind_pipeline(self.cbuf, vk::PipelineBindPoint::GRAPHICS, pipeline.pipeline.raw);
+
+        // Set 0 is bindless, set 1 holds per-frame data
+        let sets = [self.external_resources.bindless_set(), self.external_resources.per_frame_set(self.frame)];
+        self.device.cmd_bind_descriptor_sets(self.cbuf, vk::PipelineBindPoint::GRAPHICS, layout.raw, 0, &sets, &[]);
+
         self.layout = Some(layout.raw.clone());
     }
 
+    /// Select the transform shaders read as `transforms[push.transform_idx]`
+    ///
+    /// The index is kept for the following [`PassContext::push_constants`].
+    /// The next built-in draw such as [`PassContext::draw_polyline`] is
+    /// placed by it, later ones are not unless it is bound again
+    pub unsafe fn bind_transform(&mut self, transform: &Res<Transform>) {
+        profiling::scope!("PassContext::bind_transform");
+
+        #[cfg(feature = "validation")]
+        {
+            assert!(self.layout.is_some(), "Pipeline must be bind before draw");
+        }
+
+        let mut transforms = self.external_resources.transforms.write();
+        self.transform_idx = transforms.index(transform);
+        self.world = transforms.world_matrix(transform);
+        self.bound_transform = Some(self.transform_idx);
+        drop(transforms);
+
+        self.device.cmd_push_constants(
+            self.cbuf,
+            self.layout.unwrap(),
+            vk::ShaderStageFlags::FRAGMENT | vk::ShaderStageFlags::VERTEX,
+            0,
+            bytemuck::bytes_of(&self.transform_idx),
+        );
+    }
+
+    /// Select the lookup table shaders read as `textures[push.tex_idx[slot]]`,
+    /// the colormap of `colormap(slot, value)` in `shaders/inc/colormap.glsl`
+    ///
+    /// The index is kept for the following [`PassContext::push_constants`]
+    ///
+    /// # Panics
+    /// - if `slot` is 8 or more
+    pub unsafe fn bind_colormap(&mut self, slot: usize, colormap: Colormap) {
+        profiling::scope!("PassContext::bind_colormap");
+
+        assert!(slot < self.tex_idx.len(), "Push constants hold 8 texture indices");
+
+        #[cfg(feature = "validation")]
+        {
+            assert!(self.layout.is_some(), "Pipeline must be bind before draw");
+        }
+
+        self.tex_idx[slot] = self.external_resources.colormaps.read().index(colormap);
+
+        // After transform_idx
+        self.device.cmd_push_constants(
+            self.cbuf,
+            self.layout.unwrap(),
+            vk::ShaderStageFlags::FRAGMENT | vk::ShaderStageFlags::VERTEX,
+            4 + 4 * slot as u32,
+            bytemuck::bytes_of(&self.tex_idx[slot]),
+        );
+    }
+
     pub unsafe fn dispatch(&self, x: u32, y: u32, z: u32) {
         profiling::scope!("PassContext::dispatch");
         self.device.cmd_dispatch(self.cbuf, x, y, z);
@@ -103,8 +208,7 @@ impl PassContext {
         #[repr(C)]
         #[derive(Clone, Copy, Pod, Zeroable)]
         struct PushConstants {
-            transform_idx: u32,
-            tex_idx: [u32; 8],
+            header: PushHeader,
             user_data: [u8; 92]
         }
         
@@ -116,8 +220,10 @@ impl PassContext {
         }
 
         let push = PushConstants {
-            transform_idx: 0,
-            tex_idx: [0; 8],
+            header: PushHeader {
+                transform_idx: self.transform_idx,
+                tex_idx: self.tex_idx,
+            },
             user_data: out
         };
 
@@ -130,36 +236,448 @@ impl PassContext {
         );
     }
 
-    // pub unsafe fn bind_texture(&self, slot: usize, texture: &Res<TextureView>) {
+    /// Select the texture shaders read as `textures[push.tex_idx[slot]]`, or
+    /// `textures3d[push.tex_idx[slot]]` for 3D textures
+    ///
+    /// The index is kept for the following [`PassContext::push_constants`]
+    ///
+    /// # Panics
+    /// - if `slot` is 8 or more
+    pub unsafe fn bind_texture(&mut self, slot: usize, texture: &Res<Texture>) {
+        profiling::scope!("PassContext::bind_texture");
 
-    // }
+        assert!(slot < self.tex_idx.len(), "Push constants hold 8 texture indices");
 
+        #[cfg(feature = "validation")]
+        {
+            assert!(self.layout.is_some(), "Pipeline must be bind before draw");
+        }
+
+        self.tex_idx[slot] = self.external_resources.textures.read().pool.get(texture).index();
+
+        // After transform_idx
+        self.device.cmd_push_constants(
+            self.cbuf,
+            self.layout.unwrap(),
+            vk::ShaderStageFlags::FRAGMENT | vk::ShaderStageFlags::VERTEX,
+            4 + 4 * slot as u32,
+            bytemuck::bytes_of(&self.tex_idx[slot]),
+        );
+    }
+
+    /// Index of the depth buffer in `textures[]` when the pass declared
+    /// [`PassBuilder::read_depth`], reversed-Z with 0 where nothing was drawn
+    ///
+    /// [`PassBuilder::read_depth`]: crate::frame_graph::PassBuilder::read_depth
+    pub fn depth_texture(&self) -> Option<u32> {
+        self.depth_texture
+    }
+
+    /// Largest error in pixels of the mesh LODs [`PassContext::draw_mesh`]
+    /// picks, `1.0` by default
+    ///
+    /// Larger values switch to coarser LODs closer to the camera
+    pub fn set_lod_threshold(&mut self, pixels: f32) {
+        self.lod_threshold = pixels;
+    }
+
+    /// Draw every submesh of the mesh, or the whole mesh when it has none
+    ///
+    /// Meshes with LODs draw the coarsest one whose error stays under
+    /// [`PassContext::set_lod_threshold`] pixels, placed by the last
+    /// [`PassContext::bind_transform`] and seen by the [`Camera`]
+    ///
+    /// [`Camera`]: crate::Camera
     pub unsafe fn draw_mesh(&self, mesh: &Res<Mesh>) {
         profiling::scope!("PassContext::draw_mesh");
 
         let binding = self.external_resources.meshes.read();
-        let mesh = binding.get(mesh.key).unwrap();
+        let mesh = binding.pool.get(mesh);
+        let instances = mesh.instance_offset..mesh.instance_offset + mesh.instance_count;
 
-        if let Some(index_buffer) = &mesh.index_buffer {
-            self.device
-                .cmd_bind_vertex_buffers(self.cbuf, 0, &[mesh.vertex_buffer.raw], &[0]);
+        if !self.bind_mesh(mesh, None, None) {
+            return;
+        }
+
+        match mesh.submeshes() {
+            [] => {
+                let camera = self.external_resources.camera.read();
+                let lod = mesh.select_lod(
+                    &self.world,
+                    camera.view(),
+                    camera.proj(),
+                    self.resolution.height as f32,
+                    self.lod_threshold,
+                );
+                self.draw_part(mesh, lod, instances);
+            },
+            submeshes => {
+                for submesh in submeshes {
+                    self.draw_part(mesh, *submesh, instances.clone());
+                }
+            },
+        }
+    }
+
+    /// Draw submesh `index` of the mesh, materials of
+    /// [`Submesh::material`] are bound by the caller
+    ///
+    /// # Example
+    /// ```ignore
+    /// for (index, submesh) in submeshes.iter().enumerate() {
+    ///     ctx.bind_pipeline(&materials[submesh.material as usize]);
+    ///     ctx.draw_submesh(&mesh, index);
+    /// }
+    /// ```
+    ///
+    /// # Panics
+    /// - if the mesh has no submesh `index`
+    ///
+    /// [`Submesh::material`]: crate::Submesh::material
+    pub unsafe fn draw_submesh(&self, mesh: &Res<Mesh>, index: usize) {
+        profiling::scope!("PassContext::draw_submesh");
+
+        let binding = self.external_resources.meshes.read();
+        let mesh = binding.pool.get(mesh);
+        let submesh = *mesh.submeshes().get(index).expect("Submesh out of bounds");
+
+        if self.bind_mesh(mesh, None, None) {
+            self.draw_part(mesh, submesh, mesh.instance_offset..mesh.instance_offset + mesh.instance_count);
+        }
+    }
+
+    /// Draw `instances` of the mesh, attributes of each instance are read at
+    /// binding 1, see [`RasterPipelineDesc::instance_input`]
+    ///
+    /// `range` selects instances, `..` draws all of them. Every submesh is
+    /// drawn with the same instances
+    ///
+    /// # Panics
+    /// - if the range ends past the last instance
+    ///
+    /// [`RasterPipelineDesc::instance_input`]: crate::RasterPipelineDesc::instance_input
+    pub unsafe fn draw_mesh_instanced<R: RangeBounds<u32>>(&self, mesh: &Res<Mesh>, instances: &Res<InstanceBuffer>, range: R) {
+        profiling::scope!("PassContext::draw_mesh_instanced");
+
+        let store = self.external_resources.instances.read();
+        let instances = store.pool.get(instances);
+
+        let Range { start: first, end } = instances.range(range);
+
+        // Empty buffers have nothing to draw
+        let Some(instance_buffer) = instances.buffer(self.frame) else {
+            return;
+        };
+
+        if first >= end {
+            return;
+        }
+
+        let binding = self.external_resources.meshes.read();
+        let mesh = binding.pool.get(mesh);
+
+        if !self.bind_mesh(mesh, Some(instance_buffer), None) {
+            return;
+        }
+
+        match mesh.submeshes() {
+            [] => self.draw_part(mesh, mesh.whole(), first..end),
+            submeshes => {
+                for submesh in submeshes {
+                    self.draw_part(mesh, *submesh, first..end);
+                }
+            },
+        }
+    }
+
+    /// Draw every submesh of the mesh with per-vertex `attributes` read at
+    /// binding 2, see [`RasterPipelineDesc::vertex_input`]
+    ///
+    /// `attributes` holds one entry for each vertex of the mesh, like the
+    /// scalars of a field shown with a [`Colormap`]
+    ///
+    /// # Example
+    /// ```ignore
+    /// let values = world.create::<InstanceBuffer>(InstanceBufferDesc::new(&temperatures))?;
+    ///
+    /// ctx.bind_pipeline(&scalar_pipeline);
+    /// ctx.bind_colormap(0, Colormap::Inferno);
+    /// ctx.push_constants(ScalarRange::new(250.0, 350.0));
+    /// ctx.draw_mesh_with_attributes(&mesh, &values);
+    /// ```
+    ///
+    /// [`RasterPipelineDesc::vertex_input`]: crate::RasterPipelineDesc::vertex_input
+    pub unsafe fn draw_mesh_with_attributes(&self, mesh: &Res<Mesh>, attributes: &Res<InstanceBuffer>) {
+        profiling::scope!("PassContext::draw_mesh_with_attributes");
+
+        let store = self.external_resources.instances.read();
+        // Empty buffers have nothing to draw
+        let Some(attribute_buffer) = store.pool.get(attributes).buffer(self.frame) else {
+            return;
+        };
+
+        let binding = self.external_resources.meshes.read();
+        let mesh = binding.pool.get(mesh);
+        let instances = mesh.instance_offset..mesh.instance_offset + mesh.instance_count;
+
+        if !self.bind_mesh(mesh, None, Some(attribute_buffer)) {
+            return;
+        }
+
+        match mesh.submeshes() {
+            [] => self.draw_part(mesh, mesh.whole(), instances),
+            submeshes => {
+                for submesh in submeshes {
+                    self.draw_part(mesh, *submesh, instances.clone());
+                }
+            },
+        }
+    }
+
+    /// Bind vertex and index buffers of the mesh, `false` while a dynamic
+    /// mesh has no buffers for this frame
+    unsafe fn bind_mesh(&self, mesh: &Mesh, instance_buffer: Option<vk::Buffer>, attribute_buffer: Option<vk::Buffer>) -> bool {
+        let Some(vertex_buffer) = mesh.vertex_buffer(self.frame) else {
+            return false;
+        };
+
+        match instance_buffer {
+            Some(instance_buffer) => self
+                .device
+                .cmd_bind_vertex_buffers(self.cbuf, 0, &[vertex_buffer, instance_buffer], &[0, 0]),
+            None => self
+                .device
+                .cmd_bind_vertex_buffers(self.cbuf, 0, &[vertex_buffer], &[0]),
+        }
+
+        if let Some(attribute_buffer) = attribute_buffer {
             self.device
-                .cmd_bind_index_buffer(self.cbuf, index_buffer.raw, 0, vk::IndexType::UINT32);
+                .cmd_bind_vertex_buffers(self.cbuf, 2, &[attribute_buffer], &[0]);
+        }
+
+        if mesh.index_count().is_some() {
+            let Some(index_buffer) = mesh.index_buffer(self.frame) else {
+                return false;
+            };
             self.device
-                .cmd_draw_indexed(self.cbuf, index_buffer.count, 1, 0, 0, 0);
+                .cmd_bind_index_buffer(self.cbuf, index_buffer, 0, mesh.index_type());
+        }
+
+        true
+    }
+
+    unsafe fn draw_part(&self, mesh: &Mesh, submesh: Submesh, instances: Range<u32>) {
+        let instance_count = instances.end - instances.start;
+
+        debug_assert!(
+            submesh.first_index as u64 + submesh.index_count as u64 <= mesh.index_count().unwrap_or(mesh.vertex_count()) as u64,
+            "Submesh past the end of the mesh"
+        );
+
+        if mesh.index_count().is_some() {
+            self.device.cmd_draw_indexed(
+                self.cbuf,
+                submesh.index_count,
+                instance_count,
+                submesh.first_index,
+                submesh.base_vertex,
+                instances.start,
+            );
         } else {
-            self.device
-                .cmd_bind_vertex_buffers(self.cbuf, 0, &[mesh.vertex_buffer.raw], &[0]);
             self.device.cmd_draw(
                 self.cbuf,
-                mesh.vertex_buffer.count,
-                mesh.instance_count,
-                mesh.vertex_offset,
-                mesh.instance_offset,
+                submesh.index_count,
+                instance_count,
+                (submesh.first_index as i32 + submesh.base_vertex) as u32,
+                instances.start,
             );
         }
     }
 
+    /// Draw the line with its own pipeline over the whole target, placed by
+    /// the transform bound before it
+    ///
+    /// The bound pipeline, viewport and scissors are replaced, bind them
+    /// again before drawing anything else
+    pub unsafe fn draw_polyline(&mut self, line: &Res<Polyline>) {
+        profiling::scope!("PassContext::draw_polyline");
+
+        let transform_idx = self.bound_transform.take();
+        let store = self.external_resources.polylines.read();
+        let sets = [self.external_resources.bindless_set(), self.external_resources.per_frame_set(self.frame)];
+        store.draw(
+            &self.device,
+            self.cbuf,
+            self.frame,
+            self.resolution,
+            &sets,
+            store.pool.get(line),
+            transform_idx,
+        );
+    }
+
+    /// Draw the surface of the height field with its own pipeline over the
+    /// whole target, placed by the transform bound before it
+    ///
+    /// The surface writes depth. The bound pipeline, viewport and scissors
+    /// are replaced, bind them again before drawing anything else
+    pub unsafe fn draw_height_field(&mut self, field: &Res<HeightField>) {
+        profiling::scope!("PassContext::draw_height_field");
+
+        let transform_idx = self.bound_transform.take();
+        let store = self.external_resources.height_fields.read();
+        let field = store.pool.get(field);
+        let colormap_idx = self.external_resources.colormaps.read().index(field.colormap);
+        let sets = [self.external_resources.bindless_set(), self.external_resources.per_frame_set(self.frame)];
+        store.draw(
+            &self.device,
+            self.cbuf,
+            self.frame,
+            self.resolution,
+            &sets,
+            field,
+            transform_idx,
+            colormap_idx,
+        );
+    }
+
+    /// Draw the triangles of the last extraction of `surface` with its own
+    /// pipeline over the whole target, placed by the transform bound before
+    /// it
+    ///
+    /// The triangle count is read from the GPU by the indirect draw. The
+    /// bound pipeline, viewport and scissors are replaced, bind them again
+    /// before drawing anything else
+    pub unsafe fn draw_isosurface(&mut self, surface: &Res<Isosurface>) {
+        profiling::scope!("PassContext::draw_isosurface");
+
+        let transform_idx = self.bound_transform.take();
+        let store = self.external_resources.isosurfaces.read();
+        let surface = store.pool.get(surface);
+        let sets = [self.external_resources.bindless_set(), self.external_resources.per_frame_set(self.frame)];
+        store.draw(
+            &self.device,
+            self.cbuf,
+            self.resolution,
+            &sets,
+            surface,
+            transform_idx,
+        );
+    }
+
+    /// Ray march the volume with its own pipeline over the whole target,
+    /// placed by the transform bound before it
+    ///
+    /// Rays stop at the depth buffer, which the pass reads. The bound
+    /// pipeline, viewport and scissors are replaced, bind them again before
+    /// drawing anything else
+    ///
+    /// # Panics
+    /// - if the pass did not declare [`PassBuilder::read_depth`]
+    ///
+    /// [`PassBuilder::read_depth`]: crate::frame_graph::PassBuilder::read_depth
+    pub unsafe fn draw_volume(&mut self, volume: &Res<Volume>) {
+        profiling::scope!("PassContext::draw_volume");
+
+        let transform_idx = self.bound_transform.take();
+        let depth_idx = self.depth_texture.expect("Volumes are drawn in passes reading depth");
+        let store = self.external_resources.volumes.read();
+        let volume = store.pool.get(volume);
+        let colormap_idx = self.external_resources.colormaps.read().index(volume.colormap);
+        let sets = [self.external_resources.bindless_set(), self.external_resources.per_frame_set(self.frame)];
+        store.draw(
+            &self.device,
+            self.cbuf,
+            self.resolution,
+            &sets,
+            volume,
+            transform_idx,
+            colormap_idx,
+            depth_idx,
+        );
+    }
+
+    /// Draw the chunks of the cloud the [`Camera`] needs with their own
+    /// pipeline over the whole target, placed by the transform bound before
+    /// it
+    ///
+    /// Chunks missing on the GPU are copied before drawing, a few million
+    /// points per draw, the rest follow on later frames. The bound pipeline,
+    /// viewport and scissors are replaced, bind them again before drawing
+    /// anything else
+    ///
+    /// [`Camera`]: crate::Camera
+    pub unsafe fn draw_point_cloud(&mut self, cloud: &Res<PointCloud>) {
+        profiling::scope!("PassContext::draw_point_cloud");
+
+        let transform_idx = self.bound_transform.take();
+        let world = if transform_idx.is_some() { &self.world } else { &math::IDENTITY };
+        let camera = self.external_resources.camera.read();
+        let mut store = self.external_resources.point_clouds.write();
+        let colormap_idx = match store.pool.get(cloud).color {
+            PointColor::Scalar { colormap, .. } => self.external_resources.colormaps.read().index(colormap),
+            PointColor::Rgba => 0,
+        };
+        let sets = [self.external_resources.bindless_set(), self.external_resources.per_frame_set(self.frame)];
+        let result = store.draw(
+            &self.render_context.device,
+            self.cbuf,
+            self.resolution,
+            &sets,
+            cloud,
+            (camera.view(), camera.proj()),
+            world,
+            transform_idx,
+            colormap_idx,
+        );
+
+        if let Err(err) = result {
+            error!("Error draw point cloud: {:?}", err);
+        }
+    }
+
+    /// Draw the text with its own pipeline over the whole target, world
+    /// anchors are placed by the transform bound before it
+    ///
+    /// The bound pipeline, viewport and scissors are replaced, bind them
+    /// again before drawing anything else
+    #[cfg(feature = "text")]
+    pub unsafe fn draw_text(&mut self, text: &Res<crate::Text>) {
+        profiling::scope!("PassContext::draw_text");
+
+        let transform_idx = self.bound_transform.take();
+        let store = self.external_resources.text.read();
+        let sets = [self.external_resources.bindless_set(), self.external_resources.per_frame_set(self.frame)];
+        store.draw(
+            &self.device,
+            self.cbuf,
+            self.frame,
+            self.resolution,
+            &sets,
+            store.texts.get(text),
+            transform_idx,
+        );
+    }
+
+    /// Draw every [`SceneObject`] the cull pass found inside the camera
+    /// frustum this frame, one indexed indirect draw per object
+    ///
+    /// Vertices come from the shared scene buffers and `gl_InstanceIndex` is
+    /// the object index, shaders read their transform with
+    /// `transforms[objects[gl_InstanceIndex].transform_idx]`
+    ///
+    /// [`SceneObject`]: crate::SceneObject
+    pub unsafe fn draw_indirect_count(&self) {
+        profiling::scope!("PassContext::draw_indirect_count");
+
+        #[cfg(feature = "validation")]
+        {
+            assert!(self.layout.is_some(), "Pipeline must be bind before draw");
+        }
+
+        self.external_resources.scene.read().draw(&self.device, self.cbuf, self.frame);
+    }
+
     pub unsafe fn draw(&self, vertex_count: u32) {
         self.device.cmd_draw(self.cbuf, vertex_count, 1, 0, 0);
     }
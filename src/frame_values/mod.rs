@@ -1,9 +1,12 @@
+use std::time::Instant;
+
 use ash::vk;
 use bytemuck::{Pod, Zeroable};
 
 use crate::core::{Device, VulkanResult};
 use crate::per_frame::{PerFrameBuffer, PerFrameBufferBuilder};
 
+/// Frame values as seen by shaders at set 1, binding 1
 #[repr(C)]
 #[derive(Clone, Copy, Pod, Zeroable)]
 pub struct FrameData {
@@ -14,42 +17,56 @@ pub struct FrameData {
     _pad: f32,
 }
 
+/// Resolution, frame counter and timings uploaded once per frame
 pub struct FrameValues {
-    pub global_time_sec: std::time::Instant,
-    pub delta_time_sec: std::time::Instant,
-    pub buffer: PerFrameBuffer,
-    pub data: FrameData,
+    start: Instant,
+    last: Instant,
+    pub(crate) buffer: PerFrameBuffer,
+    pub(crate) data: FrameData,
 }
 
 impl FrameValues {
     pub fn new(device: &Device, frame_count: usize) -> VulkanResult<Self> {
-        let buffer = PerFrameBufferBuilder::new(device)
+        let mut buffer = PerFrameBufferBuilder::new(device)
             .frame_count(frame_count)
             .buffer_size(size_of::<FrameData>() as u64)
             .usage(vk::BufferUsageFlags::UNIFORM_BUFFER)
             .build()?;
 
+        let data = FrameData::zeroed();
+
+        for i in 0..frame_count {
+            buffer.get_mut(i as u32).upload_data(&[data])?;
+        }
+
+        let now = Instant::now();
+
         Ok(Self {
-            global_time_sec: std::time::Instant::now(),
-            delta_time_sec: std::time::Instant::now(),
-            data: FrameData {
-                resolution: [0, 0],
-                frame_index: 0,
-                delta_time_sec: 0.0,
-                time_sec: 0.0,
-                _pad: 0.0,
-            },
+            start: now,
+            last: now,
+            data,
             buffer,
         })
     }
 
-    pub fn update(&mut self, image_index: u32, frame_index: u32) -> VulkanResult<()> {
-        let buffer = self.buffer.get_mut(image_index);
+    /// Advance timings and upload them into the buffer of `frame`
+    ///
+    /// `frame_index` counts every drawn frame, `frame` is the frame in flight
+    pub fn update(&mut self, frame: usize, frame_index: u32, resolution: vk::Extent2D) -> VulkanResult<()> {
+        profiling::scope!("FrameValues::update");
+
+        let now = Instant::now();
+
+        self.data.resolution = [resolution.width, resolution.height];
         self.data.frame_index = frame_index;
-        self.data.time_sec = self.global_time_sec.elapsed().as_secs_f32();
-        self.data.delta_time_sec = self.delta_time_sec.elapsed().as_secs_f32();
-        self.delta_time_sec = std::time::Instant::now();
-        buffer.upload_data(&[self.data])?;
-        Ok(())
+        self.data.time_sec = now.duration_since(self.start).as_secs_f32();
+        self.data.delta_time_sec = now.duration_since(self.last).as_secs_f32();
+        self.last = now;
+
+        self.buffer.get_mut(frame as u32).upload_data(&[self.data])
+    }
+
+    pub fn destroy(&mut self, device: &Device) {
+        self.buffer.destroy(device);
     }
 }
@@ -0,0 +1,125 @@
+//! CPU-side triangle meshes ready for [`MeshDesc`](crate::MeshDesc)
+
+use crate::core::{PbrVertex, TextureVertex, Vertex};
+use crate::math::{add, Vec3};
+
+mod primitives;
+mod process;
+mod simplify;
+
+/// Indexed triangle list with per-vertex attributes
+///
+/// Normals, UVs and tangents are either empty or hold one value per
+/// position, missing ones read as zero. Triangles are counter-clockwise
+/// seen from the side normals point to, `v` of UVs grows downward as in
+/// images. Tangents are `[x, y, z, w]`, the bitangent is
+/// `w * cross(normal, tangent)`
+///
+/// # Example
+/// ```ignore
+/// let sphere = Geometry::uv_sphere(0.5, 32, 16);
+/// let vertices = sphere.to_pbr_vertices([1.0, 0.5, 0.2, 1.0]);
+/// let mesh = world.create::<Mesh>(MeshDesc::new(&vertices).with_indices(&sphere.indices))?;
+/// ```
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Geometry {
+    /// Positions
+    pub positions: Vec<Vec3>,
+    /// Unit normals
+    pub normals: Vec<Vec3>,
+    /// Texture coordinates
+    pub uvs: Vec<[f32; 2]>,
+    /// Unit tangents with the bitangent sign in `w`
+    pub tangents: Vec<[f32; 4]>,
+    /// Three indices per triangle
+    pub indices: Vec<u32>,
+}
+
+impl Geometry {
+    /// Number of vertices
+    pub fn vertex_count(&self) -> usize {
+        self.positions.len()
+    }
+
+    /// Triangle list of `positions` without other attributes, see
+    /// [`Geometry::compute_smooth_normals`] and [`Geometry::compute_tangents`]
+    pub fn new(positions: Vec<Vec3>, indices: Vec<u32>) -> Self {
+        Self {
+            positions,
+            indices,
+            ..Default::default()
+        }
+    }
+
+    // Missing attributes read as zero
+    fn normal(&self, i: usize) -> Vec3 {
+        self.normals.get(i).copied().unwrap_or_default()
+    }
+
+    fn uv(&self, i: usize) -> [f32; 2] {
+        self.uvs.get(i).copied().unwrap_or_default()
+    }
+
+    fn tangent(&self, i: usize) -> [f32; 4] {
+        self.tangents.get(i).copied().unwrap_or_default()
+    }
+
+    /// Add the vertices and triangles of `other`, both need the same
+    /// attributes
+    pub fn append(&mut self, other: &Geometry) {
+        let base = self.positions.len() as u32;
+
+        self.positions.extend_from_slice(&other.positions);
+        self.normals.extend_from_slice(&other.normals);
+        self.uvs.extend_from_slice(&other.uvs);
+        self.tangents.extend_from_slice(&other.tangents);
+        self.indices
+            .extend(other.indices.iter().map(|index| index + base));
+    }
+
+    /// Move every vertex by `offset`
+    pub fn translate(mut self, offset: Vec3) -> Self {
+        for position in &mut self.positions {
+            *position = add(*position, offset);
+        }
+        self
+    }
+
+    /// Vertices in the layout of [`PbrVertex`], all of one `color`
+    pub fn to_pbr_vertices(&self, color: [f32; 4]) -> Vec<PbrVertex> {
+        (0..self.vertex_count())
+            .map(|i| {
+                let [x, y, z] = self.positions[i];
+                let [nx, ny, nz] = self.normal(i);
+                PbrVertex {
+                    pos: [x, y, z, 1.0],
+                    normal: [nx, ny, nz, 0.0],
+                    uv: self.uv(i),
+                    color,
+                    tangent: self.tangent(i),
+                }
+            })
+            .collect()
+    }
+
+    /// Vertices in the layout of [`Vertex`], all of one `color`
+    pub fn to_vertices(&self, color: Vec3) -> Vec<Vertex> {
+        (0..self.vertex_count())
+            .map(|i| Vertex {
+                pos: self.positions[i],
+                color,
+                normal: self.normal(i),
+            })
+            .collect()
+    }
+
+    /// Vertices in the layout of [`TextureVertex`]
+    pub fn to_texture_vertices(&self) -> Vec<TextureVertex> {
+        (0..self.vertex_count())
+            .map(|i| TextureVertex {
+                pos: self.positions[i],
+                uv: self.uv(i),
+            })
+            .collect()
+    }
+}
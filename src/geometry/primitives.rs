@@ -0,0 +1,588 @@
+//! Generators of common shapes, centered on the origin with `+Y` up
+
+use std::collections::HashMap;
+use std::f32::consts::{PI, TAU};
+
+use super::Geometry;
+use crate::math::{add, normalize, scale, Vec3};
+
+/// Row of a surface of revolution around `Y`
+#[derive(Clone, Copy)]
+struct Ring {
+    radius: f32,
+    y: f32,
+    /// Normal in the `(radius, y)` plane
+    normal: [f32; 2],
+    v: f32,
+}
+
+impl Geometry {
+    /// Box of `size`, four vertices per face
+    pub fn cube(size: Vec3) -> Self {
+        let half = scale(size, 0.5);
+        let mut geometry = Self::default();
+
+        // Normal, `u` axis and `v` axis with `cross(u, v) == normal`
+        let faces: [[Vec3; 3]; 6] = [
+            [[1.0, 0.0, 0.0], [0.0, 0.0, -1.0], [0.0, 1.0, 0.0]],
+            [[-1.0, 0.0, 0.0], [0.0, 0.0, 1.0], [0.0, 1.0, 0.0]],
+            [[0.0, 1.0, 0.0], [1.0, 0.0, 0.0], [0.0, 0.0, -1.0]],
+            [[0.0, -1.0, 0.0], [1.0, 0.0, 0.0], [0.0, 0.0, 1.0]],
+            [[0.0, 0.0, 1.0], [1.0, 0.0, 0.0], [0.0, 1.0, 0.0]],
+            [[0.0, 0.0, -1.0], [-1.0, 0.0, 0.0], [0.0, 1.0, 0.0]],
+        ];
+
+        for [normal, u, v] in faces {
+            let base = geometry.positions.len() as u32;
+            let corner = |su: f32, sv: f32| {
+                let offset = add(add(normal, scale(u, su)), scale(v, sv));
+                [0, 1, 2].map(|axis| offset[axis] * half[axis])
+            };
+
+            for (su, sv) in [(-1.0, -1.0), (1.0, -1.0), (1.0, 1.0), (-1.0, 1.0)] {
+                geometry.positions.push(corner(su, sv));
+                geometry.normals.push(normal);
+                geometry.uvs.push([(su + 1.0) * 0.5, (1.0 - sv) * 0.5]);
+            }
+            geometry
+                .indices
+                .extend([base, base + 1, base + 2, base, base + 2, base + 3]);
+        }
+
+        geometry.compute_tangents();
+        geometry
+    }
+
+    /// Flat square of `size` in the `XZ` plane facing `+Y`
+    pub fn plane(size: [f32; 2]) -> Self {
+        Self::grid(size, [1, 1])
+    }
+
+    /// Flat rectangle of `size` in the `XZ` plane facing `+Y`, split into
+    /// `divisions` quads along `X` and `Z`
+    ///
+    /// # Panics
+    /// - if a division is zero
+    pub fn grid(size: [f32; 2], divisions: [u32; 2]) -> Self {
+        let [nx, nz] = divisions;
+        assert!(nx > 0 && nz > 0, "Grid needs at least one division");
+
+        let mut geometry = Self::default();
+        for j in 0..=nz {
+            for i in 0..=nx {
+                let (u, v) = (i as f32 / nx as f32, j as f32 / nz as f32);
+                geometry
+                    .positions
+                    .push([(u - 0.5) * size[0], 0.0, (v - 0.5) * size[1]]);
+                geometry.normals.push([0.0, 1.0, 0.0]);
+                geometry.uvs.push([u, v]);
+            }
+        }
+
+        let row = nx + 1;
+        for j in 0..nz {
+            for i in 0..nx {
+                let a = j * row + i;
+                let (b, c, d) = (a + 1, a + row + 1, a + row);
+                geometry.indices.extend([a, d, c, a, c, b]);
+            }
+        }
+
+        geometry.compute_tangents();
+        geometry
+    }
+
+    /// Sphere of `segments` around `Y` and `rings` from pole to pole
+    ///
+    /// # Panics
+    /// - if `segments < 3` or `rings < 2`
+    pub fn uv_sphere(radius: f32, segments: u32, rings: u32) -> Self {
+        assert!(segments >= 3 && rings >= 2, "Sphere needs 3 segments and 2 rings");
+
+        let rows = (0..=rings).map(|k| {
+            let theta = PI * k as f32 / rings as f32;
+            Ring {
+                radius: radius * theta.sin(),
+                y: radius * theta.cos(),
+                normal: [theta.sin(), theta.cos()],
+                v: k as f32 / rings as f32,
+            }
+        });
+
+        Self::lathe(&rows.collect::<Vec<_>>(), segments)
+    }
+
+    /// Sphere from an icosahedron split `subdivisions` times, triangles are
+    /// about the same size everywhere
+    ///
+    /// UVs are spherical, vertices are repeated along the seam and at the
+    /// poles
+    pub fn ico_sphere(radius: f32, subdivisions: u32) -> Self {
+        let t = (1.0 + 5f32.sqrt()) * 0.5;
+        let mut directions: Vec<Vec3> = [
+            [-1.0, t, 0.0],
+            [1.0, t, 0.0],
+            [-1.0, -t, 0.0],
+            [1.0, -t, 0.0],
+            [0.0, -1.0, t],
+            [0.0, 1.0, t],
+            [0.0, -1.0, -t],
+            [0.0, 1.0, -t],
+            [t, 0.0, -1.0],
+            [t, 0.0, 1.0],
+            [-t, 0.0, -1.0],
+            [-t, 0.0, 1.0],
+        ]
+        .map(normalize)
+        .to_vec();
+
+        let mut triangles: Vec<[u32; 3]> = vec![
+            [0, 11, 5],
+            [0, 5, 1],
+            [0, 1, 7],
+            [0, 7, 10],
+            [0, 10, 11],
+            [1, 5, 9],
+            [5, 11, 4],
+            [11, 10, 2],
+            [10, 7, 6],
+            [7, 1, 8],
+            [3, 9, 4],
+            [3, 4, 2],
+            [3, 2, 6],
+            [3, 6, 8],
+            [3, 8, 9],
+            [4, 9, 5],
+            [2, 4, 11],
+            [6, 2, 10],
+            [8, 6, 7],
+            [9, 8, 1],
+        ];
+
+        for _ in 0..subdivisions {
+            let mut midpoints = HashMap::new();
+            let mut midpoint = |a: u32, b: u32| {
+                *midpoints.entry((a.min(b), a.max(b))).or_insert_with(|| {
+                    let (pa, pb) = (directions[a as usize], directions[b as usize]);
+                    directions.push(normalize(scale(add(pa, pb), 0.5)));
+                    directions.len() as u32 - 1
+                })
+            };
+
+            triangles = triangles
+                .into_iter()
+                .flat_map(|[a, b, c]| {
+                    let (ab, bc, ca) = (midpoint(a, b), midpoint(b, c), midpoint(c, a));
+                    [[a, ab, ca], [b, bc, ab], [c, ca, bc], [ab, bc, ca]]
+                })
+                .collect();
+        }
+
+        let spherical = |d: Vec3| [0.5 + d[2].atan2(d[0]) / TAU, d[1].clamp(-1.0, 1.0).acos() / PI];
+
+        // One vertex per direction and UV, corners across the seam or on a
+        // pole need their own
+        let mut geometry = Self::default();
+        let mut vertices = HashMap::new();
+        for triangle in triangles {
+            let mut uvs = triangle.map(|index| spherical(directions[index as usize]));
+
+            let (min, max) = uvs
+                .iter()
+                .fold((f32::MAX, f32::MIN), |(min, max), uv| (min.min(uv[0]), max.max(uv[0])));
+            if max - min > 0.5 {
+                for uv in &mut uvs {
+                    if uv[0] < 0.5 {
+                        uv[0] += 1.0;
+                    }
+                }
+            }
+
+            for corner in 0..3 {
+                let direction = directions[triangle[corner] as usize];
+                if direction[1].abs() > 1.0 - 1e-6 {
+                    uvs[corner][0] = (uvs[(corner + 1) % 3][0] + uvs[(corner + 2) % 3][0]) * 0.5;
+                }
+            }
+
+            for corner in 0..3 {
+                let key = (triangle[corner], uvs[corner].map(f32::to_bits));
+                let index = *vertices.entry(key).or_insert_with(|| {
+                    let direction = directions[triangle[corner] as usize];
+                    geometry.positions.push(scale(direction, radius));
+                    geometry.normals.push(direction);
+                    geometry.uvs.push(uvs[corner]);
+                    geometry.positions.len() as u32 - 1
+                });
+                geometry.indices.push(index);
+            }
+        }
+
+        geometry.compute_tangents();
+        geometry
+    }
+
+    /// Closed cylinder of `height` along `Y`
+    ///
+    /// # Panics
+    /// - if `segments < 3`
+    pub fn cylinder(radius: f32, height: f32, segments: u32) -> Self {
+        let half = height * 0.5;
+        let mut geometry = Self::lathe(
+            &[
+                Ring {
+                    radius,
+                    y: half,
+                    normal: [1.0, 0.0],
+                    v: 0.0,
+                },
+                Ring {
+                    radius,
+                    y: -half,
+                    normal: [1.0, 0.0],
+                    v: 1.0,
+                },
+            ],
+            segments,
+        );
+
+        geometry.append(&Self::disk(radius, half, true, segments));
+        geometry.append(&Self::disk(radius, -half, false, segments));
+        geometry
+    }
+
+    /// Closed cone of `height` along `Y`, tip up
+    ///
+    /// # Panics
+    /// - if `segments < 3`
+    pub fn cone(radius: f32, height: f32, segments: u32) -> Self {
+        let half = height * 0.5;
+        let slope = normalize([height, radius, 0.0]);
+        let side = |radius: f32, y: f32, v: f32| Ring {
+            radius,
+            y,
+            normal: [slope[0], slope[1]],
+            v,
+        };
+
+        let mut geometry = Self::lathe(&[side(0.0, half, 0.0), side(radius, -half, 1.0)], segments);
+        geometry.append(&Self::disk(radius, -half, false, segments));
+        geometry
+    }
+
+    /// Capsule made of a cylinder of `height` between two half spheres,
+    /// `rings` rows per half sphere
+    ///
+    /// # Panics
+    /// - if `segments < 3` or `rings < 1`
+    pub fn capsule(radius: f32, height: f32, segments: u32, rings: u32) -> Self {
+        assert!(rings >= 1, "Capsule needs 1 ring per half sphere");
+
+        let half = height * 0.5;
+        // `v` follows the length of the profile
+        let total = PI * radius + height;
+        let hemisphere = |k: u32, center: f32, offset: f32| {
+            let theta = PI * 0.5 * k as f32 / rings as f32 + offset;
+            Ring {
+                radius: radius * theta.sin(),
+                y: center + radius * theta.cos(),
+                normal: [theta.sin(), theta.cos()],
+                v: (radius * theta + if offset > 0.0 { height } else { 0.0 }) / total,
+            }
+        };
+
+        let rows = (0..=rings)
+            .map(|k| hemisphere(k, half, 0.0))
+            .chain((0..=rings).map(|k| hemisphere(k, -half, PI * 0.5)))
+            .collect::<Vec<_>>();
+
+        Self::lathe(&rows, segments)
+    }
+
+    /// Ring around `Y`, `major` from the center to the middle of the tube of
+    /// radius `minor`
+    ///
+    /// # Panics
+    /// - if `major_segments < 3` or `minor_segments < 3`
+    pub fn torus(major: f32, minor: f32, major_segments: u32, minor_segments: u32) -> Self {
+        assert!(minor_segments >= 3, "Torus needs 3 minor segments");
+
+        // Outside of the tube downward first, keeps triangles facing out
+        let rows = (0..=minor_segments)
+            .map(|k| {
+                let alpha = TAU * k as f32 / minor_segments as f32;
+                Ring {
+                    radius: major + minor * alpha.cos(),
+                    y: -minor * alpha.sin(),
+                    normal: [alpha.cos(), -alpha.sin()],
+                    v: k as f32 / minor_segments as f32,
+                }
+            })
+            .collect::<Vec<_>>();
+
+        Self::lathe(&rows, major_segments)
+    }
+
+    /// Arrow along `+Y` starting at the origin, a cylinder of
+    /// `shaft_radius` ending in a cone of `head_radius` and `head_length`
+    ///
+    /// # Panics
+    /// - if `segments < 3`
+    /// - if `head_length` is longer than `length`
+    pub fn arrow(length: f32, shaft_radius: f32, head_radius: f32, head_length: f32, segments: u32) -> Self {
+        assert!(head_length <= length, "Arrow head longer than the arrow");
+
+        let shaft = length - head_length;
+        let mut geometry = Self::cylinder(shaft_radius, shaft, segments).translate([0.0, shaft * 0.5, 0.0]);
+        geometry.append(&Self::cone(head_radius, head_length, segments).translate([0.0, shaft + head_length * 0.5, 0.0]));
+        geometry
+    }
+
+    /// Surface of revolution of `rows` from top to bottom around `Y`
+    ///
+    /// Rows of zero radius are poles, their degenerate triangles are skipped
+    fn lathe(rows: &[Ring], segments: u32) -> Self {
+        assert!(segments >= 3, "Surface of revolution needs 3 segments");
+
+        // `sin(PI)` is not zero, snap poles to the axis
+        let max = rows.iter().fold(0.0f32, |max, row| max.max(row.radius));
+        let rows = rows
+            .iter()
+            .map(|row| Ring {
+                radius: if row.radius <= max * 1e-6 { 0.0 } else { row.radius },
+                ..*row
+            })
+            .collect::<Vec<_>>();
+
+        let mut geometry = Self::default();
+        for row in &rows {
+            for s in 0..=segments {
+                let u = s as f32 / segments as f32;
+                let (sin, cos) = (TAU * u).sin_cos();
+                geometry
+                    .positions
+                    .push([row.radius * cos, row.y, -row.radius * sin]);
+                geometry
+                    .normals
+                    .push(normalize([row.normal[0] * cos, row.normal[1], -row.normal[0] * sin]));
+                geometry.uvs.push([u, row.v]);
+            }
+        }
+
+        let stride = segments + 1;
+        for (k, pair) in rows.windows(2).enumerate() {
+            for s in 0..segments {
+                let a = k as u32 * stride + s;
+                let (b, c, d) = (a + 1, a + stride + 1, a + stride);
+                if pair[1].radius > 0.0 {
+                    geometry.indices.extend([a, d, c]);
+                }
+                if pair[0].radius > 0.0 {
+                    geometry.indices.extend([a, c, b]);
+                }
+            }
+        }
+
+        geometry.compute_tangents();
+        geometry
+    }
+
+    /// Flat disk at height `y` facing `+Y` when `up`, `-Y` otherwise
+    fn disk(radius: f32, y: f32, up: bool, segments: u32) -> Self {
+        let normal = if up { [0.0, 1.0, 0.0] } else { [0.0, -1.0, 0.0] };
+        let mut geometry = Self::default();
+
+        geometry.positions.push([0.0, y, 0.0]);
+        geometry.normals.push(normal);
+        geometry.uvs.push([0.5, 0.5]);
+
+        for s in 0..segments {
+            let (sin, cos) = (TAU * s as f32 / segments as f32).sin_cos();
+            geometry.positions.push([radius * cos, y, -radius * sin]);
+            geometry.normals.push(normal);
+            // Seen from outside, `u` goes right and `v` down
+            let side = if up { -1.0 } else { 1.0 };
+            geometry
+                .uvs
+                .push([0.5 + 0.5 * cos, 0.5 + 0.5 * sin * side]);
+        }
+
+        for s in 0..segments {
+            let (a, b) = (1 + s, 1 + (s + 1) % segments);
+            let triangle = if up { [0, a, b] } else { [0, b, a] };
+            geometry.indices.extend(triangle);
+        }
+
+        geometry.compute_tangents();
+        geometry
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::math::{cross, dot, length, sub, Aabb};
+
+    fn close(a: f32, b: f32, eps: f32) -> bool {
+        (a - b).abs() <= eps
+    }
+
+    /// Attributes are complete, unit and orthogonal, triangles face the side
+    /// of their normals
+    fn check(geometry: &Geometry) {
+        let count = geometry.vertex_count();
+        assert_eq!(geometry.normals.len(), count);
+        assert_eq!(geometry.uvs.len(), count);
+        assert_eq!(geometry.tangents.len(), count);
+        assert_eq!(geometry.indices.len() % 3, 0);
+        assert!(geometry.indices.iter().all(|&index| (index as usize) < count));
+
+        for (normal, tangent) in geometry.normals.iter().zip(&geometry.tangents) {
+            let t = [tangent[0], tangent[1], tangent[2]];
+            assert!(close(length(*normal), 1.0, 1e-4));
+            assert!(close(length(t), 1.0, 1e-4));
+            assert!(close(dot(*normal, t), 0.0, 1e-4));
+            assert!(tangent[3] == 1.0 || tangent[3] == -1.0);
+        }
+
+        for triangle in geometry.indices.chunks_exact(3) {
+            let [a, b, c] = [0, 1, 2].map(|corner| geometry.positions[triangle[corner] as usize]);
+            let face = cross(sub(b, a), sub(c, a));
+            assert!(length(face) > 0.0, "Degenerate triangle");
+
+            let normal = triangle
+                .iter()
+                .fold([0.0; 3], |sum, &index| add(sum, geometry.normals[index as usize]));
+            assert!(dot(face, normal) > 0.0, "Triangle faces away from its normals");
+        }
+    }
+
+    /// Enclosed volume, positive when triangles face outward
+    fn volume(geometry: &Geometry) -> f32 {
+        geometry
+            .indices
+            .chunks_exact(3)
+            .map(|triangle| {
+                let [a, b, c] = [0, 1, 2].map(|corner| geometry.positions[triangle[corner] as usize]);
+                dot(a, cross(b, c)) / 6.0
+            })
+            .sum()
+    }
+
+    fn bounds(geometry: &Geometry) -> Aabb {
+        Aabb::from_points(geometry.positions.iter().copied()).unwrap()
+    }
+
+    #[test]
+    fn cube() {
+        let cube = Geometry::cube([2.0, 4.0, 6.0]);
+        check(&cube);
+
+        assert_eq!(cube.vertex_count(), 24);
+        assert_eq!(cube.indices.len(), 36);
+        assert_eq!(bounds(&cube), Aabb::new([-1.0, -2.0, -3.0], [1.0, 2.0, 3.0]));
+        assert!(close(volume(&cube), 48.0, 1e-3));
+    }
+
+    #[test]
+    fn grid() {
+        let grid = Geometry::grid([4.0, 2.0], [4, 2]);
+        check(&grid);
+
+        assert_eq!(grid.vertex_count(), 15);
+        assert_eq!(grid.indices.len(), 4 * 2 * 6);
+        assert_eq!(bounds(&grid), Aabb::new([-2.0, 0.0, -1.0], [2.0, 0.0, 1.0]));
+        assert!(grid.tangents.iter().all(|t| *t == [1.0, 0.0, 0.0, 1.0] || *t == [1.0, 0.0, 0.0, -1.0]));
+        assert_eq!(Geometry::plane([1.0, 1.0]).indices.len(), 6);
+    }
+
+    #[test]
+    fn spheres() {
+        for sphere in [Geometry::uv_sphere(2.0, 32, 16), Geometry::ico_sphere(2.0, 3)] {
+            check(&sphere);
+
+            assert!(sphere.positions.iter().all(|p| close(length(*p), 2.0, 1e-4)));
+            assert!(sphere.uvs.iter().all(|uv| (0.0..=1.5).contains(&uv[0]) && (0.0..=1.0).contains(&uv[1])));
+            let exact = 4.0 / 3.0 * PI * 8.0;
+            assert!(volume(&sphere) > exact * 0.97 && volume(&sphere) <= exact);
+        }
+
+        // 20 * 4^3 triangles
+        assert_eq!(Geometry::ico_sphere(1.0, 3).indices.len(), 1280 * 3);
+        assert_eq!(Geometry::ico_sphere(1.0, 0).indices.len(), 60);
+    }
+
+    #[test]
+    fn cylinder_and_cone() {
+        let cylinder = Geometry::cylinder(1.0, 2.0, 64);
+        check(&cylinder);
+        assert_eq!(bounds(&cylinder).min[1], -1.0);
+        assert_eq!(bounds(&cylinder).max[1], 1.0);
+        assert!(close(volume(&cylinder), PI * 2.0, 0.02));
+
+        let cone = Geometry::cone(1.0, 3.0, 64);
+        check(&cone);
+        assert!(close(bounds(&cone).max[1], 1.5, 1e-6));
+        assert!(close(volume(&cone), PI, 0.02));
+    }
+
+    #[test]
+    fn capsule_and_torus() {
+        let capsule = Geometry::capsule(0.5, 2.0, 32, 8);
+        check(&capsule);
+        assert!(close(bounds(&capsule).max[1], 1.5, 1e-5));
+        let exact = PI * 0.25 * 2.0 + 4.0 / 3.0 * PI * 0.125;
+        assert!(close(volume(&capsule), exact, exact * 0.03));
+        assert!(capsule.uvs.windows(2).all(|pair| pair[0][1] <= pair[1][1]));
+
+        let torus = Geometry::torus(2.0, 0.5, 48, 24);
+        check(&torus);
+        for position in &torus.positions {
+            let ring = [position[0], 0.0, position[2]];
+            let center = scale(normalize(ring), 2.0);
+            assert!(close(length(sub(*position, center)), 0.5, 1e-4));
+        }
+        let exact = 2.0 * PI * PI * 2.0 * 0.25;
+        assert!(close(volume(&torus), exact, exact * 0.02));
+    }
+
+    #[test]
+    fn arrow() {
+        let arrow = Geometry::arrow(2.0, 0.05, 0.1, 0.4, 16);
+        check(&arrow);
+
+        let aabb = bounds(&arrow);
+        assert!(close(aabb.min[1], 0.0, 1e-6));
+        assert!(close(aabb.max[1], 2.0, 1e-6));
+        assert!(close(aabb.max[0], 0.1, 1e-6));
+    }
+
+    #[test]
+    fn tangents_follow_u() {
+        let sphere = Geometry::uv_sphere(1.0, 16, 8);
+        // Equator vertex at +X, `u` grows toward -Z
+        let index = sphere
+            .positions
+            .iter()
+            .position(|p| close(p[0], 1.0, 1e-5) && close(p[1], 0.0, 1e-5))
+            .unwrap();
+        let tangent = sphere.tangents[index];
+
+        assert!(close(tangent[2], -1.0, 1e-4));
+        // `v` grows downward
+        let bitangent = scale(cross(sphere.normals[index], [tangent[0], tangent[1], tangent[2]]), tangent[3]);
+        assert!(close(bitangent[1], -1.0, 1e-4));
+    }
+
+    #[test]
+    fn vertex_layouts() {
+        let cube = Geometry::cube([1.0; 3]);
+
+        let pbr = cube.to_pbr_vertices([1.0, 0.0, 0.0, 1.0]);
+        assert_eq!(pbr.len(), 24);
+        assert_eq!(pbr[0].pos[3], 1.0);
+        assert_eq!(pbr[0].tangent, cube.tangents[0]);
+        assert_eq!(cube.to_vertices([1.0; 3])[5].normal, cube.normals[5]);
+        assert_eq!(cube.to_texture_vertices()[7].uv, cube.uvs[7]);
+    }
+}
@@ -0,0 +1,526 @@
+//! Normals, tangents, welding and index reordering of imported meshes
+
+use std::collections::{HashMap, VecDeque};
+
+use super::Geometry;
+use crate::math::{add, cross, dot, length, normalize, scale, sub, Vec3};
+
+/// Size of the LRU cache [`Geometry::optimize_vertex_cache`] plans for
+const CACHE_SIZE: usize = 32;
+
+impl Geometry {
+    /// Normals averaged from the triangles around each position, weighted by
+    /// the angle of the triangle at the vertex
+    ///
+    /// Vertices split on the same position, along a UV seam for example,
+    /// get the same normal. Vertices of no triangle point up `+Y`, tangents
+    /// should be computed again
+    pub fn compute_smooth_normals(&mut self) {
+        let mut sums: HashMap<[u32; 3], Vec3> = HashMap::new();
+
+        for triangle in self.indices.chunks_exact(3) {
+            let p = [0, 1, 2].map(|corner| self.positions[triangle[corner] as usize]);
+            let face = normalize(cross(sub(p[1], p[0]), sub(p[2], p[0])));
+
+            for corner in 0..3 {
+                let sum = sums.entry(position_key(p[corner])).or_default();
+                *sum = add(*sum, scale(face, corner_angle(&p, corner)));
+            }
+        }
+
+        self.normals = self
+            .positions
+            .iter()
+            .map(|&position| match sums.get(&position_key(position)).map(|&sum| normalize(sum)) {
+                Some(normal) if length(normal) > 0.5 => normal,
+                _ => [0.0, 1.0, 0.0],
+            })
+            .collect();
+    }
+
+    /// Normals of the triangles, every triangle gets its own three vertices
+    ///
+    /// Tangents present are computed again
+    pub fn compute_flat_normals(&mut self) {
+        let corners = std::mem::take(&mut self.indices);
+
+        self.positions = corners.iter().map(|&i| self.positions[i as usize]).collect();
+        if !self.uvs.is_empty() {
+            self.uvs = corners.iter().map(|&i| self.uvs[i as usize]).collect();
+        }
+        self.indices = (0..corners.len() as u32).collect();
+        self.normals = self
+            .positions
+            .chunks_exact(3)
+            .flat_map(|p| {
+                let normal = normalize(cross(sub(p[1], p[0]), sub(p[2], p[0])));
+                [normal; 3]
+            })
+            .collect();
+
+        if !self.tangents.is_empty() {
+            self.compute_tangents();
+        }
+    }
+
+    /// Tangents along increasing `u`, computed the way `MikkTSpace` does for
+    /// vertices whose triangles agree on the UV orientation
+    ///
+    /// Each triangle adds its tangent projected on the vertex normal,
+    /// weighted by the corner angle, `w` follows the orientation of the UVs.
+    /// Vertices without UV gradient get any tangent orthogonal to the normal
+    ///
+    /// # Panics
+    /// - if normals are missing, see [`Geometry::compute_smooth_normals`]
+    pub fn compute_tangents(&mut self) {
+        let count = self.vertex_count();
+        assert_eq!(self.normals.len(), count, "Tangents need normals");
+
+        let mut tangents = vec![[0.0; 3]; count];
+        let mut orientation = vec![0.0f32; count];
+
+        if self.uvs.len() == count {
+            for triangle in self.indices.chunks_exact(3) {
+                let i = [0, 1, 2].map(|corner| triangle[corner] as usize);
+                let p = i.map(|i| self.positions[i]);
+                let uv = i.map(|i| self.uvs[i]);
+
+                let (e1, e2) = (sub(p[1], p[0]), sub(p[2], p[0]));
+                let (du1, dv1) = (uv[1][0] - uv[0][0], uv[1][1] - uv[0][1]);
+                let (du2, dv2) = (uv[2][0] - uv[0][0], uv[2][1] - uv[0][1]);
+
+                let det = du1 * dv2 - du2 * dv1;
+                if det.abs() <= f32::EPSILON {
+                    continue;
+                }
+                let tangent = scale(sub(scale(e1, dv2), scale(e2, dv1)), 1.0 / det);
+
+                for (corner, &vertex) in i.iter().enumerate() {
+                    let n = self.normals[vertex];
+                    let projected = normalize(sub(tangent, scale(n, dot(n, tangent))));
+                    let angle = corner_angle(&p, corner);
+
+                    tangents[vertex] = add(tangents[vertex], scale(projected, angle));
+                    orientation[vertex] += det.signum() * angle;
+                }
+            }
+        }
+
+        self.tangents = (0..count)
+            .map(|i| {
+                let n = self.normals[i];
+                let t = normalize(sub(tangents[i], scale(n, dot(n, tangents[i]))));
+                let t = if length(t) > 0.5 { t } else { orthogonal(n) };
+                let w = if orientation[i] < 0.0 { -1.0 } else { 1.0 };
+                [t[0], t[1], t[2], w]
+            })
+            .collect();
+    }
+
+    /// Merge vertices whose attributes all differ by at most `epsilon`,
+    /// `0.0` merges exact duplicates only
+    ///
+    /// Triangles collapsed by the merge are removed. Merged vertices keep
+    /// the attributes of the first one
+    pub fn weld(&mut self, epsilon: f32) {
+        let cell = |p: Vec3| -> [i64; 3] {
+            if epsilon > 0.0 {
+                p.map(|value| (value / epsilon).floor() as i64)
+            } else {
+                p.map(|value| (value + 0.0).to_bits() as i64)
+            }
+        };
+        let neighbors: &[i64] = if epsilon > 0.0 { &[-1, 0, 1] } else { &[0] };
+
+        let mut welded = Geometry::default();
+        let mut grid: HashMap<[i64; 3], Vec<u32>> = HashMap::new();
+        let mut remap = Vec::with_capacity(self.vertex_count());
+
+        for vertex in 0..self.vertex_count() {
+            let key = cell(self.positions[vertex]);
+
+            let mut found = None;
+            'search: for &x in neighbors {
+                for &y in neighbors {
+                    for &z in neighbors {
+                        let Some(candidates) = grid.get(&[key[0] + x, key[1] + y, key[2] + z]) else {
+                            continue;
+                        };
+                        if let Some(&candidate) = candidates
+                            .iter()
+                            .find(|&&candidate| self.same_vertex(vertex, &welded, candidate as usize, epsilon))
+                        {
+                            found = Some(candidate);
+                            break 'search;
+                        }
+                    }
+                }
+            }
+
+            let index = found.unwrap_or_else(|| {
+                let index = welded.positions.len() as u32;
+                welded.positions.push(self.positions[vertex]);
+                if !self.normals.is_empty() {
+                    welded.normals.push(self.normals[vertex]);
+                }
+                if !self.uvs.is_empty() {
+                    welded.uvs.push(self.uvs[vertex]);
+                }
+                if !self.tangents.is_empty() {
+                    welded.tangents.push(self.tangents[vertex]);
+                }
+                grid.entry(key).or_default().push(index);
+                index
+            });
+            remap.push(index);
+        }
+
+        welded.indices = self
+            .indices
+            .chunks_exact(3)
+            .map(|triangle| [0, 1, 2].map(|corner| remap[triangle[corner] as usize]))
+            .filter(|[a, b, c]| a != b && b != c && c != a)
+            .flatten()
+            .collect();
+
+        *self = welded;
+    }
+
+    fn same_vertex(&self, vertex: usize, welded: &Geometry, candidate: usize, epsilon: f32) -> bool {
+        let near = |a: &[f32], b: &[f32]| a.iter().zip(b).all(|(a, b)| (a - b).abs() <= epsilon);
+
+        near(&self.positions[vertex], &welded.positions[candidate])
+            && (self.normals.is_empty() || near(&self.normals[vertex], &welded.normals[candidate]))
+            && (self.uvs.is_empty() || near(&self.uvs[vertex], &welded.uvs[candidate]))
+            && (self.tangents.is_empty() || near(&self.tangents[vertex], &welded.tangents[candidate]))
+    }
+
+    /// Reorder triangles so vertices are reused while still in the
+    /// post-transform cache, Tom Forsyth's linear-speed algorithm
+    ///
+    /// Triangles keep their corners and winding, only their order changes
+    pub fn optimize_vertex_cache(&mut self) {
+        let triangle_count = self.indices.len() / 3;
+        let vertex_count = self.indices.iter().max().map_or(0, |&max| max as usize + 1);
+
+        let mut triangles_of: Vec<Vec<u32>> = vec![vec![]; vertex_count];
+        for (triangle, corners) in self.indices.chunks_exact(3).enumerate() {
+            for &vertex in corners {
+                triangles_of[vertex as usize].push(triangle as u32);
+            }
+        }
+
+        let mut cache_position: Vec<Option<usize>> = vec![None; vertex_count];
+        let mut vertex_scores: Vec<f32> = triangles_of
+            .iter()
+            .map(|triangles| vertex_score(None, triangles.len()))
+            .collect();
+        let mut triangle_scores: Vec<f32> = self
+            .indices
+            .chunks_exact(3)
+            .map(|corners| corners.iter().map(|&v| vertex_scores[v as usize]).sum())
+            .collect();
+
+        let mut emitted = vec![false; triangle_count];
+        let mut order = Vec::with_capacity(self.indices.len());
+        let mut cache: Vec<u32> = vec![];
+        let mut best = None;
+        let mut scan = 0;
+
+        for _ in 0..triangle_count {
+            // Nothing in the cache left to continue with
+            let triangle = best.unwrap_or_else(|| {
+                while emitted[scan] {
+                    scan += 1;
+                }
+                scan
+            });
+
+            emitted[triangle] = true;
+            let corners = [0, 1, 2].map(|corner| self.indices[triangle * 3 + corner]);
+            order.extend(corners);
+
+            for vertex in corners {
+                triangles_of[vertex as usize].retain(|&t| t as usize != triangle);
+            }
+
+            let mut next: Vec<u32> = corners.to_vec();
+            next.dedup();
+            next.extend(cache.iter().filter(|vertex| !corners.contains(vertex)));
+            let evicted = next.split_off(next.len().min(CACHE_SIZE));
+
+            for (position, &vertex) in next.iter().enumerate() {
+                cache_position[vertex as usize] = Some(position);
+            }
+            for &vertex in &evicted {
+                cache_position[vertex as usize] = None;
+            }
+
+            for &vertex in next.iter().chain(&evicted) {
+                let vertex = vertex as usize;
+                let score = vertex_score(cache_position[vertex], triangles_of[vertex].len());
+                let delta = score - vertex_scores[vertex];
+                vertex_scores[vertex] = score;
+                for &t in &triangles_of[vertex] {
+                    triangle_scores[t as usize] += delta;
+                }
+            }
+
+            best = next
+                .iter()
+                .flat_map(|&vertex| triangles_of[vertex as usize].iter())
+                .map(|&t| t as usize)
+                .max_by(|&a, &b| triangle_scores[a].total_cmp(&triangle_scores[b]));
+            cache = next;
+        }
+
+        self.indices = order;
+    }
+
+    /// Reorder vertices by first use in the index buffer, vertices of no
+    /// triangle are dropped
+    pub fn optimize_vertex_fetch(&mut self) {
+        let mut remap = vec![u32::MAX; self.vertex_count()];
+        let mut order = vec![];
+
+        for index in &mut self.indices {
+            let vertex = *index as usize;
+            if remap[vertex] == u32::MAX {
+                remap[vertex] = order.len() as u32;
+                order.push(vertex);
+            }
+            *index = remap[vertex];
+        }
+
+        fn reorder<T: Copy>(values: &mut Vec<T>, order: &[usize]) {
+            if !values.is_empty() {
+                *values = order.iter().map(|&i| values[i]).collect();
+            }
+        }
+        reorder(&mut self.positions, &order);
+        reorder(&mut self.normals, &order);
+        reorder(&mut self.uvs, &order);
+        reorder(&mut self.tangents, &order);
+    }
+
+    /// Average vertices transformed per triangle with a FIFO cache of
+    /// `cache_size` vertices, 3.0 without any reuse and about 0.5 at best
+    pub fn average_cache_miss_ratio(&self, cache_size: usize) -> f32 {
+        let triangles = self.indices.len() / 3;
+        if triangles == 0 {
+            return 0.0;
+        }
+
+        let mut cache = VecDeque::with_capacity(cache_size);
+        let mut misses = 0;
+        for index in &self.indices {
+            if !cache.contains(index) {
+                misses += 1;
+                if cache.len() == cache_size {
+                    cache.pop_front();
+                }
+                cache.push_back(*index);
+            }
+        }
+
+        misses as f32 / triangles as f32
+    }
+}
+
+/// Key of bit-identical positions, `-0.0` and `0.0` match
+fn position_key(p: Vec3) -> [u32; 3] {
+    p.map(|value| (value + 0.0).to_bits())
+}
+
+/// Angle of the triangle `p` at `corner`
+fn corner_angle(p: &[Vec3; 3], corner: usize) -> f32 {
+    let to_next = normalize(sub(p[(corner + 1) % 3], p[corner]));
+    let to_prev = normalize(sub(p[(corner + 2) % 3], p[corner]));
+    dot(to_next, to_prev).clamp(-1.0, 1.0).acos()
+}
+
+/// Any unit vector orthogonal to unit `n`
+pub(super) fn orthogonal(n: Vec3) -> Vec3 {
+    let axis = if n[0].abs() < 0.9 { [1.0, 0.0, 0.0] } else { [0.0, 1.0, 0.0] };
+    normalize(cross(n, axis))
+}
+
+/// Forsyth's score, recently used vertices and vertices with few triangles
+/// left come first
+fn vertex_score(cache_position: Option<usize>, triangles_left: usize) -> f32 {
+    if triangles_left == 0 {
+        return -1.0;
+    }
+
+    let cache = match cache_position {
+        // The last triangle, a fixed score avoids favoring its own edges
+        Some(position) if position < 3 => 0.75,
+        Some(position) => (1.0 - (position - 3) as f32 / (CACHE_SIZE - 3) as f32).powf(1.5),
+        None => 0.0,
+    };
+
+    cache + 2.0 * (triangles_left as f32).powf(-0.5)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn close(a: Vec3, b: Vec3, eps: f32) -> bool {
+        (0..3).all(|i| (a[i] - b[i]).abs() <= eps)
+    }
+
+    fn triangles(geometry: &Geometry) -> Vec<[Vec3; 3]> {
+        let mut triangles: Vec<_> = geometry
+            .indices
+            .chunks_exact(3)
+            .map(|t| [0, 1, 2].map(|corner| geometry.positions[t[corner] as usize]))
+            .collect();
+        triangles.sort_by(|a, b| a.partial_cmp(b).unwrap());
+        triangles
+    }
+
+    #[test]
+    fn smooth_normals_point_out_of_a_sphere() {
+        let mut sphere = Geometry::uv_sphere(1.0, 24, 12);
+        sphere.normals.clear();
+        sphere.compute_smooth_normals();
+
+        for (position, normal) in sphere.positions.iter().zip(&sphere.normals) {
+            assert!(dot(*position, *normal) > 0.99);
+        }
+
+        // Both sides of the UV seam agree
+        let seam: Vec<_> = (0..sphere.vertex_count())
+            .filter(|&i| close(sphere.positions[i], [0.0, 0.0, 1.0], 1e-3) || sphere.positions[i][2].abs() < 1e-6 && sphere.positions[i][0] > 0.5)
+            .collect();
+        assert!(seam.len() >= 2);
+        for pair in seam.windows(2) {
+            if sphere.positions[pair[0]] == sphere.positions[pair[1]] {
+                assert_eq!(sphere.normals[pair[0]], sphere.normals[pair[1]]);
+            }
+        }
+    }
+
+    #[test]
+    fn flat_normals_split_vertices() {
+        let mut cube = Geometry::cube([2.0; 3]);
+        cube.normals.clear();
+        cube.uvs.clear();
+        cube.tangents.clear();
+        cube.weld(0.0);
+        assert_eq!(cube.vertex_count(), 8);
+
+        cube.compute_smooth_normals();
+        let diagonal = 1.0 / 3f32.sqrt();
+        assert!(cube.normals.iter().all(|n| close(n.map(f32::abs), [diagonal; 3], 1e-5)));
+
+        cube.compute_flat_normals();
+        assert_eq!(cube.vertex_count(), 36);
+        for (triangle, normals) in cube.positions.chunks_exact(3).zip(cube.normals.chunks_exact(3)) {
+            let axis = normals[0].iter().filter(|value| value.abs() == 1.0).count();
+            assert_eq!(axis, 1);
+            // The face lies on the plane of its normal
+            assert!(triangle.iter().all(|p| dot(*p, normals[0]) == 1.0));
+        }
+    }
+
+    #[test]
+    fn tangents_follow_uvs() {
+        let mut grid = Geometry::grid([2.0, 2.0], [2, 2]);
+        grid.compute_tangents();
+        assert!(grid.tangents.iter().all(|t| *t == [1.0, 0.0, 0.0, -1.0]));
+
+        // Mirrored UVs flip the tangent and keep the bitangent
+        for uv in &mut grid.uvs {
+            uv[0] = 1.0 - uv[0];
+        }
+        grid.compute_tangents();
+        assert!(grid.tangents.iter().all(|t| *t == [-1.0, 0.0, 0.0, 1.0]));
+
+        // Generated shapes already hold the same tangents
+        let sphere = Geometry::uv_sphere(1.0, 16, 8);
+        let mut computed = sphere.clone();
+        computed.compute_tangents();
+        for (a, b) in sphere.tangents.iter().zip(&computed.tangents) {
+            assert!(close([a[0], a[1], a[2]], [b[0], b[1], b[2]], 0.05));
+            assert_eq!(a[3], b[3]);
+        }
+
+        let mut bare = Geometry::new(vec![[0.0; 3], [1.0, 0.0, 0.0], [0.0, 0.0, -1.0]], vec![0, 1, 2]);
+        bare.compute_smooth_normals();
+        bare.compute_tangents();
+        assert!(close(bare.normals[0], [0.0, 1.0, 0.0], 1e-6));
+        assert_eq!(dot(bare.normals[0], [bare.tangents[0][0], bare.tangents[0][1], bare.tangents[0][2]]), 0.0);
+    }
+
+    #[test]
+    fn weld_merges_close_vertices() {
+        // Two triangles of a quad without shared vertices, slightly apart
+        let mut quad = Geometry::new(
+            vec![
+                [0.0, 0.0, 0.0],
+                [1.0, 0.0, 0.0],
+                [1.0, 1.0, 0.0],
+                [0.0, 0.0, 0.0005],
+                [1.0, 1.0, 0.0],
+                [0.0, 1.0, 0.0],
+            ],
+            vec![0, 1, 2, 3, 4, 5],
+        );
+
+        let mut exact = quad.clone();
+        exact.weld(0.0);
+        assert_eq!(exact.vertex_count(), 5);
+
+        quad.weld(0.001);
+        assert_eq!(quad.vertex_count(), 4);
+        assert_eq!(quad.indices, vec![0, 1, 2, 0, 2, 3]);
+
+        // Collapsed triangles disappear
+        quad.weld(2.0);
+        assert_eq!(quad.vertex_count(), 1);
+        assert!(quad.indices.is_empty());
+
+        // Different normals keep vertices apart
+        let mut cube = Geometry::cube([1.0; 3]);
+        cube.weld(0.0);
+        assert_eq!(cube.vertex_count(), 24);
+    }
+
+    #[test]
+    fn cache_optimization_keeps_triangles() {
+        let mut grid = Geometry::grid([1.0, 1.0], [48, 48]);
+        // Worst case order: alternate triangles from both ends
+        let count = grid.indices.len() / 3;
+        let shuffled: Vec<u32> = (0..count)
+            .map(|i| if i % 2 == 0 { i / 2 } else { count - 1 - i / 2 })
+            .flat_map(|t| grid.indices[t * 3..t * 3 + 3].to_vec())
+            .collect();
+        grid.indices = shuffled;
+
+        let before = grid.average_cache_miss_ratio(32);
+        let expected = triangles(&grid);
+
+        grid.optimize_vertex_cache();
+        let after = grid.average_cache_miss_ratio(32);
+
+        assert!(after < before * 0.7, "{before} -> {after}");
+        assert_eq!(triangles(&grid), expected);
+    }
+
+    #[test]
+    fn fetch_optimization_orders_by_first_use() {
+        let mut geometry = Geometry::new(
+            vec![[0.0; 3], [1.0, 0.0, 0.0], [2.0, 0.0, 0.0], [3.0, 0.0, 0.0], [4.0, 0.0, 0.0]],
+            vec![3, 1, 4, 4, 1, 0],
+        );
+        let expected = triangles(&geometry);
+
+        geometry.optimize_vertex_fetch();
+
+        assert_eq!(geometry.indices, vec![0, 1, 2, 2, 1, 3]);
+        assert_eq!(geometry.vertex_count(), 4);
+        assert_eq!(triangles(&geometry), expected);
+    }
+}
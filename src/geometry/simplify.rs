@@ -0,0 +1,308 @@
+//! Quadric edge collapse simplification and LOD chains
+
+use std::collections::HashMap;
+
+use super::Geometry;
+use crate::math::{cross, dot, normalize, sub, Vec3};
+use crate::resources::MeshLod;
+
+/// Sum of squared distances to a set of planes, upper triangle of the 4x4
+/// matrix
+#[derive(Clone, Copy, Default)]
+struct Quadric([f64; 10]);
+
+impl Quadric {
+    fn plane(normal: Vec3, point: Vec3) -> Self {
+        let [a, b, c] = normal.map(f64::from);
+        let d = -(a * point[0] as f64 + b * point[1] as f64 + c * point[2] as f64);
+        Self([a * a, a * b, a * c, a * d, b * b, b * c, b * d, c * c, c * d, d * d])
+    }
+
+    fn add(&mut self, other: &Quadric) {
+        for (value, other) in self.0.iter_mut().zip(other.0) {
+            *value += other;
+        }
+    }
+
+    fn error(&self, p: Vec3) -> f64 {
+        let [x, y, z] = p.map(f64::from);
+        let q = &self.0;
+        let error = q[0] * x * x + 2.0 * q[1] * x * y + 2.0 * q[2] * x * z + 2.0 * q[3] * x
+            + q[4] * y * y
+            + 2.0 * q[5] * y * z
+            + 2.0 * q[6] * y
+            + q[7] * z * z
+            + 2.0 * q[8] * z
+            + q[9];
+        error.max(0.0)
+    }
+}
+
+impl Geometry {
+    /// Indices of a coarser mesh with at most `target_index_count` indices,
+    /// collapsing edges while the surface moves by less than `max_error`
+    ///
+    /// Vertices are not changed, the result draws a subset of them with the
+    /// same vertex buffer. Borders and UV seams are kept. Returns the indices
+    /// and the largest error in mesh units
+    ///
+    /// # Example
+    /// ```ignore
+    /// let (coarse, error) = geometry.simplify(geometry.indices.len() / 4, f32::MAX);
+    /// ```
+    pub fn simplify(&self, target_index_count: usize, max_error: f32) -> (Vec<u32>, f32) {
+        let count = self.vertex_count();
+
+        // Vertices sharing a position are one point of the surface
+        let mut ids = HashMap::new();
+        let position_of: Vec<usize> = self
+            .positions
+            .iter()
+            .map(|p| {
+                let next = ids.len();
+                *ids.entry(p.map(|value| (value + 0.0).to_bits())).or_insert(next)
+            })
+            .collect();
+        let position_count = ids.len();
+
+        let mut wedges = vec![0u32; position_count];
+        for &position in &position_of {
+            wedges[position] += 1;
+        }
+
+        // Edges of one triangle are borders, of more than two non-manifold
+        let mut edges: HashMap<(usize, usize), u32> = HashMap::new();
+        for triangle in self.indices.chunks_exact(3) {
+            for corner in 0..3 {
+                let a = position_of[triangle[corner] as usize];
+                let b = position_of[triangle[(corner + 1) % 3] as usize];
+                *edges.entry((a.min(b), a.max(b))).or_default() += 1;
+            }
+        }
+
+        let mut locked: Vec<bool> = wedges.iter().map(|&wedges| wedges > 1).collect();
+        for (&(a, b), &triangles) in &edges {
+            if triangles != 2 {
+                locked[a] = true;
+                locked[b] = true;
+            }
+        }
+
+        let mut quadrics = vec![Quadric::default(); position_count];
+        for triangle in self.indices.chunks_exact(3) {
+            let p = [0, 1, 2].map(|corner| self.positions[triangle[corner] as usize]);
+            let normal = normalize(cross(sub(p[1], p[0]), sub(p[2], p[0])));
+            let plane = Quadric::plane(normal, p[0]);
+            for &index in triangle {
+                quadrics[position_of[index as usize]].add(&plane);
+            }
+        }
+
+        let max_cost = (max_error as f64) * (max_error as f64);
+        let mut indices = self.indices.clone();
+        let mut error = 0.0f64;
+
+        while indices.len() > target_index_count {
+            let mut triangles_of: Vec<Vec<usize>> = vec![vec![]; count];
+            for (triangle, corners) in indices.chunks_exact(3).enumerate() {
+                for &vertex in corners {
+                    triangles_of[vertex as usize].push(triangle);
+                }
+            }
+
+            // Both directions of every edge that can move its first vertex
+            let mut candidates = vec![];
+            for triangle in indices.chunks_exact(3) {
+                for corner in 0..3 {
+                    let (from, to) = (triangle[corner] as usize, triangle[(corner + 1) % 3] as usize);
+                    for (from, to) in [(from, to), (to, from)] {
+                        if locked[position_of[from]] {
+                            continue;
+                        }
+                        let mut quadric = quadrics[position_of[from]];
+                        quadric.add(&quadrics[position_of[to]]);
+                        candidates.push((quadric.error(self.positions[to]), from, to));
+                    }
+                }
+            }
+            candidates.sort_by(|a, b| a.0.total_cmp(&b.0));
+
+            let mut remap: Vec<u32> = (0..count as u32).collect();
+            let mut touched = vec![false; count];
+            let mut remaining = indices.len();
+            let mut collapsed = false;
+
+            for (cost, from, to) in candidates {
+                if cost > max_cost || remaining <= target_index_count {
+                    break;
+                }
+                if touched[from] || touched[to] {
+                    continue;
+                }
+
+                let around = &triangles_of[from];
+                let corners = |t: usize| [0, 1, 2].map(|corner| indices[t * 3 + corner] as usize);
+
+                // Moving `from` onto `to` must not flip a remaining triangle
+                let mut removed = 0;
+                let mut flips = false;
+                for &t in around {
+                    let vertices = corners(t);
+                    if vertices.iter().any(|&v| position_of[v] == position_of[to]) {
+                        removed += 3;
+                        continue;
+                    }
+                    let before = vertices.map(|v| self.positions[v]);
+                    let after = vertices.map(|v| if v == from { self.positions[to] } else { self.positions[v] });
+                    let normal = |p: [Vec3; 3]| cross(sub(p[1], p[0]), sub(p[2], p[0]));
+                    if dot(normal(before), normal(after)) <= 0.0 {
+                        flips = true;
+                        break;
+                    }
+                }
+                if flips {
+                    continue;
+                }
+
+                remap[from] = to as u32;
+                let merged = quadrics[position_of[from]];
+                quadrics[position_of[to]].add(&merged);
+                error = error.max(cost);
+                remaining -= removed;
+                collapsed = true;
+
+                // Neighbors keep their positions until the next pass
+                for &t in around {
+                    for v in corners(t) {
+                        touched[v] = true;
+                    }
+                }
+            }
+
+            if !collapsed {
+                break;
+            }
+
+            indices = indices
+                .chunks_exact(3)
+                .map(|triangle| triangle.iter().map(|&v| remap[v as usize]).collect::<Vec<_>>())
+                .filter(|t| {
+                    let [a, b, c] = [0, 1, 2].map(|corner| position_of[t[corner] as usize]);
+                    a != b && b != c && c != a
+                })
+                .flatten()
+                .collect();
+        }
+
+        (indices, error.sqrt() as f32)
+    }
+
+    /// Index buffer holding `levels` LODs, each with about `ratio` of the
+    /// indices of the previous one, see [`MeshDesc::with_lods`]
+    ///
+    /// LOD 0 is the geometry itself. The chain stops early once a level no
+    /// longer gets simpler
+    ///
+    /// # Example
+    /// ```ignore
+    /// let (indices, lods) = geometry.lod_chain(4, 0.5);
+    /// let vertices = geometry.to_pbr_vertices([1.0; 4]);
+    /// let mesh = world.create::<Mesh>(MeshDesc::new(&vertices).with_indices(&indices).with_lods(&lods))?;
+    /// ```
+    ///
+    /// [`MeshDesc::with_lods`]: crate::MeshDesc::with_lods
+    pub fn lod_chain(&self, levels: usize, ratio: f32) -> (Vec<u32>, Vec<MeshLod>) {
+        let mut indices = self.indices.clone();
+        let mut lods = vec![MeshLod {
+            first_index: 0,
+            index_count: indices.len() as u32,
+            error: 0.0,
+        }];
+
+        let mut target = self.indices.len() as f32;
+        for _ in 1..levels {
+            target *= ratio;
+            // Simplified from the full mesh, errors are not summed
+            let (lod, error) = self.simplify(target as usize, f32::MAX);
+
+            let previous = lods.last().unwrap().index_count as usize;
+            if lod.is_empty() || lod.len() as f32 > previous as f32 * 0.9 {
+                break;
+            }
+
+            lods.push(MeshLod {
+                first_index: indices.len() as u32,
+                index_count: lod.len() as u32,
+                error: error.max(lods.last().unwrap().error),
+            });
+            indices.extend(lod);
+        }
+
+        (indices, lods)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::math::length;
+
+    #[test]
+    fn flat_grid_collapses_to_few_triangles() {
+        let grid = Geometry::grid([1.0, 1.0], [16, 16]);
+        let (indices, error) = grid.simplify(0, 1e-4);
+
+        // Borders are kept, the inside is flat and free to collapse
+        assert!(indices.len() < grid.indices.len() / 3, "{}", indices.len());
+        assert!(error < 1e-4);
+
+        let area: f32 = indices
+            .chunks_exact(3)
+            .map(|t| {
+                let p = [0, 1, 2].map(|corner| grid.positions[t[corner] as usize]);
+                let normal = cross(sub(p[1], p[0]), sub(p[2], p[0]));
+                assert!(normal[1] > 0.0, "Flipped triangle");
+                length(normal) * 0.5
+            })
+            .sum();
+        assert!((area - 1.0).abs() < 1e-4);
+    }
+
+    #[test]
+    fn sphere_error_grows_with_reduction() {
+        let sphere = Geometry::uv_sphere(1.0, 32, 16);
+
+        let (half, half_error) = sphere.simplify(sphere.indices.len() / 2, f32::MAX);
+        let (quarter, quarter_error) = sphere.simplify(sphere.indices.len() / 4, f32::MAX);
+
+        assert!(half.len() <= sphere.indices.len() / 2);
+        assert!(quarter.len() <= sphere.indices.len() / 4);
+        assert!(half_error <= quarter_error);
+        // Quadrics sum over merged planes, the error bounds the distance
+        assert!(quarter_error > 0.0 && quarter_error < 1.0);
+
+        // Nothing collapses under a zero error on a curved surface
+        let (same, error) = sphere.simplify(0, 0.0);
+        assert_eq!(same.len(), sphere.indices.len());
+        assert_eq!(error, 0.0);
+    }
+
+    #[test]
+    fn lod_chain_concatenates_levels() {
+        let sphere = Geometry::ico_sphere(1.0, 3);
+        let (indices, lods) = sphere.lod_chain(4, 0.5);
+
+        assert_eq!(lods.len(), 4);
+        assert_eq!(lods[0].index_count as usize, sphere.indices.len());
+        assert_eq!(lods[0].error, 0.0);
+        for pair in lods.windows(2) {
+            assert_eq!(pair[1].first_index, pair[0].first_index + pair[0].index_count);
+            assert!(pair[1].index_count < pair[0].index_count);
+            assert!(pair[1].error >= pair[0].error);
+        }
+
+        let last = lods.last().unwrap();
+        assert_eq!(indices.len() as u32, last.first_index + last.index_count);
+        assert!(indices.iter().all(|&index| (index as usize) < sphere.vertex_count()));
+    }
+}
@@ -2,21 +2,49 @@
 pub(crate) mod bindless;
 pub(crate) mod temporal;
 pub(crate) mod camera;
+pub(crate) mod canvas;
+pub(crate) mod capture;
 pub(crate) mod core;
+pub(crate) mod debug_draw;
+pub(crate) mod math;
 pub(crate) mod frame_graph;
 pub(crate) mod frame_values;
+pub(crate) mod geometry;
 pub(crate) mod per_frame;
 pub(crate) mod render_context;
 pub(crate) mod resources;
+#[cfg(feature = "egui")]
+pub(crate) mod ui;
 pub(crate) mod world_renderer;
 
-pub use core::{VulkanError, VulkanResult};
+#[cfg(any(test, feature = "testing"))]
+pub mod testing;
+
+pub use camera::{Camera, CameraController, FlyController, OrbitController, PanZoomController, Projection};
+pub use canvas::{Canvas, Colorbar, ColorbarTick, Paint, Path, Stroke};
+pub use capture::Screenshot;
+pub use core::{CaptureError, FontError, VulkanError, VulkanResult};
+pub use debug_draw::DebugDraw;
+pub use geometry::Geometry;
 pub use temporal::TemporalFrameGraph;
-pub use frame_graph::{ComputePass, PresentPass, RasterPass, Scissor, Viewport, Handle, FrameGraphTexture, BackBuffer, RenderTargetsDesc};
-pub use resources::{Mesh, MeshDesc, RasterPipeline, RasterPipelineDesc, Res, ShaderType, Transform, TransformDesc, VertexInput};
+pub use frame_graph::{CanvasPass, ComputePass, DebugPass, PresentPass, RasterPass, Scissor, Viewport, Handle, FrameGraphTexture, BackBuffer, RenderTargetsDesc};
+#[cfg(feature = "egui")]
+pub use frame_graph::EguiPass;
+/// The egui version [`EguiPass`] draws
+#[cfg(feature = "egui")]
+pub use egui;
+pub use resources::{
+    CloudPoint, Colormap, InstanceBuffer, InstanceBufferDesc, HeightField, HeightFieldDesc, IsoVertex, Isosurface, IsosurfaceDesc, IsosurfaceMesh, LineCap, LineJoin, Mesh, MeshDesc, MeshLod, OpacityCurve, PointCloud, PointCloudDesc, PointColor, PointShape, Polyline, PolylineDesc, RasterPipeline, RasterPipelineDesc, Res, SceneMesh, SceneMeshDesc, SceneObject,
+    SceneObjectDesc, ScalarRange, ShaderType, Submesh, Texture, TextureDesc, TextureFormat, Transform, TransformDesc, VertexInput, Volume, VolumeDesc,
+    marching_cubes,
+};
+#[cfg(feature = "text")]
+pub use resources::{Font, FontDesc, Text, TextAlign, TextAnchor, TextDesc, VerticalAlign};
 pub use world_renderer::WorldRenderer;
 
 /// Basic types
 pub mod types {
     pub use super::core::{PbrVertex, TextureVertex, Vertex};
+    pub use super::resources::Index;
+    pub use super::math::{Aabb, Frustum, Mat4, Quat, Sphere, Vec3};
 }
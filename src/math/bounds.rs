@@ -0,0 +1,293 @@
+//! Bounding volumes and view frustum tests
+
+use super::{add, dot, length, scale, sub, transform_point, Mat4, Vec3};
+
+/// Axis-aligned bounding box
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Aabb {
+    /// Smallest corner
+    pub min: Vec3,
+    /// Largest corner
+    pub max: Vec3,
+}
+
+impl Aabb {
+    /// Box between two corners
+    pub fn new(min: Vec3, max: Vec3) -> Self {
+        Self { min, max }
+    }
+
+    /// Smallest box holding every point, `None` without points
+    pub fn from_points<I: IntoIterator<Item = Vec3>>(points: I) -> Option<Self> {
+        let mut points = points.into_iter();
+        let first = points.next()?;
+
+        Some(points.fold(Self::new(first, first), |aabb, p| Self {
+            min: [aabb.min[0].min(p[0]), aabb.min[1].min(p[1]), aabb.min[2].min(p[2])],
+            max: [aabb.max[0].max(p[0]), aabb.max[1].max(p[1]), aabb.max[2].max(p[2])],
+        }))
+    }
+
+    /// Middle of the box
+    pub fn center(&self) -> Vec3 {
+        scale(add(self.min, self.max), 0.5)
+    }
+
+    /// Half of the size on every axis
+    pub fn extents(&self) -> Vec3 {
+        scale(sub(self.max, self.min), 0.5)
+    }
+
+    /// Smallest box holding both boxes
+    pub fn union(&self, other: &Aabb) -> Self {
+        Self {
+            min: [0, 1, 2].map(|i| self.min[i].min(other.min[i])),
+            max: [0, 1, 2].map(|i| self.max[i].max(other.max[i])),
+        }
+    }
+
+    /// Box around the transformed box, larger than the transformed corners
+    /// when `m` rotates
+    pub fn transform(&self, m: &Mat4) -> Self {
+        let center = transform_point(m, self.center());
+        let e = self.extents();
+
+        let mut extents = [0.0; 3];
+        for (row, extent) in extents.iter_mut().enumerate() {
+            *extent = (0..3).map(|col| m[col][row].abs() * e[col]).sum();
+        }
+
+        Self::new(sub(center, extents), add(center, extents))
+    }
+}
+
+/// Bounding sphere
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Sphere {
+    /// Center
+    pub center: Vec3,
+    /// Radius
+    pub radius: f32,
+}
+
+impl Sphere {
+    /// Sphere of `radius` around `center`
+    pub fn new(center: Vec3, radius: f32) -> Self {
+        Self { center, radius }
+    }
+
+    /// Sphere centered on the bounding box of the points, `None` without
+    /// points
+    pub fn from_points<I>(points: I) -> Option<Self>
+    where
+        I: IntoIterator<Item = Vec3>,
+        I::IntoIter: Clone,
+    {
+        let points = points.into_iter();
+        let center = Aabb::from_points(points.clone())?.center();
+        let radius = points.map(|p| length(sub(p, center))).fold(0.0, f32::max);
+
+        Some(Self::new(center, radius))
+    }
+
+    /// Smallest sphere holding both spheres
+    pub fn merge(&self, other: &Sphere) -> Self {
+        let offset = sub(other.center, self.center);
+        let distance = length(offset);
+
+        if distance + other.radius <= self.radius {
+            return *self;
+        }
+        if distance + self.radius <= other.radius {
+            return *other;
+        }
+
+        let radius = (distance + self.radius + other.radius) * 0.5;
+        let center = add(self.center, scale(offset, (radius - self.radius) / distance));
+
+        Self::new(center, radius)
+    }
+
+    /// Sphere around the transformed sphere, the radius grows with the
+    /// largest scale of `m`
+    pub fn transform(&self, m: &Mat4) -> Self {
+        let axis = |col: usize| length([m[col][0], m[col][1], m[col][2]]);
+        let scale = axis(0).max(axis(1)).max(axis(2));
+
+        Self::new(transform_point(m, self.center), self.radius * scale)
+    }
+}
+
+/// Six planes bounding the volume a camera sees
+///
+/// Planes are `[nx, ny, nz, d]` with normals pointing inside, a point is
+/// inside when `dot(n, p) + d >= 0` for every plane
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Frustum {
+    planes: [[f32; 4]; 6],
+}
+
+impl Frustum {
+    /// Planes of a reversed-Z `proj * view` matrix, points come out in world
+    /// space
+    ///
+    /// The far plane of an infinite projection has no normal and never culls
+    pub fn from_view_proj(m: &Mat4) -> Self {
+        let row = |i: usize| [m[0][i], m[1][i], m[2][i], m[3][i]];
+        let combine = |a: [f32; 4], b: [f32; 4], s: f32| [a[0] + b[0] * s, a[1] + b[1] * s, a[2] + b[2] * s, a[3] + b[3] * s];
+
+        let (r0, r1, r2, r3) = (row(0), row(1), row(2), row(3));
+        let planes = [
+            combine(r3, r0, 1.0),
+            combine(r3, r0, -1.0),
+            combine(r3, r1, 1.0),
+            combine(r3, r1, -1.0),
+            // Reversed-Z: far at z = 0, near at z = w
+            r2,
+            combine(r3, r2, -1.0),
+        ]
+        .map(|plane| {
+            let len = length([plane[0], plane[1], plane[2]]);
+            if len > f32::EPSILON {
+                plane.map(|value| value / len)
+            } else {
+                [0.0, 0.0, 0.0, 1.0]
+            }
+        });
+
+        Self { planes }
+    }
+
+    /// Four side planes followed by the far and near planes
+    pub fn planes(&self) -> &[[f32; 4]; 6] {
+        &self.planes
+    }
+
+    fn distance(plane: &[f32; 4], p: Vec3) -> f32 {
+        dot([plane[0], plane[1], plane[2]], p) + plane[3]
+    }
+
+    /// Point is inside or on every plane
+    pub fn contains_point(&self, p: Vec3) -> bool {
+        self.planes.iter().all(|plane| Self::distance(plane, p) >= 0.0)
+    }
+
+    /// `false` only when the sphere is fully outside one plane
+    pub fn intersects_sphere(&self, sphere: &Sphere) -> bool {
+        self.planes
+            .iter()
+            .all(|plane| Self::distance(plane, sphere.center) >= -sphere.radius)
+    }
+
+    /// `false` only when the box is fully outside one plane
+    pub fn intersects_aabb(&self, aabb: &Aabb) -> bool {
+        self.planes.iter().all(|plane| {
+            // Corner furthest along the plane normal
+            let p = [0, 1, 2].map(|axis| if plane[axis] >= 0.0 { aabb.max[axis] } else { aabb.min[axis] });
+            Self::distance(plane, p) >= 0.0
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::math::{compose, look_at, mul, orthographic, perspective, quat_from_axis_angle};
+
+    fn camera(far: f32) -> Frustum {
+        let view = look_at([0.0, 0.0, 5.0], [0.0, 0.0, 0.0], [0.0, 1.0, 0.0]);
+        let proj = perspective(90f32.to_radians(), 1.0, 0.1, far);
+        Frustum::from_view_proj(&mul(&proj, &view))
+    }
+
+    fn close(a: Vec3, b: Vec3) -> bool {
+        (0..3).all(|i| (a[i] - b[i]).abs() < 1e-4)
+    }
+
+    #[test]
+    fn aabb_from_points() {
+        let aabb = Aabb::from_points([[1.0, -2.0, 0.0], [-1.0, 3.0, 0.5], [0.0, 0.0, -4.0]]).unwrap();
+
+        assert_eq!(aabb, Aabb::new([-1.0, -2.0, -4.0], [1.0, 3.0, 0.5]));
+        assert_eq!(aabb.center(), [0.0, 0.5, -1.75]);
+        assert!(Aabb::from_points([]).is_none());
+    }
+
+    #[test]
+    fn aabb_transform_covers_rotated_box() {
+        let aabb = Aabb::new([-1.0, -1.0, -1.0], [1.0, 1.0, 1.0]);
+        let m = compose([10.0, 0.0, 0.0], quat_from_axis_angle([0.0, 0.0, 1.0], 45f32.to_radians()), [2.0, 1.0, 1.0]);
+
+        let moved = aabb.transform(&m);
+        let half = 3.0 / 2f32.sqrt();
+
+        assert!(close(moved.center(), [10.0, 0.0, 0.0]));
+        assert!(close(moved.extents(), [half, half, 1.0]));
+    }
+
+    #[test]
+    fn sphere_from_points_and_transform() {
+        let sphere = Sphere::from_points([[-1.0, 0.0, 0.0], [1.0, 0.0, 0.0], [0.0, 0.5, 0.0]]).unwrap();
+
+        assert_eq!(sphere.center, [0.0, 0.25, 0.0]);
+        assert!((sphere.radius - (1.0f32 + 0.0625).sqrt()).abs() < 1e-6);
+
+        let m = compose([0.0, 0.0, 3.0], [0.0, 0.0, 0.0, 1.0], [1.0, 4.0, 2.0]);
+        let moved = Sphere::new([0.0, 1.0, 0.0], 1.0).transform(&m);
+
+        assert!(close(moved.center, [0.0, 4.0, 3.0]));
+        assert_eq!(moved.radius, 4.0);
+    }
+
+    #[test]
+    fn merged_bounds_hold_both() {
+        let a = Aabb::new([0.0, 0.0, 0.0], [1.0, 1.0, 1.0]);
+        let b = Aabb::new([-1.0, 0.5, 0.0], [0.5, 2.0, 0.5]);
+        assert_eq!(a.union(&b), Aabb::new([-1.0, 0.0, 0.0], [1.0, 2.0, 1.0]));
+
+        let merged = Sphere::new([0.0, 0.0, 0.0], 1.0).merge(&Sphere::new([4.0, 0.0, 0.0], 1.0));
+        assert!(close(merged.center, [2.0, 0.0, 0.0]));
+        assert_eq!(merged.radius, 3.0);
+
+        let inner = Sphere::new([0.5, 0.0, 0.0], 0.25);
+        assert_eq!(Sphere::new([0.0, 0.0, 0.0], 1.0).merge(&inner).radius, 1.0);
+        assert_eq!(inner.merge(&Sphere::new([0.0, 0.0, 0.0], 1.0)).radius, 1.0);
+    }
+
+    #[test]
+    fn frustum_culls_spheres() {
+        let frustum = camera(100.0);
+
+        assert!(frustum.contains_point([0.0, 0.0, 0.0]));
+        assert!(frustum.intersects_sphere(&Sphere::new([0.0, 0.0, 0.0], 1.0)));
+        // Behind the camera
+        assert!(!frustum.intersects_sphere(&Sphere::new([0.0, 0.0, 10.0], 1.0)));
+        // Beyond the far plane
+        assert!(!frustum.intersects_sphere(&Sphere::new([0.0, 0.0, -200.0], 1.0)));
+        // Outside the right plane, 90 degrees wide at distance 5
+        assert!(!frustum.intersects_sphere(&Sphere::new([8.0, 0.0, 0.0], 1.0)));
+        // Touching the right plane
+        assert!(frustum.intersects_sphere(&Sphere::new([5.5, 0.0, 0.0], 1.0)));
+        // Above and below
+        assert!(!frustum.intersects_sphere(&Sphere::new([0.0, 8.0, 0.0], 1.0)));
+        assert!(!frustum.intersects_sphere(&Sphere::new([0.0, -8.0, 0.0], 1.0)));
+    }
+
+    #[test]
+    fn infinite_far_plane_never_culls() {
+        let frustum = camera(f32::INFINITY);
+
+        assert!(frustum.intersects_sphere(&Sphere::new([0.0, 0.0, -1.0e6], 1.0)));
+        assert!(!frustum.intersects_sphere(&Sphere::new([0.0, 0.0, 10.0], 1.0)));
+    }
+
+    #[test]
+    fn frustum_culls_boxes() {
+        let frustum = Frustum::from_view_proj(&orthographic(-1.0, 1.0, -1.0, 1.0, 0.0, 10.0));
+
+        assert!(frustum.intersects_aabb(&Aabb::new([0.5, 0.5, -2.0], [3.0, 3.0, -1.0])));
+        assert!(!frustum.intersects_aabb(&Aabb::new([1.5, -1.0, -2.0], [3.0, 1.0, -1.0])));
+        assert!(!frustum.intersects_aabb(&Aabb::new([-1.0, -1.0, 1.0], [1.0, 1.0, 2.0])));
+        assert!(!frustum.intersects_aabb(&Aabb::new([-1.0, -1.0, -20.0], [1.0, 1.0, -11.0])));
+    }
+}
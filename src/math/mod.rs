@@ -0,0 +1,282 @@
+//! Small linear algebra helpers on plain arrays
+//!
+//! Matrices are column-major `[[f32; 4]; 4]`, `m[column][row]`, the same
+//! layout shaders read from uniform buffers
+
+/// Column-major 4x4 matrix
+pub type Mat4 = [[f32; 4]; 4];
+
+/// 3D vector
+pub type Vec3 = [f32; 3];
+
+/// Unit quaternion `[x, y, z, w]`
+pub type Quat = [f32; 4];
+
+mod bounds;
+pub use bounds::{Aabb, Frustum, Sphere};
+
+pub(crate) const IDENTITY: Mat4 = [[1.0, 0.0, 0.0, 0.0], [0.0, 1.0, 0.0, 0.0], [0.0, 0.0, 1.0, 0.0], [0.0, 0.0, 0.0, 1.0]];
+
+pub(crate) fn add(a: Vec3, b: Vec3) -> Vec3 {
+    [a[0] + b[0], a[1] + b[1], a[2] + b[2]]
+}
+
+pub(crate) fn sub(a: Vec3, b: Vec3) -> Vec3 {
+    [a[0] - b[0], a[1] - b[1], a[2] - b[2]]
+}
+
+pub(crate) fn scale(a: Vec3, s: f32) -> Vec3 {
+    [a[0] * s, a[1] * s, a[2] * s]
+}
+
+pub(crate) fn dot(a: Vec3, b: Vec3) -> f32 {
+    a[0] * b[0] + a[1] * b[1] + a[2] * b[2]
+}
+
+pub(crate) fn cross(a: Vec3, b: Vec3) -> Vec3 {
+    [a[1] * b[2] - a[2] * b[1], a[2] * b[0] - a[0] * b[2], a[0] * b[1] - a[1] * b[0]]
+}
+
+pub(crate) fn length(a: Vec3) -> f32 {
+    dot(a, a).sqrt()
+}
+
+/// Zero vector stays zero
+pub(crate) fn normalize(a: Vec3) -> Vec3 {
+    let len = length(a);
+    if len > f32::EPSILON {
+        scale(a, 1.0 / len)
+    } else {
+        a
+    }
+}
+
+/// `a * b`, `b` is applied first
+pub(crate) fn mul(a: &Mat4, b: &Mat4) -> Mat4 {
+    let mut out = [[0.0; 4]; 4];
+    for (col, out_col) in out.iter_mut().enumerate() {
+        for (row, value) in out_col.iter_mut().enumerate() {
+            *value = (0..4).map(|k| a[k][row] * b[col][k]).sum();
+        }
+    }
+    out
+}
+
+pub(crate) fn transpose(m: &Mat4) -> Mat4 {
+    let mut out = [[0.0; 4]; 4];
+    for (col, out_col) in out.iter_mut().enumerate() {
+        for (row, value) in out_col.iter_mut().enumerate() {
+            *value = m[row][col];
+        }
+    }
+    out
+}
+
+/// General inverse by cofactors, singular matrices give identity
+pub(crate) fn inverse(m: &Mat4) -> Mat4 {
+    let a = |c: usize, r: usize| m[c][r];
+
+    let s0 = a(0, 0) * a(1, 1) - a(0, 1) * a(1, 0);
+    let s1 = a(0, 0) * a(1, 2) - a(0, 2) * a(1, 0);
+    let s2 = a(0, 0) * a(1, 3) - a(0, 3) * a(1, 0);
+    let s3 = a(0, 1) * a(1, 2) - a(0, 2) * a(1, 1);
+    let s4 = a(0, 1) * a(1, 3) - a(0, 3) * a(1, 1);
+    let s5 = a(0, 2) * a(1, 3) - a(0, 3) * a(1, 2);
+
+    let c5 = a(2, 2) * a(3, 3) - a(2, 3) * a(3, 2);
+    let c4 = a(2, 1) * a(3, 3) - a(2, 3) * a(3, 1);
+    let c3 = a(2, 1) * a(3, 2) - a(2, 2) * a(3, 1);
+    let c2 = a(2, 0) * a(3, 3) - a(2, 3) * a(3, 0);
+    let c1 = a(2, 0) * a(3, 2) - a(2, 2) * a(3, 0);
+    let c0 = a(2, 0) * a(3, 1) - a(2, 1) * a(3, 0);
+
+    let det = s0 * c5 - s1 * c4 + s2 * c3 + s3 * c2 - s4 * c1 + s5 * c0;
+
+    if det.abs() <= f32::EPSILON * f32::EPSILON {
+        return IDENTITY;
+    }
+
+    let inv = 1.0 / det;
+
+    [
+        [
+            (a(1, 1) * c5 - a(1, 2) * c4 + a(1, 3) * c3) * inv,
+            (-a(0, 1) * c5 + a(0, 2) * c4 - a(0, 3) * c3) * inv,
+            (a(3, 1) * s5 - a(3, 2) * s4 + a(3, 3) * s3) * inv,
+            (-a(2, 1) * s5 + a(2, 2) * s4 - a(2, 3) * s3) * inv,
+        ],
+        [
+            (-a(1, 0) * c5 + a(1, 2) * c2 - a(1, 3) * c1) * inv,
+            (a(0, 0) * c5 - a(0, 2) * c2 + a(0, 3) * c1) * inv,
+            (-a(3, 0) * s5 + a(3, 2) * s2 - a(3, 3) * s1) * inv,
+            (a(2, 0) * s5 - a(2, 2) * s2 + a(2, 3) * s1) * inv,
+        ],
+        [
+            (a(1, 0) * c4 - a(1, 1) * c2 + a(1, 3) * c0) * inv,
+            (-a(0, 0) * c4 + a(0, 1) * c2 - a(0, 3) * c0) * inv,
+            (a(3, 0) * s4 - a(3, 1) * s2 + a(3, 3) * s0) * inv,
+            (-a(2, 0) * s4 + a(2, 1) * s2 - a(2, 3) * s0) * inv,
+        ],
+        [
+            (-a(1, 0) * c3 + a(1, 1) * c1 - a(1, 2) * c0) * inv,
+            (a(0, 0) * c3 - a(0, 1) * c1 + a(0, 2) * c0) * inv,
+            (-a(3, 0) * s3 + a(3, 1) * s1 - a(3, 2) * s0) * inv,
+            (a(2, 0) * s3 - a(2, 1) * s1 + a(2, 2) * s0) * inv,
+        ],
+    ]
+}
+
+/// `m * [p, 1]` without the projective divide
+pub(crate) fn transform_point(m: &Mat4, p: Vec3) -> Vec3 {
+    [
+        m[0][0] * p[0] + m[1][0] * p[1] + m[2][0] * p[2] + m[3][0],
+        m[0][1] * p[0] + m[1][1] * p[1] + m[2][1] * p[2] + m[3][1],
+        m[0][2] * p[0] + m[1][2] * p[1] + m[2][2] * p[2] + m[3][2],
+    ]
+}
+
+/// Right-handed view matrix, the camera looks down `-Z`
+pub(crate) fn look_at(eye: Vec3, target: Vec3, up: Vec3) -> Mat4 {
+    let f = normalize(sub(target, eye));
+    let s = normalize(cross(f, up));
+    let u = cross(s, f);
+
+    [
+        [s[0], u[0], -f[0], 0.0],
+        [s[1], u[1], -f[1], 0.0],
+        [s[2], u[2], -f[2], 0.0],
+        [-dot(s, eye), -dot(u, eye), dot(f, eye), 1.0],
+    ]
+}
+
+/// Reversed-Z perspective projection in Vulkan clip space
+///
+/// `near` maps to depth 1, `far` to depth 0, `far` may be infinite. Y points
+/// down in clip space so world Y up stays up on the screen
+pub(crate) fn perspective(fov_y: f32, aspect: f32, near: f32, far: f32) -> Mat4 {
+    let f = 1.0 / (fov_y * 0.5).tan();
+
+    let (a, b) = if far.is_infinite() {
+        (0.0, near)
+    } else {
+        (near / (far - near), near * far / (far - near))
+    };
+
+    [[f / aspect, 0.0, 0.0, 0.0], [0.0, -f, 0.0, 0.0], [0.0, 0.0, a, -1.0], [0.0, 0.0, b, 0.0]]
+}
+
+/// Reversed-Z orthographic projection in Vulkan clip space
+pub(crate) fn orthographic(left: f32, right: f32, bottom: f32, top: f32, near: f32, far: f32) -> Mat4 {
+    let w = right - left;
+    let h = top - bottom;
+    let d = far - near;
+
+    [
+        [2.0 / w, 0.0, 0.0, 0.0],
+        [0.0, -2.0 / h, 0.0, 0.0],
+        [0.0, 0.0, 1.0 / d, 0.0],
+        [-(right + left) / w, (top + bottom) / h, far / d, 1.0],
+    ]
+}
+
+/// Rotation by `angle` radians around `axis`, counter-clockwise when looking
+/// against the axis
+pub(crate) fn quat_from_axis_angle(axis: Vec3, angle: f32) -> Quat {
+    let axis = normalize(axis);
+    let (sin, cos) = (angle * 0.5).sin_cos();
+    [axis[0] * sin, axis[1] * sin, axis[2] * sin, cos]
+}
+
+/// `translation * rotation * scale`, the quaternion does not have to be
+/// normalized
+pub(crate) fn compose(translation: Vec3, rotation: Quat, scale: Vec3) -> Mat4 {
+    let [x, y, z, w] = rotation;
+    let len = (x * x + y * y + z * z + w * w).sqrt();
+    let (x, y, z, w) = if len > f32::EPSILON {
+        (x / len, y / len, z / len, w / len)
+    } else {
+        (0.0, 0.0, 0.0, 1.0)
+    };
+
+    let (xx, yy, zz) = (x * x, y * y, z * z);
+    let (xy, xz, yz) = (x * y, x * z, y * z);
+    let (wx, wy, wz) = (w * x, w * y, w * z);
+
+    [
+        [(1.0 - 2.0 * (yy + zz)) * scale[0], 2.0 * (xy + wz) * scale[0], 2.0 * (xz - wy) * scale[0], 0.0],
+        [2.0 * (xy - wz) * scale[1], (1.0 - 2.0 * (xx + zz)) * scale[1], 2.0 * (yz + wx) * scale[1], 0.0],
+        [2.0 * (xz + wy) * scale[2], 2.0 * (yz - wx) * scale[2], (1.0 - 2.0 * (xx + yy)) * scale[2], 0.0],
+        [translation[0], translation[1], translation[2], 1.0],
+    ]
+}
+
+/// Inverse transpose, transforms normals of a model matrix
+pub(crate) fn normal_matrix(model: &Mat4) -> Mat4 {
+    transpose(&inverse(model))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Clip space position after the projective divide
+    fn project(m: &Mat4, p: Vec3) -> Vec3 {
+        let w = m[0][3] * p[0] + m[1][3] * p[1] + m[2][3] * p[2] + m[3][3];
+        scale(transform_point(m, p), 1.0 / w)
+    }
+
+    fn close(a: Vec3, b: Vec3) -> bool {
+        (0..3).all(|i| (a[i] - b[i]).abs() < 1e-5)
+    }
+
+    #[test]
+    fn look_at_moves_target_down_negative_z() {
+        let view = look_at([1.0, 2.0, 3.0], [1.0, 2.0, -2.0], [0.0, 1.0, 0.0]);
+
+        assert!(close(transform_point(&view, [1.0, 2.0, 3.0]), [0.0, 0.0, 0.0]));
+        assert!(close(transform_point(&view, [1.0, 2.0, -2.0]), [0.0, 0.0, -5.0]));
+        assert!(close(transform_point(&view, [2.0, 3.0, 3.0]), [1.0, 1.0, 0.0]));
+
+        let side = look_at([0.0, 0.0, 0.0], [1.0, 0.0, 0.0], [0.0, 1.0, 0.0]);
+        assert!(close(transform_point(&side, [0.0, 0.0, 1.0]), [1.0, 0.0, 0.0]));
+    }
+
+    #[test]
+    fn perspective_reverses_depth() {
+        let proj = perspective(90f32.to_radians(), 2.0, 0.5, 10.0);
+
+        assert!(close(project(&proj, [0.0, 0.0, -0.5]), [0.0, 0.0, 1.0]));
+        assert!(close(project(&proj, [0.0, 0.0, -10.0]), [0.0, 0.0, 0.0]));
+        // Y up in view space is up on the screen, which is -Y in clip space
+        let corner = project(&proj, [2.0, 1.0, -1.0]);
+        assert!(close([corner[0], corner[1], 0.0], [1.0, -1.0, 0.0]));
+        assert!((0.0..1.0).contains(&corner[2]));
+    }
+
+    #[test]
+    fn infinite_perspective_approaches_zero() {
+        let proj = perspective(90f32.to_radians(), 1.0, 0.5, f32::INFINITY);
+
+        assert!(close(project(&proj, [0.0, 0.0, -0.5]), [0.0, 0.0, 1.0]));
+        assert!(close(project(&proj, [0.0, 0.0, -5.0]), [0.0, 0.0, 0.1]));
+        assert!(project(&proj, [0.0, 0.0, -1e6])[2] > 0.0);
+    }
+
+    #[test]
+    fn orthographic_maps_box_to_clip_space() {
+        let proj = orthographic(-2.0, 4.0, -1.0, 1.0, 1.0, 5.0);
+
+        assert!(close(project(&proj, [-2.0, -1.0, -1.0]), [-1.0, 1.0, 1.0]));
+        assert!(close(project(&proj, [4.0, 1.0, -5.0]), [1.0, -1.0, 0.0]));
+        assert!(close(project(&proj, [1.0, 0.0, -3.0]), [0.0, 0.0, 0.5]));
+    }
+
+    #[test]
+    fn inverse_undoes_compose() {
+        let m = compose([1.0, -2.0, 3.0], quat_from_axis_angle([1.0, 1.0, 0.0], 0.7), [2.0, 0.5, 1.5]);
+        let p = [0.3, -4.0, 2.0];
+
+        assert!(close(transform_point(&inverse(&m), transform_point(&m, p)), p));
+        assert_eq!(inverse(&[[0.0; 4]; 4]), IDENTITY);
+    }
+}
@@ -0,0 +1,113 @@
+use ash::vk;
+
+use super::PerFrameBuffer;
+use crate::core::{DescriptorPool, DescriptorPoolBuilder, DescriptorSetLayout, DescriptorSetLayoutBuilder, Device, GpuBuffer, VulkanResult};
+
+/// Binding of the camera uniform buffer in set 1
+pub(crate) const CAMERA_BINDING: u32 = 0;
+
+/// Binding of the frame values uniform buffer in set 1
+pub(crate) const FRAME_BINDING: u32 = 1;
+
+/// Binding of the transform storage buffer in set 1
+pub(crate) const TRANSFORMS_BINDING: u32 = 2;
+
+/// Binding of the GPU scene object storage buffer in set 1
+pub(crate) const OBJECTS_BINDING: u32 = 3;
+
+/// Binding of the indirect draws written by the cull pass in set 1
+pub(crate) const DRAW_COMMANDS_BINDING: u32 = 4;
+
+/// Binding of the indirect draw count written by the cull pass in set 1
+pub(crate) const DRAW_COUNT_BINDING: u32 = 5;
+
+/// Descriptor set 1, one copy per frame in flight
+///
+/// Each copy points at the same frame's [`PerFrameBuffer`], so buffers of a
+/// frame are only rewritten after its fence is signaled
+pub(crate) struct PerFrameDescriptors {
+    pub(crate) set_layout: DescriptorSetLayout,
+    pub(crate) sets: Vec<vk::DescriptorSet>,
+    pub(crate) pool: DescriptorPool,
+}
+
+impl PerFrameDescriptors {
+    pub fn new(device: &Device, frame_count: usize) -> VulkanResult<Self> {
+        let bindings = [
+            (CAMERA_BINDING, vk::DescriptorType::UNIFORM_BUFFER),
+            (FRAME_BINDING, vk::DescriptorType::UNIFORM_BUFFER),
+            (TRANSFORMS_BINDING, vk::DescriptorType::STORAGE_BUFFER),
+            (OBJECTS_BINDING, vk::DescriptorType::STORAGE_BUFFER),
+            (DRAW_COMMANDS_BINDING, vk::DescriptorType::STORAGE_BUFFER),
+            (DRAW_COUNT_BINDING, vk::DescriptorType::STORAGE_BUFFER),
+        ]
+        .into_iter()
+        .map(|(binding, ty)| {
+            vk::DescriptorSetLayoutBinding::default()
+                .binding(binding)
+                .descriptor_type(ty)
+                .descriptor_count(1)
+                .stage_flags(vk::ShaderStageFlags::ALL)
+        })
+        .collect::<Vec<_>>();
+
+        let set_layout = DescriptorSetLayoutBuilder::new(device)
+            .bindings(bindings.clone())
+            .build()?;
+
+        let pool_sizes = bindings
+            .iter()
+            .map(|binding| {
+                vk::DescriptorPoolSize::default()
+                    .ty(binding.descriptor_type)
+                    .descriptor_count(binding.descriptor_count * frame_count as u32)
+            })
+            .collect::<Vec<_>>();
+
+        let pool = DescriptorPoolBuilder::new(device)
+            .pool_sizes(&pool_sizes)
+            .max_sets(frame_count as u32)
+            .build()?;
+
+        let layouts = vec![set_layout.raw; frame_count];
+        let sets = pool.create_descriptor_set(device, &layouts)?;
+
+        Ok(Self { set_layout, sets, pool })
+    }
+
+    pub fn get(&self, frame: usize) -> vk::DescriptorSet {
+        self.sets[frame % self.sets.len()]
+    }
+
+    /// Point `binding` of every copy at the matching frame's buffer
+    pub fn write_buffer(&self, device: &Device, binding: u32, ty: vk::DescriptorType, buffer: &PerFrameBuffer) {
+        for (frame, buffer) in buffer.buffers.iter().enumerate() {
+            self.write_frame_buffer(device, frame, binding, ty, buffer);
+        }
+    }
+
+    /// Point `binding` of the copy used by `frame` at `buffer`
+    ///
+    /// The frame must not be in flight
+    pub fn write_frame_buffer(&self, device: &Device, frame: usize, binding: u32, ty: vk::DescriptorType, buffer: &GpuBuffer) {
+        let buffer_info = vk::DescriptorBufferInfo::default()
+            .buffer(buffer.raw)
+            .offset(0)
+            .range(vk::WHOLE_SIZE);
+
+        let write = vk::WriteDescriptorSet::default()
+            .dst_set(self.get(frame))
+            .dst_binding(binding)
+            .descriptor_type(ty)
+            .buffer_info(std::slice::from_ref(&buffer_info));
+
+        unsafe { device.update_descriptor_sets(&[write], &[]) };
+    }
+
+    pub fn destroy(&self, device: &Device) {
+        self.pool.destroy(device);
+        unsafe {
+            device.destroy_descriptor_set_layout(self.set_layout.raw, None);
+        }
+    }
+}
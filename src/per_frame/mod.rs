@@ -1,5 +1,10 @@
 mod buffer;
 pub use buffer::{PerFrameBuffer, PerFrameBufferBuilder};
 
+mod descriptor;
+pub(crate) use descriptor::{
+    PerFrameDescriptors, CAMERA_BINDING, DRAW_COMMANDS_BINDING, DRAW_COUNT_BINDING, FRAME_BINDING, OBJECTS_BINDING, TRANSFORMS_BINDING,
+};
+
 mod command_pool;
 pub use command_pool::CommandPoolPerFrame;
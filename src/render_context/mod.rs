@@ -10,10 +10,10 @@ pub use window_manager::WindowManager;
 mod graphics_device;
 pub use graphics_device::GraphicsDevice;
 
-use crate::core::{
-    App, Device, FrameBufferBuilder, FrameSync, ImageBuilder, ImageViewBuilder, Instance, PhysicalDevice, QueuePool, RenderPassBuilder, Surface,
-    SwapchainBuilder, VulkanResult,
-};
+use crate::core::{App, Device, FrameSync, Instance, PhysicalDevice, QueuePool, RenderPassBuilder, Surface, SwapchainBuilder, VulkanResult};
+
+/// Number of color images rendered into by a headless context
+const HEADLESS_FRAME_COUNT: usize = 2;
 
 /// Render Context provides initialized low-level Vulkan objects ready
 /// to use
@@ -41,33 +41,18 @@ impl RenderContext {
     /// Create [`RenderContext`]
     pub fn new(window: &winit::window::Window) -> VulkanResult<Arc<Self>> {
         let app = App::new()?;
-        let instance = Instance::new(window, &app)?;
+        let instance = Instance::new(Some(window), &app)?;
         let surface = Surface::new(&app, &instance, window)?;
         let phys_dev = PhysicalDevice::new(&instance)?;
-        let device = Device::new(&instance, &phys_dev)?;
+        let device = Device::new(&instance, &phys_dev, true)?;
 
         let caps = surface.get_physical_device_surface_capabilities(phys_dev.raw)?;
         let extent = caps.current_extent;
 
         let formats = surface.get_physical_device_surface_formats(phys_dev.raw)?;
 
-        let format_priority = [vk::Format::R8G8B8A8_SRGB];
-
-        let color_space_priority = [
-            vk::ColorSpaceKHR::SRGB_NONLINEAR,
-        ];
-
-        let mut format = vk::Format::R8G8B8A8_SRGB;
-        let mut color_space = vk::ColorSpaceKHR::SRGB_NONLINEAR;
-
-        for (f, c) in format_priority.iter().zip(color_space_priority.iter()) {
-            for j in &formats {
-                if *f == j.format && *c == j.color_space {
-                    format = j.format;
-                    color_space = j.color_space;
-                }
-            }
-        }
+        let vk::SurfaceFormatKHR { format, color_space } = window_manager::select_surface_format(&formats);
+        let image_usage = window_manager::select_image_usage(&caps);
 
         warn!("Image count: {}:{}", caps.min_image_count, caps.max_image_count);
 
@@ -79,79 +64,108 @@ impl RenderContext {
             .color_space(color_space)
             .extent(extent)
             .format(format)
+            .image_usage(image_usage)
             .build()?;
 
-        let render_pass = RenderPassBuilder::default(&device, vk::Format::R8G8B8A8_SRGB, vk::Format::D32_SFLOAT).build()?;
+        let render_pass = RenderPassBuilder::default(&device, format, vk::Format::D32_SFLOAT).build()?;
+        let resume_pass = RenderPassBuilder::resume(&device, format, vk::Format::D32_SFLOAT, false).build()?;
+        let depth_read_pass = RenderPassBuilder::resume(&device, format, vk::Format::D32_SFLOAT, true).build()?;
 
-        let depth_image = ImageBuilder::new(&device)
-            .extent(caps.current_extent.into())
-            .format(vk::Format::D32_SFLOAT)
-            .usage(vk::ImageUsageFlags::DEPTH_STENCIL_ATTACHMENT)
-            .build()?;
+        let images = swapchain.get_swapchain_images()?;
+        let targets = window_manager::create_render_targets(&device, &render_pass, &images, format, extent)?;
 
-        let depth_view = ImageViewBuilder::new(&device)
-            .format(vk::Format::D32_SFLOAT)
-            .image(depth_image.raw)
-            .subresource_range(vk::ImageSubresourceRange {
-                aspect_mask: vk::ImageAspectFlags::DEPTH,
-                base_mip_level: 0,
-                level_count: 1,
-                base_array_layer: 0,
-                layer_count: 1,
-            })
-            .view_type(vk::ImageViewType::TYPE_2D)
-            .build()?;
+        let pool = QueuePool::new(&device.raw, &phys_dev.raw, Some(&surface), &device.queue_family_props);
+        let mut frame_sync = vec![];
 
-        let mut image_views = vec![];
-
-        for i in swapchain.get_swapchain_images().unwrap() {
-            let image_view = ImageViewBuilder::new(&device)
-                .format(vk::Format::R8G8B8A8_SRGB)
-                .image(i)
-                .subresource_range(vk::ImageSubresourceRange {
-                    aspect_mask: vk::ImageAspectFlags::COLOR,
-                    base_mip_level: 0,
-                    level_count: 1,
-                    base_array_layer: 0,
-                    layer_count: 1,
-                })
-                .view_type(vk::ImageViewType::TYPE_2D)
-                .build()?;
-            image_views.push(image_view);
+        for _ in 0..targets.frame_buffers.len() {
+            frame_sync.push(FrameSync::new(&device)?);
         }
 
-        let mut frame_buffers = vec![];
+        Ok(Arc::new(Self {
+            window: RwLock::new(WindowManager {
+                resolution: extent,
+                frame_sync,
+                images,
+                image_views: targets.image_views,
+                format,
+                image_usage,
+                final_layout: vk::ImageLayout::PRESENT_SRC_KHR,
+                depth_image: targets.depth_image,
+                frame_buffers: targets.frame_buffers,
+                depth_view: targets.depth_view,
+                current_frame: 0,
+                surface: Some(surface),
+                swapchain: Some(swapchain),
+                offscreen: vec![],
+                render_pass,
+                resume_pass,
+                depth_read_pass,
+            }),
+            device: GraphicsDevice {
+                app,
+                phys_dev,
+                instance,
+                logical_device: device,
+                queue_pool: pool,
+            },
+        }))
+    }
+
+    /// Create [`RenderContext`] without a window
+    ///
+    /// Frames are rendered into owned `R8G8B8A8_SRGB` images which are left
+    /// in [`vk::ImageLayout::TRANSFER_SRC_OPTIMAL`] and can be captured
+    pub fn headless(width: u32, height: u32) -> VulkanResult<Arc<Self>> {
+        let app = App::new()?;
+        let instance = Instance::new(None, &app)?;
+        let phys_dev = PhysicalDevice::new(&instance)?;
+        let device = Device::new(&instance, &phys_dev, false)?;
 
-        for i in &image_views {
-            let frame_buffer = FrameBufferBuilder::new(&device)
-                .render_pass(render_pass.raw)
-                .attachments(&[i.raw, depth_view.raw])
-                .extent(caps.current_extent)
-                .layers(1)
-                .build()?;
+        let extent = vk::Extent2D { width, height };
+        let format = vk::Format::R8G8B8A8_SRGB;
+        let image_usage = vk::ImageUsageFlags::COLOR_ATTACHMENT | vk::ImageUsageFlags::TRANSFER_SRC;
+        let final_layout = vk::ImageLayout::TRANSFER_SRC_OPTIMAL;
 
-            frame_buffers.push(frame_buffer);
-        }
+        let render_pass = RenderPassBuilder::default(&device, format, vk::Format::D32_SFLOAT)
+            .color_final_layout(final_layout)
+            .build()?;
+        let resume_pass = RenderPassBuilder::resume(&device, format, vk::Format::D32_SFLOAT, false)
+            .color_final_layout(final_layout)
+            .build()?;
+        let depth_read_pass = RenderPassBuilder::resume(&device, format, vk::Format::D32_SFLOAT, true)
+            .color_final_layout(final_layout)
+            .build()?;
+
+        let offscreen = window_manager::create_offscreen_images(&device, format, image_usage, extent, HEADLESS_FRAME_COUNT)?;
+        let images = offscreen.iter().map(|image| image.raw).collect::<Vec<_>>();
+        let targets = window_manager::create_render_targets(&device, &render_pass, &images, format, extent)?;
 
-        let pool = QueuePool::new(&device.raw, &phys_dev.raw, &surface, &device.queue_family_props);
+        let pool = QueuePool::new(&device.raw, &phys_dev.raw, None, &device.queue_family_props);
         let mut frame_sync = vec![];
 
-        for _ in 0..frame_buffers.len() {
+        for _ in 0..targets.frame_buffers.len() {
             frame_sync.push(FrameSync::new(&device)?);
         }
 
         Ok(Arc::new(Self {
             window: RwLock::new(WindowManager {
-                resolution: caps.current_extent,
+                resolution: extent,
                 frame_sync,
-                image_views,
-                depth_image,
-                frame_buffers,
-                depth_view,
+                images,
+                image_views: targets.image_views,
+                format,
+                image_usage,
+                final_layout,
+                depth_image: targets.depth_image,
+                frame_buffers: targets.frame_buffers,
+                depth_view: targets.depth_view,
                 current_frame: 0,
-                surface,
-                swapchain,
+                surface: None,
+                swapchain: None,
+                offscreen,
                 render_pass,
+                resume_pass,
+                depth_read_pass,
             }),
             device: GraphicsDevice {
                 app,
@@ -162,6 +176,11 @@ impl RenderContext {
             },
         }))
     }
+
+    /// Rendering without a swapchain
+    pub fn is_headless(&self) -> bool {
+        self.window.read().swapchain.is_none()
+    }
 }
 
 impl Drop for RenderContext {
@@ -174,24 +193,27 @@ impl Drop for RenderContext {
                 .device_wait_idle()
                 .expect("Failed to wait for device idle during RenderContext drop!");
 
-            for i in window.image_views.drain(..) {
-                i.destroy(device);
-            }
-
-            window.depth_view.destroy(device);
-            window.depth_image.destroy(device);
+            window.destroy_render_targets(device);
             window.render_pass.destroy(device);
+            window.resume_pass.destroy(device);
+            window.depth_read_pass.destroy(device);
 
-            for i in window.frame_buffers.drain(..) {
+            for i in window.frame_sync.drain(..) {
                 i.destroy(device);
             }
 
-            for i in window.frame_sync.drain(..) {
+            for mut i in window.offscreen.drain(..) {
                 i.destroy(device);
             }
 
-            window.swapchain.destroy();
-            window.surface.destroy();
+            if let Some(swapchain) = window.swapchain.take() {
+                swapchain.destroy();
+            }
+
+            if let Some(surface) = window.surface.take() {
+                surface.destroy();
+            }
+
             device.logical_device.destroy();
             device.instance.destroy();
         }
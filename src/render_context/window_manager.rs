@@ -3,12 +3,15 @@ use tracing::info;
 
 use super::GraphicsDevice;
 use crate::core::{
-    FrameBuffer, FrameBufferBuilder, FrameSync, Image, ImageBuilder, ImageView, ImageViewBuilder, RenderPass, Surface, Swapchain, SwapchainBuilder,
-    VulkanResult,
+    Device, FrameBuffer, FrameBufferBuilder, FrameSync, Image, ImageBuilder, ImageView, ImageViewBuilder, RenderPass, Surface, Swapchain,
+    SwapchainBuilder, VulkanResult,
 };
 
 /// Manages window-related Vulkan resources (swapchain, framebuffers,
 /// etc.) Handles window resizing and frame synchronization
+///
+/// Without a window the color targets are owned images, see
+/// [`crate::render_context::RenderContext::headless`]
 pub struct WindowManager {
     /// Current window/swapchain resolution
     pub(crate) resolution: vk::Extent2D,
@@ -16,8 +19,16 @@ pub struct WindowManager {
     pub(crate) frame_sync: Vec<FrameSync>,
     /// Framebuffers (one per swapchain image)
     pub(crate) frame_buffers: Vec<FrameBuffer>,
-    /// Image views for swapchain images
+    /// Color images, owned by the presentation engine or by `offscreen`
+    pub(crate) images: Vec<vk::Image>,
+    /// Image views for color images
     pub(crate) image_views: Vec<ImageView>,
+    /// Format of the color images
+    pub(crate) format: vk::Format,
+    /// Usage the color images were created with
+    pub(crate) image_usage: vk::ImageUsageFlags,
+    /// Layout of the color images after the render pass
+    pub(crate) final_layout: vk::ImageLayout,
     /// Index of current frame
     pub(crate) current_frame: usize,
     /// Depth buffer image
@@ -26,15 +37,33 @@ pub struct WindowManager {
     pub(crate) depth_view: ImageView,
     /// Render pass defining attachment formats and operations
     pub(crate) render_pass: RenderPass,
-    /// Window surface for presentation
-    pub(crate) surface: Surface,
-    /// Swapchain containing presentable images
-    pub(crate) swapchain: Swapchain,
+    /// Continues `render_pass` after a pass which read the depth buffer
+    pub(crate) resume_pass: RenderPass,
+    /// Continues `render_pass` with a depth buffer shaders can sample, see
+    /// [`crate::frame_graph::PassBuilder::read_depth`]
+    pub(crate) depth_read_pass: RenderPass,
+    /// Window surface for presentation, `None` for headless rendering
+    pub(crate) surface: Option<Surface>,
+    /// Swapchain containing presentable images, `None` for headless rendering
+    pub(crate) swapchain: Option<Swapchain>,
+    /// Color images rendered into instead of the swapchain
+    pub(crate) offscreen: Vec<Image>,
+}
+
+/// Attachments and framebuffers created for a set of color images
+pub(crate) struct RenderTargets {
+    pub depth_image: Image,
+    pub depth_view: ImageView,
+    pub image_views: Vec<ImageView>,
+    pub frame_buffers: Vec<FrameBuffer>,
 }
 
 impl WindowManager {
     /// Recreate swapchain, image views, depth image, framebuffers for
     /// new window size
+    ///
+    /// Headless targets are recreated with `width` and `height`, the
+    /// swapchain always follows the surface extent
     pub fn resize(&mut self, device: &GraphicsDevice, width: u32, height: u32) -> VulkanResult<()> {
         profiling::scope!("WindowManager::resize");
 
@@ -42,59 +71,124 @@ impl WindowManager {
 
         unsafe { device.device_wait_idle().expect("Error wait idle") };
 
-        let caps = self
-            .surface
-            .get_physical_device_surface_capabilities(*device.phys_dev)?;
+        if let Some(surface) = &self.surface {
+            let caps = surface.get_physical_device_surface_capabilities(*device.phys_dev)?;
+            let formats = surface.get_physical_device_surface_formats(*device.phys_dev)?;
+
+            let extent = caps.current_extent;
+            let _transforms = caps.current_transform;
+
+            let vk::SurfaceFormatKHR { format, color_space } = select_surface_format(&formats);
+            let image_usage = select_image_usage(&caps);
+
+            let old_swapchain = self
+                .swapchain
+                .as_ref()
+                .map_or(vk::SwapchainKHR::null(), |swapchain| swapchain.raw);
+
+            let swapchain = SwapchainBuilder::new(device)
+                .old_swapchain(old_swapchain)
+                .min_image_count(caps.max_image_count)
+                .surface(surface)
+                .present_mode(vk::PresentModeKHR::FIFO)
+                .instance(&device.instance)
+                .color_space(color_space)
+                .extent(extent)
+                .format(format)
+                .image_usage(image_usage)
+                .build()?;
 
-        let formats = self
-            .surface
-            .get_physical_device_surface_formats(*device.phys_dev)?;
+            let images = swapchain.get_swapchain_images()?;
+            let targets = create_render_targets(device, &self.render_pass, &images, format, extent)?;
 
-        let extent = caps.current_extent;
-        let _transforms = caps.current_transform;
+            self.destroy_render_targets(device);
 
-        let format_priority = [vk::Format::R8G8B8A8_SRGB];
+            if let Some(old) = self.swapchain.replace(swapchain) {
+                old.destroy();
+            }
 
-        let color_space_priority = [
-            #[cfg(target_os = "android")]
-            vk::ColorSpaceKHR::EXTENDED_SRGB_LINEAR_EXT,
-            vk::ColorSpaceKHR::SRGB_NONLINEAR,
-        ];
+            self.images = images;
+            self.format = format;
+            self.image_usage = image_usage;
+            self.set_render_targets(targets, extent);
+        } else {
+            let extent = vk::Extent2D { width, height };
+            let offscreen = create_offscreen_images(device, self.format, self.image_usage, extent, self.offscreen.len())?;
+            let images = offscreen.iter().map(|image| image.raw).collect::<Vec<_>>();
+            let targets = create_render_targets(device, &self.render_pass, &images, self.format, extent)?;
 
-        let mut format = vk::Format::R8G8B8A8_SRGB;
-        let mut color_space = vk::ColorSpaceKHR::SRGB_NONLINEAR;
+            self.destroy_render_targets(device);
 
-        for (f, c) in format_priority.iter().zip(color_space_priority.iter()) {
-            for j in &formats {
-                if *f == j.format && *c == j.color_space {
-                    format = j.format;
-                    color_space = j.color_space;
-                }
+            for mut image in self.offscreen.drain(..) {
+                image.destroy(device);
             }
+
+            self.offscreen = offscreen;
+            self.images = images;
+            self.set_render_targets(targets, extent);
         }
 
-        let swapchain = SwapchainBuilder::new(device)
-            .old_swapchain(self.swapchain.raw)
-            .min_image_count(caps.max_image_count)
-            .surface(&self.surface)
-            .present_mode(vk::PresentModeKHR::FIFO)
-            .instance(&device.instance)
-            .color_space(color_space)
-            .extent(extent)
-            .format(format)
-            .build()?;
+        Ok(())
+    }
 
-        let depth_image = ImageBuilder::new(device)
-            .extent(caps.current_extent.into())
-            .format(vk::Format::D32_SFLOAT)
-            .usage(vk::ImageUsageFlags::DEPTH_STENCIL_ATTACHMENT)
-            .build()?;
+    fn set_render_targets(&mut self, targets: RenderTargets, extent: vk::Extent2D) {
+        self.depth_image = targets.depth_image;
+        self.depth_view = targets.depth_view;
+        self.image_views = targets.image_views;
+        self.frame_buffers = targets.frame_buffers;
+        self.resolution = extent;
+    }
+
+    /// Destroy depth buffer, image views and framebuffers
+    pub(crate) fn destroy_render_targets(&mut self, device: &Device) {
+        for i in self.frame_buffers.drain(..) {
+            i.destroy(device);
+        }
+
+        for i in self.image_views.drain(..) {
+            i.destroy(device);
+        }
+
+        self.depth_view.destroy(device);
+        self.depth_image.destroy(device);
+    }
+}
 
-        let depth_view = ImageViewBuilder::new(device)
-            .format(vk::Format::D32_SFLOAT)
-            .image(depth_image.raw)
+/// Create depth buffer, color views and framebuffers for `images`
+pub(crate) fn create_render_targets(
+    device: &Device,
+    render_pass: &RenderPass,
+    images: &[vk::Image],
+    format: vk::Format,
+    extent: vk::Extent2D,
+) -> VulkanResult<RenderTargets> {
+    let depth_image = ImageBuilder::new(device)
+        .extent(extent.into())
+        .format(vk::Format::D32_SFLOAT)
+        .usage(vk::ImageUsageFlags::DEPTH_STENCIL_ATTACHMENT | vk::ImageUsageFlags::SAMPLED)
+        .build()?;
+
+    let depth_view = ImageViewBuilder::new(device)
+        .format(vk::Format::D32_SFLOAT)
+        .image(depth_image.raw)
+        .subresource_range(vk::ImageSubresourceRange {
+            aspect_mask: vk::ImageAspectFlags::DEPTH,
+            base_mip_level: 0,
+            level_count: 1,
+            base_array_layer: 0,
+            layer_count: 1,
+        })
+        .view_type(vk::ImageViewType::TYPE_2D)
+        .build()?;
+
+    let mut image_views = vec![];
+
+    for i in images {
+        let image_view = ImageViewBuilder::new(device)
+            .format(format)
+            .image(*i)
             .subresource_range(vk::ImageSubresourceRange {
-                aspect_mask: vk::ImageAspectFlags::DEPTH,
+                aspect_mask: vk::ImageAspectFlags::COLOR,
                 base_mip_level: 0,
                 level_count: 1,
                 base_array_layer: 0,
@@ -102,61 +196,81 @@ impl WindowManager {
             })
             .view_type(vk::ImageViewType::TYPE_2D)
             .build()?;
+        image_views.push(image_view);
+    }
 
-        let mut image_views = vec![];
-
-        for i in swapchain.get_swapchain_images()? {
-            let image_view = ImageViewBuilder::new(device)
-                .format(vk::Format::R8G8B8A8_SRGB)
-                .image(i)
-                .subresource_range(vk::ImageSubresourceRange {
-                    aspect_mask: vk::ImageAspectFlags::COLOR,
-                    base_mip_level: 0,
-                    level_count: 1,
-                    base_array_layer: 0,
-                    layer_count: 1,
-                })
-                .view_type(vk::ImageViewType::TYPE_2D)
-                .build()?;
-            image_views.push(image_view);
-        }
-
-        let mut frame_buffers = vec![];
+    let mut frame_buffers = vec![];
 
-        for i in &image_views {
-            let frame_buffer = FrameBufferBuilder::new(device)
-                .render_pass(self.render_pass.raw)
-                .attachments(&[i.raw, depth_view.raw])
-                .extent(caps.current_extent)
-                .layers(1)
-                .build()?;
+    for i in &image_views {
+        let frame_buffer = FrameBufferBuilder::new(device)
+            .render_pass(render_pass.raw)
+            .attachments(&[i.raw, depth_view.raw])
+            .extent(extent)
+            .layers(1)
+            .build()?;
 
-            frame_buffers.push(frame_buffer);
-        }
+        frame_buffers.push(frame_buffer);
+    }
 
-        self.depth_view.destroy(device);
-        self.depth_view = depth_view;
+    Ok(RenderTargets {
+        depth_image,
+        depth_view,
+        image_views,
+        frame_buffers,
+    })
+}
 
-        self.depth_image.destroy(device);
-        self.depth_image = depth_image;
+/// Create `count` color images used in place of swapchain images
+pub(crate) fn create_offscreen_images(
+    device: &Device,
+    format: vk::Format,
+    usage: vk::ImageUsageFlags,
+    extent: vk::Extent2D,
+    count: usize,
+) -> VulkanResult<Vec<Image>> {
+    (0..count)
+        .map(|_| {
+            ImageBuilder::new(device)
+                .extent(extent.into())
+                .format(format)
+                .usage(usage)
+                .build()
+        })
+        .collect()
+}
 
-        for i in &self.image_views {
-            i.destroy(device);
-        }
+/// Pick the swapchain format, 8-bit sRGB formats are preferred
+pub(crate) fn select_surface_format(formats: &[vk::SurfaceFormatKHR]) -> vk::SurfaceFormatKHR {
+    let format_priority = [vk::Format::R8G8B8A8_SRGB, vk::Format::B8G8R8A8_SRGB];
 
-        self.image_views = image_views;
+    let color_space_priority = [
+        #[cfg(target_os = "android")]
+        vk::ColorSpaceKHR::EXTENDED_SRGB_LINEAR_EXT,
+        vk::ColorSpaceKHR::SRGB_NONLINEAR,
+    ];
 
-        for i in &self.frame_buffers {
-            i.destroy(device);
+    for color_space in color_space_priority {
+        for format in format_priority {
+            if let Some(found) = formats
+                .iter()
+                .find(|f| f.format == format && f.color_space == color_space)
+            {
+                return *found;
+            }
         }
+    }
 
-        self.frame_buffers = frame_buffers;
-
-        self.swapchain.destroy();
-        self.swapchain = swapchain;
-
-        self.resolution = caps.current_extent;
+    vk::SurfaceFormatKHR {
+        format: vk::Format::R8G8B8A8_SRGB,
+        color_space: vk::ColorSpaceKHR::SRGB_NONLINEAR,
+    }
+}
 
-        Ok(())
+/// Swapchain images are also copied from when a frame capture is requested
+pub(crate) fn select_image_usage(caps: &vk::SurfaceCapabilitiesKHR) -> vk::ImageUsageFlags {
+    let mut usage = vk::ImageUsageFlags::COLOR_ATTACHMENT;
+    if caps.supported_usage_flags.contains(vk::ImageUsageFlags::TRANSFER_SRC) {
+        usage |= vk::ImageUsageFlags::TRANSFER_SRC;
     }
+    usage
 }
@@ -0,0 +1,99 @@
+//! sRGB stops of the built-in colormaps, evenly spaced from 0 to 1
+
+/// viridis of matplotlib
+pub(super) const VIRIDIS: &[[u8; 3]] = &[
+    [0x44, 0x01, 0x54],
+    [0x48, 0x25, 0x76],
+    [0x41, 0x44, 0x87],
+    [0x35, 0x60, 0x8d],
+    [0x2a, 0x78, 0x8e],
+    [0x21, 0x90, 0x8c],
+    [0x22, 0xa8, 0x84],
+    [0x43, 0xbf, 0x71],
+    [0x7a, 0xd1, 0x51],
+    [0xbb, 0xdf, 0x27],
+    [0xfd, 0xe7, 0x25],
+];
+
+/// magma of matplotlib
+pub(super) const MAGMA: &[[u8; 3]] = &[
+    [0x00, 0x00, 0x04],
+    [0x14, 0x0e, 0x36],
+    [0x3b, 0x0f, 0x70],
+    [0x64, 0x1a, 0x80],
+    [0x8c, 0x29, 0x81],
+    [0xb6, 0x36, 0x79],
+    [0xde, 0x49, 0x68],
+    [0xf7, 0x6f, 0x5c],
+    [0xfe, 0x9f, 0x6d],
+    [0xfe, 0xcf, 0x92],
+    [0xfc, 0xfd, 0xbf],
+];
+
+/// plasma of matplotlib
+pub(super) const PLASMA: &[[u8; 3]] = &[
+    [0x0d, 0x08, 0x87],
+    [0x41, 0x04, 0x9d],
+    [0x6a, 0x00, 0xa8],
+    [0x8f, 0x0d, 0xa4],
+    [0xb1, 0x2a, 0x90],
+    [0xcc, 0x47, 0x78],
+    [0xe1, 0x64, 0x62],
+    [0xf2, 0x84, 0x4b],
+    [0xfc, 0xa6, 0x36],
+    [0xfc, 0xce, 0x25],
+    [0xf0, 0xf9, 0x21],
+];
+
+/// inferno of matplotlib
+pub(super) const INFERNO: &[[u8; 3]] = &[
+    [0x00, 0x00, 0x04],
+    [0x16, 0x0b, 0x39],
+    [0x42, 0x0a, 0x68],
+    [0x6a, 0x17, 0x6e],
+    [0x93, 0x26, 0x67],
+    [0xbc, 0x37, 0x54],
+    [0xdd, 0x51, 0x3a],
+    [0xf3, 0x78, 0x19],
+    [0xfc, 0xa5, 0x0a],
+    [0xf6, 0xd7, 0x46],
+    [0xfc, 0xff, 0xa4],
+];
+
+/// Diverging blue to red of Kenneth Moreland
+pub(super) const COOLWARM: &[[u8; 3]] = &[
+    [59, 76, 192],
+    [98, 130, 234],
+    [141, 176, 254],
+    [184, 208, 249],
+    [221, 221, 221],
+    [244, 196, 173],
+    [244, 154, 123],
+    [222, 96, 77],
+    [180, 4, 38],
+];
+
+/// Diverging red to blue of the Color Brewer schemes
+pub(super) const RDBU: &[[u8; 3]] = &[
+    [0x67, 0x00, 0x1f],
+    [0xb2, 0x18, 0x2b],
+    [0xd6, 0x60, 0x4d],
+    [0xf4, 0xa5, 0x82],
+    [0xfd, 0xdb, 0xc7],
+    [0xf7, 0xf7, 0xf7],
+    [0xd1, 0xe5, 0xf0],
+    [0x92, 0xc5, 0xde],
+    [0x43, 0x93, 0xc3],
+    [0x21, 0x66, 0xac],
+    [0x05, 0x30, 0x61],
+];
+
+/// Polynomial fit of Google's turbo, sRGB in 0..1
+pub(super) fn turbo(t: f32) -> [f32; 3] {
+    const RED: [f32; 6] = [0.13572138, 4.6153926, -42.660323, 132.1311, -152.9424, 59.28638];
+    const GREEN: [f32; 6] = [0.09140261, 2.1941884, 4.8429666, -14.18503, 4.2772986, 2.829566];
+    const BLUE: [f32; 6] = [0.1066733, 12.641946, -60.582048, 110.36277, -89.90311, 27.34825];
+
+    let powers = [1.0, t, t * t, t * t * t, t * t * t * t, t * t * t * t * t];
+    [RED, GREEN, BLUE].map(|coefficients| coefficients.iter().zip(powers).map(|(c, p)| c * p).sum::<f32>().clamp(0.0, 1.0))
+}
@@ -0,0 +1,262 @@
+//! Lookup tables mapping scalars to colors, sampled by shaders from the
+//! bindless set
+
+use ash::vk;
+use bytemuck::{Pod, Zeroable};
+
+use crate::bindless::Bindless;
+use crate::core::Device;
+use crate::resources::upload::BindlessImage;
+use crate::resources::ImageUploads;
+use crate::VulkanResult;
+
+mod data;
+
+/// Texels of every lookup table
+pub(crate) const LUT_SIZE: u32 = 256;
+
+/// Built-in lookup table from 0 to 1, read in shaders with `colormap` of
+/// `shaders/inc/colormap.glsl`
+///
+/// # Example
+/// ```ignore
+/// // layout(location = 5) in float value;
+/// // out_color = colormap(0, value);
+/// ctx.bind_pipeline(&pipeline);
+/// ctx.bind_colormap(0, Colormap::Viridis);
+/// ctx.push_constants(ScalarRange::new(0.0, 100.0).log(true));
+/// ctx.draw_mesh(&mesh);
+/// ```
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Colormap {
+    /// Perceptually uniform, dark blue to yellow
+    Viridis,
+    /// Perceptually uniform, black to pale yellow through purple
+    Magma,
+    /// Perceptually uniform, blue to yellow through magenta
+    Plasma,
+    /// Perceptually uniform, black to pale yellow through red
+    Inferno,
+    /// Rainbow with smooth lightness, near black through blue to dark red
+    Turbo,
+    /// Diverging blue to red through light gray
+    Coolwarm,
+    /// Diverging red to blue through white
+    RdBu,
+}
+
+impl Colormap {
+    /// Every built-in colormap
+    pub const ALL: [Colormap; 7] = [
+        Colormap::Viridis,
+        Colormap::Magma,
+        Colormap::Plasma,
+        Colormap::Inferno,
+        Colormap::Turbo,
+        Colormap::Coolwarm,
+        Colormap::RdBu,
+    ];
+
+    /// sRGB color at `t`, clamped to 0..1
+    pub fn sample(self, t: f32) -> [u8; 4] {
+        let t = if t.is_nan() { 0.0 } else { t.clamp(0.0, 1.0) };
+
+        let stops = match self {
+            Colormap::Viridis => data::VIRIDIS,
+            Colormap::Magma => data::MAGMA,
+            Colormap::Plasma => data::PLASMA,
+            Colormap::Inferno => data::INFERNO,
+            Colormap::Coolwarm => data::COOLWARM,
+            Colormap::RdBu => data::RDBU,
+            Colormap::Turbo => {
+                let [r, g, b] = data::turbo(t).map(|c| (c * 255.0).round() as u8);
+                return [r, g, b, 255];
+            }
+        };
+
+        let x = t * (stops.len() - 1) as f32;
+        let i = (x as usize).min(stops.len() - 2);
+        let f = x - i as f32;
+        let [r, g, b] = [0, 1, 2].map(|c| (f32::from(stops[i][c]) * (1.0 - f) + f32::from(stops[i + 1][c]) * f).round() as u8);
+        [r, g, b, 255]
+    }
+
+    /// Texels of the lookup table, sRGB with the first and last at 0 and 1
+    fn lut(self) -> Vec<u8> {
+        (0..LUT_SIZE)
+            .flat_map(|i| self.sample(i as f32 / (LUT_SIZE - 1) as f32))
+            .collect()
+    }
+}
+
+/// Mapping of scalars to colormap coordinates, pushed with
+/// `PassContext::push_constants` as the first four user values
+/// `shaders/inc/colormap.glsl` reads
+///
+/// Values outside the range take the colors of its ends, or are transparent
+/// with `clamp(false)`. NaN is always transparent
+#[repr(C)]
+#[derive(Clone, Copy, Debug, PartialEq, Pod, Zeroable)]
+pub struct ScalarRange {
+    min: f32,
+    max: f32,
+    /// 1 for a logarithmic scale
+    log: f32,
+    /// 1 when values outside the range take the end colors
+    clamp: f32,
+}
+
+impl ScalarRange {
+    /// `min` maps to the start of the colormap and `max` to its end,
+    /// linear and clamped
+    pub fn new(min: f32, max: f32) -> Self {
+        Self {
+            min,
+            max,
+            log: 0.0,
+            clamp: 1.0,
+        }
+    }
+
+    /// Map the logarithm of values, `min` and `max` must be positive.
+    /// Values at or below 0 are below the range
+    pub fn log(mut self, log: bool) -> Self {
+        self.log = if log { 1.0 } else { 0.0 };
+        self
+    }
+
+    /// Give values outside the range the colors of its ends, on by default
+    pub fn clamp(mut self, clamp: bool) -> Self {
+        self.clamp = if clamp { 1.0 } else { 0.0 };
+        self
+    }
+
+    /// Value at the start of the colormap
+    pub fn min(&self) -> f32 {
+        self.min
+    }
+
+    /// Value at the end of the colormap
+    pub fn max(&self) -> f32 {
+        self.max
+    }
+
+    /// Values are mapped by their logarithm
+    pub fn is_log(&self) -> bool {
+        self.log > 0.5
+    }
+
+    /// Values outside the range take the colors of its ends
+    pub fn is_clamped(&self) -> bool {
+        self.clamp > 0.5
+    }
+
+    /// Colormap coordinate of `value` in 0..1, `None` where shaders are
+    /// transparent
+    pub fn coord(&self, value: f32) -> Option<f32> {
+        let t = match self.is_log() {
+            true => (value.ln() - self.min.ln()) / (self.max.ln() - self.min.ln()),
+            false => (value - self.min) / (self.max - self.min),
+        };
+
+        match t {
+            t if t.is_nan() => None,
+            t if self.is_clamped() => Some(t.clamp(0.0, 1.0)),
+            t => (0.0..=1.0).contains(&t).then_some(t),
+        }
+    }
+
+    /// Value at colormap coordinate `t`
+    pub fn value(&self, t: f32) -> f32 {
+        match self.is_log() {
+            true => (self.min.ln() + (self.max.ln() - self.min.ln()) * t).exp(),
+            false => self.min + (self.max - self.min) * t,
+        }
+    }
+}
+
+/// Lookup tables of every [`Colormap`] in the bindless set
+pub(crate) struct ColormapStore {
+    /// In the order of [`Colormap::ALL`]
+    images: Vec<BindlessImage>,
+}
+
+impl ColormapStore {
+    /// Tables are filled by the first frame
+    pub fn new(device: &Device, bindless: &Bindless, uploads: &mut ImageUploads) -> VulkanResult<Self> {
+        let extent = vk::Extent3D {
+            width: LUT_SIZE,
+            height: 1,
+            depth: 1,
+        };
+
+        let mut images = vec![];
+        for colormap in Colormap::ALL {
+            // sRGB texels are sampled as linear colors
+            let image = BindlessImage::new(device, bindless, vk::Format::R8G8B8A8_SRGB, extent)?;
+            uploads.push(device, &image, &colormap.lut())?;
+            images.push(image);
+        }
+
+        Ok(Self { images })
+    }
+
+    /// Index of the table in `textures[]`
+    pub fn index(&self, colormap: Colormap) -> u32 {
+        self.images[colormap as usize].index
+    }
+
+    pub fn destroy(&mut self, device: &Device, bindless: &Bindless) {
+        for mut image in self.images.drain(..) {
+            image.destroy(device, bindless);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tables_follow_the_order_of_all() {
+        for (index, colormap) in Colormap::ALL.into_iter().enumerate() {
+            assert_eq!(colormap as usize, index);
+        }
+    }
+
+    #[test]
+    fn samples_interpolate_stops() {
+        assert_eq!(Colormap::Viridis.sample(0.0), [0x44, 0x01, 0x54, 255]);
+        assert_eq!(Colormap::Viridis.sample(1.0), [0xfd, 0xe7, 0x25, 255]);
+        assert_eq!(Colormap::Viridis.sample(2.0), Colormap::Viridis.sample(1.0));
+        // Halfway between the first two stops
+        assert_eq!(Colormap::Magma.sample(0.05), [10, 7, 29, 255]);
+        assert_eq!(Colormap::Coolwarm.sample(0.5), [221, 221, 221, 255]);
+
+        // Turbo goes from blue to dark red through green
+        let [r, g, b, _] = Colormap::Turbo.sample(0.1);
+        assert!(b > r && b > g);
+        let [r, g, b, _] = Colormap::Turbo.sample(0.5);
+        assert!(g > r && g > b);
+        let [r, g, b, _] = Colormap::Turbo.sample(1.0);
+        assert!(r > g && r > b);
+
+        assert_eq!(Colormap::RdBu.lut().len(), LUT_SIZE as usize * 4);
+    }
+
+    #[test]
+    fn ranges_map_to_coordinates() {
+        let linear = ScalarRange::new(10.0, 20.0);
+        assert_eq!(linear.coord(15.0), Some(0.5));
+        assert_eq!(linear.coord(30.0), Some(1.0));
+        assert_eq!(linear.coord(f32::NAN), None);
+        assert_eq!(linear.clamp(false).coord(30.0), None);
+        assert_eq!(linear.value(0.25), 12.5);
+
+        let log = ScalarRange::new(1.0, 100.0).log(true);
+        assert!((log.coord(10.0).unwrap() - 0.5).abs() < 1e-6);
+        assert_eq!(log.coord(0.0), Some(0.0));
+        assert_eq!(log.coord(-1.0), None);
+        assert!((log.value(0.5) - 10.0).abs() < 1e-4);
+    }
+}
@@ -0,0 +1,604 @@
+use std::sync::{Arc, Weak};
+
+use ash::vk;
+use bytemuck::{Pod, Zeroable};
+
+use crate::core::{
+    read_shader_from_bytes, Device, GpuBuffer, GraphicsPipeline, GraphicsPipelineBuilder, PipelineLayout, PipelineLayoutBuilder, ShaderBuilder,
+};
+use crate::frame_graph::PushHeader;
+use crate::math::{normalize, Vec3};
+use crate::render_context::RenderContext;
+use crate::resources::{Colormap, Create, Destroy, Get, GetMut, MeshBuffer, Pool, Ref, RefMut, Res, ResourceKey, Resources, Retired, ScalarRange};
+use crate::VulkanResult;
+
+const VERTEX_SHADER: &[u8] = include_bytes!(concat!(env!("CARGO_MANIFEST_DIR"), "/shaders/spv/height_field_vert.spv"));
+const FRAGMENT_SHADER: &[u8] = include_bytes!(concat!(env!("CARGO_MANIFEST_DIR"), "/shaders/spv/height_field_frag.spv"));
+
+/// Sample in the layout of `shaders/height_field.vert`, the position comes
+/// from the vertex index
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Pod, Zeroable)]
+struct HeightVertex {
+    height: f32,
+    normal: Vec3,
+}
+
+/// Push constants in the layout of `shaders/height_field.vert`
+#[repr(C)]
+#[derive(Clone, Copy, Pod, Zeroable)]
+struct Push {
+    header: PushHeader,
+    /// First as `shaders/inc/colormap.glsl` reads it
+    range: ScalarRange,
+    cell: [f32; 2],
+    columns: f32,
+    /// 0 without contours
+    contour_interval: f32,
+    contour_color: [f32; 4],
+    /// Transparent without a wireframe
+    wireframe_color: [f32; 4],
+}
+
+/// Parameters of a new [`HeightField`]
+pub struct HeightFieldDesc<'a> {
+    size: [u32; 2],
+    heights: &'a [f32],
+    extent: [f32; 2],
+    colormap: Colormap,
+    range: Option<ScalarRange>,
+    contours: Option<(f32, [f32; 4])>,
+    wireframe: Option<[f32; 4]>,
+}
+
+impl<'a> HeightFieldDesc<'a> {
+    /// Grid of `size[0]` columns and `size[1]` rows of `heights`, row after
+    /// row, spanning 1x1 world units colored by [`Colormap::Viridis`] from
+    /// the lowest to the highest sample
+    pub fn new(size: [u32; 2], heights: &'a [f32]) -> Self {
+        Self {
+            size,
+            heights,
+            extent: [1.0; 2],
+            colormap: Colormap::Viridis,
+            range: None,
+            contours: None,
+            wireframe: None,
+        }
+    }
+
+    /// World units from the first column to the last along x, and from the
+    /// first row to the last along y
+    pub fn extent(mut self, extent: [f32; 2]) -> Self {
+        self.extent = extent;
+        self
+    }
+
+    /// Color of the surface by height
+    pub fn colormap(mut self, colormap: Colormap, range: ScalarRange) -> Self {
+        self.colormap = colormap;
+        self.range = Some(range);
+        self
+    }
+
+    /// Lines of linear `color` every `interval` of height
+    pub fn contours(mut self, interval: f32, color: [f32; 4]) -> Self {
+        self.contours = Some((interval, color));
+        self
+    }
+
+    /// Lines of linear `color` along the rows and columns of the grid
+    pub fn wireframe(mut self, color: [f32; 4]) -> Self {
+        self.wireframe = Some(color);
+        self
+    }
+}
+
+/// Surface z = f(x, y) of a grid of heights
+///
+/// The first sample is at the origin, columns go along x and rows along y.
+/// Heights and their normals are kept on the CPU and copied into the buffer
+/// of every frame in flight like a dynamic [`Mesh`](crate::Mesh), a
+/// [`HeightField::write_region`] only uploads the rows it changes. See
+/// [`PassContext::draw_height_field`]
+///
+/// # Example
+/// ```ignore
+/// let field = world.create::<HeightField>(
+///     HeightFieldDesc::new([256, 256], &heights)
+///         .extent([10.0, 10.0])
+///         .colormap(Colormap::Turbo, ScalarRange::new(-1.0, 1.0))
+///         .contours(0.25, [0.0, 0.0, 0.0, 1.0]),
+/// )?;
+///
+/// // every frame
+/// world.get_mut(&field).write_region([x, y], [16, 16], &patch);
+///
+/// world.draw_frame(|graph| {
+///     graph.add_pass(PresentPass::new("Surface", |_| {}, move |ctx, _| unsafe {
+///         ctx.draw_height_field(&field);
+///     }));
+/// })?;
+/// ```
+///
+/// [`PassContext::draw_height_field`]: crate::frame_graph::PassContext::draw_height_field
+pub struct HeightField {
+    /// Color of the surface by height
+    pub colormap: Colormap,
+    /// Heights at both ends of the colormap
+    pub range: ScalarRange,
+    /// Height between contour lines, none with `None`
+    pub contour_interval: Option<f32>,
+    /// Linear RGBA color of contour lines
+    pub contour_color: [f32; 4],
+    /// Linear RGBA color of the grid lines, none with `None`
+    pub wireframe: Option<[f32; 4]>,
+    size: [u32; 2],
+    extent: [f32; 2],
+    heights: Vec<f32>,
+    vertices: MeshBuffer,
+    indices: MeshBuffer,
+    frame_count: usize,
+}
+
+impl HeightField {
+    /// Columns and rows of the grid
+    pub fn size(&self) -> [u32; 2] {
+        self.size
+    }
+
+    /// World units covered along x and y
+    pub fn extent(&self) -> [f32; 2] {
+        self.extent
+    }
+
+    /// Every height, row after row
+    pub fn heights(&self) -> &[f32] {
+        &self.heights
+    }
+
+    /// Height of the sample at `column` and `row`
+    ///
+    /// # Panics
+    /// - if the sample is outside the grid
+    pub fn height(&self, column: u32, row: u32) -> f32 {
+        assert!(column < self.size[0] && row < self.size[1], "Sample outside the height field");
+        self.heights[(row * self.size[0] + column) as usize]
+    }
+
+    /// Lowest and highest height, NaN samples are skipped
+    pub fn height_range(&self) -> [f32; 2] {
+        height_range(&self.heights)
+    }
+
+    /// Replace the block of `size` samples from `origin`, `heights` row
+    /// after row
+    ///
+    /// Normals of the samples around the block follow
+    ///
+    /// # Panics
+    /// - if the block reaches outside the grid
+    /// - if `heights` does not hold `size[0] * size[1]` samples
+    pub fn write_region(&mut self, origin: [u32; 2], size: [u32; 2], heights: &[f32]) {
+        assert!(
+            origin[0] + size[0] <= self.size[0] && origin[1] + size[1] <= self.size[1],
+            "Region outside the height field"
+        );
+        assert_eq!(heights.len(), (size[0] * size[1]) as usize, "Region size differs from its heights");
+
+        if heights.is_empty() {
+            return;
+        }
+
+        let columns = self.size[0] as usize;
+        for (row, samples) in heights.chunks_exact(size[0] as usize).enumerate() {
+            let start = (origin[1] as usize + row) * columns + origin[0] as usize;
+            self.heights[start..start + samples.len()].copy_from_slice(samples);
+        }
+
+        // Normals read the rows next to the block
+        let first = origin[1].saturating_sub(1);
+        let last = (origin[1] + size[1]).min(self.size[1] - 1);
+        self.upload_rows(first, last);
+    }
+
+    /// Replace every height
+    ///
+    /// # Panics
+    /// - if `heights` does not hold a sample for every column and row
+    pub fn set_heights(&mut self, heights: &[f32]) {
+        self.write_region([0, 0], self.size, heights);
+    }
+
+    /// Copy heights and normals of rows `first..=last` to the vertices
+    fn upload_rows(&mut self, first: u32, last: u32) {
+        let columns = self.size[0];
+        let cell = cell(self.size, self.extent);
+        let vertices = (first..=last)
+            .flat_map(|row| (0..columns).map(move |column| (column, row)))
+            .map(|(column, row)| HeightVertex {
+                height: self.heights[(row * columns + column) as usize],
+                normal: normal(&self.heights, self.size, cell, column, row),
+            })
+            .collect::<Vec<_>>();
+
+        let mut retired = vec![];
+        self.vertices.write(
+            (first * columns) as usize,
+            bytemuck::cast_slice(&vertices),
+            size_of::<HeightVertex>(),
+            self.frame_count,
+            &mut retired,
+        );
+        debug_assert!(retired.is_empty(), "Height field vertices are always dynamic");
+    }
+
+    fn push_constants(&self, transform: Option<u32>, colormap_idx: u32) -> Push {
+        let mut tex_idx = [0; 8];
+        tex_idx[0] = colormap_idx;
+
+        Push {
+            header: PushHeader::new(transform, tex_idx),
+            range: self.range,
+            cell: cell(self.size, self.extent),
+            columns: self.size[0] as f32,
+            contour_interval: self.contour_interval.unwrap_or_default(),
+            contour_color: self.contour_color,
+            wireframe_color: self.wireframe.unwrap_or_default(),
+        }
+    }
+}
+
+/// World units between neighbouring samples
+fn cell(size: [u32; 2], extent: [f32; 2]) -> [f32; 2] {
+    [extent[0] / (size[0] - 1) as f32, extent[1] / (size[1] - 1) as f32]
+}
+
+fn height_range(heights: &[f32]) -> [f32; 2] {
+    heights
+        .iter()
+        .filter(|height| !height.is_nan())
+        .fold([f32::MAX, f32::MIN], |[lo, hi], &height| [lo.min(height), hi.max(height)])
+}
+
+/// Normal from the slopes to the neighbours of the sample, one-sided at the
+/// borders of the grid
+fn normal(heights: &[f32], size: [u32; 2], cell: [f32; 2], column: u32, row: u32) -> Vec3 {
+    let at = |column: u32, row: u32| heights[(row * size[0] + column) as usize];
+
+    let (left, right) = (column.saturating_sub(1), (column + 1).min(size[0] - 1));
+    let (up, down) = (row.saturating_sub(1), (row + 1).min(size[1] - 1));
+
+    let dx = (at(right, row) - at(left, row)) / ((right - left) as f32 * cell[0]);
+    let dy = (at(column, down) - at(column, up)) / ((down - up) as f32 * cell[1]);
+
+    normalize([-dx, -dy, 1.0])
+}
+
+/// Two triangles for every cell, counter-clockwise seen from +z
+fn grid_indices(size: [u32; 2]) -> Vec<u32> {
+    let [columns, rows] = size;
+    let mut indices = Vec::with_capacity(((columns - 1) * (rows - 1) * 6) as usize);
+
+    for row in 0..rows - 1 {
+        for column in 0..columns - 1 {
+            let a = row * columns + column;
+            let (b, c, d) = (a + 1, a + columns, a + columns + 1);
+            indices.extend_from_slice(&[a, b, d, a, d, c]);
+        }
+    }
+
+    indices
+}
+
+impl Destroy for HeightField {
+    fn destroy(key: ResourceKey, _ctx: Weak<RenderContext>, resources: Weak<Resources>) {
+        let Some(resources) = resources.upgrade() else {
+            return;
+        };
+
+        let mut store = resources.height_fields.write();
+        if let Some(field) = store.pool.remove(key) {
+            let frames = store.frame_count;
+            let buffers = field.vertices.into_buffers().into_iter().chain(field.indices.into_buffers());
+            store.retired.extend(frames, buffers);
+        }
+    }
+}
+
+impl Create for HeightField {
+    type Desc<'a> = HeightFieldDesc<'a>;
+    /// # Panics
+    /// - if the grid is smaller than 2x2
+    /// - if `heights` does not hold a sample for every column and row
+    fn create(ctx: &Arc<RenderContext>, resources: &Arc<Resources>, desc: Self::Desc<'_>) -> VulkanResult<Res<Self>> {
+        let [columns, rows] = desc.size;
+        assert!(columns >= 2 && rows >= 2, "Height fields need at least 2x2 samples");
+        assert_eq!(desc.heights.len(), (columns * rows) as usize, "Grid size differs from its heights");
+
+        let range = desc.range.unwrap_or_else(|| {
+            let [lo, hi] = height_range(desc.heights);
+            // Flat fields take the start of the colormap
+            ScalarRange::new(lo, if hi > lo { hi } else { lo + 1.0 })
+        });
+
+        let indices = MeshBuffer::new_static(
+            &ctx.device,
+            vk::BufferUsageFlags::INDEX_BUFFER,
+            bytemuck::cast_slice(&grid_indices(desc.size)),
+            size_of::<u32>(),
+        )?;
+
+        let frame_count = ctx.frame_count();
+        let mut field = HeightField {
+            colormap: desc.colormap,
+            range,
+            contour_interval: desc.contours.map(|(interval, _)| interval),
+            contour_color: desc.contours.map_or([0.0, 0.0, 0.0, 1.0], |(_, color)| color),
+            wireframe: desc.wireframe,
+            size: desc.size,
+            extent: desc.extent,
+            heights: desc.heights.to_vec(),
+            vertices: MeshBuffer::new_dynamic(vk::BufferUsageFlags::VERTEX_BUFFER, size_of::<HeightVertex>(), frame_count),
+            indices,
+            frame_count,
+        };
+        field.upload_rows(0, rows - 1);
+
+        Ok(resources
+            .height_fields
+            .write()
+            .pool
+            .insert(Arc::downgrade(ctx), Arc::downgrade(resources), field))
+    }
+}
+
+impl Get for HeightField {
+    fn get<'a>(resources: &'a Resources, res: &Res<Self>) -> Ref<'a, Self> {
+        let store = resources.height_fields.try_read().expect("Height fields are already borrowed mutably");
+        Ref(parking_lot::RwLockReadGuard::map(store, |store| store.pool.get(res)))
+    }
+}
+
+impl GetMut for HeightField {
+    fn get_mut<'a>(resources: &'a Resources, res: &Res<Self>) -> RefMut<'a, Self> {
+        let store = resources.height_fields.try_write().expect("Height fields are already borrowed");
+        RefMut(parking_lot::RwLockWriteGuard::map(store, |store| store.pool.get_mut(res)))
+    }
+}
+
+/// Height fields with the pipeline drawing them
+pub struct HeightFieldStore {
+    pub(crate) pool: Pool<HeightField>,
+    retired: Retired<GpuBuffer>,
+    frame_count: usize,
+    layout: PipelineLayout,
+    pipeline: GraphicsPipeline,
+}
+
+impl HeightFieldStore {
+    pub fn new(device: &Device, frame_count: usize, render_pass: vk::RenderPass, set_layouts: Vec<vk::DescriptorSetLayout>) -> VulkanResult<Self> {
+        // The layout of every raster pipeline, set 0 holds the colormaps and
+        // set 1 the camera and transforms
+        let layout = PipelineLayoutBuilder::new(device)
+            .set_layouts(set_layouts)
+            .push_constant(vec![vk::PushConstantRange::default()
+                .offset(0)
+                .size(128)
+                .stage_flags(vk::ShaderStageFlags::VERTEX | vk::ShaderStageFlags::FRAGMENT)])
+            .build()?;
+
+        let spv = read_shader_from_bytes(VERTEX_SHADER).expect("Invalid height field vertex shader");
+        let vertex = ShaderBuilder::new(device).bytecode(&spv).build()?;
+        let spv = read_shader_from_bytes(FRAGMENT_SHADER).expect("Invalid height field fragment shader");
+        let fragment = ShaderBuilder::new(device).bytecode(&spv).build()?;
+
+        let bindings = [vk::VertexInputBindingDescription {
+            binding: 0,
+            stride: size_of::<HeightVertex>() as u32,
+            input_rate: vk::VertexInputRate::VERTEX,
+        }];
+        let attributes = [
+            vk::VertexInputAttributeDescription {
+                location: 0,
+                binding: 0,
+                format: vk::Format::R32_SFLOAT,
+                offset: 0,
+            },
+            vk::VertexInputAttributeDescription {
+                location: 1,
+                binding: 0,
+                format: vk::Format::R32G32B32_SFLOAT,
+                offset: 4,
+            },
+        ];
+
+        let vertex_input_info = vk::PipelineVertexInputStateCreateInfo::default()
+            .vertex_binding_descriptions(&bindings)
+            .vertex_attribute_descriptions(&attributes);
+
+        let color_blend = vk::PipelineColorBlendAttachmentState::default()
+            .color_write_mask(vk::ColorComponentFlags::RGBA)
+            .blend_enable(false);
+
+        // Viewport and scissors are dynamic, set for every draw. The surface
+        // is opaque and writes reversed-Z depth
+        let pipeline = GraphicsPipelineBuilder::new(device)
+            .vertex_shader(vertex.raw)
+            .fragment_shader(fragment.raw)
+            .render_pass(render_pass)
+            .pipeline_layout(layout.raw)
+            .viewport(vec![vk::Viewport::default().width(1.0).height(1.0).max_depth(1.0)])
+            .scissors(vec![vk::Rect2D::default()])
+            .input_assembly(vk::PipelineInputAssemblyStateCreateInfo::default().topology(vk::PrimitiveTopology::TRIANGLE_LIST))
+            .rasterization(
+                vk::PipelineRasterizationStateCreateInfo::default()
+                    .polygon_mode(vk::PolygonMode::FILL)
+                    .line_width(1.0)
+                    .cull_mode(vk::CullModeFlags::NONE),
+            )
+            .multisampling(vk::PipelineMultisampleStateCreateInfo::default().rasterization_samples(vk::SampleCountFlags::TYPE_1))
+            .color_blending(vk::PipelineColorBlendStateCreateInfo::default().attachments(&[color_blend]))
+            .depth_stencil(
+                vk::PipelineDepthStencilStateCreateInfo::default()
+                    .depth_test_enable(true)
+                    .depth_write_enable(true)
+                    .depth_compare_op(vk::CompareOp::GREATER_OR_EQUAL),
+            )
+            .dynamic_state(vec![vk::DynamicState::VIEWPORT, vk::DynamicState::SCISSOR])
+            .vertex_input_info(vertex_input_info)
+            .build()?;
+
+        Ok(Self {
+            pool: Pool::new(),
+            retired: Retired::default(),
+            frame_count,
+            layout,
+            pipeline,
+        })
+    }
+
+    /// Copy changed rows into the buffers of `frame` and release buffers no
+    /// frame in flight can use anymore, called once per frame
+    pub fn update(&mut self, device: &Device, frame: usize) -> VulkanResult<()> {
+        profiling::scope!("HeightFieldStore::update");
+
+        self.retired.update(device);
+
+        for field in self.pool.slots.values_mut() {
+            field.vertices.prepare(device, frame)?;
+        }
+
+        Ok(())
+    }
+
+    /// Draw the surface of `field` over the whole target, must be inside the
+    /// render pass
+    ///
+    /// `sets` are the bindless and per-frame sets of the frame, `transform`
+    /// is the index of the world matrix or `None` for identity and
+    /// `colormap_idx` the lookup table of [`HeightField::colormap`]
+    #[allow(clippy::too_many_arguments)]
+    pub(crate) unsafe fn draw(
+        &self,
+        device: &ash::Device,
+        cbuf: vk::CommandBuffer,
+        frame: usize,
+        resolution: vk::Extent2D,
+        sets: &[vk::DescriptorSet],
+        field: &HeightField,
+        transform: Option<u32>,
+        colormap_idx: u32,
+    ) {
+        let (Some(vertices), Some(indices)) = (field.vertices.raw(frame), field.indices.raw(frame)) else {
+            return;
+        };
+
+        let viewport = vk::Viewport::default()
+            .width(resolution.width as f32)
+            .height(resolution.height as f32)
+            .max_depth(1.0);
+        let scissor = vk::Rect2D::default().extent(resolution);
+
+        device.cmd_bind_pipeline(cbuf, vk::PipelineBindPoint::GRAPHICS, self.pipeline.raw);
+        device.cmd_bind_descriptor_sets(cbuf, vk::PipelineBindPoint::GRAPHICS, self.layout.raw, 0, sets, &[]);
+        device.cmd_bind_vertex_buffers(cbuf, 0, &[vertices], &[0]);
+        device.cmd_bind_index_buffer(cbuf, indices, 0, vk::IndexType::UINT32);
+        device.cmd_set_viewport(cbuf, 0, &[viewport]);
+        device.cmd_set_scissor(cbuf, 0, &[scissor]);
+        device.cmd_push_constants(
+            cbuf,
+            self.layout.raw,
+            vk::ShaderStageFlags::VERTEX | vk::ShaderStageFlags::FRAGMENT,
+            0,
+            bytemuck::bytes_of(&field.push_constants(transform, colormap_idx)),
+        );
+        device.cmd_draw_indexed(cbuf, field.indices.count(), 1, 0, 0, 0);
+    }
+
+    pub fn destroy(&mut self, device: &Device) {
+        self.retired.destroy(device);
+        for (_, field) in self.pool.slots.drain() {
+            for mut buffer in field.vertices.into_buffers().into_iter().chain(field.indices.into_buffers()) {
+                buffer.destroy(device);
+            }
+        }
+        self.pipeline.destroy(device);
+        unsafe {
+            device.destroy_pipeline_layout(self.layout.raw, None);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn field(size: [u32; 2], heights: &[f32]) -> HeightField {
+        let mut field = HeightField {
+            colormap: Colormap::Viridis,
+            range: ScalarRange::new(0.0, 1.0),
+            contour_interval: None,
+            contour_color: [1.0; 4],
+            wireframe: None,
+            size,
+            extent: [2.0, 2.0],
+            heights: heights.to_vec(),
+            vertices: MeshBuffer::new_dynamic(vk::BufferUsageFlags::VERTEX_BUFFER, size_of::<HeightVertex>(), 2),
+            indices: MeshBuffer::new_dynamic(vk::BufferUsageFlags::INDEX_BUFFER, size_of::<u32>(), 2),
+            frame_count: 2,
+        };
+        field.upload_rows(0, size[1] - 1);
+        field
+    }
+
+    fn stored(field: &HeightField) -> Vec<HeightVertex> {
+        let MeshBuffer::Dynamic(dynamic) = &field.vertices else {
+            unreachable!()
+        };
+        bytemuck::cast_slice(dynamic.data()).to_vec()
+    }
+
+    #[test]
+    fn regions_update_heights_and_neighbour_normals() {
+        let mut field = field([3, 3], &[0.0; 9]);
+        field.write_region([1, 1], [1, 1], &[2.0]);
+
+        let vertices = stored(&field);
+        assert_eq!(field.height(1, 1), 2.0);
+        assert_eq!(vertices.len(), 9);
+        assert_eq!(vertices[4].height, 2.0);
+        // The sample above the peak slopes up towards +y
+        assert!(vertices[1].normal[1] < 0.0);
+        assert_eq!(vertices[0].normal, [0.0, 0.0, 1.0]);
+        assert_eq!(field.height_range(), [0.0, 2.0]);
+    }
+
+    #[test]
+    fn normals_follow_slopes() {
+        // z = x over 3x2 samples one unit apart
+        let heights = [0.0, 1.0, 2.0, 0.0, 1.0, 2.0];
+        let n = normal(&heights, [3, 2], [1.0, 1.0], 1, 0);
+        let s = std::f32::consts::FRAC_1_SQRT_2;
+
+        assert!((n[0] + s).abs() < 1e-6 && n[1].abs() < 1e-6 && (n[2] - s).abs() < 1e-6);
+        // One-sided at the border
+        assert_eq!(normal(&heights, [3, 2], [1.0, 1.0], 0, 1), n);
+        // Flat along y
+        assert_eq!(normal(&[1.0; 4], [2, 2], [0.5, 2.0], 1, 1), [0.0, 0.0, 1.0]);
+    }
+
+    #[test]
+    fn cells_split_into_two_triangles() {
+        let indices = grid_indices([3, 2]);
+
+        assert_eq!(indices, vec![0, 1, 4, 0, 4, 3, 1, 2, 5, 1, 5, 4]);
+        assert_eq!(grid_indices([4, 5]).len(), 3 * 4 * 6);
+        assert_eq!(cell([3, 5], [1.0, 2.0]), [0.5, 0.5]);
+    }
+
+    #[test]
+    fn ranges_skip_nan() {
+        assert_eq!(height_range(&[2.0, f32::NAN, -1.0, 0.5]), [-1.0, 2.0]);
+    }
+}
@@ -0,0 +1,326 @@
+use std::ops::{Bound, Range, RangeBounds};
+use std::sync::{Arc, Weak};
+
+use ash::vk;
+use bytemuck::{Pod, Zeroable};
+
+use crate::core::{Device, GpuBuffer, GpuBufferBuilder, VulkanResult};
+use crate::render_context::RenderContext;
+use crate::resources::{Create, Destroy, Get, GetMut, Pool, Ref, RefMut, Res, ResourceKey, Resources, Retired};
+
+/// Parameters of a new [`InstanceBuffer`]
+pub struct InstanceBufferDesc<'a> {
+    data: &'a [u8],
+    stride: u32,
+}
+
+impl<'a> InstanceBufferDesc<'a> {
+    /// Initial instances, their layout must match
+    /// [`RasterPipelineDesc::instance_input`](crate::RasterPipelineDesc::instance_input)
+    pub fn new<T: Pod + Zeroable>(instances: &'a [T]) -> Self {
+        Self {
+            data: bytemuck::cast_slice(instances),
+            stride: size_of::<T>() as u32,
+        }
+    }
+}
+
+/// Per-instance data read at vertex binding 1, or per-vertex attributes at
+/// binding 2 drawn with [`PassContext::draw_mesh_with_attributes`]
+///
+/// Instances are kept on the CPU and copied into the buffer of the frame in
+/// flight before its passes are recorded, so they can be rewritten every frame
+/// without waiting for the GPU
+///
+/// # Example
+/// ```ignore
+/// let instances = world.create::<InstanceBuffer>(InstanceBufferDesc::new(&offsets))?;
+///
+/// // every frame
+/// world.get_mut(&instances).write(&offsets);
+///
+/// world.draw_frame(|graph| {
+///     graph.add_pass(PresentPass::new("Scatter", |_| {}, move |ctx, _| unsafe {
+///         ctx.bind_pipeline(&pipeline);
+///         ctx.draw_mesh_instanced(&sphere, &instances, ..);
+///     }));
+/// })?;
+/// ```
+///
+/// [`PassContext::draw_mesh_with_attributes`]: crate::frame_graph::PassContext::draw_mesh_with_attributes
+pub struct InstanceBuffer {
+    data: Vec<u8>,
+    stride: u32,
+    /// Increased by every write
+    version: u64,
+    /// Buffer of every frame in flight and the version it holds
+    frames: Vec<(Option<GpuBuffer>, u64)>,
+}
+
+impl InstanceBuffer {
+    /// Replace all instances
+    ///
+    /// # Panics
+    /// - if the size of `T` differs from the one the buffer was created with
+    pub fn write<T: Pod + Zeroable>(&mut self, instances: &[T]) {
+        assert_eq!(size_of::<T>() as u32, self.stride, "Instance stride cannot change");
+        self.data.clear();
+        self.data.extend_from_slice(bytemuck::cast_slice(instances));
+        self.version += 1;
+    }
+
+    /// Number of instances
+    pub fn len(&self) -> u32 {
+        self.data.len() as u32 / self.stride
+    }
+
+    /// No instances to draw
+    pub fn is_empty(&self) -> bool {
+        self.data.is_empty()
+    }
+
+    /// Size of one instance in bytes
+    pub fn stride(&self) -> u32 {
+        self.stride
+    }
+
+    /// Buffer written by [`InstanceBuffer::prepare`] for `frame`
+    pub(crate) fn buffer(&self, frame: usize) -> Option<vk::Buffer> {
+        self.frames[frame % self.frames.len()].0.as_ref().map(|buffer| buffer.raw)
+    }
+
+    /// Instances selected by `range`, `..` selects all of them
+    ///
+    /// # Panics
+    /// - if the range ends past the last instance
+    pub(crate) fn range(&self, range: impl RangeBounds<u32>) -> Range<u32> {
+        let first = match range.start_bound() {
+            Bound::Included(start) => *start,
+            Bound::Excluded(start) => start.checked_add(1).expect("Instance range out of bounds"),
+            Bound::Unbounded => 0,
+        };
+        let end = match range.end_bound() {
+            Bound::Included(end) => end.checked_add(1).expect("Instance range out of bounds"),
+            Bound::Excluded(end) => *end,
+            Bound::Unbounded => self.len(),
+        };
+
+        assert!(end <= self.len(), "Instance range out of bounds");
+        first..end
+    }
+
+    /// Whether the buffer of `frame` misses the latest write, marked as
+    /// current as it is copied
+    fn take_stale(&mut self, frame: usize) -> bool {
+        if self.data.is_empty() {
+            return false;
+        }
+
+        let version = &mut self.frames[frame].1;
+        let stale = *version != self.version;
+        *version = self.version;
+        stale
+    }
+
+    /// Copy the latest instances into the buffer of `frame`, the frame must
+    /// not be in flight
+    fn prepare(&mut self, device: &Device, frame: usize) -> VulkanResult<()> {
+        let frame = frame % self.frames.len();
+        if !self.take_stale(frame) {
+            return Ok(());
+        }
+
+        let buffer = &mut self.frames[frame].0;
+        let size = self.data.len() as u64;
+        let fits = buffer
+            .as_ref()
+            .and_then(|buffer| buffer.allocation.as_ref())
+            .is_some_and(|allocation| allocation.size() >= size);
+
+        if !fits {
+            if let Some(mut old) = buffer.take() {
+                old.destroy(device);
+            }
+            *buffer = Some(
+                GpuBufferBuilder::cpu_only(device)
+                    .size(size.next_power_of_two())
+                    .usage(vk::BufferUsageFlags::VERTEX_BUFFER)
+                    .build()?,
+            );
+        }
+
+        buffer.as_mut().unwrap().upload_data_at(0, &self.data)
+    }
+
+    fn into_buffers(self) -> impl Iterator<Item = GpuBuffer> {
+        self.frames.into_iter().filter_map(|(buffer, _)| buffer)
+    }
+}
+
+/// Instance buffers and the ones waiting for the GPU to finish with them
+pub struct InstanceStore {
+    pub(crate) pool: Pool<InstanceBuffer>,
+    retired: Retired<GpuBuffer>,
+    frame_count: usize,
+}
+
+impl InstanceStore {
+    pub fn new(frame_count: usize) -> Self {
+        Self {
+            pool: Pool::new(),
+            retired: Retired::default(),
+            frame_count,
+        }
+    }
+
+    /// Upload changed instances for `frame` and release buffers no frame in
+    /// flight can use anymore, called once per frame
+    pub fn update(&mut self, device: &Device, frame: usize) -> VulkanResult<()> {
+        profiling::scope!("InstanceStore::update");
+
+        self.retired.update(device);
+
+        for instances in self.pool.slots.values_mut() {
+            instances.prepare(device, frame)?;
+        }
+
+        Ok(())
+    }
+
+    pub fn destroy(&mut self, device: &Device) {
+        self.retired.destroy(device);
+        for (_, instances) in self.pool.slots.drain() {
+            for mut buffer in instances.into_buffers() {
+                buffer.destroy(device);
+            }
+        }
+    }
+}
+
+impl Destroy for InstanceBuffer {
+    fn destroy(key: ResourceKey, _ctx: Weak<RenderContext>, resources: Weak<Resources>) {
+        let Some(resources) = resources.upgrade() else {
+            return;
+        };
+
+        let mut store = resources.instances.write();
+        if let Some(instances) = store.pool.remove(key) {
+            let frames = store.frame_count;
+            store
+                .retired
+                .extend(frames, instances.into_buffers());
+        }
+    }
+}
+
+impl Create for InstanceBuffer {
+    type Desc<'a> = InstanceBufferDesc<'a>;
+
+    fn create(ctx: &Arc<RenderContext>, resources: &Arc<Resources>, desc: Self::Desc<'_>) -> VulkanResult<Res<Self>> {
+        assert!(desc.stride != 0, "Instance size cannot be zero");
+
+        let mut store = resources.instances.write();
+        let frames = (0..store.frame_count).map(|_| (None, 0)).collect();
+
+        let handle = store.pool.insert(
+            Arc::downgrade(ctx),
+            Arc::downgrade(resources),
+            InstanceBuffer {
+                data: desc.data.to_vec(),
+                stride: desc.stride,
+                version: 1,
+                frames,
+            },
+        );
+
+        Ok(handle)
+    }
+}
+
+impl Get for InstanceBuffer {
+    fn get<'a>(resources: &'a Resources, res: &Res<Self>) -> Ref<'a, Self> {
+        let store = resources.instances.try_read().expect("Instances are already borrowed mutably");
+        Ref(parking_lot::RwLockReadGuard::map(store, |store| store.pool.get(res)))
+    }
+}
+
+impl GetMut for InstanceBuffer {
+    fn get_mut<'a>(resources: &'a Resources, res: &Res<Self>) -> RefMut<'a, Self> {
+        let store = resources.instances.try_write().expect("Instances are already borrowed");
+        RefMut(parking_lot::RwLockWriteGuard::map(store, |store| store.pool.get_mut(res)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn instances(data: &[[f32; 2]], frame_count: usize) -> InstanceBuffer {
+        let desc = InstanceBufferDesc::new(data);
+        InstanceBuffer {
+            data: desc.data.to_vec(),
+            stride: desc.stride,
+            version: 1,
+            frames: (0..frame_count).map(|_| (None, 0)).collect(),
+        }
+    }
+
+    #[test]
+    fn writes_replace_instances() {
+        let mut buffer = instances(&[[0.0; 2]; 3], 2);
+        assert_eq!((buffer.len(), buffer.stride()), (3, 8));
+
+        buffer.write(&[[1.0f32, 2.0]]);
+        assert_eq!(buffer.len(), 1);
+        assert_eq!(buffer.data, bytemuck::cast_slice::<f32, u8>(&[1.0, 2.0]));
+
+        buffer.write::<[f32; 2]>(&[]);
+        assert!(buffer.is_empty());
+    }
+
+    #[test]
+    #[should_panic(expected = "Instance stride cannot change")]
+    fn writes_keep_the_stride() {
+        instances(&[[0.0; 2]], 1).write(&[0.0f32]);
+    }
+
+    #[test]
+    fn every_frame_copies_each_write_once() {
+        let mut buffer = instances(&[[0.0; 2]], 2);
+
+        // Frame 0 copies the first write, frame 1 is not prepared yet
+        assert!(buffer.take_stale(0));
+        assert!(!buffer.take_stale(0));
+
+        buffer.write(&[[1.0f32; 2]; 2]);
+        assert!(buffer.take_stale(0));
+        assert!(buffer.take_stale(1));
+        assert!(!buffer.take_stale(1));
+
+        // Nothing to copy while empty
+        buffer.write::<[f32; 2]>(&[]);
+        assert!(!buffer.take_stale(0));
+    }
+
+    #[test]
+    fn ranges_resolve_bounds() {
+        let buffer = instances(&[[0.0; 2]; 4], 1);
+        assert_eq!(buffer.range(..), 0..4);
+        assert_eq!(buffer.range(1..3), 1..3);
+        assert_eq!(buffer.range(1..=3), 1..4);
+        assert_eq!(buffer.range((Bound::Excluded(0), Bound::Unbounded)), 1..4);
+        assert_eq!(buffer.range(4..), 4..4);
+    }
+
+    #[test]
+    #[should_panic(expected = "Instance range out of bounds")]
+    fn ranges_end_inside_the_buffer() {
+        instances(&[[0.0; 2]; 4], 1).range(2..5);
+    }
+
+    #[test]
+    #[should_panic(expected = "Instance range out of bounds")]
+    fn excluded_start_cannot_overflow() {
+        instances(&[[0.0; 2]; 4], 1).range((Bound::Excluded(u32::MAX), Bound::Unbounded));
+    }
+}
@@ -0,0 +1,312 @@
+//! Marching cubes on the CPU, the reference `shaders/marching_cubes.comp` is
+//! checked against
+//!
+//! Corner `i` of a cell is offset by the bits of `i` along x, y and z. Edge
+//! `e` runs along axis `e / 4` from the corner of [`edge_corners`]. Corners
+//! at or above the iso value are inside the surface
+
+use std::collections::HashMap;
+use std::sync::OnceLock;
+
+use bytemuck::{Pod, Zeroable};
+
+use crate::math::{normalize, Vec3};
+
+/// Edges of the triangles of every cell case, three by three and -1 after
+/// the last
+pub(crate) type TriangleTable = [[i8; 16]; 256];
+
+/// Point of an isosurface in the layout of `shaders/isosurface.vert`
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Pod, Zeroable)]
+pub struct IsoVertex {
+    /// Position inside the box of the surface
+    pub position: Vec3,
+    /// Unit normal towards lower values, zero where the scalars are flat
+    pub normal: Vec3,
+}
+
+/// Indexed triangles of an isosurface
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct IsosurfaceMesh {
+    /// Points where the surface crosses edges of the grid
+    pub vertices: Vec<IsoVertex>,
+    /// Three vertices per triangle, counterclockwise seen from lower values
+    pub indices: Vec<u32>,
+}
+
+impl IsosurfaceMesh {
+    /// Triangles of the surface
+    pub fn triangle_count(&self) -> usize {
+        self.indices.len() / 3
+    }
+
+    /// Corner positions of every triangle
+    pub fn triangles(&self) -> impl Iterator<Item = [Vec3; 3]> + '_ {
+        self.indices
+            .chunks_exact(3)
+            .map(|triangle| std::array::from_fn(|i| self.vertices[triangle[i] as usize].position))
+    }
+}
+
+/// First and last corner of edge `e`
+pub(crate) fn edge_corners(e: usize) -> (usize, usize) {
+    let (axis, k) = (e / 4, e % 4);
+    // The two bits of `k` are the other axes, a zero is put at `axis`
+    let start = (k & ((1 << axis) - 1)) | ((k >> axis) << (axis + 1));
+    (start, start | (1 << axis))
+}
+
+/// Edge between two corners differing along one axis
+fn corner_edge(a: usize, b: usize) -> usize {
+    let axis = (a ^ b).trailing_zeros() as usize;
+    let start = a.min(b);
+    axis * 4 + ((start & ((1 << axis) - 1)) | ((start >> (axis + 1)) << axis))
+}
+
+fn corner_offset(corner: usize) -> [usize; 3] {
+    [corner & 1, (corner >> 1) & 1, (corner >> 2) & 1]
+}
+
+/// Triangles of every case, generated once
+///
+/// On each face of the cell a segment cuts off every run of inside corners,
+/// so two inside corners on a diagonal stay apart and neighbouring cells
+/// agree on their shared face. Segments chain into loops around the cell
+/// which are split into fans facing the outside corners
+pub(crate) fn triangle_table() -> &'static TriangleTable {
+    static TABLE: OnceLock<TriangleTable> = OnceLock::new();
+
+    TABLE.get_or_init(|| {
+        // Corners of each face counterclockwise seen from outside the cell
+        let faces: Vec<[usize; 4]> = (0..3)
+            .flat_map(|axis| {
+                let (b, c) = ((axis + 1) % 3, (axis + 2) % 3);
+                [0, 1].map(|side| {
+                    let corner = |u: usize, v: usize| (side << axis) | (u << b) | (v << c);
+                    let ring = [corner(0, 0), corner(1, 0), corner(1, 1), corner(0, 1)];
+                    if side == 1 {
+                        ring
+                    } else {
+                        [ring[3], ring[2], ring[1], ring[0]]
+                    }
+                })
+            })
+            .collect();
+
+        let mut table = [[-1i8; 16]; 256];
+        for (case, row) in table.iter_mut().enumerate() {
+            let inside = |corner: usize| case & (1 << corner) != 0;
+
+            // Edge where the surface leaves a run of inside corners to the
+            // edge where it entered, walking each face counterclockwise
+            let mut next = [None; 12];
+            for ring in &faces {
+                for i in 0..4 {
+                    if !inside(ring[i]) || inside(ring[(i + 1) % 4]) {
+                        continue;
+                    }
+                    let mut first = i;
+                    while inside(ring[(first + 3) % 4]) {
+                        first = (first + 3) % 4;
+                    }
+                    let exit = corner_edge(ring[i], ring[(i + 1) % 4]);
+                    next[exit] = Some(corner_edge(ring[(first + 3) % 4], ring[first]));
+                }
+            }
+
+            let mut entries = vec![];
+            let mut visited = [false; 12];
+            for start in 0..12 {
+                if visited[start] || next[start].is_none() {
+                    continue;
+                }
+
+                let mut ring = vec![];
+                let mut edge = start;
+                while !visited[edge] {
+                    visited[edge] = true;
+                    ring.push(edge as i8);
+                    edge = next[edge].expect("Surface loops are closed");
+                }
+                for i in 1..ring.len() - 1 {
+                    entries.extend([ring[0], ring[i + 1], ring[i]]);
+                }
+            }
+
+            assert!(entries.len() < 16, "Case {case} has {} triangles", entries.len() / 3);
+            row[..entries.len()].copy_from_slice(&entries);
+        }
+        table
+    })
+}
+
+/// Samples of a 3D grid, `x` first then rows and slices
+struct Grid<'a> {
+    size: [u32; 3],
+    values: &'a [f32],
+    extent: [f32; 3],
+}
+
+impl Grid<'_> {
+    fn value(&self, [x, y, z]: [u32; 3]) -> f32 {
+        self.values[((z * self.size[1] + y) * self.size[0] + x) as usize]
+    }
+
+    /// Gradient in samples per world unit, central differences inside and
+    /// one-sided at the border
+    fn gradient(&self, point: [u32; 3]) -> Vec3 {
+        std::array::from_fn(|axis| {
+            let (mut lo, mut hi) = (point, point);
+            lo[axis] = point[axis].saturating_sub(1);
+            hi[axis] = (point[axis] + 1).min(self.size[axis] - 1);
+            if lo[axis] == hi[axis] {
+                return 0.0;
+            }
+            let step = (hi[axis] - lo[axis]) as f32 * self.extent[axis] / self.size[axis] as f32;
+            (self.value(hi) - self.value(lo)) / step
+        })
+    }
+
+    /// Position of a point between samples, texel centers tile the box
+    /// around the origin
+    fn position(&self, point: Vec3) -> Vec3 {
+        std::array::from_fn(|axis| ((point[axis] + 0.5) / self.size[axis] as f32 - 0.5) * self.extent[axis])
+    }
+}
+
+/// Surface where `values` cross `iso` in a box of `extent` around the origin,
+/// the samples of the same grid as a [`Texture`] of `size` texels
+///
+/// Cells sharing an edge share its vertex
+///
+/// # Panics
+/// - if there are not as many values as texels
+///
+/// [`Texture`]: crate::resources::Texture
+pub fn marching_cubes(size: [u32; 3], values: &[f32], iso: f32, extent: [f32; 3]) -> IsosurfaceMesh {
+    assert_eq!(values.len(), size.iter().map(|&n| n as usize).product::<usize>(), "Grid size differs from its values");
+
+    let grid = Grid { size, values, extent };
+    let table = triangle_table();
+    let mut mesh = IsosurfaceMesh::default();
+    // Vertex of every crossed grid edge, by its first sample and axis
+    let mut shared = HashMap::new();
+
+    let cells = size.map(|n| n.saturating_sub(1));
+    for z in 0..cells[2] {
+        for y in 0..cells[1] {
+            for x in 0..cells[0] {
+                let corner = |i: usize| {
+                    let [dx, dy, dz] = corner_offset(i);
+                    [x + dx as u32, y + dy as u32, z + dz as u32]
+                };
+                let case = (0..8).fold(0, |case, i| case | (usize::from(grid.value(corner(i)) >= iso) << i));
+
+                for &edge in table[case].iter().take_while(|&&edge| edge >= 0) {
+                    let (a, b) = edge_corners(edge as usize);
+                    let (pa, pb) = (corner(a), corner(b));
+
+                    let index = *shared.entry((pa, edge / 4)).or_insert_with(|| {
+                        let (va, vb) = (grid.value(pa), grid.value(pb));
+                        let t = (iso - va) / (vb - va);
+                        let (ga, gb) = (grid.gradient(pa), grid.gradient(pb));
+                        let point = std::array::from_fn(|axis| pa[axis] as f32 + t * (pb[axis] as f32 - pa[axis] as f32));
+                        let gradient: Vec3 = std::array::from_fn(|axis| ga[axis] + t * (gb[axis] - ga[axis]));
+
+                        mesh.vertices.push(IsoVertex {
+                            position: grid.position(point),
+                            normal: normalize(gradient.map(|g| -g)),
+                        });
+                        mesh.vertices.len() as u32 - 1
+                    });
+                    mesh.indices.push(index);
+                }
+            }
+        }
+    }
+
+    mesh
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::math::{cross, dot, sub};
+
+    /// Normal of a triangle by its winding, not unit length
+    fn face_normal([a, b, c]: [Vec3; 3]) -> Vec3 {
+        cross(sub(b, a), sub(c, a))
+    }
+
+    /// Distance to the center of an `n`-cubed grid, in texels
+    fn ball(n: u32) -> Vec<f32> {
+        let c = (n as f32 - 1.0) / 2.0;
+        (0..n * n * n)
+            .map(|i| {
+                let [x, y, z] = [i % n, i / n % n, i / (n * n)].map(|v| v as f32 - c);
+                -(x * x + y * y + z * z).sqrt()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn edges_join_their_corners() {
+        for e in 0..12 {
+            let (a, b) = edge_corners(e);
+            assert_eq!((a ^ b).count_ones(), 1);
+            assert_eq!(corner_edge(a, b), e);
+            assert_eq!(corner_edge(b, a), e);
+        }
+        assert_eq!(edge_corners(5), (1, 3));
+    }
+
+    #[test]
+    fn cases_are_complementary_in_size() {
+        let table = triangle_table();
+        let count = |case: usize| table[case].iter().take_while(|&&edge| edge >= 0).count();
+
+        assert_eq!(count(0), 0);
+        assert_eq!(count(255), 0);
+        // One corner in or out is a single triangle
+        assert_eq!(count(1 << 3), 3);
+        assert_eq!(count(255 ^ (1 << 3)), 3);
+        // Opposite corners of a face stay apart
+        assert_eq!(count(1 | 1 << 3), 6);
+        assert!((0..256).all(|case| count(case) % 3 == 0 && count(case) <= 15));
+    }
+
+    #[test]
+    fn balls_are_closed_and_face_out() {
+        // Radius 3.2 inside a 9x9x9 grid, off the samples and the border
+        let mesh = marching_cubes([9; 3], &ball(9), -3.2, [9.0; 3]);
+        assert!(mesh.triangle_count() > 50);
+
+        // Every edge is shared by two triangles in opposite directions
+        let mut edges = HashMap::new();
+        for triangle in mesh.indices.chunks_exact(3) {
+            for i in 0..3 {
+                *edges.entry((triangle[i], triangle[(i + 1) % 3])).or_insert(0) += 1;
+            }
+        }
+        for (&(a, b), &count) in &edges {
+            assert_eq!((count, edges.get(&(b, a))), (1, Some(&1)));
+        }
+
+        for (triangle, indices) in mesh.triangles().zip(mesh.indices.chunks_exact(3)) {
+            let center = triangle.iter().fold([0.0; 3], |sum, p| std::array::from_fn(|i| sum[i] + p[i] / 3.0));
+            let radius = dot(center, center).sqrt();
+            assert!((radius - 3.2).abs() < 0.3, "{radius}");
+            assert!(dot(face_normal(triangle), center) > 0.0);
+
+            let normal = mesh.vertices[indices[0] as usize].normal;
+            assert!(dot(normal, center) / radius > 0.9);
+        }
+    }
+
+    #[test]
+    fn flat_grids_have_no_cells() {
+        assert_eq!(marching_cubes([4, 4, 1], &[1.0; 16], 0.5, [1.0; 3]), IsosurfaceMesh::default());
+        assert_eq!(marching_cubes([2; 3], &[0.0; 8], 0.5, [1.0; 3]).triangle_count(), 0);
+    }
+}
@@ -0,0 +1,696 @@
+use std::sync::{Arc, Weak};
+
+use ash::vk;
+use bytemuck::{Pod, Zeroable};
+
+use crate::core::{
+    read_shader_from_bytes, ComputePipeline, ComputePipelineBuilder, DescriptorPool, DescriptorPoolBuilder, DescriptorSetLayout,
+    DescriptorSetLayoutBuilder, Device, GpuBuffer, GpuBufferBuilder, GraphicsPipeline, GraphicsPipelineBuilder, PipelineLayout,
+    PipelineLayoutBuilder, ShaderBuilder,
+};
+use crate::frame_graph::PushHeader;
+use crate::render_context::RenderContext;
+use crate::resources::{Create, Destroy, Get, GetMut, Pool, Ref, RefMut, Res, ResourceKey, Resources, Retired, Texture};
+use crate::VulkanResult;
+
+mod marching_cubes;
+
+use marching_cubes::triangle_table;
+pub use marching_cubes::{marching_cubes, IsoVertex, IsosurfaceMesh};
+
+const EXTRACT_SHADER: &[u8] = include_bytes!(concat!(env!("CARGO_MANIFEST_DIR"), "/shaders/spv/marching_cubes_comp.spv"));
+const VERTEX_SHADER: &[u8] = include_bytes!(concat!(env!("CARGO_MANIFEST_DIR"), "/shaders/spv/isosurface_vert.spv"));
+const FRAGMENT_SHADER: &[u8] = include_bytes!(concat!(env!("CARGO_MANIFEST_DIR"), "/shaders/spv/isosurface_frag.spv"));
+
+/// Cells along each axis of one extraction workgroup, see
+/// `shaders/marching_cubes.comp`
+const EXTRACT_GROUP_SIZE: u32 = 4;
+
+/// Isosurfaces alive at once, each one holds a descriptor set of the store
+const MAX_ISOSURFACES: u32 = 256;
+
+/// Bindings of set 2 of the extraction, the triangle table then the
+/// vertices, indices and counters of one isosurface
+const TABLE_BINDING: u32 = 0;
+const VERTICES_BINDING: u32 = 1;
+const INDICES_BINDING: u32 = 2;
+const COUNTERS_BINDING: u32 = 3;
+
+/// Counters in the layout of `shaders/marching_cubes.comp`, the draw is
+/// read by `vkCmdDrawIndexedIndirect`
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Pod, Zeroable)]
+struct Counters {
+    index_count: u32,
+    instance_count: u32,
+    first_index: u32,
+    vertex_offset: i32,
+    first_instance: u32,
+    vertex_count: u32,
+    dropped_cells: u32,
+}
+
+/// Push constants of `shaders/marching_cubes.comp`
+#[repr(C)]
+#[derive(Clone, Copy, Pod, Zeroable)]
+struct ExtractPush {
+    texture_idx: u32,
+    size: [u32; 3],
+    iso: f32,
+    value_scale: f32,
+    value_offset: f32,
+    extent: [f32; 3],
+    max_vertices: u32,
+    max_indices: u32,
+}
+
+/// Push constants in the layout of `shaders/isosurface.vert`
+#[repr(C)]
+#[derive(Clone, Copy, Pod, Zeroable)]
+struct Push {
+    header: PushHeader,
+    color: [f32; 4],
+}
+
+/// Parameters of a new [`Isosurface`]
+pub struct IsosurfaceDesc {
+    texture: Res<Texture>,
+    iso: f32,
+    extent: [f32; 3],
+    values: [f32; 2],
+    color: [f32; 4],
+    max_triangles: u32,
+}
+
+impl IsosurfaceDesc {
+    /// Surface where the scalars of a 3D `texture` cross `iso`, in a 1x1x1
+    /// box around the origin with room for 256K light grey triangles
+    pub fn new(texture: &Res<Texture>, iso: f32) -> Self {
+        Self {
+            texture: texture.clone(),
+            iso,
+            extent: [1.0; 3],
+            values: [1.0, 0.0],
+            color: [0.8, 0.8, 0.8, 1.0],
+            max_triangles: 256 * 1024,
+        }
+    }
+
+    /// Size of the box in world units before the transform, as
+    /// [`VolumeDesc::extent`]
+    ///
+    /// [`VolumeDesc::extent`]: crate::resources::VolumeDesc::extent
+    pub fn extent(mut self, extent: [f32; 3]) -> Self {
+        self.extent = extent;
+        self
+    }
+
+    /// Scalars are `texel * scale + offset`, as [`VolumeDesc::values`]
+    ///
+    /// [`VolumeDesc::values`]: crate::resources::VolumeDesc::values
+    pub fn values(mut self, scale: f32, offset: f32) -> Self {
+        self.values = [scale, offset];
+        self
+    }
+
+    /// Linear color of the surface
+    pub fn color(mut self, color: [f32; 4]) -> Self {
+        self.color = color;
+        self
+    }
+
+    /// Triangles the buffers hold, cells past it are dropped. Vertices are
+    /// reserved for twice as many
+    pub fn max_triangles(mut self, count: u32) -> Self {
+        self.max_triangles = count;
+        self
+    }
+}
+
+/// Buffers written by the extraction of one surface
+struct IsosurfaceBuffers {
+    vertices: GpuBuffer,
+    indices: GpuBuffer,
+    counters: GpuBuffer,
+    set: vk::DescriptorSet,
+}
+
+impl IsosurfaceBuffers {
+    fn destroy(&mut self, device: &Device, pool: &DescriptorPool) {
+        self.vertices.destroy(device);
+        self.indices.destroy(device);
+        self.counters.destroy(device);
+        unsafe {
+            let _ = device.free_descriptor_sets(pool.raw, &[self.set]);
+        }
+    }
+}
+
+/// Triangles where the scalars of a 3D [`Texture`] cross an iso value,
+/// extracted by marching cubes in a compute pass and drawn indirectly, see
+/// [`PassContext::draw_isosurface`]
+///
+/// The surface is extracted before the passes of the first frame and again
+/// whenever [`Isosurface::iso`] changes. [`marching_cubes`] computes the
+/// same triangles on the CPU
+///
+/// # Example
+/// ```ignore
+/// let ct = world.create::<Texture>(TextureDesc::new(512, 512, TextureFormat::R16Unorm, bytemuck::cast_slice(&samples)).depth(256))?;
+/// let bone = world.create::<Isosurface>(
+///     IsosurfaceDesc::new(&ct, 300.0)
+///         .extent([0.25, 0.25, 0.16])
+///         .values(65535.0, -1024.0)
+///         .color([0.9, 0.85, 0.7, 1.0]),
+/// )?;
+///
+/// world.draw_frame(|graph| {
+///     graph.add_pass(PresentPass::new("Bone", |_| {}, |ctx, _| unsafe {
+///         ctx.draw_isosurface(&bone);
+///     }));
+/// })?;
+///
+/// world.get_mut::<Isosurface>(&bone).iso = 500.0;
+/// ```
+///
+/// [`PassContext::draw_isosurface`]: crate::frame_graph::PassContext::draw_isosurface
+pub struct Isosurface {
+    /// Scalar the surface passes through
+    pub iso: f32,
+    /// Linear color of the surface
+    pub color: [f32; 4],
+    /// Kept alive while the surface is extracted from it
+    texture: Res<Texture>,
+    texture_idx: u32,
+    size: [u32; 3],
+    extent: [f32; 3],
+    values: [f32; 2],
+    max_triangles: u32,
+    /// Iso value of the triangles in the buffers, `None` before the first
+    /// extraction
+    extracted: Option<f32>,
+    buffers: IsosurfaceBuffers,
+}
+
+impl Isosurface {
+    /// Texture of the scalars
+    pub fn texture(&self) -> &Res<Texture> {
+        &self.texture
+    }
+
+    /// Size of the box in world units before the transform
+    pub fn extent(&self) -> [f32; 3] {
+        self.extent
+    }
+
+    /// Triangles the buffers hold
+    pub fn max_triangles(&self) -> u32 {
+        self.max_triangles
+    }
+
+    /// Triangles of the last extraction, read back from the GPU
+    ///
+    /// Only meant for tests and tools: the frame extracting them must have
+    /// finished, e.g. one rendered by `testing::render`
+    pub fn read_back(&self) -> IsosurfaceMesh {
+        let counters = self.counters();
+        let vertices = self.buffers.vertices.download_data(counters.vertex_count as usize * size_of::<IsoVertex>());
+        let indices = self.buffers.indices.download_data(counters.index_count as usize * size_of::<u32>());
+
+        IsosurfaceMesh {
+            // Downloaded bytes are not aligned for the items
+            vertices: vertices.chunks_exact(size_of::<IsoVertex>()).map(bytemuck::pod_read_unaligned).collect(),
+            indices: indices.chunks_exact(size_of::<u32>()).map(bytemuck::pod_read_unaligned).collect(),
+        }
+    }
+
+    /// Cells of the last extraction left out as the buffers were full, see
+    /// [`IsosurfaceDesc::max_triangles`]
+    ///
+    /// Like [`Isosurface::read_back`] the extracting frame must have finished
+    pub fn dropped_cells(&self) -> u32 {
+        self.counters().dropped_cells
+    }
+
+    fn counters(&self) -> Counters {
+        bytemuck::pod_read_unaligned(&self.buffers.counters.download_data(size_of::<Counters>()))
+    }
+
+    fn extract_push(&self) -> ExtractPush {
+        let max_indices = self.max_triangles * 3;
+        ExtractPush {
+            texture_idx: self.texture_idx,
+            size: self.size,
+            iso: self.iso,
+            value_scale: self.values[0],
+            value_offset: self.values[1],
+            extent: self.extent,
+            max_vertices: max_indices / 3 * 2,
+            max_indices,
+        }
+    }
+
+    fn push_constants(&self, transform: Option<u32>) -> Push {
+        Push {
+            header: PushHeader::new(transform, [0; 8]),
+            color: self.color,
+        }
+    }
+}
+
+impl Destroy for Isosurface {
+    fn destroy(key: ResourceKey, _ctx: Weak<RenderContext>, resources: Weak<Resources>) {
+        let Some(resources) = resources.upgrade() else {
+            return;
+        };
+
+        // Dropped after the lock, the texture may be released too
+        let texture = {
+            let mut store = resources.isosurfaces.write();
+            let frames = store.frame_count;
+            store.pool.remove(key).map(|surface| {
+                store.retired.push(frames, surface.buffers);
+                surface.texture
+            })
+        };
+
+        drop(texture);
+    }
+}
+
+impl Create for Isosurface {
+    type Desc<'a> = IsosurfaceDesc;
+    /// # Panics
+    /// - if the texture is not 3D
+    /// - if `max_triangles` is 0
+    fn create(ctx: &Arc<RenderContext>, resources: &Arc<Resources>, desc: Self::Desc<'_>) -> VulkanResult<Res<Self>> {
+        assert!(desc.max_triangles > 0, "Isosurfaces need room for a triangle");
+
+        let (texture_idx, size) = {
+            let textures = resources.textures.read();
+            let texture = textures.pool.get(&desc.texture);
+            assert!(texture.extent()[2] > 1, "Isosurfaces need a 3D texture");
+            (texture.index(), texture.extent())
+        };
+
+        let mut store = resources.isosurfaces.write();
+        let buffers = store.create_buffers(&ctx.device, desc.max_triangles)?;
+
+        let surface = Isosurface {
+            iso: desc.iso,
+            color: desc.color,
+            texture: desc.texture,
+            texture_idx,
+            size,
+            extent: desc.extent,
+            values: desc.values,
+            max_triangles: desc.max_triangles,
+            extracted: None,
+            buffers,
+        };
+
+        Ok(store.pool.insert(Arc::downgrade(ctx), Arc::downgrade(resources), surface))
+    }
+}
+
+impl Get for Isosurface {
+    fn get<'a>(resources: &'a Resources, res: &Res<Self>) -> Ref<'a, Self> {
+        let store = resources.isosurfaces.try_read().expect("Isosurfaces are already borrowed mutably");
+        Ref(parking_lot::RwLockReadGuard::map(store, |store| store.pool.get(res)))
+    }
+}
+
+impl GetMut for Isosurface {
+    fn get_mut<'a>(resources: &'a Resources, res: &Res<Self>) -> RefMut<'a, Self> {
+        let store = resources.isosurfaces.try_write().expect("Isosurfaces are already borrowed");
+        RefMut(parking_lot::RwLockWriteGuard::map(store, |store| store.pool.get_mut(res)))
+    }
+}
+
+/// Isosurfaces with the compute pipeline extracting them and the pipeline
+/// drawing them
+pub struct IsosurfaceStore {
+    pub(crate) pool: Pool<Isosurface>,
+    /// Buffers of dropped surfaces
+    retired: Retired<IsosurfaceBuffers>,
+    frame_count: usize,
+    /// Triangle table of every cell case, shared by all surfaces
+    table: GpuBuffer,
+    set_layout: DescriptorSetLayout,
+    descriptor_pool: DescriptorPool,
+    extract_layout: PipelineLayout,
+    extract: ComputePipeline,
+    layout: PipelineLayout,
+    pipeline: GraphicsPipeline,
+}
+
+impl IsosurfaceStore {
+    pub fn new(device: &Device, frame_count: usize, render_pass: vk::RenderPass, set_layouts: Vec<vk::DescriptorSetLayout>) -> VulkanResult<Self> {
+        let entries: Vec<i32> = triangle_table().iter().flatten().map(|&edge| i32::from(edge)).collect();
+        let mut table = GpuBufferBuilder::cpu_only(device)
+            .size(size_of_val(entries.as_slice()) as u64)
+            .usage(vk::BufferUsageFlags::STORAGE_BUFFER)
+            .build()?;
+        table.upload_data(&entries)?;
+
+        let bindings = [TABLE_BINDING, VERTICES_BINDING, INDICES_BINDING, COUNTERS_BINDING]
+            .into_iter()
+            .map(|binding| {
+                vk::DescriptorSetLayoutBinding::default()
+                    .binding(binding)
+                    .descriptor_type(vk::DescriptorType::STORAGE_BUFFER)
+                    .descriptor_count(1)
+                    .stage_flags(vk::ShaderStageFlags::COMPUTE)
+            })
+            .collect::<Vec<_>>();
+
+        let set_layout = DescriptorSetLayoutBuilder::new(device).bindings(bindings.clone()).build()?;
+
+        let pool_sizes = [vk::DescriptorPoolSize::default()
+            .ty(vk::DescriptorType::STORAGE_BUFFER)
+            .descriptor_count(bindings.len() as u32 * MAX_ISOSURFACES)];
+
+        let descriptor_pool = DescriptorPoolBuilder::new(device)
+            .flags(vk::DescriptorPoolCreateFlags::FREE_DESCRIPTOR_SET)
+            .pool_sizes(&pool_sizes)
+            .max_sets(MAX_ISOSURFACES)
+            .build()?;
+
+        // Set 0 holds the 3D textures and set 2 the buffers of one surface
+        let extract_layout = PipelineLayoutBuilder::new(device)
+            .set_layouts(set_layouts.iter().copied().chain([set_layout.raw]).collect())
+            .push_constant(vec![vk::PushConstantRange::default()
+                .offset(0)
+                .size(size_of::<ExtractPush>() as u32)
+                .stage_flags(vk::ShaderStageFlags::COMPUTE)])
+            .build()?;
+
+        let spv = read_shader_from_bytes(EXTRACT_SHADER).expect("Invalid marching cubes shader");
+        let shader = ShaderBuilder::new(device).bytecode(&spv).build()?;
+
+        let extract = ComputePipelineBuilder::new(device)
+            .shader(shader.raw)
+            .pipeline_layout(extract_layout.raw)
+            .build()?;
+
+        // The layout of every raster pipeline, set 1 holds the camera and
+        // transforms
+        let layout = PipelineLayoutBuilder::new(device)
+            .set_layouts(set_layouts)
+            .push_constant(vec![vk::PushConstantRange::default()
+                .offset(0)
+                .size(128)
+                .stage_flags(vk::ShaderStageFlags::VERTEX | vk::ShaderStageFlags::FRAGMENT)])
+            .build()?;
+
+        let spv = read_shader_from_bytes(VERTEX_SHADER).expect("Invalid isosurface vertex shader");
+        let vertex = ShaderBuilder::new(device).bytecode(&spv).build()?;
+        let spv = read_shader_from_bytes(FRAGMENT_SHADER).expect("Invalid isosurface fragment shader");
+        let fragment = ShaderBuilder::new(device).bytecode(&spv).build()?;
+
+        let bindings = [vk::VertexInputBindingDescription {
+            binding: 0,
+            stride: size_of::<IsoVertex>() as u32,
+            input_rate: vk::VertexInputRate::VERTEX,
+        }];
+        let attributes = [
+            vk::VertexInputAttributeDescription {
+                location: 0,
+                binding: 0,
+                format: vk::Format::R32G32B32_SFLOAT,
+                offset: 0,
+            },
+            vk::VertexInputAttributeDescription {
+                location: 1,
+                binding: 0,
+                format: vk::Format::R32G32B32_SFLOAT,
+                offset: 12,
+            },
+        ];
+
+        let vertex_input_info = vk::PipelineVertexInputStateCreateInfo::default()
+            .vertex_binding_descriptions(&bindings)
+            .vertex_attribute_descriptions(&attributes);
+
+        let color_blend = vk::PipelineColorBlendAttachmentState::default()
+            .color_write_mask(vk::ColorComponentFlags::RGBA)
+            .blend_enable(false);
+
+        // Viewport and scissors are dynamic, set for every draw. Both sides
+        // are drawn, the surface is open where it meets the box
+        let pipeline = GraphicsPipelineBuilder::new(device)
+            .vertex_shader(vertex.raw)
+            .fragment_shader(fragment.raw)
+            .render_pass(render_pass)
+            .pipeline_layout(layout.raw)
+            .viewport(vec![vk::Viewport::default().width(1.0).height(1.0).max_depth(1.0)])
+            .scissors(vec![vk::Rect2D::default()])
+            .input_assembly(vk::PipelineInputAssemblyStateCreateInfo::default().topology(vk::PrimitiveTopology::TRIANGLE_LIST))
+            .rasterization(
+                vk::PipelineRasterizationStateCreateInfo::default()
+                    .polygon_mode(vk::PolygonMode::FILL)
+                    .line_width(1.0)
+                    .cull_mode(vk::CullModeFlags::NONE),
+            )
+            .multisampling(vk::PipelineMultisampleStateCreateInfo::default().rasterization_samples(vk::SampleCountFlags::TYPE_1))
+            .color_blending(vk::PipelineColorBlendStateCreateInfo::default().attachments(&[color_blend]))
+            .depth_stencil(
+                vk::PipelineDepthStencilStateCreateInfo::default()
+                    .depth_test_enable(true)
+                    .depth_write_enable(true)
+                    .depth_compare_op(vk::CompareOp::GREATER_OR_EQUAL),
+            )
+            .dynamic_state(vec![vk::DynamicState::VIEWPORT, vk::DynamicState::SCISSOR])
+            .vertex_input_info(vertex_input_info)
+            .build()?;
+
+        Ok(Self {
+            pool: Pool::new(),
+            retired: Retired::default(),
+            frame_count,
+            table,
+            set_layout,
+            descriptor_pool,
+            extract_layout,
+            extract,
+            layout,
+            pipeline,
+        })
+    }
+
+    /// Output buffers of a new surface and the set pointing at them
+    fn create_buffers(&self, device: &Device, max_triangles: u32) -> VulkanResult<IsosurfaceBuffers> {
+        let max_indices = max_triangles as u64 * 3;
+        // Read back by Isosurface::read_back
+        let buffer = |size: u64, usage: vk::BufferUsageFlags| {
+            GpuBufferBuilder::gpu_to_cpu(device)
+                .size(size)
+                .usage(vk::BufferUsageFlags::STORAGE_BUFFER | usage)
+                .build()
+        };
+
+        let vertices = buffer(max_indices / 3 * 2 * size_of::<IsoVertex>() as u64, vk::BufferUsageFlags::VERTEX_BUFFER)?;
+        let indices = buffer(max_indices * size_of::<u32>() as u64, vk::BufferUsageFlags::INDEX_BUFFER)?;
+        let mut counters = buffer(
+            size_of::<Counters>() as u64,
+            vk::BufferUsageFlags::INDIRECT_BUFFER | vk::BufferUsageFlags::TRANSFER_DST,
+        )?;
+        // Nothing is drawn before the first extraction
+        counters.upload_data(&[Counters {
+            instance_count: 1,
+            ..Counters::zeroed()
+        }])?;
+
+        let set = self.descriptor_pool.create_descriptor_set(device, &[self.set_layout.raw])?[0];
+
+        let buffer_infos = [&self.table, &vertices, &indices, &counters].map(|buffer| {
+            vk::DescriptorBufferInfo::default()
+                .buffer(buffer.raw)
+                .offset(0)
+                .range(vk::WHOLE_SIZE)
+        });
+        let writes = [TABLE_BINDING, VERTICES_BINDING, INDICES_BINDING, COUNTERS_BINDING].map(|binding| {
+            vk::WriteDescriptorSet::default()
+                .dst_set(set)
+                .dst_binding(binding)
+                .descriptor_type(vk::DescriptorType::STORAGE_BUFFER)
+                .buffer_info(std::slice::from_ref(&buffer_infos[binding as usize]))
+        });
+        unsafe { device.update_descriptor_sets(&writes, &[]) };
+
+        Ok(IsosurfaceBuffers {
+            vertices,
+            indices,
+            counters,
+            set,
+        })
+    }
+
+    /// Release buffers no frame in flight can use anymore, called once per
+    /// frame
+    pub fn update(&mut self, device: &Device) {
+        profiling::scope!("IsosurfaceStore::update");
+
+        self.retired.update_with(|mut buffers| buffers.destroy(device, &self.descriptor_pool));
+    }
+
+    /// Record the extraction of every surface whose iso value changed, must
+    /// be outside a render pass and after the uploads of new textures
+    ///
+    /// `sets` are the bindless and per-frame sets of the frame
+    pub(crate) unsafe fn record_extract(&mut self, device: &ash::Device, cbuf: vk::CommandBuffer, sets: &[vk::DescriptorSet]) {
+        profiling::scope!("IsosurfaceStore::record_extract");
+
+        let pending: Vec<&mut Isosurface> = self
+            .pool
+            .slots
+            .values_mut()
+            .filter(|surface| surface.extracted != Some(surface.iso))
+            .collect();
+        if pending.is_empty() {
+            return;
+        }
+
+        // Draws of earlier frames may still read the buffers
+        device.cmd_pipeline_barrier(
+            cbuf,
+            vk::PipelineStageFlags::VERTEX_INPUT | vk::PipelineStageFlags::DRAW_INDIRECT,
+            vk::PipelineStageFlags::TRANSFER | vk::PipelineStageFlags::COMPUTE_SHADER,
+            vk::DependencyFlags::empty(),
+            &[],
+            &[],
+            &[],
+        );
+
+        let index_count = std::mem::offset_of!(Counters, index_count) as u64;
+        let vertex_count = std::mem::offset_of!(Counters, vertex_count) as u64;
+        for surface in &pending {
+            let counters = surface.buffers.counters.raw;
+            device.cmd_fill_buffer(cbuf, counters, index_count, size_of::<u32>() as u64, 0);
+            device.cmd_fill_buffer(cbuf, counters, vertex_count, 2 * size_of::<u32>() as u64, 0);
+        }
+
+        let cleared = vk::MemoryBarrier::default()
+            .src_access_mask(vk::AccessFlags::TRANSFER_WRITE)
+            .dst_access_mask(vk::AccessFlags::SHADER_READ | vk::AccessFlags::SHADER_WRITE);
+
+        device.cmd_pipeline_barrier(
+            cbuf,
+            vk::PipelineStageFlags::TRANSFER,
+            vk::PipelineStageFlags::COMPUTE_SHADER,
+            vk::DependencyFlags::empty(),
+            &[cleared],
+            &[],
+            &[],
+        );
+
+        device.cmd_bind_pipeline(cbuf, vk::PipelineBindPoint::COMPUTE, self.extract.raw);
+        device.cmd_bind_descriptor_sets(cbuf, vk::PipelineBindPoint::COMPUTE, self.extract_layout.raw, 0, sets, &[]);
+
+        for surface in pending {
+            let push = surface.extract_push();
+            let groups = surface.size.map(|n| n.saturating_sub(1).div_ceil(EXTRACT_GROUP_SIZE));
+
+            device.cmd_bind_descriptor_sets(
+                cbuf,
+                vk::PipelineBindPoint::COMPUTE,
+                self.extract_layout.raw,
+                sets.len() as u32,
+                &[surface.buffers.set],
+                &[],
+            );
+            device.cmd_push_constants(cbuf, self.extract_layout.raw, vk::ShaderStageFlags::COMPUTE, 0, bytemuck::bytes_of(&push));
+            device.cmd_dispatch(cbuf, groups[0], groups[1], groups[2]);
+
+            surface.extracted = Some(surface.iso);
+        }
+
+        let extracted = vk::MemoryBarrier::default()
+            .src_access_mask(vk::AccessFlags::SHADER_WRITE)
+            .dst_access_mask(
+                vk::AccessFlags::INDIRECT_COMMAND_READ
+                    | vk::AccessFlags::INDEX_READ
+                    | vk::AccessFlags::VERTEX_ATTRIBUTE_READ
+                    | vk::AccessFlags::HOST_READ,
+            );
+
+        device.cmd_pipeline_barrier(
+            cbuf,
+            vk::PipelineStageFlags::COMPUTE_SHADER,
+            vk::PipelineStageFlags::DRAW_INDIRECT | vk::PipelineStageFlags::VERTEX_INPUT | vk::PipelineStageFlags::HOST,
+            vk::DependencyFlags::empty(),
+            &[extracted],
+            &[],
+            &[],
+        );
+    }
+
+    /// Draw the triangles of `surface` over the whole target, must be inside
+    /// the render pass
+    ///
+    /// `sets` are the bindless and per-frame sets of the frame, `transform`
+    /// is the index of the world matrix or `None` for identity
+    pub(crate) unsafe fn draw(
+        &self,
+        device: &ash::Device,
+        cbuf: vk::CommandBuffer,
+        resolution: vk::Extent2D,
+        sets: &[vk::DescriptorSet],
+        surface: &Isosurface,
+        transform: Option<u32>,
+    ) {
+        let viewport = vk::Viewport::default()
+            .width(resolution.width as f32)
+            .height(resolution.height as f32)
+            .max_depth(1.0);
+        let scissor = vk::Rect2D::default().extent(resolution);
+
+        device.cmd_bind_pipeline(cbuf, vk::PipelineBindPoint::GRAPHICS, self.pipeline.raw);
+        device.cmd_bind_descriptor_sets(cbuf, vk::PipelineBindPoint::GRAPHICS, self.layout.raw, 0, sets, &[]);
+        device.cmd_bind_vertex_buffers(cbuf, 0, &[surface.buffers.vertices.raw], &[0]);
+        device.cmd_bind_index_buffer(cbuf, surface.buffers.indices.raw, 0, vk::IndexType::UINT32);
+        device.cmd_set_viewport(cbuf, 0, &[viewport]);
+        device.cmd_set_scissor(cbuf, 0, &[scissor]);
+        device.cmd_push_constants(
+            cbuf,
+            self.layout.raw,
+            vk::ShaderStageFlags::VERTEX | vk::ShaderStageFlags::FRAGMENT,
+            0,
+            bytemuck::bytes_of(&surface.push_constants(transform)),
+        );
+        device.cmd_draw_indexed_indirect(cbuf, surface.buffers.counters.raw, 0, 1, size_of::<vk::DrawIndexedIndirectCommand>() as u32);
+    }
+
+    pub fn destroy(&mut self, device: &Device) {
+        self.retired.destroy_with(|mut buffers| buffers.destroy(device, &self.descriptor_pool));
+        // Textures of the surfaces are retired to their own store
+        for (_, mut surface) in self.pool.slots.drain() {
+            surface.buffers.destroy(device, &self.descriptor_pool);
+        }
+        self.table.destroy(device);
+        self.descriptor_pool.destroy(device);
+        self.extract.destroy(device);
+        self.pipeline.destroy(device);
+        unsafe {
+            device.destroy_descriptor_set_layout(self.set_layout.raw, None);
+            device.destroy_pipeline_layout(self.extract_layout.raw, None);
+            device.destroy_pipeline_layout(self.layout.raw, None);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn buffers_match_the_shader_layouts() {
+        // The draw of vkCmdDrawIndexedIndirect comes first
+        assert_eq!(std::mem::offset_of!(Counters, vertex_count), size_of::<vk::DrawIndexedIndirectCommand>());
+        // 6 floats of shaders/marching_cubes.comp
+        assert_eq!(size_of::<IsoVertex>(), 24);
+        assert_eq!(size_of::<ExtractPush>(), 48);
+        // user_data[0..4] of shaders/isosurface.vert after 36 bytes of indices
+        assert_eq!(size_of::<Push>(), 36 + 4 * 4);
+    }
+}
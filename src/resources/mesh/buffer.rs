@@ -0,0 +1,274 @@
+use std::ops::Range;
+
+use ash::vk;
+
+use crate::core::{Device, GpuBuffer, GpuBufferBuilder};
+use crate::VulkanResult;
+
+/// Vertices or indices of a [`Mesh`](super::Mesh)
+pub(crate) enum MeshBuffer {
+    /// Written once at creation and read by every frame
+    Static {
+        buffer: GpuBuffer,
+        count: u32,
+        usage: vk::BufferUsageFlags,
+    },
+    /// Changed after creation, see [`DynamicBuffer`]
+    Dynamic(DynamicBuffer),
+}
+
+/// Data kept on the CPU and copied into a buffer per frame in flight
+///
+/// Writes only touch the CPU copy, the changed bytes reach the buffer of a
+/// frame once its fence is signaled, so the GPU never reads memory that is
+/// being written
+pub(crate) struct DynamicBuffer {
+    data: Vec<u8>,
+    stride: usize,
+    usage: vk::BufferUsageFlags,
+    frames: Vec<FrameCopy>,
+}
+
+struct FrameCopy {
+    buffer: Option<GpuBuffer>,
+    /// Bytes the buffer is missing
+    pending: Option<Range<usize>>,
+}
+
+impl DynamicBuffer {
+    /// Bytes kept on the CPU
+    #[cfg(test)]
+    pub fn data(&self) -> &[u8] {
+        &self.data
+    }
+
+    /// Bytes the buffer of `frame` is missing, cleared as they are copied
+    fn take_pending(&mut self, frame: usize) -> Option<Range<usize>> {
+        let pending = self.frames[frame].pending.take()?;
+        (!self.data.is_empty()).then_some(pending)
+    }
+}
+
+impl MeshBuffer {
+    pub fn new_static(device: &Device, usage: vk::BufferUsageFlags, data: &[u8], stride: usize) -> VulkanResult<Self> {
+        let mut buffer = GpuBufferBuilder::cpu_only(device)
+            .size(data.len() as u64)
+            .usage(usage)
+            .build()?;
+
+        buffer.upload_data(data)?;
+
+        Ok(Self::Static {
+            buffer,
+            count: (data.len() / stride) as u32,
+            usage,
+        })
+    }
+
+    pub fn new_dynamic(usage: vk::BufferUsageFlags, stride: usize, frame_count: usize) -> Self {
+        Self::Dynamic(DynamicBuffer {
+            data: vec![],
+            stride,
+            usage,
+            frames: (0..frame_count).map(|_| FrameCopy { buffer: None, pending: None }).collect(),
+        })
+    }
+
+    /// Number of elements
+    pub fn count(&self) -> u32 {
+        match self {
+            Self::Static { count, .. } => *count,
+            Self::Dynamic(dynamic) => (dynamic.data.len() / dynamic.stride) as u32,
+        }
+    }
+
+    /// Buffer to bind while recording `frame`, `None` when empty
+    pub fn raw(&self, frame: usize) -> Option<vk::Buffer> {
+        match self {
+            Self::Static { buffer, .. } => Some(buffer.raw),
+            Self::Dynamic(dynamic) => dynamic.frames[frame % dynamic.frames.len()]
+                .buffer
+                .as_ref()
+                .map(|buffer| buffer.raw),
+        }
+    }
+
+    /// Replace elements from `start` with `data`, growing past the end
+    ///
+    /// A static buffer turns dynamic and is pushed to `retired`, frames in
+    /// flight may still read it
+    pub fn write(&mut self, start: usize, data: &[u8], stride: usize, frame_count: usize, retired: &mut Vec<GpuBuffer>) {
+        if let Self::Static { buffer, count, usage } = self {
+            let mut dynamic = Self::new_dynamic(*usage, stride, frame_count);
+            if let Self::Dynamic(dynamic) = &mut dynamic {
+                dynamic.data = buffer.download_data(*count as usize * stride);
+                for frame in &mut dynamic.frames {
+                    frame.pending = Some(0..dynamic.data.len());
+                }
+            }
+
+            if let Self::Static { buffer, .. } = std::mem::replace(self, dynamic) {
+                retired.push(buffer);
+            }
+        }
+
+        let Self::Dynamic(dynamic) = self else {
+            unreachable!()
+        };
+
+        let start = start * dynamic.stride;
+        let end = start + data.len();
+
+        assert!(start <= dynamic.data.len(), "Mesh update leaves a gap after the last element");
+
+        if end > dynamic.data.len() {
+            dynamic.data.resize(end, 0);
+        }
+        dynamic.data[start..end].copy_from_slice(data);
+
+        for frame in &mut dynamic.frames {
+            frame.pending = Some(match frame.pending.take() {
+                Some(pending) => pending.start.min(start)..pending.end.max(end),
+                None => start..end,
+            });
+        }
+    }
+
+    /// Replace every element of a dynamic buffer with `data`
+    pub fn replace(&mut self, data: &[u8]) {
+        let Self::Dynamic(dynamic) = self else {
+            unreachable!("Only dynamic buffers are replaced")
+        };
+
+        dynamic.data.clear();
+        dynamic.data.extend_from_slice(data);
+
+        for frame in &mut dynamic.frames {
+            frame.pending = Some(0..data.len());
+        }
+    }
+
+    /// Copy pending bytes into the buffer of `frame`, the frame must not be
+    /// in flight
+    ///
+    /// Buffers that are too small are recreated twice as large
+    pub fn prepare(&mut self, device: &Device, frame: usize) -> VulkanResult<()> {
+        let Self::Dynamic(dynamic) = self else {
+            return Ok(());
+        };
+
+        let frame = frame % dynamic.frames.len();
+        let Some(pending) = dynamic.take_pending(frame) else {
+            return Ok(());
+        };
+
+        let copy = &mut dynamic.frames[frame];
+        let size = dynamic.data.len();
+        let fits = copy
+            .buffer
+            .as_ref()
+            .and_then(|buffer| buffer.allocation.as_ref())
+            .is_some_and(|allocation| allocation.size() >= size as u64);
+
+        let pending = if fits {
+            pending
+        } else {
+            // Only this frame reads its buffer
+            if let Some(mut old) = copy.buffer.take() {
+                old.destroy(device);
+            }
+            copy.buffer = Some(
+                GpuBufferBuilder::cpu_only(device)
+                    .size((size as u64).next_power_of_two())
+                    .usage(dynamic.usage)
+                    .build()?,
+            );
+            0..size
+        };
+
+        copy.buffer
+            .as_mut()
+            .unwrap()
+            .upload_data_at(pending.start, &dynamic.data[pending])
+    }
+
+    pub fn into_buffers(self) -> Vec<GpuBuffer> {
+        match self {
+            Self::Static { buffer, .. } => vec![buffer],
+            Self::Dynamic(dynamic) => dynamic.frames.into_iter().filter_map(|frame| frame.buffer).collect(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn dynamic(frame_count: usize) -> MeshBuffer {
+        MeshBuffer::new_dynamic(vk::BufferUsageFlags::VERTEX_BUFFER, 2, frame_count)
+    }
+
+    fn write(buffer: &mut MeshBuffer, start: usize, data: &[u8]) {
+        buffer.write(start, data, 2, 2, &mut vec![]);
+    }
+
+    fn inner(buffer: &mut MeshBuffer) -> &mut DynamicBuffer {
+        let MeshBuffer::Dynamic(dynamic) = buffer else {
+            unreachable!()
+        };
+        dynamic
+    }
+
+    #[test]
+    fn writes_grow_and_overwrite() {
+        let mut buffer = dynamic(2);
+        write(&mut buffer, 0, &[1, 2, 3, 4]);
+        write(&mut buffer, 1, &[5, 6, 7, 8]);
+
+        assert_eq!(buffer.count(), 3);
+        assert_eq!(inner(&mut buffer).data(), [1, 2, 5, 6, 7, 8]);
+    }
+
+    #[test]
+    fn pending_ranges_merge_per_frame() {
+        let mut buffer = dynamic(2);
+        write(&mut buffer, 0, &[0; 8]);
+        let dynamic = inner(&mut buffer);
+
+        // Frame 0 copies everything, frame 1 is not prepared yet
+        assert_eq!(dynamic.take_pending(0), Some(0..8));
+        assert_eq!(dynamic.take_pending(0), None);
+
+        write(&mut buffer, 3, &[1, 1]);
+        write(&mut buffer, 1, &[2, 2]);
+        let dynamic = inner(&mut buffer);
+
+        assert_eq!(dynamic.take_pending(0), Some(2..8));
+        assert_eq!(dynamic.take_pending(1), Some(0..8));
+        assert_eq!(dynamic.take_pending(1), None);
+    }
+
+    #[test]
+    fn replace_resends_everything() {
+        let mut buffer = dynamic(1);
+        write(&mut buffer, 0, &[0; 8]);
+        inner(&mut buffer).take_pending(0);
+
+        buffer.replace(&[1, 2]);
+        let dynamic = inner(&mut buffer);
+        assert_eq!(dynamic.data(), [1, 2]);
+        assert_eq!(dynamic.take_pending(0), Some(0..2));
+
+        // Nothing is copied into a buffer of an empty mesh
+        buffer.replace(&[]);
+        assert_eq!(inner(&mut buffer).take_pending(0), None);
+    }
+
+    #[test]
+    #[should_panic(expected = "gap")]
+    fn writes_past_the_end_panic() {
+        let mut buffer = dynamic(1);
+        write(&mut buffer, 0, &[0; 4]);
+        write(&mut buffer, 3, &[0; 2]);
+    }
+}
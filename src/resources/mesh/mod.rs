@@ -1,13 +1,149 @@
+use std::borrow::Cow;
+use std::ops::Range;
 use std::sync::{Arc, Weak};
 
 use ash::vk;
 use bytemuck::{Pod, Zeroable};
 
-use crate::core::{Device, GpuBuffer, GpuBufferBuilder};
+use crate::core::{Device, GpuBuffer};
+use crate::math::{self, Aabb, Frustum, Mat4, Sphere, Vec3};
 use crate::render_context::RenderContext;
-use crate::resources::{Create, Destroy, Pool, ResourceKey, Resources};
+use crate::resources::{Create, Destroy, Get, Pool, Ref, Res, ResourceKey, Resources, Retired};
 use crate::VulkanResult;
 
+mod buffer;
+pub(crate) use buffer::MeshBuffer;
+
+/// Integer types usable as mesh indices
+///
+/// `u8` indices are widened to `u16` on devices without
+/// `VK_KHR_index_type_uint8`, see [`Device::index_type_uint8`]
+pub trait Index: Pod + Zeroable + Into<u32> {
+    /// Vulkan type of the index
+    const TYPE: vk::IndexType;
+}
+
+impl Index for u8 {
+    const TYPE: vk::IndexType = vk::IndexType::UINT8_KHR;
+}
+
+impl Index for u16 {
+    const TYPE: vk::IndexType = vk::IndexType::UINT16;
+}
+
+impl Index for u32 {
+    const TYPE: vk::IndexType = vk::IndexType::UINT32;
+}
+
+/// Size of one index in bytes
+fn index_size(ty: vk::IndexType) -> usize {
+    match ty {
+        vk::IndexType::UINT8_KHR => 1,
+        vk::IndexType::UINT16 => 2,
+        _ => 4,
+    }
+}
+
+/// Type indices of `ty` are stored as, `u8` needs device support
+fn stored_index_type(ty: vk::IndexType, uint8: bool) -> vk::IndexType {
+    if ty == vk::IndexType::UINT8_KHR && !uint8 {
+        vk::IndexType::UINT16
+    } else {
+        ty
+    }
+}
+
+/// Bytes of `indices` as `ty`
+///
+/// # Panics
+/// - if an index does not fit in `ty`
+fn index_bytes<I: Index>(indices: &[I], ty: vk::IndexType) -> Cow<'_, [u8]> {
+    if I::TYPE == ty {
+        return Cow::Borrowed(bytemuck::cast_slice(indices));
+    }
+
+    let wide = indices.iter().map(|&index| index.into());
+    Cow::Owned(match ty {
+        vk::IndexType::UINT8_KHR => wide
+            .map(|index: u32| u8::try_from(index).expect("Index does not fit in u8"))
+            .collect(),
+        vk::IndexType::UINT16 => wide
+            .flat_map(|index: u32| u16::try_from(index).expect("Index does not fit in u16").to_ne_bytes())
+            .collect(),
+        _ => wide.flat_map(u32::to_ne_bytes).collect(),
+    })
+}
+
+/// Part of a [`Mesh`] drawn on its own, usually with its own material
+///
+/// # Example
+/// ```ignore
+/// // Two primitives sharing the vertex and index buffers
+/// let submeshes = [
+///     Submesh::new(0..body.len() as u32).material(0),
+///     Submesh::new(body.len() as u32..indices.len() as u32)
+///         .base_vertex(body_vertices)
+///         .material(1),
+/// ];
+/// let mesh = world.create::<Mesh>(MeshDesc::new(&vertices).with_indices(&indices).with_submeshes(&submeshes))?;
+/// ```
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Submesh {
+    /// First index, or first vertex of meshes without indices
+    pub first_index: u32,
+    /// Number of indices, or vertices of meshes without indices
+    pub index_count: u32,
+    /// Added to every index before reading the vertex
+    pub base_vertex: i32,
+    /// Material slot, left to the caller to bind
+    pub material: u32,
+}
+
+impl Submesh {
+    /// Submesh drawing indices `range`
+    pub fn new(range: Range<u32>) -> Self {
+        Self {
+            first_index: range.start,
+            index_count: range.len() as u32,
+            base_vertex: 0,
+            material: 0,
+        }
+    }
+
+    /// Added to every index before reading the vertex
+    pub fn base_vertex(mut self, base_vertex: i32) -> Self {
+        self.base_vertex = base_vertex;
+        self
+    }
+
+    /// Material slot
+    pub fn material(mut self, material: u32) -> Self {
+        self.material = material;
+        self
+    }
+}
+
+/// Level of detail of a [`Mesh`], a range of its index buffer drawing the
+/// same vertices with fewer triangles, see [`Geometry::lod_chain`]
+///
+/// [`Geometry::lod_chain`]: crate::Geometry::lod_chain
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct MeshLod {
+    /// First index
+    pub first_index: u32,
+    /// Number of indices
+    pub index_count: u32,
+    /// Largest distance to the full mesh in mesh units
+    pub error: f32,
+}
+
+/// Vertices with optional indices
+///
+/// Meshes are written once at creation. The first
+/// [`WorldRenderer::update_mesh`] keeps a copy on the CPU and gives every
+/// frame in flight its own buffers, see [`MeshStore::update`]
+///
+/// [`WorldRenderer::update_mesh`]: crate::WorldRenderer::update_mesh
 pub struct Mesh {
     /// Instance offset
     pub instance_offset: u32,
@@ -15,84 +151,537 @@ pub struct Mesh {
     pub instance_count: u32,
     /// Vertex offser
     pub vertex_offset: u32,
-    /// Vertex Buffer
-    pub vertex_buffer: GpuBuffer,
-    /// Index Buffer
-    pub index_buffer: Option<GpuBuffer>,
+    /// Local bounding box, see [`MeshDesc::with_bounds`]
+    pub aabb: Option<Aabb>,
+    /// Local bounding sphere, see [`MeshDesc::with_bounds`]
+    pub sphere: Option<Sphere>,
+    /// Size of one vertex in bytes
+    stride: usize,
+    /// Offset and stride of the position in bytes
+    position: Option<(usize, usize)>,
+    vertices: MeshBuffer,
+    indices: Option<MeshBuffer>,
+    index_type: vk::IndexType,
+    /// Empty when the whole mesh is drawn at once
+    submeshes: Vec<Submesh>,
+    /// Finest first, empty without LODs
+    lods: Vec<MeshLod>,
+}
+
+impl Mesh {
+    /// Number of vertices
+    pub fn vertex_count(&self) -> u32 {
+        self.vertices.count()
+    }
+
+    /// Number of indices, `None` for non-indexed meshes
+    pub fn index_count(&self) -> Option<u32> {
+        self.indices.as_ref().map(MeshBuffer::count)
+    }
+
+    /// Type of the indices in the index buffer
+    pub(crate) fn index_type(&self) -> vk::IndexType {
+        self.index_type
+    }
+
+    /// Parts drawn by [`PassContext::draw_mesh`], empty when the mesh is one
+    /// draw
+    ///
+    /// [`PassContext::draw_mesh`]: crate::frame_graph::PassContext::draw_mesh
+    pub fn submeshes(&self) -> &[Submesh] {
+        &self.submeshes
+    }
+
+    /// Levels of detail from the finest, empty without LODs
+    pub fn lods(&self) -> &[MeshLod] {
+        &self.lods
+    }
+
+    /// Coarsest LOD whose error covers less than `threshold` pixels on a
+    /// target `height` pixels tall, placed by `world` and seen through
+    /// `view` and `proj`
+    ///
+    /// Meshes without LODs are drawn whole
+    pub(crate) fn select_lod(&self, world: &Mat4, view: &Mat4, proj: &Mat4, height: f32, threshold: f32) -> Submesh {
+        let Some(finest) = self.lods.first() else {
+            return self.whole();
+        };
+
+        let sphere = self
+            .sphere
+            .unwrap_or(Sphere::new([0.0; 3], 0.0))
+            .transform(world);
+        // Errors grow with the largest scale of `world`
+        let scale = Sphere::new([0.0; 3], 1.0).transform(world).radius;
+
+        // `w` of the closest point of the sphere, the distance for
+        // perspective and 1 for orthographic projections
+        let center = math::transform_point(view, sphere.center);
+        let w = proj[2][3] * center[2] + proj[3][3];
+        let w = (w - sphere.radius * proj[2][3].abs()).max(f32::EPSILON);
+        let pixels_per_unit = proj[1][1].abs() * height * 0.5 / w;
+
+        let lod = self
+            .lods
+            .iter()
+            .rev()
+            .find(|lod| lod.error * scale * pixels_per_unit < threshold)
+            .unwrap_or(finest);
+
+        Submesh::new(lod.first_index..lod.first_index + lod.index_count)
+    }
+
+    /// Submesh covering every index, or every vertex without indices
+    pub(crate) fn whole(&self) -> Submesh {
+        match self.index_count() {
+            Some(count) => Submesh::new(0..count),
+            None => Submesh::new(0..self.vertex_count()).base_vertex(self.vertex_offset as i32),
+        }
+    }
+
+    pub(crate) fn vertex_buffer(&self, frame: usize) -> Option<vk::Buffer> {
+        self.vertices.raw(frame)
+    }
+
+    pub(crate) fn index_buffer(&self, frame: usize) -> Option<vk::Buffer> {
+        self.indices.as_ref().and_then(|indices| indices.raw(frame))
+    }
+
+    /// Replace vertices `range`, see [`WorldRenderer::update_mesh`]
+    ///
+    /// [`WorldRenderer::update_mesh`]: crate::WorldRenderer::update_mesh
+    fn write_vertices(&mut self, range: Range<u32>, data: &[u8], frame_count: usize, retired: &mut Vec<GpuBuffer>) {
+        assert_eq!(data.len(), range.len() * self.stride, "Vertex data does not match the range");
+
+        self.vertices
+            .write(range.start as usize, data, self.stride, frame_count, retired);
+
+        // Bounds only grow, they stay conservative when vertices move inward
+        if let Some((offset, stride)) = self.position {
+            if let Some(aabb) = Aabb::from_points(positions(data, offset, stride)) {
+                self.aabb = Some(self.aabb.map_or(aabb, |old| old.union(&aabb)));
+            }
+            if let Some(sphere) = Sphere::from_points(positions(data, offset, stride)) {
+                self.sphere = Some(self.sphere.map_or(sphere, |old| old.merge(&sphere)));
+            }
+        }
+    }
+
+    /// Replace indices `range`, see [`WorldRenderer::update_mesh_indices`]
+    ///
+    /// A mesh without indices takes the type of `data`, LODs are dropped
+    ///
+    /// [`WorldRenderer::update_mesh_indices`]: crate::WorldRenderer::update_mesh_indices
+    fn write_indices<I: Index>(&mut self, range: Range<u32>, data: &[I], uint8: bool, frame_count: usize, retired: &mut Vec<GpuBuffer>) {
+        assert_eq!(data.len(), range.len(), "Index data does not match the range");
+
+        if self.indices.is_none() {
+            self.index_type = stored_index_type(I::TYPE, uint8);
+        }
+
+        // LODs were built from the old indices, the whole buffer is drawn
+        self.lods.clear();
+
+        let stride = index_size(self.index_type);
+        let bytes = index_bytes(data, self.index_type);
+        self.indices
+            .get_or_insert_with(|| MeshBuffer::new_dynamic(vk::BufferUsageFlags::INDEX_BUFFER, stride, frame_count))
+            .write(range.start as usize, &bytes, stride, frame_count, retired);
+    }
+
+    fn into_buffers(self) -> impl Iterator<Item = GpuBuffer> {
+        self.vertices
+            .into_buffers()
+            .into_iter()
+            .chain(self.indices.into_iter().flat_map(MeshBuffer::into_buffers))
+    }
+
+    /// Bounds placed by `world` intersect `frustum`, meshes without bounds
+    /// are always visible
+    pub fn is_visible(&self, frustum: &Frustum, world: &Mat4) -> bool {
+        let sphere = self.sphere.is_none_or(|sphere| frustum.intersects_sphere(&sphere.transform(world)));
+        sphere && self.aabb.is_none_or(|aabb| frustum.intersects_aabb(&aabb.transform(world)))
+    }
 }
 
 pub struct MeshDesc<'a> {
     vertices: &'a [u8],
-    indices: Option<&'a [u32]>,
+    stride: usize,
+    /// Bytes of the indices with the device-independent type
+    indices: Option<(Cow<'a, [u8]>, vk::IndexType)>,
+    submeshes: &'a [Submesh],
+    lods: &'a [MeshLod],
+    /// Offset and stride of the position in bytes
+    position: Option<(usize, usize)>,
 }
 
 impl<'a> MeshDesc<'a> {
     pub fn new<T: Pod + Zeroable>(vertices: &'a [T]) -> MeshDesc<'a> {
         MeshDesc {
             vertices: bytemuck::cast_slice(vertices),
+            stride: size_of::<T>(),
             indices: None,
+            submeshes: &[],
+            lods: &[],
+            position: None,
         }
     }
 
-    pub fn with_indices(mut self, indices: &'a [u32]) -> MeshDesc<'a> {
-        self.indices = Some(indices);
+    /// Indices of `u8`, `u16` or `u32`
+    pub fn with_indices<I: Index>(mut self, indices: &'a [I]) -> MeshDesc<'a> {
+        self.indices = Some((Cow::Borrowed(bytemuck::cast_slice(indices)), I::TYPE));
+        self
+    }
+
+    /// Split the mesh into parts drawn one after another, see [`Submesh`]
+    ///
+    /// Creation panics if a submesh ends past the last index, or the last
+    /// vertex of meshes without indices
+    pub fn with_submeshes(mut self, submeshes: &'a [Submesh]) -> MeshDesc<'a> {
+        self.submeshes = submeshes;
+        self
+    }
+
+    /// Levels of detail in the index buffer, finest first, chosen by
+    /// [`PassContext::draw_mesh`] from the size of the mesh on screen
+    ///
+    /// Without bounds, see [`MeshDesc::with_bounds`], the mesh counts as a
+    /// point at its origin. LODs can not be combined with submeshes
+    ///
+    /// [`PassContext::draw_mesh`]: crate::frame_graph::PassContext::draw_mesh
+    pub fn with_lods(mut self, lods: &'a [MeshLod]) -> MeshDesc<'a> {
+        self.lods = lods;
+        self
+    }
+
+    /// Compute the bounding box and sphere from positions of three `f32`
+    /// found `offset` bytes into every vertex of `stride` bytes
+    ///
+    /// # Example
+    /// ```ignore
+    /// MeshDesc::new(&vertices).with_bounds(offset_of!(PbrVertex, pos), size_of::<PbrVertex>())
+    /// ```
+    pub fn with_bounds(mut self, offset: usize, stride: usize) -> MeshDesc<'a> {
+        assert!(offset + size_of::<Vec3>() <= stride, "Position does not fit in the vertex");
+        self.position = Some((offset, stride));
         self
     }
+
+    /// Number of indices, or vertices of meshes without indices
+    fn element_count(&self) -> usize {
+        match &self.indices {
+            Some((bytes, ty)) => bytes.len() / index_size(*ty),
+            None => self.vertices.len() / self.stride,
+        }
+    }
+
+    fn validate(&self) {
+        assert!(self.lods.is_empty() || self.submeshes.is_empty(), "Mesh with both LODs and submeshes");
+        assert!(self.lods.is_empty() || self.indices.is_some(), "Mesh LODs need indices");
+
+        let count = self.element_count();
+        let ends = |first: u32, len: u32| first as usize + len as usize <= count;
+        assert!(
+            self.submeshes.iter().all(|submesh| ends(submesh.first_index, submesh.index_count)),
+            "Submesh past the end of the mesh"
+        );
+        assert!(self.lods.iter().all(|lod| ends(lod.first_index, lod.index_count)), "Mesh LOD past the last index");
+    }
+}
+
+/// Positions of three `f32` at `offset` of every `stride` bytes
+fn positions(vertices: &[u8], offset: usize, stride: usize) -> impl Iterator<Item = Vec3> + Clone + '_ {
+    vertices.chunks_exact(stride).map(move |vertex| {
+        let bytes = &vertex[offset..offset + size_of::<Vec3>()];
+        [0, 1, 2].map(|axis| f32::from_ne_bytes(bytes[axis * 4..axis * 4 + 4].try_into().unwrap()))
+    })
 }
 
 impl Destroy for Mesh {
-    fn destroy(key: ResourceKey, _ctx: Weak<crate::render_context::RenderContext>, _resources: Weak<Resources>) {}
+    fn destroy(key: ResourceKey, _ctx: Weak<RenderContext>, resources: Weak<Resources>) {
+        let Some(resources) = resources.upgrade() else {
+            return;
+        };
+
+        let mut store = resources.meshes.write();
+        if let Some(mesh) = store.pool.remove(key) {
+            let frames = store.frame_count;
+            store.retired.extend(frames, mesh.into_buffers());
+        }
+    }
 }
 
 impl Create for Mesh {
     type Desc<'a> = MeshDesc<'a>;
-    fn create(ctx: &Arc<RenderContext>, resources: &Arc<super::Resources>, desc: Self::Desc<'_>) -> VulkanResult<super::Res<Self>> {
-        let size = std::mem::size_of_val(desc.vertices) as u64;
-
-        let mut vertex_buffer = GpuBufferBuilder::cpu_only(&ctx.device)
-            .size(size)
-            .usage(vk::BufferUsageFlags::VERTEX_BUFFER)
-            .build()?;
-
-        vertex_buffer.upload_data(desc.vertices)?;
+    fn create(ctx: &Arc<RenderContext>, resources: &Arc<Resources>, desc: Self::Desc<'_>) -> VulkanResult<Res<Self>> {
+        desc.validate();
 
-        let index_buffer = if let Some(indices) = desc.indices {
-            let mut index_buffer = GpuBufferBuilder::cpu_only(&ctx.device)
-                .size(size_of_val(indices) as u64)
-                .usage(vk::BufferUsageFlags::INDEX_BUFFER)
-                .build()?;
+        let vertices = MeshBuffer::new_static(&ctx.device, vk::BufferUsageFlags::VERTEX_BUFFER, desc.vertices, desc.stride)?;
 
-            index_buffer.upload_data(indices)?;
+        let mut index_type = vk::IndexType::UINT32;
+        let indices = match &desc.indices {
+            Some((bytes, ty)) => {
+                index_type = stored_index_type(*ty, ctx.device.index_type_uint8());
+                // Widen `u8` indices the device can not read
+                let bytes = match *ty == index_type {
+                    true => Cow::Borrowed(&bytes[..]),
+                    false => index_bytes::<u8>(bytes, index_type),
+                };
+                Some(MeshBuffer::new_static(
+                    &ctx.device,
+                    vk::BufferUsageFlags::INDEX_BUFFER,
+                    &bytes,
+                    index_size(index_type),
+                )?)
+            },
+            None => None,
+        };
 
-            Some(index_buffer)
-        } else {
-            None
+        let (aabb, sphere) = match desc.position {
+            Some((offset, stride)) => {
+                let positions = positions(desc.vertices, offset, stride);
+                (Aabb::from_points(positions.clone()), Sphere::from_points(positions))
+            },
+            None => (None, None),
         };
 
-        let key = resources.meshes.write().insert(
+        let handle = resources.meshes.write().pool.insert(
+            Arc::downgrade(ctx),
+            Arc::downgrade(resources),
             Mesh {
                 instance_offset: 0,
                 instance_count: 1,
                 vertex_offset: 0,
-                vertex_buffer,
-                index_buffer,
+                aabb,
+                sphere,
+                stride: desc.stride,
+                position: desc.position,
+                vertices,
+                indices,
+                index_type,
+                submeshes: desc.submeshes.to_vec(),
+                lods: desc.lods.to_vec(),
             },
         );
 
-        Ok(resources.make_handle(ctx, key))
+        Ok(handle)
+    }
+}
+
+impl Get for Mesh {
+    fn get<'a>(resources: &'a Resources, res: &Res<Self>) -> Ref<'a, Self> {
+        let store = resources.meshes.try_read().expect("Meshes are already borrowed mutably");
+        Ref(parking_lot::RwLockReadGuard::map(store, |store| store.pool.get(res)))
     }
 }
 
+/// Meshes and the buffers waiting for the GPU to finish with them
 pub struct MeshStore {
-    pub data: Pool<Mesh>,
+    pub(crate) pool: Pool<Mesh>,
+    retired: Retired<GpuBuffer>,
+    frame_count: usize,
+    /// Device reads `u8` indices
+    uint8: bool,
 }
 
 impl MeshStore {
-    pub fn new() -> Self {
-        Self { data: Pool::new() }
+    pub fn new(frame_count: usize, uint8: bool) -> Self {
+        Self {
+            pool: Pool::new(),
+            retired: Retired::default(),
+            frame_count,
+            uint8,
+        }
+    }
+
+    /// Replace vertices `range` of the mesh, the mesh turns dynamic
+    pub(crate) fn write_vertices(&mut self, mesh: &Res<Mesh>, range: Range<u32>, data: &[u8]) {
+        let mut retired = vec![];
+        self.pool
+            .get_mut(mesh)
+            .write_vertices(range, data, self.frame_count, &mut retired);
+        self.retire(retired);
+    }
+
+    /// Replace indices `range` of the mesh, the mesh turns dynamic
+    pub(crate) fn write_indices<I: Index>(&mut self, mesh: &Res<Mesh>, range: Range<u32>, data: &[I]) {
+        let mut retired = vec![];
+        self.pool
+            .get_mut(mesh)
+            .write_indices(range, data, self.uint8, self.frame_count, &mut retired);
+        self.retire(retired);
+    }
+
+    fn retire(&mut self, buffers: Vec<GpuBuffer>) {
+        let frames = self.frame_count;
+        self.retired.extend(frames, buffers);
+    }
+
+    /// Copy changes of dynamic meshes into the buffers of `frame` and release
+    /// buffers no frame in flight can use anymore, called once per frame
+    pub fn update(&mut self, device: &Device, frame: usize) -> VulkanResult<()> {
+        profiling::scope!("MeshStore::update");
+
+        self.retired.update(device);
+
+        for mesh in self.pool.slots.values_mut() {
+            mesh.vertices.prepare(device, frame)?;
+            if let Some(indices) = &mut mesh.indices {
+                indices.prepare(device, frame)?;
+            }
+        }
+
+        Ok(())
     }
 
     pub fn destroy(&mut self, device: &Device) {
+        self.retired.destroy(device);
+        for (_, mesh) in self.pool.slots.drain() {
+            for mut buffer in mesh.into_buffers() {
+                buffer.destroy(device);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::PbrVertex;
+
+    #[test]
+    fn positions_follow_offset_and_stride() {
+        let vertices = [[-1.0f32, 2.0, 0.5], [3.0, -4.0, 1.0]]
+            .map(|pos| PbrVertex {
+                pos: [pos[0], pos[1], pos[2], 1.0],
+                normal: [9.0; 4],
+                ..bytemuck::Zeroable::zeroed()
+            });
+        let bytes: &[u8] = bytemuck::cast_slice(&vertices);
+
+        let read = positions(bytes, std::mem::offset_of!(PbrVertex, pos), size_of::<PbrVertex>()).collect::<Vec<_>>();
+
+        assert_eq!(read, vec![[-1.0, 2.0, 0.5], [3.0, -4.0, 1.0]]);
+    }
+
+    /// Unit sphere with three LODs in 430 indices
+    fn lod_mesh() -> Mesh {
+        Mesh {
+            instance_offset: 0,
+            instance_count: 1,
+            vertex_offset: 0,
+            aabb: None,
+            sphere: Some(Sphere::new([0.0; 3], 1.0)),
+            stride: 12,
+            position: None,
+            vertices: MeshBuffer::new_dynamic(vk::BufferUsageFlags::VERTEX_BUFFER, 12, 1),
+            indices: Some(MeshBuffer::new_dynamic(vk::BufferUsageFlags::INDEX_BUFFER, 4, 1)),
+            index_type: vk::IndexType::UINT32,
+            submeshes: vec![],
+            lods: vec![
+                MeshLod { first_index: 0, index_count: 300, error: 0.0 },
+                MeshLod { first_index: 300, index_count: 100, error: 0.01 },
+                MeshLod { first_index: 400, index_count: 30, error: 0.1 },
+            ],
+        }
+    }
+
+    /// LOD seen from `distance` along `+Z` on a 1000 pixel target, where a
+    /// unit at distance `d` covers `500 / d` pixels
+    fn lod_at(mesh: &Mesh, world: &Mat4, proj: &Mat4, distance: f32) -> Submesh {
+        let view = math::look_at([0.0, 0.0, distance], [0.0; 3], [0.0, 1.0, 0.0]);
+        mesh.select_lod(world, &view, proj, 1000.0, 1.0)
+    }
+
+    #[test]
+    fn lods_switch_at_the_pixel_threshold() {
+        let mesh = lod_mesh();
+        let proj = math::perspective(90f32.to_radians(), 1.0, 0.1, f32::INFINITY);
+        let lod = |distance| lod_at(&mesh, &math::IDENTITY, &proj, distance);
+
+        // The closest point of the sphere is one unit nearer, LOD 1 has
+        // 5 / (d - 1) pixels of error and LOD 2 has 50 / (d - 1)
+        assert_eq!(lod(5.9), Submesh::new(0..300));
+        assert_eq!(lod(6.1), Submesh::new(300..400));
+        assert_eq!(lod(50.9), Submesh::new(300..400));
+        assert_eq!(lod(51.1), Submesh::new(400..430));
+
+        // Inside the sphere the finest LOD is used
+        assert_eq!(lod(0.5), Submesh::new(0..300));
+    }
+
+    #[test]
+    fn lod_errors_scale_with_the_transform() {
+        let mesh = lod_mesh();
+        let proj = math::perspective(90f32.to_radians(), 1.0, 0.1, f32::INFINITY);
+        let world = math::compose([0.0; 3], [0.0, 0.0, 0.0, 1.0], [2.0; 3]);
+
+        // Radius 2 and doubled errors, LOD 2 needs 100 / (d - 2) < 1
+        assert_eq!(lod_at(&mesh, &world, &proj, 101.9), Submesh::new(300..400));
+        assert_eq!(lod_at(&mesh, &world, &proj, 102.1), Submesh::new(400..430));
+    }
+
+    #[test]
+    fn orthographic_lods_ignore_distance() {
+        let mesh = lod_mesh();
+        // 20 units on 1000 pixels, 50 pixels per unit
+        let proj = math::orthographic(-10.0, 10.0, -10.0, 10.0, 0.1, 1000.0);
+
+        assert_eq!(lod_at(&mesh, &math::IDENTITY, &proj, 5.0), Submesh::new(300..400));
+        assert_eq!(lod_at(&mesh, &math::IDENTITY, &proj, 500.0), Submesh::new(300..400));
+    }
+
+    #[test]
+    fn index_writes_drop_lods() {
+        let mut mesh = lod_mesh();
+        mesh.write_indices(0..3, &[0u32, 1, 2], true, 1, &mut vec![]);
+
+        assert!(mesh.lods().is_empty());
+        assert_eq!(mesh.select_lod(&math::IDENTITY, &math::IDENTITY, &math::IDENTITY, 1000.0, 1.0), Submesh::new(0..3));
+    }
+
+    #[test]
+    fn u8_indices_widen_without_device_support() {
+        assert_eq!(stored_index_type(vk::IndexType::UINT8_KHR, false), vk::IndexType::UINT16);
+        assert_eq!(stored_index_type(vk::IndexType::UINT8_KHR, true), vk::IndexType::UINT8_KHR);
+        assert_eq!(stored_index_type(vk::IndexType::UINT16, false), vk::IndexType::UINT16);
+        assert_eq!(stored_index_type(vk::IndexType::UINT32, true), vk::IndexType::UINT32);
+    }
+
+    #[test]
+    fn index_bytes_convert_between_types() {
+        let indices = [1u16, 258];
+        assert!(matches!(index_bytes(&indices, vk::IndexType::UINT16), Cow::Borrowed(_)));
+
+        let wide = index_bytes(&indices, vk::IndexType::UINT32);
+        assert_eq!(&wide[..], bytemuck::cast_slice::<u32, u8>(&[1, 258]));
+
+        let narrow = index_bytes(&[3u8, 255], vk::IndexType::UINT16);
+        assert_eq!(&narrow[..], bytemuck::cast_slice::<u16, u8>(&[3, 255]));
+
+        assert_eq!(&index_bytes(&[7u32, 9], vk::IndexType::UINT8_KHR)[..], [7, 9]);
+    }
+
+    #[test]
+    #[should_panic(expected = "does not fit in u16")]
+    fn index_bytes_reject_overflow() {
+        index_bytes(&[70_000u32], vk::IndexType::UINT16);
+    }
+
+    #[test]
+    fn submeshes_are_checked_against_the_mesh() {
+        let vertices = [[0.0f32; 3]; 4];
+        let indices = [0u16, 1, 2, 2, 3, 0];
+        let parts = [Submesh::new(0..3), Submesh::new(3..6)];
+
+        MeshDesc::new(&vertices).with_indices(&indices).with_submeshes(&parts).validate();
+        MeshDesc::new(&vertices).with_submeshes(&[Submesh::new(0..4)]).validate();
+    }
 
+    #[test]
+    #[should_panic(expected = "Submesh past the end")]
+    fn submeshes_past_the_vertices_panic() {
+        let vertices = [[0.0f32; 3]; 4];
+        MeshDesc::new(&vertices).with_submeshes(&[Submesh::new(3..6)]).validate();
     }
 }
@@ -3,12 +3,16 @@ use std::sync::atomic::{AtomicUsize, Ordering};
 use std::sync::{Arc, Weak};
 
 use ash::vk;
-use parking_lot::RwLock;
-use slotmap::{SlotMap, new_key_type};
+use parking_lot::{Mutex, RwLock};
+use slotmap::new_key_type;
 
 use crate::bindless::Bindless;
 use crate::camera::Camera;
 use crate::core::Device;
+use crate::canvas::CanvasRenderer;
+use crate::debug_draw::DebugRenderer;
+use crate::frame_values::FrameValues;
+use crate::per_frame::{PerFrameDescriptors, CAMERA_BINDING, FRAME_BINDING, TRANSFORMS_BINDING};
 use crate::render_context::RenderContext;
 use crate::VulkanResult;
 
@@ -25,7 +29,44 @@ mod pool;
 pub use pool::{LinearPool, Pool};
 
 mod mesh;
-pub use mesh::{Mesh, MeshDesc, MeshStore};
+pub use mesh::{Index, Mesh, MeshDesc, MeshLod, MeshStore, Submesh};
+pub(crate) use mesh::MeshBuffer;
+
+mod instance;
+pub use instance::{InstanceBuffer, InstanceBufferDesc, InstanceStore};
+
+mod scene;
+pub use scene::{GpuScene, SceneMesh, SceneMeshDesc, SceneObject, SceneObjectDesc};
+
+mod polyline;
+pub use polyline::{LineCap, LineJoin, Polyline, PolylineDesc, PolylineStore};
+
+mod point_cloud;
+pub use point_cloud::{CloudPoint, PointCloud, PointCloudDesc, PointCloudStore, PointColor, PointShape};
+
+mod height_field;
+pub use height_field::{HeightField, HeightFieldDesc, HeightFieldStore};
+
+mod volume;
+pub use volume::{OpacityCurve, Volume, VolumeDesc, VolumeStore};
+
+mod isosurface;
+pub use isosurface::{marching_cubes, IsoVertex, Isosurface, IsosurfaceDesc, IsosurfaceMesh, IsosurfaceStore};
+
+mod upload;
+pub(crate) use upload::ImageUploads;
+
+mod retired;
+pub(crate) use retired::Retired;
+
+mod colormap;
+pub use colormap::{Colormap, ScalarRange};
+pub(crate) use colormap::ColormapStore;
+
+#[cfg(feature = "text")]
+mod text;
+#[cfg(feature = "text")]
+pub use text::{Font, FontDesc, Text, TextAlign, TextAnchor, TextDesc, TextStore, VerticalAlign};
 
 mod pipeline_cache;
 pub use pipeline_cache::*;
@@ -35,9 +76,9 @@ new_key_type! {
     pub struct ResourceKey;
 }
 
-pub struct Ref<'a, T>(pub(crate) parking_lot::RwLockReadGuard<'a, T>);
+pub struct Ref<'a, T>(pub(crate) parking_lot::MappedRwLockReadGuard<'a, T>);
 
-pub struct RefMut<'a, T>(pub(crate) parking_lot::RwLockWriteGuard<'a, T>);
+pub struct RefMut<'a, T>(pub(crate) parking_lot::MappedRwLockWriteGuard<'a, T>);
 
 impl<'a, T> std::ops::Deref for Ref<'a, T> {
     type Target = T;
@@ -113,63 +154,141 @@ pub trait Destroy: Sized {
 
 pub struct Resources {
     pub(crate) bindless: Bindless,
-    pub(crate) meshes: RwLock<SlotMap<ResourceKey, Mesh>>,
+    pub(crate) meshes: RwLock<MeshStore>,
     pub(crate) transforms: RwLock<TransformPool>,
+    /// Transforms dropped while `transforms` was borrowed
+    pub(crate) released_transforms: Mutex<Vec<ResourceKey>>,
+    pub(crate) instances: RwLock<InstanceStore>,
+    pub(crate) scene: RwLock<GpuScene>,
     pub(crate) pipeline_cache: RwLock<PipelineCache>,
     pub(crate) camera: RwLock<Camera>,
+    pub(crate) frame_values: RwLock<FrameValues>,
+    pub(crate) per_frame: PerFrameDescriptors,
+    pub(crate) debug: RwLock<DebugRenderer>,
+    pub(crate) canvas: RwLock<CanvasRenderer>,
+    pub(crate) polylines: RwLock<PolylineStore>,
+    pub(crate) point_clouds: RwLock<PointCloudStore>,
+    pub(crate) height_fields: RwLock<HeightFieldStore>,
+    pub(crate) textures: RwLock<TextureStore>,
+    pub(crate) volumes: RwLock<VolumeStore>,
+    pub(crate) isosurfaces: RwLock<IsosurfaceStore>,
+    /// Pixels of new textures, copied before the first pass of a frame
+    pub(crate) uploads: RwLock<ImageUploads>,
+    pub(crate) colormaps: RwLock<ColormapStore>,
+    #[cfg(feature = "text")]
+    pub(crate) text: RwLock<TextStore>,
+    #[cfg(feature = "egui")]
+    pub(crate) egui: RwLock<crate::ui::EguiRenderer>,
 }
 
 impl Resources {
     pub fn new(ctx: &Arc<RenderContext>) -> VulkanResult<Arc<Self>> {
         let frame_count = ctx.frame_count();
         let camera = Camera::new(&ctx.device, frame_count)?;
+        let frame_values = FrameValues::new(&ctx.device, frame_count)?;
         let pipeline_cache = PipelineCache::new();
-        let transforms = TransformPool::new(&ctx.device, ctx.frame_count())?;
+        let transforms = TransformPool::new(&ctx.device, frame_count)?;
         let bindless = Bindless::new(&ctx)?;
 
+        let per_frame = PerFrameDescriptors::new(&ctx.device, frame_count)?;
+        per_frame.write_buffer(&ctx.device, CAMERA_BINDING, vk::DescriptorType::UNIFORM_BUFFER, camera.buffer());
+        per_frame.write_buffer(&ctx.device, FRAME_BINDING, vk::DescriptorType::UNIFORM_BUFFER, &frame_values.buffer);
+        for (frame, buffer) in transforms.buffers.iter().enumerate() {
+            per_frame.write_frame_buffer(&ctx.device, frame, TRANSFORMS_BINDING, vk::DescriptorType::STORAGE_BUFFER, buffer);
+        }
+
+        // Every pipeline reads bindless set 0 and per-frame set 1
+        let set_layouts = vec![bindless.set_layout.raw, per_frame.set_layout.raw];
+        let render_pass = ctx.window.read().render_pass.raw;
+
+        let scene = GpuScene::new(&ctx.device, frame_count, set_layouts.clone())?;
+        scene.write_descriptors(&ctx.device, &per_frame);
+
+        let debug = DebugRenderer::new(&ctx.device, frame_count, render_pass, set_layouts.clone())?;
+        let polylines = PolylineStore::new(&ctx.device, frame_count, render_pass, set_layouts.clone())?;
+        let point_clouds = PointCloudStore::new(&ctx.device, frame_count, render_pass, set_layouts.clone())?;
+        let height_fields = HeightFieldStore::new(&ctx.device, frame_count, render_pass, set_layouts.clone())?;
+        let volumes = VolumeStore::new(&ctx.device, render_pass, set_layouts.clone())?;
+        let isosurfaces = IsosurfaceStore::new(&ctx.device, frame_count, render_pass, set_layouts.clone())?;
+
+        let mut uploads = ImageUploads::default();
+        let colormaps = ColormapStore::new(&ctx.device, &bindless, &mut uploads)?;
+
+        #[cfg(feature = "text")]
+        let text = TextStore::new(&ctx.device, frame_count, render_pass, set_layouts.clone())?;
+
+        let canvas = CanvasRenderer::new(&ctx.device, frame_count, render_pass, set_layouts.clone())?;
+
+        #[cfg(feature = "egui")]
+        let egui = crate::ui::EguiRenderer::new(&ctx.device, frame_count, render_pass, set_layouts.clone())?;
+
         Ok(Arc::new(Self {
             bindless,
             pipeline_cache: RwLock::new(pipeline_cache),
             transforms: RwLock::new(transforms),
-            meshes: RwLock::new(SlotMap::with_key()),
+            released_transforms: Mutex::new(vec![]),
+            instances: RwLock::new(InstanceStore::new(frame_count)),
+            scene: RwLock::new(scene),
+            meshes: RwLock::new(MeshStore::new(frame_count, ctx.device.index_type_uint8())),
             camera: RwLock::new(camera),
+            frame_values: RwLock::new(frame_values),
+            per_frame,
+            debug: RwLock::new(debug),
+            canvas: RwLock::new(canvas),
+            polylines: RwLock::new(polylines),
+            point_clouds: RwLock::new(point_clouds),
+            height_fields: RwLock::new(height_fields),
+            textures: RwLock::new(TextureStore::new(frame_count)),
+            volumes: RwLock::new(volumes),
+            isosurfaces: RwLock::new(isosurfaces),
+            uploads: RwLock::new(uploads),
+            colormaps: RwLock::new(colormaps),
+            #[cfg(feature = "text")]
+            text: RwLock::new(text),
+            #[cfg(feature = "egui")]
+            egui: RwLock::new(egui),
         }))
     }
 
-    fn make_handle<T: Destroy>(
-        self: &Arc<Self>,
-        ctx: &Arc<RenderContext>,
-        key: ResourceKey,
-    ) -> Res<T> {
-        Res {
-            key,
-            ref_count: Arc::new(AtomicUsize::new(1)),
-            ctx: Arc::downgrade(ctx),
-            resources: Arc::downgrade(self),
-            _marker: PhantomData,
-        }
-    }
-
     // Always Set 0
     pub fn bindless_set(&self) -> vk::DescriptorSet {
         self.bindless.set
     }
 
-    // Always Set 1
-    pub fn per_frame_set(&self) -> vk::DescriptorSet {
-        todo!()
+    // Always Set 1: camera, frame values, transforms and the GPU scene
+    pub fn per_frame_set(&self, frame: usize) -> vk::DescriptorSet {
+        self.per_frame.get(frame)
+    }
+
+    /// Set layouts shared by every pipeline: bindless set 0 and per-frame
+    /// set 1
+    pub(crate) fn set_layouts(&self) -> Vec<vk::DescriptorSetLayout> {
+        vec![self.bindless.set_layout.raw, self.per_frame.set_layout.raw]
     }
 
     pub(crate) fn destroy(&self, device: &Device) {
+        #[cfg(feature = "egui")]
+        self.egui.write().destroy(device, &self.bindless);
+        self.debug.write().destroy(device);
+        self.canvas.write().destroy(device);
+        #[cfg(feature = "text")]
+        self.text.write().destroy(device, &self.bindless);
+        self.colormaps.write().destroy(device, &self.bindless);
+        // Volumes and isosurfaces hold their textures
+        self.volumes.write().destroy(device);
+        self.isosurfaces.write().destroy(device);
+        self.textures.write().destroy(device, &self.bindless);
+        self.uploads.write().destroy(device);
         self.bindless.destroy(device);
+        self.per_frame.destroy(device);
         self.camera.write().destroy(device);
+        self.frame_values.write().destroy(device);
         self.transforms.write().destroy(device);
-
-        for (_, mut mesh) in self.meshes.write().drain() {
-            mesh.vertex_buffer.destroy(device);
-            if let Some(mut index) = mesh.index_buffer.take() {
-                index.destroy(device);
-            }
-        }
+        self.instances.write().destroy(device);
+        self.scene.write().destroy(device);
+        self.meshes.write().destroy(device);
+        self.polylines.write().destroy(device);
+        self.point_clouds.write().destroy(device);
+        self.height_fields.write().destroy(device);
     }
 }
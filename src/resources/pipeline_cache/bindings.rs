@@ -18,6 +18,41 @@ pub enum ShaderType {
     U32,
 }
 
+impl ShaderType {
+    /// Format and size in bytes of every vertex attribute location the type
+    /// occupies, matrices take one location per column
+    ///
+    /// # Panics
+    /// - if the type cannot be a vertex attribute
+    pub(crate) fn vertex_attributes(&self) -> Vec<(vk::Format, u32)> {
+        match self {
+            ShaderType::Float => vec![(vk::Format::R32_SFLOAT, 4)],
+            ShaderType::Float2 => vec![(vk::Format::R32G32_SFLOAT, 8)],
+            ShaderType::Float3 => vec![(vk::Format::R32G32B32_SFLOAT, 12)],
+            ShaderType::Float4 => vec![(vk::Format::R32G32B32A32_SFLOAT, 16)],
+            ShaderType::U32 => vec![(vk::Format::R32_UINT, 4)],
+            ShaderType::Mat3x3 => vec![(vk::Format::R32G32B32_SFLOAT, 12); 3],
+            ShaderType::Mat4x4 => vec![(vk::Format::R32G32B32A32_SFLOAT, 16); 4],
+            ShaderType::Custom(format) => {
+                let size = match *format {
+                    vk::Format::R8G8B8A8_UNORM
+                    | vk::Format::R8G8B8A8_SNORM
+                    | vk::Format::R8G8B8A8_UINT
+                    | vk::Format::R16G16_SFLOAT
+                    | vk::Format::R16G16_UNORM
+                    | vk::Format::R32_SINT => 4,
+                    vk::Format::R16G16B16A16_SFLOAT | vk::Format::R16G16B16A16_UNORM | vk::Format::R32G32_UINT | vk::Format::R32G32_SINT => 8,
+                    vk::Format::R32G32B32_UINT | vk::Format::R32G32B32_SINT => 12,
+                    vk::Format::R32G32B32A32_UINT | vk::Format::R32G32B32A32_SINT => 16,
+                    format => panic!("Unsupported vertex attribute format {:?}", format),
+                };
+                vec![(*format, size)]
+            },
+            ShaderType::Texture2D => panic!("Texture2D cannot be a vertex attribute"),
+        }
+    }
+}
+
 #[derive(Eq, Hash, PartialEq, Clone)]
 pub enum ShaderStage {
     Vertex,
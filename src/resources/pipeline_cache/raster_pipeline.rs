@@ -22,16 +22,48 @@ impl VertexInput {
         self.inputs.push(ty);
         self
     }
+
+    /// Tightly packed attributes of `binding` starting at `location`
+    fn describe(
+        &self,
+        binding: u32,
+        location: u32,
+        input_rate: vk::VertexInputRate,
+    ) -> (vk::VertexInputBindingDescription, Vec<vk::VertexInputAttributeDescription>) {
+        let mut attributes = vec![];
+        let mut offset = 0;
+
+        for (format, size) in self.inputs.iter().flat_map(ShaderType::vertex_attributes) {
+            attributes.push(vk::VertexInputAttributeDescription {
+                location: location + attributes.len() as u32,
+                binding,
+                format,
+                offset,
+            });
+            offset += size;
+        }
+
+        let binding = vk::VertexInputBindingDescription {
+            binding,
+            stride: offset,
+            input_rate,
+        };
+
+        (binding, attributes)
+    }
 }
 
 pub struct RasterPipelineDesc<'a> {
     use_cache: bool,
     dynamic_viewport: bool,
     dynamic_scissors: bool,
+    depth_test: bool,
+    depth_write: bool,
     vertex_shader: Option<Source<'a>>,
     fragment_shader: Option<Source<'a>>,
     multiple_render_target: Option<usize>,
     vertex_input: Option<VertexInput>,
+    instance_input: Option<VertexInput>,
 }
 
 impl<'a> Default for RasterPipelineDesc<'a> {
@@ -40,10 +72,13 @@ impl<'a> Default for RasterPipelineDesc<'a> {
             use_cache: false,
             dynamic_viewport: false,
             dynamic_scissors: false,
+            depth_test: true,
+            depth_write: false,
             vertex_shader: None,
             fragment_shader: None,
             multiple_render_target: None,
             vertex_input: None,
+            instance_input: None,
         }
     }
 }
@@ -73,11 +108,43 @@ impl<'a> RasterPipelineDesc<'a> {
         self
     }
 
+    /// Per-vertex attributes read at binding 2 next to the mesh, from the
+    /// buffer of [`PassContext::draw_mesh_with_attributes`]
+    ///
+    /// Locations follow the mesh attributes, starting at 5. Attributes must
+    /// be tightly packed in the declared order, one entry per mesh vertex
+    ///
+    /// # Example
+    /// ```ignore
+    /// // layout(location = 5) in float value;
+    /// RasterPipelineDesc::new().vertex_input(VertexInput::new().with(ShaderType::Float))
+    /// ```
+    ///
+    /// [`PassContext::draw_mesh_with_attributes`]: crate::frame_graph::PassContext::draw_mesh_with_attributes
     pub fn vertex_input(mut self, input: VertexInput) -> Self {
         self.vertex_input = Some(input);
         self
     }
 
+    /// Per-instance attributes read from an [`InstanceBuffer`] at binding 1
+    ///
+    /// Locations follow the vertex attributes, starting at 5 without a
+    /// [`RasterPipelineDesc::vertex_input`]. Instance data must be tightly
+    /// packed in the declared order
+    ///
+    /// # Example
+    /// ```ignore
+    /// // layout(location = 5) in vec3 offset;
+    /// // layout(location = 6) in vec4 color;
+    /// RasterPipelineDesc::new().instance_input(VertexInput::new().with(ShaderType::Float3).with(ShaderType::Float4))
+    /// ```
+    ///
+    /// [`InstanceBuffer`]: crate::InstanceBuffer
+    pub fn instance_input(mut self, input: VertexInput) -> Self {
+        self.instance_input = Some(input);
+        self
+    }
+
     pub fn dynamic_viewport(mut self, value: bool) -> Self {
         self.dynamic_viewport = value;
         self
@@ -87,6 +154,25 @@ impl<'a> RasterPipelineDesc<'a> {
         self.dynamic_scissors = value;
         self
     }
+
+    /// Depth uses reversed-Z, closer fragments have greater depth
+    ///
+    /// The depth attachment is cleared to 0 and tested with
+    /// `GREATER_OR_EQUAL`. Vertex shaders written for the old 0-near, 1-far
+    /// convention have to flip their depth, or every fragment fails the test,
+    /// projections of [`Camera`] already produce reversed depth
+    ///
+    /// [`Camera`]: crate::Camera
+    pub fn depth_test(mut self, value: bool) -> Self {
+        self.depth_test = value;
+        self
+    }
+
+    /// Off by default, transparent and overlay pipelines keep it off
+    pub fn depth_write(mut self, value: bool) -> Self {
+        self.depth_write = value;
+        self
+    }
 }
 
 pub struct RasterPipeline {
@@ -105,15 +191,27 @@ impl Create for RasterPipeline {
         resources: &std::sync::Arc<Resources>,
         desc: Self::Desc<'_>,
     ) -> VulkanResult<Res<Self>> {
-        let binding = PbrVertex::bind_desc();
-        let attrs = PbrVertex::attr_desc();
+        let mut binding = PbrVertex::bind_desc();
+        let mut attrs = PbrVertex::attr_desc();
+
+        if let Some(input) = &desc.vertex_input {
+            let (vertex_binding, vertex_attrs) = input.describe(2, attrs.len() as u32, vk::VertexInputRate::VERTEX);
+            binding.push(vertex_binding);
+            attrs.extend(vertex_attrs);
+        }
+
+        if let Some(input) = &desc.instance_input {
+            let (instance_binding, instance_attrs) = input.describe(1, attrs.len() as u32, vk::VertexInputRate::INSTANCE);
+            binding.push(instance_binding);
+            attrs.extend(instance_attrs);
+        }
 
         let vertex_input_info = vk::PipelineVertexInputStateCreateInfo::default()
             .vertex_binding_descriptions(&binding)
             .vertex_attribute_descriptions(&attrs);
 
         let layout = PipelineLayoutBuilder::new(&ctx.device)
-            .set_layouts(vec![])
+            .set_layouts(resources.set_layouts())
             .push_constant(vec![vk::PushConstantRange::default()
                 .offset(0)
                 .size(128)
@@ -184,6 +282,16 @@ impl Create for RasterPipeline {
                     .front_face(vk::FrontFace::CLOCKWISE)
                     .depth_bias_enable(false),
             )
+            .depth_stencil(
+                vk::PipelineDepthStencilStateCreateInfo::default()
+                    .depth_test_enable(desc.depth_test)
+                    .depth_write_enable(desc.depth_write)
+                    .depth_compare_op(vk::CompareOp::GREATER_OR_EQUAL)
+                    .depth_bounds_test_enable(false)
+                    .min_depth_bounds(0.0)
+                    .max_depth_bounds(1.0)
+                    .stencil_test_enable(false),
+            )
             .multisampling(
                 vk::PipelineMultisampleStateCreateInfo::default()
                     .sample_shading_enable(false)
@@ -0,0 +1,591 @@
+use std::sync::{Arc, Weak};
+
+use ash::vk;
+use bytemuck::{Pod, Zeroable};
+
+use crate::core::{
+    read_shader_from_bytes, Device, GpuBuffer, GpuBufferBuilder, GraphicsPipeline, GraphicsPipelineBuilder, PipelineLayout, PipelineLayoutBuilder,
+    ShaderBuilder,
+};
+use crate::frame_graph::PushHeader;
+use crate::math::{Aabb, Mat4, Vec3};
+use crate::render_context::RenderContext;
+use crate::resources::{Colormap, Create, Destroy, Get, GetMut, Pool, Ref, RefMut, Res, ResourceKey, Resources, Retired, ScalarRange};
+use crate::VulkanResult;
+
+mod octree;
+use octree::Chunk;
+
+const VERTEX_SHADER: &[u8] = include_bytes!(concat!(env!("CARGO_MANIFEST_DIR"), "/shaders/spv/point_cloud_vert.spv"));
+const FRAGMENT_SHADER: &[u8] = include_bytes!(concat!(env!("CARGO_MANIFEST_DIR"), "/shaders/spv/point_cloud_frag.spv"));
+
+/// Vertices `shaders/point_cloud.vert` expands every point into
+const SPRITE_VERTICES: u32 = 6;
+
+/// Points copied to the GPU by one draw, chunks past it stream in over the
+/// next frames instead of stalling one
+const UPLOAD_BUDGET: usize = 4 << 20;
+
+/// Point in the layout of `shaders/point_cloud.vert`
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Pod, Zeroable)]
+pub struct CloudPoint {
+    /// Position in the space of the cloud
+    pub pos: Vec3,
+    /// sRGBA8 color or scalar bits, read as [`PointCloud::color`] says
+    value: u32,
+}
+
+impl CloudPoint {
+    /// Point with an 8-bit sRGB color and linear alpha, drawn with
+    /// [`PointColor::Rgba`]
+    pub fn rgba(pos: Vec3, color: [u8; 4]) -> Self {
+        Self {
+            pos,
+            value: u32::from_le_bytes(color),
+        }
+    }
+
+    /// Point with a value mapped to a color by [`PointColor::Scalar`]
+    pub fn scalar(pos: Vec3, value: f32) -> Self {
+        Self {
+            pos,
+            value: value.to_bits(),
+        }
+    }
+}
+
+/// Shape of every point on screen
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum PointShape {
+    /// Square of [`PointCloud::size`] pixels
+    #[default]
+    Square,
+    /// Circle of [`PointCloud::size`] pixels across
+    Circle,
+}
+
+/// Source of the color of every point
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum PointColor {
+    /// Color of [`CloudPoint::rgba`]
+    Rgba,
+    /// Values of [`CloudPoint::scalar`] mapped through a colormap like
+    /// `shaders/inc/colormap.glsl`, transparent points are not drawn
+    Scalar {
+        /// Lookup table of the colors
+        colormap: Colormap,
+        /// Values at both ends of the colormap
+        range: ScalarRange,
+    },
+}
+
+/// Push constants in the layout of `shaders/point_cloud.vert`
+#[repr(C)]
+#[derive(Clone, Copy, Pod, Zeroable)]
+struct Push {
+    header: PushHeader,
+    /// First as `shaders/inc/colormap.glsl` reads it
+    range: ScalarRange,
+    size: f32,
+    shape: f32,
+    mode: f32,
+}
+
+/// Parameters of a new [`PointCloud`]
+pub struct PointCloudDesc<'a> {
+    points: &'a [CloudPoint],
+    size: f32,
+    shape: PointShape,
+    color: PointColor,
+    budget: usize,
+    chunk_size: usize,
+}
+
+impl<'a> PointCloudDesc<'a> {
+    /// Squares of 2 pixels colored by [`CloudPoint::rgba`], at most 4M
+    /// points drawn at once
+    pub fn new(points: &'a [CloudPoint]) -> Self {
+        Self {
+            points,
+            size: 2.0,
+            shape: PointShape::default(),
+            color: PointColor::Rgba,
+            budget: 4 << 20,
+            chunk_size: 64 << 10,
+        }
+    }
+
+    /// Width of every point in pixels
+    pub fn size(mut self, size: f32) -> Self {
+        self.size = size;
+        self
+    }
+
+    /// Shape of every point
+    pub fn shape(mut self, shape: PointShape) -> Self {
+        self.shape = shape;
+        self
+    }
+
+    /// Source of the colors
+    pub fn color(mut self, color: PointColor) -> Self {
+        self.color = color;
+        self
+    }
+
+    /// Largest number of points drawn at once, see [`PointCloud::budget`]
+    pub fn budget(mut self, points: usize) -> Self {
+        self.budget = points;
+        self
+    }
+
+    /// Largest number of points in one chunk of the octree, `65536` by
+    /// default
+    ///
+    /// Smaller chunks follow the camera closer at the cost of more draws
+    pub fn chunk_size(mut self, points: usize) -> Self {
+        self.chunk_size = points;
+        self
+    }
+}
+
+/// GPU copy of a chunk
+struct Resident {
+    buffer: GpuBuffer,
+    /// Draw the chunk was last selected by
+    last_draw: u64,
+}
+
+/// Points drawn as screen-aligned squares or circles of constant size in
+/// pixels
+///
+/// Points are split into an octree of chunks on the CPU. Every draw selects
+/// the chunks the [`Camera`] needs, coarse chunks far away and finer ones
+/// close by, under [`PointCloud::budget`] points. Selected chunks are copied
+/// to the GPU on demand, the least recently drawn are released past
+/// [`PointCloud::resident_budget`] points, see
+/// [`PassContext::draw_point_cloud`]
+///
+/// # Example
+/// ```ignore
+/// let points = scan.iter().map(|p| CloudPoint::scalar(p.pos, p.intensity)).collect::<Vec<_>>();
+/// let cloud = world.create::<PointCloud>(
+///     PointCloudDesc::new(&points)
+///         .shape(PointShape::Circle)
+///         .color(PointColor::Scalar { colormap: Colormap::Viridis, range: ScalarRange::new(0.0, 1.0) }),
+/// )?;
+///
+/// world.draw_frame(|graph| {
+///     graph.add_pass(PresentPass::new("Scan", |_| {}, move |ctx, _| unsafe {
+///         ctx.draw_point_cloud(&cloud);
+///     }));
+/// })?;
+/// ```
+///
+/// [`Camera`]: crate::Camera
+/// [`PassContext::draw_point_cloud`]: crate::frame_graph::PassContext::draw_point_cloud
+pub struct PointCloud {
+    /// Width of every point in pixels
+    pub size: f32,
+    /// Shape of every point
+    pub shape: PointShape,
+    /// Source of the colors
+    pub color: PointColor,
+    /// Largest number of points drawn at once
+    pub budget: usize,
+    /// Largest number of points kept on the GPU between draws, twice
+    /// [`PointCloud::budget`] by default
+    pub resident_budget: usize,
+    /// Points ordered by chunk
+    points: Vec<CloudPoint>,
+    chunks: Vec<Chunk>,
+    /// GPU copies, one per chunk
+    resident: Vec<Option<Resident>>,
+    resident_points: usize,
+    draws: u64,
+    drawn_points: usize,
+}
+
+impl PointCloud {
+    fn new(desc: PointCloudDesc<'_>) -> Self {
+        let mut points = desc.points.to_vec();
+        let chunks = octree::build(&mut points, desc.chunk_size);
+
+        Self {
+            size: desc.size,
+            shape: desc.shape,
+            color: desc.color,
+            budget: desc.budget,
+            resident_budget: desc.budget.saturating_mul(2),
+            points,
+            resident: chunks.iter().map(|_| None).collect(),
+            chunks,
+            resident_points: 0,
+            draws: 0,
+            drawn_points: 0,
+        }
+    }
+
+    /// Number of points
+    pub fn len(&self) -> usize {
+        self.points.len()
+    }
+
+    /// No points to draw
+    pub fn is_empty(&self) -> bool {
+        self.points.is_empty()
+    }
+
+    /// Bounds of every point, `None` without points
+    pub fn aabb(&self) -> Option<Aabb> {
+        self.chunks.first().map(|root| root.aabb)
+    }
+
+    /// Number of chunks in the octree
+    pub fn chunk_count(&self) -> usize {
+        self.chunks.len()
+    }
+
+    /// Points drawn by the last draw
+    pub fn drawn_points(&self) -> usize {
+        self.drawn_points
+    }
+
+    /// Points currently copied to the GPU
+    pub fn resident_points(&self) -> usize {
+        self.resident_points
+    }
+
+    /// Copy the chunks the camera needs to the GPU and release the least
+    /// recently drawn past the resident budget, returns the chunks to draw
+    fn stream(&mut self, device: &Device, selected: &[usize], frames: usize, retired: &mut Retired<GpuBuffer>) -> VulkanResult<Vec<usize>> {
+        self.draws += 1;
+
+        let mut uploaded = 0;
+        let mut visible = Vec::with_capacity(selected.len());
+        for &index in selected {
+            let chunk = &self.chunks[index];
+
+            if self.resident[index].is_none() {
+                // Coarser chunks come first, they keep gaps covered while
+                // finer ones wait
+                if uploaded + chunk.len() > UPLOAD_BUDGET {
+                    continue;
+                }
+
+                let mut buffer = GpuBufferBuilder::cpu_only(device)
+                    .size((chunk.len() * size_of::<CloudPoint>()) as u64)
+                    .usage(vk::BufferUsageFlags::VERTEX_BUFFER)
+                    .build()?;
+                buffer.upload_data_at(0, &self.points[chunk.range.clone()])?;
+
+                self.resident[index] = Some(Resident { buffer, last_draw: 0 });
+                self.resident_points += chunk.len();
+                uploaded += chunk.len();
+            }
+
+            if let Some(resident) = &mut self.resident[index] {
+                resident.last_draw = self.draws;
+            }
+            visible.push(index);
+        }
+
+        if self.resident_points > self.resident_budget {
+            let mut evictable = self
+                .resident
+                .iter()
+                .enumerate()
+                .filter_map(|(index, resident)| resident.as_ref().map(|resident| (resident.last_draw, index)))
+                .filter(|&(last_draw, _)| last_draw < self.draws)
+                .collect::<Vec<_>>();
+            evictable.sort_unstable();
+
+            for (_, index) in evictable {
+                if self.resident_points <= self.resident_budget {
+                    break;
+                }
+                let resident = self.resident[index].take().expect("Evicted chunks are resident");
+                self.resident_points -= self.chunks[index].len();
+                retired.push(frames, resident.buffer);
+            }
+        }
+
+        self.drawn_points = visible.iter().map(|&index| self.chunks[index].len()).sum();
+        Ok(visible)
+    }
+
+    /// `colormap_idx` is read by [`PointColor::Scalar`] only
+    fn push_constants(&self, transform: Option<u32>, colormap_idx: u32) -> Push {
+        let (mode, range) = match self.color {
+            PointColor::Rgba => (0.0, ScalarRange::new(0.0, 1.0)),
+            PointColor::Scalar { range, .. } => (1.0, range),
+        };
+        let mut tex_idx = [0; 8];
+        tex_idx[0] = colormap_idx;
+
+        Push {
+            header: PushHeader::new(transform, tex_idx),
+            range,
+            size: self.size,
+            shape: self.shape as u32 as f32,
+            mode,
+        }
+    }
+
+    fn into_buffers(self) -> impl Iterator<Item = GpuBuffer> {
+        self.resident.into_iter().flatten().map(|resident| resident.buffer)
+    }
+}
+
+impl Destroy for PointCloud {
+    fn destroy(key: ResourceKey, _ctx: Weak<RenderContext>, resources: Weak<Resources>) {
+        let Some(resources) = resources.upgrade() else {
+            return;
+        };
+
+        let mut store = resources.point_clouds.write();
+        if let Some(cloud) = store.pool.remove(key) {
+            let frames = store.frame_count;
+            store.retired.extend(frames, cloud.into_buffers());
+        }
+    }
+}
+
+impl Create for PointCloud {
+    type Desc<'a> = PointCloudDesc<'a>;
+    fn create(ctx: &Arc<RenderContext>, resources: &Arc<Resources>, desc: Self::Desc<'_>) -> VulkanResult<Res<Self>> {
+        // Building the octree of large clouds takes a while, the store
+        // stays unlocked meanwhile
+        let cloud = PointCloud::new(desc);
+
+        Ok(resources
+            .point_clouds
+            .write()
+            .pool
+            .insert(Arc::downgrade(ctx), Arc::downgrade(resources), cloud))
+    }
+}
+
+impl Get for PointCloud {
+    fn get<'a>(resources: &'a Resources, res: &Res<Self>) -> Ref<'a, Self> {
+        let store = resources.point_clouds.try_read().expect("Point clouds are already borrowed mutably");
+        Ref(parking_lot::RwLockReadGuard::map(store, |store| store.pool.get(res)))
+    }
+}
+
+impl GetMut for PointCloud {
+    fn get_mut<'a>(resources: &'a Resources, res: &Res<Self>) -> RefMut<'a, Self> {
+        let store = resources.point_clouds.try_write().expect("Point clouds are already borrowed");
+        RefMut(parking_lot::RwLockWriteGuard::map(store, |store| store.pool.get_mut(res)))
+    }
+}
+
+/// Point clouds with the pipeline drawing them
+pub struct PointCloudStore {
+    pub(crate) pool: Pool<PointCloud>,
+    /// Evicted and dropped buffers
+    retired: Retired<GpuBuffer>,
+    frame_count: usize,
+    layout: PipelineLayout,
+    pipeline: GraphicsPipeline,
+}
+
+impl PointCloudStore {
+    pub fn new(device: &Device, frame_count: usize, render_pass: vk::RenderPass, set_layouts: Vec<vk::DescriptorSetLayout>) -> VulkanResult<Self> {
+        // The layout of every raster pipeline, set 1 holds the camera and
+        // transforms
+        let layout = PipelineLayoutBuilder::new(device)
+            .set_layouts(set_layouts)
+            .push_constant(vec![vk::PushConstantRange::default()
+                .offset(0)
+                .size(128)
+                .stage_flags(vk::ShaderStageFlags::VERTEX | vk::ShaderStageFlags::FRAGMENT)])
+            .build()?;
+
+        let spv = read_shader_from_bytes(VERTEX_SHADER).expect("Invalid point cloud vertex shader");
+        let vertex = ShaderBuilder::new(device).bytecode(&spv).build()?;
+        let spv = read_shader_from_bytes(FRAGMENT_SHADER).expect("Invalid point cloud fragment shader");
+        let fragment = ShaderBuilder::new(device).bytecode(&spv).build()?;
+
+        // Every point is one instance of the sprite
+        let bindings = [vk::VertexInputBindingDescription {
+            binding: 0,
+            stride: size_of::<CloudPoint>() as u32,
+            input_rate: vk::VertexInputRate::INSTANCE,
+        }];
+        let attributes = [
+            vk::VertexInputAttributeDescription {
+                location: 0,
+                binding: 0,
+                format: vk::Format::R32G32B32_SFLOAT,
+                offset: 0,
+            },
+            vk::VertexInputAttributeDescription {
+                location: 1,
+                binding: 0,
+                format: vk::Format::R32_UINT,
+                offset: size_of::<Vec3>() as u32,
+            },
+        ];
+
+        let vertex_input_info = vk::PipelineVertexInputStateCreateInfo::default()
+            .vertex_binding_descriptions(&bindings)
+            .vertex_attribute_descriptions(&attributes);
+
+        let color_blend = vk::PipelineColorBlendAttachmentState::default().color_write_mask(vk::ColorComponentFlags::RGBA);
+
+        // Viewport and scissors are dynamic, set for every draw. Points are
+        // opaque, depth is tested with the reversed-Z compare and written
+        let pipeline = GraphicsPipelineBuilder::new(device)
+            .vertex_shader(vertex.raw)
+            .fragment_shader(fragment.raw)
+            .render_pass(render_pass)
+            .pipeline_layout(layout.raw)
+            .viewport(vec![vk::Viewport::default().width(1.0).height(1.0).max_depth(1.0)])
+            .scissors(vec![vk::Rect2D::default()])
+            .input_assembly(vk::PipelineInputAssemblyStateCreateInfo::default().topology(vk::PrimitiveTopology::TRIANGLE_LIST))
+            .rasterization(
+                vk::PipelineRasterizationStateCreateInfo::default()
+                    .polygon_mode(vk::PolygonMode::FILL)
+                    .line_width(1.0)
+                    .cull_mode(vk::CullModeFlags::NONE),
+            )
+            .multisampling(vk::PipelineMultisampleStateCreateInfo::default().rasterization_samples(vk::SampleCountFlags::TYPE_1))
+            .color_blending(vk::PipelineColorBlendStateCreateInfo::default().attachments(&[color_blend]))
+            .depth_stencil(
+                vk::PipelineDepthStencilStateCreateInfo::default()
+                    .depth_test_enable(true)
+                    .depth_write_enable(true)
+                    .depth_compare_op(vk::CompareOp::GREATER_OR_EQUAL),
+            )
+            .dynamic_state(vec![vk::DynamicState::VIEWPORT, vk::DynamicState::SCISSOR])
+            .vertex_input_info(vertex_input_info)
+            .build()?;
+
+        Ok(Self {
+            pool: Pool::new(),
+            retired: Retired::default(),
+            frame_count,
+            layout,
+            pipeline,
+        })
+    }
+
+    /// Release buffers no frame in flight can use anymore, called once per
+    /// frame
+    pub fn update(&mut self, device: &Device) {
+        profiling::scope!("PointCloudStore::update");
+
+        self.retired.update(device);
+    }
+
+    /// Stream in the chunks of `cloud` seen by the camera and draw them over
+    /// the whole target, must be inside the render pass
+    ///
+    /// `sets` are the bindless and per-frame sets of the frame, `world`
+    /// places the cloud and `transform` is its index or `None` for identity.
+    /// `colormap_idx` is the lookup table of [`PointColor::Scalar`]
+    #[allow(clippy::too_many_arguments)]
+    pub(crate) unsafe fn draw(
+        &mut self,
+        device: &Device,
+        cbuf: vk::CommandBuffer,
+        resolution: vk::Extent2D,
+        sets: &[vk::DescriptorSet],
+        cloud: &Res<PointCloud>,
+        camera: (&Mat4, &Mat4),
+        world: &Mat4,
+        transform: Option<u32>,
+        colormap_idx: u32,
+    ) -> VulkanResult<()> {
+        profiling::scope!("PointCloudStore::draw");
+
+        let cloud = self.pool.get_mut(cloud);
+        let (view, proj) = camera;
+        let selected = octree::select(&cloud.chunks, world, view, proj, resolution.height as f32, cloud.budget);
+        let visible = cloud.stream(device, &selected, self.frame_count, &mut self.retired)?;
+
+        if visible.is_empty() {
+            return Ok(());
+        }
+
+        let viewport = vk::Viewport::default()
+            .width(resolution.width as f32)
+            .height(resolution.height as f32)
+            .max_depth(1.0);
+        let scissor = vk::Rect2D::default().extent(resolution);
+
+        device.cmd_bind_pipeline(cbuf, vk::PipelineBindPoint::GRAPHICS, self.pipeline.raw);
+        device.cmd_bind_descriptor_sets(cbuf, vk::PipelineBindPoint::GRAPHICS, self.layout.raw, 0, sets, &[]);
+        device.cmd_set_viewport(cbuf, 0, &[viewport]);
+        device.cmd_set_scissor(cbuf, 0, &[scissor]);
+        device.cmd_push_constants(
+            cbuf,
+            self.layout.raw,
+            vk::ShaderStageFlags::VERTEX | vk::ShaderStageFlags::FRAGMENT,
+            0,
+            bytemuck::bytes_of(&cloud.push_constants(transform, colormap_idx)),
+        );
+
+        for index in visible {
+            let resident = cloud.resident[index].as_ref().expect("Visible chunks are resident");
+            device.cmd_bind_vertex_buffers(cbuf, 0, &[resident.buffer.raw], &[0]);
+            device.cmd_draw(cbuf, SPRITE_VERTICES, cloud.chunks[index].len() as u32, 0, 0);
+        }
+
+        Ok(())
+    }
+
+    pub fn destroy(&mut self, device: &Device) {
+        self.retired.destroy(device);
+        for (_, cloud) in self.pool.slots.drain() {
+            for mut buffer in cloud.into_buffers() {
+                buffer.destroy(device);
+            }
+        }
+        self.pipeline.destroy(device);
+        unsafe {
+            device.destroy_pipeline_layout(self.layout.raw, None);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::frame_graph::NO_TRANSFORM;
+
+    #[test]
+    fn values_keep_their_bits() {
+        let color = CloudPoint::rgba([0.0; 3], [1, 2, 3, 4]);
+        let scalar = CloudPoint::scalar([0.0; 3], 0.25);
+
+        assert_eq!(bytemuck::cast::<_, [u32; 4]>(color)[3], 0x0403_0201);
+        assert_eq!(f32::from_bits(scalar.value), 0.25);
+        assert_eq!(size_of::<CloudPoint>(), 16);
+    }
+
+    #[test]
+    fn desc_builds_the_octree() {
+        let points = (0..1000).map(|i| CloudPoint::scalar([i as f32, 0.0, 0.0], 0.0)).collect::<Vec<_>>();
+        let cloud = PointCloud::new(PointCloudDesc::new(&points).chunk_size(100).budget(500));
+
+        assert_eq!(cloud.len(), 1000);
+        assert!(cloud.chunk_count() > 1);
+        assert_eq!(cloud.resident_budget, 1000);
+        assert_eq!(cloud.aabb(), Some(Aabb::new([0.0; 3], [999.0, 0.0, 0.0])));
+    }
+
+    #[test]
+    fn scalars_read_the_colormap() {
+        let range = ScalarRange::new(-1.0, 1.0);
+        let color = PointColor::Scalar { colormap: Colormap::Turbo, range };
+        let scalar = PointCloud::new(PointCloudDesc::new(&[]).color(color)).push_constants(Some(3), 7);
+        let rgba = PointCloud::new(PointCloudDesc::new(&[])).push_constants(None, 7);
+
+        assert_eq!((scalar.mode, scalar.range, scalar.header.tex_idx[0]), (1.0, range, 7));
+        assert_eq!(scalar.header.transform_idx, 3);
+        assert_eq!((rgba.mode, rgba.header.transform_idx), (0.0, NO_TRANSFORM));
+    }
+}
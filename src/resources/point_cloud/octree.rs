@@ -0,0 +1,230 @@
+//! Octree chunks of a point cloud and the chunks drawn from a camera
+
+use std::collections::BinaryHeap;
+use std::f32::consts::PI;
+use std::ops::Range;
+
+use super::CloudPoint;
+use crate::math::{self, length, Aabb, Frustum, Mat4, Sphere};
+
+/// Depth where chunks stop splitting, duplicated points would split forever
+const MAX_DEPTH: u32 = 20;
+
+/// Node of the octree
+///
+/// A chunk holds an even sample of the points under it, its children hold
+/// the rest. Drawing a chunk with all of its ancestors draws every point of
+/// its volume
+#[derive(Clone, Debug)]
+pub(super) struct Chunk {
+    /// Bounds of the points of the chunk and its children
+    pub aabb: Aabb,
+    /// Points of the chunk in the reordered points
+    pub range: Range<usize>,
+    pub children: Vec<usize>,
+}
+
+impl Chunk {
+    pub fn len(&self) -> usize {
+        self.range.len()
+    }
+}
+
+/// Split `points` into chunks of at most `chunk_size` points, the root is
+/// the first chunk
+///
+/// Points are reordered so every chunk is a contiguous range
+pub(super) fn build(points: &mut Vec<CloudPoint>, chunk_size: usize) -> Vec<Chunk> {
+    let Some(aabb) = Aabb::from_points(points.iter().map(|point| point.pos)) else {
+        return vec![];
+    };
+
+    // Cubes keep the octants of every depth the same shape
+    let center = aabb.center();
+    let half = aabb.extents().into_iter().fold(0.0, f32::max);
+    let cube = Aabb::new(center.map(|c| c - half), center.map(|c| c + half));
+
+    let mut chunks = vec![];
+    let mut ordered = Vec::with_capacity(points.len());
+    build_chunk(std::mem::take(points), cube, 0, chunk_size.max(1), &mut chunks, &mut ordered);
+    *points = ordered;
+
+    chunks
+}
+
+fn build_chunk(points: Vec<CloudPoint>, cube: Aabb, depth: u32, chunk_size: usize, chunks: &mut Vec<Chunk>, ordered: &mut Vec<CloudPoint>) -> usize {
+    let index = chunks.len();
+    chunks.push(Chunk {
+        aabb: Aabb::from_points(points.iter().map(|point| point.pos)).expect("Chunks are never empty"),
+        range: 0..0,
+        children: vec![],
+    });
+
+    let count = points.len();
+    let (own, rest) = if count <= chunk_size || depth == MAX_DEPTH {
+        (points, vec![])
+    } else {
+        // Every `count / chunk_size`th point, spread over the whole chunk
+        let mut own = Vec::with_capacity(chunk_size);
+        let mut rest = Vec::with_capacity(count - chunk_size);
+        for (i, point) in points.into_iter().enumerate() {
+            if i * chunk_size % count < chunk_size {
+                own.push(point);
+            } else {
+                rest.push(point);
+            }
+        }
+        (own, rest)
+    };
+
+    chunks[index].range = ordered.len()..ordered.len() + own.len();
+    ordered.extend(own);
+
+    let center = cube.center();
+    let mut octants: [Vec<CloudPoint>; 8] = Default::default();
+    for point in rest {
+        let octant = (0..3).fold(0, |octant, axis| octant | (usize::from(point.pos[axis] >= center[axis]) << axis));
+        octants[octant].push(point);
+    }
+
+    for (octant, points) in octants.into_iter().enumerate() {
+        if points.is_empty() {
+            continue;
+        }
+
+        let pick = |axis: usize, low: f32, high: f32| if octant >> axis & 1 == 0 { low } else { high };
+        let min = [0, 1, 2].map(|axis| pick(axis, cube.min[axis], center[axis]));
+        let max = [0, 1, 2].map(|axis| pick(axis, center[axis], cube.max[axis]));
+
+        let child = build_chunk(points, Aabb::new(min, max), depth + 1, chunk_size, chunks, ordered);
+        chunks[index].children.push(child);
+    }
+
+    index
+}
+
+/// Chunks to draw from the camera, largest on screen first
+///
+/// Chunks inside the frustum are refined while they cover more pixels than
+/// they have points, until `budget` points are selected. `world` places the
+/// cloud and `height` is the target height in pixels
+pub(super) fn select(chunks: &[Chunk], world: &Mat4, view: &Mat4, proj: &Mat4, height: f32, budget: usize) -> Vec<usize> {
+    // Planes in the space of the points, chunks are tested untransformed
+    let frustum = Frustum::from_view_proj(&math::mul(proj, &math::mul(view, world)));
+
+    // Radius in pixels of a chunk, the same estimate as mesh LODs
+    let pixels = |chunk: &Chunk| {
+        let sphere = Sphere::new(chunk.aabb.center(), length(chunk.aabb.extents())).transform(world);
+        let center = math::transform_point(view, sphere.center);
+        let w = proj[2][3] * center[2] + proj[3][3];
+        let w = (w - sphere.radius * proj[2][3].abs()).max(f32::EPSILON);
+        sphere.radius * proj[1][1].abs() * height * 0.5 / w
+    };
+
+    let mut selected = vec![];
+    let mut heap = BinaryHeap::new();
+    let mut drawn = 0;
+
+    // Positive floats order the same as their bits
+    match chunks.first() {
+        Some(root) if frustum.intersects_aabb(&root.aabb) => heap.push((pixels(root).to_bits(), 0)),
+        _ => return selected,
+    }
+
+    while let Some((radius, index)) = heap.pop() {
+        let chunk = &chunks[index];
+        if drawn + chunk.len() > budget {
+            continue;
+        }
+
+        drawn += chunk.len();
+        selected.push(index);
+
+        let radius = f32::from_bits(radius);
+        if (chunk.len() as f32) >= PI * radius * radius {
+            continue;
+        }
+
+        for &child in &chunk.children {
+            if frustum.intersects_aabb(&chunks[child].aabb) {
+                heap.push((pixels(&chunks[child]).to_bits(), child));
+            }
+        }
+    }
+
+    selected
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::math::{look_at, perspective};
+
+    fn grid(side: usize) -> Vec<CloudPoint> {
+        (0..side * side * side)
+            .map(|i| {
+                let pos = [i % side, i / side % side, i / (side * side)].map(|c| c as f32 / side as f32);
+                CloudPoint::scalar(pos, i as f32)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn chunks_cover_every_point_once() {
+        let mut points = grid(20);
+        let original = points.len();
+        let chunks = build(&mut points, 500);
+
+        assert_eq!(points.len(), original);
+        assert!(chunks.len() > 1);
+
+        let mut covered = vec![false; points.len()];
+        for chunk in &chunks {
+            assert!(chunk.len() <= 500);
+            for i in chunk.range.clone() {
+                assert!(!covered[i]);
+                covered[i] = true;
+
+                let p = points[i].pos;
+                assert!((0..3).all(|axis| chunk.aabb.min[axis] <= p[axis] && p[axis] <= chunk.aabb.max[axis]));
+            }
+            for &child in &chunk.children {
+                let inner = chunks[child].aabb;
+                assert_eq!(chunk.aabb.union(&inner), chunk.aabb);
+            }
+        }
+        assert!(covered.into_iter().all(|covered| covered));
+    }
+
+    #[test]
+    fn duplicated_points_stop_splitting() {
+        let mut points = vec![CloudPoint::scalar([1.0; 3], 0.0); 100];
+        let chunks = build(&mut points, 4);
+
+        assert_eq!(chunks.len(), MAX_DEPTH as usize + 1);
+        assert_eq!(chunks.iter().map(Chunk::len).sum::<usize>(), 100);
+    }
+
+    #[test]
+    fn selection_refines_closer_chunks_within_budget() {
+        let mut points = grid(32);
+        let chunks = build(&mut points, 256);
+        let proj = perspective(60f32.to_radians(), 1.0, 0.1, 100.0);
+
+        let far = look_at([0.5, 0.5, 100.0], [0.5; 3], [0.0, 1.0, 0.0]);
+        let near = look_at([0.5, 0.5, 2.0], [0.5; 3], [0.0, 1.0, 0.0]);
+
+        let far = select(&chunks, &math::IDENTITY, &far, &proj, 512.0, usize::MAX);
+        let near = select(&chunks, &math::IDENTITY, &near, &proj, 512.0, usize::MAX);
+        assert_eq!(far, vec![0]);
+        assert!(near.len() > far.len());
+
+        let budget = 2000;
+        let limited = select(&chunks, &math::IDENTITY, &look_at([0.5, 0.5, 2.0], [0.5; 3], [0.0, 1.0, 0.0]), &proj, 512.0, budget);
+        assert_eq!(limited[0], 0);
+        assert!(limited.iter().map(|&i| chunks[i].len()).sum::<usize>() <= budget);
+
+        let behind = look_at([0.5, 0.5, 2.0], [0.5, 0.5, 4.0], [0.0, 1.0, 0.0]);
+        assert!(select(&chunks, &math::IDENTITY, &behind, &proj, 512.0, usize::MAX).is_empty());
+    }
+}
@@ -0,0 +1,517 @@
+use std::sync::{Arc, Weak};
+
+use ash::vk;
+use bytemuck::{Pod, Zeroable};
+
+use crate::core::{
+    read_shader_from_bytes, Device, GpuBuffer, GraphicsPipeline, GraphicsPipelineBuilder, PipelineLayout, PipelineLayoutBuilder, ShaderBuilder,
+};
+use crate::frame_graph::PushHeader;
+use crate::math::{length, sub, Vec3};
+use crate::render_context::RenderContext;
+use crate::resources::{Create, Destroy, Get, GetMut, MeshBuffer, Pool, Ref, RefMut, Res, ResourceKey, Resources, Retired};
+use crate::VulkanResult;
+
+const VERTEX_SHADER: &[u8] = include_bytes!(concat!(env!("CARGO_MANIFEST_DIR"), "/shaders/spv/polyline_vert.spv"));
+const FRAGMENT_SHADER: &[u8] = include_bytes!(concat!(env!("CARGO_MANIFEST_DIR"), "/shaders/spv/polyline_frag.spv"));
+
+/// Vertices `shaders/polyline.vert` expands every segment into
+const SEGMENT_VERTICES: u32 = 12;
+
+/// Shape of the corner between two segments
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum LineJoin {
+    /// Outer edges meet in a point, beveled past [`Polyline::miter_limit`]
+    #[default]
+    Miter,
+    /// Arc around the corner
+    Round,
+    /// Outer corners joined by a straight edge
+    Bevel,
+}
+
+/// Shape of both ends of the line
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum LineCap {
+    /// Ends exactly at the first and last points
+    #[default]
+    Butt,
+    /// Extends half the width past the ends
+    Square,
+    /// Half circles around the ends
+    Round,
+}
+
+/// Point in the layout of `shaders/polyline.vert`
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Pod, Zeroable)]
+struct PolylinePoint {
+    pos: Vec3,
+    /// Distance from the first point along the line
+    distance: f32,
+}
+
+/// Push constants in the layout of `shaders/polyline.vert`
+#[repr(C)]
+#[derive(Clone, Copy, Pod, Zeroable)]
+struct Push {
+    header: PushHeader,
+    width: f32,
+    color: [f32; 4],
+    dash: [f32; 2],
+    join: f32,
+    cap: f32,
+    miter_limit: f32,
+}
+
+/// Parameters of a new [`Polyline`]
+pub struct PolylineDesc<'a> {
+    points: &'a [Vec3],
+    width: f32,
+    color: [f32; 4],
+    join: LineJoin,
+    cap: LineCap,
+    dash: Option<[f32; 2]>,
+}
+
+impl<'a> PolylineDesc<'a> {
+    /// White line 1 pixel wide through `points`, which may be empty
+    pub fn new(points: &'a [Vec3]) -> Self {
+        Self {
+            points,
+            width: 1.0,
+            color: [1.0; 4],
+            join: LineJoin::default(),
+            cap: LineCap::default(),
+            dash: None,
+        }
+    }
+
+    /// Width in pixels
+    pub fn width(mut self, width: f32) -> Self {
+        self.width = width;
+        self
+    }
+
+    /// Linear RGBA color
+    pub fn color(mut self, color: [f32; 4]) -> Self {
+        self.color = color;
+        self
+    }
+
+    /// Shape of the corners
+    pub fn join(mut self, join: LineJoin) -> Self {
+        self.join = join;
+        self
+    }
+
+    /// Shape of the ends
+    pub fn cap(mut self, cap: LineCap) -> Self {
+        self.cap = cap;
+        self
+    }
+
+    /// Alternate `dash` drawn and `gap` skipped world units along the line
+    pub fn dash(mut self, dash: f32, gap: f32) -> Self {
+        self.dash = Some([dash, gap]);
+        self
+    }
+}
+
+/// Line of constant width in pixels through points in world space
+///
+/// Segments are expanded into screen-space quads with anti-aliased edges,
+/// joins and caps, see [`PassContext::draw_polyline`]. Points are kept on
+/// the CPU and copied into the buffer of every frame in flight like a
+/// dynamic [`Mesh`](crate::Mesh), so live data can be appended every frame
+///
+/// # Example
+/// ```ignore
+/// let plot = world.create::<Polyline>(PolylineDesc::new(&[]).width(2.0).join(LineJoin::Round))?;
+///
+/// // every frame
+/// world.get_mut(&plot).push(&[[time, value, 0.0]]);
+///
+/// world.draw_frame(|graph| {
+///     graph.add_pass(PresentPass::new("Plot", |_| {}, move |ctx, _| unsafe {
+///         ctx.draw_polyline(&plot);
+///     }));
+/// })?;
+/// ```
+///
+/// [`PassContext::draw_polyline`]: crate::frame_graph::PassContext::draw_polyline
+pub struct Polyline {
+    /// Width in pixels
+    pub width: f32,
+    /// Linear RGBA color
+    pub color: [f32; 4],
+    /// Shape of the corners
+    pub join: LineJoin,
+    /// Shape of the ends
+    pub cap: LineCap,
+    /// Dash and gap lengths in world units, solid with `None`
+    pub dash: Option<[f32; 2]>,
+    /// Largest ratio of miter length to width before a miter join is
+    /// beveled, `4.0` by default
+    pub miter_limit: f32,
+    /// Points with the first and last stored twice, see `shaders/polyline.vert`
+    points: MeshBuffer,
+    count: u32,
+    last: Option<PolylinePoint>,
+    frame_count: usize,
+}
+
+impl Polyline {
+    /// Append points after the last one
+    pub fn push(&mut self, points: &[Vec3]) {
+        let Some(&first) = points.first() else {
+            return;
+        };
+
+        let mut last = self.last.unwrap_or(PolylinePoint { pos: first, distance: 0.0 });
+        let mut data = Vec::with_capacity(points.len() + 2);
+
+        if self.last.is_none() {
+            data.push(last);
+        }
+
+        for &pos in points {
+            last = PolylinePoint {
+                pos,
+                distance: last.distance + length(sub(pos, last.pos)),
+            };
+            data.push(last);
+        }
+        data.push(last);
+
+        // The new points replace the copy of the old last point
+        let start = match self.count {
+            0 => 0,
+            count => count as usize + 1,
+        };
+
+        let mut retired = vec![];
+        self.points.write(
+            start,
+            bytemuck::cast_slice(&data),
+            size_of::<PolylinePoint>(),
+            self.frame_count,
+            &mut retired,
+        );
+        debug_assert!(retired.is_empty(), "Polyline buffers are always dynamic");
+
+        self.count += points.len() as u32;
+        self.last = Some(last);
+    }
+
+    /// Replace every point
+    pub fn set_points(&mut self, points: &[Vec3]) {
+        self.clear();
+        self.push(points);
+    }
+
+    /// Remove every point
+    pub fn clear(&mut self) {
+        self.points.replace(&[]);
+        self.count = 0;
+        self.last = None;
+    }
+
+    /// Number of points
+    pub fn len(&self) -> u32 {
+        self.count
+    }
+
+    /// No points to draw
+    pub fn is_empty(&self) -> bool {
+        self.count == 0
+    }
+
+    /// Length along every segment in world units
+    pub fn length(&self) -> f32 {
+        self.last.map_or(0.0, |last| last.distance)
+    }
+
+    fn push_constants(&self, transform: Option<u32>) -> Push {
+        Push {
+            header: PushHeader::new(transform, [0; 8]),
+            width: self.width,
+            color: self.color,
+            dash: self.dash.unwrap_or_default(),
+            join: self.join as u32 as f32,
+            cap: self.cap as u32 as f32,
+            miter_limit: self.miter_limit,
+        }
+    }
+}
+
+impl Destroy for Polyline {
+    fn destroy(key: ResourceKey, _ctx: Weak<RenderContext>, resources: Weak<Resources>) {
+        let Some(resources) = resources.upgrade() else {
+            return;
+        };
+
+        let mut store = resources.polylines.write();
+        if let Some(line) = store.pool.remove(key) {
+            let frames = store.frame_count;
+            store.retired.extend(frames, line.points.into_buffers());
+        }
+    }
+}
+
+impl Create for Polyline {
+    type Desc<'a> = PolylineDesc<'a>;
+    fn create(ctx: &Arc<RenderContext>, resources: &Arc<Resources>, desc: Self::Desc<'_>) -> VulkanResult<Res<Self>> {
+        let frame_count = ctx.frame_count();
+        let mut line = Polyline {
+            width: desc.width,
+            color: desc.color,
+            join: desc.join,
+            cap: desc.cap,
+            dash: desc.dash,
+            miter_limit: 4.0,
+            points: MeshBuffer::new_dynamic(vk::BufferUsageFlags::VERTEX_BUFFER, size_of::<PolylinePoint>(), frame_count),
+            count: 0,
+            last: None,
+            frame_count,
+        };
+        line.push(desc.points);
+
+        Ok(resources
+            .polylines
+            .write()
+            .pool
+            .insert(Arc::downgrade(ctx), Arc::downgrade(resources), line))
+    }
+}
+
+impl Get for Polyline {
+    fn get<'a>(resources: &'a Resources, res: &Res<Self>) -> Ref<'a, Self> {
+        let store = resources.polylines.try_read().expect("Polylines are already borrowed mutably");
+        Ref(parking_lot::RwLockReadGuard::map(store, |store| store.pool.get(res)))
+    }
+}
+
+impl GetMut for Polyline {
+    fn get_mut<'a>(resources: &'a Resources, res: &Res<Self>) -> RefMut<'a, Self> {
+        let store = resources.polylines.try_write().expect("Polylines are already borrowed");
+        RefMut(parking_lot::RwLockWriteGuard::map(store, |store| store.pool.get_mut(res)))
+    }
+}
+
+/// Polylines with the pipeline drawing them
+pub struct PolylineStore {
+    pub(crate) pool: Pool<Polyline>,
+    retired: Retired<GpuBuffer>,
+    frame_count: usize,
+    layout: PipelineLayout,
+    pipeline: GraphicsPipeline,
+}
+
+impl PolylineStore {
+    pub fn new(device: &Device, frame_count: usize, render_pass: vk::RenderPass, set_layouts: Vec<vk::DescriptorSetLayout>) -> VulkanResult<Self> {
+        // The layout of every raster pipeline, set 1 holds the camera and
+        // transforms
+        let layout = PipelineLayoutBuilder::new(device)
+            .set_layouts(set_layouts)
+            .push_constant(vec![vk::PushConstantRange::default()
+                .offset(0)
+                .size(128)
+                .stage_flags(vk::ShaderStageFlags::VERTEX | vk::ShaderStageFlags::FRAGMENT)])
+            .build()?;
+
+        let spv = read_shader_from_bytes(VERTEX_SHADER).expect("Invalid polyline vertex shader");
+        let vertex = ShaderBuilder::new(device).bytecode(&spv).build()?;
+        let spv = read_shader_from_bytes(FRAGMENT_SHADER).expect("Invalid polyline fragment shader");
+        let fragment = ShaderBuilder::new(device).bytecode(&spv).build()?;
+
+        // prev, a, b and next are the same buffer read one point apart
+        let stride = size_of::<PolylinePoint>() as u32;
+        let bindings = (0..4)
+            .map(|binding| vk::VertexInputBindingDescription {
+                binding,
+                stride,
+                input_rate: vk::VertexInputRate::INSTANCE,
+            })
+            .collect::<Vec<_>>();
+        let attributes = (0..4)
+            .map(|location| vk::VertexInputAttributeDescription {
+                location,
+                binding: location,
+                format: vk::Format::R32G32B32A32_SFLOAT,
+                offset: 0,
+            })
+            .collect::<Vec<_>>();
+
+        let vertex_input_info = vk::PipelineVertexInputStateCreateInfo::default()
+            .vertex_binding_descriptions(&bindings)
+            .vertex_attribute_descriptions(&attributes);
+
+        let color_blend = vk::PipelineColorBlendAttachmentState::default()
+            .color_write_mask(vk::ColorComponentFlags::RGBA)
+            .blend_enable(true)
+            .src_color_blend_factor(vk::BlendFactor::SRC_ALPHA)
+            .dst_color_blend_factor(vk::BlendFactor::ONE_MINUS_SRC_ALPHA)
+            .color_blend_op(vk::BlendOp::ADD)
+            .src_alpha_blend_factor(vk::BlendFactor::ONE)
+            .dst_alpha_blend_factor(vk::BlendFactor::ONE_MINUS_SRC_ALPHA)
+            .alpha_blend_op(vk::BlendOp::ADD);
+
+        // Viewport and scissors are dynamic, set for every draw. Depth is
+        // tested with the reversed-Z compare and never written
+        let pipeline = GraphicsPipelineBuilder::new(device)
+            .vertex_shader(vertex.raw)
+            .fragment_shader(fragment.raw)
+            .render_pass(render_pass)
+            .pipeline_layout(layout.raw)
+            .viewport(vec![vk::Viewport::default().width(1.0).height(1.0).max_depth(1.0)])
+            .scissors(vec![vk::Rect2D::default()])
+            .input_assembly(vk::PipelineInputAssemblyStateCreateInfo::default().topology(vk::PrimitiveTopology::TRIANGLE_LIST))
+            .rasterization(
+                vk::PipelineRasterizationStateCreateInfo::default()
+                    .polygon_mode(vk::PolygonMode::FILL)
+                    .line_width(1.0)
+                    .cull_mode(vk::CullModeFlags::NONE),
+            )
+            .multisampling(vk::PipelineMultisampleStateCreateInfo::default().rasterization_samples(vk::SampleCountFlags::TYPE_1))
+            .color_blending(vk::PipelineColorBlendStateCreateInfo::default().attachments(&[color_blend]))
+            .dynamic_state(vec![vk::DynamicState::VIEWPORT, vk::DynamicState::SCISSOR])
+            .vertex_input_info(vertex_input_info)
+            .build()?;
+
+        Ok(Self {
+            pool: Pool::new(),
+            retired: Retired::default(),
+            frame_count,
+            layout,
+            pipeline,
+        })
+    }
+
+    /// Copy new points into the buffers of `frame` and release buffers no
+    /// frame in flight can use anymore, called once per frame
+    pub fn update(&mut self, device: &Device, frame: usize) -> VulkanResult<()> {
+        profiling::scope!("PolylineStore::update");
+
+        self.retired.update(device);
+
+        for line in self.pool.slots.values_mut() {
+            line.points.prepare(device, frame)?;
+        }
+
+        Ok(())
+    }
+
+    /// Draw every segment of `line` over the whole target, must be inside
+    /// the render pass
+    ///
+    /// `sets` are the bindless and per-frame sets of the frame, `transform`
+    /// is the index of the world matrix or `None` for identity
+    #[allow(clippy::too_many_arguments)]
+    pub(crate) unsafe fn draw(
+        &self,
+        device: &ash::Device,
+        cbuf: vk::CommandBuffer,
+        frame: usize,
+        resolution: vk::Extent2D,
+        sets: &[vk::DescriptorSet],
+        line: &Polyline,
+        transform: Option<u32>,
+    ) {
+        let Some(buffer) = line.points.raw(frame) else {
+            return;
+        };
+
+        if line.count < 2 {
+            return;
+        }
+
+        let viewport = vk::Viewport::default()
+            .width(resolution.width as f32)
+            .height(resolution.height as f32)
+            .max_depth(1.0);
+        let scissor = vk::Rect2D::default().extent(resolution);
+        let stride = size_of::<PolylinePoint>() as u64;
+
+        device.cmd_bind_pipeline(cbuf, vk::PipelineBindPoint::GRAPHICS, self.pipeline.raw);
+        device.cmd_bind_descriptor_sets(cbuf, vk::PipelineBindPoint::GRAPHICS, self.layout.raw, 0, sets, &[]);
+        device.cmd_bind_vertex_buffers(cbuf, 0, &[buffer; 4], &[0, stride, stride * 2, stride * 3]);
+        device.cmd_set_viewport(cbuf, 0, &[viewport]);
+        device.cmd_set_scissor(cbuf, 0, &[scissor]);
+        device.cmd_push_constants(
+            cbuf,
+            self.layout.raw,
+            vk::ShaderStageFlags::VERTEX | vk::ShaderStageFlags::FRAGMENT,
+            0,
+            bytemuck::bytes_of(&line.push_constants(transform)),
+        );
+        device.cmd_draw(cbuf, SEGMENT_VERTICES, line.count - 1, 0, 0);
+    }
+
+    pub fn destroy(&mut self, device: &Device) {
+        self.retired.destroy(device);
+        for (_, line) in self.pool.slots.drain() {
+            for mut buffer in line.points.into_buffers() {
+                buffer.destroy(device);
+            }
+        }
+        self.pipeline.destroy(device);
+        unsafe {
+            device.destroy_pipeline_layout(self.layout.raw, None);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn line() -> Polyline {
+        Polyline {
+            width: 1.0,
+            color: [1.0; 4],
+            join: LineJoin::Miter,
+            cap: LineCap::Butt,
+            dash: None,
+            miter_limit: 4.0,
+            points: MeshBuffer::new_dynamic(vk::BufferUsageFlags::VERTEX_BUFFER, size_of::<PolylinePoint>(), 2),
+            count: 0,
+            last: None,
+            frame_count: 2,
+        }
+    }
+
+    fn stored(line: &Polyline) -> Vec<PolylinePoint> {
+        let MeshBuffer::Dynamic(dynamic) = &line.points else {
+            unreachable!()
+        };
+        bytemuck::cast_slice(dynamic.data()).to_vec()
+    }
+
+    #[test]
+    fn ends_are_stored_twice() {
+        let mut line = line();
+        line.push(&[[0.0; 3], [3.0, 4.0, 0.0]]);
+        line.push(&[[3.0, 5.0, 0.0]]);
+
+        let points = stored(&line);
+        let distances = points.iter().map(|point| point.distance).collect::<Vec<_>>();
+
+        assert_eq!(line.len(), 3);
+        assert_eq!(distances, vec![0.0, 0.0, 5.0, 6.0, 6.0]);
+        assert_eq!(points[0], points[1]);
+        assert_eq!(points[3], points[4]);
+        assert_eq!(line.length(), 6.0);
+    }
+
+    #[test]
+    fn set_points_restarts_the_line() {
+        let mut line = line();
+        line.push(&[[0.0; 3], [1.0, 0.0, 0.0], [2.0, 0.0, 0.0]]);
+        line.set_points(&[[5.0; 3]]);
+
+        assert_eq!(line.len(), 1);
+        assert_eq!(stored(&line).len(), 3);
+        assert_eq!(line.length(), 0.0);
+    }
+}
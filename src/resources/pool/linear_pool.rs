@@ -1,4 +1,5 @@
 use std::marker::PhantomData;
+use std::ops::Range;
 use std::sync::atomic::AtomicUsize;
 use std::sync::{Arc, Weak};
 
@@ -7,10 +8,25 @@ use slotmap::SlotMap;
 use crate::render_context::RenderContext;
 use crate::resources::{Destroy, Res, ResourceKey, Resources};
 
+/// Index change made by [`LinearPool::compact`]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Moved {
+    /// Old index, no longer used
+    pub from: usize,
+    /// New index
+    pub to: usize,
+}
+
+/// Values stored contiguously and addressed by index
+///
+/// Indices of removed values are reused by later inserts. The index of a value
+/// is stable until [`LinearPool::compact`] fills the holes
 pub struct LinearPool<T: Destroy> {
-    current_index: usize,
     slots: SlotMap<ResourceKey, usize>,
     data: Vec<T>,
+    /// Owner of every index, `None` for released ones
+    keys: Vec<Option<ResourceKey>>,
+    free: Vec<usize>,
     _marker: PhantomData<T>,
 }
 
@@ -18,16 +34,25 @@ impl<T: Destroy> LinearPool<T> {
     pub fn new(size: usize) -> Self {
         Self {
             slots: SlotMap::with_key(),
-            current_index: 0,
             data: Vec::with_capacity(size),
+            keys: Vec::with_capacity(size),
+            free: Vec::new(),
             _marker: PhantomData,
         }
     }
 
     pub fn insert(&mut self, ctx: Weak<RenderContext>, resources: Weak<Resources>, value: T) -> Res<T> {
-        let key = self.slots.insert(self.current_index);
-        self.data.push(value);
-        self.current_index += 1;
+        let index = if let Some(index) = self.free.pop() {
+            self.data[index] = value;
+            index
+        } else {
+            self.data.push(value);
+            self.keys.push(None);
+            self.data.len() - 1
+        };
+
+        let key = self.slots.insert(index);
+        self.keys[index] = Some(key);
         Res {
             key,
             ref_count: Arc::new(AtomicUsize::new(1)),
@@ -37,6 +62,73 @@ impl<T: Destroy> LinearPool<T> {
         }
     }
 
+    /// Release the index of `key`, the stored value stays until it is reused
+    pub fn remove(&mut self, key: ResourceKey) -> Option<usize> {
+        let index = self.slots.remove(key)?;
+        self.keys[index] = None;
+        self.free.push(index);
+        Some(index)
+    }
+
+    /// Move values from the end into released indices and shrink the pool
+    ///
+    /// Returns every move in order of increasing `to`, values at `to` have to
+    /// be re-read by anything that mirrors the pool by index
+    pub fn compact(&mut self) -> Vec<Moved> {
+        let mut moves = Vec::new();
+        let (mut lo, mut hi) = (0, self.data.len());
+
+        loop {
+            while lo < hi && self.keys[lo].is_some() {
+                lo += 1;
+            }
+            while hi > lo && self.keys[hi - 1].is_none() {
+                hi -= 1;
+            }
+            if lo >= hi {
+                break;
+            }
+
+            // `lo` is released and `hi - 1` is alive
+            let from = hi - 1;
+            self.data.swap(lo, from);
+            self.keys.swap(lo, from);
+
+            let key = self.keys[lo].expect("Moved index must be alive");
+            self.slots[key] = lo;
+            moves.push(Moved { from, to: lo });
+        }
+
+        let len = self.slots.len();
+        self.data.truncate(len);
+        self.keys.truncate(len);
+        self.free.clear();
+
+        moves
+    }
+
+    /// Merge destinations of `moves` into ranges of consecutive indices
+    pub fn moved_ranges(moves: &[Moved]) -> Vec<Range<usize>> {
+        let mut ranges: Vec<Range<usize>> = Vec::new();
+        for moved in moves {
+            match ranges.last_mut() {
+                Some(range) if range.end == moved.to => range.end += 1,
+                _ => ranges.push(moved.to..moved.to + 1),
+            }
+        }
+        ranges
+    }
+
+    /// Number of released indices below [`LinearPool::len`]
+    pub fn free_count(&self) -> usize {
+        self.free.len()
+    }
+
+    /// Number of indices, alive and released
+    pub fn len(&self) -> usize {
+        self.data.len()
+    }
+
     pub fn get(&self, res: &Res<T>) -> &T {
         let index = self.slots.get(res.key).expect("Resource not found");
         &self.data[*index]
@@ -48,10 +140,98 @@ impl<T: Destroy> LinearPool<T> {
     }
 
     pub fn index(&self, res: &Res<T>) -> usize {
-        *self.slots.get(res.key).expect("Resource not found")
+        self.index_of(res.key).expect("Resource not found")
+    }
+
+    pub fn index_of(&self, key: ResourceKey) -> Option<usize> {
+        self.slots.get(key).copied()
     }
 
     pub fn as_slice(&self) -> &[T] {
         &self.data
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct Value(u32);
+
+    impl Destroy for Value {
+        fn destroy(_key: ResourceKey, _ctx: Weak<RenderContext>, _resources: Weak<Resources>) {}
+    }
+
+    /// Pool of `count` values numbered by their first index
+    fn pool(count: u32) -> (LinearPool<Value>, Vec<Res<Value>>) {
+        let mut pool = LinearPool::new(4);
+        let handles = (0..count).map(|i| pool.insert(Weak::new(), Weak::new(), Value(i))).collect();
+        (pool, handles)
+    }
+
+    fn values(pool: &LinearPool<Value>) -> Vec<u32> {
+        pool.as_slice().iter().map(|value| value.0).collect()
+    }
+
+    #[test]
+    fn released_indices_are_reused() {
+        let (mut pool, handles) = pool(3);
+        assert_eq!(pool.remove(handles[1].key), Some(1));
+        assert_eq!(pool.remove(handles[1].key), None);
+
+        let new = pool.insert(Weak::new(), Weak::new(), Value(7));
+        assert_eq!(pool.index(&new), 1);
+        assert_eq!(values(&pool), [0, 7, 2]);
+    }
+
+    #[test]
+    fn compact_fills_holes_from_the_end() {
+        // Holes at the start and in the middle
+        let (mut pool, handles) = pool(6);
+        pool.remove(handles[0].key);
+        pool.remove(handles[2].key);
+        pool.remove(handles[3].key);
+
+        let moves = pool.compact();
+        assert_eq!(moves, [Moved { from: 5, to: 0 }, Moved { from: 4, to: 2 }]);
+        assert_eq!(values(&pool), [5, 1, 4]);
+        assert_eq!((pool.len(), pool.free_count()), (3, 0));
+        assert_eq!([1, 4, 5].map(|i| pool.index(&handles[i])), [1, 2, 0]);
+    }
+
+    #[test]
+    fn compact_drops_holes_at_the_end() {
+        let (mut pool, handles) = pool(4);
+        pool.remove(handles[2].key);
+        pool.remove(handles[3].key);
+
+        assert_eq!(pool.compact(), []);
+        assert_eq!(values(&pool), [0, 1]);
+
+        // Released indices are not reused past the new end
+        let new = pool.insert(Weak::new(), Weak::new(), Value(9));
+        assert_eq!(pool.index(&new), 2);
+    }
+
+    #[test]
+    fn compact_of_empty_pool() {
+        let (mut pool, handles) = pool(2);
+        pool.remove(handles[0].key);
+        pool.remove(handles[1].key);
+
+        assert_eq!(pool.compact(), []);
+        assert_eq!(pool.len(), 0);
+    }
+
+    #[test]
+    fn moved_ranges_merge_consecutive_destinations() {
+        let moves = [
+            Moved { from: 9, to: 1 },
+            Moved { from: 8, to: 2 },
+            Moved { from: 7, to: 3 },
+            Moved { from: 6, to: 5 },
+        ];
+        assert_eq!(LinearPool::<Value>::moved_ranges(&moves), [1..4, 5..6]);
+        assert!(LinearPool::<Value>::moved_ranges(&[]).is_empty());
+    }
+}
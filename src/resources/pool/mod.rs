@@ -36,7 +36,7 @@ impl<T: Destroy> Pool<T> {
         self.slots.get_mut(res.key).expect("Resource not found")
     }
 
-    pub fn remove(&mut self, key: ResourceKey) {
-        self.slots.remove(key);
+    pub fn remove(&mut self, key: ResourceKey) -> Option<T> {
+        self.slots.remove(key)
     }
 }
@@ -0,0 +1,90 @@
+//! Resources dropped while frames in flight may still read them
+
+use crate::core::{Device, GpuBuffer};
+
+/// Dropped resources with the number of frames left before release
+///
+/// Stores push what they drop with the number of frames in flight and call
+/// [`Retired::update`] once per frame, an item is released on the update
+/// after its frames ran out
+pub(crate) struct Retired<T> {
+    items: Vec<(usize, T)>,
+}
+
+impl<T> Default for Retired<T> {
+    fn default() -> Self {
+        Self { items: vec![] }
+    }
+}
+
+impl<T> Retired<T> {
+    /// Release `item` after `frames` more frames
+    pub fn push(&mut self, frames: usize, item: T) {
+        self.items.push((frames, item));
+    }
+
+    /// Release every item after `frames` more frames
+    pub fn extend(&mut self, frames: usize, items: impl IntoIterator<Item = T>) {
+        self.items.extend(items.into_iter().map(|item| (frames, item)));
+    }
+
+    /// Count down one frame and pass the items whose frames ran out to
+    /// `release`
+    pub fn update_with(&mut self, mut release: impl FnMut(T)) {
+        for (frames, item) in std::mem::take(&mut self.items) {
+            match frames.checked_sub(1) {
+                Some(frames) => self.items.push((frames, item)),
+                None => release(item),
+            }
+        }
+    }
+
+    /// Pass every item to `release` at once, when no frame is in flight
+    pub fn destroy_with(&mut self, release: impl FnMut(T)) {
+        self.items.drain(..).map(|(_, item)| item).for_each(release);
+    }
+}
+
+impl Retired<GpuBuffer> {
+    /// Destroy buffers no frame in flight can use anymore, called once per
+    /// frame
+    pub fn update(&mut self, device: &Device) {
+        self.update_with(|mut buffer| buffer.destroy(device));
+    }
+
+    pub fn destroy(&mut self, device: &Device) {
+        self.destroy_with(|mut buffer| buffer.destroy(device));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn items_wait_their_frames() {
+        let mut retired = Retired::default();
+        retired.push(0, 'a');
+        retired.extend(2, ['b', 'c']);
+
+        let updates = (0..4)
+            .map(|_| {
+                let mut released = String::new();
+                retired.update_with(|item| released.push(item));
+                released
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(updates, ["a", "", "bc", ""]);
+    }
+
+    #[test]
+    fn destroy_releases_everything() {
+        let mut retired = Retired::default();
+        retired.extend(3, [1, 2]);
+
+        let mut released = vec![];
+        retired.destroy_with(|item| released.push(item));
+        retired.update_with(|item| released.push(item));
+        assert_eq!(released, [1, 2]);
+    }
+}
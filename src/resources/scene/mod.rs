@@ -0,0 +1,697 @@
+use std::ops::Range;
+use std::sync::{Arc, Weak};
+
+use ash::vk;
+use bytemuck::{Pod, Zeroable};
+
+use crate::core::{
+    read_shader_from_bytes, ComputePipeline, ComputePipelineBuilder, Device, GpuBuffer, GpuBufferBuilder, LogicalDeviceError, PbrVertex,
+    PipelineLayout, PipelineLayoutBuilder, ShaderBuilder, VulkanError, VulkanResult,
+};
+use crate::math::Sphere;
+use crate::per_frame::{PerFrameDescriptors, DRAW_COMMANDS_BINDING, DRAW_COUNT_BINDING, OBJECTS_BINDING};
+use crate::render_context::RenderContext;
+use crate::resources::{Create, Destroy, Get, Pool, Ref, Res, ResourceKey, Resources, Retired, Transform, TransformPool};
+
+/// Objects every frame buffer holds before growing
+const INITIAL_OBJECTS: usize = 1024;
+
+/// Vertices of the shared vertex buffer before growing
+const INITIAL_VERTICES: u32 = 64 * 1024;
+
+/// Indices of the shared index buffer before growing
+const INITIAL_INDICES: u32 = 256 * 1024;
+
+/// Invocations of one cull workgroup, see `shaders/cull.comp`
+const CULL_GROUP_SIZE: u32 = 64;
+
+const CULL_SHADER: &[u8] = include_bytes!(concat!(env!("CARGO_MANIFEST_DIR"), "/shaders/spv/cull_comp.spv"));
+
+/// Parameters of a new [`SceneMesh`]
+pub struct SceneMeshDesc<'a> {
+    vertices: &'a [PbrVertex],
+    indices: &'a [u32],
+}
+
+impl<'a> SceneMeshDesc<'a> {
+    /// Triangle list, indices are relative to `vertices`
+    pub fn new(vertices: &'a [PbrVertex], indices: &'a [u32]) -> Self {
+        Self { vertices, indices }
+    }
+}
+
+/// Mesh packed into the vertex and index buffers shared by the GPU scene
+///
+/// Ranges of dropped meshes are reused once no frame in flight reads them.
+/// Creation fails on devices without the `drawIndirectFirstInstance` feature,
+/// indirect draws select objects by their first instance
+pub struct SceneMesh {
+    vertices: Range<u32>,
+    indices: Range<u32>,
+    /// Local bounding sphere, xyz center and w radius
+    sphere: [f32; 4],
+}
+
+impl SceneMesh {
+    /// Local bounding sphere
+    pub fn bounding_sphere(&self) -> Sphere {
+        let [x, y, z, radius] = self.sphere;
+        Sphere::new([x, y, z], radius)
+    }
+
+    /// Number of indices
+    pub fn index_count(&self) -> u32 {
+        self.indices.end - self.indices.start
+    }
+}
+
+/// Parameters of a new [`SceneObject`]
+pub struct SceneObjectDesc<'a> {
+    mesh: &'a Res<SceneMesh>,
+    transform: &'a Res<Transform>,
+}
+
+impl<'a> SceneObjectDesc<'a> {
+    /// `mesh` placed by `transform` and its parents
+    pub fn new(mesh: &'a Res<SceneMesh>, transform: &'a Res<Transform>) -> Self {
+        Self { mesh, transform }
+    }
+}
+
+/// Mesh placed by a transform, drawn by [`PassContext::draw_indirect_count`]
+/// when its bounding sphere is inside the camera frustum
+///
+/// # Example
+/// ```ignore
+/// let mesh = world.create::<SceneMesh>(SceneMeshDesc::new(&vertices, &indices))?;
+/// let objects = transforms
+///     .iter()
+///     .map(|transform| world.create::<SceneObject>(SceneObjectDesc::new(&mesh, transform)))
+///     .collect::<VulkanResult<Vec<_>>>()?;
+///
+/// world.draw_frame(|graph| {
+///     graph.add_pass(PresentPass::new("Scene", |_| {}, move |ctx, _| unsafe {
+///         ctx.bind_pipeline(&pipeline);
+///         ctx.draw_indirect_count();
+///     }));
+/// })?;
+/// ```
+///
+/// [`PassContext::draw_indirect_count`]: crate::frame_graph::PassContext::draw_indirect_count
+pub struct SceneObject {
+    /// Index in the object buffer, read as `gl_InstanceIndex` by shaders
+    index: u32,
+    mesh: Res<SceneMesh>,
+    transform: Res<Transform>,
+}
+
+impl SceneObject {
+    /// Drawn mesh
+    pub fn mesh(&self) -> &Res<SceneMesh> {
+        &self.mesh
+    }
+
+    /// Transform placing the mesh
+    pub fn transform(&self) -> &Res<Transform> {
+        &self.transform
+    }
+
+    /// Index in the object buffer at set 1, binding 3
+    pub fn index(&self) -> u32 {
+        self.index
+    }
+}
+
+/// Object as seen by shaders at set 1, binding 3
+#[repr(C)]
+#[derive(Clone, Copy, Pod, Zeroable)]
+struct ObjectData {
+    sphere: [f32; 4],
+    first_index: u32,
+    /// Zero for released objects, the cull pass skips them
+    index_count: u32,
+    vertex_offset: i32,
+    transform_idx: u32,
+}
+
+impl ObjectData {
+    fn new(mesh: &SceneMesh, transform_idx: u32) -> Self {
+        Self {
+            sphere: mesh.sphere,
+            first_index: mesh.indices.start,
+            index_count: mesh.index_count(),
+            vertex_offset: mesh.vertices.start as i32,
+            transform_idx,
+        }
+    }
+}
+
+/// First-fit allocator of element ranges in a shared buffer
+#[derive(Default)]
+struct Ranges {
+    /// Released ranges sorted by start, never adjacent
+    free: Vec<Range<u32>>,
+    /// End of the highest allocated range
+    end: u32,
+}
+
+impl Ranges {
+    fn alloc(&mut self, len: u32) -> Range<u32> {
+        if let Some(slot) = self.free.iter().position(|range| range.end - range.start >= len) {
+            let start = self.free[slot].start;
+            self.free[slot].start += len;
+            if self.free[slot].is_empty() {
+                self.free.remove(slot);
+            }
+            return start..start + len;
+        }
+
+        let start = self.end;
+        self.end += len;
+        start..self.end
+    }
+
+    fn release(&mut self, range: Range<u32>) {
+        if range.is_empty() {
+            return;
+        }
+
+        let slot = self.free.partition_point(|free| free.start < range.start);
+        self.free.insert(slot, range);
+
+        // Merge with the next and the previous range
+        if slot + 1 < self.free.len() && self.free[slot].end == self.free[slot + 1].start {
+            self.free[slot].end = self.free.remove(slot + 1).end;
+        }
+        if slot > 0 && self.free[slot - 1].end == self.free[slot].start {
+            self.free[slot - 1].end = self.free.remove(slot).end;
+        }
+
+        if self.free.last().is_some_and(|last| last.end == self.end) {
+            self.end = self.free.pop().unwrap().start;
+        }
+    }
+}
+
+/// Vertex or index buffer shared by every [`SceneMesh`]
+struct SharedBuffer {
+    buffer: GpuBuffer,
+    ranges: Ranges,
+    /// Size of one element in bytes
+    stride: usize,
+    usage: vk::BufferUsageFlags,
+}
+
+impl SharedBuffer {
+    fn new(device: &Device, stride: usize, usage: vk::BufferUsageFlags, capacity: u32) -> VulkanResult<Self> {
+        Ok(Self {
+            buffer: Self::create_buffer(device, stride, usage, capacity)?,
+            ranges: Ranges::default(),
+            stride,
+            usage,
+        })
+    }
+
+    fn create_buffer(device: &Device, stride: usize, usage: vk::BufferUsageFlags, capacity: u32) -> VulkanResult<GpuBuffer> {
+        GpuBufferBuilder::cpu_only(device)
+            .size(capacity as u64 * stride as u64)
+            .usage(usage)
+            .build()
+    }
+
+    fn capacity(&self) -> usize {
+        self.buffer.allocation.as_ref().map_or(0, |allocation| allocation.size() as usize / self.stride)
+    }
+
+    /// Copy `data` into a free range, the buffer is replaced by one twice as
+    /// large when it does not fit and the old one retired for `frames` frames
+    fn insert<T: Pod>(
+        &mut self,
+        device: &Device,
+        data: &[T],
+        retired: &mut Retired<GpuBuffer>,
+        frames: usize,
+    ) -> VulkanResult<Range<u32>> {
+        let used = self.ranges.end as usize * self.stride;
+        let range = self.ranges.alloc(data.len() as u32);
+
+        if range.end as usize > self.capacity() {
+            let mut grown = Self::create_buffer(device, self.stride, self.usage, range.end.next_power_of_two())?;
+            if used > 0 {
+                grown.upload_data_at(0, &self.buffer.download_data(used))?;
+            }
+            retired.push(frames, std::mem::replace(&mut self.buffer, grown));
+        }
+
+        if !data.is_empty() {
+            self.buffer.upload_data_at(range.start as usize * self.stride, data)?;
+        }
+
+        Ok(range)
+    }
+}
+
+/// Buffers the cull pass of one frame in flight reads and writes
+struct FrameBuffers {
+    objects: GpuBuffer,
+    commands: GpuBuffer,
+    count: GpuBuffer,
+    /// Objects `objects` and `commands` hold
+    capacity: usize,
+    /// Version of the objects last uploaded
+    version: u64,
+}
+
+impl FrameBuffers {
+    fn new(device: &Device, capacity: usize) -> VulkanResult<Self> {
+        let objects = GpuBufferBuilder::cpu_only(device)
+            .size((capacity * size_of::<ObjectData>()) as u64)
+            .usage(vk::BufferUsageFlags::STORAGE_BUFFER)
+            .build()?;
+
+        let commands = GpuBufferBuilder::gpu_only(device)
+            .size((capacity * size_of::<vk::DrawIndexedIndirectCommand>()) as u64)
+            .usage(vk::BufferUsageFlags::STORAGE_BUFFER | vk::BufferUsageFlags::INDIRECT_BUFFER | vk::BufferUsageFlags::TRANSFER_DST)
+            .build()?;
+
+        let count = GpuBufferBuilder::gpu_only(device)
+            .size(size_of::<u32>() as u64)
+            .usage(vk::BufferUsageFlags::STORAGE_BUFFER | vk::BufferUsageFlags::INDIRECT_BUFFER | vk::BufferUsageFlags::TRANSFER_DST)
+            .build()?;
+
+        Ok(Self {
+            objects,
+            commands,
+            count,
+            capacity,
+            version: 0,
+        })
+    }
+
+    fn write_descriptors(&self, device: &Device, descriptors: &PerFrameDescriptors, frame: usize) {
+        let ty = vk::DescriptorType::STORAGE_BUFFER;
+        descriptors.write_frame_buffer(device, frame, OBJECTS_BINDING, ty, &self.objects);
+        descriptors.write_frame_buffer(device, frame, DRAW_COMMANDS_BINDING, ty, &self.commands);
+        descriptors.write_frame_buffer(device, frame, DRAW_COUNT_BINDING, ty, &self.count);
+    }
+
+    fn destroy(&mut self, device: &Device) {
+        self.objects.destroy(device);
+        self.commands.destroy(device);
+        self.count.destroy(device);
+    }
+}
+
+/// Meshes packed into shared vertex and index buffers and the objects placing
+/// them, drawn without per-object CPU work
+///
+/// Every frame a compute pass tests each object against the camera frustum
+/// and writes an indexed indirect draw for the visible ones. The draws read
+/// `objects[gl_InstanceIndex]` to find their transform
+pub struct GpuScene {
+    pub(crate) meshes: Pool<SceneMesh>,
+    pub(crate) objects: Pool<SceneObject>,
+    data: Vec<ObjectData>,
+    /// Released object indices
+    free: Vec<u32>,
+    /// Increased by every change of `data`
+    version: u64,
+    /// [`TransformPool::compactions`] the transform indices were read at
+    transform_layout: u64,
+    vertices: SharedBuffer,
+    indices: SharedBuffer,
+    frames: Vec<FrameBuffers>,
+    /// Replaced buffers
+    retired_buffers: Retired<GpuBuffer>,
+    /// Ranges of dropped meshes
+    retired_ranges: Retired<(Range<u32>, Range<u32>)>,
+    layout: PipelineLayout,
+    cull: ComputePipeline,
+    draw_indirect_count: Option<ash::khr::draw_indirect_count::Device>,
+    multi_draw: bool,
+    first_instance: bool,
+}
+
+impl GpuScene {
+    pub fn new(device: &Device, frame_count: usize, set_layouts: Vec<vk::DescriptorSetLayout>) -> VulkanResult<Self> {
+        let vertices = SharedBuffer::new(device, size_of::<PbrVertex>(), vk::BufferUsageFlags::VERTEX_BUFFER, INITIAL_VERTICES)?;
+        let indices = SharedBuffer::new(device, size_of::<u32>(), vk::BufferUsageFlags::INDEX_BUFFER, INITIAL_INDICES)?;
+
+        let frames = (0..frame_count)
+            .map(|_| FrameBuffers::new(device, INITIAL_OBJECTS))
+            .collect::<VulkanResult<Vec<_>>>()?;
+
+        let layout = PipelineLayoutBuilder::new(device)
+            .set_layouts(set_layouts)
+            .push_constant(vec![vk::PushConstantRange::default()
+                .offset(0)
+                .size(size_of::<u32>() as u32)
+                .stage_flags(vk::ShaderStageFlags::COMPUTE)])
+            .build()?;
+
+        let spv = read_shader_from_bytes(CULL_SHADER).expect("Invalid cull shader");
+        let shader = ShaderBuilder::new(device).bytecode(&spv).build()?;
+
+        let cull = ComputePipelineBuilder::new(device)
+            .shader(shader.raw)
+            .pipeline_layout(layout.raw)
+            .build()?;
+
+        Ok(Self {
+            meshes: Pool::new(),
+            objects: Pool::new(),
+            data: Vec::with_capacity(INITIAL_OBJECTS),
+            free: vec![],
+            version: 0,
+            transform_layout: 0,
+            vertices,
+            indices,
+            frames,
+            retired_buffers: Retired::default(),
+            retired_ranges: Retired::default(),
+            layout,
+            cull,
+            draw_indirect_count: device.draw_indirect_count.clone(),
+            multi_draw: device.multi_draw_indirect(),
+            first_instance: device.draw_indirect_first_instance(),
+        })
+    }
+
+    /// Point set 1 of every frame at its object, draw and count buffers
+    pub(crate) fn write_descriptors(&self, device: &Device, descriptors: &PerFrameDescriptors) {
+        for (frame, buffers) in self.frames.iter().enumerate() {
+            buffers.write_descriptors(device, descriptors, frame);
+        }
+    }
+
+    fn insert_mesh(&mut self, device: &Device, desc: &SceneMeshDesc<'_>) -> VulkanResult<SceneMesh> {
+        if !self.first_instance {
+            return Err(VulkanError::LogicalDevice(LogicalDeviceError::MissingFeature("drawIndirectFirstInstance")));
+        }
+
+        let frames = self.frames.len();
+        let vertices = self.vertices.insert(device, desc.vertices, &mut self.retired_buffers, frames)?;
+        let indices = self.indices.insert(device, desc.indices, &mut self.retired_buffers, frames)?;
+
+        Ok(SceneMesh {
+            vertices,
+            indices,
+            sphere: Sphere::from_points(desc.vertices.iter().map(|vertex| [vertex.pos[0], vertex.pos[1], vertex.pos[2]]))
+                .map_or([0.0; 4], |sphere| [sphere.center[0], sphere.center[1], sphere.center[2], sphere.radius]),
+        })
+    }
+
+    fn insert_object(&mut self, data: ObjectData) -> u32 {
+        self.version += 1;
+        if let Some(index) = self.free.pop() {
+            self.data[index as usize] = data;
+            return index;
+        }
+
+        self.data.push(data);
+        self.data.len() as u32 - 1
+    }
+
+    /// Release buffers and mesh ranges no frame in flight reads, refresh
+    /// transform indices moved by compaction and upload the objects of
+    /// `frame`, called once per frame
+    pub fn update(&mut self, device: &Device, descriptors: &PerFrameDescriptors, transforms: &TransformPool, frame: usize) -> VulkanResult<()> {
+        profiling::scope!("GpuScene::update");
+
+        self.retired_buffers.update(device);
+
+        self.retired_ranges.update_with(|(vertices, indices)| {
+            self.vertices.ranges.release(vertices);
+            self.indices.ranges.release(indices);
+        });
+
+        if self.transform_layout != transforms.compactions() {
+            self.transform_layout = transforms.compactions();
+            for object in self.objects.slots.values() {
+                self.data[object.index as usize].transform_idx = transforms.index(&object.transform);
+            }
+            self.version += 1;
+        }
+
+        let frame = frame % self.frames.len();
+        let buffers = &mut self.frames[frame];
+
+        if self.data.len() > buffers.capacity {
+            let mut grown = FrameBuffers::new(device, self.data.len().next_power_of_two())?;
+            std::mem::swap(buffers, &mut grown);
+            grown.destroy(device);
+            buffers.write_descriptors(device, descriptors, frame);
+        }
+
+        if buffers.version != self.version && !self.data.is_empty() {
+            buffers.objects.upload_data_at(0, &self.data)?;
+            buffers.version = self.version;
+        }
+
+        Ok(())
+    }
+
+    /// Record the cull pass of `frame`, must be outside a render pass
+    ///
+    /// `sets` are the bindless and per-frame sets of the frame
+    pub(crate) unsafe fn record_cull(&self, device: &ash::Device, cbuf: vk::CommandBuffer, frame: usize, sets: &[vk::DescriptorSet]) {
+        profiling::scope!("GpuScene::record_cull");
+
+        let buffers = &self.frames[frame % self.frames.len()];
+        let object_count = self.data.len() as u32;
+
+        // Stale draws past the count are skipped only by count draws
+        device.cmd_fill_buffer(cbuf, buffers.count.raw, 0, vk::WHOLE_SIZE, 0);
+        if self.draw_indirect_count.is_none() && object_count > 0 {
+            let size = object_count as u64 * size_of::<vk::DrawIndexedIndirectCommand>() as u64;
+            device.cmd_fill_buffer(cbuf, buffers.commands.raw, 0, size, 0);
+        }
+
+        let cleared = vk::MemoryBarrier::default()
+            .src_access_mask(vk::AccessFlags::TRANSFER_WRITE)
+            .dst_access_mask(vk::AccessFlags::SHADER_READ | vk::AccessFlags::SHADER_WRITE | vk::AccessFlags::INDIRECT_COMMAND_READ);
+
+        device.cmd_pipeline_barrier(
+            cbuf,
+            vk::PipelineStageFlags::TRANSFER,
+            vk::PipelineStageFlags::COMPUTE_SHADER | vk::PipelineStageFlags::DRAW_INDIRECT,
+            vk::DependencyFlags::empty(),
+            &[cleared],
+            &[],
+            &[],
+        );
+
+        if object_count == 0 {
+            return;
+        }
+
+        device.cmd_bind_pipeline(cbuf, vk::PipelineBindPoint::COMPUTE, self.cull.raw);
+        device.cmd_bind_descriptor_sets(cbuf, vk::PipelineBindPoint::COMPUTE, self.layout.raw, 0, sets, &[]);
+        device.cmd_push_constants(cbuf, self.layout.raw, vk::ShaderStageFlags::COMPUTE, 0, bytemuck::bytes_of(&object_count));
+        device.cmd_dispatch(cbuf, object_count.div_ceil(CULL_GROUP_SIZE), 1, 1);
+
+        let culled = vk::MemoryBarrier::default()
+            .src_access_mask(vk::AccessFlags::SHADER_WRITE)
+            .dst_access_mask(vk::AccessFlags::INDIRECT_COMMAND_READ);
+
+        device.cmd_pipeline_barrier(
+            cbuf,
+            vk::PipelineStageFlags::COMPUTE_SHADER,
+            vk::PipelineStageFlags::DRAW_INDIRECT,
+            vk::DependencyFlags::empty(),
+            &[culled],
+            &[],
+            &[],
+        );
+    }
+
+    /// Draw the objects visible in `frame` with the bound graphics pipeline
+    ///
+    /// Without `VK_KHR_draw_indirect_count` every object slot is drawn and
+    /// culled ones have no instances
+    pub(crate) unsafe fn draw(&self, device: &ash::Device, cbuf: vk::CommandBuffer, frame: usize) {
+        let max_draws = self.data.len() as u32;
+        if max_draws == 0 {
+            return;
+        }
+
+        let buffers = &self.frames[frame % self.frames.len()];
+        let stride = size_of::<vk::DrawIndexedIndirectCommand>() as u32;
+
+        device.cmd_bind_vertex_buffers(cbuf, 0, &[self.vertices.buffer.raw], &[0]);
+        device.cmd_bind_index_buffer(cbuf, self.indices.buffer.raw, 0, vk::IndexType::UINT32);
+
+        match &self.draw_indirect_count {
+            Some(loader) => {
+                loader.cmd_draw_indexed_indirect_count(cbuf, buffers.commands.raw, 0, buffers.count.raw, 0, max_draws, stride);
+            },
+            None if self.multi_draw => {
+                device.cmd_draw_indexed_indirect(cbuf, buffers.commands.raw, 0, max_draws, stride);
+            },
+            None => {
+                for draw in 0..max_draws {
+                    device.cmd_draw_indexed_indirect(cbuf, buffers.commands.raw, (draw * stride) as u64, 1, stride);
+                }
+            },
+        }
+    }
+
+    pub fn destroy(&mut self, device: &Device) {
+        self.retired_buffers.destroy(device);
+        for buffers in &mut self.frames {
+            buffers.destroy(device);
+        }
+        self.vertices.buffer.destroy(device);
+        self.indices.buffer.destroy(device);
+        self.cull.destroy(device);
+        unsafe {
+            device.destroy_pipeline_layout(self.layout.raw, None);
+        }
+    }
+}
+
+impl Destroy for SceneMesh {
+    fn destroy(key: ResourceKey, _ctx: Weak<RenderContext>, resources: Weak<Resources>) {
+        let Some(resources) = resources.upgrade() else {
+            return;
+        };
+
+        let mut scene = resources.scene.write();
+        if let Some(mesh) = scene.meshes.remove(key) {
+            let frames = scene.frames.len();
+            scene.retired_ranges.push(frames, (mesh.vertices, mesh.indices));
+        }
+    }
+}
+
+impl Create for SceneMesh {
+    type Desc<'a> = SceneMeshDesc<'a>;
+
+    fn create(ctx: &Arc<RenderContext>, resources: &Arc<Resources>, desc: Self::Desc<'_>) -> VulkanResult<Res<Self>> {
+        let mut scene = resources.scene.write();
+        let mesh = scene.insert_mesh(&ctx.device, &desc)?;
+        Ok(scene.meshes.insert(Arc::downgrade(ctx), Arc::downgrade(resources), mesh))
+    }
+}
+
+impl Get for SceneMesh {
+    fn get<'a>(resources: &'a Resources, res: &Res<Self>) -> Ref<'a, Self> {
+        let scene = resources.scene.try_read().expect("Scene is already borrowed mutably");
+        Ref(parking_lot::RwLockReadGuard::map(scene, |scene| scene.meshes.get(res)))
+    }
+}
+
+impl Destroy for SceneObject {
+    fn destroy(key: ResourceKey, _ctx: Weak<RenderContext>, resources: Weak<Resources>) {
+        let Some(resources) = resources.upgrade() else {
+            return;
+        };
+
+        // Dropped after the lock, the mesh and transform may be released too
+        let object = {
+            let mut scene = resources.scene.write();
+            let object = scene.objects.remove(key);
+            if let Some(object) = &object {
+                scene.data[object.index as usize] = ObjectData::zeroed();
+                scene.free.push(object.index);
+                scene.version += 1;
+            }
+            object
+        };
+
+        drop(object);
+    }
+}
+
+impl Create for SceneObject {
+    type Desc<'a> = SceneObjectDesc<'a>;
+
+    fn create(ctx: &Arc<RenderContext>, resources: &Arc<Resources>, desc: Self::Desc<'_>) -> VulkanResult<Res<Self>> {
+        let transform_idx = resources.transforms.read().index(desc.transform);
+
+        let mut scene = resources.scene.write();
+        let data = ObjectData::new(scene.meshes.get(desc.mesh), transform_idx);
+        let index = scene.insert_object(data);
+
+        Ok(scene.objects.insert(
+            Arc::downgrade(ctx),
+            Arc::downgrade(resources),
+            SceneObject {
+                index,
+                mesh: desc.mesh.clone(),
+                transform: desc.transform.clone(),
+            },
+        ))
+    }
+}
+
+impl Get for SceneObject {
+    fn get<'a>(resources: &'a Resources, res: &Res<Self>) -> Ref<'a, Self> {
+        let scene = resources.scene.try_read().expect("Scene is already borrowed mutably");
+        Ref(parking_lot::RwLockReadGuard::map(scene, |scene| scene.objects.get(res)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn free(ranges: &Ranges) -> Vec<(u32, u32)> {
+        ranges.free.iter().map(|range| (range.start, range.end)).collect()
+    }
+
+    #[test]
+    fn ranges_grow_at_the_end() {
+        let mut ranges = Ranges::default();
+
+        assert_eq!(ranges.alloc(4), 0..4);
+        assert_eq!(ranges.alloc(2), 4..6);
+        assert_eq!(ranges.end, 6);
+    }
+
+    #[test]
+    fn ranges_reuse_the_first_fit() {
+        let mut ranges = Ranges::default();
+        let [a, b, c, _] = [1, 5, 2, 1].map(|len| ranges.alloc(len));
+
+        ranges.release(a);
+        ranges.release(c);
+        assert_eq!(free(&ranges), [(0, 1), (6, 8)]);
+
+        // Too large for the first hole, taken from the second
+        assert_eq!(ranges.alloc(2), 6..8);
+        assert_eq!(free(&ranges), [(0, 1)]);
+
+        ranges.release(b);
+        assert_eq!(free(&ranges), [(0, 6)]);
+        assert_eq!(ranges.alloc(4), 0..4);
+        assert_eq!(free(&ranges), [(4, 6)]);
+    }
+
+    #[test]
+    fn released_ranges_merge() {
+        let mut ranges = Ranges::default();
+        let [a, b, c, _] = [2, 2, 2, 2].map(|len| ranges.alloc(len));
+
+        ranges.release(a);
+        ranges.release(c);
+        ranges.release(b);
+        assert_eq!(free(&ranges), [(0, 6)]);
+        assert_eq!(ranges.alloc(6), 0..6);
+        assert!(ranges.free.is_empty());
+    }
+
+    #[test]
+    fn released_tail_shrinks_the_end() {
+        let mut ranges = Ranges::default();
+        let [_, b, c] = [2, 3, 4].map(|len| ranges.alloc(len));
+
+        ranges.release(b);
+        ranges.release(c);
+        assert_eq!(ranges.end, 2);
+        assert!(ranges.free.is_empty());
+
+        ranges.release(5..5);
+        assert_eq!(ranges.alloc(1), 2..3);
+    }
+}
@@ -0,0 +1,234 @@
+//! Placement of glyphs along lines of text
+
+use std::collections::HashMap;
+
+use bytemuck::{Pod, Zeroable};
+
+/// Horizontal placement of lines around the anchor
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum TextAlign {
+    /// Lines start at the anchor
+    #[default]
+    Left,
+    /// Lines are centered on the anchor
+    Center,
+    /// Lines end at the anchor
+    Right,
+}
+
+/// Vertical placement of the text around the anchor
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum VerticalAlign {
+    /// Top of the first line at the anchor
+    Top,
+    /// Middle of the text at the anchor
+    Middle,
+    /// Baseline of the first line at the anchor
+    #[default]
+    Baseline,
+    /// Bottom of the last line at the anchor
+    Bottom,
+}
+
+/// Placement of one glyph, in ems with y down
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub(super) struct GlyphMetrics {
+    /// Distance to the next pen position
+    pub advance: f32,
+    /// Box of the field around the pen position, `[min x, min y, max x,
+    /// max y]`, empty for blank glyphs
+    pub rect: [f32; 4],
+    /// Box of the field in the atlas
+    pub uv: [f32; 4],
+}
+
+/// Everything layout needs from a font, in ems
+#[derive(Debug, Default)]
+pub(super) struct FontMetrics {
+    pub glyphs: HashMap<char, GlyphMetrics>,
+    /// Advance changes between two characters
+    pub kerning: HashMap<(char, char), f32>,
+    /// Height above the baseline
+    pub ascent: f32,
+    /// Depth below the baseline
+    pub descent: f32,
+    /// Distance between baselines
+    pub line_height: f32,
+    /// Drawn for characters the font has no glyph for
+    pub fallback: Option<char>,
+}
+
+impl FontMetrics {
+    fn glyph(&self, c: char) -> Option<&GlyphMetrics> {
+        self.glyphs
+            .get(&c)
+            .or_else(|| self.fallback.and_then(|fallback| self.glyphs.get(&fallback)))
+    }
+
+    fn kerning(&self, prev: Option<char>, c: char) -> f32 {
+        prev.and_then(|prev| self.kerning.get(&(prev, c)))
+            .copied()
+            .unwrap_or_default()
+    }
+
+    /// Width of one line in ems
+    pub fn measure(&self, line: &str) -> f32 {
+        let mut prev = None;
+        let mut width = 0.0;
+
+        for c in line.chars() {
+            width += self.glyph(c).map_or(0.0, |glyph| glyph.advance) + self.kerning(prev, c);
+            prev = Some(c);
+        }
+
+        width
+    }
+
+    /// Lines of `text` split at `\n` and before words passing `max_width`
+    /// ems, a word longer than a line gets a line of its own
+    pub fn lines<'a>(&self, text: &'a str, max_width: Option<f32>) -> Vec<&'a str> {
+        let mut lines = vec![];
+
+        for paragraph in text.split('\n') {
+            let Some(max_width) = max_width else {
+                lines.push(paragraph);
+                continue;
+            };
+
+            let mut start = 0;
+            let mut end = 0;
+            for (index, _) in paragraph.match_indices(' ').chain([(paragraph.len(), "")]) {
+                if end > start && self.measure(&paragraph[start..index]) > max_width {
+                    lines.push(&paragraph[start..end]);
+                    start = end + 1;
+                }
+                end = index;
+            }
+            lines.push(&paragraph[start..]);
+        }
+
+        lines
+    }
+}
+
+/// Glyph instance in the layout of `shaders/text.vert`, ems from the anchor
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Pod, Zeroable)]
+pub(super) struct GlyphQuad {
+    pub rect: [f32; 4],
+    pub uv: [f32; 4],
+}
+
+/// Quads of every visible glyph of `text`
+pub(super) fn layout(metrics: &FontMetrics, text: &str, align: TextAlign, vertical: VerticalAlign, max_width: Option<f32>) -> Vec<GlyphQuad> {
+    let lines = metrics.lines(text, max_width);
+    let count = lines.len() as f32;
+
+    let first_baseline = match vertical {
+        VerticalAlign::Top => metrics.ascent,
+        VerticalAlign::Middle => (metrics.ascent - metrics.descent - (count - 1.0) * metrics.line_height) * 0.5,
+        VerticalAlign::Baseline => 0.0,
+        VerticalAlign::Bottom => -metrics.descent - (count - 1.0) * metrics.line_height,
+    };
+
+    let mut quads = vec![];
+    for (index, line) in lines.into_iter().enumerate() {
+        let baseline = first_baseline + index as f32 * metrics.line_height;
+        let mut pen = match align {
+            TextAlign::Left => 0.0,
+            TextAlign::Center => -metrics.measure(line) * 0.5,
+            TextAlign::Right => -metrics.measure(line),
+        };
+
+        let mut prev = None;
+        for c in line.chars() {
+            let Some(glyph) = metrics.glyph(c) else {
+                continue;
+            };
+
+            pen += metrics.kerning(prev, c);
+            prev = Some(c);
+
+            let [x0, y0, x1, y1] = glyph.rect;
+            if x1 > x0 && y1 > y0 {
+                quads.push(GlyphQuad {
+                    rect: [pen + x0, baseline + y0, pen + x1, baseline + y1],
+                    uv: glyph.uv,
+                });
+            }
+
+            pen += glyph.advance;
+        }
+    }
+
+    quads
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Every glyph half an em wide, spaces are blank
+    fn monospace() -> FontMetrics {
+        let glyph = GlyphMetrics {
+            advance: 0.5,
+            rect: [0.0, -0.7, 0.5, 0.2],
+            uv: [0.0; 4],
+        };
+        let mut glyphs = ('!'..='~').map(|c| (c, glyph)).collect::<HashMap<_, _>>();
+        glyphs.insert(' ', GlyphMetrics { advance: 0.5, ..Default::default() });
+
+        FontMetrics {
+            glyphs,
+            kerning: HashMap::from([(('A', 'V'), -0.25)]),
+            ascent: 0.8,
+            descent: 0.2,
+            line_height: 1.2,
+            fallback: Some('?'),
+        }
+    }
+
+    #[test]
+    fn kerning_moves_the_pen() {
+        let metrics = monospace();
+
+        assert_eq!(metrics.measure("AB"), 1.0);
+        assert_eq!(metrics.measure("AV"), 0.75);
+
+        let quads = layout(&metrics, "AV", TextAlign::Left, VerticalAlign::Baseline, None);
+        assert_eq!(quads[1].rect[0], 0.25);
+    }
+
+    #[test]
+    fn words_wrap_at_the_width() {
+        let metrics = monospace();
+
+        assert_eq!(metrics.lines("ab cd ef", Some(2.5)), vec!["ab cd", "ef"]);
+        assert_eq!(metrics.lines("ab\ncd ef", None), vec!["ab", "cd ef"]);
+        assert_eq!(metrics.lines("abcdefgh ij", Some(1.0)), vec!["abcdefgh", "ij"]);
+    }
+
+    #[test]
+    fn alignment_moves_lines() {
+        let metrics = monospace();
+
+        let centered = layout(&metrics, "ab", TextAlign::Center, VerticalAlign::Top, None);
+        assert_eq!(centered[0].rect[0], -0.5);
+        assert_eq!(centered[0].rect[1], 0.8 - 0.7);
+
+        let right = layout(&metrics, "ab\nc", TextAlign::Right, VerticalAlign::Bottom, None);
+        assert_eq!(right.len(), 3);
+        assert_eq!(right[2].rect[0], -0.5);
+        // Bottom of the last line at the anchor
+        assert!((right[2].rect[3] - 0.0).abs() < 1e-6);
+    }
+
+    #[test]
+    fn missing_glyphs_use_the_fallback() {
+        let metrics = monospace();
+        let quads = layout(&metrics, "a\u{263a} b", TextAlign::Left, VerticalAlign::Baseline, None);
+
+        assert_eq!(quads.len(), 3);
+        assert_eq!(quads[2].rect[0], 1.5);
+    }
+}
@@ -0,0 +1,745 @@
+//! Fonts with signed distance field atlases and the text drawn with them
+
+use std::collections::HashMap;
+use std::sync::{Arc, Weak};
+
+use ash::vk;
+use bytemuck::{Pod, Zeroable};
+use ttf_parser::gpos::{PairAdjustment, PositioningSubtable};
+use ttf_parser::{GlyphId, Tag};
+
+use crate::bindless::Bindless;
+use crate::core::{
+    read_shader_from_bytes, Device, FontError, GpuBuffer, GraphicsPipeline, GraphicsPipelineBuilder, PipelineLayout, PipelineLayoutBuilder,
+    ShaderBuilder, VulkanError,
+};
+use crate::frame_graph::PushHeader;
+use crate::math::Vec3;
+use crate::render_context::RenderContext;
+use crate::resources::upload::BindlessImage;
+use crate::resources::{Create, Destroy, Get, GetMut, MeshBuffer, Pool, Ref, RefMut, Res, ResourceKey, Resources, Retired};
+use crate::VulkanResult;
+
+mod layout;
+mod sdf;
+
+use layout::{FontMetrics, GlyphMetrics, GlyphQuad};
+pub use layout::{TextAlign, VerticalAlign};
+use sdf::{Atlas, Outline};
+
+const VERTEX_SHADER: &[u8] = include_bytes!(concat!(env!("CARGO_MANIFEST_DIR"), "/shaders/spv/text_vert.spv"));
+const FRAGMENT_SHADER: &[u8] = include_bytes!(concat!(env!("CARGO_MANIFEST_DIR"), "/shaders/spv/text_frag.spv"));
+
+/// Vertices `shaders/text.vert` expands every glyph into
+const GLYPH_VERTICES: u32 = 6;
+
+/// Width of the atlas in pixels, rows are added below
+const ATLAS_WIDTH: usize = 1024;
+
+/// Parameters of a new [`Font`]
+pub struct FontDesc<'a> {
+    data: &'a [u8],
+    index: u32,
+    chars: Vec<char>,
+    glyph_size: f32,
+    spread: f32,
+}
+
+impl<'a> FontDesc<'a> {
+    /// First face of the TrueType or OpenType font in `data`, with printable
+    /// ASCII and Latin-1 characters
+    pub fn new(data: &'a [u8]) -> Self {
+        Self {
+            data,
+            index: 0,
+            chars: (' '..='~').chain('\u{a0}'..='\u{ff}').collect(),
+            glyph_size: 40.0,
+            spread: 6.0,
+        }
+    }
+
+    /// Face of a font collection
+    pub fn face_index(mut self, index: u32) -> Self {
+        self.index = index;
+        self
+    }
+
+    /// Characters in the atlas, others are drawn as `?`
+    pub fn chars<I: IntoIterator<Item = char>>(mut self, chars: I) -> Self {
+        self.chars = chars.into_iter().collect();
+        self
+    }
+
+    /// Pixels per em of the fields in the atlas, `40.0` by default
+    ///
+    /// Larger fields keep sharp corners at large sizes at the cost of memory
+    pub fn glyph_size(mut self, pixels: f32) -> Self {
+        self.glyph_size = pixels;
+        self
+    }
+
+    /// Pixels from the outline where the field saturates, `6.0` by default
+    pub fn spread(mut self, pixels: f32) -> Self {
+        self.spread = pixels;
+        self
+    }
+}
+
+/// Font face with a signed distance field of every glyph, see [`Text`]
+///
+/// Fields are built on the CPU when the font is created and live in one
+/// texture of the bindless set, text stays sharp at any size
+pub struct Font {
+    metrics: Arc<FontMetrics>,
+    atlas: BindlessImage,
+}
+
+impl Font {
+    /// Width and height in pixels of `text` drawn `size` pixels per em,
+    /// without wrapping
+    pub fn measure(&self, text: &str, size: f32) -> [f32; 2] {
+        let metrics = &self.metrics;
+        let lines = metrics.lines(text, None);
+        let width = lines.iter().map(|line| metrics.measure(line)).fold(0.0, f32::max);
+        let height = metrics.ascent + metrics.descent + (lines.len() - 1) as f32 * metrics.line_height;
+
+        [width * size, height * size]
+    }
+
+    /// Distance between baselines in ems
+    pub fn line_height(&self) -> f32 {
+        self.metrics.line_height
+    }
+}
+
+/// Metrics and atlas of every char of `desc` the face has
+fn build_font(desc: &FontDesc<'_>) -> Result<(FontMetrics, Atlas), FontError> {
+    profiling::scope!("build_font");
+
+    let face = ttf_parser::Face::parse(desc.data, desc.index).map_err(|err| FontError::Parse(err.to_string()))?;
+    let units = f32::from(face.units_per_em());
+    let scale = desc.glyph_size / units;
+    let pad = desc.spread.ceil();
+
+    let mut metrics = FontMetrics {
+        ascent: f32::from(face.ascender()) / units,
+        descent: -f32::from(face.descender()) / units,
+        line_height: (f32::from(face.ascender()) - f32::from(face.descender()) + f32::from(face.line_gap())) / units,
+        ..Default::default()
+    };
+
+    let mut atlas = Atlas::new(ATLAS_WIDTH);
+    let mut placed = vec![];
+    let mut ids = vec![];
+
+    for &c in &desc.chars {
+        let Some(id) = face.glyph_index(c) else {
+            continue;
+        };
+        ids.push((c, id));
+
+        let mut glyph = GlyphMetrics {
+            advance: face.glyph_hor_advance(id).map_or(0.0, |advance| f32::from(advance) / units),
+            ..Default::default()
+        };
+
+        if let Some(bbox) = face.glyph_bounding_box(id) {
+            // Font units at the top left pixel, `pad` pixels outside the box
+            let origin = [f32::from(bbox.x_min) - pad / scale, f32::from(bbox.y_max) + pad / scale];
+            let width = (f32::from(bbox.width()) * scale + 2.0 * pad).ceil() as usize;
+            let height = (f32::from(bbox.height()) * scale + 2.0 * pad).ceil() as usize;
+
+            let mut outline = Outline::new(scale, origin);
+            face.outline_glyph(id, &mut outline);
+
+            let field = sdf::distance_field(&outline.segments, width, height, desc.spread);
+            let [x, y] = atlas.insert(&field, width);
+
+            glyph.rect = [
+                origin[0] / units,
+                -origin[1] / units,
+                origin[0] / units + width as f32 / desc.glyph_size,
+                -origin[1] / units + height as f32 / desc.glyph_size,
+            ];
+            placed.push((c, [x, y, x + width, y + height]));
+        }
+
+        metrics.glyphs.insert(c, glyph);
+    }
+
+    if placed.is_empty() {
+        return Err(FontError::NoGlyphs);
+    }
+
+    // The atlas height is known once every field is placed
+    for (c, [x0, y0, x1, y1]) in placed {
+        let (width, height) = (atlas.width as f32, atlas.height as f32);
+        metrics.glyphs.get_mut(&c).expect("Placed glyphs are stored").uv =
+            [x0 as f32 / width, y0 as f32 / height, x1 as f32 / width, y1 as f32 / height];
+    }
+
+    metrics.kerning = kerning(&face, &ids, units);
+
+    metrics.fallback = metrics.glyphs.contains_key(&'?').then_some('?');
+
+    Ok((metrics, atlas))
+}
+
+/// Advance changes between every two of `ids` in ems
+///
+/// Pair adjustments of the GPOS `kern` feature win over the older `kern`
+/// table, as in shapers
+fn kerning(face: &ttf_parser::Face<'_>, ids: &[(char, GlyphId)], units: f32) -> HashMap<(char, char), f32> {
+    let mut kerning = HashMap::new();
+
+    if let Some(gpos) = face.tables().gpos {
+        // Scripts and languages share lookups between their features
+        let mut lookups = gpos
+            .features
+            .into_iter()
+            .filter(|feature| feature.tag == Tag::from_bytes(b"kern"))
+            .flat_map(|feature| feature.lookup_indices)
+            .collect::<Vec<_>>();
+        lookups.sort_unstable();
+        lookups.dedup();
+
+        for lookup in lookups.into_iter().filter_map(|index| gpos.lookups.get(index)) {
+            let subtables = lookup.subtables.into_iter::<PositioningSubtable<'_>>().collect::<Vec<_>>();
+            for &(left, left_id) in ids {
+                for &(right, right_id) in ids {
+                    // The first subtable covering the pair applies
+                    let value = subtables.iter().find_map(|subtable| match subtable {
+                        PositioningSubtable::Pair(PairAdjustment::Format1 { coverage, sets }) => {
+                            let set = sets.get(coverage.get(left_id)?)?;
+                            set.get(right_id).map(|(first, _)| first.x_advance)
+                        }
+                        PositioningSubtable::Pair(PairAdjustment::Format2 { coverage, classes, matrix }) => {
+                            coverage.get(left_id)?;
+                            let classes = (classes.0.get(left_id), classes.1.get(right_id));
+                            matrix.get(classes).map(|(first, _)| first.x_advance)
+                        }
+                        _ => None,
+                    });
+
+                    if let Some(value) = value.filter(|&value| value != 0) {
+                        *kerning.entry((left, right)).or_default() += f32::from(value) / units;
+                    }
+                }
+            }
+        }
+    }
+
+    if let Some(kern) = face.tables().kern.filter(|_| kerning.is_empty()) {
+        for subtable in kern.subtables.into_iter().filter(|subtable| subtable.horizontal && !subtable.variable) {
+            for &(left, left_id) in ids {
+                for &(right, right_id) in ids {
+                    if let Some(value) = subtable.glyphs_kerning(left_id, right_id).filter(|&value| value != 0) {
+                        *kerning.entry((left, right)).or_default() += f32::from(value) / units;
+                    }
+                }
+            }
+        }
+    }
+
+    kerning
+}
+
+impl Destroy for Font {
+    fn destroy(key: ResourceKey, _ctx: Weak<RenderContext>, resources: Weak<Resources>) {
+        let Some(resources) = resources.upgrade() else {
+            return;
+        };
+
+        let mut store = resources.text.write();
+        if let Some(font) = store.fonts.remove(key) {
+            let frames = store.frame_count;
+            store.retired_images.push(frames, font.atlas);
+        }
+    }
+}
+
+impl Create for Font {
+    type Desc<'a> = FontDesc<'a>;
+    fn create(ctx: &Arc<RenderContext>, resources: &Arc<Resources>, desc: Self::Desc<'_>) -> VulkanResult<Res<Self>> {
+        let (metrics, atlas) = build_font(&desc).map_err(VulkanError::Font)?;
+
+        let extent = vk::Extent3D {
+            width: atlas.width as u32,
+            height: atlas.height as u32,
+            depth: 1,
+        };
+        let image = BindlessImage::new(&ctx.device, &resources.bindless, vk::Format::R8_UNORM, extent)?;
+        resources.uploads.write().push(&ctx.device, &image, &atlas.pixels)?;
+
+        let font = Font {
+            metrics: Arc::new(metrics),
+            atlas: image,
+        };
+
+        Ok(resources
+            .text
+            .write()
+            .fonts
+            .insert(Arc::downgrade(ctx), Arc::downgrade(resources), font))
+    }
+}
+
+impl Get for Font {
+    fn get<'a>(resources: &'a Resources, res: &Res<Self>) -> Ref<'a, Self> {
+        let store = resources.text.try_read().expect("Fonts are already borrowed mutably");
+        Ref(parking_lot::RwLockReadGuard::map(store, |store| store.fonts.get(res)))
+    }
+}
+
+/// Place of a [`Text`]
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum TextAnchor {
+    /// Pixels from the top left corner of the target, always on top
+    Screen([f32; 2]),
+    /// Position projected by the camera, hidden behind nearer geometry
+    World(Vec3),
+}
+
+/// Push constants in the layout of `shaders/text.vert`
+#[repr(C)]
+#[derive(Clone, Copy, Pod, Zeroable)]
+struct Push {
+    header: PushHeader,
+    anchor: [f32; 3],
+    world: f32,
+    size: f32,
+    color: [f32; 4],
+}
+
+/// Parameters of a new [`Text`]
+pub struct TextDesc<'a> {
+    font: &'a Res<Font>,
+    text: &'a str,
+    anchor: TextAnchor,
+    size: f32,
+    color: [f32; 4],
+    align: TextAlign,
+    vertical: VerticalAlign,
+    max_width: Option<f32>,
+}
+
+impl<'a> TextDesc<'a> {
+    /// White text 16 pixels per em with its first baseline starting at the
+    /// top left corner of the target
+    pub fn new(font: &'a Res<Font>, text: &'a str) -> Self {
+        Self {
+            font,
+            text,
+            anchor: TextAnchor::Screen([0.0; 2]),
+            size: 16.0,
+            color: [1.0; 4],
+            align: TextAlign::default(),
+            vertical: VerticalAlign::default(),
+            max_width: None,
+        }
+    }
+
+    /// Place of the text
+    pub fn anchor(mut self, anchor: TextAnchor) -> Self {
+        self.anchor = anchor;
+        self
+    }
+
+    /// Pixels per em
+    pub fn size(mut self, pixels: f32) -> Self {
+        self.size = pixels;
+        self
+    }
+
+    /// Linear RGBA color
+    pub fn color(mut self, color: [f32; 4]) -> Self {
+        self.color = color;
+        self
+    }
+
+    /// Placement of lines and of the text around the anchor
+    pub fn align(mut self, align: TextAlign, vertical: VerticalAlign) -> Self {
+        self.align = align;
+        self.vertical = vertical;
+        self
+    }
+
+    /// Wrap words past `pixels`
+    pub fn max_width(mut self, pixels: f32) -> Self {
+        self.max_width = Some(pixels);
+        self
+    }
+}
+
+/// String laid out with a [`Font`], drawn on screen or as a billboard
+/// anchored to a world position
+///
+/// Glyph quads are rebuilt when the string or size changes and copied into
+/// the buffer of every frame in flight like a dynamic
+/// [`Mesh`](crate::Mesh). Moving the anchor is free. The font has to
+/// outlive the text, texts of a dropped font are not drawn, see
+/// [`PassContext::draw_text`]
+///
+/// # Example
+/// ```ignore
+/// let font = world.create::<Font>(FontDesc::new(include_bytes!("Inter.ttf")))?;
+/// let label = world.create::<Text>(
+///     TextDesc::new(&font, "peak")
+///         .anchor(TextAnchor::World([1.0, 2.0, 0.0]))
+///         .align(TextAlign::Center, VerticalAlign::Bottom),
+/// )?;
+///
+/// world.get_mut(&label).set_text(&format!("peak {value:.2}"));
+///
+/// world.draw_frame(|graph| {
+///     graph.add_pass(PresentPass::new("Labels", |_| {}, move |ctx, _| unsafe {
+///         ctx.draw_text(&label);
+///     }));
+/// })?;
+/// ```
+///
+/// [`PassContext::draw_text`]: crate::frame_graph::PassContext::draw_text
+pub struct Text {
+    /// Linear RGBA color
+    pub color: [f32; 4],
+    /// Place of the text
+    pub anchor: TextAnchor,
+    size: f32,
+    align: TextAlign,
+    vertical: VerticalAlign,
+    max_width: Option<f32>,
+    /// Texts of a dropped font are skipped
+    font: ResourceKey,
+    metrics: Arc<FontMetrics>,
+    string: String,
+    glyphs: MeshBuffer,
+    count: u32,
+}
+
+impl Text {
+    /// Replace the string
+    pub fn set_text(&mut self, text: &str) {
+        if self.string != text {
+            self.string = text.to_owned();
+            self.layout();
+        }
+    }
+
+    /// Current string
+    pub fn text(&self) -> &str {
+        &self.string
+    }
+
+    /// Change the pixels per em
+    pub fn set_size(&mut self, pixels: f32) {
+        if self.size != pixels {
+            self.size = pixels;
+            self.layout();
+        }
+    }
+
+    /// Pixels per em
+    pub fn size(&self) -> f32 {
+        self.size
+    }
+
+    fn layout(&mut self) {
+        let max_width = self.max_width.map(|width| width / self.size);
+        let quads = layout::layout(&self.metrics, &self.string, self.align, self.vertical, max_width);
+
+        self.glyphs.replace(bytemuck::cast_slice(&quads));
+        self.count = quads.len() as u32;
+    }
+
+    fn push_constants(&self, atlas: u32, transform: Option<u32>) -> Push {
+        let (anchor, world) = match self.anchor {
+            TextAnchor::Screen([x, y]) => ([x, y, 0.0], 0.0),
+            TextAnchor::World(position) => (position, 1.0),
+        };
+
+        Push {
+            header: PushHeader::new(transform, [atlas, 0, 0, 0, 0, 0, 0, 0]),
+            anchor,
+            world,
+            size: self.size,
+            color: self.color,
+        }
+    }
+}
+
+impl Destroy for Text {
+    fn destroy(key: ResourceKey, _ctx: Weak<RenderContext>, resources: Weak<Resources>) {
+        let Some(resources) = resources.upgrade() else {
+            return;
+        };
+
+        let mut store = resources.text.write();
+        if let Some(text) = store.texts.remove(key) {
+            let frames = store.frame_count;
+            store.retired_buffers.extend(frames, text.glyphs.into_buffers());
+        }
+    }
+}
+
+impl Create for Text {
+    type Desc<'a> = TextDesc<'a>;
+    fn create(ctx: &Arc<RenderContext>, resources: &Arc<Resources>, desc: Self::Desc<'_>) -> VulkanResult<Res<Self>> {
+        let mut store = resources.text.write();
+        let metrics = store.fonts.get(desc.font).metrics.clone();
+
+        let mut text = Text {
+            color: desc.color,
+            anchor: desc.anchor,
+            size: desc.size,
+            align: desc.align,
+            vertical: desc.vertical,
+            max_width: desc.max_width,
+            font: desc.font.key,
+            metrics,
+            string: desc.text.to_owned(),
+            glyphs: MeshBuffer::new_dynamic(vk::BufferUsageFlags::VERTEX_BUFFER, size_of::<GlyphQuad>(), ctx.frame_count()),
+            count: 0,
+        };
+        text.layout();
+
+        Ok(store.texts.insert(Arc::downgrade(ctx), Arc::downgrade(resources), text))
+    }
+}
+
+impl Get for Text {
+    fn get<'a>(resources: &'a Resources, res: &Res<Self>) -> Ref<'a, Self> {
+        let store = resources.text.try_read().expect("Texts are already borrowed mutably");
+        Ref(parking_lot::RwLockReadGuard::map(store, |store| store.texts.get(res)))
+    }
+}
+
+impl GetMut for Text {
+    fn get_mut<'a>(resources: &'a Resources, res: &Res<Self>) -> RefMut<'a, Self> {
+        let store = resources.text.try_write().expect("Texts are already borrowed");
+        RefMut(parking_lot::RwLockWriteGuard::map(store, |store| store.texts.get_mut(res)))
+    }
+}
+
+/// Fonts and texts with the pipeline drawing them
+pub struct TextStore {
+    pub(crate) fonts: Pool<Font>,
+    pub(crate) texts: Pool<Text>,
+    /// Dropped glyph buffers
+    retired_buffers: Retired<GpuBuffer>,
+    /// Atlases of dropped fonts
+    retired_images: Retired<BindlessImage>,
+    frame_count: usize,
+    layout: PipelineLayout,
+    pipeline: GraphicsPipeline,
+}
+
+impl TextStore {
+    pub fn new(device: &Device, frame_count: usize, render_pass: vk::RenderPass, set_layouts: Vec<vk::DescriptorSetLayout>) -> VulkanResult<Self> {
+        // The layout of every raster pipeline, set 0 holds the atlases
+        let layout = PipelineLayoutBuilder::new(device)
+            .set_layouts(set_layouts)
+            .push_constant(vec![vk::PushConstantRange::default()
+                .offset(0)
+                .size(128)
+                .stage_flags(vk::ShaderStageFlags::VERTEX | vk::ShaderStageFlags::FRAGMENT)])
+            .build()?;
+
+        let spv = read_shader_from_bytes(VERTEX_SHADER).expect("Invalid text vertex shader");
+        let vertex = ShaderBuilder::new(device).bytecode(&spv).build()?;
+        let spv = read_shader_from_bytes(FRAGMENT_SHADER).expect("Invalid text fragment shader");
+        let fragment = ShaderBuilder::new(device).bytecode(&spv).build()?;
+
+        // Every glyph is one instance of the quad
+        let bindings = [vk::VertexInputBindingDescription {
+            binding: 0,
+            stride: size_of::<GlyphQuad>() as u32,
+            input_rate: vk::VertexInputRate::INSTANCE,
+        }];
+        let attributes = [0, 1].map(|location| vk::VertexInputAttributeDescription {
+            location,
+            binding: 0,
+            format: vk::Format::R32G32B32A32_SFLOAT,
+            offset: location * 16,
+        });
+
+        let vertex_input_info = vk::PipelineVertexInputStateCreateInfo::default()
+            .vertex_binding_descriptions(&bindings)
+            .vertex_attribute_descriptions(&attributes);
+
+        let color_blend = vk::PipelineColorBlendAttachmentState::default()
+            .color_write_mask(vk::ColorComponentFlags::RGBA)
+            .blend_enable(true)
+            .src_color_blend_factor(vk::BlendFactor::SRC_ALPHA)
+            .dst_color_blend_factor(vk::BlendFactor::ONE_MINUS_SRC_ALPHA)
+            .color_blend_op(vk::BlendOp::ADD)
+            .src_alpha_blend_factor(vk::BlendFactor::ONE)
+            .dst_alpha_blend_factor(vk::BlendFactor::ONE_MINUS_SRC_ALPHA)
+            .alpha_blend_op(vk::BlendOp::ADD);
+
+        // Viewport and scissors are dynamic, set for every draw. Depth is
+        // tested with the reversed-Z compare and never written
+        let pipeline = GraphicsPipelineBuilder::new(device)
+            .vertex_shader(vertex.raw)
+            .fragment_shader(fragment.raw)
+            .render_pass(render_pass)
+            .pipeline_layout(layout.raw)
+            .viewport(vec![vk::Viewport::default().width(1.0).height(1.0).max_depth(1.0)])
+            .scissors(vec![vk::Rect2D::default()])
+            .input_assembly(vk::PipelineInputAssemblyStateCreateInfo::default().topology(vk::PrimitiveTopology::TRIANGLE_LIST))
+            .rasterization(
+                vk::PipelineRasterizationStateCreateInfo::default()
+                    .polygon_mode(vk::PolygonMode::FILL)
+                    .line_width(1.0)
+                    .cull_mode(vk::CullModeFlags::NONE),
+            )
+            .multisampling(vk::PipelineMultisampleStateCreateInfo::default().rasterization_samples(vk::SampleCountFlags::TYPE_1))
+            .color_blending(vk::PipelineColorBlendStateCreateInfo::default().attachments(&[color_blend]))
+            .dynamic_state(vec![vk::DynamicState::VIEWPORT, vk::DynamicState::SCISSOR])
+            .vertex_input_info(vertex_input_info)
+            .build()?;
+
+        Ok(Self {
+            fonts: Pool::new(),
+            texts: Pool::new(),
+            retired_buffers: Retired::default(),
+            retired_images: Retired::default(),
+            frame_count,
+            layout,
+            pipeline,
+        })
+    }
+
+    /// Copy changed glyphs into the buffers of `frame` and release what no
+    /// frame in flight can use anymore, called once per frame
+    pub fn update(&mut self, device: &Device, bindless: &Bindless, frame: usize) -> VulkanResult<()> {
+        profiling::scope!("TextStore::update");
+
+        self.retired_buffers.update(device);
+
+        self.retired_images.update_with(|mut image| image.destroy(device, bindless));
+
+        for text in self.texts.slots.values_mut() {
+            text.glyphs.prepare(device, frame)?;
+        }
+
+        Ok(())
+    }
+
+    /// Draw every glyph of `text` over the whole target, must be inside the
+    /// render pass
+    ///
+    /// `sets` are the bindless and per-frame sets of the frame, `transform`
+    /// places world anchors and is `None` for identity
+    #[allow(clippy::too_many_arguments)]
+    pub(crate) unsafe fn draw(
+        &self,
+        device: &ash::Device,
+        cbuf: vk::CommandBuffer,
+        frame: usize,
+        resolution: vk::Extent2D,
+        sets: &[vk::DescriptorSet],
+        text: &Text,
+        transform: Option<u32>,
+    ) {
+        let (Some(buffer), Some(font)) = (text.glyphs.raw(frame), self.fonts.slots.get(text.font)) else {
+            return;
+        };
+
+        if text.count == 0 {
+            return;
+        }
+
+        let viewport = vk::Viewport::default()
+            .width(resolution.width as f32)
+            .height(resolution.height as f32)
+            .max_depth(1.0);
+        let scissor = vk::Rect2D::default().extent(resolution);
+
+        device.cmd_bind_pipeline(cbuf, vk::PipelineBindPoint::GRAPHICS, self.pipeline.raw);
+        device.cmd_bind_descriptor_sets(cbuf, vk::PipelineBindPoint::GRAPHICS, self.layout.raw, 0, sets, &[]);
+        device.cmd_bind_vertex_buffers(cbuf, 0, &[buffer], &[0]);
+        device.cmd_set_viewport(cbuf, 0, &[viewport]);
+        device.cmd_set_scissor(cbuf, 0, &[scissor]);
+        device.cmd_push_constants(
+            cbuf,
+            self.layout.raw,
+            vk::ShaderStageFlags::VERTEX | vk::ShaderStageFlags::FRAGMENT,
+            0,
+            bytemuck::bytes_of(&text.push_constants(font.atlas.index, transform)),
+        );
+        device.cmd_draw(cbuf, GLYPH_VERTICES, text.count, 0, 0);
+    }
+
+    pub fn destroy(&mut self, device: &Device, bindless: &Bindless) {
+        self.retired_buffers.destroy(device);
+        self.retired_images.destroy_with(|mut image| image.destroy(device, bindless));
+        for (_, text) in self.texts.slots.drain() {
+            for mut buffer in text.glyphs.into_buffers() {
+                buffer.destroy(device);
+            }
+        }
+        for (_, mut font) in self.fonts.slots.drain() {
+            font.atlas.destroy(device, bindless);
+        }
+        self.pipeline.destroy(device);
+        unsafe {
+            device.destroy_pipeline_layout(self.layout.raw, None);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Box glyphs for ` ?AIV`, 1000 units per em, `I` spans 100..400 and
+    /// 0..700, `A` and `V` span 0..600 and kern by -100
+    const BOXES: &[u8] = include_bytes!(concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fonts/boxes.ttf"));
+
+    fn boxes(chars: &str) -> (FontMetrics, Atlas) {
+        build_font(&FontDesc::new(BOXES).chars(chars.chars()).glyph_size(10.0).spread(2.0)).expect("Error build font")
+    }
+
+    fn assert_close(got: [f32; 4], want: [f32; 4]) {
+        assert!(got.iter().zip(&want).all(|(a, b)| (a - b).abs() < 1e-6), "{got:?} is not {want:?}");
+    }
+
+    #[test]
+    fn glyphs_point_at_their_fields() {
+        let (metrics, atlas) = boxes("I A");
+
+        // 7x11 and 10x11 fields with 2 pixels of spread on each side
+        assert_eq!((atlas.width, atlas.height), (ATLAS_WIDTH, 16));
+        let i = metrics.glyphs[&'I'];
+        assert_close(i.rect, [-0.1, -0.9, 0.6, 0.2]);
+        assert_close(i.uv, [0.0, 0.0, 7.0 / 1024.0, 11.0 / 16.0]);
+        let a = metrics.glyphs[&'A'];
+        assert_close(a.rect, [-0.2, -0.9, 0.8, 0.2]);
+        assert_close(a.uv, [7.0 / 1024.0, 0.0, 17.0 / 1024.0, 11.0 / 16.0]);
+
+        // Inside the stem of the `I`, outside past the spread
+        assert!(atlas.pixels[5 * ATLAS_WIDTH + 3] > 128);
+        assert_eq!(atlas.pixels[5 * ATLAS_WIDTH + 20], 0);
+
+        // Blank glyphs only advance
+        assert_eq!(metrics.glyphs[&' '], GlyphMetrics { advance: 0.5, ..Default::default() });
+    }
+
+    #[test]
+    fn kerning_reads_the_kern_table() {
+        let (metrics, _) = boxes("AIV");
+
+        assert_eq!(metrics.kerning.len(), 1);
+        assert!((metrics.kerning[&('A', 'V')] + 0.1).abs() < 1e-6);
+    }
+
+    #[test]
+    fn missing_chars_are_skipped() {
+        let (metrics, _) = boxes("I?x");
+
+        assert!(!metrics.glyphs.contains_key(&'x'));
+        assert_eq!(metrics.fallback, Some('?'));
+        assert_eq!(boxes("I").0.fallback, None);
+        assert!(matches!(build_font(&FontDesc::new(BOXES).chars([' ', 'x'])), Err(FontError::NoGlyphs)));
+    }
+}
@@ -0,0 +1,226 @@
+//! Signed distance fields of glyph outlines and the atlas holding them
+
+/// Line segments every curve is split into
+const CURVE_STEPS: usize = 8;
+
+/// Outline of a glyph flattened to line segments, in pixels of the field
+/// with y down
+pub(super) struct Outline {
+    pub segments: Vec<[[f32; 2]; 2]>,
+    /// Pixels per font unit
+    scale: f32,
+    /// Font units placed at pixel 0
+    origin: [f32; 2],
+    start: [f32; 2],
+    last: [f32; 2],
+}
+
+impl Outline {
+    /// Outline mapping font units at `origin`, the top left of the glyph, to
+    /// pixel 0
+    pub fn new(scale: f32, origin: [f32; 2]) -> Self {
+        Self {
+            segments: vec![],
+            scale,
+            origin,
+            start: [0.0; 2],
+            last: [0.0; 2],
+        }
+    }
+
+    fn point(&self, x: f32, y: f32) -> [f32; 2] {
+        [(x - self.origin[0]) * self.scale, (self.origin[1] - y) * self.scale]
+    }
+
+    fn push(&mut self, p: [f32; 2]) {
+        if p != self.last {
+            self.segments.push([self.last, p]);
+        }
+        self.last = p;
+    }
+}
+
+impl ttf_parser::OutlineBuilder for Outline {
+    fn move_to(&mut self, x: f32, y: f32) {
+        self.start = self.point(x, y);
+        self.last = self.start;
+    }
+
+    fn line_to(&mut self, x: f32, y: f32) {
+        let p = self.point(x, y);
+        self.push(p);
+    }
+
+    fn quad_to(&mut self, x1: f32, y1: f32, x: f32, y: f32) {
+        let (p0, p1, p2) = (self.last, self.point(x1, y1), self.point(x, y));
+        for step in 1..=CURVE_STEPS {
+            let t = step as f32 / CURVE_STEPS as f32;
+            let s = 1.0 - t;
+            self.push([0, 1].map(|i| s * s * p0[i] + 2.0 * s * t * p1[i] + t * t * p2[i]));
+        }
+    }
+
+    fn curve_to(&mut self, x1: f32, y1: f32, x2: f32, y2: f32, x: f32, y: f32) {
+        let (p0, p1, p2, p3) = (self.last, self.point(x1, y1), self.point(x2, y2), self.point(x, y));
+        for step in 1..=CURVE_STEPS {
+            let t = step as f32 / CURVE_STEPS as f32;
+            let s = 1.0 - t;
+            self.push([0, 1].map(|i| s * s * s * p0[i] + 3.0 * s * s * t * p1[i] + 3.0 * s * t * t * p2[i] + t * t * t * p3[i]));
+        }
+    }
+
+    fn close(&mut self) {
+        let start = self.start;
+        self.push(start);
+    }
+}
+
+/// Field of `width` by `height` pixels, 128 on the outline and 255 at
+/// `spread` pixels inside
+///
+/// The inside follows the non-zero winding rule of TrueType outlines
+pub(super) fn distance_field(segments: &[[[f32; 2]; 2]], width: usize, height: usize, spread: f32) -> Vec<u8> {
+    let mut field = Vec::with_capacity(width * height);
+
+    for y in 0..height {
+        for x in 0..width {
+            let p = [x as f32 + 0.5, y as f32 + 0.5];
+            let mut distance = f32::MAX;
+            let mut winding = 0;
+
+            for &[a, b] in segments {
+                distance = distance.min(segment_distance(p, a, b));
+
+                // Crossings of a ray towards +x
+                if (a[1] <= p[1]) != (b[1] <= p[1]) {
+                    let x = a[0] + (p[1] - a[1]) * (b[0] - a[0]) / (b[1] - a[1]);
+                    if x > p[0] {
+                        winding += if b[1] > a[1] { 1 } else { -1 };
+                    }
+                }
+            }
+
+            let signed = if winding != 0 { distance } else { -distance };
+            let value = (0.5 + signed / (2.0 * spread)).clamp(0.0, 1.0);
+            field.push((value * 255.0).round() as u8);
+        }
+    }
+
+    field
+}
+
+fn segment_distance(p: [f32; 2], a: [f32; 2], b: [f32; 2]) -> f32 {
+    let ab = [b[0] - a[0], b[1] - a[1]];
+    let ap = [p[0] - a[0], p[1] - a[1]];
+    let len = ab[0] * ab[0] + ab[1] * ab[1];
+    let t = if len > 0.0 { ((ap[0] * ab[0] + ap[1] * ab[1]) / len).clamp(0.0, 1.0) } else { 0.0 };
+    let d = [ap[0] - ab[0] * t, ap[1] - ab[1] * t];
+    d[0].hypot(d[1])
+}
+
+/// Single channel image packed with rows of fields, left to right
+pub(super) struct Atlas {
+    pub width: usize,
+    pub height: usize,
+    pub pixels: Vec<u8>,
+    cursor: [usize; 2],
+    row_height: usize,
+}
+
+impl Atlas {
+    pub fn new(width: usize) -> Self {
+        Self {
+            width,
+            height: 0,
+            pixels: vec![],
+            cursor: [0; 2],
+            row_height: 0,
+        }
+    }
+
+    /// Copy a `width` pixels wide field, returns its top left corner
+    ///
+    /// # Panics
+    /// - if the field is wider than the atlas
+    pub fn insert(&mut self, field: &[u8], width: usize) -> [usize; 2] {
+        assert!(width <= self.width, "Glyph is wider than the atlas");
+        let height = field.len() / width.max(1);
+
+        if self.cursor[0] + width > self.width {
+            self.cursor = [0, self.cursor[1] + self.row_height];
+            self.row_height = 0;
+        }
+
+        let [x, y] = self.cursor;
+        if y + height > self.height {
+            // Powers of two keep the size stable while rows are added
+            self.height = (y + height).next_power_of_two();
+            self.pixels.resize(self.width * self.height, 0);
+        }
+
+        for (row, line) in field.chunks_exact(width.max(1)).enumerate() {
+            let start = (y + row) * self.width + x;
+            self.pixels[start..start + width].copy_from_slice(line);
+        }
+
+        self.cursor[0] += width;
+        self.row_height = self.row_height.max(height);
+
+        [x, y]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn square_field_is_signed() {
+        // Clockwise square from 4 to 12 on both axes, y down
+        let square = [[4.0, 4.0], [12.0, 4.0], [12.0, 12.0], [4.0, 12.0]];
+        let segments = (0..4).map(|i| [square[i], square[(i + 1) % 4]]).collect::<Vec<_>>();
+        let field = distance_field(&segments, 16, 16, 4.0);
+
+        let at = |x: usize, y: usize| field[y * 16 + x];
+        // 3.5 pixels inside and outside
+        assert_eq!(at(7, 7), 239);
+        assert_eq!(at(0, 7), 16);
+        // Saturated past the spread
+        assert_eq!(at(0, 0), 0);
+        // Both windings count as inside
+        let reversed = segments.iter().rev().map(|&[a, b]| [b, a]).collect::<Vec<_>>();
+        assert_eq!(distance_field(&reversed, 16, 16, 4.0), field);
+    }
+
+    #[test]
+    fn atlas_packs_rows() {
+        let mut atlas = Atlas::new(8);
+
+        assert_eq!(atlas.insert(&[1; 12], 4), [0, 0]);
+        assert_eq!(atlas.insert(&[2; 8], 4), [4, 0]);
+        assert_eq!(atlas.insert(&[3; 4], 2), [0, 3]);
+
+        // Rows grow the height to the next power of two
+        assert_eq!(atlas.height, 8);
+        assert_eq!(&atlas.pixels[..8], &[1, 1, 1, 1, 2, 2, 2, 2]);
+        assert_eq!(&atlas.pixels[24..34], &[3, 3, 0, 0, 0, 0, 0, 0, 3, 3]);
+    }
+
+    #[test]
+    fn rows_start_below_the_tallest_field() {
+        let mut atlas = Atlas::new(4);
+
+        assert_eq!(atlas.insert(&[1; 2], 2), [0, 0]);
+        assert_eq!(atlas.insert(&[2; 6], 2), [2, 0]);
+        assert_eq!(atlas.insert(&[3; 4], 4), [0, 3]);
+        assert_eq!(atlas.height, 4);
+        assert_eq!(&atlas.pixels[..4], &[1, 1, 2, 2]);
+        assert_eq!(&atlas.pixels[12..], &[3, 3, 3, 3]);
+    }
+
+    #[test]
+    #[should_panic(expected = "Glyph is wider than the atlas")]
+    fn fields_fit_the_width() {
+        Atlas::new(4).insert(&[0; 5], 5);
+    }
+}
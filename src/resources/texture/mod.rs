@@ -1,4 +1,12 @@
-use crate::{Res, core::{Image, ImageView}, resources::{Destroy, ResourceKey}};
+use std::sync::{Arc, Weak};
+
+use ash::vk;
+
+use crate::bindless::Bindless;
+use crate::render_context::RenderContext;
+use crate::resources::upload::BindlessImage;
+use crate::VulkanResult;
+use crate::{Res, core::{Device, Image, ImageView}, resources::{Create, Destroy, Get, Pool, Ref, ResourceKey, Resources, Retired}};
 mod texture;
 pub use texture::{Resolution, TextureFormat};
 const MAX_TEXTURE: usize = 100000;
@@ -6,17 +14,92 @@ const MAX_TEXTURE: usize = 100000;
 #[derive(Clone, Copy)]
 pub struct TextureHandle(usize);
 
+impl TextureFormat {
+    /// Format of the image and its view
+    fn vk(self) -> vk::Format {
+        match self {
+            TextureFormat::D32Sfloat => vk::Format::D32_SFLOAT,
+            TextureFormat::R8g8b8a8Srgb => vk::Format::R8G8B8A8_SRGB,
+            TextureFormat::R8g8b8a8Unorm => vk::Format::R8G8B8A8_UNORM,
+            TextureFormat::R8Unorm => vk::Format::R8_UNORM,
+            TextureFormat::R16Unorm => vk::Format::R16_UNORM,
+            TextureFormat::R32Sfloat => vk::Format::R32_SFLOAT,
+        }
+    }
+
+    /// Bytes of one texel
+    pub fn texel_size(self) -> usize {
+        match self {
+            TextureFormat::R8Unorm => 1,
+            TextureFormat::R16Unorm => 2,
+            TextureFormat::D32Sfloat | TextureFormat::R8g8b8a8Srgb | TextureFormat::R8g8b8a8Unorm | TextureFormat::R32Sfloat => 4,
+        }
+    }
+}
+
+/// Image in the bindless set, shaders sample it as `textures[index]` or
+/// `textures3d[index]` of `shaders/inc/bindless.glsl` when it has a depth
+///
+/// # Example
+/// ```ignore
+/// // 256 slices of 512x512 CT samples
+/// let texture = world.create::<Texture>(
+///     TextureDesc::new(512, 512, TextureFormat::R16Unorm, bytemuck::cast_slice(&samples)).depth(256),
+/// )?;
+/// ```
 pub struct Texture {
-    image: Image,
+    image: BindlessImage,
+    format: TextureFormat,
 }
 
+impl Texture {
+    /// Place in `textures[]`, see [`PassContext::bind_texture`]
+    ///
+    /// [`PassContext::bind_texture`]: crate::frame_graph::PassContext::bind_texture
+    pub fn index(&self) -> u32 {
+        self.image.index
+    }
+
+    /// Width, height and depth in texels
+    pub fn extent(&self) -> [u32; 3] {
+        let vk::Extent3D { width, height, depth } = self.image.extent;
+        [width, height, depth]
+    }
+
+    /// Format the texels were given in
+    pub fn format(&self) -> TextureFormat {
+        self.format
+    }
+}
+
+/// Parameters of a new [`Texture`]
 pub struct TextureDesc<'a> {
     width: u32,
     height: u32,
+    depth: u32,
     format: TextureFormat,
     pixels: &'a [u8]
 }
 
+impl<'a> TextureDesc<'a> {
+    /// 2D texture of `width` x `height` texels, row after row
+    pub fn new(width: u32, height: u32, format: TextureFormat, pixels: &'a [u8]) -> Self {
+        Self {
+            width,
+            height,
+            depth: 1,
+            format,
+            pixels,
+        }
+    }
+
+    /// Make a 3D texture of `depth` slices, `pixels` holds slice after slice
+    pub fn depth(mut self, depth: u32) -> Self {
+        self.depth = depth;
+        self
+    }
+}
+
 pub struct TextureView {
     image: Res<Image>,
     view: Res<ImageView>
@@ -26,14 +109,90 @@ pub struct TextureViewDesc {
     image: Res<Image>
 }
 
-pub struct TexturePool {
-    
+impl Destroy for Texture {
+    fn destroy(key: ResourceKey, _ctx: Weak<RenderContext>, resources: Weak<Resources>) {
+        let Some(resources) = resources.upgrade() else {
+            return;
+        };
+
+        let mut store = resources.textures.write();
+        if let Some(texture) = store.pool.remove(key) {
+            let frames = store.frame_count;
+            store.retired.push(frames, texture.image);
+        }
+    }
 }
 
-impl TexturePool {
-    fn new() -> Self {
-        Self { 
+impl Create for Texture {
+    type Desc<'a> = TextureDesc<'a>;
+    /// Texels are copied before the first pass of the next frame
+    ///
+    /// # Panics
+    /// - if the format is [`TextureFormat::D32Sfloat`]
+    /// - if a side is 0
+    /// - if `pixels` does not hold every texel
+    fn create(ctx: &Arc<RenderContext>, resources: &Arc<Resources>, desc: Self::Desc<'_>) -> VulkanResult<Res<Self>> {
+        assert!(desc.format != TextureFormat::D32Sfloat, "Depth formats cannot be sampled as textures");
+        assert!(desc.width > 0 && desc.height > 0 && desc.depth > 0, "Textures need at least one texel");
+
+        let texels = desc.width as usize * desc.height as usize * desc.depth as usize;
+        assert_eq!(desc.pixels.len(), texels * desc.format.texel_size(), "Texture size differs from its pixels");
+
+        let extent = vk::Extent3D {
+            width: desc.width,
+            height: desc.height,
+            depth: desc.depth,
+        };
+        let image = BindlessImage::new(&ctx.device, &resources.bindless, desc.format.vk(), extent)?;
+        resources.uploads.write().push(&ctx.device, &image, desc.pixels)?;
+
+        let texture = Texture {
+            image,
+            format: desc.format,
+        };
+
+        Ok(resources
+            .textures
+            .write()
+            .pool
+            .insert(Arc::downgrade(ctx), Arc::downgrade(resources), texture))
+    }
+}
+
+impl Get for Texture {
+    fn get<'a>(resources: &'a Resources, res: &Res<Self>) -> Ref<'a, Self> {
+        let store = resources.textures.try_read().expect("Textures are already borrowed mutably");
+        Ref(parking_lot::RwLockReadGuard::map(store, |store| store.pool.get(res)))
+    }
+}
+
+/// Textures with the images of dropped ones
+pub struct TextureStore {
+    pub(crate) pool: Pool<Texture>,
+    /// Dropped images
+    retired: Retired<BindlessImage>,
+    frame_count: usize,
+}
+
+impl TextureStore {
+    pub fn new(frame_count: usize) -> Self {
+        Self {
+            pool: Pool::new(),
+            retired: Retired::default(),
+            frame_count,
+        }
+    }
+
+    /// Release images no frame in flight samples anymore, called once per
+    /// frame
+    pub fn update(&mut self, device: &Device, bindless: &Bindless) {
+        self.retired.update_with(|mut image| image.destroy(device, bindless));
+    }
 
+    pub fn destroy(&mut self, device: &Device, bindless: &Bindless) {
+        self.retired.destroy_with(|mut image| image.destroy(device, bindless));
+        for (_, mut texture) in self.pool.slots.drain() {
+            texture.image.destroy(device, bindless);
         }
     }
 }
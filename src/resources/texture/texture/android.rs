@@ -10,8 +10,15 @@
     R16G16B16A16_SFLOAT	            SRGB_NONLINEAR_KHR	                87.42%	12.58%
 */
 
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub enum TextureFormat {
     D32Sfloat,
     R8g8b8a8Srgb,
     R8g8b8a8Unorm,
+    /// Scalar from 0 to 1
+    R8Unorm,
+    /// Scalar from 0 to 1 with 65536 steps, e.g. CT volumes
+    R16Unorm,
+    /// Scalar
+    R32Sfloat,
 }
@@ -13,7 +13,7 @@
 // }
 
 /// Formats for Texture
-#[derive(Debug)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub enum TextureFormat {
     /// For Depth
     D32Sfloat,
@@ -21,4 +21,10 @@ pub enum TextureFormat {
     R8g8b8a8Srgb,
     /// For Image
     R8g8b8a8Unorm,
+    /// Scalar from 0 to 1
+    R8Unorm,
+    /// Scalar from 0 to 1 with 65536 steps, e.g. CT volumes
+    R16Unorm,
+    /// Scalar
+    R32Sfloat,
 }
@@ -1,143 +1,602 @@
+use std::ops::Range;
 use std::sync::{Arc, Weak};
 
 use ash::vk;
 use bytemuck::{Pod, Zeroable};
 
-use crate::core::{Device, VulkanResult};
-use crate::per_frame::{PerFrameBuffer, PerFrameBufferBuilder};
-use crate::resources::{Create, Destroy, LinearPool, Res, ResourceKey, Resources};
+use crate::core::{Device, GpuBuffer, GpuBufferBuilder, VulkanResult};
+use crate::math::{self, Mat4, Quat, Vec3};
+use crate::per_frame::{PerFrameDescriptors, TRANSFORMS_BINDING};
+use crate::render_context::RenderContext;
+use crate::resources::{Create, Destroy, Get, GetMut, LinearPool, Ref, RefMut, Res, ResourceKey, Resources};
 
-pub const MAX_TRANSFORMS: usize = 1_000;
+/// Transforms the storage buffer holds before it has to grow
+const INITIAL_CAPACITY: usize = 1_024;
 
-/// Transform for Mesh
-#[repr(C)]
-#[derive(Clone, Copy, Pod, Zeroable)]
-pub struct TransformDesc {
-    /// Rot
-    pub rot: [f32; 4],
-    /// Scale
-    pub scale: [f32; 4],
-    /// Pos
-    pub pos: [f32; 4],
+/// Released indices needed before the pool is compacted
+const COMPACT_MIN_FREE: usize = 256;
+
+/// Parameters of a new [`Transform`]
+///
+/// # Example
+/// ```ignore
+/// let parent = world.create::<Transform>(TransformDesc::identity().translation([0.0, 1.0, 0.0]))?;
+/// let child = world.create::<Transform>(
+///     TransformDesc::identity()
+///         .axis_angle([0.0, 1.0, 0.0], std::f32::consts::FRAC_PI_2)
+///         .parent(&parent),
+/// )?;
+/// ```
+#[derive(Clone, Copy)]
+pub struct TransformDesc<'a> {
+    local: Transform,
+    parent: Option<&'a Res<Transform>>,
 }
 
-impl TransformDesc {
-    /// identity matrix
+impl<'a> TransformDesc<'a> {
+    /// No translation, rotation or scale and no parent
     pub fn identity() -> Self {
         Self {
-            scale: [1.0, 1.0, 1.0, 0.0],
-            rot: [0.0, 0.0, 0.0, 0.0],
-            pos: [0.0, 0.0, 0.0, 0.0],
+            local: Transform::identity(),
+            parent: None,
         }
     }
+
+    /// Start from an existing local transform
+    pub fn new(local: Transform) -> Self {
+        Self { local, parent: None }
+    }
+
+    /// Offset from the parent origin
+    pub fn translation(mut self, translation: Vec3) -> Self {
+        self.local.translation = translation;
+        self
+    }
+
+    /// Rotation as a unit quaternion `[x, y, z, w]`
+    pub fn rotation(mut self, rotation: Quat) -> Self {
+        self.local.rotation = rotation;
+        self
+    }
+
+    /// Rotation by `angle` radians around `axis`
+    pub fn axis_angle(mut self, axis: Vec3, angle: f32) -> Self {
+        self.local.rotation = math::quat_from_axis_angle(axis, angle);
+        self
+    }
+
+    /// Scale along the local axes
+    pub fn scale(mut self, scale: Vec3) -> Self {
+        self.local.scale = scale;
+        self
+    }
+
+    /// Place the transform into the space of `parent`
+    pub fn parent(mut self, parent: &'a Res<Transform>) -> Self {
+        self.parent = Some(parent);
+        self
+    }
 }
 
-/// Transform for Mesh
-#[repr(C)]
-#[derive(Clone, Copy, Pod, Zeroable)]
+/// Local transform relative to the parent, or to the world for roots
+///
+/// Applied as scale, then rotation, then translation. Changes made through
+/// [`WorldRenderer::get_mut`](crate::WorldRenderer::get_mut) are baked into
+/// world matrices of the transform and its children before the next frame
+#[derive(Clone, Copy, Debug, PartialEq)]
 pub struct Transform {
-    /// Rot
-    pub rot: [f32; 4],
-    /// Scale
-    pub scale: [f32; 4],
-    /// Pos
-    pub pos: [f32; 4],
-    /// Pad to 64 bytes
-    pub _pad: [f32; 4],
+    /// Offset from the parent origin
+    pub translation: Vec3,
+    /// Unit quaternion `[x, y, z, w]`
+    pub rotation: Quat,
+    /// Scale along the local axes
+    pub scale: Vec3,
 }
 
 impl Transform {
     /// identity matrix
     pub fn identity() -> Self {
         Self {
-            scale: [1.0, 1.0, 1.0, 0.0],
-            rot: [0.0, 0.0, 0.0, 0.0],
-            pos: [0.0, 0.0, 0.0, 0.0],
-            _pad: [0.0, 0.0, 0.0, 0.0],
+            translation: [0.0; 3],
+            rotation: [0.0, 0.0, 0.0, 1.0],
+            scale: [1.0; 3],
+        }
+    }
+
+    /// Local matrix, `translation * rotation * scale`
+    pub fn matrix(&self) -> Mat4 {
+        math::compose(self.translation, self.rotation, self.scale)
+    }
+}
+
+impl Default for Transform {
+    fn default() -> Self {
+        Self::identity()
+    }
+}
+
+/// Baked transform as seen by shaders in `transforms[]` at set 1, binding 2
+#[repr(C)]
+#[derive(Clone, Copy, Pod, Zeroable)]
+pub(crate) struct TransformData {
+    /// Local to world
+    model: Mat4,
+    /// Inverse transpose of `model`
+    normal: Mat4,
+}
+
+impl TransformData {
+    fn new(model: Mat4) -> Self {
+        Self {
+            model,
+            normal: math::normal_matrix(&model),
+        }
+    }
+}
+
+/// Hierarchy of a transform, stored at the same index as the transform
+#[derive(Clone)]
+struct Node {
+    parent: Option<ResourceKey>,
+    children: Vec<ResourceKey>,
+    depth: u32,
+    world: Mat4,
+    /// Last propagation which baked this node
+    baked: u64,
+    /// Waiting in `TransformPool::dirty`
+    dirty: bool,
+}
+
+impl Node {
+    fn root() -> Self {
+        Self {
+            parent: None,
+            children: Vec::new(),
+            depth: 0,
+            world: math::IDENTITY,
+            baked: 0,
+            dirty: false,
         }
     }
 }
 
 impl Destroy for Transform {
-    fn destroy(_handle: ResourceKey, _ctx: Weak<crate::render_context::RenderContext>, _resources: Weak<Resources>) {}
+    fn destroy(key: ResourceKey, _ctx: Weak<RenderContext>, resources: Weak<Resources>) {
+        let Some(resources) = resources.upgrade() else {
+            return;
+        };
+
+        // Handles may be dropped while the pool is borrowed, even by this
+        // thread, the index is then released by the next update
+        match resources.transforms.try_write() {
+            Some(mut transforms) => transforms.remove(key),
+            None => resources.released_transforms.lock().push(key),
+        };
+    }
 }
 
 impl Create for Transform {
-    type Desc<'a> = TransformDesc;
+    type Desc<'a> = TransformDesc<'a>;
 
-    fn create(ctx: &Arc<crate::render_context::RenderContext>, resources: &Arc<Resources>, desc: Self::Desc<'_>) -> VulkanResult<Res<Self>> {
+    fn create(ctx: &Arc<RenderContext>, resources: &Arc<Resources>, desc: Self::Desc<'_>) -> VulkanResult<Res<Self>> {
         let mut transforms = resources.transforms.write();
-        transforms.is_dirty = true;
-
-        let handle = transforms.pool.insert(
-            Arc::downgrade(ctx),
-            Arc::downgrade(resources),
-            Transform {
-                rot: desc.rot,
-                scale: desc.scale,
-                pos: desc.pos,
-                _pad: [0.0, 0.0, 0.0, 0.0],
-            },
-        );
-
-        Ok(handle)
+        Ok(transforms.insert(Arc::downgrade(ctx), Arc::downgrade(resources), desc))
     }
 }
 
-// impl Create for Transform {
-//     type Desc<'a> = TransformDesc;
-//     fn create(resources: &Resources, desc: Self::Desc<'_>) ->
-// VulkanResult<Res<Self>> {         let mut transforms =
-// resources.transforms.write();         let res =
-// transforms.pool.insert(Transform {             rot: desc.rot,
-//             scale: desc.scale,
-//             pos: desc.pos,
-//             _pad: [0.0, 0.0, 0.0, 0.0],
-//         });
+impl Get for Transform {
+    fn get<'a>(resources: &'a Resources, res: &Res<Self>) -> Ref<'a, Self> {
+        let transforms = resources.transforms.try_read().expect("Transforms are already borrowed mutably");
+        Ref(parking_lot::RwLockReadGuard::map(transforms, |transforms| transforms.pool.get(res)))
+    }
+}
 
-//         transforms.is_dirty = true;
-//         Ok(res)
-//     }
-// }
+impl GetMut for Transform {
+    fn get_mut<'a>(resources: &'a Resources, res: &Res<Self>) -> RefMut<'a, Self> {
+        let mut transforms = resources.transforms.try_write().expect("Transforms are already borrowed");
+        transforms.mark_dirty(res.key);
+        RefMut(parking_lot::RwLockWriteGuard::map(transforms, |transforms| transforms.pool.get_mut(res)))
+    }
+}
 
+/// All transforms with their hierarchy and the storage buffers shaders read
+///
+/// Every frame in flight owns a buffer. Only indices changed since the
+/// frame's buffer was last written are uploaded, a buffer is recreated twice
+/// as large when transforms no longer fit
 pub struct TransformPool {
-    pub is_dirty: bool,
     pub pool: LinearPool<Transform>,
-    pub buffer: PerFrameBuffer,
+    nodes: Vec<Node>,
+    data: Vec<TransformData>,
+    /// Transforms whose local matrix or parent changed, each once
+    dirty: Vec<ResourceKey>,
+    /// Increased by every propagation
+    generation: u64,
+    /// Increased every time indices move
+    compactions: u64,
+    pub(crate) buffers: Vec<GpuBuffer>,
+    /// Capacity of each buffer in transforms
+    capacity: Vec<usize>,
+    /// Sorted and disjoint index ranges each buffer is missing
+    pending: Vec<Vec<Range<usize>>>,
 }
 
 impl TransformPool {
     pub fn new(device: &Device, frame_count: usize) -> VulkanResult<Self> {
-        let mut buffer = PerFrameBufferBuilder::new(device)
-            .buffer_size(size_of::<Transform>() as u64)
-            .frame_count(frame_count)
-            .usage(vk::BufferUsageFlags::UNIFORM_BUFFER)
-            .build()?;
+        let buffers = (0..frame_count)
+            .map(|_| Self::create_buffer(device, INITIAL_CAPACITY))
+            .collect::<VulkanResult<Vec<_>>>()?;
+
+        Ok(Self::with_buffers(buffers))
+    }
+
+    fn with_buffers(buffers: Vec<GpuBuffer>) -> Self {
+        let frame_count = buffers.len();
+        Self {
+            pool: LinearPool::new(INITIAL_CAPACITY),
+            nodes: Vec::with_capacity(INITIAL_CAPACITY),
+            data: Vec::with_capacity(INITIAL_CAPACITY),
+            dirty: Vec::new(),
+            generation: 0,
+            compactions: 0,
+            buffers,
+            capacity: vec![INITIAL_CAPACITY; frame_count],
+            pending: vec![vec![]; frame_count],
+        }
+    }
 
-        let data = vec![Transform::identity(); MAX_TRANSFORMS];
+    fn insert(&mut self, ctx: Weak<RenderContext>, resources: Weak<Resources>, desc: TransformDesc<'_>) -> Res<Transform> {
+        let handle = self.pool.insert(ctx, resources, desc.local);
+
+        let index = self.pool.index(&handle);
+        if index == self.nodes.len() {
+            self.nodes.push(Node::root());
+            self.data.push(TransformData::new(math::IDENTITY));
+        } else {
+            self.nodes[index] = Node::root();
+        }
 
-        for i in 0..frame_count {
-            let buffer = buffer.get_mut(i as u32);
-            buffer.upload_data(&data)?;
+        if let Some(parent) = desc.parent {
+            self.set_parent(handle.key, Some(parent.key));
         }
 
-        Ok(Self {
-            buffer,
-            pool: LinearPool::new(MAX_TRANSFORMS),
-            is_dirty: false,
-        })
+        self.mark_dirty(handle.key);
+
+        handle
+    }
+
+    fn create_buffer(device: &Device, capacity: usize) -> VulkanResult<GpuBuffer> {
+        GpuBufferBuilder::cpu_only(device)
+            .size((size_of::<TransformData>() * capacity) as u64)
+            .usage(vk::BufferUsageFlags::STORAGE_BUFFER)
+            .build()
+    }
+
+    /// Index of the transform in `transforms[]`
+    pub fn index(&self, res: &Res<Transform>) -> u32 {
+        self.pool.index(res) as u32
+    }
+
+    /// Changes whenever indices returned by [`TransformPool::index`] move
+    pub(crate) fn compactions(&self) -> u64 {
+        self.compactions
+    }
+
+    /// Local to world matrix including every parent
+    pub fn world_matrix(&mut self, res: &Res<Transform>) -> Mat4 {
+        self.propagate();
+        self.nodes[self.pool.index(res)].world
+    }
+
+    /// Move `child` into the space of `parent`, or make it a root
+    ///
+    /// # Panics
+    /// - if `parent` is `child` or one of its descendants
+    pub fn set_parent(&mut self, child: ResourceKey, parent: Option<ResourceKey>) {
+        let index = self.pool.index_of(child).expect("Resource not found");
+
+        if let Some(parent) = parent {
+            let mut ancestor = Some(parent);
+            while let Some(key) = ancestor {
+                assert!(key != child, "Transform cannot be a parent of itself");
+                ancestor = self.nodes[self.pool.index_of(key).expect("Parent not found")].parent;
+            }
+        }
+
+        if let Some(old) = self.nodes[index].parent.take() {
+            if let Some(old) = self.pool.index_of(old) {
+                self.nodes[old].children.retain(|key| *key != child);
+            }
+        }
+
+        let depth = match parent {
+            Some(parent) => {
+                let parent = self.pool.index_of(parent).expect("Parent not found");
+                self.nodes[parent].children.push(child);
+                self.nodes[parent].depth + 1
+            },
+            None => 0,
+        };
+
+        self.nodes[index].parent = parent;
+        self.set_depth(child, depth);
+        self.mark_dirty(child);
+    }
+
+    fn set_depth(&mut self, key: ResourceKey, depth: u32) {
+        let mut stack = vec![(key, depth)];
+        while let Some((key, depth)) = stack.pop() {
+            let node = &mut self.nodes[self.pool.index_of(key).expect("Resource not found")];
+            node.depth = depth;
+            stack.extend(node.children.iter().map(|child| (*child, depth + 1)));
+        }
+    }
+
+    pub(crate) fn mark_dirty(&mut self, key: ResourceKey) {
+        let node = &mut self.nodes[self.pool.index_of(key).expect("Resource not found")];
+        if !node.dirty {
+            node.dirty = true;
+            self.dirty.push(key);
+        }
     }
 
-    pub fn update(&mut self, image_index: u32) -> VulkanResult<()> {
-        if self.is_dirty {
-            let buffer = self.buffer.get_mut(image_index);
-            buffer.upload_data(self.pool.as_slice())?;
-            self.is_dirty = false;
+    /// Release indices of handles dropped while the pool was borrowed
+    pub(crate) fn remove_released(&mut self, keys: impl IntoIterator<Item = ResourceKey>) {
+        for key in keys {
+            self.remove(key);
         }
+    }
+
+    /// Release the index, children become roots
+    fn remove(&mut self, key: ResourceKey) {
+        let Some(index) = self.pool.index_of(key) else {
+            return;
+        };
+
+        for child in std::mem::take(&mut self.nodes[index].children) {
+            self.nodes[self.pool.index_of(child).expect("Resource not found")].parent = None;
+            self.set_depth(child, 0);
+            self.mark_dirty(child);
+        }
+
+        if let Some(parent) = self.nodes[index].parent.take().and_then(|parent| self.pool.index_of(parent)) {
+            self.nodes[parent].children.retain(|child| *child != key);
+        }
+
+        self.pool.remove(key);
+    }
+
+    /// Bake world matrices of changed transforms and their descendants
+    fn propagate(&mut self) {
+        if self.dirty.is_empty() {
+            return;
+        }
+
+        profiling::scope!("TransformPool::propagate");
+
+        let mut dirty = std::mem::take(&mut self.dirty);
+        dirty.retain(|key| self.pool.index_of(*key).is_some());
+        for key in &dirty {
+            self.nodes[self.pool.index_of(*key).unwrap()].dirty = false;
+        }
+        // Parents first, so a subtree is baked once
+        dirty.sort_by_key(|key| self.nodes[self.pool.index_of(*key).unwrap()].depth);
+
+        self.generation += 1;
+
+        for key in dirty {
+            let index = self.pool.index_of(key).unwrap();
+            if self.nodes[index].baked == self.generation {
+                continue;
+            }
+
+            let mut stack = vec![key];
+            while let Some(key) = stack.pop() {
+                let index = self.pool.index_of(key).unwrap();
+                let node = &self.nodes[index];
+
+                let parent = match node.parent.and_then(|parent| self.pool.index_of(parent)) {
+                    Some(parent) => self.nodes[parent].world,
+                    None => math::IDENTITY,
+                };
+                let world = math::mul(&parent, &self.pool.as_slice()[index].matrix());
+
+                let node = &mut self.nodes[index];
+                node.world = world;
+                node.baked = self.generation;
+                stack.extend_from_slice(&node.children);
+
+                self.data[index] = TransformData::new(world);
+                self.mark_pending(index..index + 1);
+            }
+        }
+    }
+
+    /// Buffers of every frame need `range` again
+    fn mark_pending(&mut self, range: Range<usize>) {
+        for pending in &mut self.pending {
+            merge_range(pending, range.clone());
+        }
+    }
+
+    /// Fill released indices once most of the buffer is unused
+    ///
+    /// Indices of moved transforms change, `PassContext::bind_transform` looks
+    /// them up while recording, so shaders always see current ones
+    fn compact(&mut self) {
+        let free = self.pool.free_count();
+        if free < COMPACT_MIN_FREE || free * 2 < self.pool.len() {
+            return;
+        }
+
+        profiling::scope!("TransformPool::compact");
+
+        let moves = self.pool.compact();
+        self.compactions += 1;
+
+        for moved in &moves {
+            self.nodes.swap(moved.from, moved.to);
+            self.data.swap(moved.from, moved.to);
+        }
+
+        self.nodes.truncate(self.pool.len());
+        self.data.truncate(self.pool.len());
+
+        for range in LinearPool::<Transform>::moved_ranges(&moves) {
+            self.mark_pending(range);
+        }
+    }
+
+    /// Write changed transforms into the buffer of `frame`
+    ///
+    /// The buffer is recreated and set 1 of the frame rewritten if the
+    /// transforms no longer fit, the frame must not be in flight
+    pub fn update(&mut self, device: &Device, descriptors: &PerFrameDescriptors, frame: usize) -> VulkanResult<()> {
+        profiling::scope!("TransformPool::update");
+
+        self.compact();
+        self.propagate();
+
+        let frame = frame % self.buffers.len();
+        let len = self.data.len();
+
+        if len > self.capacity[frame] {
+            let capacity = len.next_power_of_two();
+            let buffer = Self::create_buffer(device, capacity)?;
+            self.buffers[frame].destroy(device);
+            self.buffers[frame] = buffer;
+            self.capacity[frame] = capacity;
+            self.pending[frame].clear();
+            self.pending[frame].push(0..len);
+
+            descriptors.write_frame_buffer(device, frame, TRANSFORMS_BINDING, vk::DescriptorType::STORAGE_BUFFER, &self.buffers[frame]);
+        }
+
+        for range in std::mem::take(&mut self.pending[frame]) {
+            self.buffers[frame].upload_data_at(range.start * size_of::<TransformData>(), &self.data[range])?;
+        }
+
         Ok(())
     }
 
     pub fn destroy(&mut self, device: &Device) {
-        self.buffer.destroy(device);
+        for buffer in &mut self.buffers {
+            buffer.destroy(device);
+        }
+    }
+}
+
+/// Add `range` to the sorted and disjoint `ranges`, merged with the ones it
+/// overlaps or touches
+fn merge_range(ranges: &mut Vec<Range<usize>>, range: Range<usize>) {
+    // Ranges before `first` end before `range`, the ones from `last` start
+    // after it
+    let first = ranges.partition_point(|r| r.end < range.start);
+    let last = first + ranges[first..].partition_point(|r| r.start <= range.end);
+
+    let merged = match &ranges[first..last] {
+        [] => range,
+        touched => touched[0].start.min(range.start)..touched[touched.len() - 1].end.max(range.end),
+    };
+    ranges.splice(first..last, [merged]);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn insert(pool: &mut TransformPool, desc: TransformDesc<'_>) -> Res<Transform> {
+        pool.insert(Weak::new(), Weak::new(), desc)
+    }
+
+    fn spans(ranges: &[Range<usize>]) -> Vec<(usize, usize)> {
+        ranges.iter().map(|range| (range.start, range.end)).collect()
+    }
+
+    fn translation(m: &Mat4) -> Vec3 {
+        [m[3][0], m[3][1], m[3][2]]
+    }
+
+    #[test]
+    fn children_follow_their_parent() {
+        let mut pool = TransformPool::with_buffers(vec![]);
+        let parent = insert(&mut pool, TransformDesc::identity().translation([1.0, 0.0, 0.0]).scale([2.0; 3]));
+        let child = insert(&mut pool, TransformDesc::identity().translation([0.0, 1.0, 0.0]).parent(&parent));
+        let grandchild = insert(&mut pool, TransformDesc::identity().translation([0.0, 0.0, 1.0]).parent(&child));
+
+        assert_eq!(translation(&pool.world_matrix(&grandchild)), [1.0, 2.0, 2.0]);
+
+        pool.pool.get_mut(&parent).translation = [-1.0, 0.0, 0.0];
+        pool.mark_dirty(parent.key);
+        assert_eq!(translation(&pool.world_matrix(&child)), [-1.0, 2.0, 0.0]);
+        assert_eq!(translation(&pool.world_matrix(&grandchild)), [-1.0, 2.0, 2.0]);
+
+        pool.set_parent(child.key, None);
+        assert_eq!(translation(&pool.world_matrix(&grandchild)), [0.0, 1.0, 1.0]);
+    }
+
+    #[test]
+    fn removed_parents_leave_roots() {
+        let mut pool = TransformPool::with_buffers(vec![]);
+        let parent = insert(&mut pool, TransformDesc::identity().translation([5.0, 0.0, 0.0]));
+        let child = insert(&mut pool, TransformDesc::identity().translation([0.0, 1.0, 0.0]).parent(&parent));
+        assert_eq!(translation(&pool.world_matrix(&child)), [5.0, 1.0, 0.0]);
+
+        pool.remove_released([parent.key]);
+        assert_eq!(translation(&pool.world_matrix(&child)), [0.0, 1.0, 0.0]);
+        assert_eq!(pool.nodes[pool.index(&child) as usize].depth, 0);
+    }
+
+    #[test]
+    #[should_panic(expected = "parent of itself")]
+    fn cycles_are_rejected() {
+        let mut pool = TransformPool::with_buffers(vec![]);
+        let a = insert(&mut pool, TransformDesc::identity());
+        let b = insert(&mut pool, TransformDesc::identity().parent(&a));
+        let c = insert(&mut pool, TransformDesc::identity().parent(&b));
+
+        pool.set_parent(a.key, Some(c.key));
+    }
+
+    #[test]
+    fn ranges_merge_when_they_touch() {
+        let mut ranges = vec![];
+        for range in [8..9, 2..3, 5..6, 3..4, 0..1, 4..5] {
+            merge_range(&mut ranges, range);
+        }
+        assert_eq!(spans(&ranges), [(0, 1), (2, 6), (8, 9)]);
+
+        merge_range(&mut ranges, 1..8);
+        assert_eq!(spans(&ranges), [(0, 9)]);
+    }
+
+    #[test]
+    fn distant_writes_upload_apart() {
+        let mut pool = TransformPool::with_buffers(vec![]);
+        pool.pending = vec![vec![]; 2];
+        let transforms = (0..100).map(|_| insert(&mut pool, TransformDesc::identity())).collect::<Vec<_>>();
+        pool.propagate();
+        assert!(pool.pending.iter().all(|ranges| spans(ranges) == [(0, 100)]));
+        pool.pending = vec![vec![]; 2];
+
+        pool.mark_dirty(transforms[0].key);
+        pool.mark_dirty(transforms[99].key);
+        pool.propagate();
+        assert!(pool.pending.iter().all(|ranges| spans(ranges) == [(0, 1), (99, 100)]));
+    }
+
+    #[test]
+    fn dirty_transforms_are_queued_once() {
+        let mut pool = TransformPool::with_buffers(vec![]);
+        let transform = insert(&mut pool, TransformDesc::identity());
+
+        for _ in 0..10 {
+            pool.mark_dirty(transform.key);
+        }
+        assert_eq!(pool.dirty.len(), 1);
+
+        pool.propagate();
+        pool.mark_dirty(transform.key);
+        assert_eq!(pool.dirty.len(), 1);
     }
 }
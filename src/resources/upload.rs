@@ -0,0 +1,170 @@
+//! Sampled images in the bindless set and the copies filling them
+
+use ash::vk;
+
+use crate::bindless::Bindless;
+use crate::core::{Device, GpuBuffer, GpuBufferBuilder, Image, ImageBuilder, ImageView, ImageViewBuilder, VulkanResult};
+use crate::resources::Retired;
+
+/// Image read by shaders as `textures[index]`
+pub(crate) struct BindlessImage {
+    pub image: Image,
+    pub view: ImageView,
+    pub index: u32,
+    pub extent: vk::Extent3D,
+}
+
+impl BindlessImage {
+    /// 2D image when `extent.depth` is 1, 3D otherwise. The content is
+    /// undefined until an [`ImageUploads::push`] is recorded
+    pub fn new(device: &Device, bindless: &Bindless, format: vk::Format, extent: vk::Extent3D) -> VulkanResult<Self> {
+        let (image_type, view_type) = match extent.depth {
+            1 => (vk::ImageType::TYPE_2D, vk::ImageViewType::TYPE_2D),
+            _ => (vk::ImageType::TYPE_3D, vk::ImageViewType::TYPE_3D),
+        };
+
+        let image = ImageBuilder::new(device)
+            .extent(extent)
+            .format(format)
+            .image_type(image_type)
+            .usage(vk::ImageUsageFlags::SAMPLED | vk::ImageUsageFlags::TRANSFER_DST)
+            .build()?;
+
+        let view = ImageViewBuilder::new(device)
+            .image(image.raw)
+            .format(format)
+            .view_type(view_type)
+            .subresource_range(
+                vk::ImageSubresourceRange::default()
+                    .aspect_mask(vk::ImageAspectFlags::COLOR)
+                    .level_count(1)
+                    .layer_count(1),
+            )
+            .build()?;
+
+        Ok(Self {
+            index: bindless.alloc_texture(device, view.raw),
+            image,
+            view,
+            extent,
+        })
+    }
+
+    /// No frame in flight may read the image anymore
+    pub fn destroy(&mut self, device: &Device, bindless: &Bindless) {
+        self.view.destroy(device);
+        self.image.destroy(device);
+        bindless.release_texture(self.index);
+    }
+}
+
+/// Copy of a staging buffer into a whole image
+struct Upload {
+    staging: GpuBuffer,
+    image: vk::Image,
+    extent: vk::Extent3D,
+}
+
+/// Pixels waiting to be copied into new images before the first pass of
+/// the next frame
+#[derive(Default)]
+pub(crate) struct ImageUploads {
+    pending: Vec<Upload>,
+    /// Staging buffers
+    retired: Retired<GpuBuffer>,
+}
+
+impl ImageUploads {
+    /// Fill every texel of `image`, which no frame has read yet
+    pub fn push(&mut self, device: &Device, image: &BindlessImage, pixels: &[u8]) -> VulkanResult<()> {
+        let mut staging = GpuBufferBuilder::cpu_only(device)
+            .size(pixels.len() as u64)
+            .usage(vk::BufferUsageFlags::TRANSFER_SRC)
+            .build()?;
+        staging.upload_data(pixels)?;
+
+        self.pending.push(Upload {
+            staging,
+            image: image.image.raw,
+            extent: image.extent,
+        });
+
+        Ok(())
+    }
+
+    /// Release staging buffers no frame in flight copies from, called once
+    /// per frame
+    pub fn update(&mut self, device: &Device) {
+        self.retired.update(device);
+    }
+
+    /// Record the copies of [`ImageUploads::push`], must be outside a render
+    /// pass. Images end up in `SHADER_READ_ONLY_OPTIMAL`
+    pub unsafe fn record(&mut self, device: &ash::Device, cbuf: vk::CommandBuffer, frames: usize) {
+        profiling::scope!("ImageUploads::record");
+
+        for upload in self.pending.drain(..) {
+            let range = vk::ImageSubresourceRange::default()
+                .aspect_mask(vk::ImageAspectFlags::COLOR)
+                .level_count(1)
+                .layer_count(1);
+
+            let to_transfer = vk::ImageMemoryBarrier::default()
+                .image(upload.image)
+                .subresource_range(range)
+                .old_layout(vk::ImageLayout::UNDEFINED)
+                .new_layout(vk::ImageLayout::TRANSFER_DST_OPTIMAL)
+                .dst_access_mask(vk::AccessFlags::TRANSFER_WRITE)
+                .src_queue_family_index(vk::QUEUE_FAMILY_IGNORED)
+                .dst_queue_family_index(vk::QUEUE_FAMILY_IGNORED);
+
+            let to_shader = vk::ImageMemoryBarrier::default()
+                .image(upload.image)
+                .subresource_range(range)
+                .old_layout(vk::ImageLayout::TRANSFER_DST_OPTIMAL)
+                .new_layout(vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL)
+                .src_access_mask(vk::AccessFlags::TRANSFER_WRITE)
+                .dst_access_mask(vk::AccessFlags::SHADER_READ)
+                .src_queue_family_index(vk::QUEUE_FAMILY_IGNORED)
+                .dst_queue_family_index(vk::QUEUE_FAMILY_IGNORED);
+
+            let region = vk::BufferImageCopy::default()
+                .image_subresource(vk::ImageSubresourceLayers {
+                    aspect_mask: vk::ImageAspectFlags::COLOR,
+                    mip_level: 0,
+                    base_array_layer: 0,
+                    layer_count: 1,
+                })
+                .image_extent(upload.extent);
+
+            device.cmd_pipeline_barrier(
+                cbuf,
+                vk::PipelineStageFlags::TOP_OF_PIPE,
+                vk::PipelineStageFlags::TRANSFER,
+                vk::DependencyFlags::empty(),
+                &[],
+                &[],
+                &[to_transfer],
+            );
+            device.cmd_copy_buffer_to_image(cbuf, upload.staging.raw, upload.image, vk::ImageLayout::TRANSFER_DST_OPTIMAL, &[region]);
+            device.cmd_pipeline_barrier(
+                cbuf,
+                vk::PipelineStageFlags::TRANSFER,
+                vk::PipelineStageFlags::VERTEX_SHADER | vk::PipelineStageFlags::FRAGMENT_SHADER | vk::PipelineStageFlags::COMPUTE_SHADER,
+                vk::DependencyFlags::empty(),
+                &[],
+                &[],
+                &[to_shader],
+            );
+
+            self.retired.push(frames, upload.staging);
+        }
+    }
+
+    pub fn destroy(&mut self, device: &Device) {
+        for mut upload in self.pending.drain(..) {
+            upload.staging.destroy(device);
+        }
+        self.retired.destroy(device);
+    }
+}
@@ -0,0 +1,451 @@
+use std::sync::{Arc, Weak};
+
+use ash::vk;
+use bytemuck::{Pod, Zeroable};
+
+use crate::core::{read_shader_from_bytes, Device, GraphicsPipeline, GraphicsPipelineBuilder, PipelineLayout, PipelineLayoutBuilder, ShaderBuilder};
+use crate::frame_graph::PushHeader;
+use crate::render_context::RenderContext;
+use crate::resources::{Colormap, Create, Destroy, Get, GetMut, Pool, Ref, RefMut, Res, ResourceKey, Resources, ScalarRange, Texture};
+use crate::VulkanResult;
+
+const VERTEX_SHADER: &[u8] = include_bytes!(concat!(env!("CARGO_MANIFEST_DIR"), "/shaders/spv/volume_vert.spv"));
+const FRAGMENT_SHADER: &[u8] = include_bytes!(concat!(env!("CARGO_MANIFEST_DIR"), "/shaders/spv/volume_frag.spv"));
+
+/// Vertices of the proxy box, see `shaders/volume.vert`
+const BOX_VERTICES: u32 = 36;
+
+/// Push constants in the layout of `shaders/volume.vert`
+#[repr(C)]
+#[derive(Clone, Copy, Pod, Zeroable)]
+struct Push {
+    header: PushHeader,
+    /// First as `shaders/volume.frag` reads it like the colormaps
+    range: ScalarRange,
+    value_scale: f32,
+    value_offset: f32,
+    extent: [f32; 3],
+    samples: f32,
+    point_count: f32,
+    points: [[f32; 2]; OpacityCurve::MAX_POINTS],
+    _pad: [f32; 2],
+}
+
+/// Opacity of scalars by their position in a [`ScalarRange`], linear between
+/// up to [`OpacityCurve::MAX_POINTS`] points
+///
+/// Opacities are those of a slab one texel thick, they hold whatever the
+/// number of samples of the [`Volume`]
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct OpacityCurve {
+    points: [[f32; 2]; OpacityCurve::MAX_POINTS],
+    count: usize,
+}
+
+impl OpacityCurve {
+    /// Most points of a curve, they are pushed with every draw
+    pub const MAX_POINTS: usize = 5;
+
+    /// Curve through `points` of position in the range from 0 to 1 and
+    /// opacity, the ends hold before the first and after the last point
+    ///
+    /// # Panics
+    /// - if there are no points or more than [`OpacityCurve::MAX_POINTS`]
+    /// - if positions are not in ascending order
+    pub fn new(points: &[[f32; 2]]) -> Self {
+        assert!(
+            (1..=Self::MAX_POINTS).contains(&points.len()),
+            "Opacity curves have 1 to {} points",
+            Self::MAX_POINTS
+        );
+        assert!(points.windows(2).all(|pair| pair[0][0] <= pair[1][0]), "Opacity points must be in ascending order");
+
+        let mut curve = Self {
+            points: [[0.0; 2]; Self::MAX_POINTS],
+            count: points.len(),
+        };
+        curve.points[..points.len()].copy_from_slice(points);
+        curve
+    }
+
+    /// Transparent up to `start` of the range, then linear to `opacity` at
+    /// `end`
+    pub fn ramp(start: f32, end: f32, opacity: f32) -> Self {
+        Self::new(&[[start, 0.0], [end, opacity]])
+    }
+
+    /// Every scalar in the range is equally opaque
+    pub fn constant(opacity: f32) -> Self {
+        Self::new(&[[0.0, opacity]])
+    }
+
+    /// Points of the curve
+    pub fn points(&self) -> &[[f32; 2]] {
+        &self.points[..self.count]
+    }
+
+    /// Opacity at `t` of the range, as `shaders/volume.frag` computes it
+    pub fn opacity(&self, t: f32) -> f32 {
+        let points = self.points();
+        let [mut x0, mut y0] = points[0];
+        if t <= x0 {
+            return y0;
+        }
+
+        for &[x1, y1] in &points[1..] {
+            if t <= x1 {
+                return y0 + (y1 - y0) * (t - x0) / (x1 - x0).max(1e-6);
+            }
+            (x0, y0) = (x1, y1);
+        }
+
+        y0
+    }
+}
+
+/// Parameters of a new [`Volume`]
+pub struct VolumeDesc {
+    texture: Res<Texture>,
+    extent: [f32; 3],
+    values: [f32; 2],
+    colormap: Colormap,
+    range: ScalarRange,
+    opacity: OpacityCurve,
+    samples: u32,
+}
+
+impl VolumeDesc {
+    /// Scalars of a 3D `texture` in a 1x1x1 box around the origin, colored
+    /// by [`Colormap::Inferno`] from 0 to 1 and more opaque as they grow
+    pub fn new(texture: &Res<Texture>) -> Self {
+        Self {
+            texture: texture.clone(),
+            extent: [1.0; 3],
+            values: [1.0, 0.0],
+            colormap: Colormap::Inferno,
+            range: ScalarRange::new(0.0, 1.0),
+            opacity: OpacityCurve::ramp(0.0, 1.0, 1.0),
+            samples: 256,
+        }
+    }
+
+    /// Size of the box in world units before the transform, e.g. the voxel
+    /// spacing times the size of the texture
+    pub fn extent(mut self, extent: [f32; 3]) -> Self {
+        self.extent = extent;
+        self
+    }
+
+    /// Scalars are `texel * scale + offset`, normalized formats read texels
+    /// from 0 to 1. Hounsfield units stored as `R16Unorm` with 1024 added
+    /// are read with `65535.0` and `-1024.0`
+    pub fn values(mut self, scale: f32, offset: f32) -> Self {
+        self.values = [scale, offset];
+        self
+    }
+
+    /// Colors of the scalars in `range`, which the opacity curve follows
+    pub fn colormap(mut self, colormap: Colormap, range: ScalarRange) -> Self {
+        self.colormap = colormap;
+        self.range = range;
+        self
+    }
+
+    /// Opacity of the scalars along the range of the colormap
+    pub fn opacity(mut self, opacity: OpacityCurve) -> Self {
+        self.opacity = opacity;
+        self
+    }
+
+    /// Samples along the diagonal of the box, 256 by default
+    pub fn samples(mut self, samples: u32) -> Self {
+        self.samples = samples;
+        self
+    }
+}
+
+/// Scalar 3D [`Texture`] ray marched inside its box, see
+/// [`PassContext::draw_volume`]
+///
+/// Rays stop at the geometry of the depth buffer and once they are nearly
+/// opaque. The texel at the corner of the lowest coordinates is the first
+/// one of the texture
+///
+/// # Example
+/// ```ignore
+/// let ct = world.create::<Texture>(TextureDesc::new(512, 512, TextureFormat::R16Unorm, bytemuck::cast_slice(&samples)).depth(256))?;
+/// let volume = world.create::<Volume>(
+///     VolumeDesc::new(&ct)
+///         .extent([0.25, 0.25, 0.16])
+///         .values(65535.0, -1024.0)
+///         .colormap(Colormap::Inferno, ScalarRange::new(-200.0, 1500.0))
+///         .opacity(OpacityCurve::new(&[[0.0, 0.0], [0.2, 0.02], [0.6, 0.3], [1.0, 0.8]])),
+/// )?;
+///
+/// world.draw_frame(|graph| {
+///     graph.add_pass(scene_pass);
+///     graph.add_pass(PresentPass::new("Volumes", |builder| builder.read_depth(), |ctx, _| unsafe {
+///         ctx.draw_volume(&volume);
+///     }));
+/// })?;
+/// ```
+///
+/// [`PassContext::draw_volume`]: crate::frame_graph::PassContext::draw_volume
+pub struct Volume {
+    /// Colors of the scalars
+    pub colormap: Colormap,
+    /// Scalars mapped to the colormap and the opacity curve
+    pub range: ScalarRange,
+    /// Opacity along the range
+    pub opacity: OpacityCurve,
+    /// Samples along the diagonal of the box, more show finer detail at a
+    /// higher cost
+    pub samples: u32,
+    /// Kept alive while the volume samples it
+    texture: Res<Texture>,
+    texture_idx: u32,
+    extent: [f32; 3],
+    values: [f32; 2],
+}
+
+impl Volume {
+    /// Texture of the scalars
+    pub fn texture(&self) -> &Res<Texture> {
+        &self.texture
+    }
+
+    /// Size of the box in world units before the transform
+    pub fn extent(&self) -> [f32; 3] {
+        self.extent
+    }
+
+    fn push_constants(&self, transform: Option<u32>, colormap_idx: u32, depth_idx: u32) -> Push {
+        let mut tex_idx = [0; 8];
+        tex_idx[..3].copy_from_slice(&[colormap_idx, self.texture_idx, depth_idx]);
+
+        let mut points = [[0.0; 2]; OpacityCurve::MAX_POINTS];
+        points[..self.opacity.count].copy_from_slice(self.opacity.points());
+
+        Push {
+            header: PushHeader::new(transform, tex_idx),
+            range: self.range,
+            value_scale: self.values[0],
+            value_offset: self.values[1],
+            extent: self.extent,
+            samples: self.samples as f32,
+            point_count: self.opacity.count as f32,
+            points,
+            _pad: [0.0; 2],
+        }
+    }
+}
+
+impl Destroy for Volume {
+    fn destroy(key: ResourceKey, _ctx: Weak<RenderContext>, resources: Weak<Resources>) {
+        let Some(resources) = resources.upgrade() else {
+            return;
+        };
+
+        // Nothing is on the GPU but the texture, which has its own retirement
+        let volume = resources.volumes.write().pool.remove(key);
+        drop(volume);
+    }
+}
+
+impl Create for Volume {
+    type Desc<'a> = VolumeDesc;
+    /// # Panics
+    /// - if the texture is not 3D
+    fn create(ctx: &Arc<RenderContext>, resources: &Arc<Resources>, desc: Self::Desc<'_>) -> VulkanResult<Res<Self>> {
+        let texture_idx = {
+            let textures = resources.textures.read();
+            let texture = textures.pool.get(&desc.texture);
+            assert!(texture.extent()[2] > 1, "Volumes need a 3D texture");
+            texture.index()
+        };
+
+        let volume = Volume {
+            colormap: desc.colormap,
+            range: desc.range,
+            opacity: desc.opacity,
+            samples: desc.samples,
+            texture: desc.texture,
+            texture_idx,
+            extent: desc.extent,
+            values: desc.values,
+        };
+
+        Ok(resources
+            .volumes
+            .write()
+            .pool
+            .insert(Arc::downgrade(ctx), Arc::downgrade(resources), volume))
+    }
+}
+
+impl Get for Volume {
+    fn get<'a>(resources: &'a Resources, res: &Res<Self>) -> Ref<'a, Self> {
+        let store = resources.volumes.try_read().expect("Volumes are already borrowed mutably");
+        Ref(parking_lot::RwLockReadGuard::map(store, |store| store.pool.get(res)))
+    }
+}
+
+impl GetMut for Volume {
+    fn get_mut<'a>(resources: &'a Resources, res: &Res<Self>) -> RefMut<'a, Self> {
+        let store = resources.volumes.try_write().expect("Volumes are already borrowed");
+        RefMut(parking_lot::RwLockWriteGuard::map(store, |store| store.pool.get_mut(res)))
+    }
+}
+
+/// Volumes with the pipeline ray marching them
+pub struct VolumeStore {
+    pub(crate) pool: Pool<Volume>,
+    layout: PipelineLayout,
+    pipeline: GraphicsPipeline,
+}
+
+impl VolumeStore {
+    pub fn new(device: &Device, render_pass: vk::RenderPass, set_layouts: Vec<vk::DescriptorSetLayout>) -> VulkanResult<Self> {
+        // The layout of every raster pipeline, set 0 holds the textures and
+        // set 1 the camera and transforms
+        let layout = PipelineLayoutBuilder::new(device)
+            .set_layouts(set_layouts)
+            .push_constant(vec![vk::PushConstantRange::default()
+                .offset(0)
+                .size(128)
+                .stage_flags(vk::ShaderStageFlags::VERTEX | vk::ShaderStageFlags::FRAGMENT)])
+            .build()?;
+
+        let spv = read_shader_from_bytes(VERTEX_SHADER).expect("Invalid volume vertex shader");
+        let vertex = ShaderBuilder::new(device).bytecode(&spv).build()?;
+        let spv = read_shader_from_bytes(FRAGMENT_SHADER).expect("Invalid volume fragment shader");
+        let fragment = ShaderBuilder::new(device).bytecode(&spv).build()?;
+
+        // Colors are premultiplied by alpha
+        let color_blend = vk::PipelineColorBlendAttachmentState::default()
+            .color_write_mask(vk::ColorComponentFlags::RGBA)
+            .blend_enable(true)
+            .src_color_blend_factor(vk::BlendFactor::ONE)
+            .dst_color_blend_factor(vk::BlendFactor::ONE_MINUS_SRC_ALPHA)
+            .color_blend_op(vk::BlendOp::ADD)
+            .src_alpha_blend_factor(vk::BlendFactor::ONE)
+            .dst_alpha_blend_factor(vk::BlendFactor::ONE_MINUS_SRC_ALPHA)
+            .alpha_blend_op(vk::BlendOp::ADD);
+
+        // Viewport and scissors are dynamic, set for every draw. Rays read
+        // the depth buffer themselves, geometry inside the box would hide
+        // the samples in front of it from a depth test
+        let pipeline = GraphicsPipelineBuilder::new(device)
+            .vertex_shader(vertex.raw)
+            .fragment_shader(fragment.raw)
+            .render_pass(render_pass)
+            .pipeline_layout(layout.raw)
+            .viewport(vec![vk::Viewport::default().width(1.0).height(1.0).max_depth(1.0)])
+            .scissors(vec![vk::Rect2D::default()])
+            .input_assembly(vk::PipelineInputAssemblyStateCreateInfo::default().topology(vk::PrimitiveTopology::TRIANGLE_LIST))
+            .rasterization(
+                vk::PipelineRasterizationStateCreateInfo::default()
+                    .polygon_mode(vk::PolygonMode::FILL)
+                    .line_width(1.0)
+                    .cull_mode(vk::CullModeFlags::NONE),
+            )
+            .multisampling(vk::PipelineMultisampleStateCreateInfo::default().rasterization_samples(vk::SampleCountFlags::TYPE_1))
+            .color_blending(vk::PipelineColorBlendStateCreateInfo::default().attachments(&[color_blend]))
+            .depth_stencil(
+                vk::PipelineDepthStencilStateCreateInfo::default()
+                    .depth_test_enable(false)
+                    .depth_write_enable(false),
+            )
+            .dynamic_state(vec![vk::DynamicState::VIEWPORT, vk::DynamicState::SCISSOR])
+            .vertex_input_info(vk::PipelineVertexInputStateCreateInfo::default())
+            .build()?;
+
+        Ok(Self {
+            pool: Pool::new(),
+            layout,
+            pipeline,
+        })
+    }
+
+    /// Ray march `volume` over the whole target, must be inside the render
+    /// pass
+    ///
+    /// `sets` are the bindless and per-frame sets of the frame, `transform`
+    /// is the index of the world matrix or `None` for identity,
+    /// `colormap_idx` the lookup table of [`Volume::colormap`] and
+    /// `depth_idx` the depth buffer in `textures[]`
+    #[allow(clippy::too_many_arguments)]
+    pub(crate) unsafe fn draw(
+        &self,
+        device: &ash::Device,
+        cbuf: vk::CommandBuffer,
+        resolution: vk::Extent2D,
+        sets: &[vk::DescriptorSet],
+        volume: &Volume,
+        transform: Option<u32>,
+        colormap_idx: u32,
+        depth_idx: u32,
+    ) {
+        let viewport = vk::Viewport::default()
+            .width(resolution.width as f32)
+            .height(resolution.height as f32)
+            .max_depth(1.0);
+        let scissor = vk::Rect2D::default().extent(resolution);
+
+        device.cmd_bind_pipeline(cbuf, vk::PipelineBindPoint::GRAPHICS, self.pipeline.raw);
+        device.cmd_bind_descriptor_sets(cbuf, vk::PipelineBindPoint::GRAPHICS, self.layout.raw, 0, sets, &[]);
+        device.cmd_set_viewport(cbuf, 0, &[viewport]);
+        device.cmd_set_scissor(cbuf, 0, &[scissor]);
+        device.cmd_push_constants(
+            cbuf,
+            self.layout.raw,
+            vk::ShaderStageFlags::VERTEX | vk::ShaderStageFlags::FRAGMENT,
+            0,
+            bytemuck::bytes_of(&volume.push_constants(transform, colormap_idx, depth_idx)),
+        );
+        device.cmd_draw(cbuf, BOX_VERTICES, 1, 0, 0);
+    }
+
+    pub fn destroy(&mut self, device: &Device) {
+        // Textures of the volumes are retired to their own store
+        self.pool.slots.clear();
+        self.pipeline.destroy(device);
+        unsafe {
+            device.destroy_pipeline_layout(self.layout.raw, None);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn opacity_is_linear_between_points() {
+        let curve = OpacityCurve::new(&[[0.2, 0.0], [0.4, 0.5], [0.8, 0.1]]);
+
+        assert_eq!(curve.opacity(0.0), 0.0);
+        assert!((curve.opacity(0.3) - 0.25).abs() < 1e-6);
+        assert!((curve.opacity(0.6) - 0.3).abs() < 1e-6);
+        assert_eq!(curve.opacity(1.0), 0.1);
+
+        assert_eq!(OpacityCurve::constant(0.4).opacity(0.7), 0.4);
+        // Steps are kept by repeated positions
+        let step = OpacityCurve::new(&[[0.5, 0.0], [0.5, 1.0]]);
+        assert_eq!((step.opacity(0.49), step.opacity(0.51)), (0.0, 1.0));
+    }
+
+    #[test]
+    #[should_panic(expected = "ascending")]
+    fn opacity_points_ascend() {
+        OpacityCurve::new(&[[0.5, 0.0], [0.2, 1.0]]);
+    }
+
+    #[test]
+    fn push_constants_fill_the_block() {
+        assert_eq!(size_of::<Push>(), 128);
+        // user_data[10] of shaders/volume.frag after 36 bytes of indices
+        assert_eq!(std::mem::offset_of!(Push, point_count), 36 + 10 * 4);
+        assert_eq!(std::mem::offset_of!(Push, points), 36 + 11 * 4);
+    }
+}
@@ -0,0 +1,338 @@
+//! Golden-image test support
+//!
+//! Scenes are rendered by a headless [`WorldRenderer`] and compared with
+//! reference images stored in the repository
+//!
+//! - References are looked up as `<reference dir>/<name>.ppm`
+//! - On mismatch `<name>.actual.ppm` and `<name>.diff.ppm` are written into
+//!   the output directory, mismatched pixels are red in the diff image
+//! - `ALUMINIUM_BLESS=1` overwrites references with the rendered images
+//!
+//! Any available Vulkan driver is used, set `VK_ICD_FILENAMES` to pin a
+//! software driver (lavapipe, `SwiftShader`). When no driver is found
+//! [`headless`] returns `None` and the test should be skipped, unless
+//! `ALUMINIUM_REQUIRE_GPU=1` is set, then it panics so CI without a driver
+//! fails instead of passing without rendering anything
+//!
+//! # Example
+//! ```ignore
+//! let Some(mut world) = aluminium::testing::headless(64, 64) else {
+//!     return;
+//! };
+//!
+//! let frame = aluminium::testing::render(&mut world, |graph| { ... })?;
+//!
+//! Golden::new(concat!(env!("CARGO_MANIFEST_DIR"), "/tests/golden")).assert("triangle", &frame);
+//! ```
+
+use std::path::{Path, PathBuf};
+
+use thiserror::Error;
+use tracing::warn;
+
+use crate::{CaptureError, Screenshot, TemporalFrameGraph, VulkanError, VulkanResult, WorldRenderer};
+
+/// Environment variable which turns comparison into reference update
+pub const BLESS_ENV: &str = "ALUMINIUM_BLESS";
+
+/// Environment variable which turns a missing Vulkan device into a failure
+pub const REQUIRE_GPU_ENV: &str = "ALUMINIUM_REQUIRE_GPU";
+
+/// Create a headless [`WorldRenderer`] or `None` if Vulkan is not available
+///
+/// # Panics
+/// - if Vulkan is not available and [`REQUIRE_GPU_ENV`] is set
+pub fn headless(width: u32, height: u32) -> Option<WorldRenderer> {
+    match WorldRenderer::headless(width, height) {
+        Ok(world) => Some(world),
+        Err(err) if env_flag(REQUIRE_GPU_ENV) => {
+            panic!("{} is set, but there is no usable Vulkan device: {}", REQUIRE_GPU_ENV, err)
+        },
+        Err(err) => {
+            warn!("Skip GPU test, no usable Vulkan device: {}", err);
+            None
+        },
+    }
+}
+
+/// Whether the variable `name` is set to anything but `0`
+fn env_flag(name: &str) -> bool {
+    std::env::var_os(name).is_some_and(|value| value != "0")
+}
+
+/// Draw a single frame and read it back
+pub fn render<'frame, F>(world: &mut WorldRenderer, callback: F) -> VulkanResult<Screenshot>
+where
+    F: FnOnce(&mut TemporalFrameGraph<'frame>),
+{
+    world.capture_to_memory()?;
+    world.draw_frame(callback)?;
+    world
+        .take_capture()?
+        .ok_or(VulkanError::Capture(CaptureError::NoCapture))
+}
+
+/// Allowed difference between rendered and reference images
+#[derive(Clone, Copy, Debug)]
+pub struct Tolerance {
+    /// Maximum absolute difference of any color channel for a pixel to match
+    pub channel: u8,
+    /// Number of pixels which may exceed `channel`
+    pub pixels: usize,
+}
+
+impl Default for Tolerance {
+    /// Rasterization rules differ slightly between drivers
+    fn default() -> Self {
+        Self { channel: 2, pixels: 0 }
+    }
+}
+
+/// Result of a per-pixel comparison
+pub struct ImageDiff {
+    /// Pixels exceeding [`Tolerance::channel`]
+    pub mismatched: usize,
+    /// Largest channel difference over the whole image
+    pub max_delta: u8,
+    /// Mismatched pixels in red over the darkened reference
+    pub image: Screenshot,
+}
+
+impl ImageDiff {
+    /// Compare two images of the same size
+    ///
+    /// # Panics
+    /// - if the images have different sizes
+    pub fn new(actual: &Screenshot, expected: &Screenshot, channel: u8) -> Self {
+        assert_eq!(
+            (actual.width, actual.height),
+            (expected.width, expected.height),
+            "Compared images must have the same size"
+        );
+
+        let mut mismatched = 0;
+        let mut max_delta = 0;
+        let mut pixels = Vec::with_capacity(actual.pixels.len());
+
+        for (a, e) in actual.pixels.chunks_exact(4).zip(expected.pixels.chunks_exact(4)) {
+            let delta = a.iter().zip(e).map(|(a, e)| a.abs_diff(*e)).max().unwrap_or(0);
+            max_delta = max_delta.max(delta);
+
+            if delta > channel {
+                mismatched += 1;
+                pixels.extend_from_slice(&[255, 0, 0, 255]);
+            } else {
+                pixels.extend_from_slice(&[e[0] / 4, e[1] / 4, e[2] / 4, 255]);
+            }
+        }
+
+        Self {
+            mismatched,
+            max_delta,
+            image: Screenshot {
+                width: actual.width,
+                height: actual.height,
+                pixels,
+            },
+        }
+    }
+}
+
+/// Errors of golden-image comparison
+#[derive(Debug, Error)]
+pub enum GoldenError {
+    /// Reference image does not exist yet
+    #[error("Missing reference {0:?}, run with {BLESS_ENV}=1 to create it")]
+    MissingReference(PathBuf),
+    /// Rendered and reference images have different sizes
+    #[error("Size mismatch: rendered {actual:?}, reference {expected:?}")]
+    SizeMismatch {
+        /// Rendered size
+        actual: (u32, u32),
+        /// Reference size
+        expected: (u32, u32),
+    },
+    /// Too many pixels differ
+    #[error("{mismatched} pixels differ (max delta {max_delta}), see {diff:?}")]
+    Mismatch {
+        /// Pixels exceeding the channel tolerance
+        mismatched: usize,
+        /// Largest channel difference
+        max_delta: u8,
+        /// Written diff image
+        diff: PathBuf,
+    },
+    /// Error reading or writing images
+    #[error("{0}")]
+    Image(VulkanError),
+}
+
+/// Compares rendered frames with reference images
+///
+/// # Example
+/// ```ignore
+/// Golden::new("tests/golden")
+///     .tolerance(Tolerance { channel: 4, pixels: 16 })
+///     .assert("cube", &frame);
+/// ```
+pub struct Golden {
+    reference_dir: PathBuf,
+    output_dir: PathBuf,
+    tolerance: Tolerance,
+}
+
+impl Golden {
+    /// Actual and diff images go into `target/golden` unless changed with
+    /// [`Golden::output_dir`]
+    pub fn new<P: Into<PathBuf>>(reference_dir: P) -> Self {
+        let reference_dir = reference_dir.into();
+        let output_dir = std::env::var_os("CARGO_TARGET_DIR")
+            .map(PathBuf::from)
+            .or_else(|| std::env::var_os("CARGO_MANIFEST_DIR").map(|dir| Path::new(&dir).join("target")))
+            .unwrap_or_else(|| PathBuf::from("target"))
+            .join("golden");
+
+        Self {
+            reference_dir,
+            output_dir,
+            tolerance: Tolerance::default(),
+        }
+    }
+
+    /// Directory for actual and diff images
+    pub fn output_dir<P: Into<PathBuf>>(mut self, dir: P) -> Self {
+        self.output_dir = dir.into();
+        self
+    }
+
+    /// Allowed difference, see [`Tolerance::default`]
+    pub fn tolerance(mut self, tolerance: Tolerance) -> Self {
+        self.tolerance = tolerance;
+        self
+    }
+
+    /// Compare `actual` with the reference `name`
+    pub fn check(&self, name: &str, actual: &Screenshot) -> Result<(), GoldenError> {
+        let reference = self.reference_dir.join(format!("{name}.ppm"));
+
+        if env_flag(BLESS_ENV) {
+            std::fs::create_dir_all(&self.reference_dir).map_err(|e| GoldenError::Image(VulkanError::Capture(crate::CaptureError::Io(e))))?;
+            return actual.save(&reference).map_err(GoldenError::Image);
+        }
+
+        if !reference.exists() {
+            self.write_output(&format!("{name}.actual.ppm"), actual)?;
+            return Err(GoldenError::MissingReference(reference));
+        }
+
+        let expected = Screenshot::open(&reference).map_err(GoldenError::Image)?;
+
+        if (actual.width, actual.height) != (expected.width, expected.height) {
+            self.write_output(&format!("{name}.actual.ppm"), actual)?;
+            return Err(GoldenError::SizeMismatch {
+                actual: (actual.width, actual.height),
+                expected: (expected.width, expected.height),
+            });
+        }
+
+        let diff = ImageDiff::new(actual, &expected, self.tolerance.channel);
+
+        if diff.mismatched > self.tolerance.pixels {
+            self.write_output(&format!("{name}.actual.ppm"), actual)?;
+            let path = self.write_output(&format!("{name}.diff.ppm"), &diff.image)?;
+            return Err(GoldenError::Mismatch {
+                mismatched: diff.mismatched,
+                max_delta: diff.max_delta,
+                diff: path,
+            });
+        }
+
+        Ok(())
+    }
+
+    /// Same as [`Golden::check`], but panics on mismatch
+    #[track_caller]
+    pub fn assert(&self, name: &str, actual: &Screenshot) {
+        if let Err(err) = self.check(name, actual) {
+            panic!("Golden image {name:?}: {err}");
+        }
+    }
+
+    fn write_output(&self, file: &str, image: &Screenshot) -> Result<PathBuf, GoldenError> {
+        std::fs::create_dir_all(&self.output_dir).map_err(|e| GoldenError::Image(VulkanError::Capture(crate::CaptureError::Io(e))))?;
+        let path = self.output_dir.join(file);
+        image.save(&path).map_err(GoldenError::Image)?;
+        Ok(path)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn solid(width: u32, height: u32, color: [u8; 4]) -> Screenshot {
+        Screenshot {
+            width,
+            height,
+            pixels: color.repeat((width * height) as usize),
+        }
+    }
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("aluminium-golden-{}-{name}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        dir
+    }
+
+    #[test]
+    fn diff_counts_pixels_over_tolerance() {
+        let expected = solid(2, 2, [100, 100, 100, 255]);
+        let mut actual = expected.clone();
+        actual.pixels[0] = 103;
+        actual.pixels[4] = 101;
+
+        let diff = ImageDiff::new(&actual, &expected, 2);
+
+        assert_eq!(diff.mismatched, 1);
+        assert_eq!(diff.max_delta, 3);
+        assert_eq!(&diff.image.pixels[..4], &[255, 0, 0, 255]);
+        assert_eq!(&diff.image.pixels[4..8], &[25, 25, 25, 255]);
+    }
+
+    #[test]
+    fn ppm_round_trip() {
+        let dir = temp_dir("ppm");
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("image.ppm");
+
+        let mut image = solid(3, 2, [10, 20, 30, 255]);
+        image.pixels[4..8].copy_from_slice(&[200, 100, 0, 255]);
+        image.save(&path).unwrap();
+
+        let loaded = Screenshot::open(&path).unwrap();
+
+        assert_eq!((loaded.width, loaded.height), (3, 2));
+        assert_eq!(loaded.pixels, image.pixels);
+    }
+
+    #[test]
+    fn check_reports_missing_and_mismatched_references() {
+        let dir = temp_dir("check");
+        let golden = Golden::new(dir.join("reference")).output_dir(dir.join("output"));
+        let image = solid(4, 4, [0, 128, 255, 255]);
+
+        assert!(matches!(golden.check("solid", &image), Err(GoldenError::MissingReference(_))));
+        assert!(dir.join("output/solid.actual.ppm").exists());
+
+        std::fs::create_dir_all(dir.join("reference")).unwrap();
+        image.save(dir.join("reference/solid.ppm")).unwrap();
+        assert!(golden.check("solid", &image).is_ok());
+
+        let other = solid(4, 4, [0, 0, 0, 255]);
+        assert!(matches!(golden.check("solid", &other), Err(GoldenError::Mismatch { mismatched: 16, .. })));
+        assert!(dir.join("output/solid.diff.ppm").exists());
+
+        let tolerant = golden.tolerance(Tolerance { channel: 2, pixels: 16 });
+        assert!(tolerant.check("solid", &other).is_ok());
+    }
+}
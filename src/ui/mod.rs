@@ -0,0 +1,566 @@
+//! Textures, buffers and pipeline of [`EguiPass`](crate::EguiPass)
+
+use std::collections::HashMap;
+
+use ash::vk;
+use bytemuck::{Pod, Zeroable};
+use egui::epaint::{ImageDelta, Primitive};
+use egui::{ClippedPrimitive, ImageData, TextureFilter, TextureId, TexturesDelta};
+use tracing::warn;
+
+use crate::bindless::{Bindless, SAMPLER_CLAMP, SAMPLER_MIP_POINT};
+use crate::core::{
+    read_shader_from_bytes, Device, GpuBuffer, GpuBufferBuilder, GraphicsPipeline, GraphicsPipelineBuilder, Image, ImageBuilder, ImageView,
+    ImageViewBuilder, PipelineLayout, PipelineLayoutBuilder, ShaderBuilder, VulkanResult,
+};
+use crate::resources::Retired;
+
+const VERTEX_SHADER: &[u8] = include_bytes!(concat!(env!("CARGO_MANIFEST_DIR"), "/shaders/spv/egui_vert.spv"));
+const FRAGMENT_SHADER: &[u8] = include_bytes!(concat!(env!("CARGO_MANIFEST_DIR"), "/shaders/spv/egui_frag.spv"));
+
+/// Vertices every frame buffer holds before growing
+const INITIAL_VERTICES: usize = 16 * 1024;
+
+/// Indices every frame buffer holds before growing
+const INITIAL_INDICES: usize = 32 * 1024;
+
+/// Push constants in the layout of `shaders/egui.vert`
+#[repr(C)]
+#[derive(Clone, Copy, Pod, Zeroable)]
+struct Push {
+    transform_idx: u32,
+    /// Bindless texture and sampler
+    tex_idx: [u32; 8],
+    /// Screen size in points
+    screen_size: [f32; 2],
+}
+
+/// Texture egui manages, `index` is its place in the bindless set
+struct EguiTexture {
+    image: Image,
+    view: ImageView,
+    index: u32,
+    sampler: u32,
+}
+
+impl EguiTexture {
+    fn destroy(&mut self, device: &Device, bindless: &Bindless) {
+        self.view.destroy(device);
+        self.image.destroy(device);
+        bindless.release_texture(self.index);
+    }
+}
+
+/// Copy of a staging buffer into a texture waiting for the next frame
+struct Upload {
+    staging: GpuBuffer,
+    image: vk::Image,
+    offset: vk::Offset3D,
+    extent: vk::Extent3D,
+    /// The image has no content yet and is not read by any frame
+    new: bool,
+}
+
+/// Vertices and indices of one frame in flight
+struct FrameBuffers {
+    vertices: GpuBuffer,
+    indices: GpuBuffer,
+    vertex_capacity: usize,
+    index_capacity: usize,
+}
+
+impl FrameBuffers {
+    fn new(device: &Device, vertex_capacity: usize, index_capacity: usize) -> VulkanResult<Self> {
+        let vertices = GpuBufferBuilder::cpu_only(device)
+            .size((vertex_capacity * size_of::<egui::epaint::Vertex>()) as u64)
+            .usage(vk::BufferUsageFlags::VERTEX_BUFFER)
+            .build()?;
+
+        let indices = GpuBufferBuilder::cpu_only(device)
+            .size((index_capacity * size_of::<u32>()) as u64)
+            .usage(vk::BufferUsageFlags::INDEX_BUFFER)
+            .build()?;
+
+        Ok(Self {
+            vertices,
+            indices,
+            vertex_capacity,
+            index_capacity,
+        })
+    }
+
+    fn destroy(&mut self, device: &Device) {
+        self.vertices.destroy(device);
+        self.indices.destroy(device);
+    }
+}
+
+/// Everything egui draws with, textures live in the bindless set
+///
+/// Texture changes are copied before the first pass of the next frame,
+/// replaced and freed textures are released once no frame in flight reads
+/// them
+pub(crate) struct EguiRenderer {
+    textures: HashMap<TextureId, EguiTexture>,
+    uploads: Vec<Upload>,
+    frames: Vec<FrameBuffers>,
+    /// Staging buffers
+    retired_buffers: Retired<GpuBuffer>,
+    /// Replaced and freed textures
+    retired_textures: Retired<EguiTexture>,
+    /// Buffers of the current frame are filled, see [`EguiRenderer::draw`]
+    drawn: bool,
+    layout: PipelineLayout,
+    pipeline: GraphicsPipeline,
+}
+
+impl EguiRenderer {
+    pub fn new(
+        device: &Device,
+        frame_count: usize,
+        render_pass: vk::RenderPass,
+        set_layouts: Vec<vk::DescriptorSetLayout>,
+    ) -> VulkanResult<Self> {
+        let frames = (0..frame_count)
+            .map(|_| FrameBuffers::new(device, INITIAL_VERTICES, INITIAL_INDICES))
+            .collect::<VulkanResult<Vec<_>>>()?;
+
+        // The layout of every raster pipeline, set 0 holds the textures
+        let layout = PipelineLayoutBuilder::new(device)
+            .set_layouts(set_layouts)
+            .push_constant(vec![vk::PushConstantRange::default()
+                .offset(0)
+                .size(128)
+                .stage_flags(vk::ShaderStageFlags::VERTEX | vk::ShaderStageFlags::FRAGMENT)])
+            .build()?;
+
+        let spv = read_shader_from_bytes(VERTEX_SHADER).expect("Invalid egui vertex shader");
+        let vertex = ShaderBuilder::new(device).bytecode(&spv).build()?;
+        let spv = read_shader_from_bytes(FRAGMENT_SHADER).expect("Invalid egui fragment shader");
+        let fragment = ShaderBuilder::new(device).bytecode(&spv).build()?;
+
+        // egui::epaint::Vertex: position and UV in f32, sRGBA color in u8
+        let bindings = [vk::VertexInputBindingDescription {
+            binding: 0,
+            stride: size_of::<egui::epaint::Vertex>() as u32,
+            input_rate: vk::VertexInputRate::VERTEX,
+        }];
+        let attributes = [
+            (vk::Format::R32G32_SFLOAT, 0),
+            (vk::Format::R32G32_SFLOAT, 8),
+            (vk::Format::R8G8B8A8_UNORM, 16),
+        ]
+        .into_iter()
+        .enumerate()
+        .map(|(location, (format, offset))| vk::VertexInputAttributeDescription {
+            location: location as u32,
+            binding: 0,
+            format,
+            offset,
+        })
+        .collect::<Vec<_>>();
+
+        let vertex_input_info = vk::PipelineVertexInputStateCreateInfo::default()
+            .vertex_binding_descriptions(&bindings)
+            .vertex_attribute_descriptions(&attributes);
+
+        // Colors and textures have premultiplied alpha
+        let color_blend = vk::PipelineColorBlendAttachmentState::default()
+            .color_write_mask(vk::ColorComponentFlags::RGBA)
+            .blend_enable(true)
+            .src_color_blend_factor(vk::BlendFactor::ONE)
+            .dst_color_blend_factor(vk::BlendFactor::ONE_MINUS_SRC_ALPHA)
+            .color_blend_op(vk::BlendOp::ADD)
+            .src_alpha_blend_factor(vk::BlendFactor::ONE_MINUS_DST_ALPHA)
+            .dst_alpha_blend_factor(vk::BlendFactor::ONE)
+            .alpha_blend_op(vk::BlendOp::ADD);
+
+        // Viewport and scissors are dynamic, set for every draw
+        let pipeline = GraphicsPipelineBuilder::new(device)
+            .vertex_shader(vertex.raw)
+            .fragment_shader(fragment.raw)
+            .render_pass(render_pass)
+            .pipeline_layout(layout.raw)
+            .viewport(vec![vk::Viewport::default().width(1.0).height(1.0).max_depth(1.0)])
+            .scissors(vec![vk::Rect2D::default()])
+            .input_assembly(vk::PipelineInputAssemblyStateCreateInfo::default().topology(vk::PrimitiveTopology::TRIANGLE_LIST))
+            .rasterization(
+                vk::PipelineRasterizationStateCreateInfo::default()
+                    .polygon_mode(vk::PolygonMode::FILL)
+                    .line_width(1.0)
+                    .cull_mode(vk::CullModeFlags::NONE),
+            )
+            .depth_stencil(
+                vk::PipelineDepthStencilStateCreateInfo::default()
+                    .depth_test_enable(false)
+                    .depth_write_enable(false),
+            )
+            .multisampling(vk::PipelineMultisampleStateCreateInfo::default().rasterization_samples(vk::SampleCountFlags::TYPE_1))
+            .color_blending(vk::PipelineColorBlendStateCreateInfo::default().attachments(&[color_blend]))
+            .dynamic_state(vec![vk::DynamicState::VIEWPORT, vk::DynamicState::SCISSOR])
+            .vertex_input_info(vertex_input_info)
+            .build()?;
+
+        Ok(Self {
+            textures: HashMap::new(),
+            uploads: vec![],
+            frames,
+            retired_buffers: Retired::default(),
+            retired_textures: Retired::default(),
+            drawn: false,
+            layout,
+            pipeline,
+        })
+    }
+
+    /// Create, update and free textures as egui asks, see
+    /// [`WorldRenderer::update_egui_textures`]
+    ///
+    /// [`WorldRenderer::update_egui_textures`]: crate::WorldRenderer::update_egui_textures
+    pub fn set_textures(&mut self, device: &Device, bindless: &Bindless, delta: &TexturesDelta) -> VulkanResult<()> {
+        profiling::scope!("EguiRenderer::set_textures");
+
+        let frames = self.frames.len();
+
+        for (id, image_delta) in &delta.set {
+            self.set_texture(device, bindless, *id, image_delta, frames)?;
+        }
+
+        for id in &delta.free {
+            if let Some(texture) = self.textures.remove(id) {
+                self.retired_textures.push(frames, texture);
+            }
+        }
+
+        Ok(())
+    }
+
+    fn set_texture(&mut self, device: &Device, bindless: &Bindless, id: TextureId, delta: &ImageDelta, frames: usize) -> VulkanResult<()> {
+        let ImageData::Color(image) = &delta.image;
+        let [width, height] = image.size.map(|size| size as u32);
+
+        if width == 0 || height == 0 {
+            return Ok(());
+        }
+
+        let mut staging = GpuBufferBuilder::cpu_only(device)
+            .size(image.pixels.len() as u64 * 4)
+            .usage(vk::BufferUsageFlags::TRANSFER_SRC)
+            .build()?;
+        staging.upload_data(&image.pixels)?;
+
+        let extent = vk::Extent3D { width, height, depth: 1 };
+
+        let Some([x, y]) = delta.pos else {
+            // The whole texture, a new image replaces the old one
+            let format = vk::Format::R8G8B8A8_SRGB;
+            let image = ImageBuilder::new(device)
+                .extent(extent)
+                .format(format)
+                .usage(vk::ImageUsageFlags::SAMPLED | vk::ImageUsageFlags::TRANSFER_DST)
+                .build()?;
+
+            let view = ImageViewBuilder::new(device)
+                .image(image.raw)
+                .format(format)
+                .subresource_range(
+                    vk::ImageSubresourceRange::default()
+                        .aspect_mask(vk::ImageAspectFlags::COLOR)
+                        .level_count(1)
+                        .layer_count(1),
+                )
+                .build()?;
+
+            let sampler = match delta.options.magnification {
+                TextureFilter::Nearest => SAMPLER_MIP_POINT,
+                TextureFilter::Linear => SAMPLER_CLAMP,
+            };
+
+            self.uploads.push(Upload {
+                staging,
+                image: image.raw,
+                offset: vk::Offset3D::default(),
+                extent,
+                new: true,
+            });
+
+            let texture = EguiTexture {
+                index: bindless.alloc_texture(device, view.raw),
+                image,
+                view,
+                sampler,
+            };
+
+            if let Some(old) = self.textures.insert(id, texture) {
+                self.retired_textures.push(frames, old);
+            }
+
+            return Ok(());
+        };
+
+        let Some(texture) = self.textures.get(&id) else {
+            warn!(id = ?id, "Partial update of a missing egui texture");
+            self.retired_buffers.push(frames, staging);
+            return Ok(());
+        };
+
+        self.uploads.push(Upload {
+            staging,
+            image: texture.image.raw,
+            offset: vk::Offset3D {
+                x: x as i32,
+                y: y as i32,
+                z: 0,
+            },
+            extent,
+            new: false,
+        });
+
+        Ok(())
+    }
+
+    /// Release staging buffers and textures no frame in flight reads, called
+    /// once per frame
+    pub fn update(&mut self, device: &Device, bindless: &Bindless) {
+        profiling::scope!("EguiRenderer::update");
+
+        self.drawn = false;
+
+        self.retired_buffers.update(device);
+
+        self.retired_textures.update_with(|mut texture| texture.destroy(device, bindless));
+    }
+
+    /// Record the texture copies of [`EguiRenderer::set_textures`], must be
+    /// outside a render pass
+    pub(crate) unsafe fn record_uploads(&mut self, device: &ash::Device, cbuf: vk::CommandBuffer) {
+        profiling::scope!("EguiRenderer::record_uploads");
+
+        let frames = self.frames.len();
+
+        for upload in self.uploads.drain(..) {
+            let range = vk::ImageSubresourceRange::default()
+                .aspect_mask(vk::ImageAspectFlags::COLOR)
+                .level_count(1)
+                .layer_count(1);
+
+            // Updated images may still be read by the previous frame
+            let (old_layout, src_access) = match upload.new {
+                true => (vk::ImageLayout::UNDEFINED, vk::AccessFlags::empty()),
+                false => (vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL, vk::AccessFlags::SHADER_READ),
+            };
+
+            let to_transfer = vk::ImageMemoryBarrier::default()
+                .image(upload.image)
+                .subresource_range(range)
+                .old_layout(old_layout)
+                .new_layout(vk::ImageLayout::TRANSFER_DST_OPTIMAL)
+                .src_access_mask(src_access)
+                .dst_access_mask(vk::AccessFlags::TRANSFER_WRITE)
+                .src_queue_family_index(vk::QUEUE_FAMILY_IGNORED)
+                .dst_queue_family_index(vk::QUEUE_FAMILY_IGNORED);
+
+            let to_shader = vk::ImageMemoryBarrier::default()
+                .image(upload.image)
+                .subresource_range(range)
+                .old_layout(vk::ImageLayout::TRANSFER_DST_OPTIMAL)
+                .new_layout(vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL)
+                .src_access_mask(vk::AccessFlags::TRANSFER_WRITE)
+                .dst_access_mask(vk::AccessFlags::SHADER_READ)
+                .src_queue_family_index(vk::QUEUE_FAMILY_IGNORED)
+                .dst_queue_family_index(vk::QUEUE_FAMILY_IGNORED);
+
+            let region = vk::BufferImageCopy::default()
+                .image_subresource(vk::ImageSubresourceLayers {
+                    aspect_mask: vk::ImageAspectFlags::COLOR,
+                    mip_level: 0,
+                    base_array_layer: 0,
+                    layer_count: 1,
+                })
+                .image_offset(upload.offset)
+                .image_extent(upload.extent);
+
+            device.cmd_pipeline_barrier(
+                cbuf,
+                vk::PipelineStageFlags::FRAGMENT_SHADER,
+                vk::PipelineStageFlags::TRANSFER,
+                vk::DependencyFlags::empty(),
+                &[],
+                &[],
+                &[to_transfer],
+            );
+            device.cmd_copy_buffer_to_image(cbuf, upload.staging.raw, upload.image, vk::ImageLayout::TRANSFER_DST_OPTIMAL, &[region]);
+            device.cmd_pipeline_barrier(
+                cbuf,
+                vk::PipelineStageFlags::TRANSFER,
+                vk::PipelineStageFlags::FRAGMENT_SHADER,
+                vk::DependencyFlags::empty(),
+                &[],
+                &[],
+                &[to_shader],
+            );
+
+            self.retired_buffers.push(frames, upload.staging);
+        }
+    }
+
+    /// Bindless texture and sampler of `id`, user textures are indices of
+    /// `textures[]`
+    fn texture(&self, id: TextureId) -> Option<(u32, u32)> {
+        match id {
+            TextureId::Managed(_) => self.textures.get(&id).map(|texture| (texture.index, texture.sampler)),
+            TextureId::User(index) => Some((index as u32, SAMPLER_CLAMP)),
+        }
+    }
+
+    /// Copy the meshes into the buffers of `frame` and draw them clipped to
+    /// their rectangles, must be inside the render pass
+    ///
+    /// `sets` are the bindless and per-frame sets of the frame, callbacks are
+    /// skipped
+    ///
+    /// # Panics
+    /// - if called twice in a frame, the second call would overwrite the
+    ///   buffers the first one draws from
+    #[allow(clippy::too_many_arguments)]
+    pub(crate) unsafe fn draw(
+        &mut self,
+        device: &Device,
+        cbuf: vk::CommandBuffer,
+        frame: usize,
+        resolution: vk::Extent2D,
+        sets: &[vk::DescriptorSet],
+        primitives: &[ClippedPrimitive],
+        pixels_per_point: f32,
+    ) -> VulkanResult<()> {
+        profiling::scope!("EguiRenderer::draw");
+
+        assert!(!std::mem::replace(&mut self.drawn, true), "Only one EguiPass can be drawn per frame");
+
+        let meshes = primitives.iter().filter_map(|primitive| match &primitive.primitive {
+            Primitive::Mesh(mesh) if !mesh.indices.is_empty() => Some((primitive.clip_rect, mesh)),
+            _ => None,
+        });
+
+        let (vertex_count, index_count) = meshes
+            .clone()
+            .fold((0, 0), |(vertices, indices), (_, mesh)| (vertices + mesh.vertices.len(), indices + mesh.indices.len()));
+
+        if index_count == 0 {
+            return Ok(());
+        }
+
+        // The GPU finished the previous frame using these buffers
+        let buffers = &mut self.frames[frame];
+        if vertex_count > buffers.vertex_capacity || index_count > buffers.index_capacity {
+            let mut grown = FrameBuffers::new(
+                device,
+                vertex_count.max(buffers.vertex_capacity).next_power_of_two(),
+                index_count.max(buffers.index_capacity).next_power_of_two(),
+            )?;
+            std::mem::swap(buffers, &mut grown);
+            grown.destroy(device);
+        }
+
+        let (mut vertex_offset, mut index_offset) = (0, 0);
+        for (_, mesh) in meshes.clone() {
+            buffers
+                .vertices
+                .upload_data_at(vertex_offset * size_of::<egui::epaint::Vertex>(), &mesh.vertices)?;
+            buffers
+                .indices
+                .upload_data_at(index_offset * size_of::<u32>(), &mesh.indices)?;
+            vertex_offset += mesh.vertices.len();
+            index_offset += mesh.indices.len();
+        }
+
+        device.cmd_bind_pipeline(cbuf, vk::PipelineBindPoint::GRAPHICS, self.pipeline.raw);
+        device.cmd_bind_descriptor_sets(cbuf, vk::PipelineBindPoint::GRAPHICS, self.layout.raw, 0, sets, &[]);
+        device.cmd_bind_vertex_buffers(cbuf, 0, &[buffers.vertices.raw], &[0]);
+        device.cmd_bind_index_buffer(cbuf, buffers.indices.raw, 0, vk::IndexType::UINT32);
+
+        let viewport = vk::Viewport::default()
+            .width(resolution.width as f32)
+            .height(resolution.height as f32)
+            .max_depth(1.0);
+        device.cmd_set_viewport(cbuf, 0, &[viewport]);
+
+        let screen_size = [
+            resolution.width as f32 / pixels_per_point,
+            resolution.height as f32 / pixels_per_point,
+        ];
+
+        let (mut vertex_offset, mut index_offset) = (0, 0);
+        for (clip_rect, mesh) in meshes {
+            let first_vertex = vertex_offset;
+            let first_index = index_offset;
+            vertex_offset += mesh.vertices.len();
+            index_offset += mesh.indices.len();
+
+            let Some((texture, sampler)) = self.texture(mesh.texture_id) else {
+                continue;
+            };
+
+            // Clip rectangles are in points, scissors in pixels
+            let min_x = (clip_rect.min.x * pixels_per_point).round().clamp(0.0, resolution.width as f32) as u32;
+            let min_y = (clip_rect.min.y * pixels_per_point).round().clamp(0.0, resolution.height as f32) as u32;
+            let max_x = (clip_rect.max.x * pixels_per_point).round().clamp(min_x as f32, resolution.width as f32) as u32;
+            let max_y = (clip_rect.max.y * pixels_per_point).round().clamp(min_y as f32, resolution.height as f32) as u32;
+
+            if max_x == min_x || max_y == min_y {
+                continue;
+            }
+
+            let scissor = vk::Rect2D {
+                offset: vk::Offset2D {
+                    x: min_x as i32,
+                    y: min_y as i32,
+                },
+                extent: vk::Extent2D {
+                    width: max_x - min_x,
+                    height: max_y - min_y,
+                },
+            };
+            device.cmd_set_scissor(cbuf, 0, &[scissor]);
+
+            let mut tex_idx = [0; 8];
+            tex_idx[0] = texture;
+            tex_idx[1] = sampler;
+
+            let push = Push {
+                transform_idx: 0,
+                tex_idx,
+                screen_size,
+            };
+            device.cmd_push_constants(
+                cbuf,
+                self.layout.raw,
+                vk::ShaderStageFlags::VERTEX | vk::ShaderStageFlags::FRAGMENT,
+                0,
+                bytemuck::bytes_of(&push),
+            );
+
+            device.cmd_draw_indexed(cbuf, mesh.indices.len() as u32, 1, first_index as u32, first_vertex as i32, 0);
+        }
+
+        Ok(())
+    }
+
+    pub fn destroy(&mut self, device: &Device, bindless: &Bindless) {
+        self.retired_buffers.destroy(device);
+        self.retired_textures.destroy_with(|mut texture| texture.destroy(device, bindless));
+        for (_, mut texture) in self.textures.drain() {
+            texture.destroy(device, bindless);
+        }
+        for mut upload in self.uploads.drain(..) {
+            upload.staging.destroy(device);
+        }
+        for buffers in &mut self.frames {
+            buffers.destroy(device);
+        }
+        self.pipeline.destroy(device);
+        unsafe {
+            device.destroy_pipeline_layout(self.layout.raw, None);
+        }
+    }
+}
@@ -1,12 +1,21 @@
 use std::marker::PhantomData;
+use std::ops::Range;
+use std::path::PathBuf;
 use std::sync::Arc;
 
+use ash::vk;
+use bytemuck::{Pod, Zeroable};
+
 use winit::window::Window;
 
 use super::render_context::RenderContext;
 use crate::camera::Camera;
-use crate::core::{SwapchainError, VulkanError, VulkanResult};
+use crate::capture::{CaptureTarget, Screenshot};
+use crate::core::{CaptureError, SwapchainError, VulkanError, VulkanResult};
+use crate::canvas::Canvas;
+use crate::debug_draw::DebugDraw;
 use crate::frame_graph::{FrameGraph};
+use crate::math::Mat4;
 use crate::{TemporalFrameGraph, resources::*};
 /// Lightweight abstraction for rendering using Vulkan API
 ///
@@ -65,6 +74,35 @@ impl WorldRenderer {
         })
     }
 
+    /// # Create new `WorldRenderer` without a window
+    ///
+    /// Frames are rendered into offscreen images of `width` x `height` and
+    /// are read back with [`WorldRenderer::capture_to_memory`] or
+    /// [`WorldRenderer::capture_next_frame`]. Any available device is used,
+    /// including software drivers (lavapipe, `SwiftShader`)
+    ///
+    /// # Example
+    /// ```ignore
+    /// let mut world = WorldRenderer::headless(256, 256)?;
+    ///
+    /// world.capture_to_memory()?;
+    /// world.draw_frame(|graph| { ... })?;
+    ///
+    /// let frame = world.take_capture()?.unwrap();
+    /// ```
+    pub fn headless(width: u32, height: u32) -> VulkanResult<WorldRenderer> {
+        let ctx = RenderContext::headless(width, height)?;
+        let resources = Resources::new(&ctx)?;
+        let graph = FrameGraph::new(&ctx)?;
+
+        Ok(WorldRenderer {
+            resources,
+            graph,
+            ctx,
+            _marker: PhantomData,
+        })
+    }
+
     /// Create new resource
     ///
     /// [`Res<T>`] is a smart handle for deferred resource deletion
@@ -146,7 +184,8 @@ impl WorldRenderer {
     ///
     /// There may be many readers, but only one writer in one area
     pub fn camera_mut(&self) -> RefMut<'_, Camera> {
-        RefMut(self.resources.camera.try_write().expect("Camera is already borrowed mutably"))
+        let camera = self.resources.camera.try_write().expect("Camera is already borrowed mutably");
+        RefMut(parking_lot::RwLockWriteGuard::map(camera, |camera| camera))
     }
 
     /// Acquires a shared read lock on the camera [`Ref<'_, Camera>`]
@@ -155,7 +194,178 @@ impl WorldRenderer {
     ///
     /// There may be many readers, but only one writer in one area
     pub fn camera(&self) -> Ref<'_, Camera> {
-        Ref(self.resources.camera.try_read().expect("Camera is already borrowed mutably"))
+        let camera = self.resources.camera.try_read().expect("Camera is already borrowed mutably");
+        Ref(parking_lot::RwLockReadGuard::map(camera, |camera| camera))
+    }
+
+    /// Acquires an exclusive write lock on the debug shapes of the next frame
+    /// [`RefMut<'_, DebugDraw>`]
+    ///
+    /// Shapes are drawn by [`DebugPass`] and cleared every frame
+    ///
+    /// # Example
+    /// ```ignore
+    /// world
+    ///     .debug()
+    ///     .line([0.0; 3], [0.0, 1.0, 0.0], [1.0, 0.0, 0.0, 1.0])
+    ///     .frustum(&world.camera(), [1.0; 4]);
+    /// ```
+    ///
+    /// [`DebugPass`]: crate::DebugPass
+    pub fn debug(&self) -> RefMut<'_, DebugDraw> {
+        let debug = self.resources.debug.try_write().expect("DebugDraw is already borrowed");
+        RefMut(parking_lot::RwLockWriteGuard::map(debug, |debug| &mut debug.shapes))
+    }
+
+    /// Acquires an exclusive write lock on the 2D shapes of the next frame
+    /// [`RefMut<'_, Canvas>`]
+    ///
+    /// Shapes are drawn by [`CanvasPass`] and cleared every frame
+    ///
+    /// # Example
+    /// ```ignore
+    /// world
+    ///     .canvas()
+    ///     .fill(&Path::circle([100.0, 100.0], 8.0), [1.0, 0.5, 0.0, 1.0]);
+    /// ```
+    ///
+    /// [`CanvasPass`]: crate::CanvasPass
+    pub fn canvas(&self) -> RefMut<'_, Canvas> {
+        let canvas = self.resources.canvas.try_write().expect("Canvas is already borrowed");
+        RefMut(parking_lot::RwLockWriteGuard::map(canvas, |canvas| &mut canvas.shapes))
+    }
+
+    /// Move `child` into the space of `parent`, or make it a root with `None`
+    ///
+    /// The child keeps its local transform, its world matrix changes
+    ///
+    /// # Panics
+    /// - if `parent` is `child` or one of its descendants
+    pub fn set_parent(&self, child: &Res<Transform>, parent: Option<&Res<Transform>>) {
+        self.resources
+            .transforms
+            .write()
+            .set_parent(child.key, parent.map(|parent| parent.key));
+    }
+
+    /// Local to world matrix of the transform including every parent
+    pub fn world_matrix(&self, transform: &Res<Transform>) -> Mat4 {
+        self.resources.transforms.write().world_matrix(transform)
+    }
+
+    /// Index of the transform in the `transforms[]` shader buffer
+    ///
+    /// Indices may change once many transforms are dropped, look them up
+    /// every frame
+    pub fn transform_index(&self, transform: &Res<Transform>) -> u32 {
+        self.resources.transforms.read().index(transform)
+    }
+
+    /// Index of the lookup table of the colormap in the `textures[]` shader
+    /// array, for push constants of pipelines that sample it themselves
+    ///
+    /// [`PassContext::bind_colormap`] sets it for `shaders/inc/colormap.glsl`
+    ///
+    /// [`PassContext::bind_colormap`]: crate::frame_graph::PassContext::bind_colormap
+    pub fn colormap_index(&self, colormap: Colormap) -> u32 {
+        self.resources.colormaps.read().index(colormap)
+    }
+
+    /// Replace vertices `range` of the mesh, vertices past the last one are
+    /// appended
+    ///
+    /// The change is copied into the buffers of each frame in flight once
+    /// the GPU no longer reads them, buffers double in size when the mesh
+    /// outgrows them. Bounds computed by [`MeshDesc::with_bounds`] grow to
+    /// include the new vertices
+    ///
+    /// # Example
+    /// ```ignore
+    /// // Append new samples of a live plot
+    /// let count = world.get(&plot).vertex_count();
+    /// world.update_mesh(&plot, count..count + samples.len() as u32, &samples);
+    /// ```
+    ///
+    /// # Panics
+    /// - if `vertices` does not hold `range.len()` vertices of the mesh
+    /// - if `range` starts past the last vertex
+    pub fn update_mesh<T: Pod + Zeroable>(&self, mesh: &Res<Mesh>, range: Range<u32>, vertices: &[T]) {
+        self.resources
+            .meshes
+            .write()
+            .write_vertices(mesh, range, bytemuck::cast_slice(vertices));
+    }
+
+    /// Replace indices `range` of the mesh, see [`WorldRenderer::update_mesh`]
+    ///
+    /// Indices are converted to the type the mesh was created with, a mesh
+    /// created without indices becomes indexed with the type of `indices`.
+    /// LODs of the mesh were built from the old indices and are dropped, the
+    /// whole index buffer is drawn afterwards
+    ///
+    /// # Panics
+    /// - if `indices` does not hold `range.len()` indices
+    /// - if `range` starts past the last index
+    /// - if an index does not fit in the index type of the mesh
+    pub fn update_mesh_indices<I: Index>(&self, mesh: &Res<Mesh>, range: Range<u32>, indices: &[I]) {
+        self.resources.meshes.write().write_indices(mesh, range, indices);
+    }
+
+    /// Indices of the `(mesh, transform)` pairs visible to the camera, see
+    /// [`MeshDesc::with_bounds`]
+    ///
+    /// # Example
+    /// ```ignore
+    /// let visible = world.cull(&objects);
+    ///
+    /// world.draw_frame(|graph| {
+    ///     graph.add_pass(PresentPass::new("Opaque", |_| {}, move |ctx, _| unsafe {
+    ///         ctx.bind_pipeline(&pipeline);
+    ///         for (mesh, transform) in visible.iter().map(|&index| &objects[index]) {
+    ///             ctx.bind_transform(transform);
+    ///             ctx.draw_mesh(mesh);
+    ///         }
+    ///     }));
+    /// })?;
+    /// ```
+    pub fn cull(&self, objects: &[(Res<Mesh>, Res<Transform>)]) -> Vec<usize> {
+        profiling::scope!("WorldRenderer::cull");
+
+        let frustum = self.resources.camera.read().frustum();
+        let meshes = self.resources.meshes.read();
+        let mut transforms = self.resources.transforms.write();
+
+        objects
+            .iter()
+            .enumerate()
+            .filter(|(_, (mesh, transform))| {
+                let mesh = meshes.pool.get(mesh);
+                mesh.is_visible(&frustum, &transforms.world_matrix(transform))
+            })
+            .map(|(index, _)| index)
+            .collect()
+    }
+
+    /// Create, update and free the textures of an egui frame, see
+    /// [`EguiPass`]
+    ///
+    /// Changes are copied before the first pass of the next
+    /// [`WorldRenderer::draw_frame`], freed textures are released once no
+    /// frame in flight reads them
+    ///
+    /// # Example
+    /// ```ignore
+    /// let output = egui_ctx.run_ui(raw_input, |ui| inspector.ui(ui));
+    /// world.update_egui_textures(&output.textures_delta)?;
+    /// ```
+    ///
+    /// [`EguiPass`]: crate::EguiPass
+    #[cfg(feature = "egui")]
+    pub fn update_egui_textures(&self, delta: &egui::TexturesDelta) -> VulkanResult<()> {
+        self.resources
+            .egui
+            .write()
+            .set_textures(&self.ctx.device, &self.resources.bindless, delta)
     }
 
     /// Re-creating the main window
@@ -172,10 +382,63 @@ impl WorldRenderer {
         }
 
         self.ctx.resize(width, height)?;
+        self.graph.targets_resized(&self.ctx, &self.resources);
+
+        // The swapchain may not match the requested size
+        let extent = self.ctx.resolution();
+        self.resources
+            .camera
+            .write()
+            .set_aspect(extent.width as f32 / extent.height as f32);
 
         Ok(())
     }
 
+    /// Save the next drawn frame into a file
+    ///
+    /// The image is copied after the last pass of the next
+    /// [`WorldRenderer::draw_frame`] and written before it returns. The file
+    /// format is selected by extension, see [`Screenshot::save`]. Write
+    /// errors are returned by [`WorldRenderer::take_capture`]
+    ///
+    /// # Example
+    /// ```ignore
+    /// world.capture_next_frame("screenshot.png")?;
+    /// world.draw_frame(|graph| { ... })?;
+    /// ```
+    pub fn capture_next_frame<P: Into<PathBuf>>(&mut self, path: P) -> VulkanResult<()> {
+        self.check_capture_support()?;
+        self.graph.capture.request(CaptureTarget::File(path.into()));
+        Ok(())
+    }
+
+    /// Keep the next drawn frame in memory
+    ///
+    /// The result is available with [`WorldRenderer::take_capture`] after the
+    /// next [`WorldRenderer::draw_frame`]
+    pub fn capture_to_memory(&mut self) -> VulkanResult<()> {
+        self.check_capture_support()?;
+        self.graph.capture.request(CaptureTarget::Memory);
+        Ok(())
+    }
+
+    /// Take the frame captured with [`WorldRenderer::capture_to_memory`]
+    ///
+    /// A capture which failed to be read back or written to its file is
+    /// reported here once instead of failing [`WorldRenderer::draw_frame`]
+    pub fn take_capture(&mut self) -> VulkanResult<Option<Screenshot>> {
+        self.graph.capture.take()
+    }
+
+    fn check_capture_support(&self) -> VulkanResult<()> {
+        let window = self.ctx.window.read();
+        if window.image_usage.contains(vk::ImageUsageFlags::TRANSFER_SRC) {
+            Ok(())
+        } else {
+            Err(VulkanError::Capture(CaptureError::TransferNotSupported))
+        }
+    }
+
     /// Accepts a closure in which the entire frame creation cycle must be
     /// described
     ///
@@ -0,0 +1,749 @@
+//! Golden-image tests, run with `cargo test --features testing`
+//!
+//! Tests are skipped when no Vulkan driver is available, references are
+//! updated with `ALUMINIUM_BLESS=1`
+
+use aluminium::testing::{self, Golden};
+use aluminium::types::PbrVertex;
+use aluminium::{Mesh, MeshDesc, PresentPass, RasterPipeline, RasterPipelineDesc, Res, Scissor, Viewport, WorldRenderer};
+
+const WIDTH: u32 = 64;
+const HEIGHT: u32 = 64;
+
+fn golden() -> Golden {
+    Golden::new(concat!(env!("CARGO_MANIFEST_DIR"), "/tests/golden"))
+}
+
+fn pipeline(world: &WorldRenderer) -> Res<RasterPipeline> {
+    world
+        .create::<RasterPipeline>(
+            RasterPipelineDesc::new()
+                .vertex_shader(concat!(env!("CARGO_MANIFEST_DIR"), "/shaders/spv/raster_vs.spv"))
+                .fragment_shader(concat!(env!("CARGO_MANIFEST_DIR"), "/shaders/spv/raster_ps.spv"))
+                .dynamic_scissors(true)
+                .dynamic_viewport(true),
+        )
+        .expect("Error create pipeline")
+}
+
+/// `raster_vs` multiplies color by normal and tangent
+fn vertex(pos: [f32; 2], color: [f32; 3]) -> PbrVertex {
+    PbrVertex {
+        pos: [pos[0], pos[1], 0.5, 1.0],
+        normal: [1.0; 4],
+        uv: [0.0; 2],
+        color: [color[0], color[1], color[2], 1.0],
+        tangent: [1.0; 4],
+    }
+}
+
+/// Draw meshes with `raster_vs`/`raster_ps`, `scale` is the first user value
+fn draw(world: &mut WorldRenderer, pipeline: &Res<RasterPipeline>, meshes: &[Res<Mesh>], scale: f32) -> aluminium::Screenshot {
+    testing::render(world, |graph| {
+        graph.add_pass(PresentPass::new(
+            "Golden Pass",
+            |_| {},
+            move |ctx, _| unsafe {
+                ctx.bind_pipeline(pipeline);
+                ctx.push_constants([scale, 0.0]);
+                ctx.set_viewport(Viewport::FullRes);
+                ctx.set_scissor(Scissor::FullRes);
+                for mesh in meshes {
+                    ctx.draw_mesh(mesh);
+                }
+            },
+        ));
+    })
+    .expect("Error render frame")
+}
+
+#[test]
+fn clear() {
+    let Some(mut world) = testing::headless(WIDTH, HEIGHT) else {
+        return;
+    };
+
+    let frame = testing::render(&mut world, |_| {}).expect("Error render frame");
+
+    golden().assert("clear", &frame);
+}
+
+#[test]
+fn triangle() {
+    let Some(mut world) = testing::headless(WIDTH, HEIGHT) else {
+        return;
+    };
+
+    let pipeline = pipeline(&world);
+    let vertices = [
+        vertex([0.0, -0.25], [1.0, 0.0, 0.0]),
+        vertex([0.25, 0.25], [0.0, 1.0, 0.0]),
+        vertex([-0.25, 0.25], [0.0, 0.0, 1.0]),
+    ];
+    let mesh = world.create::<Mesh>(MeshDesc::new(&vertices)).expect("Error create mesh");
+
+    let frame = draw(&mut world, &pipeline, &[mesh], 0.0);
+
+    golden().assert("triangle", &frame);
+}
+
+#[test]
+fn indexed_quad() {
+    let Some(mut world) = testing::headless(WIDTH, HEIGHT) else {
+        return;
+    };
+
+    let pipeline = pipeline(&world);
+    let vertices = [
+        vertex([-0.25, -0.25], [1.0, 1.0, 0.0]),
+        vertex([0.25, -0.25], [1.0, 1.0, 0.0]),
+        vertex([0.25, 0.25], [0.0, 1.0, 1.0]),
+        vertex([-0.25, 0.25], [0.0, 1.0, 1.0]),
+    ];
+    let indices = [0u32, 1, 2, 2, 3, 0];
+    let mesh = world
+        .create::<Mesh>(MeshDesc::new(&vertices).with_indices(&indices))
+        .expect("Error create mesh");
+
+    let frame = draw(&mut world, &pipeline, &[mesh], 0.0);
+
+    golden().assert("indexed_quad", &frame);
+}
+
+#[test]
+fn push_constants() {
+    let Some(mut world) = testing::headless(WIDTH, HEIGHT) else {
+        return;
+    };
+
+    let pipeline = pipeline(&world);
+    let vertices = [
+        vertex([0.0, -0.25], [1.0, 1.0, 1.0]),
+        vertex([0.25, 0.25], [1.0, 1.0, 1.0]),
+        vertex([-0.25, 0.25], [1.0, 1.0, 1.0]),
+    ];
+    let mesh = world.create::<Mesh>(MeshDesc::new(&vertices)).expect("Error create mesh");
+
+    // sin(pi / 2) = 1, the triangle is drawn three times larger
+    let frame = draw(&mut world, &pipeline, &[mesh], std::f32::consts::FRAC_PI_2);
+
+    golden().assert("push_constants", &frame);
+}
+
+#[test]
+fn resize() {
+    let Some(mut world) = testing::headless(WIDTH, HEIGHT) else {
+        return;
+    };
+
+    world.resize(WIDTH / 2, HEIGHT).expect("Error resize");
+
+    let frame = testing::render(&mut world, |_| {}).expect("Error render frame");
+
+    assert_eq!((frame.width, frame.height), (WIDTH / 2, HEIGHT));
+}
+
+#[cfg(feature = "egui")]
+#[test]
+fn egui_rect() {
+    use aluminium::egui::{self, Color32, Pos2, Rect, Vec2};
+    use aluminium::EguiPass;
+
+    let Some(mut world) = testing::headless(WIDTH, HEIGHT) else {
+        return;
+    };
+
+    let ctx = egui::Context::default();
+    let input = egui::RawInput {
+        screen_rect: Some(Rect::from_min_size(Pos2::ZERO, Vec2::new(WIDTH as f32, HEIGHT as f32))),
+        ..Default::default()
+    };
+    let output = ctx.run_ui(input, |ui| {
+        let rect = Rect::from_min_size(Pos2::ZERO, Vec2::splat(32.0));
+        ui.painter().rect_filled(rect, 0.0, Color32::RED);
+    });
+    let primitives = ctx.tessellate(output.shapes, output.pixels_per_point);
+
+    world
+        .update_egui_textures(&output.textures_delta)
+        .expect("Error update egui textures");
+    let frame = testing::render(&mut world, |graph| {
+        graph.add_pass(EguiPass::new("Ui", &primitives, output.pixels_per_point));
+    })
+    .expect("Error render frame");
+
+    // Opaque inside the rectangle, the clear color outside
+    let pixel = |x: u32, y: u32| &frame.pixels[((y * WIDTH + x) * 4) as usize..][..4];
+    assert_eq!(pixel(8, 8), [255, 0, 0, 255]);
+    assert_ne!(pixel(48, 48), [255, 0, 0, 255]);
+}
+
+#[cfg(feature = "text")]
+#[test]
+fn screen_text() {
+    use aluminium::{Font, FontDesc, Text, TextAnchor, TextDesc};
+
+    let Some(mut world) = testing::headless(WIDTH, HEIGHT) else {
+        return;
+    };
+
+    // Box glyphs, `I` spans 0.1..0.4 ems across and 0.7 ems up
+    let font = world
+        .create::<Font>(FontDesc::new(include_bytes!("fonts/boxes.ttf")))
+        .expect("Error create font");
+    let text = world
+        .create::<Text>(
+            TextDesc::new(&font, "I")
+                .anchor(TextAnchor::Screen([16.0, 48.0]))
+                .size(40.0)
+                .color([1.0, 0.0, 0.0, 1.0]),
+        )
+        .expect("Error create text");
+
+    let frame = testing::render(&mut world, |graph| {
+        graph.add_pass(PresentPass::new("Text", |_| {}, |ctx, _| unsafe {
+            ctx.draw_text(&text);
+        }));
+    })
+    .expect("Error render frame");
+
+    // The stem covers 20..32 across and 20..48 down
+    let pixel = |x: u32, y: u32| &frame.pixels[((y * WIDTH + x) * 4) as usize..][..4];
+    assert_eq!(pixel(26, 34), [255, 0, 0, 255]);
+    assert_eq!(pixel(8, 34), pixel(WIDTH / 2, 2));
+    assert_eq!(pixel(44, 34), pixel(WIDTH / 2, 2));
+    assert_eq!(pixel(26, 12), pixel(WIDTH / 2, 2));
+}
+
+#[test]
+fn debug_lines() {
+    use aluminium::DebugPass;
+
+    let Some(mut world) = testing::headless(WIDTH, HEIGHT) else {
+        return;
+    };
+
+    // The default camera is identity, positions are clip space
+    world
+        .debug()
+        .line([-1.0, 0.0, 0.5], [1.0, 0.0, 0.5], [1.0, 0.0, 0.0, 1.0])
+        .depth_test(false)
+        .line([0.0, -1.0, 0.5], [0.0, 1.0, 0.5], [0.0, 1.0, 0.0, 1.0]);
+
+    let frame = testing::render(&mut world, |graph| {
+        graph.add_pass(DebugPass::new("Debug"));
+    })
+    .expect("Error render frame");
+
+    let pixel = |x: u32, y: u32| &frame.pixels[((y * WIDTH + x) * 4) as usize..][..4];
+    let column = |x: u32| (HEIGHT / 2 - 1..=HEIGHT / 2).map(|y| pixel(x, y)).collect::<Vec<_>>();
+    let row = |y: u32| (WIDTH / 2 - 1..=WIDTH / 2).map(|x| pixel(x, y)).collect::<Vec<_>>();
+
+    assert!(column(8).contains(&&[255, 0, 0, 255][..]));
+    assert!(row(8).contains(&&[0, 255, 0, 255][..]));
+
+    // Shapes last one frame
+    assert_eq!(world.debug().vertex_count(), 0);
+}
+
+#[test]
+fn thick_polyline() {
+    use aluminium::{LineCap, Polyline, PolylineDesc};
+
+    let Some(mut world) = testing::headless(WIDTH, HEIGHT) else {
+        return;
+    };
+
+    // The default camera is identity, points are clip space
+    let points = [[-0.5, 0.0, 0.5], [0.5, 0.0, 0.5]];
+    let line = world
+        .create::<Polyline>(PolylineDesc::new(&points).width(8.0).color([0.0, 0.0, 1.0, 1.0]).cap(LineCap::Butt))
+        .expect("Error create polyline");
+
+    let frame = testing::render(&mut world, |graph| {
+        graph.add_pass(PresentPass::new("Line", |_| {}, |ctx, _| unsafe {
+            ctx.draw_polyline(&line);
+        }));
+    })
+    .expect("Error render frame");
+
+    let pixel = |x: u32, y: u32| &frame.pixels[((y * WIDTH + x) * 4) as usize..][..4];
+
+    // 8 pixels wide around the middle row, from a quarter to three quarters
+    for y in [29, 32, 34] {
+        assert_eq!(pixel(WIDTH / 2, y), [0, 0, 255, 255], "row {y}");
+    }
+    assert_ne!(pixel(WIDTH / 2, 24), [0, 0, 255, 255]);
+    assert_ne!(pixel(8, 32), [0, 0, 255, 255]);
+}
+
+#[test]
+fn point_sprites() {
+    use aluminium::{CloudPoint, PointCloud, PointCloudDesc, PointShape};
+
+    let Some(mut world) = testing::headless(WIDTH, HEIGHT) else {
+        return;
+    };
+
+    // The default camera is identity, points are clip space
+    let points = [
+        CloudPoint::rgba([-0.5, 0.0, 0.5], [255, 0, 0, 255]),
+        CloudPoint::rgba([0.5, 0.0, 0.5], [255, 0, 0, 255]),
+    ];
+    let circles = world
+        .create::<PointCloud>(PointCloudDesc::new(&points[..1]).size(16.0).shape(PointShape::Circle))
+        .expect("Error create point cloud");
+    let squares = world
+        .create::<PointCloud>(PointCloudDesc::new(&points[1..]).size(16.0))
+        .expect("Error create point cloud");
+
+    let frame = testing::render(&mut world, |graph| {
+        graph.add_pass(PresentPass::new("Points", |_| {}, |ctx, _| unsafe {
+            ctx.draw_point_cloud(&circles);
+            ctx.draw_point_cloud(&squares);
+        }));
+    })
+    .expect("Error render frame");
+
+    let pixel = |x: u32, y: u32| &frame.pixels[((y * WIDTH + x) * 4) as usize..][..4];
+    let (left, right, middle) = (WIDTH / 4, WIDTH * 3 / 4, HEIGHT / 2);
+
+    assert_eq!(pixel(left, middle), [255, 0, 0, 255]);
+    assert_eq!(pixel(right, middle), [255, 0, 0, 255]);
+    // Corners of the sprites are cut from circles only
+    assert_ne!(pixel(left - 7, middle - 7), [255, 0, 0, 255]);
+    assert_eq!(pixel(right - 7, middle - 7), [255, 0, 0, 255]);
+    assert_eq!(world.get(&circles).drawn_points(), 1);
+}
+
+#[test]
+fn scalar_points() {
+    use aluminium::{CloudPoint, Colormap, PointCloud, PointCloudDesc, PointColor, ScalarRange};
+
+    let Some(mut world) = testing::headless(WIDTH, HEIGHT) else {
+        return;
+    };
+
+    // Halfway through 0..10, and outside of it
+    let points = [CloudPoint::scalar([-0.5, 0.0, 0.5], 5.0), CloudPoint::scalar([0.5, 0.0, 0.5], 20.0)];
+    let color = PointColor::Scalar {
+        colormap: Colormap::Viridis,
+        range: ScalarRange::new(0.0, 10.0).clamp(false),
+    };
+    let cloud = world
+        .create::<PointCloud>(PointCloudDesc::new(&points).size(16.0).color(color))
+        .expect("Error create point cloud");
+
+    let frame = testing::render(&mut world, |graph| {
+        graph.add_pass(PresentPass::new("Points", |_| {}, |ctx, _| unsafe {
+            ctx.draw_point_cloud(&cloud);
+        }));
+    })
+    .expect("Error render frame");
+
+    let pixel = |x: u32, y: u32| &frame.pixels[((y * WIDTH + x) * 4) as usize..][..4];
+    let expected = Colormap::Viridis.sample(0.5);
+
+    for (&got, &want) in pixel(WIDTH / 4, HEIGHT / 2).iter().zip(&expected) {
+        assert!(got.abs_diff(want) <= 2, "{:?} is not {:?}", pixel(WIDTH / 4, HEIGHT / 2), expected);
+    }
+    // Transparent without clamping
+    assert_eq!(pixel(WIDTH * 3 / 4, HEIGHT / 2), pixel(WIDTH / 2, 2));
+}
+
+#[test]
+fn canvas_shapes() {
+    use aluminium::{CanvasPass, Path, Stroke};
+
+    let Some(mut world) = testing::headless(WIDTH, HEIGHT) else {
+        return;
+    };
+
+    // Pixels from the top left corner, the right half is clipped away
+    world
+        .canvas()
+        .fill(&Path::rect([8.0, 8.0], [16.0, 16.0]), [1.0, 0.0, 0.0, 1.0])
+        .save()
+        .clip_rect([0.0, 0.0], [48.0, 64.0])
+        .stroke(&Path::new().move_to([32.0, 48.0]).line_to([64.0, 48.0]), &Stroke::new(4.0), [0.0, 1.0, 0.0, 1.0])
+        .restore();
+
+    let frame = testing::render(&mut world, |graph| {
+        graph.add_pass(CanvasPass::new("Canvas"));
+    })
+    .expect("Error render frame");
+
+    let pixel = |x: u32, y: u32| &frame.pixels[((y * WIDTH + x) * 4) as usize..][..4];
+
+    assert_eq!(pixel(16, 16), [255, 0, 0, 255]);
+    assert_ne!(pixel(26, 16), [255, 0, 0, 255]);
+    assert_eq!(pixel(40, 47), [0, 255, 0, 255]);
+    assert_ne!(pixel(56, 47), [0, 255, 0, 255]);
+
+    // Shapes last one frame
+    assert_eq!(world.canvas().vertex_count(), 0);
+}
+
+#[test]
+fn scalar_colormap() {
+    use aluminium::{Colormap, InstanceBuffer, InstanceBufferDesc, ScalarRange, ShaderType, Transform, TransformDesc, VertexInput};
+
+    let Some(mut world) = testing::headless(WIDTH, HEIGHT) else {
+        return;
+    };
+
+    let pipeline = world
+        .create::<RasterPipeline>(
+            RasterPipelineDesc::new()
+                .vertex_shader(concat!(env!("CARGO_MANIFEST_DIR"), "/shaders/spv/scalar_vert.spv"))
+                .fragment_shader(concat!(env!("CARGO_MANIFEST_DIR"), "/shaders/spv/scalar_frag.spv"))
+                .vertex_input(VertexInput::new().with(ShaderType::Float))
+                .dynamic_scissors(true)
+                .dynamic_viewport(true),
+        )
+        .expect("Error create pipeline");
+    let transform = world.create::<Transform>(TransformDesc::identity()).expect("Error create transform");
+
+    // The default camera is identity, triangles are clip space
+    let left = [vertex([-0.5, -0.5], [1.0; 3]), vertex([0.0, 0.5], [1.0; 3]), vertex([-1.0, 0.5], [1.0; 3])];
+    let right = [vertex([0.5, -0.5], [1.0; 3]), vertex([1.0, 0.5], [1.0; 3]), vertex([0.0, 0.5], [1.0; 3])];
+    let left = world.create::<Mesh>(MeshDesc::new(&left)).expect("Error create mesh");
+    let right = world.create::<Mesh>(MeshDesc::new(&right)).expect("Error create mesh");
+    let values = world
+        .create::<InstanceBuffer>(InstanceBufferDesc::new(&[10.0f32; 3]))
+        .expect("Error create instance buffer");
+
+    let frame = testing::render(&mut world, |graph| {
+        graph.add_pass(PresentPass::new("Scalars", |_| {}, |ctx, _| unsafe {
+            ctx.bind_pipeline(&pipeline);
+            ctx.set_viewport(Viewport::FullRes);
+            ctx.set_scissor(Scissor::FullRes);
+            ctx.bind_transform(&transform);
+            ctx.bind_colormap(0, Colormap::Viridis);
+            // 10 is halfway through 1..100 on a log scale
+            ctx.push_constants(ScalarRange::new(1.0, 100.0).log(true));
+            ctx.draw_mesh_with_attributes(&left, &values);
+            // and outside 0..5, transparent without clamping
+            ctx.push_constants(ScalarRange::new(0.0, 5.0).clamp(false));
+            ctx.draw_mesh_with_attributes(&right, &values);
+        }));
+    })
+    .expect("Error render frame");
+
+    let pixel = |x: u32, y: u32| &frame.pixels[((y * WIDTH + x) * 4) as usize..][..4];
+    let expected = Colormap::Viridis.sample(0.5);
+
+    for (&got, &want) in pixel(WIDTH / 4, HEIGHT * 5 / 8).iter().zip(&expected) {
+        assert!(got.abs_diff(want) <= 2, "{:?} is not {:?}", pixel(WIDTH / 4, HEIGHT * 5 / 8), expected);
+    }
+    assert_eq!(pixel(WIDTH * 3 / 4, HEIGHT * 5 / 8), pixel(WIDTH / 2, 2));
+}
+
+#[test]
+fn instanced_draw() {
+    use aluminium::{InstanceBuffer, InstanceBufferDesc, ShaderType, VertexInput};
+
+    let Some(mut world) = testing::headless(WIDTH, HEIGHT) else {
+        return;
+    };
+
+    let pipeline = world
+        .create::<RasterPipeline>(
+            RasterPipelineDesc::new()
+                .vertex_shader(concat!(env!("CARGO_MANIFEST_DIR"), "/shaders/spv/instanced_vert.spv"))
+                .fragment_shader(concat!(env!("CARGO_MANIFEST_DIR"), "/shaders/spv/debug_frag.spv"))
+                .instance_input(VertexInput::new().with(ShaderType::Float2).with(ShaderType::Float4))
+                .dynamic_scissors(true)
+                .dynamic_viewport(true),
+        )
+        .expect("Error create pipeline");
+
+    let vertices = [
+        vertex([0.0, -0.25], [1.0; 3]),
+        vertex([0.25, 0.25], [1.0; 3]),
+        vertex([-0.25, 0.25], [1.0; 3]),
+    ];
+    let mesh = world.create::<Mesh>(MeshDesc::new(&vertices)).expect("Error create mesh");
+
+    // Offset and color of one triangle in each quarter of the screen
+    let offsets = [
+        [-0.5f32, -0.5, 1.0, 0.0, 0.0, 1.0],
+        [0.5, -0.5, 0.0, 1.0, 0.0, 1.0],
+        [-0.5, 0.5, 0.0, 0.0, 1.0, 1.0],
+        [0.5, 0.5, 1.0, 1.0, 1.0, 1.0],
+    ];
+    let instances = world
+        .create::<InstanceBuffer>(InstanceBufferDesc::new(&offsets))
+        .expect("Error create instance buffer");
+
+    let frame = testing::render(&mut world, |graph| {
+        graph.add_pass(PresentPass::new("Instances", |_| {}, |ctx, _| unsafe {
+            ctx.bind_pipeline(&pipeline);
+            ctx.set_viewport(Viewport::FullRes);
+            ctx.set_scissor(Scissor::FullRes);
+            // Skips the red triangle
+            ctx.draw_mesh_instanced(&mesh, &instances, 1..);
+        }));
+    })
+    .expect("Error render frame");
+
+    let pixel = |x: u32, y: u32| &frame.pixels[((y * WIDTH + x) * 4) as usize..][..4];
+
+    assert_eq!(pixel(WIDTH / 4, HEIGHT / 4), pixel(WIDTH / 2, HEIGHT / 2));
+    assert_eq!(pixel(WIDTH * 3 / 4, HEIGHT / 4), [0, 255, 0, 255]);
+    assert_eq!(pixel(WIDTH / 4, HEIGHT * 3 / 4), [0, 0, 255, 255]);
+    assert_eq!(pixel(WIDTH * 3 / 4, HEIGHT * 3 / 4), [255, 255, 255, 255]);
+}
+
+#[test]
+fn height_field_surface() {
+    use aluminium::{Colormap, HeightField, HeightFieldDesc, ScalarRange};
+
+    let Some(mut world) = testing::headless(WIDTH, HEIGHT) else {
+        return;
+    };
+
+    // The default camera is identity, the grid covers the bottom right
+    // quarter of clip space with heights as depth
+    let heights = [0.5; 9];
+    let field = world
+        .create::<HeightField>(HeightFieldDesc::new([3, 3], &heights).colormap(Colormap::Viridis, ScalarRange::new(0.0, 1.0)))
+        .expect("Error create height field");
+
+    let render = |world: &mut WorldRenderer| {
+        testing::render(world, |graph| {
+            graph.add_pass(PresentPass::new("Surface", |_| {}, |ctx, _| unsafe {
+                ctx.draw_height_field(&field);
+            }));
+        })
+        .expect("Error render frame")
+    };
+
+    let frame = render(&mut world);
+    let pixel = |frame: &aluminium::Screenshot, x: u32, y: u32| frame.pixels[((y * WIDTH + x) * 4) as usize..][..4].to_vec();
+    let (inside, outside) = ((WIDTH * 5 / 8, HEIGHT * 5 / 8), (WIDTH / 4, HEIGHT / 4));
+
+    // Viridis is teal halfway
+    let [r, g, b, _] = pixel(&frame, inside.0, inside.1)[..] else { unreachable!() };
+    assert!(g > r && b > r, "{:?}", [r, g, b]);
+    assert_ne!(pixel(&frame, inside.0, inside.1), pixel(&frame, outside.0, outside.1));
+
+    // Past the top of the range, the last color of viridis is yellow
+    world.get_mut(&field).write_region([0, 0], [3, 3], &[0.9; 9]);
+    world.get_mut(&field).range = ScalarRange::new(0.0, 0.1);
+    let frame = render(&mut world);
+    let [r, g, b, _] = pixel(&frame, inside.0, inside.1)[..] else { unreachable!() };
+    assert!(r > b && g > b, "{:?}", [r, g, b]);
+}
+
+#[test]
+fn volume_depth_compositing() {
+    use aluminium::{Colormap, ScalarRange, Texture, TextureDesc, TextureFormat, Transform, TransformDesc, Volume, VolumeDesc};
+
+    let Some(mut world) = testing::headless(WIDTH, HEIGHT) else {
+        return;
+    };
+
+    let pipeline = world
+        .create::<RasterPipeline>(
+            RasterPipelineDesc::new()
+                .vertex_shader(concat!(env!("CARGO_MANIFEST_DIR"), "/shaders/spv/raster_vs.spv"))
+                .fragment_shader(concat!(env!("CARGO_MANIFEST_DIR"), "/shaders/spv/raster_ps.spv"))
+                .depth_write(true)
+                .dynamic_scissors(true)
+                .dynamic_viewport(true),
+        )
+        .expect("Error create pipeline");
+
+    // The default camera is identity, a red quad covers the left half of
+    // clip space at depth 0.5, in front of the box from 0.05 to 0.45
+    let vertices = [
+        vertex([-1.0, -1.0], [1.0, 0.0, 0.0]),
+        vertex([0.0, -1.0], [1.0, 0.0, 0.0]),
+        vertex([0.0, 1.0], [1.0, 0.0, 0.0]),
+        vertex([-1.0, 1.0], [1.0, 0.0, 0.0]),
+    ];
+    let quad = world
+        .create::<Mesh>(MeshDesc::new(&vertices).with_indices(&[0u32, 1, 2, 2, 3, 0]))
+        .expect("Error create mesh");
+    let transform = world
+        .create::<Transform>(TransformDesc::identity().translation([0.0, 0.0, 0.25]))
+        .expect("Error create transform");
+
+    // Fully opaque scalars at the top of the range
+    let texels = [255u8; 4 * 4 * 4];
+    let texture = world
+        .create::<Texture>(TextureDesc::new(4, 4, TextureFormat::R8Unorm, &texels).depth(4))
+        .expect("Error create texture");
+    let volume = world
+        .create::<Volume>(
+            VolumeDesc::new(&texture)
+                .extent([1.0, 1.0, 0.4])
+                .colormap(Colormap::Inferno, ScalarRange::new(0.0, 1.0)),
+        )
+        .expect("Error create volume");
+
+    let frame = testing::render(&mut world, |graph| {
+        graph.add_pass(PresentPass::new("Scene", |_| {}, |ctx, _| unsafe {
+            ctx.bind_pipeline(&pipeline);
+            ctx.set_viewport(Viewport::FullRes);
+            ctx.set_scissor(Scissor::FullRes);
+            ctx.draw_mesh(&quad);
+        }));
+        graph.add_pass(PresentPass::new("Volumes", |builder| builder.read_depth(), |ctx, _| unsafe {
+            ctx.bind_pipeline(&pipeline);
+            ctx.bind_transform(&transform);
+            ctx.draw_volume(&volume);
+        }));
+    })
+    .expect("Error render frame");
+
+    let pixel = |x: u32, y: u32| &frame.pixels[((y * WIDTH + x) * 4) as usize..][..4];
+
+    // Pale yellow at the end of inferno, hidden by the quad on the left
+    let [r, g, b, _] = pixel(WIDTH * 5 / 8, HEIGHT / 2)[..] else { unreachable!() };
+    assert!(r > b && g > b, "{:?}", [r, g, b]);
+    assert_eq!(pixel(WIDTH * 3 / 8, HEIGHT / 2), [255, 0, 0, 255]);
+    // Outside the box
+    assert_ne!(pixel(WIDTH * 15 / 16, HEIGHT / 16), pixel(WIDTH * 5 / 8, HEIGHT / 2));
+}
+
+#[test]
+fn isosurface_matches_reference() {
+    use aluminium::{marching_cubes, Isosurface, IsosurfaceDesc, IsosurfaceMesh, Texture, TextureDesc, TextureFormat, Transform, TransformDesc};
+
+    let Some(mut world) = testing::headless(WIDTH, HEIGHT) else {
+        return;
+    };
+
+    let pipeline = pipeline(&world);
+
+    // Minus the distance to the center of a 12x12x12 grid, balls are inside
+    const N: u32 = 12;
+    let center = (N as f32 - 1.0) / 2.0;
+    let values: Vec<f32> = (0..N * N * N)
+        .map(|i| {
+            let [x, y, z] = [i % N, i / N % N, i / (N * N)].map(|v| v as f32 - center);
+            -(x * x + y * y + z * z).sqrt()
+        })
+        .collect();
+    let texture = world
+        .create::<Texture>(TextureDesc::new(N, N, TextureFormat::R32Sfloat, bytemuck::cast_slice(&values)).depth(N))
+        .expect("Error create texture");
+
+    // The default camera is identity, the box spans depths 0.25 to 0.75
+    let extent = [1.5, 1.5, 0.5];
+    let surface = world
+        .create::<Isosurface>(IsosurfaceDesc::new(&texture, -3.7).extent(extent).color([0.0, 1.0, 0.0, 1.0]))
+        .expect("Error create isosurface");
+    let transform = world
+        .create::<Transform>(TransformDesc::identity().translation([0.0, 0.0, 0.5]))
+        .expect("Error create transform");
+
+    // Triangle corners in a canonical order, the GPU shares vertices inside
+    // cells only and writes cells in any order
+    let sorted = |mesh: &IsosurfaceMesh| {
+        let mut triangles: Vec<_> = mesh
+            .triangles()
+            .map(|mut triangle| {
+                triangle.sort_by(|a, b| a.partial_cmp(b).unwrap());
+                triangle
+            })
+            .collect();
+        triangles.sort_by(|a, b| a.partial_cmp(b).unwrap());
+        triangles
+    };
+
+    for iso in [-3.7, -2.2] {
+        world.get_mut::<Isosurface>(&surface).iso = iso;
+
+        let frame = testing::render(&mut world, |graph| {
+            graph.add_pass(PresentPass::new("Isosurface", |_| {}, |ctx, _| unsafe {
+                ctx.bind_pipeline(&pipeline);
+                ctx.bind_transform(&transform);
+                ctx.draw_isosurface(&surface);
+            }));
+        })
+        .expect("Error render frame");
+
+        let gpu = world.get::<Isosurface>(&surface).read_back();
+        let cpu = marching_cubes([N; 3], &values, iso, extent);
+        assert_eq!(world.get::<Isosurface>(&surface).dropped_cells(), 0);
+        assert_eq!(gpu.triangle_count(), cpu.triangle_count());
+        for (a, b) in sorted(&gpu).iter().zip(&sorted(&cpu)) {
+            for (p, q) in a.iter().zip(b) {
+                assert!(p.iter().zip(q).all(|(p, q)| (p - q).abs() < 1e-4), "{a:?} {b:?}");
+            }
+        }
+
+        let pixel = |x: u32, y: u32| &frame.pixels[((y * WIDTH + x) * 4) as usize..][..4];
+        let [r, g, b, _] = pixel(WIDTH / 2, HEIGHT / 2)[..] else { unreachable!() };
+        assert!(g > 0 && r == 0 && b == 0, "{:?}", [r, g, b]);
+        assert_ne!(pixel(0, 0), pixel(WIDTH / 2, HEIGHT / 2));
+    }
+}
+
+#[test]
+fn scene_culling() {
+    use aluminium::{SceneMesh, SceneMeshDesc, SceneObject, SceneObjectDesc, Transform, TransformDesc};
+
+    let Some(mut world) = testing::headless(WIDTH, HEIGHT) else {
+        return;
+    };
+
+    let pipeline = world
+        .create::<RasterPipeline>(
+            RasterPipelineDesc::new()
+                .vertex_shader(concat!(env!("CARGO_MANIFEST_DIR"), "/shaders/spv/object_index_vert.spv"))
+                .fragment_shader(concat!(env!("CARGO_MANIFEST_DIR"), "/shaders/spv/debug_frag.spv"))
+                .dynamic_scissors(true)
+                .dynamic_viewport(true),
+        )
+        .expect("Error create pipeline");
+
+    let vertices = [
+        vertex([0.0, -0.25], [1.0; 3]),
+        vertex([0.25, 0.25], [1.0; 3]),
+        vertex([-0.25, 0.25], [1.0; 3]),
+    ];
+    // Devices without drawIndirectFirstInstance have no GPU scene
+    let Ok(mesh) = world.create::<SceneMesh>(SceneMeshDesc::new(&vertices, &[0, 1, 2])) else {
+        return;
+    };
+
+    // The default camera is identity, the frustum is the clip space box
+    let inside = world.create::<Transform>(TransformDesc::identity()).expect("Error create transform");
+    let outside = world
+        .create::<Transform>(TransformDesc::identity().translation([10.0, 0.0, 0.0]))
+        .expect("Error create transform");
+    let _objects = [&inside, &outside].map(|transform| {
+        world
+            .create::<SceneObject>(SceneObjectDesc::new(&mesh, transform))
+            .expect("Error create scene object")
+    });
+
+    let render = |world: &mut WorldRenderer| {
+        testing::render(world, |graph| {
+            graph.add_pass(PresentPass::new("Scene", |_| {}, |ctx, _| unsafe {
+                ctx.bind_pipeline(&pipeline);
+                ctx.set_viewport(Viewport::FullRes);
+                ctx.set_scissor(Scissor::FullRes);
+                ctx.draw_indirect_count();
+            }));
+        })
+        .expect("Error render frame")
+    };
+
+    // The shader ignores transforms, the culled object is the only one missing
+    let frame = render(&mut world);
+    let pixel = |x: u32, y: u32| &frame.pixels[((y * WIDTH + x) * 4) as usize..][..4];
+    assert_eq!(pixel(WIDTH / 4, HEIGHT / 2), [255, 0, 0, 255]);
+    assert_eq!(pixel(WIDTH * 3 / 4, HEIGHT / 2), pixel(WIDTH / 2, 2));
+
+    // Both are drawn once the second object is back inside
+    world.get_mut(&outside).translation = [0.0; 3];
+    let frame = render(&mut world);
+    let pixel = |x: u32, y: u32| &frame.pixels[((y * WIDTH + x) * 4) as usize..][..4];
+    assert_eq!(pixel(WIDTH / 4, HEIGHT / 2), [255, 0, 0, 255]);
+    assert_eq!(pixel(WIDTH * 3 / 4, HEIGHT / 2), [0, 255, 0, 255]);
+}